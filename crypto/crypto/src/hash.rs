@@ -547,6 +547,12 @@ define_hasher! {
     (ContractEventHasher, CONTRACT_EVENT_HASHER, b"ContractEvent")
 }
 
+define_hasher! {
+    /// The hasher used to compute the hash of a module binary, such as the one signed in a
+    /// SignedModule.
+    (ModuleHasher, MODULE_HASHER, b"Module")
+}
+
 define_hasher! {
     /// The hasher used only for testing. It doesn't have a salt.
     (TestOnlyHasher, TEST_ONLY_HASHER, b"")
@@ -0,0 +1,70 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Measures the cost of the signatures of modules, which are mostly short token lists: loading
+//! the standard library, and copying its signatures into heap-allocated vectors compared to the
+//! inline `SignatureTokens` they are stored as.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use vm::{
+    access::ModuleAccess,
+    file_format::{CompiledModule, SignatureToken, SignatureTokens},
+};
+
+/// Returns the token lists of every function and locals signature of the standard library.
+fn stdlib_signatures() -> Vec<Vec<SignatureToken>> {
+    let mut signatures = vec![];
+    for module in stdlib::stdlib_modules() {
+        for signature in module.function_signatures() {
+            signatures.push(signature.arg_types.to_vec());
+            signatures.push(signature.return_types.to_vec());
+        }
+        for signature in module.locals_signatures() {
+            signatures.push(signature.0.to_vec());
+        }
+    }
+    signatures
+}
+
+fn deserialize_stdlib(c: &mut Criterion) {
+    let binaries: Vec<_> = stdlib::stdlib_modules()
+        .iter()
+        .map(|module| {
+            let mut binary = vec![];
+            module
+                .as_module()
+                .serialize(&mut binary)
+                .expect("stdlib modules serialize");
+            binary
+        })
+        .collect();
+    c.bench_function("deserialize_stdlib", move |b| {
+        b.iter(|| {
+            for binary in &binaries {
+                black_box(CompiledModule::deserialize(binary).expect("stdlib modules deserialize"));
+            }
+        })
+    });
+}
+
+fn copy_signatures(c: &mut Criterion) {
+    let signatures = stdlib_signatures();
+    c.bench_function("copy_signatures_vec", move |b| {
+        b.iter(|| {
+            for tokens in &signatures {
+                black_box(tokens.iter().cloned().collect::<Vec<_>>());
+            }
+        })
+    });
+    let signatures = stdlib_signatures();
+    c.bench_function("copy_signatures_inline", move |b| {
+        b.iter(|| {
+            for tokens in &signatures {
+                black_box(tokens.iter().cloned().collect::<SignatureTokens>());
+            }
+        })
+    });
+}
+
+criterion_group!(benches, deserialize_stdlib, copy_signatures);
+criterion_main!(benches);
@@ -0,0 +1,141 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines an editor for the instructions of a code unit.
+//!
+//! Deleting or inserting instructions moves the instructions after them, so every branch offset
+//! past the edit must be updated. `CodeEditor` records edits against the original offsets and
+//! applies them all at once, remapping every branch offset to where its target ends up.
+
+use std::collections::BTreeMap;
+use vm::file_format::{Bytecode, CheckedCodeOffset, CodeOffset};
+
+/// Records edits to a sequence of instructions, and applies them with `finish`.
+///
+/// Every offset, including the branch offsets of replaced and inserted instructions, refers to
+/// the original code. A branch to an instruction is remapped to the instructions inserted before
+/// it, if any, or else to the instruction itself, or else to the next instruction that isn't
+/// deleted.
+#[derive(Clone, Debug)]
+pub struct CodeEditor {
+    code: Vec<Bytecode>,
+    deleted: Vec<bool>,
+    /// The instructions to insert at the start of the code, which no branch goes to.
+    prologue: Vec<Bytecode>,
+    /// The instructions to insert before each offset. The offset can be the length of the code,
+    /// to append instructions.
+    inserted: BTreeMap<CodeOffset, Vec<Bytecode>>,
+}
+
+impl CodeEditor {
+    pub fn new(code: Vec<Bytecode>) -> Self {
+        let deleted = vec![false; code.len()];
+        Self {
+            code,
+            deleted,
+            prologue: vec![],
+            inserted: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the original code, with replacements applied.
+    pub fn code(&self) -> &[Bytecode] {
+        &self.code
+    }
+
+    /// Deletes the instruction at `offset`.
+    pub fn delete(&mut self, offset: CodeOffset) {
+        self.deleted[offset as usize] = true;
+    }
+
+    /// Returns true if the instruction at `offset` is deleted.
+    pub fn is_deleted(&self, offset: CodeOffset) -> bool {
+        self.deleted[offset as usize]
+    }
+
+    /// Replaces the instruction at `offset` with `bytecode`.
+    pub fn replace(&mut self, offset: CodeOffset, bytecode: Bytecode) {
+        self.code[offset as usize] = bytecode;
+    }
+
+    /// Inserts `instructions` before the instruction at `offset`, after any instructions inserted
+    /// there already. Branches to `offset` branch to the inserted instructions.
+    pub fn insert_before(&mut self, offset: CodeOffset, instructions: Vec<Bytecode>) {
+        assert!(
+            offset as usize <= self.code.len(),
+            "cannot insert past the end of the code"
+        );
+        self.inserted
+            .entry(offset)
+            .or_insert_with(Vec::new)
+            .extend(instructions);
+    }
+
+    /// Inserts `instructions` at the start of the code, after any instructions inserted there
+    /// already. Unlike instructions inserted before offset 0, branches to offset 0 don't branch to
+    /// them, so they run once per call.
+    pub fn insert_prologue(&mut self, instructions: Vec<Bytecode>) {
+        self.prologue.extend(instructions);
+    }
+
+    /// Returns true if no edit was recorded.
+    pub fn is_unchanged(&self) -> bool {
+        self.prologue.is_empty() && self.inserted.is_empty() && !self.deleted.contains(&true)
+    }
+
+    /// Applies the edits, and returns the new code.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new code is too long for branches to reach all of it. Use `try_finish` to
+    /// handle that case.
+    pub fn finish(self) -> Vec<Bytecode> {
+        self.try_finish()
+            .expect("edited code has more instructions than a code offset can address")
+    }
+
+    /// Applies the edits, and returns the new code, or `None` if it is too long for branches to
+    /// reach all of it.
+    pub fn try_finish(self) -> Option<Vec<Bytecode>> {
+        let offsets = self.new_offsets()?;
+        let remap = |bytecode: Bytecode| match bytecode {
+            Bytecode::BrTrue(offset) => Bytecode::BrTrue(offsets[offset as usize].into_operand()),
+            Bytecode::BrFalse(offset) => Bytecode::BrFalse(offsets[offset as usize].into_operand()),
+            Bytecode::Branch(offset) => Bytecode::Branch(offsets[offset as usize].into_operand()),
+            bytecode => bytecode,
+        };
+
+        let mut inserted = self.inserted;
+        let mut code: Vec<_> = self.prologue.into_iter().map(remap).collect();
+        for (offset, (bytecode, deleted)) in self.code.into_iter().zip(self.deleted).enumerate() {
+            if let Some(instructions) = inserted.remove(&(offset as CodeOffset)) {
+                code.extend(instructions.into_iter().map(remap));
+            }
+            if !deleted {
+                code.push(remap(bytecode));
+            }
+        }
+        for (_, instructions) in inserted {
+            code.extend(instructions.into_iter().map(remap));
+        }
+        Some(code)
+    }
+
+    /// Returns the new offset of every original offset, and of the end of the code, or `None` if
+    /// one of them is too large for a branch to reach.
+    fn new_offsets(&self) -> Option<Vec<CheckedCodeOffset>> {
+        let mut offsets = Vec::with_capacity(self.code.len() + 1);
+        let mut next = self.prologue.len();
+        for offset in 0..=self.code.len() {
+            offsets.push(CheckedCodeOffset::from_index(next)?);
+            next += self
+                .inserted
+                .get(&(offset as CodeOffset))
+                .map_or(0, Vec::len);
+            if offset < self.code.len() && !self.deleted[offset] {
+                next += 1;
+            }
+        }
+        Some(offsets)
+    }
+}
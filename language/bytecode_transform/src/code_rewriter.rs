@@ -0,0 +1,74 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines a rewriter that maps every instruction of a code unit to a sequence of instructions.
+//!
+//! Where `CodeEditor` records edits at chosen offsets, `CodeRewriter` visits every instruction and
+//! lets a mapping decide what it becomes: itself, nothing, or several instructions. Branch offsets
+//! are remapped the same way `CodeEditor` remaps them, and the locals the code refers to can be
+//! renumbered along the way, for rewrites that add or remove locals.
+
+use crate::{
+    code_editor::CodeEditor,
+    dead_code::{local_index, with_local_index},
+};
+use std::collections::BTreeMap;
+use vm::file_format::{Bytecode, CodeOffset, LocalIndex};
+
+/// Rewrites code instruction by instruction with `rewrite`.
+#[derive(Clone, Debug, Default)]
+pub struct CodeRewriter {
+    /// The new index of every local that is renumbered.
+    locals: BTreeMap<LocalIndex, LocalIndex>,
+}
+
+impl CodeRewriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Renumbers the locals the rewritten code refers to: local `old` becomes `new` for every
+    /// `(old, new)` in `renumbering`, and other locals are kept as they are. This applies to the
+    /// instructions returned by the mapping, so they refer to locals by their original index.
+    pub fn renumber_locals(mut self, renumbering: BTreeMap<LocalIndex, LocalIndex>) -> Self {
+        self.locals.extend(renumbering);
+        self
+    }
+
+    /// Returns `code` with every instruction replaced by the instructions `rewrite` returns for
+    /// it, given its offset. Returns `None` if the new code is too long for branches to reach all
+    /// of it.
+    ///
+    /// Branch offsets, in `code` as well as in the instructions `rewrite` returns, refer to the
+    /// original code. A branch to an instruction is remapped to the first instruction it is
+    /// rewritten to, or, if it is rewritten to nothing, to where the next instruction ends up.
+    pub fn rewrite<F>(&self, code: &[Bytecode], mut rewrite: F) -> Option<Vec<Bytecode>>
+    where
+        F: FnMut(CodeOffset, &Bytecode) -> Vec<Bytecode>,
+    {
+        let mut editor = CodeEditor::new(code.to_vec());
+        for (offset, bytecode) in code.iter().enumerate() {
+            let offset = offset as CodeOffset;
+            let mut instructions: Vec<_> = rewrite(offset, bytecode)
+                .into_iter()
+                .map(|instruction| self.renumber(instruction))
+                .collect();
+            if instructions.len() == 1 {
+                editor.replace(offset, instructions.remove(0));
+            } else {
+                editor.delete(offset);
+                if !instructions.is_empty() {
+                    editor.insert_before(offset, instructions);
+                }
+            }
+        }
+        editor.try_finish()
+    }
+
+    fn renumber(&self, bytecode: Bytecode) -> Bytecode {
+        match local_index(&bytecode).and_then(|local| self.locals.get(&local)) {
+            Some(new_local) => with_local_index(&bytecode, *new_local),
+            None => bytecode,
+        }
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements constant folding and propagation, which evaluates at compile time what only depends
+//! on constants:
+//! - arithmetic, comparisons and boolean operations on constant operands are replaced with their
+//!   result, unless the arithmetic fails, which has to happen at runtime;
+//! - conditional branches on a constant become unconditional branches, or are removed, leaving
+//!   unreachable code for dead code elimination;
+//! - a read of a local whose value is the same constant on every path is replaced with the
+//!   constant, using the reaching definitions of the local;
+//! - a constant stored in a local that is never read afterwards is removed along with the store.
+//!
+//! As with the peephole optimizer, instructions are only folded if no branch goes between them.
+//! The rewrites are repeated until none applies anymore, and the resulting module is verified.
+
+use crate::{
+    code_editor::CodeEditor,
+    dead_code::arg_count,
+    peephole::{branch_targets, OptimizationError},
+};
+use bytecode_verifier::{
+    control_flow_graph::VMControlFlowGraph,
+    dataflow::{Definition, Liveness, ReachingDefinitions},
+    VerifiedModule,
+};
+use vm::file_format::{Bytecode, CodeOffset};
+
+/// The number of rewrites of each kind constant folding made.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ConstantFoldingStats {
+    /// Operations on constants replaced with their result.
+    pub folded_operations: usize,
+    /// Conditional branches on a constant made unconditional or removed.
+    pub folded_branches: usize,
+    /// Reads of locals replaced with a constant.
+    pub propagated_constants: usize,
+    /// Constants stored in locals that are never read afterwards, removed with the store.
+    pub dead_stores: usize,
+}
+
+impl ConstantFoldingStats {
+    /// Returns the total number of rewrites.
+    pub fn total(&self) -> usize {
+        self.folded_operations + self.folded_branches + self.propagated_constants + self.dead_stores
+    }
+}
+
+/// Folds and propagates the constants of every function of `module`, and verifies the resulting
+/// module.
+pub fn fold_constants(
+    module: VerifiedModule,
+) -> Result<(VerifiedModule, ConstantFoldingStats), OptimizationError> {
+    let mut module = module.into_inner().into_inner();
+    let mut stats = ConstantFoldingStats::default();
+    for idx in 0..module.function_defs.len() {
+        let function_def = &module.function_defs[idx];
+        if function_def.is_native() {
+            continue;
+        }
+        let arg_count = arg_count(&module, function_def);
+        let mut code = function_def.code.code.clone();
+        loop {
+            let total = stats.total();
+            code = fold_operations(code, &mut stats);
+            code = propagate_constants(code, arg_count, &mut stats);
+            code = remove_dead_stores(code, &mut stats);
+            if stats.total() == total {
+                break;
+            }
+        }
+        module.function_defs[idx].code.code = code;
+    }
+    let module = module
+        .freeze()
+        .map_err(|err| OptimizationError::Miscompiled(err.into_errors()))?;
+    let module = VerifiedModule::new(module)
+        .map_err(|(_, errors)| OptimizationError::Miscompiled(errors))?;
+    Ok((module, stats))
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Constant {
+    U64(u64),
+    Bool(bool),
+}
+
+impl Constant {
+    /// Returns the constant `bytecode` loads, if it is a constant load.
+    fn loaded_by(bytecode: &Bytecode) -> Option<Self> {
+        match bytecode {
+            Bytecode::LdConst(value) => Some(Constant::U64(*value)),
+            Bytecode::LdTrue => Some(Constant::Bool(true)),
+            Bytecode::LdFalse => Some(Constant::Bool(false)),
+            _ => None,
+        }
+    }
+
+    fn load(self) -> Bytecode {
+        match self {
+            Constant::U64(value) => Bytecode::LdConst(value),
+            Constant::Bool(true) => Bytecode::LdTrue,
+            Constant::Bool(false) => Bytecode::LdFalse,
+        }
+    }
+}
+
+/// Returns the result of the binary operation `bytecode` on `lhs` and `rhs`, if it is one that
+/// succeeds.
+fn evaluate(bytecode: &Bytecode, lhs: Constant, rhs: Constant) -> Option<Constant> {
+    use Constant::{Bool, U64};
+
+    Some(match (bytecode, lhs, rhs) {
+        (Bytecode::Add, U64(l), U64(r)) => U64(l.checked_add(r)?),
+        (Bytecode::Sub, U64(l), U64(r)) => U64(l.checked_sub(r)?),
+        (Bytecode::Mul, U64(l), U64(r)) => U64(l.checked_mul(r)?),
+        (Bytecode::Mod, U64(l), U64(r)) => U64(l.checked_rem(r)?),
+        (Bytecode::Div, U64(l), U64(r)) => U64(l.checked_div(r)?),
+        (Bytecode::BitOr, U64(l), U64(r)) => U64(l | r),
+        (Bytecode::BitAnd, U64(l), U64(r)) => U64(l & r),
+        (Bytecode::Xor, U64(l), U64(r)) => U64(l ^ r),
+        (Bytecode::Lt, U64(l), U64(r)) => Bool(l < r),
+        (Bytecode::Gt, U64(l), U64(r)) => Bool(l > r),
+        (Bytecode::Le, U64(l), U64(r)) => Bool(l <= r),
+        (Bytecode::Ge, U64(l), U64(r)) => Bool(l >= r),
+        (Bytecode::Or, Bool(l), Bool(r)) => Bool(l || r),
+        (Bytecode::And, Bool(l), Bool(r)) => Bool(l && r),
+        (Bytecode::Eq, _, _) => Bool(lhs == rhs),
+        (Bytecode::Neq, _, _) => Bool(lhs != rhs),
+        _ => return None,
+    })
+}
+
+fn fold_operations(code: Vec<Bytecode>, stats: &mut ConstantFoldingStats) -> Vec<Bytecode> {
+    let targets = branch_targets(&code);
+    let mut editor = CodeEditor::new(code.clone());
+    // The constant loads left right before the current instruction, with the constants they load.
+    // Branches go to an empty stack, so a load at a branch target may be folded with the loads
+    // after it, but not with those before it.
+    let mut loads: Vec<(CodeOffset, Constant)> = vec![];
+    for (offset, bytecode) in code.iter().enumerate() {
+        let offset = offset as CodeOffset;
+        if targets.contains(&offset) {
+            loads.clear();
+        }
+        let folded = match (bytecode, loads.last().cloned()) {
+            (Bytecode::Not, Some((load, Constant::Bool(value)))) => {
+                editor.delete(load);
+                loads.pop();
+                Some(Constant::Bool(!value))
+            }
+            (Bytecode::BrTrue(target), Some((load, Constant::Bool(value))))
+            | (Bytecode::BrFalse(target), Some((load, Constant::Bool(value)))) => {
+                editor.delete(load);
+                let branch_if = *bytecode == Bytecode::BrTrue(*target);
+                if value == branch_if {
+                    editor.replace(offset, Bytecode::Branch(*target));
+                } else {
+                    editor.delete(offset);
+                }
+                loads.clear();
+                stats.folded_branches += 1;
+                continue;
+            }
+            _ if loads.len() >= 2 => {
+                let (lhs_load, lhs) = loads[loads.len() - 2];
+                let (rhs_load, rhs) = loads[loads.len() - 1];
+                let folded = evaluate(bytecode, lhs, rhs);
+                if folded.is_some() {
+                    editor.delete(lhs_load);
+                    editor.delete(rhs_load);
+                    loads.truncate(loads.len() - 2);
+                }
+                folded
+            }
+            _ => None,
+        };
+        match folded {
+            Some(constant) => {
+                editor.replace(offset, constant.load());
+                loads.push((offset, constant));
+                stats.folded_operations += 1;
+            }
+            None => match Constant::loaded_by(bytecode) {
+                Some(constant) => loads.push((offset, constant)),
+                None => loads.clear(),
+            },
+        }
+    }
+    editor.finish()
+}
+
+fn propagate_constants(
+    code: Vec<Bytecode>,
+    arg_count: usize,
+    stats: &mut ConstantFoldingStats,
+) -> Vec<Bytecode> {
+    let targets = branch_targets(&code);
+    let cfg = VMControlFlowGraph::new(&code);
+    let reaching = ReachingDefinitions::new(&code, &cfg, arg_count);
+    // Returns the constant stored by the definition, if it stores one that was just loaded.
+    let stored_constant = |definition: &Definition| match definition {
+        Definition::At(offset) if *offset > 0 && !targets.contains(offset) => {
+            match code[*offset as usize] {
+                Bytecode::StLoc(_) => Constant::loaded_by(&code[*offset as usize - 1]),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    let mut editor = CodeEditor::new(code.clone());
+    for (offset, bytecode) in code.iter().enumerate() {
+        let local = match bytecode {
+            Bytecode::CopyLoc(local) | Bytecode::MoveLoc(local) => *local,
+            _ => continue,
+        };
+        let definitions = reaching.reaching(offset as CodeOffset, local);
+        let mut constants = definitions.iter().map(stored_constant);
+        let constant = match constants.next() {
+            Some(Some(constant)) => constant,
+            _ => continue,
+        };
+        if constants.all(|other| other == Some(constant)) {
+            editor.replace(offset as CodeOffset, constant.load());
+            stats.propagated_constants += 1;
+        }
+    }
+    editor.finish()
+}
+
+fn remove_dead_stores(code: Vec<Bytecode>, stats: &mut ConstantFoldingStats) -> Vec<Bytecode> {
+    let targets = branch_targets(&code);
+    let liveness = Liveness::new(&code, &VMControlFlowGraph::new(&code));
+    let mut editor = CodeEditor::new(code.clone());
+    for (offset, window) in code.windows(2).enumerate() {
+        let store = (offset + 1) as CodeOffset;
+        let local = match window[1] {
+            Bytecode::StLoc(local) => local,
+            _ => continue,
+        };
+        if Constant::loaded_by(&window[0]).is_some()
+            && !targets.contains(&store)
+            && !liveness.is_live_after(store, local)
+        {
+            editor.delete(offset as CodeOffset);
+            editor.delete(store);
+            stats.dead_stores += 1;
+        }
+    }
+    editor.finish()
+}
@@ -0,0 +1,202 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements dead code elimination, which removes from every function:
+//! - the basic blocks that can't be reached from the entry block;
+//! - the locals past the arguments that no instruction left refers to, compacting the locals
+//!   signature and renumbering the locals after them.
+//!
+//! Neither changes what the code does, and the verifier only checks reachable code and the locals
+//! it uses, so the module stays verifiable. Locals signatures that are no longer used are left in
+//! the pool.
+
+use crate::{code_editor::CodeEditor, peephole::OptimizationError};
+use bytecode_verifier::{
+    control_flow_graph::{ControlFlowGraph, VMControlFlowGraph},
+    VerifiedModule,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use vm::file_format::{
+    Bytecode, CodeOffset, CompiledModuleMut, FunctionDefinition, FunctionDefinitionIndex,
+    LocalIndex, LocalsSignature, LocalsSignatureIndex, SignatureTokens, TableIndex,
+};
+
+/// What dead code elimination removed from a function.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RemovedCode {
+    /// The unreachable blocks, as the offsets of their first and last instructions in the
+    /// original code.
+    pub blocks: Vec<(CodeOffset, CodeOffset)>,
+    /// The unused locals, as their indexes in the original locals signature.
+    pub locals: Vec<LocalIndex>,
+}
+
+impl RemovedCode {
+    /// Returns the number of instructions removed.
+    pub fn instruction_count(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|(start, end)| (end - start) as usize + 1)
+            .sum()
+    }
+}
+
+/// What dead code elimination removed from a module.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeadCodeReport {
+    /// The functions something was removed from.
+    pub functions: BTreeMap<FunctionDefinitionIndex, RemovedCode>,
+}
+
+impl DeadCodeReport {
+    /// Returns true if nothing was removed.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Returns the number of instructions removed from all functions.
+    pub fn instruction_count(&self) -> usize {
+        self.functions
+            .values()
+            .map(RemovedCode::instruction_count)
+            .sum()
+    }
+}
+
+/// Removes the unreachable blocks and unused locals of every function of `module`, and verifies
+/// the resulting module.
+pub fn eliminate_dead_code(
+    module: VerifiedModule,
+) -> Result<(VerifiedModule, DeadCodeReport), OptimizationError> {
+    let mut module = module.into_inner().into_inner();
+    let mut report = DeadCodeReport::default();
+    for idx in 0..module.function_defs.len() {
+        if module.function_defs[idx].is_native() {
+            continue;
+        }
+        let removed = eliminate_in_function(&mut module, idx);
+        if removed != RemovedCode::default() {
+            report
+                .functions
+                .insert(FunctionDefinitionIndex::new(idx as TableIndex), removed);
+        }
+    }
+    let module = module
+        .freeze()
+        .map_err(|err| OptimizationError::Miscompiled(err.into_errors()))?;
+    let module = VerifiedModule::new(module)
+        .map_err(|(_, errors)| OptimizationError::Miscompiled(errors))?;
+    Ok((module, report))
+}
+
+fn eliminate_in_function(module: &mut CompiledModuleMut, idx: usize) -> RemovedCode {
+    let function_def = &module.function_defs[idx];
+    let mut removed = RemovedCode::default();
+
+    let mut editor = CodeEditor::new(function_def.code.code.clone());
+    let cfg = VMControlFlowGraph::new(&function_def.code.code);
+    let reachable: BTreeSet<_> = cfg
+        .reachable_from(cfg.entry_block_id())
+        .into_iter()
+        .collect();
+    for block_id in cfg.blocks() {
+        if !reachable.contains(&block_id) {
+            for offset in cfg.instr_indexes(&block_id) {
+                editor.delete(offset);
+            }
+            removed
+                .blocks
+                .push((cfg.block_start(&block_id), cfg.block_end(&block_id)));
+        }
+    }
+
+    // Map the locals left to their new indexes, keeping the arguments in place.
+    let arg_count = arg_count(module, function_def);
+    let used_locals: BTreeSet<_> = editor
+        .code()
+        .iter()
+        .enumerate()
+        .filter(|(offset, _)| !editor.is_deleted(*offset as CodeOffset))
+        .filter_map(|(_, bytecode)| local_index(bytecode))
+        .collect();
+    let locals = &module.locals_signatures[function_def.code.locals.0 as usize].0;
+    let mut new_indexes = BTreeMap::new();
+    let mut new_locals = SignatureTokens::new();
+    for (local, token) in locals.iter().enumerate() {
+        let local = local as LocalIndex;
+        if (local as usize) < arg_count || used_locals.contains(&local) {
+            new_indexes.insert(local, new_locals.len() as LocalIndex);
+            new_locals.push(token.clone());
+        } else {
+            removed.locals.push(local);
+        }
+    }
+
+    if !removed.locals.is_empty() {
+        for offset in 0..editor.code().len() {
+            let offset = offset as CodeOffset;
+            if editor.is_deleted(offset) {
+                continue;
+            }
+            let bytecode = &editor.code()[offset as usize];
+            if let Some(local) = local_index(bytecode) {
+                let renumbered = with_local_index(bytecode, new_indexes[&local]);
+                editor.replace(offset, renumbered);
+            }
+        }
+        let locals = intern_locals_signature(module, LocalsSignature(new_locals));
+        module.function_defs[idx].code.locals = locals;
+    }
+    module.function_defs[idx].code.code = editor.finish();
+    removed
+}
+
+pub(crate) fn arg_count(module: &CompiledModuleMut, function_def: &FunctionDefinition) -> usize {
+    let handle = &module.function_handles[function_def.function.0 as usize];
+    module.function_signatures[handle.signature.0 as usize]
+        .arg_types
+        .len()
+}
+
+/// Returns the index of `signature` in the locals signature pool, adding it if needed.
+pub(crate) fn intern_locals_signature(
+    module: &mut CompiledModuleMut,
+    signature: LocalsSignature,
+) -> LocalsSignatureIndex {
+    let position = match module
+        .locals_signatures
+        .iter()
+        .position(|existing| *existing == signature)
+    {
+        Some(position) => position,
+        None => {
+            module.locals_signatures.push(signature);
+            module.locals_signatures.len() - 1
+        }
+    };
+    LocalsSignatureIndex::new(position as TableIndex)
+}
+
+/// Returns the local `bytecode` refers to, if any.
+pub(crate) fn local_index(bytecode: &Bytecode) -> Option<LocalIndex> {
+    match bytecode {
+        Bytecode::CopyLoc(idx)
+        | Bytecode::MoveLoc(idx)
+        | Bytecode::StLoc(idx)
+        | Bytecode::MutBorrowLoc(idx)
+        | Bytecode::ImmBorrowLoc(idx) => Some(*idx),
+        _ => None,
+    }
+}
+
+/// Returns `bytecode`, which must refer to a local, referring to local `idx` instead.
+pub(crate) fn with_local_index(bytecode: &Bytecode, idx: LocalIndex) -> Bytecode {
+    match bytecode {
+        Bytecode::CopyLoc(_) => Bytecode::CopyLoc(idx),
+        Bytecode::MoveLoc(_) => Bytecode::MoveLoc(idx),
+        Bytecode::StLoc(_) => Bytecode::StLoc(idx),
+        Bytecode::MutBorrowLoc(_) => Bytecode::MutBorrowLoc(idx),
+        Bytecode::ImmBorrowLoc(_) => Bytecode::ImmBorrowLoc(idx),
+        _ => unreachable!("{:?} doesn't refer to a local", bytecode),
+    }
+}
@@ -0,0 +1,267 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements instrumentation, which inserts probes into the code of every function of a module,
+//! e.g. to trace calls or record coverage without recompiling the module from source.
+//!
+//! A probe is a sequence of instructions inserted at function entry, before every `Ret`, or
+//! before every instruction matching a filter. Probes must leave the stack as they found it and
+//! can't branch, return or abort, so they don't change what the code computes, as long as the
+//! functions they call don't. A probe can use locals of its own, which are added to the locals of
+//! every function it is inserted in.
+
+use crate::{
+    code_editor::CodeEditor,
+    dead_code::{intern_locals_signature, local_index, with_local_index},
+};
+use bytecode_verifier::VerifiedModule;
+use std::fmt;
+use vm::{
+    errors::VerificationError,
+    file_format::{
+        Bytecode, CheckedCodeOffset, CodeOffset, CompiledModuleMut, FunctionDefinitionIndex,
+        LocalIndex, LocalsSignature, SignatureToken, TableIndex,
+    },
+};
+
+/// Where a probe is inserted.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProbeSite<'a> {
+    /// The function the probe is inserted in.
+    pub function: FunctionDefinitionIndex,
+    /// The offset, in the original code, of the instruction the probe runs before.
+    pub offset: CodeOffset,
+    /// The instruction the probe runs before.
+    pub bytecode: &'a Bytecode,
+}
+
+/// Why a module couldn't be instrumented.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum InstrumentationError {
+    /// A probe contains an instruction that branches, returns or aborts, or refers to a local it
+    /// doesn't have.
+    InvalidProbe(Bytecode),
+    /// A function would have more locals than a local index can refer to.
+    TooManyLocals(FunctionDefinitionIndex),
+    /// A function would have more instructions than a branch can reach.
+    CodeTooLong(FunctionDefinitionIndex),
+    /// The instrumented module doesn't verify, e.g. because a probe doesn't leave the stack as it
+    /// found it.
+    Unverified(Vec<VerificationError>),
+}
+
+impl fmt::Display for InstrumentationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstrumentationError::InvalidProbe(bytecode) => {
+                write!(f, "invalid instruction {:?} in probe", bytecode)
+            }
+            InstrumentationError::TooManyLocals(idx) => {
+                write!(f, "too many locals in instrumented function {}", idx)
+            }
+            InstrumentationError::CodeTooLong(idx) => {
+                write!(f, "too many instructions in instrumented function {}", idx)
+            }
+            InstrumentationError::Unverified(errors) => {
+                write!(
+                    f,
+                    "instrumented module has {} verification errors",
+                    errors.len()
+                )
+            }
+        }
+    }
+}
+
+type ProbeFn = Box<dyn Fn(&ProbeSite) -> Vec<Bytecode>>;
+type FilterFn = Box<dyn Fn(&Bytecode) -> bool>;
+
+struct Probe {
+    /// The locals of the probe. Its instructions refer to them from index 0, whatever the locals
+    /// of the function it is inserted in.
+    locals: Vec<SignatureToken>,
+    code: ProbeFn,
+}
+
+/// Inserts probes into the functions of a module.
+///
+/// Probes are given as functions of the site they are inserted at, so that a probe can e.g. pass
+/// the offset it runs at to a tracing function. Probes refer to the handles and pools of the
+/// module, which must already have the entries they need. At a given instruction, probes run in
+/// the order they were added, the probes before matching instructions running before the exit
+/// probes.
+#[derive(Default)]
+pub struct Instrumenter {
+    entry: Vec<Probe>,
+    exit: Vec<Probe>,
+    before: Vec<(FilterFn, Probe)>,
+}
+
+impl Instrumenter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `probe` at the entry of every function. It runs once per call, even if the code
+    /// branches back to its first instruction.
+    pub fn on_entry(
+        &mut self,
+        locals: Vec<SignatureToken>,
+        probe: impl Fn(&ProbeSite) -> Vec<Bytecode> + 'static,
+    ) -> &mut Self {
+        self.entry.push(Probe {
+            locals,
+            code: Box::new(probe),
+        });
+        self
+    }
+
+    /// Inserts `probe` before every `Ret`. The values returned are on the stack when it runs.
+    pub fn on_exit(
+        &mut self,
+        locals: Vec<SignatureToken>,
+        probe: impl Fn(&ProbeSite) -> Vec<Bytecode> + 'static,
+    ) -> &mut Self {
+        self.exit.push(Probe {
+            locals,
+            code: Box::new(probe),
+        });
+        self
+    }
+
+    /// Inserts `probe` before every instruction `filter` returns true for.
+    pub fn before(
+        &mut self,
+        filter: impl Fn(&Bytecode) -> bool + 'static,
+        locals: Vec<SignatureToken>,
+        probe: impl Fn(&ProbeSite) -> Vec<Bytecode> + 'static,
+    ) -> &mut Self {
+        self.before.push((
+            Box::new(filter),
+            Probe {
+                locals,
+                code: Box::new(probe),
+            },
+        ));
+        self
+    }
+
+    /// Inserts the probes into every function of `module` that isn't native, and verifies the
+    /// instrumented module.
+    pub fn instrument(
+        &self,
+        module: VerifiedModule,
+    ) -> Result<VerifiedModule, InstrumentationError> {
+        let mut module = module.into_inner().into_inner();
+        for idx in 0..module.function_defs.len() {
+            if module.function_defs[idx].is_native() {
+                continue;
+            }
+            self.instrument_function(&mut module, idx)?;
+        }
+        let module = module
+            .freeze()
+            .map_err(|err| InstrumentationError::Unverified(err.into_errors()))?;
+        VerifiedModule::new(module).map_err(|(_, errors)| InstrumentationError::Unverified(errors))
+    }
+
+    fn instrument_function(
+        &self,
+        module: &mut CompiledModuleMut,
+        idx: usize,
+    ) -> Result<(), InstrumentationError> {
+        let function = FunctionDefinitionIndex::new(idx as TableIndex);
+        let code_unit = &module.function_defs[idx].code;
+        let mut locals = module.locals_signatures[code_unit.locals.0 as usize]
+            .0
+            .clone();
+        let mut editor = CodeEditor::new(code_unit.code.clone());
+
+        // The locals of a probe are added once per function, the first time it is inserted.
+        let mut first_locals = vec![None; self.entry.len() + self.exit.len() + self.before.len()];
+        let mut probe_code = |probe_idx: usize, probe: &Probe, site: &ProbeSite| {
+            let first_local = match first_locals[probe_idx] {
+                Some(first_local) => first_local,
+                None => {
+                    let first_local = locals.len();
+                    locals.extend(probe.locals.iter().cloned());
+                    if locals.len() > LocalIndex::max_value() as usize + 1 {
+                        return Err(InstrumentationError::TooManyLocals(function));
+                    }
+                    first_locals[probe_idx] = Some(first_local);
+                    first_local
+                }
+            };
+            relocate((probe.code)(site), first_local, probe.locals.len())
+        };
+
+        let code = &code_unit.code;
+        if let Some(bytecode) = code.first() {
+            for (probe_idx, probe) in self.entry.iter().enumerate() {
+                let site = ProbeSite {
+                    function,
+                    offset: 0,
+                    bytecode,
+                };
+                editor.insert_prologue(probe_code(probe_idx, probe, &site)?);
+            }
+        }
+        for (offset, bytecode) in code.iter().enumerate() {
+            let site = ProbeSite {
+                function,
+                offset: CheckedCodeOffset::from_index(offset)
+                    .ok_or_else(|| InstrumentationError::CodeTooLong(function))?
+                    .into_operand(),
+                bytecode,
+            };
+            let first_probe_idx = self.entry.len() + self.exit.len();
+            for (probe_idx, (filter, probe)) in self.before.iter().enumerate() {
+                if filter(bytecode) {
+                    let instructions = probe_code(first_probe_idx + probe_idx, probe, &site)?;
+                    editor.insert_before(site.offset, instructions);
+                }
+            }
+            if *bytecode == Bytecode::Ret {
+                for (probe_idx, probe) in self.exit.iter().enumerate() {
+                    let instructions = probe_code(self.entry.len() + probe_idx, probe, &site)?;
+                    editor.insert_before(site.offset, instructions);
+                }
+            }
+        }
+
+        let code = editor
+            .try_finish()
+            .ok_or_else(|| InstrumentationError::CodeTooLong(function))?;
+        let locals = intern_locals_signature(module, LocalsSignature(locals));
+        let code_unit = &mut module.function_defs[idx].code;
+        code_unit.code = code;
+        code_unit.locals = locals;
+        Ok(())
+    }
+}
+
+/// Checks that `code` doesn't branch, return or abort, and that it only refers to its
+/// `local_count` locals, and renumbers them from `first_local`.
+fn relocate(
+    code: Vec<Bytecode>,
+    first_local: usize,
+    local_count: usize,
+) -> Result<Vec<Bytecode>, InstrumentationError> {
+    code.into_iter()
+        .map(|bytecode| {
+            if bytecode.is_branch() {
+                return Err(InstrumentationError::InvalidProbe(bytecode));
+            }
+            match local_index(&bytecode) {
+                Some(local) if local as usize >= local_count => {
+                    Err(InstrumentationError::InvalidProbe(bytecode))
+                }
+                Some(local) => Ok(with_local_index(
+                    &bytecode,
+                    (first_local + local as usize) as LocalIndex,
+                )),
+                None => Ok(bytecode),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,24 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Transforms compiled modules while keeping them verifiable.
+
+pub mod code_editor;
+pub mod code_rewriter;
+pub mod constant_folding;
+pub mod dead_code;
+pub mod instrument;
+pub mod patch;
+pub mod peephole;
+pub mod strip;
+#[cfg(test)]
+mod unit_tests;
+
+pub use code_editor::CodeEditor;
+pub use code_rewriter::CodeRewriter;
+pub use constant_folding::{fold_constants, ConstantFoldingStats};
+pub use dead_code::{eliminate_dead_code, DeadCodeReport, RemovedCode};
+pub use instrument::{InstrumentationError, Instrumenter, ProbeSite};
+pub use patch::{ModulePatcher, PatchError};
+pub use peephole::{optimize_code, optimize_module, OptimizationError, PeepholeStats};
+pub use strip::{strip_module, IdentifierMapping, StripOptions, StripReport};
@@ -0,0 +1,325 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements a patcher that rebinds the modules a module refers to and renames structs and
+//! functions, e.g. to redeploy a module and its dependencies under new addresses in a test
+//! environment.
+//!
+//! Handles refer to names and addresses through the pools, which other entries may share, so a
+//! patch never changes a pool entry in place: it points the handle to another entry, adding it if
+//! needed. A patch may make two handles identical, e.g. when a dependency is rebound to a module
+//! the module already refers to. Identical handles are merged when the patch is finished, and
+//! every index referring to a handle is updated, so that a patched module never has duplicate
+//! handles.
+
+use std::{collections::HashMap, fmt, hash::Hash};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+use vm::{
+    errors::VerificationError,
+    file_format::{
+        AddressPoolIndex, Bytecode, CompiledModule, CompiledModuleMut, FunctionHandleIndex,
+        ModuleHandle, ModuleHandleIndex, SignatureToken, StringPoolIndex, StructHandleIndex,
+        TableIndex,
+    },
+};
+
+/// Why a patch couldn't be applied.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PatchError {
+    /// The module doesn't refer to this module.
+    UnknownModule(ModuleId),
+    /// The module doesn't refer to this struct of this module.
+    UnknownStruct(ModuleId, String),
+    /// The module doesn't refer to this function of this module.
+    UnknownFunction(ModuleId, String),
+    /// A dependency was rebound to the module itself.
+    SelfDependency(ModuleId),
+    /// Two structs were given the same name, but don't have the same kind and type formals, or
+    /// are both defined by the module.
+    ConflictingStructs(ModuleId, String),
+    /// Two functions were given the same name, but don't have the same signature, or are both
+    /// defined by the module.
+    ConflictingFunctions(ModuleId, String),
+    /// The patched module is not bounds-valid.
+    OutOfBounds(Vec<VerificationError>),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchError::UnknownModule(id) => write!(f, "no handle to module {:?}", id),
+            PatchError::UnknownStruct(id, name) => {
+                write!(f, "no handle to struct {} of module {:?}", name, id)
+            }
+            PatchError::UnknownFunction(id, name) => {
+                write!(f, "no handle to function {} of module {:?}", name, id)
+            }
+            PatchError::SelfDependency(id) => write!(f, "module {:?} depends on itself", id),
+            PatchError::ConflictingStructs(id, name) => {
+                write!(f, "conflicting structs {} of module {:?}", name, id)
+            }
+            PatchError::ConflictingFunctions(id, name) => {
+                write!(f, "conflicting functions {} of module {:?}", name, id)
+            }
+            PatchError::OutOfBounds(errors) => {
+                write!(f, "patched module has {} bounds errors", errors.len())
+            }
+        }
+    }
+}
+
+/// Applies patches to a module. Patches apply in order, so a patch refers to modules, structs
+/// and functions by the names earlier patches gave them.
+pub struct ModulePatcher {
+    module: CompiledModuleMut,
+}
+
+impl ModulePatcher {
+    pub fn new(module: CompiledModule) -> Self {
+        Self {
+            module: module.into_inner(),
+        }
+    }
+
+    /// Makes the handle to module `from` refer to module `to`. Rebinding the module itself
+    /// changes the address and name it is published under.
+    pub fn rebind_module(&mut self, from: &ModuleId, to: &ModuleId) -> Result<(), PatchError> {
+        let idx = self
+            .find_module_handle(from)
+            .ok_or_else(|| PatchError::UnknownModule(from.clone()))?;
+        let address = self.intern_address(*to.address());
+        let name = self.intern_string(to.name());
+        self.module.module_handles[idx.0 as usize] = ModuleHandle { address, name };
+        Ok(())
+    }
+
+    /// Renames struct `from` of module `module` to `to`.
+    pub fn rename_struct(
+        &mut self,
+        module: &ModuleId,
+        from: &str,
+        to: &str,
+    ) -> Result<(), PatchError> {
+        let unknown = || PatchError::UnknownStruct(module.clone(), from.to_string());
+        let module_idx = self.find_module_handle(module).ok_or_else(unknown)?;
+        let position = self
+            .module
+            .struct_handles
+            .iter()
+            .position(|handle| handle.module == module_idx && self.string(handle.name) == from)
+            .ok_or_else(unknown)?;
+        let name = self.intern_string(to);
+        self.module.struct_handles[position].name = name;
+        Ok(())
+    }
+
+    /// Renames function `from` of module `module` to `to`.
+    pub fn rename_function(
+        &mut self,
+        module: &ModuleId,
+        from: &str,
+        to: &str,
+    ) -> Result<(), PatchError> {
+        let unknown = || PatchError::UnknownFunction(module.clone(), from.to_string());
+        let module_idx = self.find_module_handle(module).ok_or_else(unknown)?;
+        let position = self
+            .module
+            .function_handles
+            .iter()
+            .position(|handle| handle.module == module_idx && self.string(handle.name) == from)
+            .ok_or_else(unknown)?;
+        let name = self.intern_string(to);
+        self.module.function_handles[position].name = name;
+        Ok(())
+    }
+
+    /// Merges the handles the patches made identical, and returns the patched module.
+    pub fn finish(mut self) -> Result<CompiledModule, PatchError> {
+        self.merge_module_handles()?;
+        self.merge_struct_handles()?;
+        self.merge_function_handles()?;
+        self.module
+            .freeze()
+            .map_err(|err| PatchError::OutOfBounds(err.into_errors()))
+    }
+
+    fn merge_module_handles(&mut self) -> Result<(), PatchError> {
+        let self_handle = self.module.module_handles[0].clone();
+        if self.module.module_handles[1..].contains(&self_handle) {
+            return Err(PatchError::SelfDependency(self.module_id(&self_handle)));
+        }
+        let new_indexes = dedup(&mut self.module.module_handles, |handle| handle.clone());
+        for handle in &mut self.module.struct_handles {
+            handle.module = ModuleHandleIndex::new(new_indexes[handle.module.0 as usize]);
+        }
+        for handle in &mut self.module.function_handles {
+            handle.module = ModuleHandleIndex::new(new_indexes[handle.module.0 as usize]);
+        }
+        Ok(())
+    }
+
+    fn merge_struct_handles(&mut self) -> Result<(), PatchError> {
+        // Handles to the same struct must agree on the struct, and only one can be defined.
+        let mut seen = HashMap::new();
+        for (idx, handle) in self.module.struct_handles.iter().enumerate() {
+            if let Some(first) = seen.insert((handle.module, handle.name), idx) {
+                let first = &self.module.struct_handles[first];
+                let is_self = handle.module.0 == CompiledModule::IMPLEMENTED_MODULE_INDEX;
+                if is_self
+                    || first.is_nominal_resource != handle.is_nominal_resource
+                    || first.type_formals != handle.type_formals
+                {
+                    return Err(PatchError::ConflictingStructs(
+                        self.module_id(&self.module.module_handles[handle.module.0 as usize]),
+                        self.string(handle.name).to_string(),
+                    ));
+                }
+            }
+        }
+
+        let new_indexes = dedup(&mut self.module.struct_handles, |handle| {
+            (handle.module, handle.name)
+        });
+        let remap = |idx: StructHandleIndex| StructHandleIndex::new(new_indexes[idx.0 as usize]);
+        let module = &mut self.module;
+        for signature in &mut module.type_signatures {
+            remap_token(&mut signature.0, &remap);
+        }
+        for signature in &mut module.function_signatures {
+            for token in signature
+                .arg_types
+                .iter_mut()
+                .chain(signature.return_types.iter_mut())
+            {
+                remap_token(token, &remap);
+            }
+        }
+        for signature in &mut module.locals_signatures {
+            for token in &mut signature.0 {
+                remap_token(token, &remap);
+            }
+        }
+        for struct_def in &mut module.struct_defs {
+            struct_def.struct_handle = remap(struct_def.struct_handle);
+        }
+        for field_def in &mut module.field_defs {
+            field_def.struct_ = remap(field_def.struct_);
+        }
+        Ok(())
+    }
+
+    fn merge_function_handles(&mut self) -> Result<(), PatchError> {
+        // Handles to the same function must agree on its signature, and only one can be defined.
+        let mut seen = HashMap::new();
+        for (idx, handle) in self.module.function_handles.iter().enumerate() {
+            if let Some(first) = seen.insert((handle.module, handle.name), idx) {
+                let first = &self.module.function_handles[first];
+                let is_self = handle.module.0 == CompiledModule::IMPLEMENTED_MODULE_INDEX;
+                let signatures = &self.module.function_signatures;
+                if is_self
+                    || signatures[first.signature.0 as usize]
+                        != signatures[handle.signature.0 as usize]
+                {
+                    return Err(PatchError::ConflictingFunctions(
+                        self.module_id(&self.module.module_handles[handle.module.0 as usize]),
+                        self.string(handle.name).to_string(),
+                    ));
+                }
+            }
+        }
+
+        let new_indexes = dedup(&mut self.module.function_handles, |handle| {
+            (handle.module, handle.name)
+        });
+        let remap =
+            |idx: FunctionHandleIndex| FunctionHandleIndex::new(new_indexes[idx.0 as usize]);
+        for function_def in &mut self.module.function_defs {
+            function_def.function = remap(function_def.function);
+            for bytecode in &mut function_def.code.code {
+                if let Bytecode::Call(idx, _) = bytecode {
+                    *idx = remap(*idx);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find_module_handle(&self, id: &ModuleId) -> Option<ModuleHandleIndex> {
+        self.module
+            .module_handles
+            .iter()
+            .position(|handle| self.module_id(handle) == *id)
+            .map(|position| ModuleHandleIndex::new(position as TableIndex))
+    }
+
+    fn module_id(&self, handle: &ModuleHandle) -> ModuleId {
+        ModuleId::new(
+            self.module.address_pool[handle.address.0 as usize],
+            self.string(handle.name).to_string(),
+        )
+    }
+
+    fn string(&self, idx: StringPoolIndex) -> &str {
+        &self.module.string_pool[idx.0 as usize]
+    }
+
+    fn intern_string(&mut self, string: &str) -> StringPoolIndex {
+        StringPoolIndex::new(intern(&mut self.module.string_pool, string.into()))
+    }
+
+    fn intern_address(&mut self, address: AccountAddress) -> AddressPoolIndex {
+        AddressPoolIndex::new(intern(&mut self.module.address_pool, address))
+    }
+}
+
+/// Returns the index of `value` in `pool`, adding it if needed.
+fn intern<T: PartialEq>(pool: &mut Vec<T>, value: T) -> TableIndex {
+    let position = match pool.iter().position(|existing| *existing == value) {
+        Some(position) => position,
+        None => {
+            pool.push(value);
+            pool.len() - 1
+        }
+    };
+    position as TableIndex
+}
+
+/// Removes the items of `items` with the same key as an earlier one, and returns the new index
+/// of every item, the removed ones being mapped to the earlier one they duplicate.
+fn dedup<T, K: Eq + Hash>(items: &mut Vec<T>, key: impl Fn(&T) -> K) -> Vec<TableIndex> {
+    let mut first_indexes = HashMap::new();
+    let mut new_indexes = Vec::with_capacity(items.len());
+    let mut keep = Vec::with_capacity(items.len());
+    for item in items.iter() {
+        let next = first_indexes.len() as TableIndex;
+        let new_index = *first_indexes.entry(key(item)).or_insert(next);
+        keep.push(new_index == next);
+        new_indexes.push(new_index);
+    }
+    let mut keep = keep.into_iter();
+    items.retain(|_| keep.next().unwrap_or(false));
+    new_indexes
+}
+
+fn remap_token(
+    token: &mut SignatureToken,
+    remap: &impl Fn(StructHandleIndex) -> StructHandleIndex,
+) {
+    match token {
+        SignatureToken::Struct(idx, type_actuals) => {
+            *idx = remap(*idx);
+            for type_actual in type_actuals {
+                remap_token(type_actual, remap);
+            }
+        }
+        SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+            remap_token(inner, remap)
+        }
+        SignatureToken::Bool
+        | SignatureToken::U64
+        | SignatureToken::String
+        | SignatureToken::ByteArray
+        | SignatureToken::Address
+        | SignatureToken::TypeParameter(_) => (),
+    }
+}
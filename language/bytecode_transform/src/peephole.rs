@@ -0,0 +1,176 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements a peephole optimizer, which rewrites short sequences of instructions into
+//! equivalent shorter ones:
+//! - a pure load immediately popped is removed (e.g. `LdConst(1); Pop`);
+//! - a branch to an unconditional branch goes directly to the target of the latter;
+//! - `Not` followed by a conditional branch is fused into the opposite conditional branch.
+//!
+//! A sequence is only rewritten if no branch goes into the middle of it, so that every path
+//! through the code executes the same operations as before. The rewrites preserve verification,
+//! which `optimize_module` checks before and after optimizing.
+
+use crate::code_editor::CodeEditor;
+use bytecode_verifier::VerifiedModule;
+use std::collections::{BTreeSet, HashSet};
+use vm::{
+    errors::VerificationError,
+    file_format::{Bytecode, CodeOffset, CompiledModule},
+};
+
+/// The number of rewrites of each kind the optimizer made.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PeepholeStats {
+    /// Pure loads removed along with the `Pop` following them.
+    pub dead_pops: usize,
+    /// Branches redirected past an unconditional branch.
+    pub threaded_branches: usize,
+    /// `Not` instructions fused into the conditional branch following them.
+    pub fused_branches: usize,
+}
+
+impl PeepholeStats {
+    /// Returns the total number of rewrites.
+    pub fn total(&self) -> usize {
+        self.dead_pops + self.threaded_branches + self.fused_branches
+    }
+}
+
+/// Why a module couldn't be optimized.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum OptimizationError {
+    /// The module didn't verify before optimizing it.
+    Unverified(Vec<VerificationError>),
+    /// The module didn't verify after optimizing it, which is a bug in the optimizer.
+    Miscompiled(Vec<VerificationError>),
+}
+
+/// Verifies `module`, optimizes the code of every function until no rewrite applies anymore, and
+/// verifies the optimized module.
+pub fn optimize_module(
+    module: CompiledModule,
+) -> Result<(VerifiedModule, PeepholeStats), OptimizationError> {
+    let module =
+        VerifiedModule::new(module).map_err(|(_, errors)| OptimizationError::Unverified(errors))?;
+    let mut stats = PeepholeStats::default();
+    let mut module = module.into_inner().into_inner();
+    for function_def in &mut module.function_defs {
+        if function_def.is_native() {
+            continue;
+        }
+        function_def.code.code = optimize_code(&function_def.code.code, &mut stats);
+    }
+    let module = module
+        .freeze()
+        .map_err(|err| OptimizationError::Miscompiled(err.into_errors()))?;
+    let module = VerifiedModule::new(module)
+        .map_err(|(_, errors)| OptimizationError::Miscompiled(errors))?;
+    Ok((module, stats))
+}
+
+/// Optimizes `code` until no rewrite applies anymore, and adds the rewrites made to `stats`.
+pub fn optimize_code(code: &[Bytecode], stats: &mut PeepholeStats) -> Vec<Bytecode> {
+    let mut code = code.to_vec();
+    loop {
+        let total = stats.total();
+        code = remove_dead_pops(code, stats);
+        code = thread_branches(code, stats);
+        code = fuse_not_branches(code, stats);
+        if stats.total() == total {
+            return code;
+        }
+    }
+}
+
+fn remove_dead_pops(code: Vec<Bytecode>, stats: &mut PeepholeStats) -> Vec<Bytecode> {
+    let targets = branch_targets(&code);
+    let mut editor = CodeEditor::new(code);
+    let mut offset = 0;
+    while offset + 1 < editor.code().len() {
+        let pop = offset + 1;
+        if is_pure_load(&editor.code()[offset])
+            && editor.code()[pop] == Bytecode::Pop
+            && !targets.contains(&(pop as CodeOffset))
+        {
+            editor.delete(offset as CodeOffset);
+            editor.delete(pop as CodeOffset);
+            stats.dead_pops += 1;
+            offset += 2;
+        } else {
+            offset += 1;
+        }
+    }
+    editor.finish()
+}
+
+fn thread_branches(code: Vec<Bytecode>, stats: &mut PeepholeStats) -> Vec<Bytecode> {
+    let mut editor = CodeEditor::new(code.clone());
+    for (offset, bytecode) in code.iter().enumerate() {
+        let (target, rebuild): (_, fn(CodeOffset) -> Bytecode) = match bytecode {
+            Bytecode::BrTrue(target) => (*target, Bytecode::BrTrue),
+            Bytecode::BrFalse(target) => (*target, Bytecode::BrFalse),
+            Bytecode::Branch(target) => (*target, Bytecode::Branch),
+            _ => continue,
+        };
+        // Follow the chain of unconditional branches, stopping at a cycle.
+        let mut final_target = target;
+        let mut visited = HashSet::new();
+        while let Some(Bytecode::Branch(next)) = code.get(final_target as usize) {
+            if !visited.insert(final_target) {
+                break;
+            }
+            final_target = *next;
+        }
+        if final_target != target {
+            editor.replace(offset as CodeOffset, rebuild(final_target));
+            stats.threaded_branches += 1;
+        }
+    }
+    editor.finish()
+}
+
+fn fuse_not_branches(code: Vec<Bytecode>, stats: &mut PeepholeStats) -> Vec<Bytecode> {
+    let targets = branch_targets(&code);
+    let mut editor = CodeEditor::new(code.clone());
+    for (offset, window) in code.windows(2).enumerate() {
+        let branch = (offset + 1) as CodeOffset;
+        if window[0] != Bytecode::Not || targets.contains(&branch) {
+            continue;
+        }
+        let fused = match window[1] {
+            Bytecode::BrTrue(target) => Bytecode::BrFalse(target),
+            Bytecode::BrFalse(target) => Bytecode::BrTrue(target),
+            _ => continue,
+        };
+        editor.delete(offset as CodeOffset);
+        editor.replace(branch, fused);
+        stats.fused_branches += 1;
+    }
+    editor.finish()
+}
+
+/// Returns true if `bytecode` pushes a value without any other effect.
+fn is_pure_load(bytecode: &Bytecode) -> bool {
+    match bytecode {
+        Bytecode::LdConst(_)
+        | Bytecode::LdStr(_)
+        | Bytecode::LdByteArray(_)
+        | Bytecode::LdAddr(_)
+        | Bytecode::LdTrue
+        | Bytecode::LdFalse
+        | Bytecode::CopyLoc(_) => true,
+        _ => false,
+    }
+}
+
+pub(crate) fn branch_targets(code: &[Bytecode]) -> BTreeSet<CodeOffset> {
+    code.iter()
+        .filter_map(|bytecode| match bytecode {
+            Bytecode::BrTrue(target) | Bytecode::BrFalse(target) | Bytecode::Branch(target) => {
+                Some(*target)
+            }
+            _ => None,
+        })
+        .collect()
+}
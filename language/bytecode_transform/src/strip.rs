@@ -0,0 +1,271 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Implements stripping, which shrinks a module for release deployments:
+//! - the entries of the string, byte array and address pools that nothing refers to anymore are
+//!   removed, e.g. the names left behind by other transforms;
+//! - optionally, the identifiers that other modules can't refer to are mangled to short names.
+//!   These are the names of the functions that are neither public nor native, and the names of
+//!   fields. Struct names are kept, since they are part of the type of published resources.
+//!
+//! The binary format has no debug sections: debug information lives in source maps, which are
+//! stored next to modules and never published. Mangling makes the source names of a module
+//! unrecoverable from the module alone, so it returns an `IdentifierMapping` to reverse it.
+
+use crate::peephole::OptimizationError;
+use bytecode_verifier::VerifiedModule;
+use failure::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use vm::file_format::{
+    AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CodeUnit, CompiledModule, CompiledModuleMut,
+    StringPoolIndex, StructFieldInformation, TableIndex,
+};
+
+/// What to strip from a module.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StripOptions {
+    /// Whether to mangle the names of private functions and of fields.
+    pub mangle_identifiers: bool,
+}
+
+/// Maps the identifiers stripping mangled back to the original ones.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct IdentifierMapping {
+    /// The original names of the mangled functions, by mangled name.
+    pub functions: BTreeMap<String, String>,
+    /// The original names of the mangled fields, by struct name and mangled name.
+    pub fields: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+impl IdentifierMapping {
+    /// Returns true if no identifier was mangled.
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.fields.is_empty()
+    }
+
+    /// Returns the original name of the function now named `name`.
+    pub fn function_name<'a>(&'a self, name: &'a str) -> &'a str {
+        self.functions.get(name).map_or(name, String::as_str)
+    }
+
+    /// Returns the original name of the field of struct `struct_name` now named `name`.
+    pub fn field_name<'a>(&'a self, struct_name: &str, name: &'a str) -> &'a str {
+        self.fields
+            .get(struct_name)
+            .and_then(|fields| fields.get(name))
+            .map_or(name, String::as_str)
+    }
+
+    /// Serializes the mapping to JSON, to be stored next to the stripped module.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parses a mapping serialized with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+/// What stripping removed from a module.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StripReport {
+    /// The number of entries removed from the string pool.
+    pub strings: usize,
+    /// The number of entries removed from the byte array pool.
+    pub byte_arrays: usize,
+    /// The number of entries removed from the address pool.
+    pub addresses: usize,
+    /// How to reverse the mangling of identifiers.
+    pub mapping: IdentifierMapping,
+}
+
+/// Strips `module` according to `options`, and verifies the resulting module.
+pub fn strip_module(
+    module: VerifiedModule,
+    options: StripOptions,
+) -> Result<(VerifiedModule, StripReport), OptimizationError> {
+    let mut module = module.into_inner().into_inner();
+    let mut report = StripReport::default();
+    if options.mangle_identifiers {
+        report.mapping = mangle_identifiers(&mut module);
+    }
+    compact_pools(&mut module, &mut report);
+    let module = module
+        .freeze()
+        .map_err(|err| OptimizationError::Miscompiled(err.into_errors()))?;
+    let module = VerifiedModule::new(module)
+        .map_err(|(_, errors)| OptimizationError::Miscompiled(errors))?;
+    Ok((module, report))
+}
+
+fn mangle_identifiers(module: &mut CompiledModuleMut) -> IdentifierMapping {
+    let mut mapping = IdentifierMapping::default();
+    let self_module = CompiledModule::IMPLEMENTED_MODULE_INDEX;
+
+    // Mangled function names must not collide with the names that are kept.
+    let mut to_mangle = BTreeSet::new();
+    for function_def in &module.function_defs {
+        if function_def.flags & CodeUnit::PUBLIC == 0 && !function_def.is_native() {
+            to_mangle.insert(function_def.function.0);
+        }
+    }
+    let kept_names: BTreeSet<_> = module
+        .function_handles
+        .iter()
+        .enumerate()
+        .filter(|(idx, handle)| {
+            handle.module.0 == self_module && !to_mangle.contains(&(*idx as TableIndex))
+        })
+        .map(|(_, handle)| module.string_pool[handle.name.0 as usize].to_string())
+        .collect();
+    let mut names = (0..)
+        .map(short_name)
+        .filter(|name| !kept_names.contains(name));
+    for idx in to_mangle {
+        let mangled = names.next().expect("names are unbounded");
+        let handle_name = module.function_handles[idx as usize].name;
+        let original = module.string_pool[handle_name.0 as usize].to_string();
+        module.function_handles[idx as usize].name = intern_string(module, &mangled);
+        mapping.functions.insert(mangled, original);
+    }
+
+    // Field names only need to be unique within their struct.
+    for struct_idx in 0..module.struct_defs.len() {
+        let (field_count, first_field) = match module.struct_defs[struct_idx].field_information {
+            StructFieldInformation::Native => continue,
+            StructFieldInformation::Declared {
+                field_count,
+                fields,
+            } => (field_count as usize, fields.0 as usize),
+        };
+        let handle =
+            &module.struct_handles[module.struct_defs[struct_idx].struct_handle.0 as usize];
+        let struct_name = module.string_pool[handle.name.0 as usize].to_string();
+        let mut fields = BTreeMap::new();
+        for (position, field_idx) in (first_field..first_field + field_count).enumerate() {
+            let mangled = short_name(position);
+            let original_name = module.field_defs[field_idx].name;
+            let original = module.string_pool[original_name.0 as usize].to_string();
+            module.field_defs[field_idx].name = intern_string(module, &mangled);
+            fields.insert(mangled, original);
+        }
+        if !fields.is_empty() {
+            mapping.fields.insert(struct_name, fields);
+        }
+    }
+    mapping
+}
+
+/// Returns the `n`th shortest lowercase identifier: `a`, ..., `z`, `aa`, `ab`, ...
+fn short_name(n: usize) -> String {
+    let mut n = n + 1;
+    let mut name = vec![];
+    while n > 0 {
+        n -= 1;
+        name.push(b'a' + (n % 26) as u8);
+        n /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("identifiers are ASCII")
+}
+
+fn intern_string(module: &mut CompiledModuleMut, string: &str) -> StringPoolIndex {
+    let position = match module.string_pool.iter().position(|s| s == string) {
+        Some(position) => position,
+        None => {
+            module.string_pool.push(string.into());
+            module.string_pool.len() - 1
+        }
+    };
+    StringPoolIndex::new(position as TableIndex)
+}
+
+fn compact_pools(module: &mut CompiledModuleMut, report: &mut StripReport) {
+    let mut strings = BTreeSet::new();
+    let mut byte_arrays = BTreeSet::new();
+    let mut addresses = BTreeSet::new();
+    for handle in &module.module_handles {
+        strings.insert(handle.name.0);
+        addresses.insert(handle.address.0);
+    }
+    for handle in &module.struct_handles {
+        strings.insert(handle.name.0);
+    }
+    for handle in &module.function_handles {
+        strings.insert(handle.name.0);
+    }
+    for field_def in &module.field_defs {
+        strings.insert(field_def.name.0);
+    }
+    for bytecode in code(module) {
+        match bytecode {
+            Bytecode::LdStr(idx) => strings.insert(idx.0),
+            Bytecode::LdByteArray(idx) => byte_arrays.insert(idx.0),
+            Bytecode::LdAddr(idx) => addresses.insert(idx.0),
+            _ => continue,
+        };
+    }
+
+    let new_strings = compact(&mut module.string_pool, &strings);
+    let new_byte_arrays = compact(&mut module.byte_array_pool, &byte_arrays);
+    let new_addresses = compact(&mut module.address_pool, &addresses);
+    report.strings = new_strings.len() - strings.len();
+    report.byte_arrays = new_byte_arrays.len() - byte_arrays.len();
+    report.addresses = new_addresses.len() - addresses.len();
+
+    let string = |idx: StringPoolIndex| StringPoolIndex::new(new_strings[idx.0 as usize]);
+    let address = |idx: AddressPoolIndex| AddressPoolIndex::new(new_addresses[idx.0 as usize]);
+    for handle in &mut module.module_handles {
+        handle.name = string(handle.name);
+        handle.address = address(handle.address);
+    }
+    for handle in &mut module.struct_handles {
+        handle.name = string(handle.name);
+    }
+    for handle in &mut module.function_handles {
+        handle.name = string(handle.name);
+    }
+    for field_def in &mut module.field_defs {
+        field_def.name = string(field_def.name);
+    }
+    for function_def in &mut module.function_defs {
+        for bytecode in &mut function_def.code.code {
+            match bytecode {
+                Bytecode::LdStr(idx) => *idx = string(*idx),
+                Bytecode::LdByteArray(idx) => {
+                    *idx = ByteArrayPoolIndex::new(new_byte_arrays[idx.0 as usize])
+                }
+                Bytecode::LdAddr(idx) => *idx = address(*idx),
+                _ => (),
+            }
+        }
+    }
+}
+
+fn code(module: &CompiledModuleMut) -> impl Iterator<Item = &Bytecode> {
+    module
+        .function_defs
+        .iter()
+        .flat_map(|function_def| function_def.code.code.iter())
+}
+
+/// Removes the entries of `pool` whose index isn't in `used`, and returns the new index of every
+/// original entry. Removed entries map to an arbitrary index, since nothing refers to them.
+fn compact<T>(pool: &mut Vec<T>, used: &BTreeSet<TableIndex>) -> Vec<TableIndex> {
+    let mut new_indexes = Vec::with_capacity(pool.len());
+    let mut next = 0;
+    for idx in 0..pool.len() {
+        new_indexes.push(next);
+        if used.contains(&(idx as TableIndex)) {
+            next += 1;
+        }
+    }
+    let mut idx = 0;
+    pool.retain(|_| {
+        idx += 1;
+        used.contains(&(idx - 1))
+    });
+    new_indexes
+}
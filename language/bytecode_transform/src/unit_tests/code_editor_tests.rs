@@ -0,0 +1,110 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::code_editor::CodeEditor;
+use vm::file_format::{Bytecode, CodeOffset};
+
+fn code() -> Vec<Bytecode> {
+    vec![
+        Bytecode::LdTrue,
+        Bytecode::BrTrue(3),
+        Bytecode::Branch(0),
+        Bytecode::LdConst(1),
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]
+}
+
+#[test]
+fn no_edits_keep_code() {
+    let editor = CodeEditor::new(code());
+    assert!(editor.is_unchanged());
+    assert_eq!(editor.finish(), code());
+}
+
+#[test]
+fn deletions_remap_branches() {
+    let mut editor = CodeEditor::new(code());
+    editor.delete(3);
+    editor.delete(4);
+    assert!(!editor.is_unchanged());
+    // The branch to the deleted offset 3 goes to the next instruction left.
+    assert_eq!(
+        editor.finish(),
+        vec![
+            Bytecode::LdTrue,
+            Bytecode::BrTrue(3),
+            Bytecode::Branch(0),
+            Bytecode::Ret,
+        ]
+    );
+
+    let mut editor = CodeEditor::new(code());
+    editor.delete(0);
+    editor.replace(2, Bytecode::Branch(5));
+    assert_eq!(
+        editor.finish(),
+        vec![
+            Bytecode::BrTrue(2),
+            Bytecode::Branch(4),
+            Bytecode::LdConst(1),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]
+    );
+}
+
+#[test]
+fn insertions_are_branched_to() {
+    let mut editor = CodeEditor::new(code());
+    editor.insert_before(3, vec![Bytecode::LdFalse, Bytecode::Pop]);
+    editor.insert_before(6, vec![Bytecode::Ret]);
+    editor.delete(5);
+    assert_eq!(
+        editor.finish(),
+        vec![
+            Bytecode::LdTrue,
+            Bytecode::BrTrue(3),
+            Bytecode::Branch(0),
+            Bytecode::LdFalse,
+            Bytecode::Pop,
+            Bytecode::LdConst(1),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]
+    );
+}
+
+#[test]
+fn prologue_is_not_branched_to() {
+    let mut editor = CodeEditor::new(code());
+    editor.insert_prologue(vec![Bytecode::LdFalse, Bytecode::Pop]);
+    editor.insert_before(0, vec![Bytecode::LdTrue, Bytecode::Pop]);
+    assert!(!editor.is_unchanged());
+    assert_eq!(
+        editor.finish(),
+        vec![
+            Bytecode::LdFalse,
+            Bytecode::Pop,
+            Bytecode::LdTrue,
+            Bytecode::Pop,
+            Bytecode::LdTrue,
+            Bytecode::BrTrue(7),
+            Bytecode::Branch(2),
+            Bytecode::LdConst(1),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]
+    );
+}
+
+#[test]
+fn too_long_code_is_reported() {
+    let mut editor = CodeEditor::new(code());
+    editor.insert_before(3, vec![Bytecode::Pop; CodeOffset::max_value() as usize]);
+    // The branch to offset 3 could still be remapped, but not the end of the code.
+    assert_eq!(editor.try_finish(), None);
+
+    let editor = CodeEditor::new(code());
+    assert_eq!(editor.try_finish(), Some(code()));
+}
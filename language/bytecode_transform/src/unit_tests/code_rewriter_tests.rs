@@ -0,0 +1,105 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::code_rewriter::CodeRewriter;
+use std::collections::BTreeMap;
+use vm::file_format::{Bytecode, CodeOffset};
+
+fn code() -> Vec<Bytecode> {
+    vec![
+        Bytecode::CopyLoc(0),
+        Bytecode::BrTrue(4),
+        Bytecode::LdConst(1),
+        Bytecode::StLoc(1),
+        Bytecode::MoveLoc(1),
+        Bytecode::Pop,
+        Bytecode::Branch(0),
+    ]
+}
+
+#[test]
+fn identity_rewrite_keeps_code() {
+    let rewritten = CodeRewriter::new()
+        .rewrite(&code(), |_, bytecode| vec![bytecode.clone()])
+        .unwrap();
+    assert_eq!(rewritten, code());
+}
+
+#[test]
+fn expansions_and_removals_remap_branches() {
+    // Check every load of a local before it, and drop the store.
+    let rewritten = CodeRewriter::new()
+        .rewrite(&code(), |offset, bytecode| match bytecode {
+            Bytecode::MoveLoc(_) => vec![
+                Bytecode::LdTrue,
+                Bytecode::BrFalse(offset),
+                bytecode.clone(),
+            ],
+            Bytecode::StLoc(_) => vec![Bytecode::Pop],
+            Bytecode::LdConst(_) => vec![],
+            _ => vec![bytecode.clone()],
+        })
+        .unwrap();
+    assert_eq!(
+        rewritten,
+        vec![
+            Bytecode::CopyLoc(0),
+            // Branches to an expanded instruction go to the first instruction of the expansion.
+            Bytecode::BrTrue(3),
+            Bytecode::Pop,
+            Bytecode::LdTrue,
+            Bytecode::BrFalse(3),
+            Bytecode::MoveLoc(1),
+            Bytecode::Pop,
+            Bytecode::Branch(0),
+        ]
+    );
+
+    // Branches to an instruction rewritten to nothing go to the next one.
+    let rewritten = CodeRewriter::new()
+        .rewrite(&code(), |offset, bytecode| {
+            if offset == 4 {
+                vec![]
+            } else {
+                vec![bytecode.clone()]
+            }
+        })
+        .unwrap();
+    assert_eq!(rewritten[1], Bytecode::BrTrue(4));
+    assert_eq!(rewritten[4], Bytecode::Pop);
+}
+
+#[test]
+fn locals_are_renumbered() {
+    let mut renumbering = BTreeMap::new();
+    renumbering.insert(1, 2);
+    let rewritten = CodeRewriter::new()
+        .renumber_locals(renumbering)
+        .rewrite(&code(), |_, bytecode| match bytecode {
+            // The instructions returned refer to the original locals.
+            Bytecode::Pop => vec![Bytecode::ImmBorrowLoc(1), Bytecode::Pop, bytecode.clone()],
+            _ => vec![bytecode.clone()],
+        })
+        .unwrap();
+    assert_eq!(
+        &rewritten[..7],
+        &[
+            Bytecode::CopyLoc(0),
+            Bytecode::BrTrue(4),
+            Bytecode::LdConst(1),
+            Bytecode::StLoc(2),
+            Bytecode::MoveLoc(2),
+            Bytecode::ImmBorrowLoc(2),
+            Bytecode::Pop,
+        ][..]
+    );
+}
+
+#[test]
+fn too_long_code_is_reported() {
+    let expansion = vec![Bytecode::LdTrue; CodeOffset::max_value() as usize / 2];
+    assert_eq!(
+        CodeRewriter::new().rewrite(&code()[..3], |_, _| expansion.clone()),
+        None
+    );
+}
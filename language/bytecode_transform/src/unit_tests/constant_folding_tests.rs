@@ -0,0 +1,170 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    constant_folding::{fold_constants, ConstantFoldingStats},
+    dead_code::eliminate_dead_code,
+};
+use bytecode_verifier::VerifiedModule;
+use types::account_address::AccountAddress;
+use vm::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, FunctionSignature, LocalsSignature, SignatureToken},
+};
+
+/// Builds a module with a function `f(u64)` with `code` and an extra `u64` local.
+fn verified_module(code: Vec<Bytecode>) -> VerifiedModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let mut code_builder = CodeBuilder::new();
+    for bytecode in code {
+        code_builder.emit(bytecode);
+    }
+    let signature = FunctionSignature {
+        arg_types: vec![SignatureToken::U64].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature,
+        vec![SignatureToken::U64],
+        vec![],
+        code_builder,
+    );
+    VerifiedModule::new(builder.build().expect("module is bounds-valid")).expect("module verifies")
+}
+
+fn fold(code: Vec<Bytecode>) -> (Vec<Bytecode>, ConstantFoldingStats) {
+    let (module, stats) = fold_constants(verified_module(code)).expect("module stays verifiable");
+    (module.function_defs()[0].code.code.clone(), stats)
+}
+
+#[test]
+fn constants_fold_through_locals_and_branches() {
+    let module = verified_module(vec![
+        // x = 2 + 3
+        Bytecode::LdConst(2),
+        Bytecode::LdConst(3),
+        Bytecode::Add,
+        Bytecode::StLoc(1),
+        // assert(x == 5, 1)
+        Bytecode::CopyLoc(1),
+        Bytecode::LdConst(5),
+        Bytecode::Eq,
+        Bytecode::BrTrue(10),
+        Bytecode::LdConst(1),
+        Bytecode::Abort,
+        // arg + x
+        Bytecode::MoveLoc(0),
+        Bytecode::CopyLoc(1),
+        Bytecode::Add,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    let (module, stats) = fold_constants(module).expect("module stays verifiable");
+    assert_eq!(
+        stats,
+        ConstantFoldingStats {
+            folded_operations: 2,
+            folded_branches: 1,
+            propagated_constants: 2,
+            dead_stores: 1,
+        }
+    );
+    assert_eq!(
+        module.function_defs()[0].code.code,
+        vec![
+            Bytecode::Branch(3),
+            Bytecode::LdConst(1),
+            Bytecode::Abort,
+            Bytecode::MoveLoc(0),
+            Bytecode::LdConst(5),
+            Bytecode::Add,
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]
+    );
+
+    // The assertion and the local are now dead.
+    let (module, _) = eliminate_dead_code(module).expect("module stays verifiable");
+    let code_unit = &module.function_defs()[0].code;
+    assert_eq!(
+        code_unit.code,
+        vec![
+            Bytecode::Branch(1),
+            Bytecode::MoveLoc(0),
+            Bytecode::LdConst(5),
+            Bytecode::Add,
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]
+    );
+    assert_eq!(
+        module.locals_signature_at(code_unit.locals),
+        &LocalsSignature(vec![SignatureToken::U64].into())
+    );
+}
+
+#[test]
+fn failing_arithmetic_is_kept() {
+    for code in vec![
+        vec![Bytecode::LdConst(1), Bytecode::LdConst(0), Bytecode::Div],
+        vec![Bytecode::LdConst(1), Bytecode::LdConst(0), Bytecode::Mod],
+        vec![Bytecode::LdConst(0), Bytecode::LdConst(1), Bytecode::Sub],
+        vec![
+            Bytecode::LdConst(u64::max_value()),
+            Bytecode::LdConst(1),
+            Bytecode::Add,
+        ],
+    ] {
+        let mut code = code;
+        code.extend(vec![Bytecode::Pop, Bytecode::Ret]);
+        let (folded, stats) = fold(code.clone());
+        assert_eq!(folded, code);
+        assert_eq!(stats, ConstantFoldingStats::default());
+    }
+}
+
+/// Stores `a` or `b` in local 1 depending on the argument, then reads local 1.
+fn store_either(a: u64, b: u64) -> Vec<Bytecode> {
+    vec![
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(0),
+        Bytecode::Eq,
+        Bytecode::BrFalse(7),
+        Bytecode::LdConst(a),
+        Bytecode::StLoc(1),
+        Bytecode::Branch(9),
+        Bytecode::LdConst(b),
+        Bytecode::StLoc(1),
+        Bytecode::MoveLoc(1),
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]
+}
+
+#[test]
+fn constants_propagate_only_if_every_path_agrees() {
+    let (folded, stats) = fold(store_either(1, 2));
+    assert_eq!(folded, store_either(1, 2));
+    assert_eq!(stats, ConstantFoldingStats::default());
+
+    let (folded, stats) = fold(store_either(1, 1));
+    assert_eq!(
+        folded,
+        vec![
+            Bytecode::CopyLoc(0),
+            Bytecode::LdConst(0),
+            Bytecode::Eq,
+            Bytecode::BrFalse(5),
+            Bytecode::Branch(5),
+            Bytecode::LdConst(1),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ]
+    );
+    assert_eq!(stats.propagated_constants, 1);
+    assert_eq!(stats.dead_stores, 2);
+}
@@ -0,0 +1,79 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::dead_code::{eliminate_dead_code, RemovedCode};
+use bytecode_verifier::VerifiedModule;
+use types::account_address::AccountAddress;
+use vm::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, FunctionDefinitionIndex, FunctionSignature, LocalsSignature,
+        SignatureToken,
+    },
+};
+
+fn verified_module(locals: Vec<SignatureToken>, code: CodeBuilder) -> VerifiedModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let signature = FunctionSignature {
+        arg_types: vec![SignatureToken::U64].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    builder.add_function("f", CodeUnit::PUBLIC, signature, locals, vec![], code);
+    VerifiedModule::new(builder.build().expect("module is bounds-valid")).expect("module verifies")
+}
+
+#[test]
+fn unreachable_blocks_and_unused_locals_are_removed() {
+    let mut code = CodeBuilder::new();
+    let exit = code.new_label();
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::StLoc(2));
+    code.emit_branch(Bytecode::Branch, exit);
+    // Unreachable, and the only use of local 1.
+    code.emit(Bytecode::LdTrue);
+    code.emit(Bytecode::StLoc(1));
+    code.bind(exit);
+    code.emit(Bytecode::Ret);
+    let module = verified_module(vec![SignatureToken::Bool, SignatureToken::U64], code);
+
+    let (module, report) = eliminate_dead_code(module).expect("module stays verifiable");
+    let removed = &report.functions[&FunctionDefinitionIndex::new(0)];
+    assert_eq!(
+        *removed,
+        RemovedCode {
+            blocks: vec![(3, 4)],
+            locals: vec![1],
+        }
+    );
+    assert_eq!(report.instruction_count(), 2);
+
+    let code_unit = &module.function_defs()[0].code;
+    assert_eq!(
+        code_unit.code,
+        vec![
+            Bytecode::CopyLoc(0),
+            Bytecode::StLoc(1),
+            Bytecode::Branch(3),
+            Bytecode::Ret,
+        ]
+    );
+    assert_eq!(
+        module.locals_signature_at(code_unit.locals),
+        &LocalsSignature(vec![SignatureToken::U64, SignatureToken::U64].into())
+    );
+}
+
+#[test]
+fn unused_arguments_are_kept() {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    let module = verified_module(vec![], code);
+    let locals = module.function_defs()[0].code.locals;
+
+    let (module, report) = eliminate_dead_code(module).expect("module stays verifiable");
+    assert!(report.is_empty());
+    assert_eq!(module.function_defs()[0].code.locals, locals);
+    assert_eq!(module.function_defs()[0].code.code, vec![Bytecode::Ret]);
+}
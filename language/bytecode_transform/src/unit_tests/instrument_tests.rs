@@ -0,0 +1,125 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::instrument::{InstrumentationError, Instrumenter};
+use bytecode_verifier::VerifiedModule;
+use types::account_address::AccountAddress;
+use vm::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, FunctionDefinitionIndex, FunctionSignature, SignatureToken},
+    reference_interpreter::{ReferenceInterpreter, Value},
+};
+
+/// Builds a module with a function counting its argument down to 0 in a loop starting at offset
+/// 0, and returning 7.
+fn verified_module() -> VerifiedModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let signature = FunctionSignature {
+        arg_types: vec![SignatureToken::U64].into(),
+        return_types: vec![SignatureToken::U64].into(),
+        type_formals: vec![],
+    };
+    let mut code = CodeBuilder::new();
+    let head = code.new_label();
+    let exit = code.new_label();
+    code.bind(head);
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::LdConst(0));
+    code.emit(Bytecode::Eq);
+    code.emit_branch(Bytecode::BrTrue, exit);
+    code.emit(Bytecode::MoveLoc(0));
+    code.emit(Bytecode::LdConst(1));
+    code.emit(Bytecode::Sub);
+    code.emit(Bytecode::StLoc(0));
+    code.emit_branch(Bytecode::Branch, head);
+    code.bind(exit);
+    code.emit(Bytecode::LdConst(7));
+    code.emit(Bytecode::Ret);
+    builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    VerifiedModule::new(builder.build().expect("module is bounds-valid")).expect("module verifies")
+}
+
+#[test]
+fn probes_are_inserted_with_their_locals() {
+    let mut instrumenter = Instrumenter::new();
+    instrumenter
+        .on_entry(vec![SignatureToken::U64], |_| {
+            vec![Bytecode::LdConst(42), Bytecode::StLoc(0)]
+        })
+        .on_exit(vec![SignatureToken::Bool], |_| {
+            vec![Bytecode::LdTrue, Bytecode::StLoc(0)]
+        })
+        .before(
+            |bytecode| *bytecode == Bytecode::Sub,
+            vec![],
+            |site| vec![Bytecode::LdConst(u64::from(site.offset)), Bytecode::Pop],
+        );
+    let module = instrumenter.instrument(verified_module()).unwrap();
+
+    let code_unit = &module.function_defs()[0].code;
+    assert_eq!(
+        code_unit.code,
+        vec![
+            // The entry probe, which the loop doesn't branch back to.
+            Bytecode::LdConst(42),
+            Bytecode::StLoc(1),
+            Bytecode::CopyLoc(0),
+            Bytecode::LdConst(0),
+            Bytecode::Eq,
+            Bytecode::BrTrue(13),
+            Bytecode::MoveLoc(0),
+            Bytecode::LdConst(1),
+            Bytecode::LdConst(6),
+            Bytecode::Pop,
+            Bytecode::Sub,
+            Bytecode::StLoc(0),
+            Bytecode::Branch(2),
+            Bytecode::LdConst(7),
+            Bytecode::LdTrue,
+            Bytecode::StLoc(2),
+            Bytecode::Ret,
+        ]
+    );
+    assert_eq!(
+        module.locals_signature_at(code_unit.locals).0.to_vec(),
+        vec![
+            SignatureToken::U64,
+            SignatureToken::U64,
+            SignatureToken::Bool
+        ]
+    );
+
+    let f = FunctionDefinitionIndex::new(0);
+    assert_eq!(
+        ReferenceInterpreter::new(module.as_inner()).execute(f, vec![Value::U64(3)]),
+        Ok(vec![Value::U64(7)])
+    );
+}
+
+#[test]
+fn invalid_probes_are_rejected() {
+    let mut instrumenter = Instrumenter::new();
+    instrumenter.on_exit(vec![], |_| vec![Bytecode::Ret]);
+    assert_eq!(
+        instrumenter.instrument(verified_module()).err(),
+        Some(InstrumentationError::InvalidProbe(Bytecode::Ret))
+    );
+
+    let mut instrumenter = Instrumenter::new();
+    instrumenter.on_entry(vec![SignatureToken::U64], |_| {
+        vec![Bytecode::LdConst(0), Bytecode::StLoc(1)]
+    });
+    assert_eq!(
+        instrumenter.instrument(verified_module()).err(),
+        Some(InstrumentationError::InvalidProbe(Bytecode::StLoc(1)))
+    );
+
+    // A probe that leaves a value on the stack breaks verification.
+    let mut instrumenter = Instrumenter::new();
+    instrumenter.on_entry(vec![], |_| vec![Bytecode::LdTrue]);
+    match instrumenter.instrument(verified_module()) {
+        Err(InstrumentationError::Unverified(errors)) => assert!(!errors.is_empty()),
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+}
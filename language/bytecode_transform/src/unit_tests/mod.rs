@@ -0,0 +1,11 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+mod code_editor_tests;
+mod code_rewriter_tests;
+mod constant_folding_tests;
+mod dead_code_tests;
+mod instrument_tests;
+mod patch_tests;
+mod peephole_tests;
+mod strip_tests;
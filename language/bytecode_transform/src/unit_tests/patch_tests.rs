@@ -0,0 +1,137 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::patch::{ModulePatcher, PatchError};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+use vm::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, FunctionSignature, SignatureToken, NO_TYPE_ACTUALS,
+    },
+};
+
+fn id(address: u8, name: &str) -> ModuleId {
+    ModuleId::new(AccountAddress::new([address; 32]), name.to_string())
+}
+
+fn unit_signature() -> FunctionSignature {
+    FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+/// Builds module 0x00::M, with a struct `S { t: 0x01::N::T }` and a function `f` calling
+/// `0x01::N::g` and `0x02::O::g`, whose signature is `g_signature`.
+fn module(g_signature: FunctionSignature) -> CompiledModule {
+    let m = id(0, "M");
+    let mut builder = CompiledModuleBuilder::new(*m.address(), m.name());
+    let n = builder.add_module_handle(AccountAddress::new([1; 32]), "N");
+    let o = builder.add_module_handle(AccountAddress::new([2; 32]), "O");
+    let t = builder.add_struct_handle(n, "T", false, vec![]);
+    builder.add_struct(
+        "S",
+        false,
+        vec![],
+        vec![("t", SignatureToken::Struct(t, vec![]))],
+    );
+    let n_g = builder.add_function_handle(n, "g", unit_signature());
+    let o_g = builder.add_function_handle(o, "g", g_signature);
+
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Call(n_g, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Call(o_g, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Ret);
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        unit_signature(),
+        vec![],
+        vec![],
+        code,
+    );
+    builder.build().expect("module is bounds-valid")
+}
+
+#[test]
+fn rebinding_self_moves_module() {
+    let mut patcher = ModulePatcher::new(module(unit_signature()));
+    patcher.rebind_module(&id(0, "M"), &id(9, "M2")).unwrap();
+    let patched = patcher.finish().unwrap();
+    assert_eq!(patched.self_id(), id(9, "M2"));
+    assert_eq!(patched.module_handles().len(), 3);
+}
+
+#[test]
+fn rebinding_merges_handles() {
+    let mut patcher = ModulePatcher::new(module(unit_signature()));
+    patcher.rebind_module(&id(2, "O"), &id(1, "N")).unwrap();
+    let patched = patcher.finish().unwrap();
+
+    assert_eq!(patched.module_handles().len(), 2);
+    assert_eq!(patched.function_handles().len(), 2);
+    let code = &patched.function_defs()[0].code.code;
+    assert_eq!(code[0], code[1]);
+    match code[0] {
+        Bytecode::Call(idx, _) => {
+            let handle = patched.function_handle_at(idx);
+            let module = patched.module_handle_at(handle.module);
+            assert_eq!(patched.module_id_for_handle(module), id(1, "N"));
+            assert_eq!(patched.string_at(handle.name), "g");
+        }
+        ref bytecode => panic!("unexpected instruction: {:?}", bytecode),
+    }
+}
+
+#[test]
+fn renames_apply_in_order() {
+    let mut patcher = ModulePatcher::new(module(unit_signature()));
+    patcher.rename_struct(&id(1, "N"), "T", "U").unwrap();
+    patcher.rename_struct(&id(1, "N"), "U", "V").unwrap();
+    patcher.rename_function(&id(0, "M"), "f", "h").unwrap();
+    assert_eq!(
+        patcher.rename_function(&id(0, "M"), "f", "k"),
+        Err(PatchError::UnknownFunction(id(0, "M"), "f".to_string()))
+    );
+    assert_eq!(
+        patcher.rebind_module(&id(3, "P"), &id(4, "P")),
+        Err(PatchError::UnknownModule(id(3, "P")))
+    );
+    let patched = patcher.finish().unwrap();
+
+    let struct_names: Vec<_> = patched
+        .struct_handles()
+        .iter()
+        .map(|handle| patched.string_at(handle.name))
+        .collect();
+    assert_eq!(struct_names, vec!["V", "S"]);
+    let function_handle = patched.function_handle_at(patched.function_defs()[0].function);
+    assert_eq!(patched.string_at(function_handle.name), "h");
+}
+
+#[test]
+fn conflicts_are_rejected() {
+    let mut patcher = ModulePatcher::new(module(unit_signature()));
+    patcher.rebind_module(&id(1, "N"), &id(0, "M")).unwrap();
+    assert_eq!(
+        patcher.finish().err(),
+        Some(PatchError::SelfDependency(id(0, "M")))
+    );
+
+    let other_signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![SignatureToken::U64].into(),
+        type_formals: vec![],
+    };
+    let mut patcher = ModulePatcher::new(module(other_signature));
+    patcher.rebind_module(&id(2, "O"), &id(1, "N")).unwrap();
+    assert_eq!(
+        patcher.finish().err(),
+        Some(PatchError::ConflictingFunctions(
+            id(1, "N"),
+            "g".to_string()
+        ))
+    );
+}
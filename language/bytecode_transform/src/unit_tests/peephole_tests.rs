@@ -0,0 +1,152 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::peephole::{optimize_code, optimize_module, OptimizationError, PeepholeStats};
+use types::account_address::AccountAddress;
+use vm::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature, SignatureToken},
+};
+
+fn optimize(code: Vec<Bytecode>) -> (Vec<Bytecode>, PeepholeStats) {
+    let mut stats = PeepholeStats::default();
+    let code = optimize_code(&code, &mut stats);
+    (code, stats)
+}
+
+#[test]
+fn dead_pops_are_removed() {
+    let (code, stats) = optimize(vec![
+        Bytecode::LdConst(1),
+        Bytecode::Pop,
+        Bytecode::CopyLoc(0),
+        Bytecode::Pop,
+        Bytecode::MoveLoc(0),
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    assert_eq!(
+        code,
+        vec![Bytecode::MoveLoc(0), Bytecode::Pop, Bytecode::Ret]
+    );
+    assert_eq!(stats.dead_pops, 2);
+}
+
+#[test]
+fn pops_branched_to_are_kept() {
+    let code = vec![
+        Bytecode::LdTrue,
+        Bytecode::LdConst(1),
+        Bytecode::Pop,
+        Bytecode::BrTrue(2),
+        Bytecode::Ret,
+    ];
+    let (optimized, stats) = optimize(code.clone());
+    assert_eq!(optimized, code);
+    assert_eq!(stats.total(), 0);
+}
+
+#[test]
+fn branches_are_threaded() {
+    let (code, stats) = optimize(vec![
+        Bytecode::LdTrue,
+        Bytecode::BrTrue(3),
+        Bytecode::Ret,
+        Bytecode::Branch(4),
+        Bytecode::Branch(2),
+        // A cycle of unconditional branches is left alone.
+        Bytecode::Branch(6),
+        Bytecode::Branch(5),
+    ]);
+    assert_eq!(code[1], Bytecode::BrTrue(2));
+    assert_eq!(code[3], Bytecode::Branch(2));
+    assert_eq!(code[5], Bytecode::Branch(6));
+    assert_eq!(code[6], Bytecode::Branch(5));
+    assert_eq!(stats.threaded_branches, 2);
+}
+
+#[test]
+fn not_is_fused_into_branches() {
+    let (code, stats) = optimize(vec![
+        Bytecode::LdTrue,
+        Bytecode::Not,
+        Bytecode::BrFalse(4),
+        Bytecode::Ret,
+        Bytecode::LdFalse,
+        Bytecode::Not,
+        Bytecode::BrTrue(3),
+        Bytecode::Ret,
+    ]);
+    assert_eq!(
+        code,
+        vec![
+            Bytecode::LdTrue,
+            Bytecode::BrTrue(3),
+            Bytecode::Ret,
+            Bytecode::LdFalse,
+            Bytecode::BrFalse(2),
+            Bytecode::Ret,
+        ]
+    );
+    assert_eq!(stats.fused_branches, 2);
+}
+
+fn module(code: CodeBuilder) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let signature = FunctionSignature {
+        arg_types: vec![SignatureToken::Bool].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    builder.build().expect("module is bounds-valid")
+}
+
+#[test]
+fn optimized_modules_verify() {
+    let mut code = CodeBuilder::new();
+    let exit = code.new_label();
+    let trampoline = code.new_label();
+    code.emit(Bytecode::LdConst(7));
+    code.emit(Bytecode::Pop);
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::Not);
+    code.emit_branch(Bytecode::BrFalse, trampoline);
+    code.emit_branch(Bytecode::Branch, exit);
+    code.bind(trampoline);
+    code.emit_branch(Bytecode::Branch, exit);
+    code.bind(exit);
+    code.emit(Bytecode::Ret);
+
+    let (module, stats) = optimize_module(module(code)).expect("module optimizes");
+    assert_eq!(
+        stats,
+        PeepholeStats {
+            dead_pops: 1,
+            threaded_branches: 1,
+            fused_branches: 1,
+        }
+    );
+    assert_eq!(
+        module.function_defs()[0].code.code,
+        vec![
+            Bytecode::CopyLoc(0),
+            Bytecode::BrTrue(4),
+            Bytecode::Branch(4),
+            Bytecode::Branch(4),
+            Bytecode::Ret,
+        ]
+    );
+}
+
+#[test]
+fn unverified_modules_are_rejected() {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Pop);
+    code.emit(Bytecode::Ret);
+    match optimize_module(module(code)) {
+        Err(OptimizationError::Unverified(errors)) => assert!(!errors.is_empty()),
+        result => panic!("unexpected result: {:?}", result.map(|(_, stats)| stats)),
+    }
+}
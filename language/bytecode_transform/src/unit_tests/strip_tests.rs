@@ -0,0 +1,112 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::strip::{strip_module, IdentifierMapping, StripOptions};
+use bytecode_verifier::VerifiedModule;
+use types::{account_address::AccountAddress, byte_array::ByteArray};
+use vm::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, FunctionSignature, ModuleHandleIndex, SignatureToken, NO_TYPE_ACTUALS,
+    },
+};
+
+/// Builds a module with a struct `S { amount: u64, owner: address }`, a public function `b`
+/// calling the private function `helper`, a private function `other`, and unused pool entries.
+fn verified_module() -> VerifiedModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.intern_string("unused");
+    builder.intern_byte_array(ByteArray::new(vec![1, 2, 3]));
+    builder.intern_address(AccountAddress::new([7; 32]));
+    builder.add_struct(
+        "S",
+        false,
+        vec![],
+        vec![
+            ("amount", SignatureToken::U64),
+            ("owner", SignatureToken::Address),
+        ],
+    );
+
+    let signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    let helper =
+        builder.add_function_handle(ModuleHandleIndex::new(0), "helper", signature.clone());
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Call(helper, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Ret);
+    builder.add_function(
+        "b",
+        CodeUnit::PUBLIC,
+        signature.clone(),
+        vec![],
+        vec![],
+        code,
+    );
+    for name in &["helper", "other"] {
+        let mut code = CodeBuilder::new();
+        code.emit(Bytecode::Ret);
+        builder.add_function(name, 0, signature.clone(), vec![], vec![], code);
+    }
+    VerifiedModule::new(builder.build().expect("module is bounds-valid")).expect("module verifies")
+}
+
+fn function_names(module: &VerifiedModule) -> Vec<&str> {
+    module
+        .function_defs()
+        .iter()
+        .map(|function_def| {
+            let handle = module.function_handle_at(function_def.function);
+            module.string_at(handle.name)
+        })
+        .collect()
+}
+
+fn field_names(module: &VerifiedModule) -> Vec<&str> {
+    module
+        .field_defs()
+        .iter()
+        .map(|field_def| module.string_at(field_def.name))
+        .collect()
+}
+
+#[test]
+fn unused_pool_entries_are_removed() {
+    let (module, report) = strip_module(verified_module(), StripOptions::default()).unwrap();
+    assert_eq!(
+        (report.strings, report.byte_arrays, report.addresses),
+        (1, 1, 1)
+    );
+    assert!(report.mapping.is_empty());
+    assert!(!module.string_pool().iter().any(|s| s == "unused"));
+    assert!(module.byte_array_pool().is_empty());
+    assert_eq!(module.address_pool(), &[AccountAddress::default()]);
+    assert_eq!(function_names(&module), vec!["b", "helper", "other"]);
+    assert_eq!(field_names(&module), vec!["amount", "owner"]);
+}
+
+#[test]
+fn mangling_is_reversible() {
+    let options = StripOptions {
+        mangle_identifiers: true,
+    };
+    let (module, report) = strip_module(verified_module(), options).unwrap();
+    // The original names of the mangled identifiers are removed along with the unused string.
+    assert_eq!(report.strings, 5);
+    // Mangled names skip the name of the public function.
+    assert_eq!(function_names(&module), vec!["b", "a", "c"]);
+    assert_eq!(field_names(&module), vec!["a", "b"]);
+
+    let mapping = IdentifierMapping::from_json(&report.mapping.to_json().unwrap()).unwrap();
+    assert_eq!(mapping, report.mapping);
+    assert_eq!(mapping.function_name("a"), "helper");
+    assert_eq!(mapping.function_name("c"), "other");
+    assert_eq!(mapping.function_name("b"), "b");
+    assert_eq!(mapping.field_name("S", "a"), "amount");
+    assert_eq!(mapping.field_name("S", "b"), "owner");
+    assert_eq!(mapping.field_name("T", "b"), "b");
+}
@@ -0,0 +1,164 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exposes deserialization and verification through a C ABI, so that tools written in other
+//! languages can link against the verifier instead of re-implementing it.
+//!
+//! The functions take a binary and write the errors found to a buffer they allocate, as a JSON
+//! array of `{ "code": 6033, "severity": "warning", "message": "..." }` objects. The buffer must
+//! be released with `verifier_free_errors`. They return one of the `VERIFIER_*` status codes:
+//!
+//! ```c
+//! int32_t verifier_verify_module(const uint8_t *binary, size_t binary_len,
+//!                                uint8_t **errors, size_t *errors_len);
+//! int32_t verifier_verify_script(const uint8_t *binary, size_t binary_len,
+//!                                uint8_t **errors, size_t *errors_len);
+//! void verifier_free_errors(uint8_t *errors, size_t errors_len);
+//! ```
+//!
+//! Modules and scripts are verified on their own, without their dependencies, with every pass,
+//! advisory ones included (see `VerifierConfig::advisory`).
+use bytecode_verifier::{verify_module_with_config, ScriptSignatureChecker, VerifierConfig};
+use serde_json::{json, Value};
+use std::{panic, ptr, slice};
+use vm::{
+    access::ModuleAccess,
+    errors::{has_errors, BinaryError, VerificationError},
+    file_format::{CompiledModule, CompiledScript},
+    views::ModuleView,
+};
+
+/// The binary deserialized and verified. The errors may still contain warnings.
+pub const VERIFIER_OK: i32 = 0;
+/// The binary deserialized, but failed verification.
+pub const VERIFIER_FAILED: i32 = 1;
+/// The binary couldn't be deserialized. The errors contain the reason.
+pub const VERIFIER_MALFORMED: i32 = 2;
+/// A pointer argument was null. Nothing was written.
+pub const VERIFIER_INVALID_ARGUMENT: i32 = 3;
+/// The verifier panicked, which is a bug. Nothing was written.
+pub const VERIFIER_PANIC: i32 = 4;
+
+/// Deserializes and verifies the module in the `binary_len` bytes at `binary`, then points
+/// `*errors` to a buffer of `*errors_len` bytes with the errors found.
+///
+/// # Safety
+///
+/// `binary` must point to `binary_len` readable bytes, and `errors` and `errors_len` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn verifier_verify_module(
+    binary: *const u8,
+    binary_len: usize,
+    errors: *mut *mut u8,
+    errors_len: *mut usize,
+) -> i32 {
+    run(binary, binary_len, errors, errors_len, verify_module)
+}
+
+/// Deserializes and verifies the script in the `binary_len` bytes at `binary`, then points
+/// `*errors` to a buffer of `*errors_len` bytes with the errors found.
+///
+/// # Safety
+///
+/// `binary` must point to `binary_len` readable bytes, and `errors` and `errors_len` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn verifier_verify_script(
+    binary: *const u8,
+    binary_len: usize,
+    errors: *mut *mut u8,
+    errors_len: *mut usize,
+) -> i32 {
+    run(binary, binary_len, errors, errors_len, verify_script)
+}
+
+/// Releases a buffer of errors written by one of the verification functions. Does nothing if
+/// `errors` is null.
+///
+/// # Safety
+///
+/// `errors` and `errors_len` must have been written by a verification function, and the buffer
+/// must not have been released already.
+#[no_mangle]
+pub unsafe extern "C" fn verifier_free_errors(errors: *mut u8, errors_len: usize) {
+    if !errors.is_null() {
+        drop(Box::from_raw(slice::from_raw_parts_mut(errors, errors_len)));
+    }
+}
+
+unsafe fn run(
+    binary: *const u8,
+    binary_len: usize,
+    errors: *mut *mut u8,
+    errors_len: *mut usize,
+    verify: fn(&[u8]) -> (i32, Vec<Value>),
+) -> i32 {
+    if binary.is_null() || errors.is_null() || errors_len.is_null() {
+        return VERIFIER_INVALID_ARGUMENT;
+    }
+    let binary = slice::from_raw_parts(binary, binary_len);
+    // Unwinding into foreign code is undefined behavior.
+    let (status, found) = match panic::catch_unwind(|| verify(binary)) {
+        Ok(outcome) => outcome,
+        Err(_) => return VERIFIER_PANIC,
+    };
+    let buffer = Value::Array(found)
+        .to_string()
+        .into_bytes()
+        .into_boxed_slice();
+    ptr::write(errors_len, buffer.len());
+    ptr::write(errors, Box::into_raw(buffer) as *mut u8);
+    status
+}
+
+fn verify_module(binary: &[u8]) -> (i32, Vec<Value>) {
+    let module = match CompiledModule::deserialize(binary) {
+        Ok(module) => module,
+        Err(err) => return malformed(err),
+    };
+    let errors = verify_module_with_config(&module, &VerifierConfig::advisory());
+    outcome(&module, &errors)
+}
+
+fn verify_script(binary: &[u8]) -> (i32, Vec<Value>) {
+    let script = match CompiledScript::deserialize(binary) {
+        Ok(script) => script,
+        Err(err) => return malformed(err),
+    };
+    // Like `VerifiedScript::new`, but with the advisory passes.
+    let module = script.into_module();
+    let mut errors = verify_module_with_config(&module, &VerifierConfig::advisory());
+    let script = module.into_script();
+    errors.extend(ScriptSignatureChecker::new(&script).verify());
+    outcome(&script.into_module(), &errors)
+}
+
+fn malformed(err: BinaryError) -> (i32, Vec<Value>) {
+    let error = json!({
+        "code": err.code(),
+        "severity": "error",
+        "message": err.to_string(),
+    });
+    (VERIFIER_MALFORMED, vec![error])
+}
+
+fn outcome(module: &impl ModuleAccess, errors: &[VerificationError]) -> (i32, Vec<Value>) {
+    let view = ModuleView::new(module);
+    let described = errors
+        .iter()
+        .map(|err| {
+            json!({
+                "code": err.code(),
+                "severity": err.severity().to_string(),
+                "message": err.display_with(&view).to_string(),
+            })
+        })
+        .collect();
+    let status = if has_errors(errors) {
+        VERIFIER_FAILED
+    } else {
+        VERIFIER_OK
+    };
+    (status, described)
+}
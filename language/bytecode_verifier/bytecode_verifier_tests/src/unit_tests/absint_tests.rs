@@ -0,0 +1,166 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    control_flow_graph::VMControlFlowGraph, AbstractDomain, AbstractInterpreter, BlockPrecondition,
+    IterationBudgetExceeded, JoinResult, TransferFunctions,
+};
+use vm::file_format::Bytecode;
+
+/// An upper bound on the value of each local and stack slot of type u64, or `None` if there is
+/// none. Ascending chains are as long as the range of u64, so widening is needed for the analysis
+/// of loops to converge in a reasonable number of iterations.
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Bounds {
+    locals: Vec<Option<u64>>,
+    stack: Vec<Option<u64>>,
+    /// Whether `widen` widens, or joins like `join`.
+    widening: bool,
+}
+
+fn join_bound(bound: &mut Option<u64>, other: Option<u64>, widening: bool) -> bool {
+    let joined = match (*bound, other) {
+        (Some(bound), Some(other)) if other <= bound => Some(bound),
+        (Some(_), Some(_)) if widening => None,
+        (Some(_), Some(other)) => Some(other),
+        (_, _) => None,
+    };
+    let changed = joined != *bound;
+    *bound = joined;
+    changed
+}
+
+impl Bounds {
+    fn join_with(&mut self, other: &Self, widening: bool) -> JoinResult {
+        if self.stack.len() != other.stack.len() {
+            return JoinResult::Error;
+        }
+        let mut changed = false;
+        for (bound, other) in self
+            .locals
+            .iter_mut()
+            .chain(self.stack.iter_mut())
+            .zip(other.locals.iter().chain(other.stack.iter()))
+        {
+            changed |= join_bound(bound, *other, widening);
+        }
+        if changed {
+            JoinResult::Changed
+        } else {
+            JoinResult::Unchanged
+        }
+    }
+}
+
+impl AbstractDomain for Bounds {
+    fn join(&mut self, other: &Self) -> JoinResult {
+        self.join_with(other, false)
+    }
+
+    fn widen(&mut self, other: &Self) -> JoinResult {
+        let widening = self.widening;
+        self.join_with(other, widening)
+    }
+}
+
+struct BoundsAnalysis;
+
+impl TransferFunctions for BoundsAnalysis {
+    type State = Bounds;
+    type AnalysisError = ();
+
+    fn execute(
+        &mut self,
+        state: &mut Bounds,
+        instr: &Bytecode,
+        _index: usize,
+        _last_index: usize,
+    ) -> Result<(), ()> {
+        match instr {
+            Bytecode::LdConst(value) => state.stack.push(Some(*value)),
+            Bytecode::CopyLoc(idx) | Bytecode::MoveLoc(idx) => {
+                state.stack.push(state.locals[*idx as usize])
+            }
+            Bytecode::StLoc(idx) => state.locals[*idx as usize] = state.stack.pop().ok_or(())?,
+            Bytecode::Add => {
+                let rhs = state.stack.pop().ok_or(())?;
+                let lhs = state.stack.pop().ok_or(())?;
+                let sum = match (lhs, rhs) {
+                    (Some(lhs), Some(rhs)) => lhs.checked_add(rhs),
+                    _ => None,
+                };
+                state.stack.push(sum);
+            }
+            Bytecode::Lt => {
+                state.stack.pop().ok_or(())?;
+                state.stack.pop().ok_or(())?;
+                state.stack.push(Some(1));
+            }
+            Bytecode::BrTrue(_) | Bytecode::BrFalse(_) => {
+                state.stack.pop().ok_or(())?;
+            }
+            Bytecode::Branch(_) | Bytecode::Ret => (),
+            _ => return Err(()),
+        }
+        Ok(())
+    }
+}
+
+impl AbstractInterpreter for BoundsAnalysis {}
+
+/// x = 0; while (x < 10) { x = x + 1 }
+fn counting_loop() -> Vec<Bytecode> {
+    vec![
+        Bytecode::LdConst(0),
+        Bytecode::StLoc(0),
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(10),
+        Bytecode::Lt,
+        Bytecode::BrFalse(11),
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(1),
+        Bytecode::Add,
+        Bytecode::StLoc(0),
+        Bytecode::Branch(2),
+        Bytecode::Ret,
+    ]
+}
+
+fn initial_state(widening: bool) -> Bounds {
+    Bounds {
+        locals: vec![Some(0)],
+        stack: vec![],
+        widening,
+    }
+}
+
+#[test]
+fn widening_makes_loops_converge() {
+    let code = counting_loop();
+    let cfg = VMControlFlowGraph::new(&code);
+    let inv_map = BoundsAnalysis
+        .analyze_code(initial_state(true), &code, &cfg, Some(100))
+        .expect("the analysis converges");
+
+    // The entry block is analyzed once, from the initial state.
+    match inv_map[&0].pre() {
+        BlockPrecondition::State(state) => assert_eq!(state.locals, vec![Some(0)]),
+        BlockPrecondition::JoinFailure => panic!("entry block has a precondition"),
+    }
+    // The loop header is reached with x = 0, then with x = 1 along the back edge, which widens.
+    match inv_map[&2].pre() {
+        BlockPrecondition::State(state) => assert_eq!(state.locals, vec![None]),
+        BlockPrecondition::JoinFailure => panic!("loop header has a precondition"),
+    }
+    assert!(inv_map.contains_key(&11));
+}
+
+#[test]
+fn joining_alone_exhausts_the_budget() {
+    let code = counting_loop();
+    let cfg = VMControlFlowGraph::new(&code);
+    match BoundsAnalysis.analyze_code(initial_state(false), &code, &cfg, Some(100)) {
+        Err(IterationBudgetExceeded) => (),
+        Ok(_) => panic!("the analysis should not converge without widening"),
+    }
+}
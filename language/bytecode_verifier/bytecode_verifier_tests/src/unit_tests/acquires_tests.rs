@@ -0,0 +1,110 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::acquires_list_verifier::AcquiresVerifier;
+use invalid_mutations::acquires::{AcquiresMutation, ApplyAcquiresContext};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    access::ModuleAccess,
+    errors::{sort_errors, VMStaticViolation, VerificationError},
+    file_format::{
+        Bytecode, CompiledModule, CompiledModuleMut, StructDefinitionIndex, TableIndex,
+        NO_TYPE_ACTUALS,
+    },
+    IndexKind,
+};
+
+/// Replaces the body of every function with code that accesses some of the resources of the
+/// module, and annotates the function with exactly those resources.
+fn annotate_functions(module: &mut CompiledModuleMut) {
+    let resources: Vec<_> = module
+        .struct_defs
+        .iter()
+        .enumerate()
+        .filter(|(_, struct_def)| {
+            module.struct_handles[struct_def.struct_handle.0 as usize].is_nominal_resource
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    for (idx, function_def) in module.function_defs.iter_mut().enumerate() {
+        if function_def.is_native() {
+            continue;
+        }
+        // Leave out every other resource so that there's something to add spurious annotations
+        // for.
+        let acquired: Vec<_> = resources
+            .iter()
+            .filter(|resource| (*resource + idx) % 2 == 0)
+            .map(|resource| StructDefinitionIndex::new(*resource as TableIndex))
+            .collect();
+        let mut code = vec![];
+        for resource in &acquired {
+            code.push(Bytecode::BorrowGlobal(*resource, NO_TYPE_ACTUALS));
+            code.push(Bytecode::MoveFrom(*resource, NO_TYPE_ACTUALS));
+        }
+        code.push(Bytecode::Ret);
+        function_def.code.code = code;
+        function_def.acquires_global_resources = acquired;
+    }
+}
+
+fn verify_acquires(module: &CompiledModule) -> Vec<VerificationError> {
+    let mut errors = vec![];
+    for (idx, function_def) in module.function_defs().iter().enumerate() {
+        if function_def.is_native() {
+            continue;
+        }
+        errors.extend(
+            AcquiresVerifier::verify(module, function_def)
+                .into_iter()
+                .map(|err| VerificationError::new(IndexKind::FunctionDefinition, idx, err)),
+        );
+    }
+    errors
+}
+
+proptest! {
+    #[test]
+    fn valid_acquires(module in CompiledModule::valid_strategy(20)) {
+        let mut module = module.into_inner();
+        annotate_functions(&mut module);
+        let module = module.freeze().expect("should satisfy bounds checker");
+        prop_assert_eq!(verify_acquires(&module), vec![]);
+    }
+
+    #[test]
+    fn invalid_acquires(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(AcquiresMutation::strategy(), 0..40),
+    ) {
+        let mut module = module.into_inner();
+        annotate_functions(&mut module);
+        let expected_violations = {
+            let context = ApplyAcquiresContext::new(&mut module, mutations);
+            context.apply()
+        };
+        // Annotations of undeclared structs keep the module from being frozen, so those are
+        // checked separately.
+        let (mut expected_bounds_violations, mut expected_violations): (Vec<_>, Vec<_>) =
+            expected_violations.into_iter().partition(|err| match &err.err {
+                VMStaticViolation::IndexOutOfBounds(..) => true,
+                _ => false,
+            });
+        sort_errors(&mut expected_bounds_violations);
+        sort_errors(&mut expected_violations);
+
+        match module.freeze() {
+            Ok(module) => {
+                prop_assert_eq!(expected_bounds_violations, vec![]);
+                let mut actual_violations = verify_acquires(&module);
+                sort_errors(&mut actual_violations);
+                prop_assert_eq!(expected_violations, actual_violations);
+            }
+            Err(err) => {
+                let mut actual_violations = err.into_errors();
+                sort_errors(&mut actual_violations);
+                prop_assert_eq!(expected_bounds_violations, actual_violations);
+            }
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{BorrowGraphDumper, BorrowGraphFormat};
+use serde_json::{json, Value};
+use vm::file_format::{
+    dummy_procedure_module, Bytecode, CompiledModule, FunctionDefinitionIndex, LocalsSignature,
+    LocalsSignatureIndex, SignatureToken,
+};
+
+/// Returns a module whose only function borrows a local in its first block, and moves the local
+/// out while it is still borrowed in its second block.
+fn moves_borrowed_local() -> CompiledModule {
+    let mut module = dummy_procedure_module(vec![
+        Bytecode::LdConst(0),
+        Bytecode::StLoc(0),
+        Bytecode::MutBorrowLoc(0),
+        Bytecode::StLoc(1),
+        Bytecode::Branch(5),
+        Bytecode::MoveLoc(0),
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ])
+    .into_inner();
+    module.locals_signatures.push(LocalsSignature(
+        vec![
+            SignatureToken::U64,
+            SignatureToken::MutableReference(Box::new(SignatureToken::U64)),
+        ]
+        .into(),
+    ));
+    module.function_defs[0].code.locals = LocalsSignatureIndex::new(1);
+    module.freeze().expect("should satisfy bounds checker")
+}
+
+#[test]
+fn json_dump() {
+    let module = moves_borrowed_local();
+    let dumper = BorrowGraphDumper::new(&module, BorrowGraphFormat::Json);
+    let dump = dumper
+        .dump_function(FunctionDefinitionIndex::new(0))
+        .expect("function should be rejected");
+    let dump: Value = serde_json::from_str(&dump).expect("dump should be valid JSON");
+
+    assert_eq!(dump["function"], 0);
+    assert_eq!(dump["errors"].as_array().map(Vec::len), Some(1));
+    assert_eq!(
+        dump["blocks"],
+        json!([
+            {
+                "block": 0,
+                "start": 0,
+                "end": 4,
+                "status": "ok",
+                "pre": { "locals": [], "globals": [], "borrows": [] },
+            },
+            {
+                "block": 5,
+                "start": 5,
+                "end": 7,
+                "status": "error",
+                "pre": {
+                    "locals": [
+                        { "local": 0, "kind": "unrestricted", "borrowed_by": [1] },
+                        { "local": 1, "reference": 1 },
+                    ],
+                    "globals": [],
+                    "borrows": [],
+                },
+            },
+        ])
+    );
+}
+
+#[test]
+fn dot_dump() {
+    let module = moves_borrowed_local();
+    let dumper = BorrowGraphDumper::new(&module, BorrowGraphFormat::Dot);
+    let dumps = dumper.dump_failures();
+    assert_eq!(dumps.len(), 1);
+    let (idx, dump) = &dumps[0];
+    assert_eq!(*idx, FunctionDefinitionIndex::new(0));
+
+    assert!(dump.starts_with("digraph \"function 0\" {"));
+    assert!(dump.contains("subgraph cluster_5 {"));
+    assert!(dump.contains("label=\"block 5 (offsets 5..=7): error\";"));
+    assert!(dump.contains("b5_local0 -> b5_n1;"));
+    assert!(dump.contains("b5_local1 -> b5_n1 [style=dashed];"));
+    assert!(dump.ends_with('}'));
+}
+
+#[test]
+fn accepted_functions_are_not_dumped() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let dumper = BorrowGraphDumper::new(&module, BorrowGraphFormat::Json);
+    assert_eq!(dumper.dump_function(FunctionDefinitionIndex::new(0)), None);
+    assert!(dumper.dump_failures().is_empty());
+}
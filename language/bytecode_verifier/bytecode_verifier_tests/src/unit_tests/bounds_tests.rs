@@ -2,15 +2,20 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use invalid_mutations::bounds::{
-    ApplyCodeUnitBoundsContext, ApplyOutOfBoundsContext, CodeUnitBoundsMutation,
-    OutOfBoundsMutation,
+    ApplyByteArrayBoundsContext, ApplyCodeUnitBoundsContext, ApplyOutOfBoundsContext,
+    ByteArrayBoundsMutation, CodeUnitBoundsDescription, CodeUnitBoundsMutation,
+    OutOfBoundsDescription, OutOfBoundsMutation,
 };
 use proptest::{collection::vec, prelude::*};
 use types::{account_address::AccountAddress, byte_array::ByteArray};
 use vm::{
     check_bounds::BoundsChecker,
-    errors::{VMStaticViolation, VerificationError},
-    file_format::{CompiledModule, CompiledModuleMut},
+    errors::{sort_errors, VMStaticViolation, VerificationError},
+    file_format::{
+        dummy_procedure_module, Bytecode, CodeOffset, CompiledModule, CompiledModuleMut,
+        FunctionDefinitionIndex,
+    },
+    identifier::Identifier,
     proptest_types::CompiledModuleStrategyGen,
     IndexKind,
 };
@@ -45,14 +50,32 @@ proptest! {
             let oob_context = ApplyOutOfBoundsContext::new(module, oob_mutations);
             oob_context.apply()
         };
-        expected_violations.sort();
+        sort_errors(&mut expected_violations);
 
         let bounds_checker = BoundsChecker::new(&module);
         let mut actual_violations = bounds_checker.verify();
-        actual_violations.sort();
+        sort_errors(&mut actual_violations);
         prop_assert_eq!(expected_violations, actual_violations);
     }
 
+    #[test]
+    fn capped_out_of_bounds(
+        module in CompiledModule::valid_strategy(20),
+        oob_mutations in vec(OutOfBoundsMutation::strategy(), 0..40),
+        max_errors in 0..10usize,
+    ) {
+        let (module, expected_violations) = {
+            let oob_context = ApplyOutOfBoundsContext::new(module, oob_mutations);
+            oob_context.apply()
+        };
+
+        let capped = BoundsChecker::new(&module).with_max_errors(max_errors).verify_capped();
+        let uncapped = BoundsChecker::new(&module).verify();
+        prop_assert_eq!(uncapped.len(), expected_violations.len());
+        prop_assert_eq!(capped.total(), uncapped.len());
+        prop_assert_eq!(capped.errors(), &uncapped[..uncapped.len().min(max_errors)]);
+    }
+
     #[test]
     fn code_unit_out_of_bounds(
         module in CompiledModule::valid_strategy(20),
@@ -63,17 +86,75 @@ proptest! {
             let context = ApplyCodeUnitBoundsContext::new(&mut module, mutations);
             context.apply()
         };
-        expected_violations.sort();
+        sort_errors(&mut expected_violations);
+
+        let bounds_checker = BoundsChecker::new(&module);
+        let mut actual_violations = bounds_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn byte_array_out_of_bounds(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(ByteArrayBoundsMutation::strategy(), 0..40),
+    ) {
+        let mut module = module.into_inner();
+        let mut expected_violations = {
+            let context = ApplyByteArrayBoundsContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
 
         let bounds_checker = BoundsChecker::new(&module);
         let mut actual_violations = bounds_checker.verify();
-        actual_violations.sort();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn replay_out_of_bounds(
+        module in CompiledModule::valid_strategy(20),
+        oob_mutations in vec(OutOfBoundsMutation::strategy(), 0..40),
+    ) {
+        let (expected_module, expected_violations, descriptions) = {
+            let oob_context = ApplyOutOfBoundsContext::new(module.clone(), oob_mutations);
+            oob_context.apply_and_describe()
+        };
+
+        let serialized = serde_json::to_string(&descriptions).expect("should serialize");
+        let descriptions: Vec<OutOfBoundsDescription> =
+            serde_json::from_str(&serialized).expect("should deserialize");
+        let (actual_module, actual_violations) =
+            ApplyOutOfBoundsContext::apply_from_description(module, &descriptions);
+        prop_assert_eq!(expected_module, actual_module);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn replay_code_unit_out_of_bounds(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(CodeUnitBoundsMutation::strategy(), 0..40),
+    ) {
+        let mut expected_module = module.clone().into_inner();
+        let (expected_violations, descriptions) = {
+            let context = ApplyCodeUnitBoundsContext::new(&mut expected_module, mutations);
+            context.apply_and_describe()
+        };
+
+        let serialized = serde_json::to_string(&descriptions).expect("should serialize");
+        let descriptions: Vec<CodeUnitBoundsDescription> =
+            serde_json::from_str(&serialized).expect("should deserialize");
+        let mut actual_module = module.into_inner();
+        let actual_violations =
+            ApplyCodeUnitBoundsContext::apply_from_description(&mut actual_module, &descriptions);
+        prop_assert_eq!(expected_module, actual_module);
         prop_assert_eq!(expected_violations, actual_violations);
     }
 
     #[test]
     fn no_module_handles(
-        string_pool in vec(".*", 0..20),
+        string_pool in vec(".*".prop_map(Identifier::from), 0..20),
         address_pool in vec(any::<AccountAddress>(), 0..20),
         byte_array_pool in vec(any::<ByteArray>(), 0..20),
     ) {
@@ -89,16 +170,31 @@ proptest! {
         prop_assert_eq!(
             actual_violations,
             vec![
-                VerificationError {
-                    kind: IndexKind::ModuleHandle,
-                    idx: 0,
-                    err: VMStaticViolation::NoModuleHandles,
-                },
+                VerificationError::new(IndexKind::ModuleHandle, 0, VMStaticViolation::NoModuleHandles),
             ]
         );
     }
 }
 
+#[test]
+fn code_unit_too_long() {
+    let mut module = dummy_procedure_module(vec![Bytecode::Ret]).into_inner();
+    let code_len = CodeOffset::max_value() as usize + 1;
+    module.function_defs[0].code.code = vec![Bytecode::Ret; code_len];
+
+    assert_eq!(
+        BoundsChecker::new(&module).verify(),
+        vec![VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::CodeUnitTooLong(code_len),
+        )]
+    );
+
+    // The longest code unit a code offset can address is fine.
+    module.function_defs[0].code.code.pop();
+    assert_eq!(BoundsChecker::new(&module).verify(), vec![]);
+}
+
 proptest! {
     // Generating arbitrary compiled modules is really slow, possibly because of
     // https://github.com/AltSysrq/proptest/issues/143.
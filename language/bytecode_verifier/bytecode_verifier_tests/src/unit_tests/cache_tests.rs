@@ -0,0 +1,79 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    LruVerificationCache, VerificationCache, VerificationCacheKey, VerifiedModule, VerifierConfig,
+};
+use proptest::prelude::*;
+use vm::file_format::{dummy_procedure_module, Bytecode, CompiledModule};
+
+proptest! {
+    #[test]
+    fn cached_outcome_matches(module in CompiledModule::valid_strategy(20)) {
+        let cache = LruVerificationCache::new(4);
+        let expected = VerifiedModule::new(module.clone());
+        prop_assert_eq!(VerifiedModule::new_with_cache(module.clone(), &cache), expected.clone());
+        prop_assert_eq!(cache.len(), 1);
+        prop_assert_eq!(VerifiedModule::new_with_cache(module, &cache), expected);
+        prop_assert_eq!(cache.len(), 1);
+    }
+}
+
+#[test]
+fn cache_hit() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let other_module = dummy_procedure_module(vec![Bytecode::LdTrue, Bytecode::Pop]);
+    let key = VerificationCacheKey::new(&module, &VerifierConfig::all()).unwrap();
+
+    // The cached outcome is used as is, even if it doesn't match the module.
+    let cache = LruVerificationCache::new(4);
+    let (_, errors) = VerifiedModule::new(other_module).unwrap_err();
+    cache.insert(key.clone(), errors.clone());
+    assert_eq!(cache.get(&key), Some(errors.clone()));
+    let (_, cached_errors) = VerifiedModule::new_with_cache(module, &cache).unwrap_err();
+    assert_eq!(cached_errors, errors);
+}
+
+#[test]
+fn key_depends_on_config() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let config = VerifierConfig::all();
+    let key = VerificationCacheKey::new(&module, &config).unwrap();
+    assert_eq!(VerificationCacheKey::new(&module, &config).unwrap(), key);
+
+    let other_config = VerifierConfig {
+        type_safety: false,
+        ..VerifierConfig::all()
+    };
+    assert_ne!(config.fingerprint(), other_config.fingerprint());
+    assert_ne!(
+        VerificationCacheKey::new(&module, &other_config).unwrap(),
+        key
+    );
+}
+
+#[test]
+fn evict_least_recently_used() {
+    let modules: Vec<_> = (0..3)
+        .map(|idx| {
+            dummy_procedure_module(vec![Bytecode::LdConst(idx), Bytecode::Pop, Bytecode::Ret])
+        })
+        .collect();
+    let keys: Vec<_> = modules
+        .iter()
+        .map(|module| VerificationCacheKey::new(module, &VerifierConfig::all()).unwrap())
+        .collect();
+
+    let cache = LruVerificationCache::new(2);
+    assert!(cache.is_empty());
+    VerifiedModule::new_with_cache(modules[0].clone(), &cache).unwrap();
+    VerifiedModule::new_with_cache(modules[1].clone(), &cache).unwrap();
+    // Using the first outcome makes the second one the least recently used.
+    assert_eq!(cache.get(&keys[0]), Some(vec![]));
+    VerifiedModule::new_with_cache(modules[2].clone(), &cache).unwrap();
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get(&keys[0]).is_some());
+    assert!(cache.get(&keys[1]).is_none());
+    assert!(cache.get(&keys[2]).is_some());
+}
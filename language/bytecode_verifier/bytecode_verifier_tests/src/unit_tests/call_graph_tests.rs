@@ -0,0 +1,147 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{CallGraph, FunctionId};
+use std::collections::BTreeSet;
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+use vm::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature, NO_TYPE_ACTUALS},
+};
+
+/// Builds module `name` defining public functions, each given with the functions it calls as
+/// `M::f`.
+fn module(name: &str, functions: &[(&str, &[&str])]) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), name);
+    let signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    for (function, callees) in functions {
+        let mut code = CodeBuilder::new();
+        for callee in *callees {
+            let mut parts = callee.split("::");
+            let callee_module = parts.next().unwrap();
+            let callee_name = parts.next().unwrap();
+            let callee_module = builder.add_module_handle(AccountAddress::default(), callee_module);
+            let f = builder.add_function_handle(callee_module, callee_name, signature.clone());
+            code.emit(Bytecode::Call(f, NO_TYPE_ACTUALS));
+        }
+        code.emit(Bytecode::Ret);
+        builder.add_function(
+            function,
+            CodeUnit::PUBLIC,
+            signature.clone(),
+            vec![],
+            vec![],
+            code,
+        );
+    }
+    builder.build().expect("module is bounds-valid")
+}
+
+fn f(name: &str) -> FunctionId {
+    let mut parts = name.split("::");
+    let module = ModuleId::new(AccountAddress::default(), parts.next().unwrap().to_string());
+    FunctionId::new(module, parts.next().unwrap())
+}
+
+fn set(names: &[&str]) -> BTreeSet<FunctionId> {
+    names.iter().map(|name| f(name)).collect()
+}
+
+fn owned(functions: BTreeSet<&FunctionId>) -> BTreeSet<FunctionId> {
+    functions.into_iter().cloned().collect()
+}
+
+fn modules() -> Vec<CompiledModule> {
+    vec![
+        module("Coin", &[("mint", &[]), ("burn", &[])]),
+        module(
+            "Bank",
+            &[
+                ("deposit", &["Coin::mint"]),
+                ("audit", &["Bank::audit", "Log::write"]),
+            ],
+        ),
+        module("Admin", &[("run", &["Bank::deposit", "Bank::audit"])]),
+    ]
+}
+
+#[test]
+fn calls_are_resolved_through_handles() {
+    let modules = modules();
+    let graph = CallGraph::new(&modules);
+    assert_eq!(
+        graph.functions().cloned().collect::<BTreeSet<_>>(),
+        set(&[
+            "Admin::run",
+            "Bank::audit",
+            "Bank::deposit",
+            "Coin::burn",
+            "Coin::mint"
+        ])
+    );
+    assert_eq!(
+        owned(graph.callees(&f("Admin::run"))),
+        set(&["Bank::audit", "Bank::deposit"])
+    );
+    assert_eq!(
+        owned(graph.callers(&f("Coin::mint"))),
+        set(&["Bank::deposit"])
+    );
+    assert_eq!(
+        owned(graph.unresolved_callees(&f("Bank::audit"))),
+        set(&["Log::write"])
+    );
+    assert!(!graph.contains(&f("Log::write")));
+}
+
+#[test]
+fn reachability() {
+    let modules = modules();
+    let graph = CallGraph::new(&modules);
+    assert_eq!(
+        owned(graph.reaching(&f("Coin::mint"))),
+        set(&["Admin::run", "Bank::deposit"])
+    );
+    assert!(graph.reaching(&f("Coin::burn")).is_empty());
+    assert_eq!(
+        owned(graph.reachable_from(&f("Admin::run"))),
+        set(&["Bank::audit", "Bank::deposit", "Coin::mint"])
+    );
+    // Only recursive functions reach themselves.
+    assert!(graph
+        .reachable_from(&f("Bank::audit"))
+        .contains(&f("Bank::audit")));
+    assert!(!graph
+        .reachable_from(&f("Bank::deposit"))
+        .contains(&f("Bank::deposit")));
+    assert!(graph.reachable_from(&f("Log::write")).is_empty());
+}
+
+#[test]
+fn dot_export() {
+    let modules = vec![
+        module("A", &[("f", &["B::g", "C::h"])]),
+        module("B", &[("g", &[])]),
+    ];
+    let dot = CallGraph::new(&modules).to_dot();
+    assert_eq!(
+        dot,
+        "digraph \"call graph\" {
+    subgraph cluster_0 {
+        label=\"0x0::A\";
+        n0 [label=\"f\"];
+    }
+    subgraph cluster_1 {
+        label=\"0x0::B\";
+        n1 [label=\"g\"];
+    }
+    n2 [label=\"0x0::C::h\", style=dashed];
+    n0 -> n1;
+    n0 -> n2 [style=dashed];
+}"
+    );
+}
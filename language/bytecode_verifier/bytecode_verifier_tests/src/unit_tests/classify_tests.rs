@@ -0,0 +1,97 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    classify_binary, classify_module, Classification, ConfigurablePass, PassOutcome,
+};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    errors::{BinaryError, VMStaticViolation},
+    file_format::{
+        dummy_procedure_module, empty_module, Bytecode, FunctionHandle, FunctionSignatureIndex,
+        ModuleHandleIndex, StringPoolIndex,
+    },
+};
+
+proptest! {
+    #[test]
+    fn arbitrary_binaries_are_classified(binary in vec(any::<u8>(), 0..1024)) {
+        // This would fail the test by panicking.
+        classify_binary(&binary);
+    }
+}
+
+#[test]
+fn malformed_binary() {
+    assert_eq!(
+        classify_binary(&[0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef]),
+        Classification::Malformed(BinaryError::BadMagic)
+    );
+}
+
+#[test]
+fn out_of_bounds_binary() {
+    let mut module = empty_module();
+    module.function_handles.push(FunctionHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(0),
+        signature: FunctionSignatureIndex::new(1),
+    });
+    let mut binary = vec![];
+    module.serialize(&mut binary).expect("should serialize");
+    match classify_binary(&binary) {
+        Classification::OutOfBounds(errors) => assert!(!errors.is_empty()),
+        classification => panic!("unexpected classification: {}", classification),
+    }
+}
+
+#[test]
+fn verified_binary() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let mut binary = vec![];
+    module.serialize(&mut binary).expect("should serialize");
+    let classification = classify_binary(&binary);
+    assert!(classification.is_verified());
+    assert!(classification.failed_passes().is_empty());
+    assert_eq!(classification.to_string(), "verified");
+}
+
+#[test]
+fn passes_run_past_failures() {
+    let module = dummy_procedure_module(vec![Bytecode::Pop, Bytecode::Ret]);
+    let classification = classify_module(&module);
+    assert!(!classification.is_verified());
+    assert_eq!(
+        classification.failed_passes(),
+        vec![ConfigurablePass::StackUsage]
+    );
+
+    let outcomes = match classification {
+        Classification::Checked(outcomes) => outcomes,
+        classification => panic!("unexpected classification: {}", classification),
+    };
+    match &outcomes[&ConfigurablePass::StackUsage] {
+        PassOutcome::Failed(errors) => {
+            assert_eq!(errors.len(), 1);
+            assert_eq!(
+                errors[0].err,
+                VMStaticViolation::NegativeStackSizeInsideBlock(0, 0)
+            );
+        }
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    // Passes depending on stack usage are skipped, but the others still run.
+    assert_eq!(
+        outcomes[&ConfigurablePass::TypeSafety],
+        PassOutcome::Skipped
+    );
+    assert_eq!(
+        outcomes[&ConfigurablePass::StackHeight],
+        PassOutcome::Skipped
+    );
+    assert_eq!(outcomes[&ConfigurablePass::Acquires], PassOutcome::Passed);
+    assert_eq!(
+        outcomes[&ConfigurablePass::Reducibility],
+        PassOutcome::Passed
+    );
+}
@@ -0,0 +1,157 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{CompatibilityChecker, Incompatibility};
+use types::account_address::AccountAddress;
+use vm::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature, SignatureToken},
+};
+
+fn signature(arg_types: Vec<SignatureToken>) -> FunctionSignature {
+    FunctionSignature {
+        arg_types: arg_types.into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+fn ret() -> CodeBuilder {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    code
+}
+
+/// Builds a module with a resource `R { x: u64 }`, a public function `f(u64)` and a private
+/// function `g()`, then lets `customize` add to it.
+fn module(
+    fields: Vec<(&str, SignatureToken)>,
+    f_args: Vec<SignatureToken>,
+    customize: impl FnOnce(&mut CompiledModuleBuilder),
+) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_struct("R", true, vec![], fields);
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature(f_args),
+        vec![],
+        vec![],
+        ret(),
+    );
+    builder.add_function("g", 0, signature(vec![]), vec![], vec![], ret());
+    customize(&mut builder);
+    builder.build().expect("module is bounds-valid")
+}
+
+fn old_module() -> CompiledModule {
+    module(
+        vec![("x", SignatureToken::U64)],
+        vec![SignatureToken::U64],
+        |_| (),
+    )
+}
+
+#[test]
+fn additions_are_compatible() {
+    let new = module(
+        vec![("x", SignatureToken::U64)],
+        vec![SignatureToken::U64],
+        |builder| {
+            builder.add_struct("S", false, vec![], vec![("b", SignatureToken::Bool)]);
+            builder.add_function(
+                "h",
+                CodeUnit::PUBLIC,
+                signature(vec![]),
+                vec![],
+                vec![],
+                ret(),
+            );
+        },
+    );
+    let report = CompatibilityChecker::new(&old_module(), &new).verify();
+    assert!(report.is_compatible(), "{}", report);
+    assert!(CompatibilityChecker::new(&old_module(), &old_module())
+        .verify()
+        .is_compatible());
+}
+
+#[test]
+fn resource_field_change_breaks_layout() {
+    let new = module(
+        vec![("x", SignatureToken::U64), ("y", SignatureToken::U64)],
+        vec![SignatureToken::U64],
+        |_| (),
+    );
+    let report = CompatibilityChecker::new(&old_module(), &new).verify();
+    assert_eq!(
+        report.incompatibilities,
+        vec![Incompatibility::StructLayoutChanged("R".to_string())]
+    );
+    assert!(!report.is_layout_compatible());
+    assert!(report.is_api_compatible());
+}
+
+#[test]
+fn public_signature_change_breaks_api() {
+    let new = module(
+        vec![("x", SignatureToken::U64)],
+        vec![SignatureToken::Bool],
+        |_| (),
+    );
+    let report = CompatibilityChecker::new(&old_module(), &new).verify();
+    assert_eq!(
+        report.incompatibilities,
+        vec![Incompatibility::PublicFunctionSignatureChanged(
+            "f".to_string()
+        )]
+    );
+    assert!(report.is_layout_compatible());
+    assert!(!report.is_api_compatible());
+}
+
+#[test]
+fn removals_are_reported() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    // `f` is no longer public, `g` (which was private) and `R` are gone.
+    builder.add_function(
+        "f",
+        0,
+        signature(vec![SignatureToken::U64]),
+        vec![],
+        vec![],
+        ret(),
+    );
+    let new = builder.build().unwrap();
+
+    let report = CompatibilityChecker::new(&old_module(), &new).verify();
+    assert_eq!(
+        report.incompatibilities,
+        vec![
+            Incompatibility::StructRemoved("R".to_string()),
+            Incompatibility::PublicFunctionRemoved("f".to_string()),
+        ]
+    );
+    assert_eq!(
+        report.to_string(),
+        "struct R is removed, public function f is removed"
+    );
+}
+
+#[test]
+fn module_id_must_match() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "N");
+    builder.add_struct("R", true, vec![], vec![("x", SignatureToken::U64)]);
+    let new = builder.build().unwrap();
+
+    let report = CompatibilityChecker::new(&old_module(), &new).verify();
+    match &report.incompatibilities[0] {
+        Incompatibility::ModuleIdChanged { old, new } => {
+            assert_eq!(old.name(), "M");
+            assert_eq!(new.name(), "N");
+        }
+        incompatibility => panic!("unexpected incompatibility: {}", incompatibility),
+    }
+    assert!(!report.is_layout_compatible());
+    assert!(!report.is_api_compatible());
+}
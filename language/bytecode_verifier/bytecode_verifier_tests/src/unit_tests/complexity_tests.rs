@@ -0,0 +1,126 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{ComplexityReport, FunctionComplexity};
+use types::account_address::AccountAddress;
+use vm::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, FunctionDefinitionIndex, FunctionSignature,
+        SignatureToken,
+    },
+};
+
+fn code(code: Vec<Bytecode>) -> CodeBuilder {
+    let mut code_builder = CodeBuilder::new();
+    for bytecode in code {
+        code_builder.emit(bytecode);
+    }
+    code_builder
+}
+
+/// Builds a module defining `g()`, which returns right away and has unreachable code, then
+/// `f(u64)`, which has two nested loops.
+fn module() -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_function(
+        "g",
+        CodeUnit::PUBLIC,
+        FunctionSignature {
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        },
+        vec![],
+        vec![],
+        code(vec![Bytecode::Ret, Bytecode::Ret]),
+    );
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        FunctionSignature {
+            arg_types: vec![SignatureToken::U64].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        },
+        vec![SignatureToken::U64],
+        vec![],
+        code(vec![
+            Bytecode::LdConst(0),
+            Bytecode::StLoc(1),
+            // The outer loop.
+            Bytecode::CopyLoc(0),
+            Bytecode::BrFalse(8),
+            // The inner loop.
+            Bytecode::CopyLoc(1),
+            Bytecode::BrFalse(7),
+            Bytecode::Branch(4),
+            Bytecode::Branch(2),
+            Bytecode::Ret,
+        ]),
+    );
+    builder.build().expect("module is bounds-valid")
+}
+
+#[test]
+fn metrics_of_every_function() {
+    let report = ComplexityReport::new(&module());
+    assert_eq!(report.functions.len(), 2);
+    assert_eq!(
+        report.functions[&FunctionDefinitionIndex::new(0)],
+        FunctionComplexity {
+            cyclomatic_complexity: 1,
+            basic_blocks: 2,
+            max_loop_depth: 0,
+            locals: 0,
+            max_live_locals: 0,
+        }
+    );
+    assert_eq!(
+        report.functions[&FunctionDefinitionIndex::new(1)],
+        FunctionComplexity {
+            cyclomatic_complexity: 3,
+            basic_blocks: 6,
+            max_loop_depth: 2,
+            locals: 2,
+            max_live_locals: 2,
+        }
+    );
+}
+
+#[test]
+fn most_complex_first() {
+    let report = ComplexityReport::new(&module());
+    let functions: Vec<_> = report
+        .most_complex(2)
+        .into_iter()
+        .map(|(idx, _)| idx)
+        .collect();
+    assert_eq!(
+        functions,
+        vec![
+            FunctionDefinitionIndex::new(1),
+            FunctionDefinitionIndex::new(0)
+        ]
+    );
+    assert_eq!(report.most_complex(1).len(), 1);
+    assert!(report.most_complex(0).is_empty());
+}
+
+#[test]
+fn straight_line_code() {
+    let complexity = FunctionComplexity::new(
+        &[
+            Bytecode::LdConst(1),
+            Bytecode::StLoc(0),
+            Bytecode::MoveLoc(0),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ],
+        1,
+    );
+    assert_eq!(complexity.cyclomatic_complexity, 1);
+    assert_eq!(complexity.basic_blocks, 1);
+    assert_eq!(complexity.max_loop_depth, 0);
+    assert_eq!(complexity.max_live_locals, 1);
+}
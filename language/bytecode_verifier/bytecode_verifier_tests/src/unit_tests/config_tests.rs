@@ -0,0 +1,263 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    verify_module_by_pass, verify_module_with_config, CodeUnitVerifier, ConfigurablePass,
+    VerifiedModule, VerifierConfig, VerifierLimits,
+};
+use proptest::prelude::*;
+use stdlib::stdlib_modules;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        dummy_procedure_module, Bytecode, CompiledModule, FunctionDefinitionIndex,
+        ModuleHandleIndex, SignatureToken, StringPoolIndex, StructDefinition,
+        StructDefinitionIndex, StructFieldInformation, StructHandle, StructHandleIndex,
+        TypeSignature,
+    },
+    IndexKind,
+};
+
+proptest! {
+    #[test]
+    fn all_passes_match_verified_module(module in CompiledModule::valid_strategy(20)) {
+        let expected = match VerifiedModule::new_with_warnings(module.clone()) {
+            Ok((_, warnings)) => warnings,
+            Err((_, errors)) => errors,
+        };
+        prop_assert_eq!(verify_module_with_config(&module, &VerifierConfig::all()), expected);
+    }
+
+    #[test]
+    fn collect_all_finds_more(module in CompiledModule::valid_strategy(20)) {
+        let errors = verify_module_with_config(&module, &VerifierConfig::all());
+        let config = VerifierConfig {
+            collect_all: true,
+            ..VerifierConfig::all()
+        };
+        let collected = verify_module_by_pass(&module, &config).into_errors();
+        for err in &errors {
+            prop_assert!(collected.contains(err));
+        }
+        if errors.is_empty() {
+            prop_assert!(collected.is_empty());
+        }
+    }
+
+    #[test]
+    fn no_passes(module in CompiledModule::valid_strategy(20)) {
+        prop_assert_eq!(verify_module_with_config(&module, &VerifierConfig::none()), vec![]);
+    }
+}
+
+#[test]
+fn disabled_dependency() {
+    let mut config = VerifierConfig::none();
+    config.type_safety = true;
+    assert!(config.is_enabled(ConfigurablePass::TypeSafety));
+    assert!(!config.runs(ConfigurablePass::TypeSafety));
+
+    let config = VerifierConfig {
+        stack_usage: false,
+        ..VerifierConfig::all()
+    };
+    assert!(config.runs(ConfigurablePass::ControlFlow));
+    assert!(config.runs(ConfigurablePass::Acquires));
+    assert!(!config.runs(ConfigurablePass::StackUsage));
+    assert!(!config.runs(ConfigurablePass::TypeSafety));
+}
+
+#[test]
+fn skip_stack_usage() {
+    let module = dummy_procedure_module(vec![Bytecode::LdTrue, Bytecode::Ret]);
+    let errors = verify_module_with_config(&module, &VerifierConfig::all());
+    assert_eq!(errors.len(), 1);
+
+    let config = VerifierConfig {
+        stack_usage: false,
+        ..VerifierConfig::all()
+    };
+    assert_eq!(verify_module_with_config(&module, &config), vec![]);
+}
+
+#[test]
+fn skip_type_safety() {
+    let module = dummy_procedure_module(vec![
+        Bytecode::LdTrue,
+        Bytecode::LdConst(1),
+        Bytecode::Add,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    let errors = verify_module_with_config(&module, &VerifierConfig::all());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].err,
+        VMStaticViolation::IntegerOpTypeMismatchError(2)
+    );
+
+    let config = VerifierConfig {
+        type_safety: false,
+        ..VerifierConfig::all()
+    };
+    assert_eq!(verify_module_with_config(&module, &config), vec![]);
+}
+
+#[test]
+fn stop_at_first_function() {
+    let mut module = dummy_procedure_module(vec![Bytecode::LdTrue, Bytecode::Pop]).into_inner();
+    let function_def = module.function_defs[0].clone();
+    module.function_defs.push(function_def);
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let mut config = VerifierConfig::all();
+    let errors = CodeUnitVerifier::verify_with_config(&module, &config);
+    assert_eq!(errors.len(), 2);
+
+    config.stop_at_first_function = true;
+    let errors = CodeUnitVerifier::verify_with_config(&module, &config);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].err, VMStaticViolation::InvalidFallThrough);
+}
+
+#[test]
+fn collect_all() {
+    // A function that falls through, and whose acquires list has a struct that isn't a resource
+    // and isn't actually acquired.
+    let mut module = dummy_procedure_module(vec![Bytecode::LdTrue, Bytecode::Pop]).into_inner();
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(0),
+        is_nominal_resource: false,
+        type_formals: vec![],
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Native,
+    });
+    module.function_defs[0]
+        .acquires_global_resources
+        .push(StructDefinitionIndex::new(0));
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let errors = verify_module_by_pass(&module, &VerifierConfig::all());
+    let grouped = errors.grouped();
+    assert_eq!(grouped.len(), 1);
+    assert_eq!(
+        errors.for_pass(ConfigurablePass::ControlFlow)[0].err,
+        VMStaticViolation::InvalidFallThrough
+    );
+
+    let config = VerifierConfig {
+        collect_all: true,
+        ..VerifierConfig::all()
+    };
+    let errors = verify_module_by_pass(&module, &config);
+    assert!(errors.has_errors());
+    let grouped = errors.grouped();
+    assert_eq!(grouped.len(), 2);
+    assert_eq!(grouped[&ConfigurablePass::ControlFlow].len(), 1);
+    let acquires_errors: Vec<_> = grouped[&ConfigurablePass::Acquires]
+        .iter()
+        .map(|err| err.err.clone())
+        .collect();
+    assert_eq!(
+        acquires_errors,
+        vec![
+            VMStaticViolation::ExtraneousAcquiresResourceAnnotationError,
+            VMStaticViolation::InvalidAcquiresResourceAnnotationError,
+        ]
+    );
+}
+
+#[test]
+fn signature_depth_limit() {
+    let mut module = dummy_procedure_module(vec![Bytecode::Ret]).into_inner();
+    // Nested references are rejected by the signature checker, but only if it gets to run.
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::Reference(Box::new(
+            SignatureToken::Reference(Box::new(SignatureToken::U64)),
+        ))));
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let errors = verify_module_with_config(&module, &VerifierConfig::all());
+    assert_eq!(errors.len(), 1);
+    assert_ne!(errors[0].err, VMStaticViolation::VerificationBudgetExceeded);
+
+    let mut config = VerifierConfig::all();
+    config.limits.max_signature_depth = Some(2);
+    assert_eq!(
+        verify_module_with_config(&module, &config),
+        vec![VerificationError::new(
+            IndexKind::TypeSignature,
+            0,
+            VMStaticViolation::VerificationBudgetExceeded,
+        )]
+    );
+}
+
+#[test]
+fn function_complexity_limit() {
+    let mut module =
+        dummy_procedure_module(vec![Bytecode::LdConst(0), Bytecode::Pop, Bytecode::Ret])
+            .into_inner();
+    let function_def = module.function_defs[0].clone();
+    module.function_defs.push(function_def);
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let mut config = VerifierConfig::all();
+    assert_eq!(verify_module_with_config(&module, &config), vec![]);
+
+    // Verification aborts at the first function over the limit.
+    config.limits.max_function_complexity = Some(2);
+    assert_eq!(
+        verify_module_with_config(&module, &config),
+        vec![VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::VerificationBudgetExceeded,
+        )]
+    );
+}
+
+#[test]
+fn fixpoint_iteration_limit() {
+    // A loop made of two blocks, which takes two iterations to reach a fixed point.
+    let module = dummy_procedure_module(vec![Bytecode::LdTrue, Bytecode::BrTrue(0), Bytecode::Ret]);
+
+    let mut config = VerifierConfig {
+        collect_all: true,
+        limits: VerifierLimits::unlimited(),
+        ..VerifierConfig::all()
+    };
+    assert_eq!(verify_module_with_config(&module, &config), vec![]);
+
+    config.limits.max_fixpoint_iterations = Some(1);
+    assert_eq!(
+        verify_module_with_config(&module, &config),
+        vec![VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::VerificationBudgetExceeded,
+        )]
+    );
+}
+
+#[test]
+fn stdlib_is_within_recommended_limits() {
+    // Enforcing the limits is a consensus change, so `VerifiedModule::new` must not do it.
+    assert_eq!(VerifierConfig::all().limits, VerifierLimits::unlimited());
+
+    let config = VerifierConfig {
+        limits: VerifierLimits::recommended(),
+        ..VerifierConfig::all()
+    };
+    for module in stdlib_modules() {
+        assert_eq!(
+            verify_module_with_config(module.as_inner(), &config),
+            vec![],
+            "{}",
+            module.self_id()
+        );
+    }
+}
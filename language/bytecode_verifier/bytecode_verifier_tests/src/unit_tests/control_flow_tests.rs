@@ -0,0 +1,70 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::CodeUnitVerifier;
+use invalid_mutations::control_flow::{
+    ApplyControlFlowContext, ControlFlowMutation, ControlFlowMutationKind,
+};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    check_bounds::BoundsChecker,
+    errors::{sort_errors, VMStaticViolation},
+    file_format::{dummy_procedure_module, Bytecode, CompiledModule},
+};
+
+proptest! {
+    #[test]
+    fn branches_out_of_bounds(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(ControlFlowMutation::strategy(), 0..40),
+    ) {
+        let mut module = module.into_inner();
+        let expected_violations = {
+            let context = ApplyControlFlowContext::new(&mut module, mutations);
+            context.apply()
+        };
+        // Functions that fall off their end are caught by the code unit verifier instead.
+        let mut expected_violations: Vec<_> = expected_violations
+            .into_iter()
+            .filter(|err| err.err != VMStaticViolation::InvalidFallThrough)
+            .collect();
+        sort_errors(&mut expected_violations);
+
+        let bounds_checker = BoundsChecker::new(&module);
+        let mut actual_violations = bounds_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn fall_off_the_end(
+        body_len in 0..10usize,
+        mutations in vec(ControlFlowMutation::strategy(), 0..4),
+    ) {
+        let mut code = vec![];
+        for _ in 0..body_len {
+            code.push(Bytecode::LdTrue);
+            code.push(Bytecode::Pop);
+        }
+        code.push(Bytecode::Ret);
+        let mut module = dummy_procedure_module(code).into_inner();
+
+        let removals: Vec<_> = mutations
+            .into_iter()
+            .filter(|mutation| match mutation.kind() {
+                ControlFlowMutationKind::RemoveReturn => true,
+                _ => false,
+            })
+            .collect();
+        let any_removals = !removals.is_empty();
+        let expected_violations = {
+            let context = ApplyControlFlowContext::new(&mut module, removals);
+            context.apply()
+        };
+        prop_assert_eq!(expected_violations.len(), any_removals as usize);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let actual_violations = CodeUnitVerifier::verify(&module);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::SignatureChecker;
+use invalid_mutations::{
+    bounds::{ApplyOutOfBoundsContext, OutOfBoundsMutation},
+    coverage::{CoverageReport, MutationClass, VerifierPass},
+    signature::{ApplyMalformedTokenContext, MalformedTokenMutation},
+};
+use proptest::{collection::vec, prelude::*, test_runner::TestRunner};
+use std::cell::RefCell;
+use vm::{
+    check_bounds::BoundsChecker,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::CompiledModule,
+    IndexKind,
+};
+
+#[test]
+fn exercised_passes() {
+    let report = RefCell::new(CoverageReport::new());
+    let mut runner = TestRunner::default();
+
+    runner
+        .run(
+            &(
+                CompiledModule::valid_strategy(20),
+                vec(OutOfBoundsMutation::strategy(), 1..40),
+            ),
+            |(module, mutations)| {
+                let (module, expected_violations) =
+                    ApplyOutOfBoundsContext::new(module, mutations).apply();
+                let actual_violations = BoundsChecker::new(&module).verify();
+                report.borrow_mut().record(
+                    MutationClass::OutOfBounds,
+                    VerifierPass::BoundsChecker,
+                    &expected_violations,
+                    &actual_violations,
+                );
+                Ok(())
+            },
+        )
+        .expect("recording coverage should not fail");
+    runner
+        .run(
+            &(
+                CompiledModule::valid_strategy(20),
+                vec(MalformedTokenMutation::strategy(), 1..40),
+            ),
+            |(module, mutations)| {
+                let mut module = module.into_inner();
+                let expected_violations =
+                    ApplyMalformedTokenContext::new(&mut module, mutations).apply();
+                let module = module.freeze().expect("should satisfy bounds checker");
+                let actual_violations = SignatureChecker::new(&module).verify();
+                report.borrow_mut().record(
+                    MutationClass::MalformedToken,
+                    VerifierPass::SignatureChecker,
+                    &expected_violations,
+                    &actual_violations,
+                );
+                Ok(())
+            },
+        )
+        .expect("recording coverage should not fail");
+
+    let report = report.into_inner();
+    let exercised = report.exercised_passes();
+    assert!(
+        exercised.contains(&VerifierPass::BoundsChecker),
+        "{}",
+        report
+    );
+    assert!(
+        exercised.contains(&VerifierPass::SignatureChecker),
+        "{}",
+        report
+    );
+    assert!(!report
+        .unexercised_passes()
+        .contains(&VerifierPass::BoundsChecker));
+    assert!(report.unexpected_codes().is_empty(), "{}", report);
+}
+
+#[test]
+fn unexpected_codes() {
+    let mut report = CoverageReport::new();
+    let expected = vec![VerificationError::new(
+        IndexKind::StructDefinition,
+        0,
+        VMStaticViolation::InconsistentFields,
+    )];
+    let actual = vec![VerificationError::new(
+        IndexKind::FieldDefinition,
+        0,
+        VMStaticViolation::UnusedFields,
+    )];
+    report.record(
+        MutationClass::FieldRange,
+        VerifierPass::DuplicationChecker,
+        &expected,
+        &actual,
+    );
+
+    let coverage = report
+        .coverage(MutationClass::FieldRange, VerifierPass::DuplicationChecker)
+        .expect("coverage should have been recorded");
+    assert_eq!(coverage.cases, 1);
+    assert_eq!(coverage.exercised, 0);
+    assert!(report
+        .unexercised_passes()
+        .contains(&VerifierPass::DuplicationChecker));
+    let unexpected = report.unexpected_codes();
+    let codes = &unexpected[&(MutationClass::FieldRange, VerifierPass::DuplicationChecker)];
+    assert!(codes.contains(&VMStaticViolation::UnusedFields.code()));
+}
@@ -0,0 +1,108 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    control_flow_graph::VMControlFlowGraph,
+    dataflow::{Definition, Liveness, ReachingDefinitions},
+};
+use std::collections::BTreeSet;
+use vm::file_format::{Bytecode, LocalIndex};
+
+fn locals(locals: &[LocalIndex]) -> BTreeSet<LocalIndex> {
+    locals.iter().cloned().collect()
+}
+
+fn definitions(definitions: &[Definition]) -> BTreeSet<Definition> {
+    definitions.iter().cloned().collect()
+}
+
+/// Stores a different constant in local 1 depending on argument 0, then reads local 1.
+fn diamond() -> Vec<Bytecode> {
+    vec![
+        Bytecode::CopyLoc(0),
+        Bytecode::BrFalse(5),
+        Bytecode::LdConst(1),
+        Bytecode::StLoc(1),
+        Bytecode::Branch(7),
+        Bytecode::LdConst(2),
+        Bytecode::StLoc(1),
+        Bytecode::MoveLoc(1),
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]
+}
+
+/// Borrows local 0 mutably in a loop, followed by unreachable code.
+fn borrowing_loop() -> Vec<Bytecode> {
+    vec![
+        Bytecode::LdConst(0),
+        Bytecode::StLoc(0),
+        Bytecode::CopyLoc(0),
+        Bytecode::BrFalse(7),
+        Bytecode::MutBorrowLoc(0),
+        Bytecode::Pop,
+        Bytecode::Branch(2),
+        Bytecode::Ret,
+        Bytecode::Ret,
+    ]
+}
+
+#[test]
+fn liveness_across_branches() {
+    let code = diamond();
+    let liveness = Liveness::new(&code, &VMControlFlowGraph::new(&code));
+    assert_eq!(liveness.live_before(0), &locals(&[0]));
+    assert_eq!(liveness.live_after(0), &locals(&[]));
+    assert_eq!(liveness.live_before(2), &locals(&[]));
+    assert!(liveness.is_live_after(3, 1));
+    assert!(liveness.is_live_after(6, 1));
+    assert_eq!(liveness.live_before(7), &locals(&[1]));
+    assert_eq!(liveness.live_after(7), &locals(&[]));
+}
+
+#[test]
+fn liveness_in_loops() {
+    let code = borrowing_loop();
+    let liveness = Liveness::new(&code, &VMControlFlowGraph::new(&code));
+    assert_eq!(liveness.live_before(0), &locals(&[]));
+    assert!(liveness.is_live_after(1, 0));
+    // Local 0 is read again on the next iteration.
+    assert!(liveness.is_live_after(6, 0));
+    assert!(liveness.is_live_after(3, 0));
+    assert!(!liveness.is_live_after(7, 0));
+}
+
+#[test]
+fn reaching_definitions_across_branches() {
+    let code = diamond();
+    let reaching = ReachingDefinitions::new(&code, &VMControlFlowGraph::new(&code), 1);
+    assert_eq!(
+        reaching.reaching(0, 0),
+        definitions(&[Definition::Argument])
+    );
+    assert!(reaching.reaching(2, 1).is_empty());
+    assert_eq!(
+        reaching.reaching(7, 1),
+        definitions(&[Definition::At(3), Definition::At(6)])
+    );
+    assert_eq!(reaching.after(3)[&1], definitions(&[Definition::At(3)]));
+    assert_eq!(reaching.before(7)[&0], definitions(&[Definition::Argument]));
+}
+
+#[test]
+fn reaching_definitions_in_loops() {
+    let code = borrowing_loop();
+    let reaching = ReachingDefinitions::new(&code, &VMControlFlowGraph::new(&code), 0);
+    assert!(reaching.before(0).is_empty());
+    // Mutable borrows add a definition without replacing the others.
+    assert_eq!(
+        reaching.reaching(2, 0),
+        definitions(&[Definition::At(1), Definition::At(4)])
+    );
+    assert_eq!(
+        reaching.after(4)[&0],
+        definitions(&[Definition::At(1), Definition::At(4)])
+    );
+    // Nothing reaches unreachable code.
+    assert!(reaching.before(8).is_empty());
+}
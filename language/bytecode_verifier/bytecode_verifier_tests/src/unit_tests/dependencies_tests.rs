@@ -0,0 +1,247 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    verify_module_dependencies, verify_module_dependencies_in, VerifiedModule,
+};
+use std::collections::HashMap;
+use types::account_address::AccountAddress;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        AddressPoolIndex, Bytecode, CodeUnit, CompiledModuleMut, FieldDefinition,
+        FieldDefinitionIndex, FunctionDefinition, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, LocalsSignature, LocalsSignatureIndex,
+        ModuleHandle, ModuleHandleIndex, SignatureToken, StringPoolIndex, StructDefinition,
+        StructFieldInformation, StructHandle, StructHandleIndex, TypeSignature, TypeSignatureIndex,
+    },
+    IndexKind,
+};
+
+/// The signature of the function `M.f`.
+fn f_signature() -> FunctionSignature {
+    FunctionSignature {
+        arg_types: vec![SignatureToken::U64].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+/// Returns a module `M` defining a resource `S` with a single field and the functions `g` and `f`,
+/// in that order. Errors about `f` carry its definition index 1, while the handle to it in `N` is
+/// at index 0.
+fn dependency(native_struct: bool, public_function: bool) -> VerifiedModule {
+    let field_information = if native_struct {
+        StructFieldInformation::Native
+    } else {
+        StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        }
+    };
+    let f_flags = if public_function { CodeUnit::PUBLIC } else { 0 };
+    let code = CodeUnit {
+        locals: LocalsSignatureIndex::new(0),
+        code: vec![Bytecode::Ret],
+        ..CodeUnit::default()
+    };
+    let module = CompiledModuleMut {
+        module_handles: vec![ModuleHandle {
+            address: AddressPoolIndex::new(0),
+            name: StringPoolIndex::new(0),
+        }],
+        struct_handles: vec![StructHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new(1),
+            is_nominal_resource: true,
+            type_formals: vec![],
+        }],
+        function_handles: vec![
+            FunctionHandle {
+                module: ModuleHandleIndex::new(0),
+                name: StringPoolIndex::new(3),
+                signature: FunctionSignatureIndex::new(1),
+            },
+            FunctionHandle {
+                module: ModuleHandleIndex::new(0),
+                name: StringPoolIndex::new(2),
+                signature: FunctionSignatureIndex::new(0),
+            },
+        ],
+        type_signatures: vec![TypeSignature(SignatureToken::U64)],
+        function_signatures: vec![
+            f_signature(),
+            FunctionSignature {
+                arg_types: vec![].into(),
+                return_types: vec![].into(),
+                type_formals: vec![],
+            },
+        ],
+        locals_signatures: vec![LocalsSignature(vec![SignatureToken::U64].into())],
+        string_pool: vec!["M".into(), "S".into(), "f".into(), "g".into()],
+        byte_array_pool: vec![],
+        address_pool: vec![AccountAddress::default()],
+        struct_defs: vec![StructDefinition {
+            struct_handle: StructHandleIndex::new(0),
+            field_information,
+        }],
+        field_defs: vec![FieldDefinition {
+            struct_: StructHandleIndex::new(0),
+            name: StringPoolIndex::new(1),
+            signature: TypeSignatureIndex::new(0),
+        }],
+        function_defs: vec![
+            FunctionDefinition {
+                function: FunctionHandleIndex::new(0),
+                flags: CodeUnit::PUBLIC,
+                code: code.clone(),
+                ..FunctionDefinition::default()
+            },
+            FunctionDefinition {
+                function: FunctionHandleIndex::new(1),
+                flags: f_flags,
+                code,
+                ..FunctionDefinition::default()
+            },
+        ],
+    };
+    VerifiedModule::bypass_verifier_DANGEROUS_FOR_TESTING_ONLY(
+        module.freeze().expect("should satisfy bounds checker"),
+    )
+}
+
+/// Returns a module `N` with a handle to `M.S` and a handle to `M.f`, declared as given.
+fn dependent(is_nominal_resource: bool, f_signature: FunctionSignature) -> VerifiedModule {
+    let module = CompiledModuleMut {
+        module_handles: vec![
+            ModuleHandle {
+                address: AddressPoolIndex::new(0),
+                name: StringPoolIndex::new(0),
+            },
+            ModuleHandle {
+                address: AddressPoolIndex::new(0),
+                name: StringPoolIndex::new(1),
+            },
+        ],
+        struct_handles: vec![StructHandle {
+            module: ModuleHandleIndex::new(1),
+            name: StringPoolIndex::new(2),
+            is_nominal_resource,
+            type_formals: vec![],
+        }],
+        function_handles: vec![FunctionHandle {
+            module: ModuleHandleIndex::new(1),
+            name: StringPoolIndex::new(3),
+            signature: FunctionSignatureIndex::new(0),
+        }],
+        type_signatures: vec![],
+        function_signatures: vec![f_signature],
+        locals_signatures: vec![],
+        string_pool: vec!["N".into(), "M".into(), "S".into(), "f".into()],
+        byte_array_pool: vec![],
+        address_pool: vec![AccountAddress::default()],
+        struct_defs: vec![],
+        field_defs: vec![],
+        function_defs: vec![],
+    };
+    VerifiedModule::bypass_verifier_DANGEROUS_FOR_TESTING_ONLY(
+        module.freeze().expect("should satisfy bounds checker"),
+    )
+}
+
+#[test]
+fn matching_dependency() {
+    let module = dependent(true, f_signature());
+    assert_eq!(
+        verify_module_dependencies(&module, &[dependency(false, true)]),
+        vec![]
+    );
+}
+
+#[test]
+fn struct_kind_mismatch() {
+    let module = dependent(false, f_signature());
+    assert_eq!(
+        verify_module_dependencies(&module, &[dependency(false, true)]),
+        vec![VerificationError::new(
+            IndexKind::StructHandle,
+            0,
+            VMStaticViolation::StructKindMismatch(0),
+        )]
+    );
+}
+
+#[test]
+fn function_signature_mismatch() {
+    let signature = FunctionSignature {
+        return_types: vec![SignatureToken::U64].into(),
+        ..f_signature()
+    };
+    let module = dependent(true, signature);
+    assert_eq!(
+        verify_module_dependencies(&module, &[dependency(false, true)]),
+        vec![VerificationError::new(
+            IndexKind::FunctionHandle,
+            0,
+            VMStaticViolation::FunctionSignatureMismatch(1),
+        )]
+    );
+}
+
+#[test]
+fn function_visibility_mismatch() {
+    let module = dependent(true, f_signature());
+    assert_eq!(
+        verify_module_dependencies(&module, &[dependency(false, false)]),
+        vec![VerificationError::new(
+            IndexKind::FunctionHandle,
+            0,
+            VMStaticViolation::FunctionVisibilityMismatch(1),
+        )]
+    );
+}
+
+#[test]
+fn native_flag_mismatch() {
+    // The VM doesn't implement any native struct named `M.S`.
+    let module = dependent(true, f_signature());
+    assert_eq!(
+        verify_module_dependencies(&module, &[dependency(true, true)]),
+        vec![VerificationError::new(
+            IndexKind::StructHandle,
+            0,
+            VMStaticViolation::NativeFlagMismatch(0),
+        )]
+    );
+}
+
+#[test]
+fn compiled_module_dependency() {
+    let module = dependent(true, f_signature());
+    let dependency = dependency(false, false).into_inner();
+    assert_eq!(
+        verify_module_dependencies(&module, &[dependency]),
+        vec![VerificationError::new(
+            IndexKind::FunctionHandle,
+            0,
+            VMStaticViolation::FunctionVisibilityMismatch(1),
+        )]
+    );
+}
+
+#[test]
+fn dependencies_in_cache() {
+    let module = dependent(true, f_signature());
+    let mut cache: HashMap<_, VerifiedModule> = HashMap::new();
+    assert_eq!(
+        verify_module_dependencies_in(&module, &cache),
+        verify_module_dependencies(&module, Vec::<&VerifiedModule>::new())
+    );
+
+    // The module itself is ignored if the cache holds it.
+    let dependency = dependency(false, true);
+    cache.insert(dependency.self_id(), dependency);
+    cache.insert(module.self_id(), module.clone());
+    assert_eq!(verify_module_dependencies_in(&module, &cache), vec![]);
+}
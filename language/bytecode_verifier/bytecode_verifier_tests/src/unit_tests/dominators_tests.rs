@@ -0,0 +1,99 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    control_flow_graph::{BlockId, ControlFlowGraph, VMControlFlowGraph},
+    dominators::{DominatorTree, LoopNest},
+};
+use proptest::prelude::*;
+use std::collections::BTreeSet;
+use vm::file_format::{Bytecode, CompiledModule};
+
+/// Returns the blocks reachable from the entry block without going through `removed`.
+fn reachable_without(cfg: &VMControlFlowGraph, removed: BlockId) -> BTreeSet<BlockId> {
+    let mut reachable = BTreeSet::new();
+    let mut work_list = vec![cfg.entry_block_id()];
+    while let Some(block_id) = work_list.pop() {
+        if block_id != removed && reachable.insert(block_id) {
+            work_list.extend(cfg.successors(&block_id));
+        }
+    }
+    reachable
+}
+
+proptest! {
+    #[test]
+    fn dominators_match_reachability(module in CompiledModule::valid_strategy(20)) {
+        for function_def in module.as_inner().function_defs.iter() {
+            let code = &function_def.code.code;
+            if code.is_empty() {
+                continue;
+            }
+            let cfg = VMControlFlowGraph::new(code);
+            let dominators = DominatorTree::new(&cfg);
+            let reachable = reachable_without(&cfg, BlockId::max_value());
+            for dominator in cfg.blocks() {
+                prop_assert_eq!(dominators.is_reachable(dominator), reachable.contains(&dominator));
+                let reachable_without_dominator = reachable_without(&cfg, dominator);
+                for block_id in &reachable {
+                    // A block dominates another one if removing it makes the other unreachable.
+                    let expected = reachable.contains(&dominator)
+                        && !reachable_without_dominator.contains(block_id);
+                    prop_assert_eq!(dominators.dominates(dominator, *block_id), expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn nested_loops() {
+    let code = vec![
+        // Block 0, the outer loop header.
+        Bytecode::LdTrue,
+        Bytecode::BrFalse(7),
+        // Block 2, the inner loop header.
+        Bytecode::LdTrue,
+        Bytecode::BrFalse(5),
+        // Block 4, the latch of the inner loop.
+        Bytecode::Branch(2),
+        // Block 5, the latch of the outer loop.
+        Bytecode::Branch(0),
+        // Block 6, unreachable.
+        Bytecode::Ret,
+        // Block 7, after the loops.
+        Bytecode::Ret,
+    ];
+    let cfg = VMControlFlowGraph::new(&code);
+    let dominators = DominatorTree::new(&cfg);
+
+    assert_eq!(dominators.reverse_postorder()[0], 0);
+    assert_eq!(dominators.immediate_dominator(0), None);
+    assert_eq!(dominators.immediate_dominator(2), Some(0));
+    assert_eq!(dominators.immediate_dominator(4), Some(2));
+    assert_eq!(dominators.immediate_dominator(5), Some(2));
+    assert_eq!(dominators.immediate_dominator(7), Some(0));
+    assert_eq!(dominators.children(0), vec![2, 7]);
+    assert!(!dominators.is_reachable(6));
+    assert_eq!(dominators.immediate_dominator(6), None);
+    assert!(!dominators.dominates(6, 6));
+
+    let loop_nest = LoopNest::new(&cfg, &dominators);
+    let loops = loop_nest.loops();
+    assert_eq!(loops.len(), 2);
+    assert_eq!(loops[0].header, 0);
+    assert_eq!(loops[0].latches, vec![5]);
+    assert_eq!(loops[0].blocks, [0, 2, 4, 5].iter().cloned().collect());
+    assert_eq!(loops[1].header, 2);
+    assert_eq!(loops[1].latches, vec![4]);
+    assert_eq!(loops[1].blocks, [2, 4].iter().cloned().collect());
+
+    assert_eq!(loop_nest.parent(0), None);
+    assert_eq!(loop_nest.parent(1), Some(0));
+    assert_eq!(loop_nest.innermost_loop(4), Some(1));
+    assert_eq!(loop_nest.innermost_loop(5), Some(0));
+    assert_eq!(loop_nest.innermost_loop(7), None);
+    assert_eq!(loop_nest.depth(4), 2);
+    assert_eq!(loop_nest.depth(5), 1);
+    assert_eq!(loop_nest.depth(7), 0);
+}
@@ -1,9 +1,28 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use bytecode_verifier::DuplicationChecker;
-use proptest::prelude::*;
-use vm::file_format::CompiledModule;
+use bytecode_verifier::{verify_module_address, DuplicationChecker};
+use invalid_mutations::{
+    deletion::{ApplyDeletionContext, DeletionMutation},
+    duplication::{ApplyDuplicationContext, DuplicationMutation},
+    identifiers::{ApplyIdentifierContext, IdentifierMutation},
+    self_handle::{ApplySelfHandleContext, SelfHandleMutation},
+    struct_defs::{ApplyFieldRangeContext, FieldRangeMutation},
+};
+use proptest::{collection::vec, prelude::*};
+use types::account_address::{AccountAddress, ADDRESS_LENGTH};
+use vm::{
+    errors::{sort_errors, DuplicateKey, VMStaticViolation, VerificationError},
+    file_format::{
+        empty_module, AddressPoolIndex, CompiledModule, CompiledModuleMut, FieldDefinition,
+        FieldDefinitionIndex, FunctionDefinition, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, LocalsSignature, ModuleHandle,
+        ModuleHandleIndex, SignatureToken, StringPoolIndex, StructDefinition,
+        StructFieldInformation, StructHandle, StructHandleIndex, TableIndex, TypeSignature,
+        TypeSignatureIndex,
+    },
+    IndexKind,
+};
 
 proptest! {
     #[test]
@@ -12,3 +31,260 @@ proptest! {
         prop_assert!(!duplication_checker.verify().is_empty());
     }
 }
+
+/// Builds a module without any duplicate entries, with `module_count` imported modules that
+/// declare `struct_count` structs each, and as many structs with `field_count` fields declared in
+/// the module itself.
+fn unique_module(
+    module_count: usize,
+    struct_count: usize,
+    field_count: usize,
+) -> CompiledModuleMut {
+    fn add_string(module: &mut CompiledModuleMut, s: String) -> StringPoolIndex {
+        module.string_pool.push(s.into());
+        StringPoolIndex::new((module.string_pool.len() - 1) as TableIndex)
+    }
+
+    let mut module = empty_module();
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    let field_names: Vec<_> = (0..field_count)
+        .map(|idx| add_string(&mut module, format!("f{}", idx)))
+        .collect();
+    for module_idx in 0..=module_count {
+        if module_idx > 0 {
+            let name = add_string(&mut module, format!("M{}", module_idx));
+            module.module_handles.push(ModuleHandle {
+                address: AddressPoolIndex::new(0),
+                name,
+            });
+        }
+        for struct_idx in 0..struct_count {
+            let name = add_string(&mut module, format!("S{}_{}", module_idx, struct_idx));
+            module.struct_handles.push(StructHandle {
+                module: ModuleHandleIndex::new(module_idx as TableIndex),
+                name,
+                is_nominal_resource: false,
+                type_formals: vec![],
+            });
+            if module_idx > 0 {
+                continue;
+            }
+            let struct_handle = StructHandleIndex::new(struct_idx as TableIndex);
+            module.struct_defs.push(StructDefinition {
+                struct_handle,
+                field_information: StructFieldInformation::Declared {
+                    field_count: field_count as TableIndex,
+                    fields: FieldDefinitionIndex::new(module.field_defs.len() as TableIndex),
+                },
+            });
+            for name in &field_names {
+                module.field_defs.push(FieldDefinition {
+                    struct_: struct_handle,
+                    name: *name,
+                    signature: TypeSignatureIndex::new(0),
+                });
+            }
+        }
+    }
+    module
+}
+
+/// Adds `function_count` functions with distinct names to a module built by `unique_module`.
+fn add_functions(module: &mut CompiledModuleMut, function_count: usize) {
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    for idx in 0..function_count {
+        module.string_pool.push(format!("g{}", idx).into());
+        module.function_handles.push(FunctionHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new((module.string_pool.len() - 1) as TableIndex),
+            signature: FunctionSignatureIndex::new(0),
+        });
+        let mut function_def = FunctionDefinition::default();
+        function_def.function = FunctionHandleIndex::new(idx as TableIndex);
+        module.function_defs.push(function_def);
+    }
+}
+
+proptest! {
+    #[test]
+    fn unique_entries(
+        module_count in 0usize..4,
+        struct_count in 0usize..4,
+        field_count in 0usize..4,
+    ) {
+        let module = unique_module(module_count, struct_count, field_count)
+            .freeze()
+            .expect("should satisfy bounds checker");
+        let duplication_checker = DuplicationChecker::new(&module);
+        prop_assert_eq!(duplication_checker.verify(), vec![]);
+    }
+
+    #[test]
+    fn duplicate_entries(
+        module_count in 0usize..4,
+        struct_count in 0usize..4,
+        field_count in 0usize..4,
+        mutations in vec(DuplicationMutation::strategy(), 0..20),
+    ) {
+        let mut module = unique_module(module_count, struct_count, field_count);
+        let mut expected_violations = {
+            let context = ApplyDuplicationContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let duplication_checker = DuplicationChecker::new(&module);
+        let mut actual_violations = duplication_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn invalid_identifiers(
+        module_count in 0usize..4,
+        struct_count in 0usize..4,
+        field_count in 0usize..4,
+        mutations in vec(IdentifierMutation::strategy(), 0..20),
+    ) {
+        let mut module = unique_module(module_count, struct_count, field_count);
+        let mut expected_violations = {
+            let context = ApplyIdentifierContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let duplication_checker = DuplicationChecker::new(&module);
+        let mut actual_violations = duplication_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn inconsistent_field_ranges(
+        module_count in 0usize..4,
+        struct_count in 0usize..4,
+        field_count in 0usize..4,
+        mutations in vec(FieldRangeMutation::strategy(), 0..10),
+    ) {
+        let mut module = unique_module(module_count, struct_count, field_count);
+        let expected = {
+            let context = ApplyFieldRangeContext::new(&mut module, mutations);
+            context.apply_expected()
+        };
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let duplication_checker = DuplicationChecker::new(&module);
+        let actual_violations = duplication_checker.verify();
+        // The checker stops at the first inconsistency, so at most one error is reported.
+        prop_assert_eq!(expected.primary, actual_violations);
+        prop_assert!(!expected.primary.is_empty() || expected.secondary.is_empty());
+    }
+
+    #[test]
+    fn deleted_definitions(
+        module_count in 0usize..4,
+        struct_count in 0usize..4,
+        field_count in 0usize..4,
+        function_count in 0usize..4,
+        mutations in vec(DeletionMutation::strategy(), 0..10),
+    ) {
+        let mut module = unique_module(module_count, struct_count, field_count);
+        add_functions(&mut module, function_count);
+        let mut expected_violations = {
+            let context = ApplyDeletionContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let duplication_checker = DuplicationChecker::new(&module);
+        let mut actual_violations = duplication_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn corrupted_self_handle(
+        module_count in 0usize..4,
+        struct_count in 0usize..4,
+        address_count in 0usize..4,
+        mutations in vec(SelfHandleMutation::strategy(), 0..10),
+    ) {
+        let mut module = unique_module(module_count, struct_count, 0);
+        module.address_pool.extend(
+            (0..address_count).map(|idx| AccountAddress::new([idx as u8 + 1; ADDRESS_LENGTH])),
+        );
+        // unique_module declares itself at the first address.
+        let sender = module.address_pool[0];
+        let mut expected_violations = {
+            let context = ApplySelfHandleContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let mut actual_violations = verify_module_address(&module, &sender);
+        actual_violations.extend(DuplicationChecker::new(&module).verify());
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
+
+#[test]
+fn duplicate_signatures() {
+    let mut module = unique_module(0, 0, 0);
+    add_functions(&mut module, 0);
+    let signature = module.function_signatures[0].clone();
+    module.function_signatures.push(signature);
+    module.locals_signatures.extend(vec![
+        LocalsSignature(vec![SignatureToken::U64].into()),
+        LocalsSignature(vec![SignatureToken::Bool].into()),
+        LocalsSignature(vec![SignatureToken::U64].into()),
+    ]);
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let mut actual_violations = DuplicationChecker::new(&module).verify();
+    sort_errors(&mut actual_violations);
+    assert_eq!(
+        actual_violations,
+        vec![
+            VerificationError::new(
+                IndexKind::FunctionSignature,
+                1,
+                VMStaticViolation::DuplicateElement(0, DuplicateKey::Signature),
+            ),
+            VerificationError::new(
+                IndexKind::LocalsSignature,
+                3,
+                VMStaticViolation::DuplicateElement(1, DuplicateKey::Signature),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn duplicate_definitions_report_both_indices() {
+    let mut module = unique_module(0, 0, 0);
+    add_functions(&mut module, 2);
+    let mut function_def = FunctionDefinition::default();
+    function_def.function = FunctionHandleIndex::new(0);
+    module.function_defs.push(function_def);
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    assert_eq!(
+        DuplicationChecker::new(&module).verify(),
+        vec![VerificationError::new(
+            IndexKind::FunctionDefinition,
+            2,
+            VMStaticViolation::DuplicateElement(0, DuplicateKey::Handle),
+        )]
+    );
+}
@@ -0,0 +1,102 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier_ffi::{
+    verifier_free_errors, verifier_verify_module, verifier_verify_script, VERIFIER_FAILED,
+    VERIFIER_INVALID_ARGUMENT, VERIFIER_MALFORMED, VERIFIER_OK,
+};
+use serde_json::Value;
+use std::{ptr, slice};
+use vm::file_format::{dummy_procedure_module, Bytecode};
+
+type VerifyFn = unsafe extern "C" fn(*const u8, usize, *mut *mut u8, *mut usize) -> i32;
+
+/// Calls `verify` on `binary` the way foreign code would, and returns the status along with the
+/// errors, parsed.
+fn call(verify: VerifyFn, binary: &[u8]) -> (i32, Value) {
+    let mut errors: *mut u8 = ptr::null_mut();
+    let mut errors_len = 0;
+    unsafe {
+        let status = verify(binary.as_ptr(), binary.len(), &mut errors, &mut errors_len);
+        let parsed = serde_json::from_slice(slice::from_raw_parts(errors, errors_len))
+            .expect("errors are JSON");
+        verifier_free_errors(errors, errors_len);
+        (status, parsed)
+    }
+}
+
+fn binary(code: Vec<Bytecode>) -> Vec<u8> {
+    let mut binary = vec![];
+    dummy_procedure_module(code)
+        .serialize(&mut binary)
+        .expect("should serialize");
+    binary
+}
+
+#[test]
+fn verified_module() {
+    let (status, errors) = call(verifier_verify_module, &binary(vec![Bytecode::Ret]));
+    assert_eq!(status, VERIFIER_OK);
+    assert_eq!(errors, Value::Array(vec![]));
+}
+
+#[test]
+fn failing_module() {
+    let (status, errors) = call(
+        verifier_verify_module,
+        &binary(vec![Bytecode::Pop, Bytecode::Ret]),
+    );
+    assert_eq!(status, VERIFIER_FAILED);
+    let errors = errors.as_array().expect("errors are an array");
+    assert!(!errors.is_empty());
+    assert!(errors.iter().any(|error| error["severity"] == "error"));
+    assert!(errors.iter().all(|error| error["message"].is_string()));
+}
+
+#[test]
+fn advisory_warnings() {
+    // The second `Ret` can never be executed.
+    let (status, errors) = call(
+        verifier_verify_module,
+        &binary(vec![Bytecode::Ret, Bytecode::Ret]),
+    );
+    assert_eq!(status, VERIFIER_OK);
+    assert_eq!(errors[0]["code"], 6033);
+    assert_eq!(errors[0]["severity"], "warning");
+}
+
+#[test]
+fn malformed_binaries() {
+    let garbage = [0xde, 0xad, 0xbe, 0xef, 0xde, 0xad, 0xbe, 0xef];
+    for verify in &[verifier_verify_module as VerifyFn, verifier_verify_script] {
+        let (status, errors) = call(*verify, &garbage);
+        assert_eq!(status, VERIFIER_MALFORMED);
+        assert_eq!(errors[0]["code"], 1002);
+        assert_eq!(errors[0]["message"], "Bad magic");
+    }
+}
+
+#[test]
+fn null_arguments() {
+    let binary = binary(vec![Bytecode::Ret]);
+    let mut errors: *mut u8 = ptr::null_mut();
+    let mut errors_len = 0;
+    unsafe {
+        assert_eq!(
+            verifier_verify_module(ptr::null(), 0, &mut errors, &mut errors_len),
+            VERIFIER_INVALID_ARGUMENT
+        );
+        assert_eq!(
+            verifier_verify_module(
+                binary.as_ptr(),
+                binary.len(),
+                ptr::null_mut(),
+                &mut errors_len
+            ),
+            VERIFIER_INVALID_ARGUMENT
+        );
+        // Nothing was written, and releasing a null buffer does nothing.
+        assert!(errors.is_null());
+        verifier_free_errors(errors, errors_len);
+    }
+}
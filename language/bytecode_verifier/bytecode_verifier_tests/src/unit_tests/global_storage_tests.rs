@@ -0,0 +1,158 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{verify_module_with_config, GlobalStorageChecker, VerifierConfig};
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        empty_module, Bytecode, CodeUnit, CompiledModule, FieldDefinition, FieldDefinitionIndex,
+        FunctionDefinition, FunctionDefinitionIndex, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, ModuleHandleIndex, SignatureToken,
+        StringPoolIndex, StructDefinition, StructDefinitionIndex, StructFieldInformation,
+        StructHandle, StructHandleIndex, TypeSignature, TypeSignatureIndex, NO_TYPE_ACTUALS,
+    },
+};
+
+const RESOURCE: StructDefinitionIndex = StructDefinitionIndex(0);
+const NOT_RESOURCE: StructDefinitionIndex = StructDefinitionIndex(1);
+const ACQUIRING_FUNCTION: FunctionHandleIndex = FunctionHandleIndex(1);
+
+/// Builds a module with a resource struct, a struct that isn't a resource and two functions: the
+/// first runs `code` and acquires `acquires`, and the second acquires the resource.
+fn global_storage_module(
+    code: Vec<Bytecode>,
+    acquires: Vec<StructDefinitionIndex>,
+) -> CompiledModule {
+    let mut module = empty_module();
+    for name in &["R", "S", "x", "f", "g"] {
+        module.string_pool.push((*name).into());
+    }
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    for (idx, is_nominal_resource) in [true, false].iter().enumerate() {
+        module.struct_handles.push(StructHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new(1 + idx as u16),
+            is_nominal_resource: *is_nominal_resource,
+            type_formals: vec![],
+        });
+        module.field_defs.push(FieldDefinition {
+            struct_: StructHandleIndex::new(idx as u16),
+            name: StringPoolIndex::new(3),
+            signature: TypeSignatureIndex::new(0),
+        });
+        module.struct_defs.push(StructDefinition {
+            struct_handle: StructHandleIndex::new(idx as u16),
+            field_information: StructFieldInformation::Declared {
+                field_count: 1,
+                fields: FieldDefinitionIndex::new(idx as u16),
+            },
+        });
+    }
+
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    let functions = vec![
+        (code, acquires),
+        (
+            vec![Bytecode::MoveFrom(RESOURCE, NO_TYPE_ACTUALS), Bytecode::Ret],
+            vec![RESOURCE],
+        ),
+    ];
+    for (idx, (code, acquires)) in functions.into_iter().enumerate() {
+        module.function_handles.push(FunctionHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new(4 + idx as u16),
+            signature: FunctionSignatureIndex::new(0),
+        });
+        module.function_defs.push(FunctionDefinition {
+            function: FunctionHandleIndex::new(idx as u16),
+            acquires_global_resources: acquires,
+            code: CodeUnit {
+                code,
+                ..CodeUnit::default()
+            },
+            ..FunctionDefinition::default()
+        });
+    }
+    module.freeze().expect("should satisfy bounds checker")
+}
+
+fn in_f(err: VMStaticViolation) -> VerificationError {
+    VerificationError::in_function(FunctionDefinitionIndex::new(0), err)
+}
+
+#[test]
+fn consistent_accesses() {
+    let module = global_storage_module(
+        vec![
+            Bytecode::Exists(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::MoveToSender(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::BorrowGlobal(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::MoveFrom(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::Call(ACQUIRING_FUNCTION, NO_TYPE_ACTUALS),
+            Bytecode::Ret,
+        ],
+        vec![RESOURCE],
+    );
+    assert_eq!(GlobalStorageChecker::new(&module).verify(), vec![]);
+}
+
+#[test]
+fn accesses_to_structs_that_are_not_resources() {
+    let module = global_storage_module(
+        vec![
+            Bytecode::Exists(NOT_RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::MoveToSender(NOT_RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::BorrowGlobal(NOT_RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::MoveFrom(NOT_RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::Ret,
+        ],
+        vec![],
+    );
+    assert_eq!(
+        GlobalStorageChecker::new(&module).verify(),
+        vec![
+            in_f(VMStaticViolation::ExistsNoResourceError(0)),
+            in_f(VMStaticViolation::MoveToSenderNoResourceError(1)),
+            in_f(VMStaticViolation::BorrowGlobalNoResourceError(2)),
+            in_f(VMStaticViolation::MoveFromNoResourceError(3)),
+        ]
+    );
+}
+
+#[test]
+fn accesses_missing_from_acquires() {
+    // Unreachable code is checked as well.
+    let module = global_storage_module(
+        vec![
+            Bytecode::Ret,
+            Bytecode::BorrowGlobal(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::Exists(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::MoveFrom(RESOURCE, NO_TYPE_ACTUALS),
+            Bytecode::Call(ACQUIRING_FUNCTION, NO_TYPE_ACTUALS),
+            Bytecode::Ret,
+        ],
+        vec![],
+    );
+    let expected = vec![
+        in_f(VMStaticViolation::MissingAcquiresResourceAnnotationError(1)),
+        in_f(VMStaticViolation::MissingAcquiresResourceAnnotationError(3)),
+        in_f(VMStaticViolation::MissingAcquiresResourceAnnotationError(4)),
+    ];
+    assert_eq!(GlobalStorageChecker::new(&module).verify(), expected);
+
+    // The code doesn't balance the stack, so only run the passes global storage depends on.
+    let config = VerifierConfig {
+        duplication: true,
+        signature: true,
+        recursive_structs: true,
+        global_storage: true,
+        ..VerifierConfig::none()
+    };
+    assert_eq!(verify_module_with_config(&module, &config), expected);
+}
@@ -0,0 +1,172 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{ModuleChanges, VerifiedModule};
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        empty_module, Bytecode, CodeUnit, CompiledModuleMut, FieldDefinition, FieldDefinitionIndex,
+        FunctionDefinition, FunctionDefinitionIndex, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, ModuleHandleIndex, SignatureToken,
+        StringPoolIndex, StructDefinition, StructDefinitionIndex, StructFieldInformation,
+        StructHandle, StructHandleIndex, TypeSignature, TypeSignatureIndex, NO_TYPE_ACTUALS,
+    },
+    identifier::Identifier,
+};
+
+/// Builds a module with a struct `S { f: u64 }`, and two functions: `pack_s`, which packs and
+/// unpacks an `S`, and `other`, which doesn't touch it.
+fn module() -> CompiledModuleMut {
+    let mut module = empty_module();
+    module.string_pool.extend(
+        ["pack_s", "other", "S", "f"]
+            .iter()
+            .map(|s| Identifier::new(s)),
+    );
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(3),
+        is_nominal_resource: false,
+        type_formals: vec![],
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+    module.field_defs.push(FieldDefinition {
+        struct_: StructHandleIndex::new(0),
+        name: StringPoolIndex::new(4),
+        signature: TypeSignatureIndex::new(0),
+    });
+
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    let s = StructDefinitionIndex::new(0);
+    let codes = vec![
+        vec![
+            Bytecode::LdConst(1),
+            Bytecode::Pack(s, NO_TYPE_ACTUALS),
+            Bytecode::Unpack(s, NO_TYPE_ACTUALS),
+            Bytecode::Pop,
+            Bytecode::Ret,
+        ],
+        vec![Bytecode::Ret],
+    ];
+    for (idx, code) in codes.into_iter().enumerate() {
+        module.function_handles.push(FunctionHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new(idx as u16 + 1),
+            signature: FunctionSignatureIndex::new(0),
+        });
+        module.function_defs.push(FunctionDefinition {
+            function: FunctionHandleIndex::new(idx as u16),
+            code: CodeUnit {
+                code,
+                ..CodeUnit::default()
+            },
+            ..FunctionDefinition::default()
+        });
+    }
+    module
+}
+
+fn verified(module: CompiledModuleMut) -> VerifiedModule {
+    VerifiedModule::new(module.freeze().unwrap()).expect("module should verify")
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+#[test]
+fn unchanged_module() {
+    let old = verified(module());
+    let changes = ModuleChanges::new(old.as_inner(), old.as_inner());
+    assert!(changes.is_empty());
+    assert!(VerifiedModule::new_upgrade(&old, module().freeze().unwrap()).is_ok());
+}
+
+#[test]
+fn reordering_tables_is_not_a_change() {
+    let old = verified(module());
+    let mut new = module();
+    new.function_handles.swap(0, 1);
+    new.function_defs[0].function = FunctionHandleIndex::new(1);
+    new.function_defs[1].function = FunctionHandleIndex::new(0);
+    new.function_defs.swap(0, 1);
+    let new = new.freeze().unwrap();
+    assert!(ModuleChanges::new(old.as_inner(), &new).is_empty());
+}
+
+#[test]
+fn changed_function_body() {
+    let old = verified(module());
+    let mut new = module();
+    new.function_defs[1].code.code = vec![Bytecode::LdTrue, Bytecode::Pop, Bytecode::Ret];
+    let new = new.freeze().unwrap();
+
+    let changes = ModuleChanges::new(old.as_inner(), &new);
+    assert!(changes.changed_structs.is_empty());
+    assert_eq!(
+        changes.changed_functions.into_iter().collect::<Vec<_>>(),
+        names(&["other"])
+    );
+    assert_eq!(
+        changes.functions_to_verify,
+        vec![FunctionDefinitionIndex::new(1)]
+    );
+}
+
+#[test]
+fn changed_struct_affects_its_users() {
+    let old = verified(module());
+    let mut new = module();
+    new.type_signatures[0] = TypeSignature(SignatureToken::Bool);
+    let new = new.freeze().unwrap();
+
+    let changes = ModuleChanges::new(old.as_inner(), &new);
+    assert_eq!(
+        changes.changed_structs.into_iter().collect::<Vec<_>>(),
+        names(&["S"])
+    );
+    assert!(changes.changed_functions.is_empty());
+    assert_eq!(
+        changes.functions_to_verify,
+        vec![FunctionDefinitionIndex::new(0)]
+    );
+
+    // `pack_s` now packs a u64 into a bool field.
+    let (_, errors) = VerifiedModule::new_upgrade(&old, new).unwrap_err();
+    assert_eq!(
+        errors,
+        vec![VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::PackTypeMismatchError(1),
+        )]
+    );
+}
+
+#[test]
+fn unchanged_functions_are_not_verified_again() {
+    // `old` is trusted as verified, so a broken function it already contains goes unnoticed as
+    // long as it doesn't change.
+    let mut old = module();
+    old.function_defs[0].code.code = vec![Bytecode::Pop, Bytecode::Ret];
+    let old = VerifiedModule::bypass_verifier_DANGEROUS_FOR_TESTING_ONLY(old.freeze().unwrap());
+
+    let mut new = module();
+    new.function_defs[0].code.code = vec![Bytecode::Pop, Bytecode::Ret];
+    new.function_defs[1].code.code = vec![Bytecode::LdTrue, Bytecode::Pop, Bytecode::Ret];
+    let new = new.freeze().unwrap();
+    assert!(VerifiedModule::new(new.clone()).is_err());
+    assert!(VerifiedModule::new_upgrade(&old, new).is_ok());
+}
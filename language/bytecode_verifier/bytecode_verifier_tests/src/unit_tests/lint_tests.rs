@@ -0,0 +1,253 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    Lint, Linter, ResourceReferenceLint, UnboundedLoopLint, UncheckedArithmeticLint,
+};
+use types::account_address::AccountAddress;
+use vm::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    errors::{Severity, VMStaticViolation, VerificationError},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, FunctionSignature, SignatureToken, NO_TYPE_ACTUALS,
+    },
+    views::ModuleView,
+    IndexKind,
+};
+
+fn signature(
+    arg_types: Vec<SignatureToken>,
+    return_types: Vec<SignatureToken>,
+) -> FunctionSignature {
+    FunctionSignature {
+        arg_types: arg_types.into(),
+        return_types: return_types.into(),
+        type_formals: vec![],
+    }
+}
+
+fn code(code: Vec<Bytecode>) -> CodeBuilder {
+    let mut code_builder = CodeBuilder::new();
+    for bytecode in code {
+        code_builder.emit(bytecode);
+    }
+    code_builder
+}
+
+/// Counts from 0 to `bound`, which is loaded by `load_bound`, in a function whose local 1 is the
+/// counter.
+fn counting_loop(load_bound: Bytecode) -> Vec<Bytecode> {
+    vec![
+        Bytecode::LdConst(0),
+        Bytecode::StLoc(1),
+        Bytecode::CopyLoc(1),
+        load_bound,
+        Bytecode::Lt,
+        Bytecode::BrFalse(11),
+        Bytecode::CopyLoc(1),
+        Bytecode::LdConst(1),
+        Bytecode::Add,
+        Bytecode::StLoc(1),
+        Bytecode::Branch(2),
+        Bytecode::Ret,
+    ]
+}
+
+/// Builds a module defining, in order:
+/// 0. public `add(u64, u64): u64`, which adds its arguments and calls `square`;
+/// 1. `square(u64): u64`, which multiplies, then divides by a constant;
+/// 2. `double(u64): u64`, which adds, but isn't called by any public function;
+/// 3. public `count(u64)`, which loops as many times as its argument;
+/// 4. public `count_to_ten(u64)`, which loops ten times;
+/// 5. public `borrow_r(&R): &R`, for a resource `R`;
+/// 6. public `borrow_s(&S): &S`, for a struct `S` that isn't a resource.
+fn module() -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_struct("R", true, vec![], vec![("value", SignatureToken::U64)]);
+    builder.add_struct("S", false, vec![], vec![("value", SignatureToken::U64)]);
+    let self_handle = builder.add_module_handle(AccountAddress::default(), "M");
+    let r = builder.add_struct_handle(self_handle, "R", true, vec![]);
+    let s = builder.add_struct_handle(self_handle, "S", false, vec![]);
+    let unary = signature(vec![SignatureToken::U64], vec![SignatureToken::U64]);
+    let square = builder.add_function_handle(self_handle, "square", unary.clone());
+
+    builder.add_function(
+        "add",
+        CodeUnit::PUBLIC,
+        signature(
+            vec![SignatureToken::U64, SignatureToken::U64],
+            vec![SignatureToken::U64],
+        ),
+        vec![],
+        vec![],
+        code(vec![
+            Bytecode::CopyLoc(0),
+            Bytecode::CopyLoc(1),
+            Bytecode::Add,
+            Bytecode::Call(square, NO_TYPE_ACTUALS),
+            Bytecode::Ret,
+        ]),
+    );
+    builder.add_function(
+        "square",
+        0,
+        unary.clone(),
+        vec![],
+        vec![],
+        code(vec![
+            Bytecode::CopyLoc(0),
+            Bytecode::CopyLoc(0),
+            Bytecode::Mul,
+            Bytecode::LdConst(2),
+            Bytecode::Div,
+            Bytecode::Ret,
+        ]),
+    );
+    builder.add_function(
+        "double",
+        0,
+        unary,
+        vec![],
+        vec![],
+        code(vec![
+            Bytecode::CopyLoc(0),
+            Bytecode::CopyLoc(0),
+            Bytecode::Add,
+            Bytecode::Ret,
+        ]),
+    );
+    builder.add_function(
+        "count",
+        CodeUnit::PUBLIC,
+        signature(vec![SignatureToken::U64], vec![]),
+        vec![SignatureToken::U64],
+        vec![],
+        code(counting_loop(Bytecode::CopyLoc(0))),
+    );
+    builder.add_function(
+        "count_to_ten",
+        CodeUnit::PUBLIC,
+        signature(vec![SignatureToken::U64], vec![]),
+        vec![SignatureToken::U64],
+        vec![],
+        code(counting_loop(Bytecode::LdConst(10))),
+    );
+    for (name, handle) in &[("borrow_r", r), ("borrow_s", s)] {
+        let reference =
+            SignatureToken::Reference(Box::new(SignatureToken::Struct(*handle, vec![])));
+        builder.add_function(
+            name,
+            CodeUnit::PUBLIC,
+            signature(vec![reference.clone()], vec![reference]),
+            vec![],
+            vec![],
+            code(vec![Bytecode::MoveLoc(0), Bytecode::Ret]),
+        );
+    }
+    builder.build().expect("module is bounds-valid")
+}
+
+fn findings(errors: &[VerificationError]) -> Vec<(usize, VMStaticViolation)> {
+    assert!(errors
+        .iter()
+        .all(|error| error.severity() == Severity::Warning));
+    errors
+        .iter()
+        .map(|error| (error.idx, error.err.clone()))
+        .collect()
+}
+
+#[test]
+fn unchecked_arithmetic_reachable_from_public_functions() {
+    let errors = Linter::new()
+        .with_lint(UncheckedArithmeticLint)
+        .run(&module());
+    assert_eq!(
+        findings(&errors),
+        vec![
+            (0, VMStaticViolation::UncheckedArithmetic(2)),
+            (1, VMStaticViolation::UncheckedArithmetic(2)),
+            (3, VMStaticViolation::UncheckedArithmetic(8)),
+            (4, VMStaticViolation::UncheckedArithmetic(8)),
+        ]
+    );
+    assert_eq!(errors[0].code_offset, Some(2));
+}
+
+#[test]
+fn loops_bounded_by_arguments() {
+    let errors = Linter::new().with_lint(UnboundedLoopLint).run(&module());
+    assert_eq!(
+        findings(&errors),
+        vec![(3, VMStaticViolation::UnboundedLoop(2))]
+    );
+}
+
+#[test]
+fn resources_returned_by_reference() {
+    let errors = Linter::new()
+        .with_lint(ResourceReferenceLint)
+        .run(&module());
+    assert_eq!(
+        findings(&errors),
+        vec![(5, VMStaticViolation::ResourceReturnedByReference(0))]
+    );
+    assert_eq!(errors[0].code_offset, None);
+}
+
+#[test]
+fn builtin_lints_can_be_allowed() {
+    let linter = Linter::with_builtin_lints();
+    assert_eq!(
+        linter.lint_names(),
+        vec![
+            "unchecked_arithmetic",
+            "unbounded_loop",
+            "resource_reference_return"
+        ]
+    );
+    assert_eq!(linter.run(&module()).len(), 6);
+
+    let linter = linter.allow("unchecked_arithmetic");
+    assert_eq!(
+        findings(&linter.run(&module())),
+        vec![
+            (3, VMStaticViolation::UnboundedLoop(2)),
+            (5, VMStaticViolation::ResourceReturnedByReference(0)),
+        ]
+    );
+}
+
+/// Flags every function named `double`, with an existing warning.
+struct NoDouble;
+
+impl Lint for NoDouble {
+    fn name(&self) -> &'static str {
+        "no_double"
+    }
+
+    fn check(&self, module: &ModuleView<CompiledModule>) -> Vec<VerificationError> {
+        module
+            .functions()
+            .enumerate()
+            .filter(|(_, function)| function.name() == "double")
+            .map(|(idx, _)| {
+                VerificationError::new(
+                    IndexKind::FunctionDefinition,
+                    idx,
+                    VMStaticViolation::UnreachableBlock(0),
+                )
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn custom_lints() {
+    let linter = Linter::new().with_lint(NoDouble);
+    assert_eq!(linter.lint_names(), vec!["no_double"]);
+    assert_eq!(
+        findings(&linter.run(&module())),
+        vec![(2, VMStaticViolation::UnreachableBlock(0))]
+    );
+}
@@ -0,0 +1,97 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::CodeUnitVerifier;
+use invalid_mutations::locals::{ApplyLocalsContext, LocalsMutation};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    errors::{sort_errors, VMStaticViolation},
+    file_format::{
+        empty_module, Bytecode, CodeUnit, CompiledModuleMut, FunctionDefinition, FunctionHandle,
+        FunctionHandleIndex, FunctionSignature, FunctionSignatureIndex, LocalsSignature,
+        LocalsSignatureIndex, ModuleHandleIndex, SignatureToken, StringPoolIndex,
+    },
+};
+
+/// Builds a module whose functions take an integer as their argument, store it in a local that
+/// isn't an argument and then copy and move it out of there.
+fn locals_module(function_count: usize) -> CompiledModuleMut {
+    let mut module = empty_module();
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![SignatureToken::U64].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    module.function_handles.push(FunctionHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(0),
+        signature: FunctionSignatureIndex::new(0),
+    });
+    module.locals_signatures.push(LocalsSignature(
+        vec![SignatureToken::U64, SignatureToken::U64].into(),
+    ));
+    for _ in 0..function_count {
+        module.function_defs.push(FunctionDefinition {
+            function: FunctionHandleIndex::new(0),
+            code: CodeUnit {
+                locals: LocalsSignatureIndex::new(1),
+                code: vec![
+                    Bytecode::MoveLoc(0),
+                    Bytecode::StLoc(1),
+                    Bytecode::CopyLoc(1),
+                    Bytecode::Pop,
+                    Bytecode::MoveLoc(1),
+                    Bytecode::Pop,
+                    Bytecode::Ret,
+                ],
+                ..CodeUnit::default()
+            },
+            ..FunctionDefinition::default()
+        });
+    }
+    module
+}
+
+proptest! {
+    #[test]
+    fn valid_locals(function_count in 1usize..8) {
+        let module = locals_module(function_count)
+            .freeze()
+            .expect("should satisfy bounds checker");
+        prop_assert_eq!(CodeUnitVerifier::verify(&module), vec![]);
+    }
+
+    #[test]
+    fn misused_locals(
+        function_count in 1usize..8,
+        mutations in vec(LocalsMutation::strategy(), 0..10),
+    ) {
+        let mut module = locals_module(function_count);
+        let expected_violations = {
+            let context = ApplyLocalsContext::new(&mut module, mutations);
+            context.apply()
+        };
+        // Locals out of range keep the module from being frozen, so those are checked separately.
+        let (mut expected_bounds_violations, mut expected_violations): (Vec<_>, Vec<_>) =
+            expected_violations.into_iter().partition(|err| match &err.err {
+                VMStaticViolation::CodeUnitIndexOutOfBounds(..) => true,
+                _ => false,
+            });
+        sort_errors(&mut expected_bounds_violations);
+        sort_errors(&mut expected_violations);
+
+        match module.freeze() {
+            Ok(module) => {
+                prop_assert_eq!(expected_bounds_violations, vec![]);
+                let mut actual_violations = CodeUnitVerifier::verify(&module);
+                sort_errors(&mut actual_violations);
+                prop_assert_eq!(expected_violations, actual_violations);
+            }
+            Err(err) => {
+                let mut actual_violations = err.into_errors();
+                sort_errors(&mut actual_violations);
+                prop_assert_eq!(expected_bounds_violations, actual_violations);
+            }
+        }
+    }
+}
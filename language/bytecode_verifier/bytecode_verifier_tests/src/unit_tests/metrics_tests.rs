@@ -0,0 +1,71 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    verify_module_by_pass, verify_module_with_metrics, ConfigurablePass, VerifiedModule,
+    VerifierConfig,
+};
+use std::time::Duration;
+use vm::file_format::{dummy_procedure_module, Bytecode, CodeUnit, FunctionDefinitionIndex};
+
+#[test]
+fn metrics_cover_every_pass_that_ran() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let config = VerifierConfig::all();
+    let (errors, metrics) = verify_module_with_metrics(&module, &config);
+    assert!(errors.is_empty());
+    assert_eq!(metrics.total_errors(), 0);
+
+    let passes: Vec<_> = metrics.passes.keys().copied().collect();
+    assert_eq!(
+        passes,
+        vec![
+            ConfigurablePass::Duplication,
+            ConfigurablePass::StructuralLimits,
+            ConfigurablePass::Signature,
+            ConfigurablePass::Resources,
+            ConfigurablePass::RecursiveStructs,
+            ConfigurablePass::ControlFlow,
+            ConfigurablePass::StackUsage,
+            ConfigurablePass::StackHeight,
+            ConfigurablePass::Acquires,
+            ConfigurablePass::TypeSafety,
+        ]
+    );
+    assert!(metrics.passes.values().all(|pass| pass.runs == 1));
+    assert_eq!(
+        metrics.functions.keys().copied().collect::<Vec<_>>(),
+        vec![FunctionDefinitionIndex::new(0)]
+    );
+    let pass_time: Duration = metrics.passes.values().map(|pass| pass.time).sum();
+    assert!(metrics.total_time >= pass_time);
+}
+
+#[test]
+fn metrics_count_errors() {
+    let module = dummy_procedure_module(vec![Bytecode::Pop, Bytecode::Ret]);
+    let config = VerifierConfig::all();
+    let (errors, metrics) = verify_module_with_metrics(&module, &config);
+    // Recording metrics doesn't change the outcome.
+    assert_eq!(errors, verify_module_by_pass(&module, &config));
+
+    assert_eq!(metrics.total_errors(), 1);
+    assert_eq!(metrics.passes[&ConfigurablePass::StackUsage].errors, 1);
+    assert_eq!(
+        metrics.functions[&FunctionDefinitionIndex::new(0)].errors,
+        1
+    );
+    // Type safety depends on stack usage.
+    assert!(!metrics.passes.contains_key(&ConfigurablePass::TypeSafety));
+}
+
+#[test]
+fn native_functions_are_left_out() {
+    let mut module = dummy_procedure_module(vec![]).into_inner();
+    module.function_defs[0].flags |= CodeUnit::NATIVE;
+    let module = module.freeze().unwrap();
+    let (result, metrics) = VerifiedModule::new_with_metrics(module);
+    assert!(result.is_ok());
+    assert!(metrics.functions.is_empty());
+    assert!(metrics.slowest_functions(1).is_empty());
+}
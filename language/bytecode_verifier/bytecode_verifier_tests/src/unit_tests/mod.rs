@@ -1,9 +1,44 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod absint_tests;
+pub mod acquires_tests;
+pub mod borrow_graph_dump_tests;
 pub mod bounds_tests;
+pub mod cache_tests;
+pub mod call_graph_tests;
+pub mod classify_tests;
 pub mod code_unit_tests;
+pub mod compatibility_tests;
+pub mod complexity_tests;
+pub mod config_tests;
+pub mod control_flow_tests;
+pub mod coverage_tests;
+pub mod dataflow_tests;
+pub mod dependencies_tests;
+pub mod dominators_tests;
 pub mod duplication_tests;
+pub mod ffi_tests;
+pub mod global_storage_tests;
+pub mod incremental_tests;
+pub mod lint_tests;
+pub mod locals_tests;
+pub mod metrics_tests;
+pub mod module_cycles_tests;
+pub mod native_functions_tests;
+pub mod package_tests;
+pub mod pipeline_tests;
+pub mod publish_tests;
+pub mod reducibility_tests;
+pub mod report_tests;
 pub mod resources_tests;
+pub mod script_tests;
 pub mod signature_tests;
+pub mod stack_height_tests;
+pub mod stack_usage_tests;
 pub mod struct_defs_tests;
+pub mod structural_limits_tests;
+pub mod trace_tests;
+pub mod type_confusion_tests;
+pub mod undo_tests;
+pub mod unreachable_code_tests;
@@ -0,0 +1,92 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::DependencyCycleChecker;
+use proptest::prelude::*;
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{empty_module, AddressPoolIndex, CompiledModule, ModuleHandle, StringPoolIndex},
+    IndexKind,
+};
+
+/// Returns a module named `name` with a handle to each of `dependencies`, in order.
+fn module(name: &str, dependencies: &[&str]) -> CompiledModule {
+    let mut module = empty_module();
+    module.string_pool = vec![name.into()];
+    for dependency in dependencies {
+        module.module_handles.push(ModuleHandle {
+            address: AddressPoolIndex::new(0),
+            name: StringPoolIndex::new(module.string_pool.len() as u16),
+        });
+        module.string_pool.push((*dependency).into());
+    }
+    module.freeze().expect("should satisfy bounds checker")
+}
+
+fn module_id(name: &str) -> ModuleId {
+    ModuleId::new(AccountAddress::default(), name.to_string())
+}
+
+fn cycle_error(handle_idx: usize, cycle: &[&str]) -> VerificationError {
+    VerificationError::new(
+        IndexKind::ModuleHandle,
+        handle_idx,
+        VMStaticViolation::CyclicModuleDependency(
+            cycle.iter().map(|name| module_id(name)).collect(),
+        ),
+    )
+}
+
+proptest! {
+    #[test]
+    fn ring(len in 1usize..20) {
+        let names: Vec<_> = (0..len).map(|idx| format!("M{}", idx)).collect();
+        let modules: Vec<_> = (0..len)
+            .map(|idx| module(&names[idx], &[&names[(idx + 1) % len]]))
+            .collect();
+
+        // The ring is reported once, starting with the smallest module id.
+        let cycle: Vec<_> = names.iter().map(String::as_str).collect();
+        prop_assert_eq!(
+            DependencyCycleChecker::new(&modules).verify(),
+            vec![cycle_error(1, &cycle)]
+        );
+    }
+}
+
+#[test]
+fn acyclic() {
+    let modules = vec![
+        module("A", &["B", "C"]),
+        module("B", &["C"]),
+        module("C", &[]),
+    ];
+    assert_eq!(DependencyCycleChecker::new(&modules).verify(), vec![]);
+}
+
+#[test]
+fn self_dependency() {
+    let modules = vec![module("A", &["B", "A"]), module("B", &[])];
+    assert_eq!(
+        DependencyCycleChecker::new(&modules).verify(),
+        vec![cycle_error(2, &["A"])]
+    );
+}
+
+#[test]
+fn shortest_cycle_per_group() {
+    // A and B depend on each other, and so do C, D and E. The shortest cycle through C doesn't go
+    // through D. Dependencies on modules outside of the set are ignored.
+    let modules = vec![
+        module("E", &["C"]),
+        module("D", &["E"]),
+        module("C", &["X", "D", "E"]),
+        module("B", &["A"]),
+        module("A", &["B", "Y"]),
+    ];
+    assert_eq!(
+        DependencyCycleChecker::new(&modules).verify(),
+        vec![cycle_error(1, &["A", "B"]), cycle_error(3, &["C", "E"])]
+    );
+}
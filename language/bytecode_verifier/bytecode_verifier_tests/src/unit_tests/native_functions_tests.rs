@@ -0,0 +1,83 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{NativeFunctionChecker, NativeFunctionRegistry};
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        dummy_procedure_module, Bytecode, CodeUnit, CompiledModule, FunctionSignature,
+        SignatureToken,
+    },
+    IndexKind,
+};
+
+/// Returns a module defining a single native function, `<SELF>()`.
+fn native_module() -> CompiledModule {
+    let mut module = dummy_procedure_module(vec![]).into_inner();
+    module.function_defs[0].flags |= CodeUnit::NATIVE;
+    module.freeze().expect("should satisfy bounds checker")
+}
+
+fn signature(arg_types: Vec<SignatureToken>) -> FunctionSignature {
+    FunctionSignature {
+        return_types: vec![].into(),
+        arg_types: arg_types.into(),
+        type_formals: vec![],
+    }
+}
+
+#[test]
+fn permitted_native() {
+    let module = native_module();
+    let mut registry = NativeFunctionRegistry::new();
+    registry.insert(module.self_id(), "<SELF>", signature(vec![]));
+    assert_eq!(
+        NativeFunctionChecker::new(&module, &registry).verify(),
+        vec![]
+    );
+}
+
+#[test]
+fn unknown_native() {
+    let module = native_module();
+    let mut registry = NativeFunctionRegistry::new();
+    registry.insert(module.self_id(), "other", signature(vec![]));
+    assert_eq!(
+        NativeFunctionChecker::new(&module, &registry).verify(),
+        vec![VerificationError::new(
+            IndexKind::FunctionDefinition,
+            0,
+            VMStaticViolation::UnknownNativeFunction,
+        )]
+    );
+}
+
+#[test]
+fn native_signature_mismatch() {
+    let module = native_module();
+    let mut registry = NativeFunctionRegistry::new();
+    registry.insert(
+        module.self_id(),
+        "<SELF>",
+        signature(vec![SignatureToken::U64]),
+    );
+    assert_eq!(
+        NativeFunctionChecker::new(&module, &registry).verify(),
+        vec![VerificationError::new(
+            IndexKind::FunctionDefinition,
+            0,
+            VMStaticViolation::NativeFunctionSignatureMismatch,
+        )]
+    );
+}
+
+#[test]
+fn non_native_functions_are_ignored() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let registry = NativeFunctionRegistry::new();
+    assert_eq!(
+        NativeFunctionChecker::new(&module, &registry).verify(),
+        vec![]
+    );
+}
@@ -0,0 +1,62 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{PackageVerificationError, VerifiedModule, VerifiedPackage};
+use vm::{
+    access::ModuleAccess,
+    errors::VMStaticViolation,
+    package::{Package, PackageError},
+    test_helpers::{package_manifest, package_module, package_module_id},
+};
+
+#[test]
+fn modules_are_verified_against_each_other() {
+    let lib = VerifiedModule::new(package_module("Lib", &[])).unwrap();
+    let package = Package::new(
+        package_manifest(vec![]),
+        vec![
+            package_module("A", &["B", "Lib"]),
+            package_module("B", &["Lib"]),
+        ],
+    )
+    .unwrap();
+    let package = VerifiedPackage::new(package, vec![&lib]).unwrap();
+    let names: Vec<_> = package
+        .modules()
+        .iter()
+        .map(|module| module.self_id())
+        .collect();
+    assert_eq!(names, vec![package_module_id("B"), package_module_id("A")]);
+    assert_eq!(package.manifest(), &package_manifest(vec![]));
+}
+
+#[test]
+fn failures_name_the_module() {
+    // `Lib` is neither in the package nor among the dependencies.
+    let package = Package::new(
+        package_manifest(vec![]),
+        vec![package_module("A", &["B"]), package_module("B", &["Lib"])],
+    )
+    .unwrap();
+    match VerifiedPackage::new(package, vec![]) {
+        Err(PackageVerificationError::Module { module, errors }) => {
+            assert_eq!(module, package_module_id("B"));
+            assert!(errors
+                .iter()
+                .any(|error| error.err == VMStaticViolation::MissingDependency));
+        }
+        result => panic!("unexpected result: {:?}", result.map(|_| ())),
+    }
+
+    let package = Package::new(
+        package_manifest(vec![]),
+        vec![package_module("A", &["B"]), package_module("B", &["A"])],
+    )
+    .unwrap();
+    assert_eq!(
+        VerifiedPackage::new(package, vec![]),
+        Err(PackageVerificationError::Package(
+            PackageError::DependencyCycle(vec![package_module_id("A"), package_module_id("B")])
+        ))
+    );
+}
@@ -0,0 +1,42 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::SignatureChecker;
+use invalid_mutations::pipeline::MutationPipeline;
+use proptest::prelude::*;
+use vm::{
+    errors::{sort_errors, VMStaticViolation},
+    file_format::CompiledModule,
+};
+
+proptest! {
+    #[test]
+    fn combined_mutations(
+        module in CompiledModule::valid_strategy(20),
+        pipeline in MutationPipeline::strategy(10),
+    ) {
+        let (module, mut expected_violations) = pipeline.apply(module);
+        sort_errors(&mut expected_violations);
+
+        match module.freeze() {
+            Err(err) => {
+                let mut actual_violations = err.into_errors();
+                sort_errors(&mut actual_violations);
+                prop_assert_eq!(expected_violations, actual_violations);
+            }
+            Ok(module) => {
+                let mut actual_violations = SignatureChecker::new(&module).verify();
+                sort_errors(&mut actual_violations);
+                if actual_violations.is_empty() {
+                    // Modules from valid_strategy don't generally pass the code unit verifier, so
+                    // only check that the expected violations are left for it.
+                    prop_assert!(expected_violations
+                        .iter()
+                        .all(|err| err.err == VMStaticViolation::InvalidFallThrough));
+                } else {
+                    prop_assert_eq!(expected_violations, actual_violations);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{validate_publish, PublishIssue, PublishLimits, VerifiedModule};
+use std::collections::HashMap;
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+use vm::{
+    access::ModuleAccess,
+    file_format::CompiledModule,
+    package::{module_digest, Package, PackageDependency},
+    test_helpers::{package_manifest, package_module, package_module_id},
+};
+
+fn published(modules: Vec<CompiledModule>) -> HashMap<ModuleId, VerifiedModule> {
+    modules
+        .into_iter()
+        .map(|module| (module.self_id(), VerifiedModule::new(module).unwrap()))
+        .collect()
+}
+
+/// Pins `name` to the digest of `module`.
+fn pin(name: &str, module: &CompiledModule) -> PackageDependency {
+    PackageDependency {
+        module: package_module_id(name),
+        digest: module_digest(module).unwrap(),
+    }
+}
+
+#[test]
+fn pinned_package_is_publishable() {
+    let lib = package_module("Lib", &[]);
+    let package = Package::new(
+        package_manifest(vec![pin("Lib", &lib)]),
+        vec![
+            package_module("A", &["B", "Lib"]),
+            package_module("B", &["Lib"]),
+        ],
+    )
+    .unwrap();
+    let report =
+        validate_publish(&package, &published(vec![lib]), &PublishLimits::default()).unwrap();
+    assert!(report.is_publishable(), "{}", report);
+    assert_eq!(report.to_string(), "publishable");
+}
+
+#[test]
+fn every_issue_is_reported() {
+    let lib = package_module("Lib", &[]);
+    let stale = package_module("Lib", &["Other"]);
+    let package = Package::new(
+        package_manifest(vec![pin("Lib", &stale), pin("Gone", &lib), pin("B", &lib)]),
+        vec![
+            package_module("A", &["B", "Lib", "Unpinned"]),
+            package_module("B", &[]),
+            package_module("Lib2", &["Lib"]),
+        ],
+    )
+    .unwrap();
+    let published = published(vec![lib, package_module("Lib2", &[])]);
+    let limits = PublishLimits {
+        max_module_size: None,
+        max_package_size: Some(1),
+    };
+    let issues = validate_publish(&package, &published, &limits)
+        .unwrap()
+        .issues;
+
+    assert!(issues.contains(&PublishIssue::AlreadyPublished(package_module_id("Lib2"))));
+    assert!(issues.contains(&PublishIssue::PinnedPackageModule(package_module_id("B"))));
+    assert!(issues.contains(&PublishIssue::UnpinnedDependency {
+        module: package_module_id("A"),
+        dependency: package_module_id("Unpinned"),
+    }));
+    assert!(issues.contains(&PublishIssue::MissingDependency(package_module_id("Gone"))));
+    assert!(issues.contains(&PublishIssue::DigestMismatch {
+        dependency: package_module_id("Lib"),
+        pinned: module_digest(&stale).unwrap(),
+        published: module_digest(&package_module("Lib", &[])).unwrap(),
+    }));
+    match issues.iter().find_map(|issue| match issue {
+        PublishIssue::VerificationFailed { module, .. } => Some(module),
+        _ => None,
+    }) {
+        Some(module) => assert_eq!(module, &package_module_id("A")),
+        None => panic!("A should fail verification: {:?}", issues),
+    }
+    match issues.last() {
+        Some(PublishIssue::PackageTooLarge { max: 1, .. }) => (),
+        issue => panic!("unexpected last issue: {:?}", issue),
+    }
+}
+
+#[test]
+fn modules_must_be_under_the_package_address() {
+    let mut manifest = package_manifest(vec![]);
+    manifest.address = AccountAddress::new([1; 32]);
+    let package = Package::new(manifest, vec![package_module("A", &[])]).unwrap();
+    let report = validate_publish(&package, &published(vec![]), &PublishLimits::default()).unwrap();
+    assert_eq!(
+        report.issues,
+        vec![PublishIssue::AddressMismatch {
+            module: package_module_id("A"),
+            package_address: AccountAddress::new([1; 32]),
+        }]
+    );
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    control_flow_graph::VMControlFlowGraph, verify_module_with_config, LoopAnalysis,
+    ReducibilityChecker, VerifiedModule, VerifierConfig,
+};
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{dummy_procedure_module, Bytecode, FunctionDefinitionIndex},
+};
+
+/// Returns code whose entry block branches into both blocks of a two-block cycle.
+fn irreducible_code() -> Vec<Bytecode> {
+    vec![
+        // Block 0, which enters the cycle at both blocks.
+        Bytecode::LdTrue,
+        Bytecode::BrTrue(5),
+        // Block 2.
+        Bytecode::LdTrue,
+        Bytecode::BrTrue(5),
+        // Block 4, after the cycle.
+        Bytecode::Ret,
+        // Block 5.
+        Bytecode::Branch(2),
+    ]
+}
+
+#[test]
+fn nested_loops() {
+    let code = vec![
+        // Block 0, the outer loop header.
+        Bytecode::LdTrue,
+        Bytecode::BrFalse(6),
+        // Block 2, the inner loop header.
+        Bytecode::LdTrue,
+        Bytecode::BrFalse(5),
+        // Block 4, the latch of the inner loop.
+        Bytecode::Branch(2),
+        // Block 5, the latch of the outer loop.
+        Bytecode::Branch(0),
+        // Block 6, after the loops.
+        Bytecode::Ret,
+    ];
+    let analysis = LoopAnalysis::new(&VMControlFlowGraph::new(&code));
+    assert!(analysis.is_reducible());
+    assert_eq!(analysis.back_edges(), &[(4, 2), (5, 0)]);
+    assert_eq!(analysis.loop_headers(), vec![0, 2]);
+    assert!(analysis.irreducible_edges().is_empty());
+}
+
+#[test]
+fn irreducible_cycle() {
+    let code = irreducible_code();
+    let analysis = LoopAnalysis::new(&VMControlFlowGraph::new(&code));
+    assert!(!analysis.is_reducible());
+    assert!(analysis.back_edges().is_empty());
+    assert_eq!(analysis.irreducible_edges(), &[(5, 2)]);
+
+    let module = dummy_procedure_module(code);
+    assert_eq!(
+        ReducibilityChecker::new(&module).verify(),
+        vec![VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::IrreducibleControlFlow(5),
+        )]
+    );
+}
+
+#[test]
+fn rejected_only_when_enabled() {
+    let module = dummy_procedure_module(irreducible_code());
+    assert!(VerifiedModule::new(module.clone()).is_ok());
+    assert!(verify_module_with_config(&module, &VerifierConfig::all()).is_empty());
+
+    let config = VerifierConfig {
+        reducibility: true,
+        ..VerifierConfig::all()
+    };
+    let errors = verify_module_with_config(&module, &config);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].err, VMStaticViolation::IrreducibleControlFlow(5));
+}
@@ -0,0 +1,62 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{ConfigurablePass, PassStatus, VerifiedModule};
+use vm::file_format::{dummy_procedure_module, Bytecode};
+
+#[test]
+fn passing_module() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let (_, report) = VerifiedModule::new_with_report(module).expect("module should verify");
+    assert!(report.passed());
+    assert!(report.errors().is_empty());
+    for pass in ConfigurablePass::all() {
+        let expected = match pass {
+            // Not enabled by `VerifiedModule::new`.
+            ConfigurablePass::UnreachableCode
+            | ConfigurablePass::Reducibility
+            | ConfigurablePass::GlobalStorage => PassStatus::Skipped,
+            _ => PassStatus::Passed,
+        };
+        assert_eq!(report.status(*pass), expected, "{:?}", pass);
+    }
+}
+
+#[test]
+fn failing_module() {
+    let module = dummy_procedure_module(vec![Bytecode::Pop, Bytecode::Ret]);
+    let (_, errors) = VerifiedModule::new(module.clone()).unwrap_err();
+    let (_, report) = VerifiedModule::new_with_report(module).unwrap_err();
+    assert!(!report.passed());
+    assert_eq!(report.errors(), errors);
+
+    assert_eq!(
+        report.status(ConfigurablePass::ControlFlow),
+        PassStatus::Passed
+    );
+    assert_eq!(
+        report.status(ConfigurablePass::StackUsage),
+        PassStatus::Failed
+    );
+    // Every later pass is skipped once one fails.
+    assert_eq!(
+        report.status(ConfigurablePass::StackHeight),
+        PassStatus::Skipped
+    );
+    assert_eq!(
+        report.status(ConfigurablePass::Acquires),
+        PassStatus::Skipped
+    );
+    assert_eq!(
+        report.status(ConfigurablePass::TypeSafety),
+        PassStatus::Skipped
+    );
+
+    let stack_usage = report
+        .passes
+        .iter()
+        .find(|pass_report| pass_report.pass == ConfigurablePass::StackUsage)
+        .unwrap();
+    assert_eq!(stack_usage.errors, errors);
+    assert!(report.to_string().contains("StackUsage: failed\n    "));
+}
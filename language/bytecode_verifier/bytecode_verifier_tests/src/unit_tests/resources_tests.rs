@@ -1,9 +1,23 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use bytecode_verifier::ResourceTransitiveChecker;
-use proptest::prelude::*;
-use vm::file_format::CompiledModule;
+use bytecode_verifier::{CodeUnitVerifier, ResourceTransitiveChecker};
+use invalid_mutations::resources::{
+    ApplyResourceCodeContext, ApplyResourceFieldContext, ResourceCodeMutation,
+    ResourceFieldMutation,
+};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    errors::sort_errors,
+    file_format::{
+        empty_module, Bytecode, CodeUnit, CompiledModule, CompiledModuleMut, FieldDefinition,
+        FieldDefinitionIndex, FunctionDefinition, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, LocalsSignature, LocalsSignatureIndex,
+        ModuleHandleIndex, SignatureToken, StringPoolIndex, StructDefinition,
+        StructDefinitionIndex, StructFieldInformation, StructHandle, StructHandleIndex,
+        TypeSignature, TypeSignatureIndex, NO_TYPE_ACTUALS,
+    },
+};
 
 proptest! {
     #[test]
@@ -11,4 +25,114 @@ proptest! {
         let resource_checker = ResourceTransitiveChecker::new(&module);
         prop_assert!(resource_checker.verify().is_empty());
     }
+
+    #[test]
+    fn resource_fields(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(ResourceFieldMutation::strategy(), 0..40),
+    ) {
+        let mut module = module.into_inner();
+        let mut expected_violations = {
+            let context = ApplyResourceFieldContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let resource_checker = ResourceTransitiveChecker::new(&module);
+        let mut actual_violations = resource_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
+
+/// Builds a module whose functions take a resource as their argument and move it around before
+/// unpacking it.
+fn resource_module(function_count: usize) -> CompiledModuleMut {
+    let mut module = empty_module();
+    module.string_pool.push("R".into());
+    module.string_pool.push("f".into());
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(1),
+        is_nominal_resource: true,
+        type_formals: vec![],
+    });
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    module.field_defs.push(FieldDefinition {
+        struct_: StructHandleIndex::new(0),
+        name: StringPoolIndex::new(2),
+        signature: TypeSignatureIndex::new(0),
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+
+    let resource = SignatureToken::Struct(StructHandleIndex::new(0), vec![]);
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![resource.clone()].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    module.function_handles.push(FunctionHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(0),
+        signature: FunctionSignatureIndex::new(0),
+    });
+    module
+        .locals_signatures
+        .push(LocalsSignature(vec![resource.clone(), resource].into()));
+    for _ in 0..function_count {
+        module.function_defs.push(FunctionDefinition {
+            function: FunctionHandleIndex::new(0),
+            code: CodeUnit {
+                locals: LocalsSignatureIndex::new(1),
+                code: vec![
+                    Bytecode::MoveLoc(0),
+                    Bytecode::StLoc(1),
+                    Bytecode::MoveLoc(1),
+                    Bytecode::Unpack(StructDefinitionIndex::new(0), NO_TYPE_ACTUALS),
+                    Bytecode::Pop,
+                    Bytecode::Ret,
+                ],
+                ..CodeUnit::default()
+            },
+            ..FunctionDefinition::default()
+        });
+    }
+    module
+}
+
+proptest! {
+    #[test]
+    fn valid_resource_usage(function_count in 1usize..8) {
+        let module = resource_module(function_count)
+            .freeze()
+            .expect("should satisfy bounds checker");
+        prop_assert_eq!(CodeUnitVerifier::verify(&module), vec![]);
+    }
+
+    #[test]
+    fn copied_and_dropped_resources(
+        function_count in 1usize..8,
+        mutations in vec(ResourceCodeMutation::strategy(), 0..10),
+    ) {
+        let mut module = resource_module(function_count);
+        let mut expected_violations = {
+            let context = ApplyResourceCodeContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let mut actual_violations = CodeUnitVerifier::verify(&module);
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
 }
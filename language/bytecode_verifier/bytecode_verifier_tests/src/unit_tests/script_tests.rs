@@ -0,0 +1,242 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{verify_main_signature, ScriptSignatureChecker, VerifiedScript};
+use invalid_mutations::{
+    bounds::{
+        ApplyCodeUnitBoundsContext, ApplyOutOfBoundsContext, CodeUnitBoundsMutation,
+        OutOfBoundsMutation,
+    },
+    script::{ApplyMainSignatureContext, MainSignatureMutation},
+};
+use proptest::{collection::vec, prelude::*, sample::Index as PropIndex};
+use types::{account_address::AccountAddress, byte_array::ByteArray};
+use vm::{
+    check_bounds::BoundsChecker,
+    errors::{sort_errors, VMStaticViolation, VerificationError},
+    file_format::{
+        dummy_procedure_module, AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CodeUnit,
+        CompiledScript, CompiledScriptMut, FunctionDefinition, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, Kind, LocalsSignature, LocalsSignatureIndex,
+        ModuleHandle, ModuleHandleIndex, SignatureToken, StringPoolIndex, StructHandle,
+        StructHandleIndex, TypeSignature, NO_TYPE_ACTUALS, SELF_MODULE_NAME,
+    },
+    IndexKind,
+};
+
+fn primitive_strategy() -> impl Strategy<Value = SignatureToken> {
+    prop_oneof![
+        Just(SignatureToken::Bool),
+        Just(SignatureToken::U64),
+        Just(SignatureToken::String),
+        Just(SignatureToken::ByteArray),
+        Just(SignatureToken::Address),
+    ]
+}
+
+/// Generates scripts whose `main` takes primitive arguments and whose code refers to every kind of
+/// table a script has.
+fn valid_script_strategy() -> impl Strategy<Value = CompiledScript> {
+    (
+        vec(primitive_strategy(), 0..4),
+        vec(any::<PropIndex>(), 0..20),
+    )
+        .prop_map(|(arg_types, code)| {
+            let bytecodes = [
+                Bytecode::LdAddr(AddressPoolIndex::new(0)),
+                Bytecode::LdStr(StringPoolIndex::new(0)),
+                Bytecode::LdByteArray(ByteArrayPoolIndex::new(0)),
+                Bytecode::Call(FunctionHandleIndex::new(0), NO_TYPE_ACTUALS),
+                Bytecode::Branch(0),
+                Bytecode::CopyLoc(0),
+                Bytecode::StLoc(0),
+            ];
+            let mut code: Vec<_> = code.iter().map(|idx| idx.get(&bytecodes).clone()).collect();
+            code.push(Bytecode::Ret);
+
+            // Locals start with the arguments, and always have at least one entry.
+            let mut locals = arg_types.clone();
+            locals.push(SignatureToken::U64);
+            let script = CompiledScriptMut {
+                module_handles: vec![
+                    ModuleHandle {
+                        address: AddressPoolIndex::new(0),
+                        name: StringPoolIndex::new(0),
+                    },
+                    ModuleHandle {
+                        address: AddressPoolIndex::new(0),
+                        name: StringPoolIndex::new(1),
+                    },
+                ],
+                struct_handles: vec![StructHandle {
+                    module: ModuleHandleIndex::new(1),
+                    name: StringPoolIndex::new(2),
+                    is_nominal_resource: false,
+                    type_formals: vec![],
+                }],
+                function_handles: vec![FunctionHandle {
+                    module: ModuleHandleIndex::new(0),
+                    name: StringPoolIndex::new(3),
+                    signature: FunctionSignatureIndex::new(0),
+                }],
+                type_signatures: vec![TypeSignature(SignatureToken::Struct(
+                    StructHandleIndex::new(0),
+                    vec![],
+                ))],
+                function_signatures: vec![FunctionSignature {
+                    arg_types: arg_types.into(),
+                    return_types: vec![].into(),
+                    type_formals: vec![],
+                }],
+                locals_signatures: vec![LocalsSignature(locals.into())],
+                string_pool: vec![
+                    SELF_MODULE_NAME.into(),
+                    "M".into(),
+                    "T".into(),
+                    "main".into(),
+                ],
+                byte_array_pool: vec![ByteArray::new(vec![0])],
+                address_pool: vec![AccountAddress::default()],
+                main: FunctionDefinition {
+                    function: FunctionHandleIndex::new(0),
+                    code: CodeUnit {
+                        locals: LocalsSignatureIndex::new(0),
+                        code,
+                        ..CodeUnit::default()
+                    },
+                    ..FunctionDefinition::default()
+                },
+            };
+            script.freeze().expect("should satisfy bounds checker")
+        })
+}
+
+proptest! {
+    #[test]
+    fn valid_main_signature(script in valid_script_strategy()) {
+        prop_assert_eq!(verify_main_signature(&script), vec![]);
+    }
+
+    #[test]
+    fn invalid_main_signature(
+        script in valid_script_strategy(),
+        mutations in vec(MainSignatureMutation::strategy(), 0..10),
+    ) {
+        let mut script = script.into_inner();
+        let expected_violations = {
+            let context = ApplyMainSignatureContext::new(&mut script, mutations);
+            context.apply()
+        };
+        let script = script.freeze().expect("should satisfy bounds checker");
+        prop_assert_eq!(expected_violations, verify_main_signature(&script));
+    }
+
+    #[test]
+    fn generic_main_signature(script in valid_script_strategy()) {
+        let mut script = script.into_inner();
+        script.function_signatures[0].type_formals.push(Kind::All);
+        let script = script.freeze().expect("should satisfy bounds checker");
+        prop_assert_eq!(ScriptSignatureChecker::new(&script).verify(), vec![]);
+    }
+
+    #[test]
+    fn main_signature_errors_are_located(script in valid_script_strategy()) {
+        let mut script = script.into_inner();
+        let signature = &mut script.function_signatures[0];
+        signature.return_types = vec![SignatureToken::U64, SignatureToken::Bool].into();
+        signature.arg_types = vec![
+            SignatureToken::U64,
+            SignatureToken::Reference(Box::new(SignatureToken::U64)),
+            SignatureToken::Address,
+            SignatureToken::Struct(StructHandleIndex::new(0), vec![]),
+        ]
+        .into();
+        let script = script.freeze().expect("should satisfy bounds checker");
+
+        let expected: Vec<_> = vec![
+            VMStaticViolation::InvalidMainFunctionReturn(0),
+            VMStaticViolation::InvalidMainFunctionReturn(1),
+            VMStaticViolation::InvalidMainFunctionArgument(
+                1,
+                SignatureToken::Reference(Box::new(SignatureToken::U64)),
+            ),
+            VMStaticViolation::InvalidMainFunctionArgument(
+                3,
+                SignatureToken::Struct(StructHandleIndex::new(0), vec![]),
+            ),
+        ]
+        .into_iter()
+        .map(|err| VerificationError::new(IndexKind::FunctionSignature, 0, err))
+        .collect();
+        prop_assert_eq!(&ScriptSignatureChecker::new(&script).verify(), &expected);
+
+        // The script itself fails verification with every one of these errors.
+        let errors = match VerifiedScript::new(script) {
+            Ok(_) => vec![],
+            Err((_, errors)) => errors,
+        };
+        for err in &expected {
+            prop_assert!(errors.contains(err));
+        }
+    }
+
+    #[test]
+    fn script_out_of_bounds(
+        script in valid_script_strategy(),
+        oob_mutations in vec(OutOfBoundsMutation::strategy(), 0..40),
+    ) {
+        let (module, mut expected_violations) = {
+            let oob_context = ApplyOutOfBoundsContext::new_script(script, oob_mutations);
+            oob_context.apply()
+        };
+        sort_errors(&mut expected_violations);
+
+        let bounds_checker = BoundsChecker::new(&module);
+        let mut actual_violations = bounds_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(&expected_violations, &actual_violations);
+
+        // Errors are reported the same way for the script itself.
+        let script = module.into_script();
+        let mut actual_violations = script
+            .freeze()
+            .err()
+            .map_or_else(Vec::new, |err| err.into_errors());
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+
+    #[test]
+    fn script_code_unit_out_of_bounds(
+        script in valid_script_strategy(),
+        mutations in vec(CodeUnitBoundsMutation::strategy(), 0..40),
+    ) {
+        let mut module = script.into_inner().into_module();
+        let mut expected_violations = {
+            let context = ApplyCodeUnitBoundsContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+
+        let script = module.into_script();
+        let mut actual_violations = script
+            .freeze()
+            .err()
+            .map_or_else(Vec::new, |err| err.into_errors());
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
+
+#[test]
+fn generic_main_still_verifies() {
+    // Scripts whose `main` has type formals verified before `ScriptSignatureChecker` existed.
+    // Rejecting them would be a consensus change, so they must keep verifying.
+    let mut module = dummy_procedure_module(vec![Bytecode::Ret]).into_inner();
+    module.function_signatures[0].type_formals.push(Kind::All);
+    let script = module
+        .into_script()
+        .freeze()
+        .expect("should satisfy bounds checker");
+    assert!(VerifiedScript::new(script).is_ok());
+}
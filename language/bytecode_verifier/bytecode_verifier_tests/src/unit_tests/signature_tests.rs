@@ -1,13 +1,21 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use bytecode_verifier::SignatureChecker;
+use bytecode_verifier::{SignatureChecker, VerifiedModule};
 use invalid_mutations::signature::{
-    ApplySignatureDoubleRefContext, ApplySignatureFieldRefContext, DoubleRefMutation,
-    FieldRefMutation,
+    ApplyMalformedTokenContext, ApplySignatureDoubleRefContext, ApplySignatureFieldRefContext,
+    DoubleRefMutation, FieldRefMutation, MalformedTokenMutation,
 };
 use proptest::{collection::vec, prelude::*};
-use vm::{errors::VMStaticViolation, file_format::CompiledModule};
+use vm::{
+    errors::{sort_errors, VMStaticViolation},
+    file_format::{
+        empty_module, CompiledModule, FieldDefinition, FieldDefinitionIndex, FunctionSignature,
+        ModuleHandleIndex, SignatureToken, StringPoolIndex, StructDefinition,
+        StructFieldInformation, StructHandle, StructHandleIndex, TypeSignature, TypeSignatureIndex,
+    },
+    IndexKind,
+};
 
 proptest! {
     #[test]
@@ -26,7 +34,7 @@ proptest! {
             let context = ApplySignatureDoubleRefContext::new(&mut module, mutations);
             context.apply()
         };
-        expected_violations.sort();
+        sort_errors(&mut expected_violations);
         let module = module.freeze().expect("should satisfy bounds checker");
 
         let signature_checker = SignatureChecker::new(&module);
@@ -42,7 +50,7 @@ proptest! {
                 _ => true,
             })
             .collect();
-        actual_violations.sort();
+        sort_errors(&mut actual_violations);
         prop_assert_eq!(expected_violations, actual_violations);
     }
 
@@ -56,7 +64,7 @@ proptest! {
             let context = ApplySignatureFieldRefContext::new(&mut module, mutations);
             context.apply()
         };
-        expected_violations.sort();
+        sort_errors(&mut expected_violations);
         let module = module.freeze().expect("should satisfy bounds checker");
 
         let signature_checker = SignatureChecker::new(&module);
@@ -64,7 +72,85 @@ proptest! {
         let mut actual_violations = signature_checker.verify();
         // Note that this shouldn't cause any InvalidSignatureToken errors because there are no
         // double references involved. So no filtering is required here.
-        actual_violations.sort();
+        sort_errors(&mut actual_violations);
         prop_assert_eq!(expected_violations, actual_violations);
     }
+
+    #[test]
+    fn malformed_tokens(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(MalformedTokenMutation::strategy(), 0..40),
+    ) {
+        let mut module = module.into_inner();
+        let mut expected_violations = {
+            let context = ApplyMalformedTokenContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let signature_checker = SignatureChecker::new(&module);
+
+        // Mutations that target fields and mutations that target other tokens never touch the
+        // same signature, so no filtering is required here.
+        let mut actual_violations = signature_checker.verify();
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
+
+#[test]
+fn errors_say_which_token_is_malformed() {
+    use SignatureToken::*;
+
+    // struct S { f: &u64 }, and a function signature whose second argument is &&u64.
+    let mut module = empty_module();
+    module.string_pool.push("S".into());
+    module.string_pool.push("f".into());
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(1),
+        is_nominal_resource: false,
+        type_formals: vec![],
+    });
+    module
+        .type_signatures
+        .push(TypeSignature(Reference(Box::new(U64))));
+    module.field_defs.push(FieldDefinition {
+        struct_: StructHandleIndex::new(0),
+        name: StringPoolIndex::new(2),
+        signature: TypeSignatureIndex::new(0),
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![U64, Reference(Box::new(Reference(Box::new(U64))))].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let (_, errors) = VerifiedModule::new(module).unwrap_err();
+    let field_error = errors
+        .iter()
+        .find(|err| err.kind == IndexKind::FieldDefinition)
+        .expect("the field should be rejected");
+    assert_eq!(field_error.context, vec!["field f".to_string()]);
+    assert!(field_error
+        .to_string()
+        .ends_with("(while checking field f)"));
+
+    let signature_error = errors
+        .iter()
+        .find(|err| err.kind == IndexKind::FunctionSignature)
+        .expect("the function signature should be rejected");
+    assert_eq!(signature_error.context, vec!["argument 1".to_string()]);
+    assert!(signature_error
+        .to_string()
+        .ends_with("(while checking argument 1)"));
 }
@@ -0,0 +1,91 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    control_flow_graph::VMControlFlowGraph, verify_module_with_config, StackHeightLimit,
+    StackHeightVerifier, VerifierConfig,
+};
+use proptest::prelude::*;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{dummy_procedure_module, Bytecode, CompiledModule, FunctionDefinitionIndex},
+};
+
+/// Returns code that pushes `height` values in a block of its own, then pops them all.
+fn stack_of_height(height: usize) -> Vec<Bytecode> {
+    let mut code = vec![Bytecode::Branch(1)];
+    code.extend((0..height).map(|_| Bytecode::LdTrue));
+    code.extend((0..height).map(|_| Bytecode::Pop));
+    code.push(Bytecode::Ret);
+    code
+}
+
+fn verify_stack_height(module: &CompiledModule, limit: StackHeightLimit) -> Vec<VMStaticViolation> {
+    let function_def = &module.as_inner().function_defs[0];
+    let cfg = VMControlFlowGraph::new(&function_def.code.code);
+    StackHeightVerifier::verify(module, function_def, &cfg, limit)
+}
+
+proptest! {
+    #[test]
+    fn fixed_limit(height in 0usize..20, max_height in 0u16..20) {
+        let module = dummy_procedure_module(stack_of_height(height));
+        let errors = verify_stack_height(&module, StackHeightLimit::Fixed(max_height));
+        if height <= max_height as usize {
+            prop_assert_eq!(errors, vec![]);
+        } else {
+            // The first instruction going over the limit is reported.
+            let offset = max_height as usize + 1;
+            prop_assert_eq!(errors, vec![VMStaticViolation::StackHeightLimitExceeded(offset)]);
+        }
+    }
+}
+
+#[test]
+fn declared_limit() {
+    let mut module = dummy_procedure_module(stack_of_height(3)).into_inner();
+    module.function_defs[0].code.max_stack_size = 2;
+    let module = module.freeze().expect("should satisfy bounds checker");
+    assert_eq!(
+        verify_stack_height(&module, StackHeightLimit::Declared),
+        vec![VMStaticViolation::StackHeightLimitExceeded(3)]
+    );
+
+    let mut module = module.into_inner();
+    module.function_defs[0].code.max_stack_size = 3;
+    let module = module.freeze().expect("should satisfy bounds checker");
+    assert_eq!(
+        verify_stack_height(&module, StackHeightLimit::Declared),
+        vec![]
+    );
+}
+
+#[test]
+fn configured_limit() {
+    let module = dummy_procedure_module(stack_of_height(3));
+    // The pass is opt-in, and unlimited by default.
+    assert!(!VerifierConfig::all().stack_height);
+    let config = VerifierConfig {
+        stack_height: true,
+        ..VerifierConfig::all()
+    };
+    assert_eq!(verify_module_with_config(&module, &config), vec![]);
+
+    let config = VerifierConfig {
+        max_stack_height: StackHeightLimit::Fixed(2),
+        ..config
+    };
+    assert_eq!(
+        verify_module_with_config(&module, &config),
+        vec![VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::StackHeightLimitExceeded(3),
+        )]
+    );
+
+    let config = VerifierConfig {
+        stack_height: false,
+        ..config
+    };
+    assert_eq!(verify_module_with_config(&module, &config), vec![]);
+}
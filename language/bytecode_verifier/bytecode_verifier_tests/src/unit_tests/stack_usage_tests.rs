@@ -0,0 +1,81 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{control_flow_graph::VMControlFlowGraph, StackUsageVerifier};
+use invalid_mutations::stack_usage::{ApplyStackUsageContext, StackUsageMutation};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    access::ModuleAccess,
+    errors::{sort_errors, VerificationError},
+    file_format::{Bytecode, CompiledModule, CompiledModuleMut, FunctionDefinitionIndex},
+};
+
+/// Replaces the body of every function with code that uses the stack in a balanced manner.
+fn balance_functions(module: &mut CompiledModuleMut) {
+    for function_def in &mut module.function_defs {
+        if function_def.is_native() {
+            continue;
+        }
+        let handle = &module.function_handles[function_def.function.0 as usize];
+        let return_count = module.function_signatures[handle.signature.0 as usize]
+            .return_types
+            .len();
+        // The branch splits the body into several basic blocks.
+        let mut code = vec![
+            Bytecode::LdTrue,
+            Bytecode::BrTrue(4),
+            Bytecode::LdFalse,
+            Bytecode::Pop,
+        ];
+        code.extend((0..return_count).map(|_| Bytecode::LdTrue));
+        code.push(Bytecode::Ret);
+        function_def.code.code = code;
+    }
+}
+
+fn verify_stack_usage(module: &CompiledModule) -> Vec<VerificationError> {
+    let mut errors = vec![];
+    for (idx, function_def) in module.function_defs().iter().enumerate() {
+        if function_def.is_native() {
+            continue;
+        }
+        let cfg = VMControlFlowGraph::new(&function_def.code.code);
+        errors.extend(
+            StackUsageVerifier::verify(module, function_def, &cfg)
+                .into_iter()
+                .map(|err| {
+                    VerificationError::in_function(FunctionDefinitionIndex::new(idx as u16), err)
+                }),
+        );
+    }
+    errors
+}
+
+proptest! {
+    #[test]
+    fn balanced_stack(module in CompiledModule::valid_strategy(20)) {
+        let mut module = module.into_inner();
+        balance_functions(&mut module);
+        let module = module.freeze().expect("should satisfy bounds checker");
+        prop_assert_eq!(verify_stack_usage(&module), vec![]);
+    }
+
+    #[test]
+    fn unbalanced_stack(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(StackUsageMutation::strategy(), 0..40),
+    ) {
+        let mut module = module.into_inner();
+        balance_functions(&mut module);
+        let mut expected_violations = {
+            let context = ApplyStackUsageContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let mut actual_violations = verify_stack_usage(&module);
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
@@ -1,9 +1,17 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use bytecode_verifier::RecursiveStructDefChecker;
-use proptest::prelude::*;
-use vm::file_format::CompiledModule;
+use bytecode_verifier::{RecursiveStructDefChecker, VerifiedModule};
+use invalid_mutations::struct_defs::{ApplyRecursiveStructContext, RecursiveStructMutation};
+use proptest::{collection::vec, prelude::*};
+use vm::{
+    errors::VMStaticViolation,
+    file_format::{
+        empty_module, CompiledModule, FieldDefinition, FieldDefinitionIndex, ModuleHandleIndex,
+        SignatureToken, StringPoolIndex, StructDefinition, StructFieldInformation, StructHandle,
+        StructHandleIndex, TypeSignature, TypeSignatureIndex,
+    },
+};
 
 proptest! {
     #[test]
@@ -11,4 +19,64 @@ proptest! {
         let recursive_checker = RecursiveStructDefChecker::new(&module);
         prop_assert!(recursive_checker.verify().is_empty());
     }
+
+    #[test]
+    fn recursive_struct_defs(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(RecursiveStructMutation::strategy(), 0..10),
+    ) {
+        let mut module = module.into_inner();
+        let expected = {
+            let context = ApplyRecursiveStructContext::new(&mut module, mutations);
+            context.apply_expected()
+        };
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let recursive_checker = RecursiveStructDefChecker::new(&module);
+        let actual_violations = recursive_checker.verify();
+        // The checker only reports one struct definition, and only if there is a cycle at all.
+        prop_assert_eq!(expected.primary, actual_violations);
+        prop_assert!(expected.primary.len() == 1 || expected.secondary.is_empty());
+    }
+}
+
+#[test]
+fn errors_name_the_recursive_struct() {
+    // struct S { f: S }
+    let mut module = empty_module();
+    module.string_pool.push("S".into());
+    module.string_pool.push("f".into());
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(1),
+        is_nominal_resource: false,
+        type_formals: vec![],
+    });
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::Struct(
+            StructHandleIndex::new(0),
+            vec![],
+        )));
+    module.field_defs.push(FieldDefinition {
+        struct_: StructHandleIndex::new(0),
+        name: StringPoolIndex::new(2),
+        signature: TypeSignatureIndex::new(0),
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+    let module = module.freeze().expect("should satisfy bounds checker");
+
+    let (_, errors) = VerifiedModule::new(module).unwrap_err();
+    let error = errors
+        .iter()
+        .find(|err| err.err == VMStaticViolation::RecursiveStructDef)
+        .expect("S should be rejected");
+    assert_eq!(error.context, vec!["struct S".to_string()]);
+    assert!(error.to_string().ends_with("(while checking struct S)"));
 }
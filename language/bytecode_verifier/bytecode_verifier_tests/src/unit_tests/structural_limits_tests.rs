@@ -0,0 +1,212 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    verify_module_by_pass, ConfigurablePass, StructuralLimits, StructuralLimitsChecker,
+    VerifierConfig,
+};
+use proptest::prelude::*;
+use stdlib::stdlib_modules;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        dummy_procedure_module, empty_module, Bytecode, CompiledModule, FieldDefinition,
+        FieldDefinitionIndex, FunctionDefinitionIndex, Kind, ModuleHandleIndex, SignatureToken,
+        StringPoolIndex, StructDefinition, StructFieldInformation, StructHandle, StructHandleIndex,
+        TypeSignature, TypeSignatureIndex,
+    },
+    IndexKind,
+};
+
+/// Returns a module defining a struct `S` with `type_formals` type formals and `field_count`
+/// distinct fields of type `field_type`.
+fn struct_module(
+    type_formals: usize,
+    field_count: usize,
+    field_type: SignatureToken,
+) -> CompiledModule {
+    let mut module = empty_module();
+    module.string_pool.push("S".into());
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(1),
+        is_nominal_resource: false,
+        type_formals: vec![Kind::All; type_formals],
+    });
+    module.type_signatures.push(TypeSignature(field_type));
+    for idx in 0..field_count {
+        module.field_defs.push(FieldDefinition {
+            struct_: StructHandleIndex::new(0),
+            name: StringPoolIndex::new(module.string_pool.len() as u16),
+            signature: TypeSignatureIndex::new(0),
+        });
+        module.string_pool.push(format!("f{}", idx).into());
+    }
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: field_count as u16,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+    module.freeze().expect("should satisfy bounds checker")
+}
+
+/// Returns `S<S<...<u64>...>>`, with `depth` instantiations of `S`.
+fn nested_instantiation(depth: usize) -> SignatureToken {
+    (0..depth).fold(SignatureToken::U64, |token, _| {
+        SignatureToken::Struct(StructHandleIndex::new(0), vec![token])
+    })
+}
+
+proptest! {
+    #[test]
+    fn basic_blocks(blocks in 1usize..20, max in 1usize..20) {
+        // Every branch starts a new block.
+        let mut code: Vec<_> = (1..blocks).map(|idx| Bytecode::Branch(idx as u16)).collect();
+        code.push(Bytecode::Ret);
+        let module = dummy_procedure_module(code);
+        let limits = StructuralLimits {
+            max_basic_blocks: Some(max),
+            ..StructuralLimits::unlimited()
+        };
+
+        let errors = StructuralLimitsChecker::new(&module, &limits).verify();
+        if blocks <= max {
+            prop_assert_eq!(errors, vec![]);
+        } else {
+            prop_assert_eq!(
+                errors,
+                vec![VerificationError::in_function(
+                    FunctionDefinitionIndex::new(0),
+                    VMStaticViolation::TooManyBasicBlocks,
+                )]
+            );
+        }
+    }
+
+    #[test]
+    fn type_instantiation_depth(depth in 0usize..10, max in 0usize..10) {
+        let module = struct_module(1, 1, nested_instantiation(depth));
+        let limits = StructuralLimits {
+            max_type_instantiation_depth: Some(max),
+            ..StructuralLimits::unlimited()
+        };
+
+        let errors = StructuralLimitsChecker::new(&module, &limits).verify();
+        if depth <= max {
+            prop_assert_eq!(errors, vec![]);
+        } else {
+            prop_assert_eq!(
+                errors,
+                vec![VerificationError::new(
+                    IndexKind::TypeSignature,
+                    0,
+                    VMStaticViolation::TypeInstantiationTooDeep,
+                )]
+            );
+        }
+    }
+}
+
+#[test]
+fn references_are_not_instantiations() {
+    let token = SignatureToken::Reference(Box::new(nested_instantiation(1)));
+    let module = struct_module(1, 1, token);
+    let limits = StructuralLimits {
+        max_type_instantiation_depth: Some(1),
+        ..StructuralLimits::unlimited()
+    };
+    assert_eq!(
+        StructuralLimitsChecker::new(&module, &limits).verify(),
+        vec![]
+    );
+}
+
+#[test]
+fn type_parameters() {
+    let module = struct_module(3, 1, SignatureToken::U64);
+    let limits = StructuralLimits {
+        max_type_parameters: Some(2),
+        ..StructuralLimits::unlimited()
+    };
+    assert_eq!(
+        StructuralLimitsChecker::new(&module, &limits).verify(),
+        vec![VerificationError::new(
+            IndexKind::StructHandle,
+            0,
+            VMStaticViolation::TooManyTypeParameters,
+        )]
+    );
+}
+
+#[test]
+fn struct_fields() {
+    let module = struct_module(0, 3, SignatureToken::U64);
+    let limits = StructuralLimits {
+        max_struct_fields: Some(2),
+        ..StructuralLimits::unlimited()
+    };
+    assert_eq!(
+        StructuralLimitsChecker::new(&module, &limits).verify(),
+        vec![VerificationError::new(
+            IndexKind::StructDefinition,
+            0,
+            VMStaticViolation::TooManyStructFields,
+        )]
+    );
+}
+
+#[test]
+fn configured_limits() {
+    let module = struct_module(0, 3, SignatureToken::U64);
+    let config = VerifierConfig {
+        structural_limits: true,
+        ..VerifierConfig::all()
+    };
+    assert!(verify_module_by_pass(&module, &config).is_empty());
+
+    let config = VerifierConfig {
+        max_structure: StructuralLimits {
+            max_struct_fields: Some(2),
+            ..StructuralLimits::default()
+        },
+        ..config
+    };
+    let errors = verify_module_by_pass(&module, &config);
+    assert_eq!(
+        errors.for_pass(ConfigurablePass::StructuralLimits),
+        vec![VerificationError::new(
+            IndexKind::StructDefinition,
+            0,
+            VMStaticViolation::TooManyStructFields,
+        )]
+    );
+
+    let config = VerifierConfig {
+        structural_limits: false,
+        ..config
+    };
+    assert!(verify_module_by_pass(&module, &config).is_empty());
+}
+
+#[test]
+fn stdlib_is_within_default_limits() {
+    // Enforcing the limits is a consensus change, so `VerifiedModule::new` must not do it.
+    assert!(!VerifierConfig::all().structural_limits);
+
+    let config = VerifierConfig {
+        structural_limits: true,
+        ..VerifierConfig::all()
+    };
+    for module in stdlib_modules() {
+        let errors = verify_module_by_pass(module.as_inner(), &config);
+        assert!(
+            errors.is_empty(),
+            "{}: {:?}",
+            module.self_id(),
+            errors.for_pass(ConfigurablePass::StructuralLimits)
+        );
+    }
+}
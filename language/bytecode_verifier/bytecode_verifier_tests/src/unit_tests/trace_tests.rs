@@ -0,0 +1,78 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{verify_module_with_config, VerifierConfig};
+use vm::{
+    errors::VMStaticViolation,
+    file_format::{dummy_procedure_module, Bytecode, CompiledModule},
+};
+
+fn tracing() -> VerifierConfig {
+    VerifierConfig {
+        traces: true,
+        ..VerifierConfig::all()
+    }
+}
+
+/// Returns a module whose only function branches over a return into `tail`, which starts at code
+/// offset 3.
+fn branch_into(tail: Vec<Bytecode>) -> CompiledModule {
+    let mut code = vec![Bytecode::LdTrue, Bytecode::BrTrue(3), Bytecode::Ret];
+    code.extend(tail);
+    dummy_procedure_module(code)
+}
+
+#[test]
+fn type_safety_trace() {
+    let module = branch_into(vec![
+        Bytecode::LdConst(0),
+        Bytecode::LdTrue,
+        Bytecode::Add,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    let errors = verify_module_with_config(&module, &tracing());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].err,
+        VMStaticViolation::IntegerOpTypeMismatchError(5)
+    );
+    assert_eq!(errors[0].trace, vec![0, 1, 3, 4, 5]);
+    assert!(errors[0]
+        .to_string()
+        .ends_with("(reached through code offsets 0 -> 1 -> 3 -> 4 -> 5)"));
+}
+
+#[test]
+fn stack_usage_trace() {
+    let module = branch_into(vec![Bytecode::Pop, Bytecode::Ret]);
+    let errors = verify_module_with_config(&module, &tracing());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].err,
+        VMStaticViolation::NegativeStackSizeInsideBlock(3, 3)
+    );
+    assert_eq!(errors[0].trace, vec![0, 1, 3]);
+}
+
+#[test]
+fn unreachable_block_trace_starts_at_the_block() {
+    let module = dummy_procedure_module(vec![
+        Bytecode::Ret,
+        Bytecode::LdTrue,
+        Bytecode::Pop,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    let errors = verify_module_with_config(&module, &tracing());
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].trace, vec![1, 2, 3]);
+}
+
+#[test]
+fn no_traces_by_default() {
+    let module = branch_into(vec![Bytecode::Pop, Bytecode::Ret]);
+    let errors = verify_module_with_config(&module, &VerifierConfig::all());
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].trace.is_empty());
+}
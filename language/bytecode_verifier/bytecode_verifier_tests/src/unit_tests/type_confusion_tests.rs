@@ -0,0 +1,117 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::CodeUnitVerifier;
+use invalid_mutations::type_confusion::{ApplyTypeConfusionContext, TypeConfusionMutation};
+use proptest::{collection::vec, prelude::*};
+use types::byte_array::ByteArray;
+use vm::{
+    errors::sort_errors,
+    file_format::{
+        empty_module, Bytecode, CodeUnit, CompiledModuleMut, FieldDefinition, FieldDefinitionIndex,
+        FunctionDefinition, FunctionHandle, FunctionHandleIndex, FunctionSignature,
+        FunctionSignatureIndex, ModuleHandleIndex, SignatureToken, StringPoolIndex,
+        StructDefinition, StructDefinitionIndex, StructFieldInformation, StructHandle,
+        StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex, NO_TYPE_ACTUALS,
+    },
+};
+
+/// Builds a module whose functions each contain every pattern that type confusion mutations look
+/// for, along with structs of several sizes for `Pack` to be retargeted to and a byte array to
+/// confuse operands with.
+fn well_typed_module(function_count: usize) -> CompiledModuleMut {
+    let mut module = empty_module();
+    module.byte_array_pool.push(ByteArray::new(vec![0]));
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    for (idx, field_count) in [1, 2, 4].iter().enumerate() {
+        module.string_pool.push(format!("S{}", idx).into());
+        let name = StringPoolIndex::new((module.string_pool.len() - 1) as TableIndex);
+        module.struct_handles.push(StructHandle {
+            module: ModuleHandleIndex::new(0),
+            name,
+            is_nominal_resource: false,
+            type_formals: vec![],
+        });
+        let struct_handle = StructHandleIndex::new(idx as TableIndex);
+        module.struct_defs.push(StructDefinition {
+            struct_handle,
+            field_information: StructFieldInformation::Declared {
+                field_count: *field_count,
+                fields: FieldDefinitionIndex::new(module.field_defs.len() as TableIndex),
+            },
+        });
+        for field_idx in 0..*field_count {
+            module.string_pool.push(format!("f{}", field_idx).into());
+            module.field_defs.push(FieldDefinition {
+                struct_: struct_handle,
+                name: StringPoolIndex::new((module.string_pool.len() - 1) as TableIndex),
+                signature: TypeSignatureIndex::new(0),
+            });
+        }
+    }
+
+    module.function_signatures.push(FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    });
+    module.function_handles.push(FunctionHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(0),
+        signature: FunctionSignatureIndex::new(0),
+    });
+    let one_field = StructDefinitionIndex::new(0);
+    for _ in 0..function_count {
+        module.function_defs.push(FunctionDefinition {
+            function: FunctionHandleIndex::new(0),
+            code: CodeUnit {
+                code: vec![
+                    Bytecode::LdConst(1),
+                    Bytecode::LdConst(2),
+                    Bytecode::Add,
+                    Bytecode::Pop,
+                    Bytecode::LdTrue,
+                    Bytecode::BrTrue(6),
+                    Bytecode::LdConst(3),
+                    Bytecode::Pack(one_field, NO_TYPE_ACTUALS),
+                    Bytecode::Unpack(one_field, NO_TYPE_ACTUALS),
+                    Bytecode::Pop,
+                    Bytecode::Ret,
+                ],
+                ..CodeUnit::default()
+            },
+            ..FunctionDefinition::default()
+        });
+    }
+    module
+}
+
+proptest! {
+    #[test]
+    fn well_typed(function_count in 1usize..8) {
+        let module = well_typed_module(function_count)
+            .freeze()
+            .expect("should satisfy bounds checker");
+        prop_assert_eq!(CodeUnitVerifier::verify(&module), vec![]);
+    }
+
+    #[test]
+    fn confused_types(
+        function_count in 1usize..8,
+        mutations in vec(TypeConfusionMutation::strategy(), 0..10),
+    ) {
+        let mut module = well_typed_module(function_count);
+        let mut expected_violations = {
+            let context = ApplyTypeConfusionContext::new(&mut module, mutations);
+            context.apply()
+        };
+        sort_errors(&mut expected_violations);
+        let module = module.freeze().expect("should satisfy bounds checker");
+
+        let mut actual_violations = CodeUnitVerifier::verify(&module);
+        sort_errors(&mut actual_violations);
+        prop_assert_eq!(expected_violations, actual_violations);
+    }
+}
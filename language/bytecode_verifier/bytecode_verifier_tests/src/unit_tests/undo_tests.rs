@@ -0,0 +1,62 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use invalid_mutations::{
+    bounds::{
+        ApplyByteArrayBoundsContext, ApplyCodeUnitBoundsContext, ByteArrayBoundsMutation,
+        CodeUnitBoundsMutation,
+    },
+    control_flow::{ApplyControlFlowContext, ControlFlowMutation},
+    type_confusion::{ApplyTypeConfusionContext, TypeConfusionMutation},
+};
+use proptest::{collection::vec, prelude::*};
+use vm::{check_bounds::BoundsChecker, errors::sort_errors, file_format::CompiledModule};
+
+proptest! {
+    #[test]
+    fn reuse_module_across_mutations(
+        module in CompiledModule::valid_strategy(20),
+        mutations in vec(CodeUnitBoundsMutation::strategy(), 0..20),
+    ) {
+        let original = module.into_inner();
+        let mut module = original.clone();
+        for mutation in mutations {
+            let (mut expected_violations, undo) =
+                ApplyCodeUnitBoundsContext::new(&mut module, vec![mutation]).apply_with_undo();
+            sort_errors(&mut expected_violations);
+
+            let mut actual_violations = BoundsChecker::new(&module).verify();
+            sort_errors(&mut actual_violations);
+            prop_assert_eq!(expected_violations, actual_violations);
+
+            undo.undo(&mut module);
+            prop_assert_eq!(&original, &module);
+        }
+    }
+
+    #[test]
+    fn undo_restores_module(
+        module in CompiledModule::valid_strategy(20),
+        control_flow in vec(ControlFlowMutation::strategy(), 0..10),
+        type_confusion in vec(TypeConfusionMutation::strategy(), 0..10),
+        byte_array in vec(ByteArrayBoundsMutation::strategy(), 0..10),
+        code_unit in vec(CodeUnitBoundsMutation::strategy(), 0..10),
+    ) {
+        let original = module.into_inner();
+        let mut module = original.clone();
+        let (_, control_flow_undo) =
+            ApplyControlFlowContext::new(&mut module, control_flow).apply_with_undo();
+        let (_, type_confusion_undo) =
+            ApplyTypeConfusionContext::new(&mut module, type_confusion).apply_with_undo();
+        let (_, byte_array_undo) =
+            ApplyByteArrayBoundsContext::new(&mut module, byte_array).apply_with_undo();
+        let (_, code_unit_undo) =
+            ApplyCodeUnitBoundsContext::new(&mut module, code_unit).apply_with_undo();
+
+        code_unit_undo.undo(&mut module);
+        byte_array_undo.undo(&mut module);
+        type_confusion_undo.undo(&mut module);
+        control_flow_undo.undo(&mut module);
+        prop_assert_eq!(original, module);
+    }
+}
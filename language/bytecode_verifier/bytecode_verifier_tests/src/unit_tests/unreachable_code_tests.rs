@@ -0,0 +1,74 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use bytecode_verifier::{
+    verify_module_with_config, UnreachableCodeChecker, VerifiedModule, VerifierConfig,
+};
+use vm::{
+    errors::{has_errors, Severity, VMStaticViolation, VerificationError},
+    file_format::{dummy_procedure_module, Bytecode, FunctionDefinitionIndex},
+};
+
+fn in_main(err: VMStaticViolation) -> VerificationError {
+    VerificationError::in_function(FunctionDefinitionIndex::new(0), err)
+}
+
+#[test]
+fn reachable_code() {
+    let module = dummy_procedure_module(vec![
+        Bytecode::LdTrue,
+        Bytecode::BrTrue(3),
+        Bytecode::Branch(0),
+        Bytecode::Ret,
+    ]);
+    assert_eq!(UnreachableCodeChecker::new(&module).verify(), vec![]);
+}
+
+#[test]
+fn dead_code_after_branch() {
+    let module = dummy_procedure_module(vec![
+        Bytecode::Branch(3),
+        Bytecode::LdTrue,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    assert_eq!(
+        UnreachableCodeChecker::new(&module).verify(),
+        vec![in_main(VMStaticViolation::DeadCodeAfterBranch(1))]
+    );
+}
+
+#[test]
+fn unreachable_branch_target() {
+    // The last block is branched to, but only from dead code.
+    let module = dummy_procedure_module(vec![Bytecode::Ret, Bytecode::Branch(2), Bytecode::Ret]);
+    assert_eq!(
+        UnreachableCodeChecker::new(&module).verify(),
+        vec![
+            in_main(VMStaticViolation::DeadCodeAfterBranch(1)),
+            in_main(VMStaticViolation::UnreachableBlock(2)),
+        ]
+    );
+}
+
+#[test]
+fn warnings_dont_fail_verification() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret, Bytecode::Ret]);
+    assert_eq!(
+        verify_module_with_config(&module, &VerifierConfig::all()),
+        vec![]
+    );
+
+    let config = VerifierConfig {
+        unreachable_code: true,
+        ..VerifierConfig::all()
+    };
+    let errors = verify_module_with_config(&module, &config);
+    assert_eq!(
+        errors,
+        vec![in_main(VMStaticViolation::DeadCodeAfterBranch(1))]
+    );
+    assert_eq!(errors[0].severity(), Severity::Warning);
+    assert!(!has_errors(&errors));
+    assert!(VerifiedModule::new(module).is_ok());
+}
@@ -0,0 +1,117 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exposes deserialization, disassembly and verification of modules to JavaScript through
+//! wasm-bindgen, so that modules can be inspected in a browser, e.g. by block explorers. Build
+//! with `wasm-pack build`.
+//!
+//! Every function takes a module in binary form, and fails with a string describing why if the
+//! binary can't be deserialized. Structured results are returned as JSON strings.
+use bytecode_verifier::{verify_module_with_config, VerifierConfig};
+use serde_json::{json, Value};
+use types::language_storage::ModuleId;
+use vm::{
+    errors::{has_errors, short_address},
+    file_format::CompiledModule,
+    views::ModuleView,
+};
+use wasm_bindgen::prelude::*;
+
+fn deserialize(binary: &[u8]) -> Result<CompiledModule, JsValue> {
+    CompiledModule::deserialize(binary)
+        .map_err(|err| JsValue::from_str(&format!("malformed module: {}", err)))
+}
+
+/// Deserializes a module, and describes its declarations as JSON:
+///
+/// ```json
+/// {
+///   "address": "0x0",
+///   "name": "M",
+///   "dependencies": ["0x0::LibraCoin"],
+///   "structs": [{ "name": "T", "resource": true, "native": false }],
+///   "functions": [{ "name": "f", "public": true, "native": false, "arguments": 1, "returns": 0 }]
+/// }
+/// ```
+#[wasm_bindgen(js_name = deserializeModule)]
+pub fn deserialize_module(binary: &[u8]) -> Result<String, JsValue> {
+    let module = deserialize(binary)?;
+    let view = ModuleView::new(&module);
+    let id = view.id();
+    let name = |id: &ModuleId| format!("{}::{}", short_address(id.address()), id.name());
+    let dependencies: Vec<_> = view
+        .module_handles()
+        .map(|handle| handle.module_id())
+        .filter(|dependency| *dependency != id)
+        .map(|dependency| name(&dependency))
+        .collect();
+    let structs: Vec<_> = view
+        .structs()
+        .map(|struct_def| {
+            json!({
+                "name": struct_def.name(),
+                "resource": struct_def.is_nominal_resource(),
+                "native": struct_def.is_native(),
+            })
+        })
+        .collect();
+    let functions: Vec<_> = view
+        .functions()
+        .map(|function| {
+            json!({
+                "name": function.name(),
+                "public": function.is_public(),
+                "native": function.is_native(),
+                "arguments": function.signature().arg_count(),
+                "returns": function.signature().return_count(),
+            })
+        })
+        .collect();
+    Ok(json!({
+        "address": short_address(id.address()),
+        "name": id.name(),
+        "dependencies": dependencies,
+        "structs": structs,
+        "functions": functions,
+    })
+    .to_string())
+}
+
+/// Deserializes a module, and returns its disassembly.
+#[wasm_bindgen(js_name = disassembleModule)]
+pub fn disassemble_module(binary: &[u8]) -> Result<String, JsValue> {
+    Ok(deserialize(binary)?.to_string())
+}
+
+/// Deserializes a module, verifies it on its own with every pass, advisory ones included, and
+/// returns the outcome as JSON:
+///
+/// ```json
+/// {
+///   "verified": true,
+///   "errors": [{ "code": 6033, "severity": "warning", "message": "..." }]
+/// }
+/// ```
+///
+/// The module is verified without its dependencies, which the caller may not have.
+#[wasm_bindgen(js_name = verifyModule)]
+pub fn verify_module(binary: &[u8]) -> Result<String, JsValue> {
+    let module = deserialize(binary)?;
+    let errors = verify_module_with_config(&module, &VerifierConfig::advisory());
+    let view = ModuleView::new(&module);
+    let described: Vec<Value> = errors
+        .iter()
+        .map(|err| {
+            json!({
+                "code": err.code(),
+                "severity": err.severity().to_string(),
+                "message": err.display_with(&view).to_string(),
+            })
+        })
+        .collect();
+    Ok(json!({
+        "verified": !has_errors(&errors),
+        "errors": described,
+    })
+    .to_string())
+}
@@ -0,0 +1,183 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use std::collections::BTreeSet;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        Bytecode, CompiledModuleMut, FunctionDefinition, StructDefinitionIndex, TableIndex,
+    },
+    IndexKind,
+};
+
+/// Represents a mutation to the acquires annotations of a function definition.
+///
+/// Use `AcquiresMutation::strategy()` to generate them, preferably using `Vec` to generate many at
+/// a time. Then use `ApplyAcquiresContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct AcquiresMutation {
+    function_def: PropIndex,
+    kind: AcquiresMutationKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum AcquiresMutationKind {
+    /// Removes an annotated resource that the function borrows or moves from global storage.
+    RemoveRequired(PropIndex),
+    /// Annotates a resource that the function never accesses.
+    AddSpurious(PropIndex),
+    /// Annotates a struct definition index that doesn't exist in the module.
+    UndeclaredStruct(PropIndex),
+}
+
+impl AcquiresMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), AcquiresMutationKind::strategy())
+            .prop_map(|(function_def, kind)| Self { function_def, kind })
+    }
+}
+
+impl AsRef<PropIndex> for AcquiresMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.function_def
+    }
+}
+
+impl AcquiresMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            any::<PropIndex>().prop_map(AcquiresMutationKind::RemoveRequired),
+            any::<PropIndex>().prop_map(AcquiresMutationKind::AddSpurious),
+            any::<PropIndex>().prop_map(AcquiresMutationKind::UndeclaredStruct),
+        ]
+    }
+}
+
+/// Context for applying a list of `AcquiresMutation` instances.
+///
+/// Each mutation is applied to a different function. Missing and spurious annotations are reported
+/// by `AcquiresVerifier`, while annotations of undeclared structs are reported by the bounds
+/// checker as `IndexOutOfBounds`.
+///
+/// The expected errors are only precise if the acquires annotations of the module are correct to
+/// begin with, and if no function calls another function of the module that acquires resources.
+pub struct ApplyAcquiresContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<AcquiresMutation>,
+}
+
+impl<'a> ApplyAcquiresContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<AcquiresMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let struct_handles = &self.module.struct_handles;
+        let resources: Vec<_> = self
+            .module
+            .struct_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, struct_def)| {
+                struct_handles[struct_def.struct_handle.0 as usize].is_nominal_resource
+            })
+            .map(|(idx, _)| StructDefinitionIndex::new(idx as TableIndex))
+            .collect();
+        let struct_defs_len = self.module.struct_defs.len();
+        let function_def_idxs: Vec<_> = self
+            .module
+            .function_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| !function_def.is_native())
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(function_def_idxs.len(), &self.mutations);
+
+        let mut errs = vec![];
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let idx = function_def_idxs[picked_idx];
+            let function_def = &mut self.module.function_defs[idx];
+            errs.extend(
+                apply_one(function_def, &resources, struct_defs_len, &mutation.kind)
+                    .into_iter()
+                    .map(|err| VerificationError::new(IndexKind::FunctionDefinition, idx, err)),
+            );
+        }
+        errs
+    }
+}
+
+fn apply_one(
+    function_def: &mut FunctionDefinition,
+    resources: &[StructDefinitionIndex],
+    struct_defs_len: usize,
+    kind: &AcquiresMutationKind,
+) -> Vec<VMStaticViolation> {
+    let accessed = accessed_resources(&function_def.code.code);
+    let annotations = &mut function_def.acquires_global_resources;
+    match kind {
+        AcquiresMutationKind::RemoveRequired(removed) => {
+            let candidates: Vec<_> = annotations
+                .iter()
+                .filter(|idx| accessed.contains(idx))
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            if candidates.is_empty() {
+                return vec![];
+            }
+            let removed = candidates[removed.index(candidates.len())];
+            annotations.retain(|idx| *idx != removed);
+            // Every access to the resource is now missing its annotation.
+            function_def
+                .code
+                .code
+                .iter()
+                .enumerate()
+                .filter(|(_, bytecode)| global_access(bytecode) == Some(removed))
+                .map(|(offset, _)| {
+                    VMStaticViolation::MissingAcquiresResourceAnnotationError(offset)
+                })
+                .collect()
+        }
+        AcquiresMutationKind::AddSpurious(added) => {
+            let candidates: Vec<_> = resources
+                .iter()
+                .filter(|idx| !accessed.contains(idx) && !annotations.contains(idx))
+                .collect();
+            if candidates.is_empty() {
+                return vec![];
+            }
+            annotations.push(*candidates[added.index(candidates.len())]);
+            vec![VMStaticViolation::ExtraneousAcquiresResourceAnnotationError]
+        }
+        AcquiresMutationKind::UndeclaredStruct(added) => {
+            // Pick any index that can be encoded but that isn't in the struct definition table.
+            let idx = struct_defs_len
+                + added.index(TableIndex::max_value() as usize + 1 - struct_defs_len);
+            annotations.push(StructDefinitionIndex::new(idx as TableIndex));
+            vec![VMStaticViolation::IndexOutOfBounds(
+                IndexKind::StructDefinition,
+                struct_defs_len,
+                idx,
+            )]
+        }
+    }
+}
+
+/// Returns the resources that `code` borrows or moves from global storage.
+fn accessed_resources(code: &[Bytecode]) -> BTreeSet<StructDefinitionIndex> {
+    code.iter().filter_map(global_access).collect()
+}
+
+fn global_access(bytecode: &Bytecode) -> Option<StructDefinitionIndex> {
+    match bytecode {
+        Bytecode::BorrowGlobal(idx, _) | Bytecode::MoveFrom(idx, _) => Some(*idx),
+        _ => None,
+    }
+}
@@ -6,11 +6,12 @@ use proptest::{
     sample::{self, Index as PropIndex},
 };
 use proptest_helpers::pick_slice_idxs;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use vm::{
     errors::{VMStaticViolation, VerificationError},
     file_format::{
-        AddressPoolIndex, CompiledModule, CompiledModuleMut, FieldDefinitionIndex,
+        AddressPoolIndex, CompiledModule, CompiledModuleMut, CompiledScript, FieldDefinitionIndex,
         FunctionHandleIndex, FunctionSignatureIndex, LocalsSignatureIndex, ModuleHandleIndex,
         StringPoolIndex, StructFieldInformation, StructHandleIndex, TableIndex, TypeSignatureIndex,
     },
@@ -19,8 +20,12 @@ use vm::{
     IndexKind,
 };
 
+mod byte_array;
 mod code_unit;
-pub use code_unit::{ApplyCodeUnitBoundsContext, CodeUnitBoundsMutation};
+pub use byte_array::{ApplyByteArrayBoundsContext, ByteArrayBoundsMutation};
+pub use code_unit::{
+    ApplyCodeUnitBoundsContext, CodeUnitBoundsDescription, CodeUnitBoundsMutation,
+};
 
 /// Represents the number of pointers that exist out from a node of a particular kind.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -35,39 +40,136 @@ pub enum PointerKind {
     Star(IndexKind),
 }
 
-impl PointerKind {
-    /// A list of what pointers (indexes) exist out from a particular kind of node within the
-    /// module.
-    ///
-    /// The only special case is `FunctionDefinition`, which contains a `CodeUnit` that can contain
-    /// one of several kinds of pointers out. That is not represented in this table.
-    #[inline]
-    pub fn pointers_from(src_kind: IndexKind) -> &'static [PointerKind] {
-        use IndexKind::*;
-        use PointerKind::*;
-
-        match src_kind {
-            ModuleHandle => &[One(AddressPool), One(StringPool)],
-            StructHandle => &[One(ModuleHandle), One(StringPool)],
-            FunctionHandle => &[One(ModuleHandle), One(StringPool), One(FunctionSignature)],
-            StructDefinition => &[One(StructHandle), One(FieldDefinition)],
-            FieldDefinition => &[One(StructHandle), One(StringPool), One(TypeSignature)],
-            FunctionDefinition => &[One(FunctionHandle), One(LocalsSignature)],
-            TypeSignature => &[Optional(StructHandle)],
-            FunctionSignature => &[Star(StructHandle)],
-            LocalsSignature => &[Star(StructHandle)],
-            StringPool => &[],
-            ByteArrayPool => &[],
-            AddressPool => &[],
-            // LocalPool and CodeDefinition are function-local, and this only works for
-            // module-scoped indexes.
-            // XXX maybe don't treat LocalPool and CodeDefinition the same way as the others?
-            LocalPool => &[],
-            CodeDefinition => &[],
-            TypeParameter => &[],
+/// Defines the pointers out from every kind of node, along with how to set each of them.
+///
+/// Every edge is listed once, as `PointerKind(destination kind) => setter`, and both
+/// `PointerKind::pointers_from` and the setters used by `ApplyOutOfBoundsContext` are generated
+/// from that list. This way an edge can't be generated by `OutOfBoundsMutation::strategy` without
+/// being applicable, and every kind of node has to be listed as a source.
+macro_rules! define_pointer_edges {
+    ($($src: ident => [$($pointer: ident($dst: ident) => $setter: expr,)*],)*) => {
+        impl PointerKind {
+            /// A list of what pointers (indexes) exist out from a particular kind of node within
+            /// the module.
+            ///
+            /// The only special case is `FunctionDefinition`, which contains a `CodeUnit` that can
+            /// contain one of several kinds of pointers out. That is not represented in this
+            /// table.
+            #[inline]
+            pub fn pointers_from(src_kind: IndexKind) -> &'static [PointerKind] {
+                match src_kind {
+                    $(IndexKind::$src => &[$(PointerKind::$pointer(IndexKind::$dst),)*],)*
+                }
+            }
         }
-    }
 
+        /// Every pointer out from every kind of node, as `(source kind, pointer)` pairs.
+        pub static POINTER_EDGES: &[(IndexKind, PointerKind)] = &[
+            $($((IndexKind::$src, PointerKind::$pointer(IndexKind::$dst)),)*)*
+        ];
+
+        impl ApplyOutOfBoundsContext {
+            /// Returns the setter for pointers from `src_kind` to `dst_kind`, or `None` if there
+            /// are no such pointers.
+            fn setter(src_kind: IndexKind, dst_kind: IndexKind) -> Option<SetIndex> {
+                match (src_kind, dst_kind) {
+                    $($((IndexKind::$src, IndexKind::$dst) => {
+                        let setter: SetIndex = $setter;
+                        Some(setter)
+                    })*)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+define_pointer_edges! {
+    ModuleHandle => [
+        One(AddressPool) => |context, update| {
+            context.module.module_handles[update.src_idx].address =
+                AddressPoolIndex::new(update.new_idx)
+        },
+        One(StringPool) => |context, update| {
+            context.module.module_handles[update.src_idx].name =
+                StringPoolIndex::new(update.new_idx)
+        },
+    ],
+    StructHandle => [
+        One(ModuleHandle) => |context, update| {
+            context.module.struct_handles[update.src_idx].module =
+                ModuleHandleIndex::new(update.new_idx)
+        },
+        One(StringPool) => |context, update| {
+            context.module.struct_handles[update.src_idx].name =
+                StringPoolIndex::new(update.new_idx)
+        },
+    ],
+    FunctionHandle => [
+        One(ModuleHandle) => |context, update| {
+            context.module.function_handles[update.src_idx].module =
+                ModuleHandleIndex::new(update.new_idx)
+        },
+        One(StringPool) => |context, update| {
+            context.module.function_handles[update.src_idx].name =
+                StringPoolIndex::new(update.new_idx)
+        },
+        One(FunctionSignature) => |context, update| {
+            context.module.function_handles[update.src_idx].signature =
+                FunctionSignatureIndex::new(update.new_idx)
+        },
+    ],
+    StructDefinition => [
+        One(StructHandle) => |context, update| {
+            context.module.struct_defs[update.src_idx].struct_handle =
+                StructHandleIndex::new(update.new_idx)
+        },
+        One(FieldDefinition) => ApplyOutOfBoundsContext::set_struct_def_fields,
+    ],
+    FieldDefinition => [
+        One(StructHandle) => |context, update| {
+            context.module.field_defs[update.src_idx].struct_ =
+                StructHandleIndex::new(update.new_idx)
+        },
+        One(StringPool) => |context, update| {
+            context.module.field_defs[update.src_idx].name = StringPoolIndex::new(update.new_idx)
+        },
+        One(TypeSignature) => |context, update| {
+            context.module.field_defs[update.src_idx].signature =
+                TypeSignatureIndex::new(update.new_idx)
+        },
+    ],
+    FunctionDefinition => [
+        One(FunctionHandle) => |context, update| {
+            context.module.function_defs[update.src_idx].function =
+                FunctionHandleIndex::new(update.new_idx)
+        },
+        One(LocalsSignature) => |context, update| {
+            context.module.function_defs[update.src_idx].code.locals =
+                LocalsSignatureIndex::new(update.new_idx)
+        },
+    ],
+    TypeSignature => [
+        Optional(StructHandle) => ApplyOutOfBoundsContext::set_type_sig_struct,
+    ],
+    FunctionSignature => [
+        Star(StructHandle) => ApplyOutOfBoundsContext::set_function_sig_struct,
+    ],
+    LocalsSignature => [
+        Star(StructHandle) => ApplyOutOfBoundsContext::set_locals_sig_struct,
+    ],
+    StringPool => [],
+    ByteArrayPool => [],
+    AddressPool => [],
+    // LocalPool and CodeDefinition are function-local, and this only works for module-scoped
+    // indexes. Pointers into them are mutated by `ApplyCodeUnitBoundsContext`, and their sizes
+    // are given by `FunctionDefinitionView::kind_count`.
+    LocalPool => [],
+    CodeDefinition => [],
+    TypeParameter => [],
+}
+
+impl PointerKind {
     #[inline]
     pub fn to_index_kind(self) -> IndexKind {
         match self {
@@ -91,6 +193,14 @@ pub static VALID_POINTER_SRCS: &[IndexKind] = &[
 #[cfg(test)]
 mod test {
     use super::*;
+    use vm::{
+        check_bounds::BoundsChecker,
+        file_format::{
+            empty_module, Bytecode, FieldDefinition, FunctionDefinition, FunctionHandle,
+            FunctionSignature, LocalsSignature, SignatureToken, StructDefinition, StructHandle,
+            TypeSignature,
+        },
+    };
 
     #[test]
     fn pointer_kind_sanity() {
@@ -110,6 +220,82 @@ mod test {
             }
         }
     }
+
+    /// Returns a module where every kind of pointer source has an entry with every kind of
+    /// pointer out of it.
+    fn module_with_every_edge() -> CompiledModule {
+        let struct_token = SignatureToken::Struct(StructHandleIndex::new(0), vec![]);
+        let mut module = empty_module();
+        module.struct_handles.push(StructHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new(0),
+            is_nominal_resource: false,
+            type_formals: vec![],
+        });
+        module
+            .type_signatures
+            .push(TypeSignature(struct_token.clone()));
+        module.field_defs.push(FieldDefinition {
+            struct_: StructHandleIndex::new(0),
+            name: StringPoolIndex::new(0),
+            signature: TypeSignatureIndex::new(0),
+        });
+        module.struct_defs.push(StructDefinition {
+            struct_handle: StructHandleIndex::new(0),
+            field_information: StructFieldInformation::Declared {
+                field_count: 1,
+                fields: FieldDefinitionIndex::new(0),
+            },
+        });
+        module.function_signatures.push(FunctionSignature {
+            return_types: vec![struct_token.clone()].into(),
+            arg_types: vec![struct_token.clone()].into(),
+            type_formals: vec![],
+        });
+        module.function_handles.push(FunctionHandle {
+            module: ModuleHandleIndex::new(0),
+            name: StringPoolIndex::new(0),
+            signature: FunctionSignatureIndex::new(0),
+        });
+        module.locals_signatures = vec![LocalsSignature(vec![struct_token].into())];
+        let mut function_def = FunctionDefinition::default();
+        function_def.code.code = vec![Bytecode::Ret];
+        module.function_defs.push(function_def);
+        module.freeze().expect("module is bounds-valid")
+    }
+
+    #[test]
+    fn every_edge_can_be_set_out_of_bounds() {
+        let module = module_with_every_edge();
+        for (src_kind, pointer) in POINTER_EDGES {
+            let dst_kind = pointer.to_index_kind();
+            assert!(VALID_POINTER_SRCS.contains(src_kind));
+            assert!(PointerKind::pointers_from(*src_kind).contains(pointer));
+
+            let description = OutOfBoundsDescription {
+                src_kind: *src_kind,
+                src_idx: 0,
+                dst_kind,
+                offset: 1,
+            };
+            let (mutated, expected) =
+                ApplyOutOfBoundsContext::apply_from_description(module.clone(), &[description]);
+            assert_eq!(
+                expected.len(),
+                1,
+                "expected edge {:?} -> {:?} to be set",
+                src_kind,
+                dst_kind,
+            );
+            assert_eq!(
+                BoundsChecker::new(&mutated).verify(),
+                expected,
+                "edge {:?} -> {:?}",
+                src_kind,
+                dst_kind,
+            );
+        }
+    }
 }
 
 /// Represents a single mutation to a `CompiledModule` to produce an out-of-bounds situation.
@@ -163,6 +349,28 @@ impl AsRef<PropIndex> for OutOfBoundsMutation {
     }
 }
 
+/// A serializable description of an `OutOfBoundsMutation` once it has been applied to a particular
+/// module.
+///
+/// Descriptions returned by `ApplyOutOfBoundsContext::apply_and_describe` can be stored along with
+/// the module they were applied to, and replayed with
+/// `ApplyOutOfBoundsContext::apply_from_description`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct OutOfBoundsDescription {
+    /// The kind of the entry that was mutated.
+    pub src_kind: IndexKind,
+    /// The index of the entry that was mutated. For signatures, this only counts the tokens that
+    /// contain struct handles.
+    pub src_idx: usize,
+    /// The kind of the index that was made out of bounds.
+    pub dst_kind: IndexKind,
+    /// How far past the end of the destination table the new index is.
+    pub offset: usize,
+}
+
+/// Context for applying a list of `OutOfBoundsMutation` instances.
+///
+/// Scripts can be mutated as well, see `new_script`.
 pub struct ApplyOutOfBoundsContext {
     module: CompiledModuleMut,
     // This is an Option because it gets moved out in apply before apply_one is called. Rust
@@ -190,7 +398,29 @@ impl ApplyOutOfBoundsContext {
         }
     }
 
-    pub fn apply(mut self) -> (CompiledModuleMut, Vec<VerificationError>) {
+    /// Creates a context that mutates `script` as a module with `main` as its only function
+    /// definition. Errors in `main` are reported for function definition 0.
+    ///
+    /// The module returned by `apply` can be turned back into a script through
+    /// `CompiledModuleMut::into_script`.
+    pub fn new_script(script: CompiledScript, mutations: Vec<OutOfBoundsMutation>) -> Self {
+        Self::new(script.into_module(), mutations)
+    }
+
+    pub fn apply(self) -> (CompiledModuleMut, Vec<VerificationError>) {
+        let (module, errs, _) = self.apply_and_describe();
+        (module, errs)
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning descriptions of them that can
+    /// be replayed on the same module with `apply_from_description`.
+    pub fn apply_and_describe(
+        mut self,
+    ) -> (
+        CompiledModuleMut,
+        Vec<VerificationError>,
+        Vec<OutOfBoundsDescription>,
+    ) {
         // This is a map from (source kind, dest kind) to the actual mutations -- this is done to
         // figure out how many mutations to do for a particular pair, which is required for
         // pick_slice_idxs below.
@@ -206,22 +436,31 @@ impl ApplyOutOfBoundsContext {
                 .push(mutation);
         }
 
-        let mut results = vec![];
-
+        let mut descriptions = vec![];
         for ((src_kind, dst_kind), mutations) in mutation_map {
-            // It would be cool to use an iterator here, if someone could figure out exactly how
-            // to get the lifetimes right :)
-            results.extend(self.apply_one(src_kind, dst_kind, mutations));
+            descriptions.extend(self.describe_one(src_kind, dst_kind, mutations));
         }
-        (self.module, results)
+
+        let results = self.apply_descriptions(&descriptions);
+        (self.module, results, descriptions)
     }
 
-    fn apply_one(
-        &mut self,
+    /// Replays mutations described by `apply_and_describe` on the module they were applied to.
+    pub fn apply_from_description(
+        module: CompiledModule,
+        descriptions: &[OutOfBoundsDescription],
+    ) -> (CompiledModuleMut, Vec<VerificationError>) {
+        let mut context = Self::new(module, vec![]);
+        let results = context.apply_descriptions(descriptions);
+        (context.module, results)
+    }
+
+    fn describe_one(
+        &self,
         src_kind: IndexKind,
         dst_kind: IndexKind,
         mutations: Vec<OutOfBoundsMutation>,
-    ) -> Vec<VerificationError> {
+    ) -> Vec<OutOfBoundsDescription> {
         let src_count = match src_kind {
             // Only the signature indexes that have structs in them (i.e. are in *_sig_structs)
             // are going to be modifiable, so pick among them.
@@ -231,20 +470,35 @@ impl ApplyOutOfBoundsContext {
             // For the other sorts it's always possible to change an index.
             src_kind => self.module.kind_count(src_kind),
         };
-        // Any signature can be a destination, not just the ones that have structs in them.
-        let dst_count = self.module.kind_count(dst_kind);
         let to_mutate = pick_slice_idxs(src_count, &mutations);
 
         mutations
             .iter()
             .zip(to_mutate)
-            .filter_map(move |(mutation, src_idx)| {
+            .map(|(mutation, src_idx)| OutOfBoundsDescription {
+                src_kind,
+                src_idx,
+                dst_kind,
+                offset: mutation.offset,
+            })
+            .collect()
+    }
+
+    fn apply_descriptions(
+        &mut self,
+        descriptions: &[OutOfBoundsDescription],
+    ) -> Vec<VerificationError> {
+        descriptions
+            .iter()
+            .filter_map(|description| {
+                // Any signature can be a destination, not just the ones that have structs in them.
+                let dst_count = self.module.kind_count(description.dst_kind);
                 self.set_index(
-                    src_kind,
-                    src_idx,
-                    dst_kind,
+                    description.src_kind,
+                    description.src_idx,
+                    description.dst_kind,
                     dst_count,
-                    (dst_count + mutation.offset) as TableIndex,
+                    (dst_count + description.offset) as TableIndex,
                 )
             })
             .collect()
@@ -264,125 +518,90 @@ impl ApplyOutOfBoundsContext {
         dst_count: usize,
         new_idx: TableIndex,
     ) -> Option<VerificationError> {
-        use IndexKind::*;
+        let setter = Self::setter(src_kind, dst_kind)
+            .unwrap_or_else(|| panic!("Invalid pointer kind: {:?} -> {:?}", src_kind, dst_kind));
+        let mut update = IndexUpdate {
+            src_idx,
+            dst_count,
+            new_idx,
+            err: Some(VMStaticViolation::IndexOutOfBounds(
+                dst_kind,
+                dst_count,
+                new_idx as usize,
+            )),
+        };
+        setter(self, &mut update);
+        update
+            .err
+            .map(|err| VerificationError::new(src_kind, update.src_idx, err))
+    }
 
-        // These are default values, but some of the match arms below mutate them.
-        let mut src_idx = src_idx;
-        let mut err = VMStaticViolation::IndexOutOfBounds(dst_kind, dst_count, new_idx as usize);
+    fn set_struct_def_fields(&mut self, update: &mut IndexUpdate) {
+        let field_count = match self.module.struct_defs[update.src_idx].field_information {
+            // There is no way to set an invalid index for a native struct definition
+            StructFieldInformation::Native => {
+                update.err = None;
+                return;
+            }
+            StructFieldInformation::Declared { field_count, .. } => field_count,
+        };
 
-        // A dynamic type system would be able to express this next block of code far more
-        // concisely. A static type system would require some sort of complicated dependent type
-        // structure that Rust doesn't have. As things stand today, every possible case needs to
-        // be listed out.
+        // Consider a situation with 3 fields, and with first field = 1 and count = 2.
+        // A graphical representation of that might be:
+        //
+        //      |___|___|___|
+        //  idx   0   1   2
+        //          ^       ^
+        //          |       |
+        // first field = 1  (first field + count) = 3
+        //
+        // Given that the lowest value for new_idx is 3 (offset 0), the goal is to make
+        // (first field + count) at least 4, or (new_idx + 1). This means that the first
+        // field would be new_idx + 1 - count.
+        let end_idx = update.new_idx + 1;
+        let first_new_idx = end_idx - field_count;
+        let field_information = StructFieldInformation::Declared {
+            field_count,
+            fields: FieldDefinitionIndex::new(first_new_idx),
+        };
+        self.module.struct_defs[update.src_idx].field_information = field_information;
+        update.err = Some(VMStaticViolation::RangeOutOfBounds(
+            IndexKind::FieldDefinition,
+            update.dst_count,
+            first_new_idx as usize,
+            end_idx as usize,
+        ));
+    }
 
-        match (src_kind, dst_kind) {
-            (ModuleHandle, AddressPool) => {
-                self.module.module_handles[src_idx].address = AddressPoolIndex::new(new_idx);
-            }
-            (ModuleHandle, StringPool) => {
-                self.module.module_handles[src_idx].name = StringPoolIndex::new(new_idx)
-            }
-            (StructHandle, ModuleHandle) => {
-                self.module.struct_handles[src_idx].module = ModuleHandleIndex::new(new_idx)
-            }
-            (StructHandle, StringPool) => {
-                self.module.struct_handles[src_idx].name = StringPoolIndex::new(new_idx)
-            }
-            (FunctionHandle, ModuleHandle) => {
-                self.module.function_handles[src_idx].module = ModuleHandleIndex::new(new_idx)
-            }
-            (FunctionHandle, StringPool) => {
-                self.module.function_handles[src_idx].name = StringPoolIndex::new(new_idx)
-            }
-            (FunctionHandle, FunctionSignature) => {
-                self.module.function_handles[src_idx].signature =
-                    FunctionSignatureIndex::new(new_idx)
-            }
-            (StructDefinition, StructHandle) => {
-                self.module.struct_defs[src_idx].struct_handle = StructHandleIndex::new(new_idx)
-            }
-            (StructDefinition, FieldDefinition) => {
-                let field_count = match self.module.struct_defs[src_idx].field_information {
-                    // There is no way to set an invalid index for a native struct definition
-                    StructFieldInformation::Native => return None,
-                    StructFieldInformation::Declared { field_count, .. } => field_count,
-                };
-
-                // Consider a situation with 3 fields, and with first field = 1 and count = 2.
-                // A graphical representation of that might be:
-                //
-                //      |___|___|___|
-                //  idx   0   1   2
-                //          ^       ^
-                //          |       |
-                // first field = 1  (first field + count) = 3
-                //
-                // Given that the lowest value for new_idx is 3 (offset 0), the goal is to make
-                // (first field + count) at least 4, or (new_idx + 1). This means that the first
-                // field would be new_idx + 1 - count.
-                let end_idx = new_idx + 1;
-                let first_new_idx = end_idx - field_count;
-                let field_information = StructFieldInformation::Declared {
-                    field_count,
-                    fields: FieldDefinitionIndex::new(first_new_idx),
-                };
-                self.module.struct_defs[src_idx].field_information = field_information;
-                err = VMStaticViolation::RangeOutOfBounds(
-                    dst_kind,
-                    dst_count,
-                    first_new_idx as usize,
-                    end_idx as usize,
-                );
-            }
-            (FieldDefinition, StructHandle) => {
-                self.module.field_defs[src_idx].struct_ = StructHandleIndex::new(new_idx)
-            }
-            (FieldDefinition, StringPool) => {
-                self.module.field_defs[src_idx].name = StringPoolIndex::new(new_idx)
-            }
-            (FieldDefinition, TypeSignature) => {
-                self.module.field_defs[src_idx].signature = TypeSignatureIndex::new(new_idx)
-            }
-            (FunctionDefinition, FunctionHandle) => {
-                self.module.function_defs[src_idx].function = FunctionHandleIndex::new(new_idx)
-            }
-            (FunctionDefinition, LocalsSignature) => {
-                self.module.function_defs[src_idx].code.locals = LocalsSignatureIndex::new(new_idx)
-            }
-            (TypeSignature, StructHandle) => {
-                // For this and the other signatures, the source index will be picked from
-                // only the ones that have struct handles in them.
-                src_idx = self.type_sig_structs[src_idx].into_index();
-                self.module.type_signatures[src_idx]
-                    .0
-                    .debug_set_sh_idx(StructHandleIndex::new(new_idx));
+    // For this and the other signatures, the source index is picked from only the ones that have
+    // struct handles in them.
+    fn set_type_sig_struct(&mut self, update: &mut IndexUpdate) {
+        update.src_idx = self.type_sig_structs[update.src_idx].into_index();
+        self.module.type_signatures[update.src_idx]
+            .0
+            .debug_set_sh_idx(StructHandleIndex::new(update.new_idx));
+    }
+
+    fn set_function_sig_struct(&mut self, update: &mut IndexUpdate) {
+        match self.function_sig_structs[update.src_idx] {
+            FunctionSignatureTokenIndex::ReturnType(actual_src_idx, ret_idx) => {
+                update.src_idx = actual_src_idx.into_index();
+                self.module.function_signatures[update.src_idx].return_types[ret_idx]
+                    .debug_set_sh_idx(StructHandleIndex::new(update.new_idx));
             }
-            (FunctionSignature, StructHandle) => match &self.function_sig_structs[src_idx] {
-                FunctionSignatureTokenIndex::ReturnType(actual_src_idx, ret_idx) => {
-                    src_idx = actual_src_idx.into_index();
-                    self.module.function_signatures[src_idx].return_types[*ret_idx]
-                        .debug_set_sh_idx(StructHandleIndex::new(new_idx));
-                }
-                FunctionSignatureTokenIndex::ArgType(actual_src_idx, arg_idx) => {
-                    src_idx = actual_src_idx.into_index();
-                    self.module.function_signatures[src_idx].arg_types[*arg_idx]
-                        .debug_set_sh_idx(StructHandleIndex::new(new_idx));
-                }
-            },
-            (LocalsSignature, StructHandle) => {
-                let (actual_src_idx, arg_idx) = self.locals_sig_structs[src_idx];
-                src_idx = actual_src_idx.into_index();
-                self.module.locals_signatures[src_idx].0[arg_idx]
-                    .debug_set_sh_idx(StructHandleIndex::new(new_idx));
+            FunctionSignatureTokenIndex::ArgType(actual_src_idx, arg_idx) => {
+                update.src_idx = actual_src_idx.into_index();
+                self.module.function_signatures[update.src_idx].arg_types[arg_idx]
+                    .debug_set_sh_idx(StructHandleIndex::new(update.new_idx));
             }
-            _ => panic!("Invalid pointer kind: {:?} -> {:?}", src_kind, dst_kind),
         }
+    }
 
-        Some(VerificationError {
-            kind: src_kind,
-            idx: src_idx,
-            err,
-        })
+    fn set_locals_sig_struct(&mut self, update: &mut IndexUpdate) {
+        let (actual_src_idx, arg_idx) = self.locals_sig_structs[update.src_idx];
+        update.src_idx = actual_src_idx.into_index();
+        self.module.locals_signatures[update.src_idx].0[arg_idx]
+            .debug_set_sh_idx(StructHandleIndex::new(update.new_idx));
     }
 
     /// Returns the indexes of type signatures that contain struct handles inside them.
@@ -464,3 +683,18 @@ enum FunctionSignatureTokenIndex {
     ReturnType(FunctionSignatureIndex, usize),
     ArgType(FunctionSignatureIndex, usize),
 }
+
+/// An index being set out of bounds by a `SetIndex`.
+struct IndexUpdate {
+    /// The entry the index is in. Setters that report the error for another entry change it.
+    src_idx: usize,
+    /// The size of the table the index points into.
+    dst_count: usize,
+    new_idx: TableIndex,
+    /// The error the new index causes. Setters that cause another error change it, or set it to
+    /// `None` if the index can't be set.
+    err: Option<VMStaticViolation>,
+}
+
+/// Sets an index of a particular kind of pointer, as described by an `IndexUpdate`.
+type SetIndex = fn(&mut ApplyOutOfBoundsContext, &mut IndexUpdate);
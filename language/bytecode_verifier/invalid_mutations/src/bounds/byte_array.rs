@@ -0,0 +1,106 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::undo::UndoLog;
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        ByteArrayPoolIndex, Bytecode, CompiledModuleMut, FunctionDefinitionIndex, TableIndex,
+    },
+    internals::ModuleIndex,
+};
+
+/// Represents a single mutation making the operand of an `LdByteArray` instruction out of bounds.
+///
+/// No table in a module points into the byte array pool, so `OutOfBoundsMutation` never reaches
+/// it, and `CodeUnitBoundsMutation` only rarely picks an `LdByteArray` among all the instructions
+/// that have an index.
+#[derive(Debug)]
+pub struct ByteArrayBoundsMutation {
+    site: PropIndex,
+    offset: usize,
+}
+
+impl ByteArrayBoundsMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), 0..16 as usize).prop_map(|(site, offset)| Self { site, offset })
+    }
+}
+
+impl AsRef<PropIndex> for ByteArrayBoundsMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.site
+    }
+}
+
+/// Context for applying a list of `ByteArrayBoundsMutation` instances.
+///
+/// Every mutation is applied to a different `LdByteArray` instruction across all the function
+/// definitions in the module. If there are fewer such instructions than mutations, the extra
+/// mutations are dropped.
+pub struct ApplyByteArrayBoundsContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<ByteArrayBoundsMutation>,
+}
+
+impl<'a> ApplyByteArrayBoundsContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<ByteArrayBoundsMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let (results, _) = self.apply_with_undo();
+        results
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning a log that can be used to
+    /// undo them.
+    pub fn apply_with_undo(self) -> (Vec<VerificationError>, UndoLog) {
+        let Self { module, mutations } = self;
+        let sites: Vec<_> = module
+            .function_defs
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, function_def)| {
+                function_def
+                    .code
+                    .code
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, bytecode)| match bytecode {
+                        Bytecode::LdByteArray(_) => true,
+                        _ => false,
+                    })
+                    .map(move |(bytecode_idx, _)| (idx, bytecode_idx))
+            })
+            .collect();
+        let picked = pick_slice_idxs(sites.len(), &mutations);
+
+        let byte_array_pool_len = module.byte_array_pool.len();
+        let mut undo = UndoLog::new();
+        let results = mutations
+            .iter()
+            .zip(picked)
+            .map(|(mutation, picked_idx)| {
+                let (idx, bytecode_idx) = sites[picked_idx];
+                undo.record_bytecode(module, idx, bytecode_idx);
+                let new_idx = byte_array_pool_len + mutation.offset;
+                module.function_defs[idx].code.code[bytecode_idx] =
+                    Bytecode::LdByteArray(ByteArrayPoolIndex::new(new_idx as TableIndex));
+                VerificationError::in_function(
+                    FunctionDefinitionIndex::new(idx as TableIndex),
+                    VMStaticViolation::CodeUnitIndexOutOfBounds(
+                        ByteArrayPoolIndex::KIND,
+                        bytecode_idx,
+                        byte_array_pool_len,
+                        new_idx,
+                    ),
+                )
+            })
+            .collect();
+        (results, undo)
+    }
+}
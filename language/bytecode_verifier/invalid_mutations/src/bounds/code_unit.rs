@@ -1,15 +1,17 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use crate::undo::UndoLog;
 use proptest::{prelude::*, sample::Index as PropIndex};
 use proptest_helpers::pick_slice_idxs;
+use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use vm::{
     errors::{VMStaticViolation, VerificationError},
     file_format::{
         AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CodeOffset, CompiledModuleMut,
-        FieldDefinitionIndex, FunctionHandleIndex, LocalIndex, StringPoolIndex,
-        StructDefinitionIndex, TableIndex, NO_TYPE_ACTUALS,
+        FieldDefinitionIndex, FunctionDefinitionIndex, FunctionHandleIndex, LocalIndex,
+        StringPoolIndex, StructDefinitionIndex, TableIndex, NO_TYPE_ACTUALS,
     },
     internals::ModuleIndex,
     IndexKind,
@@ -42,6 +44,27 @@ impl AsRef<PropIndex> for CodeUnitBoundsMutation {
     }
 }
 
+/// A serializable description of a `CodeUnitBoundsMutation` once it has been applied to a
+/// particular module.
+///
+/// Descriptions returned by `ApplyCodeUnitBoundsContext::apply_and_describe` can be stored along
+/// with the module they were applied to, and replayed with
+/// `ApplyCodeUnitBoundsContext::apply_from_description`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CodeUnitBoundsDescription {
+    /// The function definition whose code was mutated.
+    pub function_def: usize,
+    /// The offset of the instruction that was mutated.
+    pub bytecode: usize,
+    /// How far past the end of the destination table the new index is.
+    pub offset: usize,
+}
+
+/// Context for applying a list of `CodeUnitBoundsMutation` instances.
+///
+/// To mutate the `main` function of a script, apply this to the module returned by
+/// `CompiledScriptMut::into_module` and convert the result back with
+/// `CompiledModuleMut::into_script`.
 pub struct ApplyCodeUnitBoundsContext<'a> {
     module: &'a mut CompiledModuleMut,
     // This is so apply_one can be called after mutations has been iterated on.
@@ -121,7 +144,52 @@ impl<'a> ApplyCodeUnitBoundsContext<'a> {
         }
     }
 
-    pub fn apply(mut self) -> Vec<VerificationError> {
+    pub fn apply(self) -> Vec<VerificationError> {
+        let (results, _) = self.apply_and_describe();
+        results
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning descriptions of them that can
+    /// be replayed on the same module with `apply_from_description`.
+    pub fn apply_and_describe(
+        mut self,
+    ) -> (Vec<VerificationError>, Vec<CodeUnitBoundsDescription>) {
+        let descriptions = self.describe();
+        let results = descriptions
+            .iter()
+            .map(|description| self.apply_one(description))
+            .collect();
+        (results, descriptions)
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning a log that can be used to
+    /// undo them.
+    pub fn apply_with_undo(mut self) -> (Vec<VerificationError>, UndoLog) {
+        let mut undo = UndoLog::new();
+        let results = self
+            .describe()
+            .iter()
+            .map(|description| {
+                undo.record_bytecode(self.module, description.function_def, description.bytecode);
+                self.apply_one(description)
+            })
+            .collect();
+        (results, undo)
+    }
+
+    /// Replays mutations described by `apply_and_describe` on the module they were applied to.
+    pub fn apply_from_description(
+        module: &'a mut CompiledModuleMut,
+        descriptions: &[CodeUnitBoundsDescription],
+    ) -> Vec<VerificationError> {
+        let mut context = Self::new(module, vec![]);
+        descriptions
+            .iter()
+            .map(|description| context.apply_one(description))
+            .collect()
+    }
+
+    fn describe(&mut self) -> Vec<CodeUnitBoundsDescription> {
         let function_def_len = self.module.function_defs.len();
 
         let mut mutation_map = BTreeMap::new();
@@ -137,34 +205,62 @@ impl<'a> ApplyCodeUnitBoundsContext<'a> {
                 .push(mutation);
         }
 
-        let mut results = vec![];
-
+        let mut descriptions = vec![];
         for (idx, mutations) in mutation_map {
-            results.extend(self.apply_one(idx, mutations));
+            descriptions.extend(self.describe_one(idx, mutations));
         }
-        results
+        descriptions
     }
 
-    fn apply_one(
-        &mut self,
+    fn describe_one(
+        &self,
         idx: usize,
         mutations: Vec<CodeUnitBoundsMutation>,
-    ) -> Vec<VerificationError> {
+    ) -> Vec<CodeUnitBoundsDescription> {
         // For this function def, find all the places where a bounds mutation can be applied.
+        // Function defs whose locals signature is already out of bounds are left alone, since the
+        // offsets reported for locals would be meaningless.
+        let code = &self.module.function_defs[idx].code;
+        if self
+            .module
+            .locals_signatures
+            .get(code.locals.into_index())
+            .is_none()
+        {
+            return vec![];
+        }
+
+        let interesting_offsets: Vec<usize> = (0..code.code.len())
+            .filter(|bytecode_idx| is_interesting(&code.code[*bytecode_idx]))
+            .collect();
+        let to_mutate = pick_slice_idxs(interesting_offsets.len(), &mutations);
+
+        mutations
+            .iter()
+            .zip(to_mutate)
+            .map(
+                |(mutation, interesting_offsets_idx)| CodeUnitBoundsDescription {
+                    function_def: idx,
+                    bytecode: interesting_offsets[interesting_offsets_idx],
+                    offset: mutation.offset,
+                },
+            )
+            .collect()
+    }
+
+    fn apply_one(&mut self, description: &CodeUnitBoundsDescription) -> VerificationError {
+        let idx = description.function_def;
+        let bytecode_idx = description.bytecode;
+        let offset = description.offset;
+
         let (code_len, locals_len) = {
-            let code = &mut self.module.function_defs[idx].code;
+            let code = &self.module.function_defs[idx].code;
             (
                 code.code.len(),
                 self.module.locals_signatures[code.locals.into_index()].len(),
             )
         };
 
-        let code = &mut self.module.function_defs[idx].code.code;
-        let interesting_offsets: Vec<usize> = (0..code.len())
-            .filter(|bytecode_idx| is_interesting(&code[*bytecode_idx]))
-            .collect();
-        let to_mutate = pick_slice_idxs(interesting_offsets.len(), &mutations);
-
         // These have to be computed upfront because self.module is being mutated below.
         let address_pool_len = self.module.address_pool.len();
         let string_pool_len = self.module.string_pool.len();
@@ -173,132 +269,120 @@ impl<'a> ApplyCodeUnitBoundsContext<'a> {
         let field_defs_len = self.module.field_defs.len();
         let struct_defs_len = self.module.struct_defs.len();
 
-        mutations
-            .iter()
-            .zip(to_mutate)
-            .map(|(mutation, interesting_offsets_idx)| {
-                let bytecode_idx = interesting_offsets[interesting_offsets_idx];
-                let offset = mutation.offset;
-                use Bytecode::*;
-
-                let (new_bytecode, err) = match code[bytecode_idx] {
-                    LdAddr(_) => new_bytecode!(
-                        address_pool_len,
-                        bytecode_idx,
-                        offset,
-                        AddressPoolIndex,
-                        LdAddr
-                    ),
-                    LdStr(_) => new_bytecode!(
-                        string_pool_len,
-                        bytecode_idx,
-                        offset,
-                        StringPoolIndex,
-                        LdStr
-                    ),
-                    LdByteArray(_) => new_bytecode!(
-                        byte_array_pool_len,
-                        bytecode_idx,
-                        offset,
-                        ByteArrayPoolIndex,
-                        LdByteArray
-                    ),
-                    ImmBorrowField(_) => new_bytecode!(
-                        field_defs_len,
-                        bytecode_idx,
-                        offset,
-                        FieldDefinitionIndex,
-                        ImmBorrowField
-                    ),
-                    MutBorrowField(_) => new_bytecode!(
-                        field_defs_len,
-                        bytecode_idx,
-                        offset,
-                        FieldDefinitionIndex,
-                        MutBorrowField
-                    ),
-                    Call(_, _) => struct_bytecode!(
-                        function_handles_len,
-                        bytecode_idx,
-                        offset,
-                        FunctionHandleIndex,
-                        Call
-                    ),
-                    Pack(_, _) => struct_bytecode!(
-                        struct_defs_len,
-                        bytecode_idx,
-                        offset,
-                        StructDefinitionIndex,
-                        Pack
-                    ),
-                    Unpack(_, _) => struct_bytecode!(
-                        struct_defs_len,
-                        bytecode_idx,
-                        offset,
-                        StructDefinitionIndex,
-                        Unpack
-                    ),
-                    Exists(_, _) => struct_bytecode!(
-                        struct_defs_len,
-                        bytecode_idx,
-                        offset,
-                        StructDefinitionIndex,
-                        Exists
-                    ),
-                    BorrowGlobal(_, _) => struct_bytecode!(
-                        struct_defs_len,
-                        bytecode_idx,
-                        offset,
-                        StructDefinitionIndex,
-                        BorrowGlobal
-                    ),
-                    MoveFrom(_, _) => struct_bytecode!(
-                        struct_defs_len,
-                        bytecode_idx,
-                        offset,
-                        StructDefinitionIndex,
-                        MoveFrom
-                    ),
-                    MoveToSender(_, _) => struct_bytecode!(
-                        struct_defs_len,
-                        bytecode_idx,
-                        offset,
-                        StructDefinitionIndex,
-                        MoveToSender
-                    ),
-                    BrTrue(_) => code_bytecode!(code_len, bytecode_idx, offset, BrTrue),
-                    BrFalse(_) => code_bytecode!(code_len, bytecode_idx, offset, BrFalse),
-                    Branch(_) => code_bytecode!(code_len, bytecode_idx, offset, Branch),
-                    CopyLoc(_) => locals_bytecode!(locals_len, bytecode_idx, offset, CopyLoc),
-                    MoveLoc(_) => locals_bytecode!(locals_len, bytecode_idx, offset, MoveLoc),
-                    StLoc(_) => locals_bytecode!(locals_len, bytecode_idx, offset, StLoc),
-                    MutBorrowLoc(_) => {
-                        locals_bytecode!(locals_len, bytecode_idx, offset, MutBorrowLoc)
-                    }
-                    ImmBorrowLoc(_) => {
-                        locals_bytecode!(locals_len, bytecode_idx, offset, ImmBorrowLoc)
-                    }
-
-                    // List out the other options explicitly so there's a compile error if a new
-                    // bytecode gets added.
-                    FreezeRef | Pop | Ret | LdConst(_) | LdTrue | LdFalse | ReadRef | WriteRef
-                    | Add | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor | Or | And | Not | Eq
-                    | Neq | Lt | Gt | Le | Ge | Abort | GetTxnGasUnitPrice | GetTxnMaxGasUnits
-                    | GetGasRemaining | GetTxnSenderAddress | CreateAccount
-                    | GetTxnSequenceNumber | GetTxnPublicKey => {
-                        panic!("Bytecode has no internal index: {:?}", code[bytecode_idx])
-                    }
-                };
-
-                code[bytecode_idx] = new_bytecode;
-
-                VerificationError {
-                    kind: IndexKind::FunctionDefinition,
-                    idx,
-                    err,
-                }
-            })
-            .collect()
+        let code = &mut self.module.function_defs[idx].code.code;
+        use Bytecode::*;
+
+        let (new_bytecode, err) = match code[bytecode_idx] {
+            LdAddr(_) => new_bytecode!(
+                address_pool_len,
+                bytecode_idx,
+                offset,
+                AddressPoolIndex,
+                LdAddr
+            ),
+            LdStr(_) => new_bytecode!(
+                string_pool_len,
+                bytecode_idx,
+                offset,
+                StringPoolIndex,
+                LdStr
+            ),
+            LdByteArray(_) => new_bytecode!(
+                byte_array_pool_len,
+                bytecode_idx,
+                offset,
+                ByteArrayPoolIndex,
+                LdByteArray
+            ),
+            ImmBorrowField(_) => new_bytecode!(
+                field_defs_len,
+                bytecode_idx,
+                offset,
+                FieldDefinitionIndex,
+                ImmBorrowField
+            ),
+            MutBorrowField(_) => new_bytecode!(
+                field_defs_len,
+                bytecode_idx,
+                offset,
+                FieldDefinitionIndex,
+                MutBorrowField
+            ),
+            Call(_, _) => struct_bytecode!(
+                function_handles_len,
+                bytecode_idx,
+                offset,
+                FunctionHandleIndex,
+                Call
+            ),
+            Pack(_, _) => struct_bytecode!(
+                struct_defs_len,
+                bytecode_idx,
+                offset,
+                StructDefinitionIndex,
+                Pack
+            ),
+            Unpack(_, _) => struct_bytecode!(
+                struct_defs_len,
+                bytecode_idx,
+                offset,
+                StructDefinitionIndex,
+                Unpack
+            ),
+            Exists(_, _) => struct_bytecode!(
+                struct_defs_len,
+                bytecode_idx,
+                offset,
+                StructDefinitionIndex,
+                Exists
+            ),
+            BorrowGlobal(_, _) => struct_bytecode!(
+                struct_defs_len,
+                bytecode_idx,
+                offset,
+                StructDefinitionIndex,
+                BorrowGlobal
+            ),
+            MoveFrom(_, _) => struct_bytecode!(
+                struct_defs_len,
+                bytecode_idx,
+                offset,
+                StructDefinitionIndex,
+                MoveFrom
+            ),
+            MoveToSender(_, _) => struct_bytecode!(
+                struct_defs_len,
+                bytecode_idx,
+                offset,
+                StructDefinitionIndex,
+                MoveToSender
+            ),
+            BrTrue(_) => code_bytecode!(code_len, bytecode_idx, offset, BrTrue),
+            BrFalse(_) => code_bytecode!(code_len, bytecode_idx, offset, BrFalse),
+            Branch(_) => code_bytecode!(code_len, bytecode_idx, offset, Branch),
+            CopyLoc(_) => locals_bytecode!(locals_len, bytecode_idx, offset, CopyLoc),
+            MoveLoc(_) => locals_bytecode!(locals_len, bytecode_idx, offset, MoveLoc),
+            StLoc(_) => locals_bytecode!(locals_len, bytecode_idx, offset, StLoc),
+            MutBorrowLoc(_) => {
+                locals_bytecode!(locals_len, bytecode_idx, offset, MutBorrowLoc)
+            }
+            ImmBorrowLoc(_) => {
+                locals_bytecode!(locals_len, bytecode_idx, offset, ImmBorrowLoc)
+            }
+
+            // List out the other options explicitly so there's a compile error if a new
+            // bytecode gets added.
+            FreezeRef | Pop | Ret | LdConst(_) | LdTrue | LdFalse | ReadRef | WriteRef | Add
+            | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor | Or | And | Not | Eq | Neq | Lt
+            | Gt | Le | Ge | Abort | GetTxnGasUnitPrice | GetTxnMaxGasUnits | GetGasRemaining
+            | GetTxnSenderAddress | CreateAccount | GetTxnSequenceNumber | GetTxnPublicKey => {
+                panic!("Bytecode has no internal index: {:?}", code[bytecode_idx])
+            }
+        };
+
+        code[bytecode_idx] = new_bytecode;
+
+        VerificationError::in_function(FunctionDefinitionIndex::new(idx as TableIndex), err)
     }
 }
 
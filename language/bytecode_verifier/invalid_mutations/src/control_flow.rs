@@ -0,0 +1,226 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::undo::UndoLog;
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use std::collections::BTreeMap;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{Bytecode, CodeOffset, CompiledModuleMut, FunctionDefinitionIndex, TableIndex},
+    IndexKind,
+};
+
+/// Represents a single mutation to the control flow of a code unit.
+///
+/// Use `ControlFlowMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyControlFlowContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct ControlFlowMutation {
+    function_def: PropIndex,
+    bytecode: PropIndex,
+    kind: ControlFlowMutationKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum ControlFlowMutationKind {
+    /// Retargets an existing branch to this many instructions past the end of the function.
+    BranchPastEnd(usize),
+    /// Removes the final `Ret` of the function, so that execution can fall off its end.
+    ///
+    /// Functions that don't end with `Ret`, or whose final `Ret` is the target of a branch, are
+    /// left alone: removing the instruction would also leave the branch out of bounds.
+    RemoveReturn,
+    /// Replaces an instruction that isn't a branch with a jump to an arbitrary offset outside of
+    /// the function.
+    JumpToNowhere(PropIndex),
+}
+
+impl ControlFlowMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (
+            any::<PropIndex>(),
+            any::<PropIndex>(),
+            ControlFlowMutationKind::strategy(),
+        )
+            .prop_map(|(function_def, bytecode, kind)| Self {
+                function_def,
+                bytecode,
+                kind,
+            })
+    }
+
+    pub fn kind(&self) -> &ControlFlowMutationKind {
+        &self.kind
+    }
+}
+
+impl AsRef<PropIndex> for ControlFlowMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.bytecode
+    }
+}
+
+impl ControlFlowMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            (0..16 as usize).prop_map(ControlFlowMutationKind::BranchPastEnd),
+            Just(ControlFlowMutationKind::RemoveReturn),
+            any::<PropIndex>().prop_map(ControlFlowMutationKind::JumpToNowhere),
+        ]
+    }
+}
+
+/// Context for applying a list of `ControlFlowMutation` instances.
+///
+/// Branches past the end of a function are reported by the bounds checker as
+/// `CodeUnitIndexOutOfBounds`, and functions that fall off their end are reported by the code unit
+/// verifier as `InvalidFallThrough`.
+pub struct ApplyControlFlowContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<ControlFlowMutation>,
+}
+
+impl<'a> ApplyControlFlowContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<ControlFlowMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let (results, _) = self.apply_with_undo();
+        results
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning a log that can be used to
+    /// undo them.
+    pub fn apply_with_undo(mut self) -> (Vec<VerificationError>, UndoLog) {
+        let function_def_len = self.module.function_defs.len();
+
+        let mut mutation_map = BTreeMap::new();
+        for mutation in std::mem::replace(&mut self.mutations, vec![]) {
+            let picked_idx = mutation.function_def.index(function_def_len);
+            mutation_map
+                .entry(picked_idx)
+                .or_insert_with(|| vec![])
+                .push(mutation);
+        }
+
+        let mut results = vec![];
+        let mut undo = UndoLog::new();
+        for (idx, mutations) in mutation_map {
+            results.extend(self.apply_one(idx, mutations, &mut undo));
+        }
+        (results, undo)
+    }
+
+    fn apply_one(
+        &mut self,
+        idx: usize,
+        mutations: Vec<ControlFlowMutation>,
+        undo: &mut UndoLog,
+    ) -> Vec<VerificationError> {
+        if self.module.function_defs[idx].is_native() {
+            return vec![];
+        }
+        // Removing a return changes the length of the code, so the whole code is recorded.
+        undo.record_code(self.module, idx);
+        let function_def = &mut self.module.function_defs[idx];
+        let code = &mut function_def.code.code;
+
+        // Returns are removed first, so that the other mutations see the final length of the code.
+        let (removals, mutations): (Vec<_>, Vec<_>) =
+            mutations
+                .into_iter()
+                .partition(|mutation| match mutation.kind {
+                    ControlFlowMutationKind::RemoveReturn => true,
+                    _ => false,
+                });
+        let mut removed_return = false;
+        for _ in removals {
+            if can_remove_return(code) {
+                code.pop();
+                removed_return = true;
+            }
+        }
+
+        let code_len = code.len();
+        let (retargets, jumps): (Vec<_>, Vec<_>) =
+            mutations
+                .into_iter()
+                .partition(|mutation| match mutation.kind {
+                    ControlFlowMutationKind::BranchPastEnd(_) => true,
+                    _ => false,
+                });
+        let (branch_offsets, other_offsets): (Vec<usize>, Vec<usize>) =
+            (0..code_len).partition(|bytecode_idx| code[*bytecode_idx].offset().is_some());
+
+        let mut errs = vec![];
+
+        let picked = pick_slice_idxs(branch_offsets.len(), &retargets);
+        for (mutation, picked_idx) in retargets.iter().zip(picked) {
+            let bytecode_idx = branch_offsets[picked_idx];
+            let target = match mutation.kind {
+                ControlFlowMutationKind::BranchPastEnd(offset) => code_len + offset,
+                _ => unreachable!("only branch retargets are picked here"),
+            };
+            code[bytecode_idx] = match code[bytecode_idx] {
+                Bytecode::BrTrue(_) => Bytecode::BrTrue(target as CodeOffset),
+                Bytecode::BrFalse(_) => Bytecode::BrFalse(target as CodeOffset),
+                Bytecode::Branch(_) => Bytecode::Branch(target as CodeOffset),
+                ref bytecode => panic!("Bytecode has no branch offset: {:?}", bytecode),
+            };
+            errs.push(out_of_bounds(bytecode_idx, code_len, target));
+        }
+
+        let picked = pick_slice_idxs(other_offsets.len(), &jumps);
+        for (mutation, picked_idx) in jumps.iter().zip(picked) {
+            let bytecode_idx = other_offsets[picked_idx];
+            let target = match &mutation.kind {
+                ControlFlowMutationKind::JumpToNowhere(target) => {
+                    // Pick any offset that a branch can encode but that isn't in the code.
+                    code_len + target.index(CodeOffset::max_value() as usize + 1 - code_len)
+                }
+                _ => unreachable!("only jumps to nowhere are picked here"),
+            };
+            code[bytecode_idx] = Bytecode::Branch(target as CodeOffset);
+            errs.push(out_of_bounds(bytecode_idx, code_len, target));
+        }
+
+        // A jump to nowhere may have replaced the new final instruction with a branch, so this
+        // has to be checked last.
+        let falls_through = code
+            .last()
+            .map_or(true, |bytecode| !bytecode.is_unconditional_branch());
+        if removed_return && falls_through {
+            errs.push(VMStaticViolation::InvalidFallThrough);
+        }
+
+        errs.into_iter()
+            .map(|err| {
+                VerificationError::in_function(FunctionDefinitionIndex::new(idx as TableIndex), err)
+            })
+            .collect()
+    }
+}
+
+fn can_remove_return(code: &[Bytecode]) -> bool {
+    match code.last() {
+        Some(Bytecode::Ret) => {
+            let last_offset = (code.len() - 1) as CodeOffset;
+            !code
+                .iter()
+                .any(|bytecode| bytecode.offset() == Some(&last_offset))
+        }
+        _ => false,
+    }
+}
+
+fn out_of_bounds(bytecode_idx: usize, code_len: usize, target: usize) -> VMStaticViolation {
+    VMStaticViolation::CodeUnitIndexOutOfBounds(
+        IndexKind::CodeDefinition,
+        bytecode_idx,
+        code_len,
+        target,
+    )
+}
@@ -0,0 +1,216 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+use vm::errors::VerificationError;
+
+/// Defines an enum along with a `variants` method returning every variant, so that the coverage
+/// report can't silently leave a new variant out.
+macro_rules! define_with_variants {
+    ($(#[$attr: meta])* pub enum $name: ident { $($variant: ident,)* }) => {
+        $(#[$attr])*
+        #[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        pub enum $name {
+            $($variant,)*
+        }
+
+        impl $name {
+            /// Returns every variant, in the order they are declared.
+            pub fn variants() -> &'static [$name] {
+                &[$($name::$variant,)*]
+            }
+        }
+    };
+}
+
+define_with_variants! {
+    /// A pass of the bytecode verifier that mutations are meant to trigger.
+    pub enum VerifierPass {
+        BoundsChecker,
+        DuplicationChecker,
+        SignatureChecker,
+        ResourceTransitiveChecker,
+        RecursiveStructDefChecker,
+        StackUsageVerifier,
+        AcquiresVerifier,
+        CodeUnitVerifier,
+        MainSignature,
+        ModuleAddress,
+    }
+}
+
+define_with_variants! {
+    /// A class of mutations in this crate, named after the mutation type that generates it.
+    pub enum MutationClass {
+        OutOfBounds,
+        CodeUnitBounds,
+        ByteArrayBounds,
+        ControlFlow,
+        Duplication,
+        Deletion,
+        Identifier,
+        FieldRange,
+        SelfHandle,
+        DoubleRef,
+        FieldRef,
+        MalformedToken,
+        RecursiveStruct,
+        ResourceField,
+        ResourceCode,
+        Locals,
+        TypeConfusion,
+        StackUsage,
+        Acquires,
+        MainSignature,
+    }
+}
+
+impl MutationClass {
+    /// The verifier passes that mutations of this class are meant to trigger.
+    ///
+    /// Most classes target a single pass. Control flow mutations are caught by the bounds checker
+    /// if they branch out of the function, and by the code unit verifier if they make it fall off
+    /// its end. Self handle mutations are caught by the module address check if they change the
+    /// address, and by the duplication checker if they alias a dependency.
+    pub fn target_passes(self) -> &'static [VerifierPass] {
+        use MutationClass::*;
+        use VerifierPass::*;
+
+        match self {
+            OutOfBounds | CodeUnitBounds | ByteArrayBounds => &[BoundsChecker],
+            ControlFlow => &[BoundsChecker, CodeUnitVerifier],
+            SelfHandle => &[DuplicationChecker, ModuleAddress],
+            Duplication | Deletion | Identifier | FieldRange => &[DuplicationChecker],
+            DoubleRef | FieldRef | MalformedToken => &[SignatureChecker],
+            RecursiveStruct => &[RecursiveStructDefChecker],
+            ResourceField => &[ResourceTransitiveChecker],
+            ResourceCode | Locals | TypeConfusion => &[CodeUnitVerifier],
+            StackUsage => &[StackUsageVerifier],
+            Acquires => &[AcquiresVerifier],
+            MutationClass::MainSignature => &[VerifierPass::MainSignature],
+        }
+    }
+}
+
+/// What was observed for a mutation class run against a single verifier pass over a test run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PassCoverage {
+    /// The number of test cases recorded.
+    pub cases: usize,
+    /// The number of test cases where the pass reported at least one of the expected errors.
+    pub exercised: usize,
+    /// The codes of errors the pass reported that weren't expected.
+    pub unexpected_codes: BTreeSet<u32>,
+}
+
+/// Collects, over a test run, which verifier passes the mutations actually exercised.
+///
+/// Call `record` once per test case with the errors returned by the `Apply*Context` and the errors
+/// returned by the verifier pass under test.
+#[derive(Clone, Debug, Default)]
+pub struct CoverageReport {
+    entries: BTreeMap<(MutationClass, VerifierPass), PassCoverage>,
+}
+
+impl CoverageReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a test case where mutations of `class` were checked by `pass`.
+    ///
+    /// Errors are compared by code only, since the point is to find out which kinds of errors each
+    /// pass produced rather than whether the expected errors were exact.
+    pub fn record(
+        &mut self,
+        class: MutationClass,
+        pass: VerifierPass,
+        expected: &[VerificationError],
+        actual: &[VerificationError],
+    ) {
+        debug_assert!(
+            class.target_passes().contains(&pass),
+            "{:?} mutations don't target {:?}",
+            class,
+            pass,
+        );
+        let expected_codes: BTreeSet<_> = expected.iter().map(VerificationError::code).collect();
+        let coverage = self.entries.entry((class, pass)).or_default();
+        coverage.cases += 1;
+        if actual
+            .iter()
+            .any(|err| expected_codes.contains(&err.code()))
+        {
+            coverage.exercised += 1;
+        }
+        coverage.unexpected_codes.extend(
+            actual
+                .iter()
+                .map(VerificationError::code)
+                .filter(|code| !expected_codes.contains(code)),
+        );
+    }
+
+    /// Returns what was recorded for mutations of `class` checked by `pass`, if anything.
+    pub fn coverage(&self, class: MutationClass, pass: VerifierPass) -> Option<&PassCoverage> {
+        self.entries.get(&(class, pass))
+    }
+
+    /// Returns the passes that reported an expected error at least once.
+    pub fn exercised_passes(&self) -> BTreeSet<VerifierPass> {
+        self.entries
+            .iter()
+            .filter(|(_, coverage)| coverage.exercised > 0)
+            .map(|((_, pass), _)| *pass)
+            .collect()
+    }
+
+    /// Returns the passes that never reported an expected error.
+    pub fn unexercised_passes(&self) -> BTreeSet<VerifierPass> {
+        let exercised = self.exercised_passes();
+        VerifierPass::variants()
+            .iter()
+            .copied()
+            .filter(|pass| !exercised.contains(pass))
+            .collect()
+    }
+
+    /// Returns the codes of the errors that weren't expected, for every class and pass that
+    /// reported some.
+    pub fn unexpected_codes(&self) -> BTreeMap<(MutationClass, VerifierPass), &BTreeSet<u32>> {
+        self.entries
+            .iter()
+            .filter(|(_, coverage)| !coverage.unexpected_codes.is_empty())
+            .map(|(key, coverage)| (*key, &coverage.unexpected_codes))
+            .collect()
+    }
+}
+
+impl fmt::Display for CoverageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let exercised = self.exercised_passes();
+        for pass in VerifierPass::variants() {
+            let status = if exercised.contains(pass) {
+                "exercised"
+            } else {
+                "not exercised"
+            };
+            writeln!(f, "{:?}: {}", pass, status)?;
+        }
+        for ((class, pass), coverage) in &self.entries {
+            write!(
+                f,
+                "{:?} -> {:?}: {}/{} cases exercised",
+                class, pass, coverage.exercised, coverage.cases
+            )?;
+            if !coverage.unexpected_codes.is_empty() {
+                write!(f, ", unexpected codes {:?}", coverage.unexpected_codes)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
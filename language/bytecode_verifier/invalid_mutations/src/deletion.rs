@@ -0,0 +1,261 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use std::collections::BTreeSet;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        Bytecode, CompiledModule, CompiledModuleMut, FieldDefinitionIndex, StructDefinitionIndex,
+        StructFieldInformation, StructHandleIndex, TableIndex,
+    },
+    internals::ModuleIndex,
+    IndexKind,
+};
+
+/// Represents a mutation that deletes a definition while leaving its handle in place.
+///
+/// The tables after the deleted definition are compacted, so unlike with `OutOfBoundsMutation`
+/// every index stays in bounds and the handle is what ends up dangling.
+///
+/// Pool entries aren't deleted: every reference to a compacted pool entry would still resolve to
+/// some other entry, which no verifier pass can tell apart from a valid module.
+///
+/// Use `DeletionMutation::strategy()` to generate them, preferably using `Vec` to generate many at
+/// a time. Then use `ApplyDeletionContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct DeletionMutation {
+    kind: DeletionMutationKind,
+    idx: PropIndex,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeletionMutationKind {
+    /// Deletes a struct definition along with its field definitions.
+    StructDefinition,
+    /// Deletes a function definition.
+    FunctionDefinition,
+}
+
+impl DeletionMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (DeletionMutationKind::strategy(), any::<PropIndex>())
+            .prop_map(|(kind, idx)| Self { kind, idx })
+    }
+}
+
+impl AsRef<PropIndex> for DeletionMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.idx
+    }
+}
+
+impl DeletionMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            Just(DeletionMutationKind::StructDefinition),
+            Just(DeletionMutationKind::FunctionDefinition),
+        ]
+    }
+}
+
+/// Context for applying a list of `DeletionMutation` instances.
+///
+/// Only definitions of handles in the module itself are deleted, and struct definitions are only
+/// deleted if no code refers to them or to their fields. Every deleted definition leaves its handle
+/// unimplemented, and `DuplicationChecker` reports the first unimplemented struct and function
+/// handle, which is the one with the lowest index.
+///
+/// The expected errors are only precise if the module passes the duplication checker to begin
+/// with.
+pub struct ApplyDeletionContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<DeletionMutation>,
+}
+
+impl<'a> ApplyDeletionContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<DeletionMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(mut self) -> Vec<VerificationError> {
+        let (struct_mutations, function_mutations): (Vec<_>, Vec<_>) =
+            std::mem::replace(&mut self.mutations, vec![])
+                .into_iter()
+                .partition(|mutation| mutation.kind == DeletionMutationKind::StructDefinition);
+
+        let mut errs = vec![];
+        if let Some(idx) = self.delete_struct_defs(&struct_mutations) {
+            errs.push(VerificationError::new(
+                IndexKind::StructHandle,
+                idx,
+                VMStaticViolation::UnimplementedHandle,
+            ));
+        }
+        if let Some(idx) = self.delete_function_defs(&function_mutations) {
+            errs.push(VerificationError::new(
+                IndexKind::FunctionHandle,
+                idx,
+                VMStaticViolation::UnimplementedHandle,
+            ));
+        }
+        errs
+    }
+
+    /// Deletes struct definitions, and returns the lowest index of a struct handle left without a
+    /// definition.
+    fn delete_struct_defs(&mut self, mutations: &[DeletionMutation]) -> Option<usize> {
+        // Deleting definitions that code refers to would leave that code dangling as well.
+        let mut referenced_structs = BTreeSet::new();
+        let mut referenced_fields = BTreeSet::new();
+        for function_def in &mut self.module.function_defs {
+            referenced_structs.extend(
+                function_def
+                    .acquires_global_resources
+                    .iter()
+                    .map(|idx| idx.into_index()),
+            );
+            for bytecode in &mut function_def.code.code {
+                if let Some(idx) = struct_def_operand(bytecode) {
+                    referenced_structs.insert(idx.into_index());
+                }
+                if let Some(idx) = field_def_operand(bytecode) {
+                    referenced_fields.insert(idx.into_index());
+                }
+            }
+        }
+
+        let candidates: Vec<_> = self
+            .module
+            .struct_defs
+            .iter()
+            .enumerate()
+            .filter(|(idx, struct_def)| {
+                self.is_implemented_here(struct_def.struct_handle)
+                    && !referenced_structs.contains(idx)
+                    && !field_range(&struct_def.field_information)
+                        .any(|field_idx| referenced_fields.contains(&field_idx))
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(candidates.len(), mutations);
+        let deleted: BTreeSet<_> = picked
+            .iter()
+            .map(|picked_idx| candidates[*picked_idx])
+            .collect();
+        let deleted_fields: BTreeSet<_> = deleted
+            .iter()
+            .flat_map(|idx| field_range(&self.module.struct_defs[*idx].field_information))
+            .collect();
+        let first_handle = deleted
+            .iter()
+            .map(|idx| self.module.struct_defs[*idx].struct_handle.into_index())
+            .min();
+
+        remove_all(&mut self.module.struct_defs, &deleted);
+        remove_all(&mut self.module.field_defs, &deleted_fields);
+        for struct_def in &mut self.module.struct_defs {
+            if let StructFieldInformation::Declared { fields, .. } =
+                &mut struct_def.field_information
+            {
+                *fields = FieldDefinitionIndex::new(compact(fields.into_index(), &deleted_fields));
+            }
+        }
+        for function_def in &mut self.module.function_defs {
+            for idx in &mut function_def.acquires_global_resources {
+                *idx = StructDefinitionIndex::new(compact(idx.into_index(), &deleted));
+            }
+            for bytecode in &mut function_def.code.code {
+                if let Some(idx) = struct_def_operand(bytecode) {
+                    *idx = StructDefinitionIndex::new(compact(idx.into_index(), &deleted));
+                }
+                if let Some(idx) = field_def_operand(bytecode) {
+                    *idx = FieldDefinitionIndex::new(compact(idx.into_index(), &deleted_fields));
+                }
+            }
+        }
+
+        first_handle
+    }
+
+    /// Deletes function definitions, and returns the lowest index of a function handle left without
+    /// a definition.
+    ///
+    /// Code refers to function handles rather than definitions, so nothing else needs updating.
+    fn delete_function_defs(&mut self, mutations: &[DeletionMutation]) -> Option<usize> {
+        let candidates: Vec<_> = self
+            .module
+            .function_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| {
+                let handle = &self.module.function_handles[function_def.function.into_index()];
+                handle.module.0 == CompiledModule::IMPLEMENTED_MODULE_INDEX
+            })
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(candidates.len(), mutations);
+        let deleted: BTreeSet<_> = picked
+            .iter()
+            .map(|picked_idx| candidates[*picked_idx])
+            .collect();
+        let first_handle = deleted
+            .iter()
+            .map(|idx| self.module.function_defs[*idx].function.into_index())
+            .min();
+
+        remove_all(&mut self.module.function_defs, &deleted);
+        first_handle
+    }
+
+    fn is_implemented_here(&self, struct_handle: StructHandleIndex) -> bool {
+        self.module.struct_handles[struct_handle.into_index()]
+            .module
+            .0
+            == CompiledModule::IMPLEMENTED_MODULE_INDEX
+    }
+}
+
+fn field_range(field_information: &StructFieldInformation) -> std::ops::Range<usize> {
+    match field_information {
+        StructFieldInformation::Native => 0..0,
+        StructFieldInformation::Declared {
+            field_count,
+            fields,
+        } => fields.into_index()..fields.into_index() + *field_count as usize,
+    }
+}
+
+fn struct_def_operand(bytecode: &mut Bytecode) -> Option<&mut StructDefinitionIndex> {
+    match bytecode {
+        Bytecode::Pack(idx, _)
+        | Bytecode::Unpack(idx, _)
+        | Bytecode::Exists(idx, _)
+        | Bytecode::BorrowGlobal(idx, _)
+        | Bytecode::MoveFrom(idx, _)
+        | Bytecode::MoveToSender(idx, _) => Some(idx),
+        _ => None,
+    }
+}
+
+fn field_def_operand(bytecode: &mut Bytecode) -> Option<&mut FieldDefinitionIndex> {
+    match bytecode {
+        Bytecode::ImmBorrowField(idx) | Bytecode::MutBorrowField(idx) => Some(idx),
+        _ => None,
+    }
+}
+
+/// Returns the index the entry at `idx` ends up at once the entries in `deleted` are removed.
+fn compact(idx: usize, deleted: &BTreeSet<usize>) -> TableIndex {
+    (idx - deleted.range(..idx).count()) as TableIndex
+}
+
+fn remove_all<T>(entries: &mut Vec<T>, deleted: &BTreeSet<usize>) {
+    let mut idx = 0;
+    entries.retain(|_| {
+        idx += 1;
+        !deleted.contains(&(idx - 1))
+    });
+}
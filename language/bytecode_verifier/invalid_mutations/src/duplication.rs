@@ -0,0 +1,187 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use std::collections::{BTreeMap, BTreeSet};
+use vm::{
+    errors::{DuplicateKey, VMStaticViolation, VerificationError},
+    file_format::CompiledModuleMut,
+    IndexKind,
+};
+
+/// Represents a mutation that makes an entry of a module table a duplicate of an earlier one.
+///
+/// Use `DuplicationMutation::strategy()` to generate them, preferably using `Vec` to generate many
+/// at a time. Then use `ApplyDuplicationContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct DuplicationMutation {
+    kind: DuplicationMutationKind,
+    target: PropIndex,
+    source: PropIndex,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum DuplicationMutationKind {
+    /// Makes a module handle identical to an earlier one.
+    ModuleHandle,
+    /// Gives a struct handle the name of an earlier struct handle from the same module.
+    StructHandle,
+    /// Gives a field the name of an earlier field of the same struct.
+    FieldName,
+}
+
+impl DuplicationMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (
+            DuplicationMutationKind::strategy(),
+            any::<PropIndex>(),
+            any::<PropIndex>(),
+        )
+            .prop_map(|(kind, target, source)| Self {
+                kind,
+                target,
+                source,
+            })
+    }
+}
+
+impl AsRef<PropIndex> for DuplicationMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.target
+    }
+}
+
+impl DuplicationMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            Just(DuplicationMutationKind::ModuleHandle),
+            Just(DuplicationMutationKind::StructHandle),
+            Just(DuplicationMutationKind::FieldName),
+        ]
+    }
+
+    fn index_kind(self) -> IndexKind {
+        match self {
+            DuplicationMutationKind::ModuleHandle => IndexKind::ModuleHandle,
+            DuplicationMutationKind::StructHandle => IndexKind::StructHandle,
+            DuplicationMutationKind::FieldName => IndexKind::FieldDefinition,
+        }
+    }
+
+    fn duplicate_key(self) -> DuplicateKey {
+        match self {
+            DuplicationMutationKind::ModuleHandle => DuplicateKey::Value,
+            DuplicationMutationKind::StructHandle | DuplicationMutationKind::FieldName => {
+                DuplicateKey::Name
+            }
+        }
+    }
+}
+
+/// Context for applying a list of `DuplicationMutation` instances.
+///
+/// Entries are only ever copied from entries that aren't mutated themselves, so every mutated entry
+/// stays a duplicate. `DuplicationChecker` reports the first duplicate of each table, which is the
+/// mutated entry with the lowest index.
+///
+/// The expected errors are only precise if the module passes the duplication checker to begin
+/// with.
+pub struct ApplyDuplicationContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<DuplicationMutation>,
+}
+
+impl<'a> ApplyDuplicationContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<DuplicationMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(mut self) -> Vec<VerificationError> {
+        let mut mutation_map = BTreeMap::new();
+        for mutation in std::mem::replace(&mut self.mutations, vec![]) {
+            mutation_map
+                .entry(mutation.kind)
+                .or_insert_with(|| vec![])
+                .push(mutation);
+        }
+
+        let mut errs = vec![];
+        for (kind, mutations) in mutation_map {
+            if let Some((source, target)) = self.apply_kind(kind, mutations) {
+                errs.push(VerificationError::new(
+                    kind.index_kind(),
+                    target,
+                    VMStaticViolation::DuplicateElement(source, kind.duplicate_key()),
+                ));
+            }
+        }
+        errs
+    }
+
+    /// Applies mutations of a single kind, and returns the lowest index that was made a duplicate
+    /// along with the index it was copied from, as `(source, target)`.
+    fn apply_kind(
+        &mut self,
+        kind: DuplicationMutationKind,
+        mutations: Vec<DuplicationMutation>,
+    ) -> Option<(usize, usize)> {
+        // Entries can only be duplicates of entries in the same group: struct handles of the same
+        // module, or fields of the same struct.
+        let groups: Vec<usize> = match kind {
+            DuplicationMutationKind::ModuleHandle => vec![0; self.module.module_handles.len()],
+            DuplicationMutationKind::StructHandle => self
+                .module
+                .struct_handles
+                .iter()
+                .map(|handle| handle.module.0 as usize)
+                .collect(),
+            DuplicationMutationKind::FieldName => self
+                .module
+                .field_defs
+                .iter()
+                .map(|field_def| field_def.struct_.0 as usize)
+                .collect(),
+        };
+        let candidates: Vec<_> = (0..groups.len())
+            .filter(|idx| (0..*idx).any(|source| groups[source] == groups[*idx]))
+            .collect();
+        let picked = pick_slice_idxs(candidates.len(), &mutations);
+        let targets: BTreeSet<_> = picked
+            .iter()
+            .map(|picked_idx| candidates[*picked_idx])
+            .collect();
+
+        let mut first = None;
+        for (mutation, picked_idx) in mutations.iter().zip(picked) {
+            let target = candidates[picked_idx];
+            let sources: Vec<_> = (0..target)
+                .filter(|source| groups[*source] == groups[target] && !targets.contains(source))
+                .collect();
+            if sources.is_empty() {
+                continue;
+            }
+            let source = sources[mutation.source.index(sources.len())];
+            self.duplicate(kind, source, target);
+            if first.map_or(true, |(_, first_target)| target < first_target) {
+                first = Some((source, target));
+            }
+        }
+        first
+    }
+
+    fn duplicate(&mut self, kind: DuplicationMutationKind, source: usize, target: usize) {
+        match kind {
+            DuplicationMutationKind::ModuleHandle => {
+                self.module.module_handles[target] = self.module.module_handles[source].clone();
+            }
+            DuplicationMutationKind::StructHandle => {
+                self.module.struct_handles[target].name = self.module.struct_handles[source].name;
+            }
+            DuplicationMutationKind::FieldName => {
+                self.module.field_defs[target].name = self.module.field_defs[source].name;
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use vm::errors::{sort_errors, VerificationError};
+
+/// The full set of violations introduced by a list of mutations.
+///
+/// Most verifier checks stop at the first violation they find, so for every check only the
+/// `primary` errors are actually reported. The `secondary` errors are the other violations the
+/// mutations introduced, which a verifier that kept going would have to report as well.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ExpectedErrors {
+    /// The errors the verifier reports, in no particular order.
+    pub primary: Vec<VerificationError>,
+    /// The violations the verifier doesn't get to, in no particular order.
+    pub secondary: Vec<VerificationError>,
+}
+
+impl ExpectedErrors {
+    /// Returns every violation, primary and secondary, sorted with `sort_errors`.
+    pub fn all(&self) -> Vec<VerificationError> {
+        let mut errors: Vec<_> = self
+            .primary
+            .iter()
+            .chain(&self.secondary)
+            .cloned()
+            .collect();
+        sort_errors(&mut errors);
+        errors
+    }
+}
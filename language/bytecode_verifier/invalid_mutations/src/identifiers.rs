@@ -0,0 +1,135 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use std::collections::BTreeSet;
+use vm::{
+    errors::{DuplicateKey, VMStaticViolation, VerificationError},
+    file_format::CompiledModuleMut,
+    IndexKind,
+};
+
+/// The number of characters added to an identifier to make it extremely long.
+const LONG_IDENTIFIER_PADDING: usize = 1 << 16;
+
+/// Represents a mutation that rewrites a string pool entry used as an identifier into a form that
+/// isn't a valid identifier.
+///
+/// Use `IdentifierMutation::strategy()` to generate them, preferably using `Vec` to generate many
+/// at a time. Then use `ApplyIdentifierContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct IdentifierMutation {
+    entry: PropIndex,
+    kind: IdentifierMutationKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IdentifierMutationKind {
+    /// Replaces the identifier with the empty string.
+    Empty,
+    /// Inserts a whitespace character in the middle of the identifier.
+    EmbeddedWhitespace(char),
+    /// Pads the identifier with `LONG_IDENTIFIER_PADDING` underscores.
+    Long,
+}
+
+impl IdentifierMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), IdentifierMutationKind::strategy())
+            .prop_map(|(entry, kind)| Self { entry, kind })
+    }
+}
+
+impl AsRef<PropIndex> for IdentifierMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.entry
+    }
+}
+
+impl IdentifierMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            Just(IdentifierMutationKind::Empty),
+            prop_oneof![Just(' '), Just('\t'), Just('\n')]
+                .prop_map(IdentifierMutationKind::EmbeddedWhitespace),
+            Just(IdentifierMutationKind::Long),
+        ]
+    }
+
+    fn apply(self, identifier: &str) -> String {
+        match self {
+            IdentifierMutationKind::Empty => String::new(),
+            IdentifierMutationKind::EmbeddedWhitespace(whitespace) => {
+                let mut chars: Vec<_> = identifier.chars().collect();
+                let mid = chars.len() / 2;
+                chars.insert(mid, whitespace);
+                chars.into_iter().collect()
+            }
+            IdentifierMutationKind::Long => {
+                format!("{}{}", identifier, "_".repeat(LONG_IDENTIFIER_PADDING))
+            }
+        }
+    }
+}
+
+/// Context for applying a list of `IdentifierMutation` instances.
+///
+/// Each mutation is applied to a different string pool entry that names a module, struct, function
+/// or field. The verifier doesn't validate the contents of identifiers, so the only errors these
+/// mutations cause come from the duplication checker: every emptied entry past the first one
+/// duplicates it. Whitespace and padding are added the same way to every entry, so they keep
+/// distinct entries distinct.
+///
+/// The expected errors are only precise if the module passes the duplication checker to begin
+/// with, and if none of its strings contain whitespace or are as long as padded identifiers.
+pub struct ApplyIdentifierContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<IdentifierMutation>,
+}
+
+impl<'a> ApplyIdentifierContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<IdentifierMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let module = &self.module;
+        let identifiers: BTreeSet<_> = module
+            .module_handles
+            .iter()
+            .map(|handle| handle.name)
+            .chain(module.struct_handles.iter().map(|handle| handle.name))
+            .chain(module.function_handles.iter().map(|handle| handle.name))
+            .chain(module.field_defs.iter().map(|field_def| field_def.name))
+            .map(|name| name.0 as usize)
+            .collect();
+        let identifiers: Vec<_> = identifiers.into_iter().collect();
+        let picked = pick_slice_idxs(identifiers.len(), &self.mutations);
+
+        let mut empty_entries: BTreeSet<_> = (0..self.module.string_pool.len())
+            .filter(|idx| self.module.string_pool[*idx].is_empty())
+            .collect();
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let idx = identifiers[picked_idx];
+            let entry = &mut self.module.string_pool[idx];
+            *entry = mutation.kind.apply(entry).into();
+            if mutation.kind == IdentifierMutationKind::Empty {
+                empty_entries.insert(idx);
+            } else {
+                empty_entries.remove(&idx);
+            }
+        }
+
+        let mut empty_entries = empty_entries.into_iter();
+        match (empty_entries.next(), empty_entries.next()) {
+            (Some(first), Some(idx)) => vec![VerificationError::new(
+                IndexKind::StringPool,
+                idx,
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Value),
+            )],
+            _ => vec![],
+        }
+    }
+}
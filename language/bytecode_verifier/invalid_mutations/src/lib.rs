@@ -1,5 +1,21 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+pub mod acquires;
 pub mod bounds;
+pub mod control_flow;
+pub mod coverage;
+pub mod deletion;
+pub mod duplication;
+pub mod expected;
+pub mod identifiers;
+pub mod locals;
+pub mod pipeline;
+pub mod resources;
+pub mod script;
+pub mod self_handle;
 pub mod signature;
+pub mod stack_usage;
+pub mod struct_defs;
+pub mod type_confusion;
+pub mod undo;
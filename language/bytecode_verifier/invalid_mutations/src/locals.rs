@@ -0,0 +1,190 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::stack_usage::insert;
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{Bytecode, CompiledModuleMut, FunctionDefinitionIndex, LocalIndex, TableIndex},
+    IndexKind,
+};
+
+/// Represents a mutation that misuses the locals of a function.
+///
+/// Use `LocalsMutation::strategy()` to generate them, preferably using `Vec` to generate many at a
+/// time. Then use `ApplyLocalsContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct LocalsMutation {
+    function_def: PropIndex,
+    local: PropIndex,
+    kind: LocalsMutationKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LocalsMutationKind {
+    /// Makes an instruction that refers to a local refer to this many locals past the end of the
+    /// locals signature.
+    OutOfRange(usize),
+    /// Loads a local that isn't an argument at the start of the function, before anything was
+    /// stored in it. Moves the local if `true`, copies it otherwise.
+    UseBeforeAssignment(bool),
+    /// Moves an argument out twice at the start of the function.
+    DoubleMove,
+}
+
+impl LocalsMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (
+            any::<PropIndex>(),
+            any::<PropIndex>(),
+            LocalsMutationKind::strategy(),
+        )
+            .prop_map(|(function_def, local, kind)| Self {
+                function_def,
+                local,
+                kind,
+            })
+    }
+}
+
+impl AsRef<PropIndex> for LocalsMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.function_def
+    }
+}
+
+impl LocalsMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            (0..16 as usize).prop_map(LocalsMutationKind::OutOfRange),
+            any::<bool>().prop_map(LocalsMutationKind::UseBeforeAssignment),
+            Just(LocalsMutationKind::DoubleMove),
+        ]
+    }
+}
+
+/// Context for applying a list of `LocalsMutation` instances.
+///
+/// Each mutation is applied to a different function. Locals out of range are reported by the bounds
+/// checker as `CodeUnitIndexOutOfBounds`, while the other misuses are reported by the type safety
+/// analysis. Since the code inserted for those runs at the very start of the function, the error
+/// it causes is the only one the analysis reports.
+///
+/// The expected errors are only precise if the module passes the code unit verifier to begin with.
+/// Only arguments of primitive types are moved twice, so that dropping them isn't an error.
+pub struct ApplyLocalsContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<LocalsMutation>,
+}
+
+impl<'a> ApplyLocalsContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<LocalsMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let function_def_idxs: Vec<_> = self
+            .module
+            .function_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| !function_def.is_native())
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(function_def_idxs.len(), &self.mutations);
+
+        let mut errs = vec![];
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let idx = function_def_idxs[picked_idx];
+            let function_def = &self.module.function_defs[idx];
+            let handle = &self.module.function_handles[function_def.function.0 as usize];
+            let arg_types = &self.module.function_signatures[handle.signature.0 as usize].arg_types;
+            let locals_len =
+                self.module.locals_signatures[function_def.code.locals.0 as usize].len();
+            let primitive_args: Vec<_> = (0..arg_types.len())
+                .filter(|arg| arg_types[*arg].is_primitive())
+                .collect();
+            let arg_count = arg_types.len();
+
+            let code = &mut self.module.function_defs[idx].code.code;
+            let err = match mutation.kind {
+                LocalsMutationKind::OutOfRange(offset) => {
+                    let sites: Vec<_> = (0..code.len())
+                        .filter(|bytecode_idx| local_index(&code[*bytecode_idx]).is_some())
+                        .collect();
+                    if sites.is_empty() {
+                        continue;
+                    }
+                    let bytecode_idx = sites[mutation.local.index(sites.len())];
+                    let new_idx = locals_len + offset;
+                    set_local_index(&mut code[bytecode_idx], new_idx as LocalIndex);
+                    VMStaticViolation::CodeUnitIndexOutOfBounds(
+                        IndexKind::LocalPool,
+                        bytecode_idx,
+                        locals_len,
+                        new_idx,
+                    )
+                }
+                LocalsMutationKind::UseBeforeAssignment(is_move) => {
+                    if arg_count == locals_len {
+                        continue;
+                    }
+                    let local = (arg_count + mutation.local.index(locals_len - arg_count)) as u8;
+                    if is_move {
+                        insert(code, 0, vec![Bytecode::MoveLoc(local), Bytecode::Pop]);
+                        VMStaticViolation::MoveLocUnavailableError(0)
+                    } else {
+                        insert(code, 0, vec![Bytecode::CopyLoc(local), Bytecode::Pop]);
+                        VMStaticViolation::CopyLocUnavailableError(0)
+                    }
+                }
+                LocalsMutationKind::DoubleMove => {
+                    if primitive_args.is_empty() {
+                        continue;
+                    }
+                    let local = primitive_args[mutation.local.index(primitive_args.len())] as u8;
+                    insert(
+                        code,
+                        0,
+                        vec![
+                            Bytecode::MoveLoc(local),
+                            Bytecode::Pop,
+                            Bytecode::MoveLoc(local),
+                            Bytecode::Pop,
+                        ],
+                    );
+                    VMStaticViolation::MoveLocUnavailableError(2)
+                }
+            };
+            errs.push(VerificationError::in_function(
+                FunctionDefinitionIndex::new(idx as TableIndex),
+                err,
+            ));
+        }
+        errs
+    }
+}
+
+fn local_index(bytecode: &Bytecode) -> Option<LocalIndex> {
+    use Bytecode::*;
+
+    match bytecode {
+        CopyLoc(idx) | MoveLoc(idx) | StLoc(idx) | MutBorrowLoc(idx) | ImmBorrowLoc(idx) => {
+            Some(*idx)
+        }
+        _ => None,
+    }
+}
+
+fn set_local_index(bytecode: &mut Bytecode, new_idx: LocalIndex) {
+    use Bytecode::*;
+
+    match bytecode {
+        CopyLoc(idx) | MoveLoc(idx) | StLoc(idx) | MutBorrowLoc(idx) | ImmBorrowLoc(idx) => {
+            *idx = new_idx
+        }
+        bytecode => panic!("Bytecode has no local index: {:?}", bytecode),
+    }
+}
@@ -0,0 +1,123 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    bounds::{
+        ApplyCodeUnitBoundsContext, ApplyOutOfBoundsContext, CodeUnitBoundsMutation,
+        OutOfBoundsMutation,
+    },
+    control_flow::{ApplyControlFlowContext, ControlFlowMutation},
+    signature::{ApplyMalformedTokenContext, MalformedTokenMutation},
+};
+use proptest::{collection::vec, prelude::*};
+use std::collections::BTreeSet;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{CompiledModule, CompiledModuleMut},
+};
+
+/// Represents several classes of mutations to be applied to the same module.
+///
+/// Use `MutationPipeline::strategy()` to generate them, or build one up with `new` and the `with_*`
+/// methods. Then use `apply` to apply all of those mutations.
+#[derive(Debug, Default)]
+pub struct MutationPipeline {
+    bounds: Vec<OutOfBoundsMutation>,
+    signature: Vec<MalformedTokenMutation>,
+    control_flow: Vec<ControlFlowMutation>,
+    code_unit: Vec<CodeUnitBoundsMutation>,
+}
+
+impl MutationPipeline {
+    /// Creates a pipeline with no mutations in it.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Generates a pipeline with up to `max_per_class` mutations of every class.
+    pub fn strategy(max_per_class: usize) -> impl Strategy<Value = Self> {
+        (
+            vec(OutOfBoundsMutation::strategy(), 0..=max_per_class),
+            vec(MalformedTokenMutation::strategy(), 0..=max_per_class),
+            vec(ControlFlowMutation::strategy(), 0..=max_per_class),
+            vec(CodeUnitBoundsMutation::strategy(), 0..=max_per_class),
+        )
+            .prop_map(|(bounds, signature, control_flow, code_unit)| Self {
+                bounds,
+                signature,
+                control_flow,
+                code_unit,
+            })
+    }
+
+    pub fn with_bounds(mut self, mutations: Vec<OutOfBoundsMutation>) -> Self {
+        self.bounds = mutations;
+        self
+    }
+
+    pub fn with_signature(mut self, mutations: Vec<MalformedTokenMutation>) -> Self {
+        self.signature = mutations;
+        self
+    }
+
+    pub fn with_control_flow(mut self, mutations: Vec<ControlFlowMutation>) -> Self {
+        self.control_flow = mutations;
+        self
+    }
+
+    pub fn with_code_unit(mut self, mutations: Vec<CodeUnitBoundsMutation>) -> Self {
+        self.code_unit = mutations;
+        self
+    }
+
+    /// Applies every mutation in this pipeline to `module`.
+    ///
+    /// The mutations are applied in this order:
+    ///
+    /// 1. Signature mutations, while the module is still in bounds so that every signature token
+    ///    can be reached.
+    /// 2. Out-of-bounds mutations.
+    /// 3. Control flow mutations, so that code unit mutations see the final length of the code.
+    /// 4. Code unit bounds mutations. These override any control flow mutation made to the same
+    ///    instruction, and leave functions with an out-of-bounds locals signature alone.
+    ///
+    /// The returned errors are the ones the verifier is expected to stop at, following the order
+    /// its passes run in: the bounds checker if anything was made out of bounds, otherwise the
+    /// signature checker if any signature was mutated, otherwise the code unit verifier. Like with
+    /// the individual contexts, they are only precise if `module` passes those passes to begin
+    /// with.
+    pub fn apply(self, module: CompiledModule) -> (CompiledModuleMut, Vec<VerificationError>) {
+        let mut module = module.into_inner();
+        let signature_errs = ApplyMalformedTokenContext::new(&mut module, self.signature).apply();
+        let module = module
+            .freeze()
+            .expect("signature mutations should stay within bounds");
+
+        let (mut module, mut bounds_errs) =
+            ApplyOutOfBoundsContext::new(module, self.bounds).apply();
+
+        let control_flow_errs =
+            ApplyControlFlowContext::new(&mut module, self.control_flow).apply();
+        let code_unit_errs = ApplyCodeUnitBoundsContext::new(&mut module, self.code_unit).apply();
+
+        let overridden: BTreeSet<_> = code_unit_errs
+            .iter()
+            .map(|err| (err.function_definition_index, err.code_offset))
+            .collect();
+        let (fall_through_errs, control_flow_errs): (Vec<_>, Vec<_>) = control_flow_errs
+            .into_iter()
+            .filter(|err| !overridden.contains(&(err.function_definition_index, err.code_offset)))
+            .partition(|err| err.err == VMStaticViolation::InvalidFallThrough);
+        bounds_errs.extend(control_flow_errs);
+        bounds_errs.extend(code_unit_errs);
+
+        let errs = if !bounds_errs.is_empty() {
+            bounds_errs
+        } else if !signature_errs.is_empty() {
+            signature_errs
+        } else {
+            fall_through_errs
+        };
+        (module, errs)
+    }
+}
@@ -0,0 +1,243 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        Bytecode, CompiledModuleMut, FunctionDefinitionIndex, SignatureToken,
+        StructFieldInformation, StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex,
+    },
+    IndexKind,
+};
+
+/// Represents a mutation that makes a function copy or drop a resource.
+///
+/// Use `ResourceCodeMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyResourceCodeContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct ResourceCodeMutation {
+    function_def: PropIndex,
+    site: PropIndex,
+    kind: ResourceCodeMutationKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResourceCodeMutationKind {
+    /// Replaces a `MoveLoc` of a resource local with a `CopyLoc`.
+    CopyResource,
+    /// In `MoveLoc, StLoc` on a resource local, replaces the `StLoc` with a `Pop`.
+    PopResource,
+}
+
+impl ResourceCodeMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (
+            any::<PropIndex>(),
+            any::<PropIndex>(),
+            ResourceCodeMutationKind::strategy(),
+        )
+            .prop_map(|(function_def, site, kind)| Self {
+                function_def,
+                site,
+                kind,
+            })
+    }
+}
+
+impl AsRef<PropIndex> for ResourceCodeMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.function_def
+    }
+}
+
+impl ResourceCodeMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            Just(ResourceCodeMutationKind::CopyResource),
+            Just(ResourceCodeMutationKind::PopResource),
+        ]
+    }
+}
+
+/// Context for applying a list of `ResourceCodeMutation` instances.
+///
+/// Each mutation is applied to a different function, at a site that matches its pattern. Functions
+/// without such a site are left alone. Only locals whose type is a nominal resource without type
+/// actuals are considered resources here.
+///
+/// The expected errors are only precise if the module passes the code unit verifier to begin with,
+/// and if an error in a basic block keeps the analysis from reaching any other block that would
+/// report one.
+pub struct ApplyResourceCodeContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<ResourceCodeMutation>,
+}
+
+impl<'a> ApplyResourceCodeContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<ResourceCodeMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let function_def_idxs: Vec<_> = self
+            .module
+            .function_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| !function_def.is_native())
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(function_def_idxs.len(), &self.mutations);
+
+        let mut errs = vec![];
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let idx = function_def_idxs[picked_idx];
+            let code_unit = &self.module.function_defs[idx].code;
+            let locals = &self.module.locals_signatures[code_unit.locals.0 as usize].0;
+            let struct_handles = &self.module.struct_handles;
+            let is_resource = |local: u8| match &locals[local as usize] {
+                SignatureToken::Struct(sh_idx, type_actuals) => {
+                    type_actuals.is_empty() && struct_handles[sh_idx.0 as usize].is_nominal_resource
+                }
+                _ => false,
+            };
+
+            let code = &code_unit.code;
+            let sites: Vec<_> = (0..code.len())
+                .filter(|offset| match (mutation.kind, &code[*offset]) {
+                    (ResourceCodeMutationKind::CopyResource, Bytecode::MoveLoc(local)) => {
+                        is_resource(*local)
+                    }
+                    (ResourceCodeMutationKind::PopResource, Bytecode::MoveLoc(local)) => {
+                        is_resource(*local)
+                            && match code.get(offset + 1) {
+                                Some(Bytecode::StLoc(_)) => true,
+                                _ => false,
+                            }
+                    }
+                    _ => false,
+                })
+                .collect();
+            if sites.is_empty() {
+                continue;
+            }
+            let offset = sites[mutation.site.index(sites.len())];
+
+            let code = &mut self.module.function_defs[idx].code.code;
+            let err = match mutation.kind {
+                ResourceCodeMutationKind::CopyResource => {
+                    code[offset] = match code[offset] {
+                        Bytecode::MoveLoc(local) => Bytecode::CopyLoc(local),
+                        ref bytecode => panic!("expected a MoveLoc, found {:?}", bytecode),
+                    };
+                    VMStaticViolation::CopyLocResourceError(offset)
+                }
+                ResourceCodeMutationKind::PopResource => {
+                    code[offset + 1] = Bytecode::Pop;
+                    VMStaticViolation::PopResourceError(offset + 1)
+                }
+            };
+            errs.push(VerificationError::in_function(
+                FunctionDefinitionIndex::new(idx as TableIndex),
+                err,
+            ));
+        }
+        errs
+    }
+}
+
+/// Represents a mutation that stores a resource in a field of a struct that isn't a resource.
+///
+/// Use `ResourceFieldMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyResourceFieldContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct ResourceFieldMutation {
+    struct_def: PropIndex,
+    field: PropIndex,
+    resource: PropIndex,
+}
+
+impl ResourceFieldMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), any::<PropIndex>(), any::<PropIndex>()).prop_map(
+            |(struct_def, field, resource)| Self {
+                struct_def,
+                field,
+                resource,
+            },
+        )
+    }
+}
+
+impl AsRef<PropIndex> for ResourceFieldMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.struct_def
+    }
+}
+
+/// Context for applying a list of `ResourceFieldMutation` instances.
+///
+/// Each mutation is applied to a different struct definition that isn't a resource. Its field gets
+/// a type signature of its own, so other fields sharing the original signature are left alone.
+pub struct ApplyResourceFieldContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<ResourceFieldMutation>,
+}
+
+impl<'a> ApplyResourceFieldContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<ResourceFieldMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let struct_handles = &self.module.struct_handles;
+        let resources: Vec<_> = (0..struct_handles.len())
+            .filter(|idx| struct_handles[*idx].is_nominal_resource)
+            .map(|idx| StructHandleIndex::new(idx as TableIndex))
+            .collect();
+        if resources.is_empty() {
+            return vec![];
+        }
+        let struct_defs: Vec<_> = self
+            .module
+            .struct_defs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, struct_def)| {
+                if struct_handles[struct_def.struct_handle.0 as usize].is_nominal_resource {
+                    return None;
+                }
+                match struct_def.field_information {
+                    StructFieldInformation::Declared {
+                        field_count,
+                        fields,
+                    } if field_count > 0 => Some((idx, fields.0 as usize, field_count as usize)),
+                    _ => None,
+                }
+            })
+            .collect();
+        let picked = pick_slice_idxs(struct_defs.len(), &self.mutations);
+
+        let mut errs = vec![];
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let (idx, fields, field_count) = struct_defs[picked_idx];
+            let resource = resources[mutation.resource.index(resources.len())];
+            self.module
+                .type_signatures
+                .push(TypeSignature(SignatureToken::Struct(resource, vec![])));
+            let field = fields + mutation.field.index(field_count);
+            self.module.field_defs[field].signature =
+                TypeSignatureIndex::new((self.module.type_signatures.len() - 1) as TableIndex);
+            errs.push(VerificationError::new(
+                IndexKind::StructDefinition,
+                idx,
+                VMStaticViolation::InvalidResourceField,
+            ));
+        }
+        errs
+    }
+}
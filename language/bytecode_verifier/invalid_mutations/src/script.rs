@@ -0,0 +1,121 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use vm::{
+    errors::VMStaticViolation,
+    file_format::{
+        CompiledScriptMut, FunctionSignatureIndex, SignatureToken, StructHandleIndex, TableIndex,
+    },
+};
+
+/// Represents a mutation to the signature of the `main` function of a script.
+///
+/// Use `MainSignatureMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyMainSignatureContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub enum MainSignatureMutation {
+    /// Makes `main` return a value.
+    AddReturn,
+    /// Turns an argument of `main` into a reference to its original type (or adds a reference
+    /// argument if there are none).
+    ReferenceArg { arg: PropIndex, mutable: bool },
+    /// Turns an argument of `main` into a struct (or adds a struct argument if there are none).
+    StructArg { arg: PropIndex, handle: PropIndex },
+}
+
+impl MainSignatureMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            Just(MainSignatureMutation::AddReturn),
+            (any::<PropIndex>(), any::<bool>())
+                .prop_map(|(arg, mutable)| MainSignatureMutation::ReferenceArg { arg, mutable }),
+            (any::<PropIndex>(), any::<PropIndex>())
+                .prop_map(|(arg, handle)| MainSignatureMutation::StructArg { arg, handle }),
+        ]
+    }
+}
+
+/// Context for applying a list of `MainSignatureMutation` instances.
+///
+/// `main` gets a signature of its own before it is mutated, so other function handles that shared
+/// its signature are left alone. `verify_main_signature` reports every return type of `main`, then
+/// every argument that can't be passed by a transaction, so that's what is expected.
+pub struct ApplyMainSignatureContext<'a> {
+    script: &'a mut CompiledScriptMut,
+    mutations: Vec<MainSignatureMutation>,
+}
+
+impl<'a> ApplyMainSignatureContext<'a> {
+    pub fn new(script: &'a mut CompiledScriptMut, mutations: Vec<MainSignatureMutation>) -> Self {
+        Self { script, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VMStaticViolation> {
+        let struct_handles_len = self.script.struct_handles.len();
+        let handle_idx = self.script.main.function.0 as usize;
+        let mut signature = self.script.function_signatures
+            [self.script.function_handles[handle_idx].signature.0 as usize]
+            .clone();
+
+        let mut mutated = false;
+        for mutation in self.mutations {
+            match mutation {
+                MainSignatureMutation::AddReturn => {
+                    signature.return_types.push(SignatureToken::U64)
+                }
+                MainSignatureMutation::ReferenceArg { arg, mutable } => {
+                    let arg_types = &mut signature.arg_types;
+                    if arg_types.is_empty() {
+                        arg_types.push(SignatureToken::U64);
+                    }
+                    let arg_idx = arg.index(arg_types.len());
+                    let token = Box::new(arg_types[arg_idx].clone());
+                    arg_types[arg_idx] = if mutable {
+                        SignatureToken::MutableReference(token)
+                    } else {
+                        SignatureToken::Reference(token)
+                    };
+                }
+                MainSignatureMutation::StructArg { arg, handle } => {
+                    if struct_handles_len == 0 {
+                        continue;
+                    }
+                    let token = SignatureToken::Struct(
+                        StructHandleIndex::new(handle.index(struct_handles_len) as TableIndex),
+                        vec![],
+                    );
+                    let arg_types = &mut signature.arg_types;
+                    if arg_types.is_empty() {
+                        arg_types.push(token);
+                    } else {
+                        let arg_idx = arg.index(arg_types.len());
+                        arg_types[arg_idx] = token;
+                    }
+                }
+            }
+            mutated = true;
+        }
+
+        if !mutated {
+            return vec![];
+        }
+        let mut expected: Vec<_> = (0..signature.return_types.len())
+            .map(VMStaticViolation::InvalidMainFunctionReturn)
+            .collect();
+        expected.extend(
+            signature
+                .arg_types
+                .iter()
+                .enumerate()
+                .filter(|(_, arg_type)| !arg_type.is_primitive())
+                .map(|(arg_idx, arg_type)| {
+                    VMStaticViolation::InvalidMainFunctionArgument(arg_idx, arg_type.clone())
+                }),
+        );
+        self.script.function_signatures.push(signature);
+        self.script.function_handles[handle_idx].signature =
+            FunctionSignatureIndex::new((self.script.function_signatures.len() - 1) as TableIndex);
+        expected
+    }
+}
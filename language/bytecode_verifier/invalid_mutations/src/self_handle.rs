@@ -0,0 +1,124 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use vm::{
+    errors::{DuplicateKey, VMStaticViolation, VerificationError},
+    file_format::{
+        AddressPoolIndex, CompiledModule, CompiledModuleMut, StringPoolIndex, TableIndex,
+    },
+    internals::ModuleIndex,
+    IndexKind,
+};
+
+/// Represents a mutation to the module handle at `CompiledModule::IMPLEMENTED_MODULE_INDEX`, which
+/// a module uses to identify itself.
+///
+/// Use `SelfHandleMutation::strategy()` to generate them, preferably using `Vec` to generate many
+/// at a time. Then use `ApplySelfHandleContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub enum SelfHandleMutation {
+    /// Points the self handle at a different address in the address pool.
+    WrongAddress(PropIndex),
+    /// Points the self handle at a different name in the string pool.
+    WrongName(PropIndex),
+    /// Makes the self handle identical to the handle of a dependency.
+    Dependency(PropIndex),
+}
+
+impl SelfHandleMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            any::<PropIndex>().prop_map(SelfHandleMutation::WrongAddress),
+            any::<PropIndex>().prop_map(SelfHandleMutation::WrongName),
+            any::<PropIndex>().prop_map(SelfHandleMutation::Dependency),
+        ]
+    }
+}
+
+/// Context for applying a list of `SelfHandleMutation` instances.
+///
+/// The mutations are applied one after the other, and the expected errors only depend on where the
+/// self handle ends up:
+/// - `verify_module_address` reports a self address other than the original one, as the module is
+///   expected to be published by the account it was originally declared at.
+/// - `DuplicationChecker` reports a self handle identical to the handle of a dependency. A module
+///   is otherwise free to pick its own name, so renaming it isn't an error.
+///
+/// The expected errors are only precise if the module passes the duplication checker to begin
+/// with.
+pub struct ApplySelfHandleContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<SelfHandleMutation>,
+}
+
+impl<'a> ApplySelfHandleContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<SelfHandleMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(mut self) -> Vec<VerificationError> {
+        let self_idx = CompiledModule::IMPLEMENTED_MODULE_INDEX as usize;
+        let original_address =
+            self.module.address_pool[self.module.module_handles[self_idx].address.into_index()];
+
+        for mutation in std::mem::replace(&mut self.mutations, vec![]) {
+            let self_handle = &self.module.module_handles[self_idx];
+            match mutation {
+                SelfHandleMutation::WrongAddress(idx) => {
+                    let current = self.module.address_pool[self_handle.address.into_index()];
+                    let candidates: Vec<_> = (0..self.module.address_pool.len())
+                        .filter(|address_idx| self.module.address_pool[*address_idx] != current)
+                        .collect();
+                    if candidates.is_empty() {
+                        continue;
+                    }
+                    let address_idx = candidates[idx.index(candidates.len())];
+                    self.module.module_handles[self_idx].address =
+                        AddressPoolIndex::new(address_idx as TableIndex);
+                }
+                SelfHandleMutation::WrongName(idx) => {
+                    let current = &self.module.string_pool[self_handle.name.into_index()];
+                    let candidates: Vec<_> = (0..self.module.string_pool.len())
+                        .filter(|name_idx| &self.module.string_pool[*name_idx] != current)
+                        .collect();
+                    if candidates.is_empty() {
+                        continue;
+                    }
+                    let name_idx = candidates[idx.index(candidates.len())];
+                    self.module.module_handles[self_idx].name =
+                        StringPoolIndex::new(name_idx as TableIndex);
+                }
+                SelfHandleMutation::Dependency(idx) => {
+                    let dependencies = self.module.module_handles.len() - 1;
+                    if dependencies == 0 {
+                        continue;
+                    }
+                    let dependency_idx = self_idx + 1 + idx.index(dependencies);
+                    self.module.module_handles[self_idx] =
+                        self.module.module_handles[dependency_idx].clone();
+                }
+            }
+        }
+
+        let mut errs = vec![];
+        let self_handle = &self.module.module_handles[self_idx];
+        if self.module.address_pool[self_handle.address.into_index()] != original_address {
+            errs.push(VerificationError::new(
+                IndexKind::AddressPool,
+                self_idx,
+                VMStaticViolation::ModuleAddressDoesNotMatchSender,
+            ));
+        }
+        if let Some(idx) = (self_idx + 1..self.module.module_handles.len())
+            .find(|idx| &self.module.module_handles[*idx] == self_handle)
+        {
+            errs.push(VerificationError::new(
+                IndexKind::ModuleHandle,
+                idx,
+                VMStaticViolation::DuplicateElement(self_idx, DuplicateKey::Value),
+            ));
+        }
+        errs
+    }
+}
@@ -2,13 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use proptest::{
+    collection::vec,
     prelude::*,
     sample::{select, Index as PropIndex},
 };
 use proptest_helpers::{pick_slice_idxs, RepeatVec};
 use std::collections::BTreeMap;
 use vm::{
-    errors::{VMStaticViolation, VerificationError},
+    errors::{VMStaticViolation, VerificationError, WithContext},
     file_format::{CompiledModuleMut, SignatureToken},
     internals::ModuleIndex,
     IndexKind, SignatureTokenKind,
@@ -58,39 +59,20 @@ impl<'a> ApplySignatureDoubleRefContext<'a> {
             // When there's one level of indexing (e.g. Type), idx2 represents that level.
             // When there's two levels of indexing (e.g. FunctionArg), idx1 represents the outer
             // level (signature index) and idx2 the inner level (token index).
-            let (token, kind, error_idx) = match sig_idx {
-                SignatureIndex::Type => (
-                    &mut self.module.type_signatures[idx2].0,
-                    IndexKind::TypeSignature,
-                    idx2,
-                ),
-                SignatureIndex::FunctionReturn(idx1) => (
-                    &mut self.module.function_signatures[*idx1].return_types[idx2],
-                    IndexKind::FunctionSignature,
-                    *idx1,
-                ),
-                SignatureIndex::FunctionArg(idx1) => (
-                    &mut self.module.function_signatures[*idx1].arg_types[idx2],
-                    IndexKind::FunctionSignature,
-                    *idx1,
-                ),
-                SignatureIndex::Locals(idx1) => (
-                    &mut self.module.locals_signatures[*idx1].0[idx2],
-                    IndexKind::LocalsSignature,
-                    *idx1,
-                ),
+            let slot = match sig_idx {
+                SignatureIndex::Type => TokenSlot::Type(idx2),
+                SignatureIndex::FunctionReturn(idx1) => TokenSlot::FunctionReturn(*idx1, idx2),
+                SignatureIndex::FunctionArg(idx1) => TokenSlot::FunctionArg(*idx1, idx2),
+                SignatureIndex::Locals(idx1) => TokenSlot::Locals(*idx1, idx2),
             };
-
+            let token = slot.token_mut(self.module);
             *token = double_ref.kind.wrap(token.clone());
-            errs.push(VerificationError {
-                kind,
-                idx: error_idx,
-                err: VMStaticViolation::InvalidSignatureToken(
-                    token.clone(),
-                    double_ref.kind.outer,
-                    double_ref.kind.inner,
-                ),
-            });
+            let violation = VMStaticViolation::InvalidSignatureToken(
+                token.clone(),
+                double_ref.kind.outer,
+                double_ref.kind.inner,
+            );
+            errs.push(slot.error(violation));
         }
 
         errs
@@ -177,19 +159,235 @@ impl<'a> ApplySignatureFieldRefContext<'a> {
             *token = new_token;
 
             let violation = VMStaticViolation::InvalidFieldDefReference(token.clone(), token_kind);
+            let module = &*self.module;
+            errs.extend(
+                field_def_idxs
+                    .iter()
+                    .map(|field_def_idx| field_error(module, *field_def_idx, &violation)),
+            );
+        }
+
+        errs
+    }
+}
+
+/// Represents a mutation that produces an illegal signature token shape.
+///
+/// Use `MalformedTokenMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyMalformedTokenContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct MalformedTokenMutation {
+    idx: PropIndex,
+    kind: MalformedTokenKind,
+}
+
+/// The illegal token shapes produced by `MalformedTokenMutation`.
+#[derive(Clone, Debug)]
+pub enum MalformedTokenKind {
+    /// Wraps a token in a reference to a reference.
+    RefToRef {
+        outer: SignatureTokenKind,
+        inner: SignatureTokenKind,
+    },
+    /// Turns the type of a struct field into a mutable reference.
+    MutableRefInField,
+    /// Wraps a token in a chain of references, listed from the outermost one in.
+    DeepNesting(Vec<SignatureTokenKind>),
+}
+
+/// The range of reference chain lengths produced by `MalformedTokenKind::DeepNesting`.
+const DEEP_NESTING_DEPTHS: std::ops::Range<usize> = 3..9;
+
+impl MalformedTokenMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), MalformedTokenKind::strategy())
+            .prop_map(|(idx, kind)| Self { idx, kind })
+    }
+}
+
+impl AsRef<PropIndex> for MalformedTokenMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.idx
+    }
+}
+
+impl MalformedTokenKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        let reference_kind = DoubleRefMutationKind::outer_strategy;
+        prop_oneof![
+            (reference_kind(), reference_kind())
+                .prop_map(|(outer, inner)| MalformedTokenKind::RefToRef { outer, inner }),
+            Just(MalformedTokenKind::MutableRefInField),
+            vec(reference_kind(), DEEP_NESTING_DEPTHS).prop_map(MalformedTokenKind::DeepNesting),
+        ]
+    }
+
+    /// Returns the reference kinds this mutation wraps a token in, from the outermost one in.
+    fn wrapping_kinds(&self) -> Vec<SignatureTokenKind> {
+        match self {
+            MalformedTokenKind::RefToRef { outer, inner } => vec![*outer, *inner],
+            MalformedTokenKind::MutableRefInField => vec![SignatureTokenKind::MutableReference],
+            MalformedTokenKind::DeepNesting(kinds) => kinds.clone(),
+        }
+    }
+}
+
+/// Context for applying a list of `MalformedTokenMutation` instances.
+///
+/// `MutableRefInField` mutations are applied to the type signatures of field definitions, and the
+/// other mutations to the remaining signature tokens, so that every mutated token is reported
+/// exactly once and with a single violation.
+pub struct ApplyMalformedTokenContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<MalformedTokenMutation>,
+}
+
+impl<'a> ApplyMalformedTokenContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<MalformedTokenMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let mut field_sigs = BTreeMap::new();
+        for (field_def_idx, field_def) in self.module.field_defs.iter().enumerate() {
+            field_sigs
+                .entry(field_def.signature.into_index())
+                .or_insert_with(|| vec![])
+                .push(field_def_idx);
+        }
+        let field_sigs: Vec<_> = field_sigs.into_iter().collect();
+        let token_slots = self.token_slots(&field_sigs);
+
+        let (field_mutations, token_mutations): (Vec<_>, Vec<_>) = self
+            .mutations
+            .into_iter()
+            .partition(|mutation| match mutation.kind {
+                MalformedTokenKind::MutableRefInField => true,
+                _ => false,
+            });
+
+        let mut errs = vec![];
+
+        let picked = pick_slice_idxs(field_sigs.len(), &field_mutations);
+        for (mutation, picked_idx) in field_mutations.iter().zip(picked) {
+            let (type_sig_idx, field_def_idxs) = &field_sigs[picked_idx];
+            let token = &mut self.module.type_signatures[*type_sig_idx].0;
+            *token = wrap_all(token.clone(), &mutation.kind.wrapping_kinds());
+
+            let violation = VMStaticViolation::InvalidFieldDefReference(
+                token.clone(),
+                SignatureTokenKind::MutableReference,
+            );
+            let module = &*self.module;
             errs.extend(
                 field_def_idxs
                     .iter()
-                    .map(|field_def_idx| VerificationError {
-                        kind: IndexKind::FieldDefinition,
-                        idx: *field_def_idx,
-                        err: violation.clone(),
-                    }),
+                    .map(|field_def_idx| field_error(module, *field_def_idx, &violation)),
             );
         }
 
+        let picked = pick_slice_idxs(token_slots.len(), &token_mutations);
+        for (mutation, picked_idx) in token_mutations.iter().zip(picked) {
+            let slot = token_slots[picked_idx];
+            let token = slot.token_mut(self.module);
+
+            // The signature checker only reports the outermost two references of a chain.
+            let kinds = mutation.kind.wrapping_kinds();
+            *token = wrap_all(token.clone(), &kinds);
+            let violation =
+                VMStaticViolation::InvalidSignatureToken(token.clone(), kinds[0], kinds[1]);
+            errs.push(slot.error(violation));
+        }
+
         errs
     }
+
+    /// Returns every signature token in the module, except for the type signatures of fields.
+    fn token_slots(&self, field_sigs: &[(usize, Vec<usize>)]) -> Vec<TokenSlot> {
+        let mut slots = vec![];
+        for idx in 0..self.module.type_signatures.len() {
+            if field_sigs
+                .binary_search_by_key(&idx, |(type_sig_idx, _)| *type_sig_idx)
+                .is_err()
+            {
+                slots.push(TokenSlot::Type(idx));
+            }
+        }
+        for (idx1, sig) in self.module.function_signatures.iter().enumerate() {
+            slots.extend(
+                (0..sig.return_types.len()).map(|idx2| TokenSlot::FunctionReturn(idx1, idx2)),
+            );
+            slots.extend((0..sig.arg_types.len()).map(|idx2| TokenSlot::FunctionArg(idx1, idx2)));
+        }
+        for (idx1, sig) in self.module.locals_signatures.iter().enumerate() {
+            slots.extend((0..sig.0.len()).map(|idx2| TokenSlot::Locals(idx1, idx2)));
+        }
+        slots
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum TokenSlot {
+    Type(usize),
+    FunctionReturn(usize, usize),
+    FunctionArg(usize, usize),
+    Locals(usize, usize),
+}
+
+impl TokenSlot {
+    fn token_mut(self, module: &mut CompiledModuleMut) -> &mut SignatureToken {
+        match self {
+            TokenSlot::Type(idx) => &mut module.type_signatures[idx].0,
+            TokenSlot::FunctionReturn(idx1, idx2) => {
+                &mut module.function_signatures[idx1].return_types[idx2]
+            }
+            TokenSlot::FunctionArg(idx1, idx2) => {
+                &mut module.function_signatures[idx1].arg_types[idx2]
+            }
+            TokenSlot::Locals(idx1, idx2) => &mut module.locals_signatures[idx1].0[idx2],
+        }
+    }
+
+    /// Returns the error the signature checker reports for `violation` in the token in this slot.
+    fn error(self, violation: VMStaticViolation) -> VerificationError {
+        match self {
+            TokenSlot::Type(idx) => {
+                VerificationError::new(IndexKind::TypeSignature, idx, violation)
+            }
+            TokenSlot::FunctionReturn(idx1, idx2) => {
+                VerificationError::new(IndexKind::FunctionSignature, idx1, violation)
+                    .with_context(|| format!("return type {}", idx2))
+            }
+            TokenSlot::FunctionArg(idx1, idx2) => {
+                VerificationError::new(IndexKind::FunctionSignature, idx1, violation)
+                    .with_context(|| format!("argument {}", idx2))
+            }
+            TokenSlot::Locals(idx1, idx2) => {
+                VerificationError::new(IndexKind::LocalsSignature, idx1, violation)
+                    .with_context(|| format!("local {}", idx2))
+            }
+        }
+    }
+}
+
+/// Returns the error the signature checker reports for `violation` in the field definition at
+/// `field_def_idx`.
+fn field_error(
+    module: &CompiledModuleMut,
+    field_def_idx: usize,
+    violation: &VMStaticViolation,
+) -> VerificationError {
+    let name = &module.string_pool[module.field_defs[field_def_idx].name.into_index()];
+    VerificationError::new(IndexKind::FieldDefinition, field_def_idx, violation.clone())
+        .with_context(|| format!("field {}", name))
+}
+
+/// Wraps `token` in references of the given kinds, listed from the outermost one in.
+fn wrap_all(token: SignatureToken, kinds: &[SignatureTokenKind]) -> SignatureToken {
+    kinds.iter().rev().fold(token, |token, kind| {
+        DoubleRefMutationKind::wrap_one(token, *kind)
+    })
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -0,0 +1,159 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{Bytecode, CodeOffset, CompiledModuleMut, FunctionDefinitionIndex, TableIndex},
+};
+
+/// Represents a mutation that unbalances the value stack of a function.
+///
+/// Use `StackUsageMutation::strategy()` to generate them, preferably using `Vec` to generate many
+/// at a time. Then use `ApplyStackUsageContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct StackUsageMutation {
+    function_def: PropIndex,
+    kind: StackUsageMutationKind,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StackUsageMutationKind {
+    /// Inserts a `Pop` at the start of the function, when the stack is still empty.
+    ExtraPop,
+    /// Inserts `LdConst, Add, Pop` at the start of the function, so that `Add` is missing one of
+    /// its operands.
+    MissingOperand,
+    /// Inserts an `LdTrue` right before the final `Ret` of the function, leaving an extra value on
+    /// the stack.
+    LeftoverAtReturn,
+}
+
+impl StackUsageMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), StackUsageMutationKind::strategy())
+            .prop_map(|(function_def, kind)| Self { function_def, kind })
+    }
+}
+
+impl AsRef<PropIndex> for StackUsageMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.function_def
+    }
+}
+
+impl StackUsageMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            Just(StackUsageMutationKind::ExtraPop),
+            Just(StackUsageMutationKind::MissingOperand),
+            Just(StackUsageMutationKind::LeftoverAtReturn),
+        ]
+    }
+}
+
+/// Context for applying a list of `StackUsageMutation` instances.
+///
+/// Each mutation is applied to a different function. The expected errors are only precise if the
+/// stack usage of every basic block of the module is balanced to begin with.
+pub struct ApplyStackUsageContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<StackUsageMutation>,
+}
+
+impl<'a> ApplyStackUsageContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<StackUsageMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let function_def_idxs: Vec<_> = self
+            .module
+            .function_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| !function_def.is_native())
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(function_def_idxs.len(), &self.mutations);
+
+        let mut errs = vec![];
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let idx = function_def_idxs[picked_idx];
+            let code = &mut self.module.function_defs[idx].code.code;
+            if let Some(err) = apply_one(code, mutation.kind) {
+                errs.push(VerificationError::in_function(
+                    FunctionDefinitionIndex::new(idx as TableIndex),
+                    err,
+                ));
+            }
+        }
+        errs
+    }
+}
+
+fn apply_one(code: &mut Vec<Bytecode>, kind: StackUsageMutationKind) -> Option<VMStaticViolation> {
+    match kind {
+        // The stack is empty at the start of the function, so the inserted code underflows it
+        // right away.
+        StackUsageMutationKind::ExtraPop => {
+            insert(code, 0, vec![Bytecode::Pop]);
+            Some(VMStaticViolation::NegativeStackSizeInsideBlock(0, 0))
+        }
+        StackUsageMutationKind::MissingOperand => {
+            insert(
+                code,
+                0,
+                vec![Bytecode::LdConst(0), Bytecode::Add, Bytecode::Pop],
+            );
+            Some(VMStaticViolation::NegativeStackSizeInsideBlock(0, 2))
+        }
+        StackUsageMutationKind::LeftoverAtReturn => {
+            if code.last() != Some(&Bytecode::Ret) {
+                return None;
+            }
+            let offset = code.len() - 1;
+            insert(code, offset, vec![Bytecode::LdTrue]);
+            Some(VMStaticViolation::PositiveStackSizeAtBlockEnd(block_start(
+                code, offset,
+            )))
+        }
+    }
+}
+
+/// Inserts `bytecodes` at `offset`, shifting branches so that they still target the same
+/// instructions.
+pub(crate) fn insert(code: &mut Vec<Bytecode>, offset: usize, bytecodes: Vec<Bytecode>) {
+    let shift = bytecodes.len() as CodeOffset;
+    for bytecode in code.iter_mut() {
+        match bytecode {
+            Bytecode::BrTrue(target) | Bytecode::BrFalse(target) | Bytecode::Branch(target)
+                if *target as usize >= offset =>
+            {
+                *target += shift
+            }
+            _ => (),
+        }
+    }
+    code.splice(offset..offset, bytecodes);
+}
+
+/// Returns the start of the basic block that contains `offset`.
+fn block_start(code: &[Bytecode], offset: usize) -> usize {
+    (0..=offset)
+        .rev()
+        .find(|start| is_block_start(code, *start))
+        .unwrap_or(0)
+}
+
+/// Returns true if a basic block starts at `offset`, i.e. if it's the entry point of the function,
+/// follows a branch or is the target of one.
+pub(crate) fn is_block_start(code: &[Bytecode], offset: usize) -> bool {
+    offset == 0
+        || code[offset - 1].is_branch()
+        || code
+            .iter()
+            .any(|bytecode| bytecode.offset() == Some(&(offset as CodeOffset)))
+}
@@ -0,0 +1,400 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::expected::ExpectedErrors;
+use proptest::{collection::vec, prelude::*, sample::Index as PropIndex};
+use proptest_helpers::{pick_slice_idxs, Index};
+use std::collections::{BTreeMap, BTreeSet};
+use vm::{
+    errors::{VMStaticViolation, VerificationError, WithContext},
+    file_format::{
+        CompiledModuleMut, FieldDefinitionIndex, SignatureToken, StructFieldInformation,
+        StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex,
+    },
+    IndexKind,
+};
+
+/// The longest cycle of struct definitions a single mutation creates.
+const MAX_CYCLE_LEN: usize = 4;
+
+/// Represents a mutation that rewrites field types so that struct definitions contain themselves.
+///
+/// Use `RecursiveStructMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyRecursiveStructContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub enum RecursiveStructMutation {
+    /// Gives a field of a struct definition the type of that struct.
+    SelfReference(PropIndex),
+    /// Links several struct definitions into a cycle, each of them having a field with the type of
+    /// the next one.
+    Cycle(Vec<Index>),
+    /// Gives a field of a struct definition the type of a struct declared in another module.
+    ///
+    /// A cycle through another module would also require that module to depend on this one, which
+    /// the acyclic module dependency graph rules out, so these never cause errors.
+    ThroughImport(PropIndex, PropIndex),
+}
+
+impl RecursiveStructMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            any::<PropIndex>().prop_map(RecursiveStructMutation::SelfReference),
+            vec(any::<Index>(), 2..=MAX_CYCLE_LEN).prop_map(RecursiveStructMutation::Cycle),
+            (any::<PropIndex>(), any::<PropIndex>())
+                .prop_map(|(def, handle)| RecursiveStructMutation::ThroughImport(def, handle)),
+        ]
+    }
+}
+
+/// Context for applying a list of `RecursiveStructMutation` instances.
+///
+/// Each field is rewritten at most once, so every cycle that a mutation creates survives the
+/// mutations applied after it. `RecursiveStructDefChecker` only reports the struct definition with
+/// the lowest index among those that are part of a cycle, which is what `apply` returns. The other
+/// ones are returned as secondary errors by `apply_expected`. These include struct definitions
+/// that end up on a cycle through fields that weren't rewritten.
+pub struct ApplyRecursiveStructContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<RecursiveStructMutation>,
+    /// The fields of each struct definition that haven't been rewritten yet.
+    free_fields: BTreeMap<usize, Vec<FieldDefinitionIndex>>,
+}
+
+impl<'a> ApplyRecursiveStructContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<RecursiveStructMutation>) -> Self {
+        let free_fields = module
+            .struct_defs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, struct_def)| match struct_def.field_information {
+                StructFieldInformation::Native => None,
+                StructFieldInformation::Declared {
+                    field_count,
+                    fields,
+                } => {
+                    let fields: Vec<_> = (0..field_count)
+                        .rev()
+                        .map(|offset| FieldDefinitionIndex::new(fields.0 + offset))
+                        .collect();
+                    if fields.is_empty() {
+                        None
+                    } else {
+                        Some((idx, fields))
+                    }
+                }
+            })
+            .collect();
+        Self {
+            module,
+            mutations,
+            free_fields,
+        }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        self.apply_expected().primary
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning the struct definitions that
+    /// are part of a cycle but aren't reported.
+    pub fn apply_expected(mut self) -> ExpectedErrors {
+        let defined_handles: BTreeSet<_> = self
+            .module
+            .struct_defs
+            .iter()
+            .map(|struct_def| struct_def.struct_handle)
+            .collect();
+        let imported_handles: Vec<_> = (0..self.module.struct_handles.len())
+            .map(|idx| StructHandleIndex::new(idx as TableIndex))
+            .filter(|idx| !defined_handles.contains(idx))
+            .collect();
+
+        for mutation in std::mem::replace(&mut self.mutations, vec![]) {
+            match mutation {
+                RecursiveStructMutation::SelfReference(def) => {
+                    if let Some(def) = self.pick_def(&def) {
+                        self.link(def, def);
+                    }
+                }
+                RecursiveStructMutation::Cycle(defs) => {
+                    let candidates: Vec<_> = self.free_fields.keys().cloned().collect();
+                    let cycle: Vec<_> = pick_slice_idxs(candidates.len(), &defs)
+                        .into_iter()
+                        .map(|picked_idx| candidates[picked_idx])
+                        .collect();
+                    for (idx, def) in cycle.iter().enumerate() {
+                        self.link(*def, cycle[(idx + 1) % cycle.len()]);
+                    }
+                }
+                RecursiveStructMutation::ThroughImport(def, handle) => {
+                    if imported_handles.is_empty() {
+                        continue;
+                    }
+                    if let Some(def) = self.pick_def(&def) {
+                        let handle = imported_handles[handle.index(imported_handles.len())];
+                        self.rewrite_field(def, handle);
+                    }
+                }
+            }
+        }
+
+        let module = &*self.module;
+        let mut errs = self.recursive_defs().into_iter().map(|def| {
+            let handle = &module.struct_handles[module.struct_defs[def].struct_handle.0 as usize];
+            let name = &module.string_pool[handle.name.0 as usize];
+            VerificationError::new(
+                IndexKind::StructDefinition,
+                def,
+                VMStaticViolation::RecursiveStructDef,
+            )
+            .with_context(|| format!("struct {}", name))
+        });
+        // recursive_defs is ordered, so the first error is the one with the lowest index.
+        ExpectedErrors {
+            primary: errs.next().into_iter().collect(),
+            secondary: errs.collect(),
+        }
+    }
+
+    /// Returns the struct definitions that contain themselves, directly or not.
+    fn recursive_defs(&self) -> BTreeSet<usize> {
+        let handle_to_def: BTreeMap<_, _> = self
+            .module
+            .struct_defs
+            .iter()
+            .enumerate()
+            .map(|(idx, struct_def)| (struct_def.struct_handle, idx))
+            .collect();
+        let members: Vec<Vec<usize>> = self
+            .module
+            .struct_defs
+            .iter()
+            .map(|struct_def| match struct_def.field_information {
+                StructFieldInformation::Native => vec![],
+                StructFieldInformation::Declared {
+                    field_count,
+                    fields,
+                } => (fields.0..fields.0 + field_count)
+                    .filter_map(|field| {
+                        let signature = self.module.field_defs[field as usize].signature;
+                        let handle = self.module.type_signatures[signature.0 as usize]
+                            .0
+                            .struct_index()?;
+                        handle_to_def.get(&handle).cloned()
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        (0..members.len())
+            .filter(|def| {
+                // Look for a path from the members of this struct definition back to it.
+                let mut seen = BTreeSet::new();
+                let mut stack = members[*def].clone();
+                while let Some(member) = stack.pop() {
+                    if member == *def {
+                        return true;
+                    }
+                    if seen.insert(member) {
+                        stack.extend(&members[member]);
+                    }
+                }
+                false
+            })
+            .collect()
+    }
+
+    /// Picks a struct definition that still has a field that hasn't been rewritten.
+    fn pick_def(&self, def: &PropIndex) -> Option<usize> {
+        if self.free_fields.is_empty() {
+            None
+        } else {
+            self.free_fields
+                .keys()
+                .nth(def.index(self.free_fields.len()))
+                .cloned()
+        }
+    }
+
+    /// Makes a field of the struct definition `from` contain the struct definition `to`.
+    fn link(&mut self, from: usize, to: usize) {
+        let handle = self.module.struct_defs[to].struct_handle;
+        self.rewrite_field(from, handle);
+    }
+
+    fn rewrite_field(&mut self, def: usize, handle: StructHandleIndex) {
+        let fields = self
+            .free_fields
+            .get_mut(&def)
+            .expect("struct definition should have a free field");
+        let field = fields.pop().expect("free fields should never be empty");
+        if fields.is_empty() {
+            self.free_fields.remove(&def);
+        }
+
+        // Fields might share a type signature, so give this one its own.
+        self.module
+            .type_signatures
+            .push(TypeSignature(SignatureToken::Struct(handle, vec![])));
+        self.module.field_defs[field.0 as usize].signature =
+            TypeSignatureIndex::new((self.module.type_signatures.len() - 1) as TableIndex);
+    }
+}
+
+/// Represents a mutation that corrupts the range of fields of a struct definition while keeping it
+/// within bounds.
+///
+/// Unlike the `(StructDefinition, FieldDefinition)` case of `OutOfBoundsMutation`, these are caught
+/// by the duplication checker, which requires the fields of struct definitions to be laid out
+/// consecutively and in order.
+///
+/// Use `FieldRangeMutation::strategy()` to generate them, preferably using `Vec` to generate many
+/// at a time. Then use `ApplyFieldRangeContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct FieldRangeMutation {
+    struct_def: PropIndex,
+    kind: FieldRangeMutationKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum FieldRangeMutationKind {
+    /// Moves the start of the range back so that it overlaps the fields of the struct definitions
+    /// before it.
+    Overlap(PropIndex),
+    /// Declares the struct definition with no fields, leaving its fields to the next one.
+    ZeroCount,
+    /// Moves the start of the range forward, skipping some of the fields of the struct definition.
+    SkipFields(PropIndex),
+}
+
+impl FieldRangeMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (any::<PropIndex>(), FieldRangeMutationKind::strategy())
+            .prop_map(|(struct_def, kind)| Self { struct_def, kind })
+    }
+}
+
+impl AsRef<PropIndex> for FieldRangeMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.struct_def
+    }
+}
+
+impl FieldRangeMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            any::<PropIndex>().prop_map(FieldRangeMutationKind::Overlap),
+            Just(FieldRangeMutationKind::ZeroCount),
+            any::<PropIndex>().prop_map(FieldRangeMutationKind::SkipFields),
+        ]
+    }
+}
+
+/// Context for applying a list of `FieldRangeMutation` instances.
+///
+/// Each mutation is applied to a different declared struct definition with at least one field.
+/// Overlapping and skipping ranges are reported as `InconsistentFields` on the mutated struct
+/// definition. A struct definition declared with no fields is reported through the next declared
+/// one, or as `UnusedFields` if it was the last. `DuplicationChecker` stops at the first
+/// inconsistency, so only the earliest of these is returned by `apply`. The other ones are
+/// returned as secondary errors by `apply_expected`.
+///
+/// The expected errors are only precise if the module passes the duplication checker to begin
+/// with.
+pub struct ApplyFieldRangeContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<FieldRangeMutation>,
+}
+
+impl<'a> ApplyFieldRangeContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<FieldRangeMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        self.apply_expected().primary
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning the inconsistencies that
+    /// aren't reported.
+    pub fn apply_expected(self) -> ExpectedErrors {
+        let declared: Vec<_> = self
+            .module
+            .struct_defs
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, struct_def)| match struct_def.field_information {
+                StructFieldInformation::Native => None,
+                StructFieldInformation::Declared {
+                    field_count,
+                    fields,
+                } => Some((idx, fields.0, field_count)),
+            })
+            .collect();
+        let candidates: Vec<_> = (0..declared.len())
+            .filter(|pos| declared[*pos].2 > 0)
+            .collect();
+        let picked = pick_slice_idxs(candidates.len(), &self.mutations);
+
+        let mut inconsistent = BTreeSet::new();
+        let mut unused = None;
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let pos = candidates[picked_idx];
+            let (idx, start, field_count) = declared[pos];
+            let (new_start, new_count) = match &mutation.kind {
+                FieldRangeMutationKind::Overlap(back) => {
+                    if start == 0 {
+                        continue;
+                    }
+                    let back = 1 + back.index(start as usize) as TableIndex;
+                    (start - back, field_count)
+                }
+                FieldRangeMutationKind::ZeroCount => (start, 0),
+                FieldRangeMutationKind::SkipFields(skipped) => {
+                    let skipped = 1 + skipped.index(field_count as usize) as TableIndex;
+                    (start + skipped, field_count - skipped)
+                }
+            };
+            self.module.struct_defs[idx].field_information = StructFieldInformation::Declared {
+                field_count: new_count,
+                fields: FieldDefinitionIndex::new(new_start),
+            };
+
+            let reported = match mutation.kind {
+                FieldRangeMutationKind::ZeroCount => match declared.get(pos + 1) {
+                    Some((next_idx, _, _)) => *next_idx,
+                    None => {
+                        unused = Some(start as usize);
+                        continue;
+                    }
+                },
+                _ => idx,
+            };
+            inconsistent.insert(reported);
+        }
+
+        // inconsistent is ordered, and the unused fields are only looked for once every struct
+        // definition turned out to be consistent.
+        let mut errs = inconsistent
+            .into_iter()
+            .map(|idx| {
+                VerificationError::new(
+                    IndexKind::StructDefinition,
+                    idx,
+                    VMStaticViolation::InconsistentFields,
+                )
+            })
+            .chain(unused.map(|idx| {
+                VerificationError::new(
+                    IndexKind::FieldDefinition,
+                    idx,
+                    VMStaticViolation::UnusedFields,
+                )
+            }));
+        ExpectedErrors {
+            primary: errs.next().into_iter().collect(),
+            secondary: errs.collect(),
+        }
+    }
+}
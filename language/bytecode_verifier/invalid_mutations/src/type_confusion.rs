@@ -0,0 +1,282 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{stack_usage::is_block_start, undo::UndoLog};
+use proptest::{prelude::*, sample::Index as PropIndex};
+use proptest_helpers::pick_slice_idxs;
+use vm::{
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        ByteArrayPoolIndex, Bytecode, CompiledModuleMut, FunctionDefinitionIndex,
+        StructDefinitionIndex, StructFieldInformation, TableIndex,
+    },
+};
+
+/// Represents a mutation that feeds an instruction operands of the wrong type.
+///
+/// Use `TypeConfusionMutation::strategy()` to generate them, preferably using `Vec` to generate
+/// many at a time. Then use `ApplyTypeConfusionContext` to apply those mutations.
+#[derive(Clone, Debug)]
+pub struct TypeConfusionMutation {
+    function_def: PropIndex,
+    site: PropIndex,
+    kind: TypeConfusionMutationKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum TypeConfusionMutationKind {
+    /// In `LdConst, LdConst, <integer op>`, replaces one of the constants with `LdTrue`.
+    BoolOperand { second: bool },
+    /// In `LdTrue | LdFalse, BrTrue | BrFalse`, replaces the condition with `LdConst`.
+    IntegerCondition,
+    /// In `LdConst, LdConst, <integer op>`, replaces one of the constants with `LdByteArray`.
+    ByteArrayOperand { second: bool },
+    /// In `LdTrue | LdFalse, BrTrue | BrFalse`, replaces the condition with `LdByteArray`.
+    ByteArrayCondition,
+    /// Retargets a `Pack` whose fields are loaded right before it, at the start of a basic block,
+    /// to a struct with at least two more fields.
+    PackFieldCount(PropIndex),
+}
+
+impl TypeConfusionMutation {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        (
+            any::<PropIndex>(),
+            any::<PropIndex>(),
+            TypeConfusionMutationKind::strategy(),
+        )
+            .prop_map(|(function_def, site, kind)| Self {
+                function_def,
+                site,
+                kind,
+            })
+    }
+}
+
+impl AsRef<PropIndex> for TypeConfusionMutation {
+    #[inline]
+    fn as_ref(&self) -> &PropIndex {
+        &self.function_def
+    }
+}
+
+impl TypeConfusionMutationKind {
+    pub fn strategy() -> impl Strategy<Value = Self> {
+        prop_oneof![
+            any::<bool>().prop_map(|second| TypeConfusionMutationKind::BoolOperand { second }),
+            Just(TypeConfusionMutationKind::IntegerCondition),
+            any::<bool>().prop_map(|second| TypeConfusionMutationKind::ByteArrayOperand { second }),
+            Just(TypeConfusionMutationKind::ByteArrayCondition),
+            any::<PropIndex>().prop_map(TypeConfusionMutationKind::PackFieldCount),
+        ]
+    }
+}
+
+/// Context for applying a list of `TypeConfusionMutation` instances.
+///
+/// Each mutation is applied to a different function, at a site that matches its pattern. Functions
+/// without such a site are left alone, as are byte array mutations in modules with an empty byte
+/// array pool. Operand swaps are reported by the type safety analysis, while a `Pack` of a struct
+/// with more fields underflows the stack of its block and is reported by the stack usage verifier.
+///
+/// The expected errors are only precise if the module passes the code unit verifier to begin with,
+/// and if an error in a basic block keeps the analysis from reaching any other block that would
+/// report one.
+pub struct ApplyTypeConfusionContext<'a> {
+    module: &'a mut CompiledModuleMut,
+    mutations: Vec<TypeConfusionMutation>,
+}
+
+impl<'a> ApplyTypeConfusionContext<'a> {
+    pub fn new(module: &'a mut CompiledModuleMut, mutations: Vec<TypeConfusionMutation>) -> Self {
+        Self { module, mutations }
+    }
+
+    pub fn apply(self) -> Vec<VerificationError> {
+        let (results, _) = self.apply_with_undo();
+        results
+    }
+
+    /// Applies the mutations the same way as `apply`, also returning a log that can be used to
+    /// undo them.
+    pub fn apply_with_undo(self) -> (Vec<VerificationError>, UndoLog) {
+        let field_counts: Vec<_> = self
+            .module
+            .struct_defs
+            .iter()
+            .map(|struct_def| match &struct_def.field_information {
+                StructFieldInformation::Native => None,
+                StructFieldInformation::Declared { field_count, .. } => Some(*field_count as usize),
+            })
+            .collect();
+        let has_byte_arrays = !self.module.byte_array_pool.is_empty();
+        let function_def_idxs: Vec<_> = self
+            .module
+            .function_defs
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| !function_def.is_native())
+            .map(|(idx, _)| idx)
+            .collect();
+        let picked = pick_slice_idxs(function_def_idxs.len(), &self.mutations);
+
+        let mut errs = vec![];
+        let mut undo = UndoLog::new();
+        for (mutation, picked_idx) in self.mutations.iter().zip(picked) {
+            let idx = function_def_idxs[picked_idx];
+            undo.record_code(self.module, idx);
+            let code = &mut self.module.function_defs[idx].code.code;
+            if let Some(err) = apply_one(code, &field_counts, has_byte_arrays, mutation) {
+                errs.push(VerificationError::in_function(
+                    FunctionDefinitionIndex::new(idx as TableIndex),
+                    err,
+                ));
+            }
+        }
+        (errs, undo)
+    }
+}
+
+fn apply_one(
+    code: &mut [Bytecode],
+    field_counts: &[Option<usize>],
+    has_byte_arrays: bool,
+    mutation: &TypeConfusionMutation,
+) -> Option<VMStaticViolation> {
+    // Any entry in the byte array pool will do, since only the type of the operand matters.
+    let byte_array = Bytecode::LdByteArray(ByteArrayPoolIndex::new(0));
+    match &mutation.kind {
+        TypeConfusionMutationKind::BoolOperand { second } => {
+            replace_integer_operand(code, &mutation.site, *second, Bytecode::LdTrue)
+        }
+        TypeConfusionMutationKind::IntegerCondition => {
+            replace_condition(code, &mutation.site, Bytecode::LdConst(0))
+        }
+        TypeConfusionMutationKind::ByteArrayOperand { second } if has_byte_arrays => {
+            replace_integer_operand(code, &mutation.site, *second, byte_array)
+        }
+        TypeConfusionMutationKind::ByteArrayCondition if has_byte_arrays => {
+            replace_condition(code, &mutation.site, byte_array)
+        }
+        TypeConfusionMutationKind::ByteArrayOperand { .. }
+        | TypeConfusionMutationKind::ByteArrayCondition => None,
+        TypeConfusionMutationKind::PackFieldCount(target) => {
+            let max_field_count = field_counts.iter().filter_map(|count| *count).max()?;
+            let sites: Vec<_> = (0..code.len())
+                .filter_map(|offset| {
+                    let field_count = match &code[offset] {
+                        Bytecode::Pack(idx, _) => field_counts[idx.0 as usize]?,
+                        _ => return None,
+                    };
+                    let start = offset.checked_sub(field_count)?;
+                    let loads_fields = (start..offset).all(|load| {
+                        is_load(&code[load]) && (load == start || !is_block_start(code, load))
+                    });
+                    if loads_fields
+                        && is_block_start(code, start)
+                        && !is_block_start(code, offset)
+                        && field_count + 2 <= max_field_count
+                    {
+                        Some((offset, start, field_count))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            let (offset, start, field_count) = pick(&sites, &mutation.site)?;
+            let targets: Vec<_> = field_counts
+                .iter()
+                .enumerate()
+                .filter(|(_, count)| count.map_or(false, |count| count >= field_count + 2))
+                .map(|(idx, _)| idx)
+                .collect();
+            let new_idx = targets[target.index(targets.len())];
+            code[offset] = match &code[offset] {
+                Bytecode::Pack(_, type_actuals) => Bytecode::Pack(
+                    StructDefinitionIndex::new(new_idx as TableIndex),
+                    *type_actuals,
+                ),
+                bytecode => panic!("expected a Pack, found {:?}", bytecode),
+            };
+            // The stack only holds the fields of the original struct, so it underflows at the Pack.
+            Some(VMStaticViolation::NegativeStackSizeInsideBlock(
+                start, offset,
+            ))
+        }
+    }
+}
+
+/// In `LdConst, LdConst, <integer op>`, replaces one of the constants with `operand`.
+fn replace_integer_operand(
+    code: &mut [Bytecode],
+    site: &PropIndex,
+    second: bool,
+    operand: Bytecode,
+) -> Option<VMStaticViolation> {
+    let sites: Vec<_> = (0..code.len().saturating_sub(2))
+        .filter(|offset| {
+            is_const(&code[*offset])
+                && is_const(&code[offset + 1])
+                && is_integer_op(&code[offset + 2])
+        })
+        .collect();
+    let offset = pick(&sites, site)?;
+    code[offset + second as usize] = operand;
+    Some(VMStaticViolation::IntegerOpTypeMismatchError(offset + 2))
+}
+
+/// In `LdTrue | LdFalse, BrTrue | BrFalse`, replaces the condition with `condition`.
+fn replace_condition(
+    code: &mut [Bytecode],
+    site: &PropIndex,
+    condition: Bytecode,
+) -> Option<VMStaticViolation> {
+    let sites: Vec<_> = (0..code.len().saturating_sub(1))
+        .filter(|offset| is_bool(&code[*offset]) && code[offset + 1].is_conditional_branch())
+        .collect();
+    let offset = pick(&sites, site)?;
+    code[offset] = condition;
+    Some(VMStaticViolation::BrTypeMismatchError(offset + 1))
+}
+
+fn pick<T: Copy>(sites: &[T], site: &PropIndex) -> Option<T> {
+    if sites.is_empty() {
+        None
+    } else {
+        Some(sites[site.index(sites.len())])
+    }
+}
+
+fn is_const(bytecode: &Bytecode) -> bool {
+    match bytecode {
+        Bytecode::LdConst(_) => true,
+        _ => false,
+    }
+}
+
+fn is_bool(bytecode: &Bytecode) -> bool {
+    match bytecode {
+        Bytecode::LdTrue | Bytecode::LdFalse => true,
+        _ => false,
+    }
+}
+
+fn is_integer_op(bytecode: &Bytecode) -> bool {
+    use Bytecode::*;
+
+    match bytecode {
+        Add | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor => true,
+        _ => false,
+    }
+}
+
+/// Returns true if `bytecode` pushes exactly one value without popping any.
+fn is_load(bytecode: &Bytecode) -> bool {
+    use Bytecode::*;
+
+    match bytecode {
+        LdConst(_) | LdTrue | LdFalse | LdAddr(_) | LdStr(_) | LdByteArray(_) | CopyLoc(_)
+        | MoveLoc(_) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,83 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use vm::file_format::{Bytecode, CompiledModuleMut};
+
+/// Records the parts of a module that were changed by mutations, along with their prior values,
+/// so that the changes can be undone.
+///
+/// This is returned by the `apply_with_undo` methods on the contexts that only mutate code units.
+/// Undoing the mutations is much cheaper than cloning the whole module up front, which lets a
+/// single module that is expensive to generate be reused across many verification runs.
+#[derive(Debug, Default)]
+pub struct UndoLog {
+    changes: Vec<Change>,
+}
+
+#[derive(Debug)]
+enum Change {
+    /// A single instruction was replaced.
+    Bytecode {
+        function_def: usize,
+        offset: usize,
+        bytecode: Bytecode,
+    },
+    /// The code of a function was changed in a way that may have altered its length.
+    Code {
+        function_def: usize,
+        code: Vec<Bytecode>,
+    },
+}
+
+impl UndoLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if nothing was recorded, i.e. the module was left untouched.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Records the instruction at `offset` in function definition `function_def`. This must be
+    /// called before the instruction is replaced.
+    pub(crate) fn record_bytecode(
+        &mut self,
+        module: &CompiledModuleMut,
+        function_def: usize,
+        offset: usize,
+    ) {
+        let bytecode = module.function_defs[function_def].code.code[offset].clone();
+        self.changes.push(Change::Bytecode {
+            function_def,
+            offset,
+            bytecode,
+        });
+    }
+
+    /// Records the whole code of function definition `function_def`. This must be called before
+    /// the code is changed.
+    pub(crate) fn record_code(&mut self, module: &CompiledModuleMut, function_def: usize) {
+        let code = module.function_defs[function_def].code.code.clone();
+        self.changes.push(Change::Code { function_def, code });
+    }
+
+    /// Restores everything that was recorded, in the reverse order it was recorded in.
+    ///
+    /// `module` must be the module the mutations were applied to, and must not have been changed
+    /// since other than by the mutations this log records.
+    pub fn undo(self, module: &mut CompiledModuleMut) {
+        for change in self.changes.into_iter().rev() {
+            match change {
+                Change::Bytecode {
+                    function_def,
+                    offset,
+                    bytecode,
+                } => module.function_defs[function_def].code.code[offset] = bytecode,
+                Change::Code { function_def, code } => {
+                    module.function_defs[function_def].code.code = code
+                }
+            }
+        }
+    }
+}
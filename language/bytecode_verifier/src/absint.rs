@@ -1,3 +1,15 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a framework for abstract interpretation over the control flow graph of a
+//! code unit. The type and memory safety verifier is built on it, and other static analyses (e.g.
+//! constant propagation or taint tracking) can be written by providing:
+//! - an abstract domain (`AbstractDomain`), with a join and optionally a widening;
+//! - transfer functions (`TransferFunctions`), which execute an instruction on an abstract state.
+//!
+//! `AbstractInterpreter::analyze_code` then computes the state at the start of every reachable
+//! block by iterating to a fixed point, widening instead of joining along edges that go back in
+//! the code, so that domains of infinite height converge as well.
 use crate::control_flow_graph::{BlockId, ControlFlowGraph};
 use std::collections::HashMap;
 use vm::{
@@ -5,10 +17,16 @@ use vm::{
     views::FunctionDefinitionView,
 };
 
-/// Trait for finite-height abstract domains. Infinite height domains would require a more complex
-/// trait with widening and a partial order.
+/// Trait for abstract domains. Domains of finite height only need a join; domains of infinite
+/// height must also override `widen` so that the analysis of loops converges.
 pub trait AbstractDomain: Clone + Sized {
     fn join(&mut self, other: &Self) -> JoinResult;
+
+    /// Joins `other` into `self` at a block that may start a loop, so that repeatedly widening
+    /// only changes `self` a finite number of times. Defaults to `join`.
+    fn widen(&mut self, other: &Self) -> JoinResult {
+        self.join(other)
+    }
 }
 
 #[derive(Debug)]
@@ -40,6 +58,8 @@ pub struct BlockInvariant<State> {
     pre: BlockPrecondition<State>,
     /// Postcondition of the block---just success/error for now
     post: BlockPostcondition,
+    /// The block whose postcondition first reached this block, or `None` for the entry block
+    predecessor: Option<BlockId>,
 }
 
 impl<State> BlockInvariant<State> {
@@ -50,12 +70,20 @@ impl<State> BlockInvariant<State> {
     pub fn post(&self) -> &BlockPostcondition {
         &self.post
     }
+
+    pub fn predecessor(&self) -> Option<BlockId> {
+        self.predecessor
+    }
 }
 
 /// A map from block id's to the pre/post of each block after a fixed point is reached.
 #[allow(dead_code)]
 pub type InvariantMap<State> = HashMap<BlockId, BlockInvariant<State>>;
 
+/// The analysis analyzed more blocks than it was allowed to before reaching a fixed point.
+#[derive(Debug)]
+pub struct IterationBudgetExceeded;
+
 /// Take a pre-state + instruction and mutate it to produce a post-state
 /// Auxiliary data can be stored in self.
 pub trait TransferFunctions {
@@ -83,12 +111,32 @@ pub trait TransferFunctions {
 
 pub trait AbstractInterpreter: TransferFunctions {
     /// Analyze procedure local@function_view starting from pre-state local@initial_state.
+    /// Gives up if a fixed point isn't reached after analyzing local@max_iterations blocks.
     fn analyze_function(
         &mut self,
         initial_state: Self::State,
         function_view: &FunctionDefinitionView<CompiledModule>,
         cfg: &dyn ControlFlowGraph,
-    ) -> InvariantMap<Self::State> {
+        max_iterations: Option<u64>,
+    ) -> Result<InvariantMap<Self::State>, IterationBudgetExceeded> {
+        self.analyze_code(
+            initial_state,
+            &function_view.code().code,
+            cfg,
+            max_iterations,
+        )
+    }
+
+    /// Analyze local@code, whose control flow graph is local@cfg, starting from pre-state
+    /// local@initial_state. Gives up if a fixed point isn't reached after analyzing
+    /// local@max_iterations blocks.
+    fn analyze_code(
+        &mut self,
+        initial_state: Self::State,
+        code: &[Bytecode],
+        cfg: &dyn ControlFlowGraph,
+        max_iterations: Option<u64>,
+    ) -> Result<InvariantMap<Self::State>, IterationBudgetExceeded> {
         let mut inv_map: InvariantMap<Self::State> = InvariantMap::new();
         let entry_block_id = cfg.entry_block_id();
         let mut work_list = vec![entry_block_id];
@@ -97,9 +145,11 @@ pub trait AbstractInterpreter: TransferFunctions {
             BlockInvariant {
                 pre: BlockPrecondition::State(initial_state),
                 post: BlockPostcondition::Success,
+                predecessor: None,
             },
         );
 
+        let mut iterations = 0;
         while let Some(block_id) = work_list.pop() {
             let mut block_invariant = match inv_map.get_mut(&block_id) {
                 Some(BlockInvariant {
@@ -122,9 +172,11 @@ pub trait AbstractInterpreter: TransferFunctions {
                     continue
                 }
             };
-            let block_ends_in_error = self
-                .execute_block(block_id, &mut state, &function_view, cfg)
-                .is_err();
+            iterations += 1;
+            if max_iterations.map_or(false, |max| iterations > max) {
+                return Err(IterationBudgetExceeded);
+            }
+            let block_ends_in_error = self.execute_block(block_id, &mut state, code, cfg).is_err();
             if block_ends_in_error {
                 block_invariant.post = BlockPostcondition::Error;
                 continue;
@@ -137,6 +189,11 @@ pub trait AbstractInterpreter: TransferFunctions {
                 match inv_map.get_mut(next_block_id) {
                     Some(next_block_invariant) => {
                         let join_result = match &mut next_block_invariant.pre {
+                            // Every cycle has an edge going back in the code, so widening along
+                            // those edges is enough for the analysis to converge.
+                            BlockPrecondition::State(old_pre) if *next_block_id <= block_id => {
+                                old_pre.widen(&state)
+                            }
                             BlockPrecondition::State(old_pre) => old_pre.join(&state),
                             BlockPrecondition::JoinFailure => JoinResult::Error,
                         };
@@ -165,6 +222,7 @@ pub trait AbstractInterpreter: TransferFunctions {
                             BlockInvariant {
                                 pre: BlockPrecondition::State(state.clone()),
                                 post: BlockPostcondition::Success,
+                                predecessor: Some(block_id),
                             },
                         );
                         work_list.push(*next_block_id);
@@ -173,19 +231,19 @@ pub trait AbstractInterpreter: TransferFunctions {
             }
         }
 
-        inv_map
+        Ok(inv_map)
     }
 
     fn execute_block(
         &mut self,
         block_id: BlockId,
         state: &mut Self::State,
-        function_view: &FunctionDefinitionView<CompiledModule>,
+        code: &[Bytecode],
         cfg: &dyn ControlFlowGraph,
     ) -> Result<(), Self::AnalysisError> {
         let block_end = cfg.block_end(&block_id);
         for offset in cfg.instr_indexes(&block_id) {
-            let instr = &function_view.code().code[offset as usize];
+            let instr = &code[offset as usize];
             self.execute(state, instr, offset as usize, block_end as usize)?
         }
 
@@ -8,6 +8,7 @@ use crate::{
     partition::Partition,
 };
 use mirai_annotations::checked_verify;
+use serde_json::{json, Value};
 use std::collections::{BTreeMap, BTreeSet};
 use vm::file_format::{FieldDefinitionIndex, Kind, LocalIndex, StructDefinitionIndex};
 
@@ -464,6 +465,139 @@ impl AbstractState {
         }
     }
 
+    /// returns a JSON representation of self, for debugging
+    /// nonces are represented by their numbers, which are only meaningful within a single state
+    pub fn to_json(&self) -> Value {
+        let locals: Vec<_> = self
+            .locals
+            .iter()
+            .map(|(idx, value)| match value {
+                AbstractValue::Reference(nonce) => {
+                    json!({ "local": idx, "reference": nonce.inner() })
+                }
+                AbstractValue::Value(kind, borrowed_by) => json!({
+                    "local": idx,
+                    "kind": kind_name(*kind),
+                    "borrowed_by": nonce_numbers(borrowed_by),
+                }),
+            })
+            .collect();
+        let globals: Vec<_> = self
+            .globals
+            .iter()
+            .filter(|(_, borrowed_by)| !borrowed_by.is_empty())
+            .map(|(idx, borrowed_by)| {
+                json!({ "struct_definition": idx.0, "borrowed_by": nonce_numbers(borrowed_by) })
+            })
+            .collect();
+        let borrows: Vec<_> = self
+            .borrows
+            .iter()
+            .map(|(nonce, borrow_info)| match borrow_info {
+                BorrowInfo::BorrowedBy(borrowed_by) => json!({
+                    "nonce": nonce.inner(),
+                    "borrowed_by": nonce_numbers(borrowed_by),
+                }),
+                BorrowInfo::FieldsBorrowedBy(fields_borrowed_by) => {
+                    let fields: Vec<_> = fields_borrowed_by
+                        .iter()
+                        .map(|(idx, borrowed_by)| {
+                            json!({ "field": idx.0, "borrowed_by": nonce_numbers(borrowed_by) })
+                        })
+                        .collect();
+                    json!({ "nonce": nonce.inner(), "fields_borrowed_by": fields })
+                }
+            })
+            .collect();
+        json!({ "locals": locals, "globals": globals, "borrows": borrows })
+    }
+
+    /// returns the borrow graph of self as DOT node and edge statements, with an edge from
+    /// everything that is borrowed to each nonce borrowing from it
+    /// node names are prefixed with local@prefix, so that the graphs of several states can be
+    /// rendered in the same file
+    pub fn to_dot_statements(&self, prefix: &str) -> Vec<String> {
+        let nonce_node = |nonce: &Nonce| format!("{}n{}", prefix, nonce.inner());
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        let mut nonces = BTreeSet::new();
+
+        for (idx, value) in &self.locals {
+            let local_node = format!("{}local{}", prefix, idx);
+            nodes.push(format!(
+                "{} [label=\"local {}\", shape=box];",
+                local_node, idx
+            ));
+            match value {
+                // A reference stored in a local is drawn as a dashed edge to its nonce.
+                AbstractValue::Reference(nonce) => {
+                    edges.push(format!(
+                        "{} -> {} [style=dashed];",
+                        local_node,
+                        nonce_node(nonce)
+                    ));
+                    nonces.insert(nonce);
+                }
+                AbstractValue::Value(_, borrowed_by) => {
+                    for nonce in borrowed_by {
+                        edges.push(format!("{} -> {};", local_node, nonce_node(nonce)));
+                        nonces.insert(nonce);
+                    }
+                }
+            }
+        }
+        for (idx, borrowed_by) in &self.globals {
+            if borrowed_by.is_empty() {
+                continue;
+            }
+            let global_node = format!("{}global{}", prefix, idx);
+            nodes.push(format!(
+                "{} [label=\"global {}\", shape=box];",
+                global_node, idx
+            ));
+            for nonce in borrowed_by {
+                edges.push(format!("{} -> {};", global_node, nonce_node(nonce)));
+                nonces.insert(nonce);
+            }
+        }
+        for (src_nonce, borrow_info) in &self.borrows {
+            nonces.insert(src_nonce);
+            match borrow_info {
+                BorrowInfo::BorrowedBy(borrowed_by) => {
+                    for nonce in borrowed_by {
+                        edges.push(format!(
+                            "{} -> {};",
+                            nonce_node(src_nonce),
+                            nonce_node(nonce)
+                        ));
+                        nonces.insert(nonce);
+                    }
+                }
+                BorrowInfo::FieldsBorrowedBy(fields_borrowed_by) => {
+                    for (idx, borrowed_by) in fields_borrowed_by {
+                        for nonce in borrowed_by {
+                            edges.push(format!(
+                                "{} -> {} [label=\"field {}\"];",
+                                nonce_node(src_nonce),
+                                nonce_node(nonce),
+                                idx
+                            ));
+                            nonces.insert(nonce);
+                        }
+                    }
+                }
+            }
+        }
+
+        nodes.extend(
+            nonces
+                .into_iter()
+                .map(|nonce| format!("{} [label=\"#{}\"];", nonce_node(nonce), nonce.inner())),
+        );
+        nodes.extend(edges);
+        nodes
+    }
+
     fn unrestricted_borrowed_value_unavailable(
         state1: &AbstractState,
         state2: &AbstractState,
@@ -704,3 +838,15 @@ impl AbstractDomain for AbstractState {
         }
     }
 }
+
+fn kind_name(kind: Kind) -> &'static str {
+    match kind {
+        Kind::All => "all",
+        Kind::Resource => "resource",
+        Kind::Unrestricted => "unrestricted",
+    }
+}
+
+fn nonce_numbers(nonces: &BTreeSet<Nonce>) -> Vec<usize> {
+    nonces.iter().map(Nonce::inner).collect()
+}
@@ -0,0 +1,241 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Verifies compiled modules or scripts (`.mv` files) offline, against the modules of an optional
+//! dependency directory, for CI pipelines that gate module publishing.
+//!
+//! Exits with 0 if every input verified, with 1 if any input couldn't be deserialized or failed
+//! verification, and with 2 if the inputs or dependencies couldn't be read.
+
+use bytecode_verifier::{
+    verify_module_dependencies, verify_module_with_config, verify_script_dependencies,
+    VerifiedModule, VerifiedScript, VerifierConfig,
+};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+use structopt::StructOpt;
+use vm::{
+    access::ModuleAccess,
+    errors::{has_errors, BinaryError, VerificationError},
+    file_format::{CompiledModule, CompiledScript},
+    views::ModuleView,
+};
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_UNREADABLE: i32 = 2;
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "Bytecode Verifier",
+    author = "The Libra Association",
+    about = "Verifies compiled Move modules and scripts"
+)]
+struct Args {
+    /// Treat the inputs as scripts (default is to treat them as modules)
+    #[structopt(short = "s", long = "script")]
+    pub script: bool,
+    /// Directory of the compiled modules (`.mv` files) the inputs may depend on
+    #[structopt(short = "d", long = "deps", parse(from_os_str))]
+    pub deps_dir: Option<PathBuf>,
+    /// Keep running passes after one of them fails, to report as many errors as possible (modules
+    /// only)
+    #[structopt(long = "collect-all")]
+    pub collect_all: bool,
+    /// Also run the advisory passes: unreachable code, reducibility and global storage (modules
+    /// only)
+    #[structopt(long = "advisory")]
+    pub advisory: bool,
+    /// Print the results as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+    /// Paths to the compiled modules or scripts to verify
+    #[structopt(parse(from_os_str), raw(required = "true"))]
+    pub inputs: Vec<PathBuf>,
+}
+
+/// The outcome of verifying a single input.
+struct InputReport {
+    path: PathBuf,
+    /// The errors and warnings found, along with their descriptions, or the reason the input
+    /// couldn't be deserialized.
+    result: Result<Vec<(VerificationError, String)>, BinaryError>,
+}
+
+impl InputReport {
+    fn verified(&self) -> bool {
+        match &self.result {
+            Ok(errors) => !errors.iter().any(|(err, _)| err.is_error()),
+            Err(_) => false,
+        }
+    }
+
+    fn print(&self) {
+        match &self.result {
+            Ok(errors) => {
+                let status = if self.verified() {
+                    "verified"
+                } else {
+                    "failed"
+                };
+                println!("{}: {}", self.path.display(), status);
+                for (err, description) in errors {
+                    println!("    {}: {}", err.severity(), description);
+                }
+            }
+            Err(err) => println!("{}: malformed: {}", self.path.display(), err),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match &self.result {
+            Ok(errors) => json!({
+                "path": self.path.display().to_string(),
+                "verified": self.verified(),
+                "errors": errors
+                    .iter()
+                    .map(|(err, description)| json!({
+                        "code": err.err.code(),
+                        "severity": err.severity().to_string(),
+                        "message": description,
+                    }))
+                    .collect::<Vec<_>>(),
+            }),
+            Err(err) => json!({
+                "path": self.path.display().to_string(),
+                "verified": false,
+                "malformed": err.to_string(),
+            }),
+        }
+    }
+}
+
+fn main() {
+    let args = Args::from_args();
+
+    let mut config = if args.advisory {
+        VerifierConfig::advisory()
+    } else {
+        VerifierConfig::all()
+    };
+    config.collect_all = args.collect_all;
+    let deps = match &args.deps_dir {
+        Some(dir) => load_dependencies(dir),
+        None => vec![],
+    };
+
+    let reports: Vec<_> = args
+        .inputs
+        .iter()
+        .map(|path| {
+            let binary = fs::read(path).unwrap_or_else(|err| {
+                eprintln!("Unable to read {}: {}", path.display(), err);
+                process::exit(EXIT_UNREADABLE)
+            });
+            let result = if args.script {
+                verify_script(&binary, &deps)
+            } else {
+                verify_module(&binary, &config, &deps)
+            };
+            InputReport {
+                path: path.clone(),
+                result,
+            }
+        })
+        .collect();
+
+    if args.json {
+        let reports: Vec<_> = reports.iter().map(InputReport::to_json).collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&reports).expect("JSON values always serialize")
+        );
+    } else {
+        for report in &reports {
+            report.print();
+        }
+    }
+    if !reports.iter().all(InputReport::verified) {
+        process::exit(EXIT_FAILED);
+    }
+}
+
+/// Loads every `.mv` file in `dir` as a module, exiting if any of them can't be read.
+fn load_dependencies(dir: &Path) -> Vec<CompiledModule> {
+    let unreadable = |path: &Path, err: &dyn std::fmt::Display| -> ! {
+        eprintln!("Unable to read dependency {}: {}", path.display(), err);
+        process::exit(EXIT_UNREADABLE)
+    };
+    let entries = fs::read_dir(dir).unwrap_or_else(|err| unreadable(dir, &err));
+    let mut paths: Vec<_> = entries
+        .map(|entry| entry.unwrap_or_else(|err| unreadable(dir, &err)).path())
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |extension| extension == "mv")
+        })
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .map(|path| {
+            let binary = fs::read(path).unwrap_or_else(|err| unreadable(path, &err));
+            CompiledModule::deserialize(&binary).unwrap_or_else(|err| unreadable(path, &err))
+        })
+        .collect()
+}
+
+/// Verifies a module with `config`, and then against `deps` if it verified.
+fn verify_module(
+    binary: &[u8],
+    config: &VerifierConfig,
+    deps: &[CompiledModule],
+) -> Result<Vec<(VerificationError, String)>, BinaryError> {
+    let module = CompiledModule::deserialize(binary)?;
+    let errors = verify_module_with_config(&module, config);
+    if has_errors(&errors) {
+        return Ok(describe(&module, errors));
+    }
+    // `config` runs every pass `VerifiedModule::new` does, so this only fails if it panics.
+    match VerifiedModule::new(module) {
+        Ok(module) => {
+            let mut errors = errors;
+            errors.extend(verify_module_dependencies(&module, deps));
+            Ok(describe(&module, errors))
+        }
+        Err((module, errors)) => Ok(describe(&module, errors)),
+    }
+}
+
+/// Verifies a script the same way as `VerifiedScript::new`, and then against `deps` if it
+/// verified.
+fn verify_script(
+    binary: &[u8],
+    deps: &[CompiledModule],
+) -> Result<Vec<(VerificationError, String)>, BinaryError> {
+    let script = CompiledScript::deserialize(binary)?;
+    match VerifiedScript::new(script) {
+        Ok(script) => {
+            let errors = verify_script_dependencies(&script, deps);
+            Ok(describe(&script.into_module(), errors))
+        }
+        Err((script, errors)) => Ok(describe(&script.into_module(), errors)),
+    }
+}
+
+/// Pairs every error with its description, with locations resolved to names in `module`.
+fn describe(
+    module: &impl ModuleAccess,
+    errors: Vec<VerificationError>,
+) -> Vec<(VerificationError, String)> {
+    let view = ModuleView::new(module);
+    errors
+        .into_iter()
+        .map(|err| {
+            let description = err.display_with(&view).to_string();
+            (err, description)
+        })
+        .collect()
+}
@@ -0,0 +1,190 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module dumps the abstract states computed by the type and memory safety analysis for
+//! function definitions it rejects, so that developers can see why a borrow was rejected instead
+//! of only getting an error code.
+//!
+//! For every basic block the analysis reached, a dump shows the state the block starts in: what
+//! the locals hold, which globals are borrowed, and which nonces (abstract references) borrow from
+//! which. Nonce numbers are only meaningful within a single block, as states are made canonical at
+//! the end of every block.
+use crate::{
+    absint::{BlockPostcondition, BlockPrecondition},
+    config::VerifierLimits,
+    control_flow_graph::{BlockId, ControlFlowGraph, VMControlFlowGraph},
+    stack_usage_verifier::StackUsageVerifier,
+    type_memory_safety::TypeAndMemorySafetyAnalysis,
+};
+use serde_json::{json, Value};
+use vm::{
+    access::ModuleAccess,
+    file_format::{Bytecode, CodeOffset, CompiledModule, FunctionDefinitionIndex, TableIndex},
+};
+
+/// The format of a borrow graph dump.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BorrowGraphFormat {
+    /// A JSON object listing the errors of the function and the state at the start of each block.
+    Json,
+    /// A Graphviz digraph with a cluster per block, holding the borrow graph the block starts
+    /// with.
+    Dot,
+}
+
+pub struct BorrowGraphDumper<'a> {
+    module: &'a CompiledModule,
+    format: BorrowGraphFormat,
+    limits: VerifierLimits,
+}
+
+impl<'a> BorrowGraphDumper<'a> {
+    pub fn new(module: &'a CompiledModule, format: BorrowGraphFormat) -> Self {
+        Self {
+            module,
+            format,
+            limits: VerifierLimits::recommended(),
+        }
+    }
+
+    /// Returns the dumps of every function definition the type and memory safety analysis
+    /// rejects.
+    ///
+    /// The module is expected to pass the module-level passes the analysis depends on (see
+    /// `ConfigurablePass::dependencies`). Functions failing the control flow or stack usage passes
+    /// can't be analyzed, and are skipped.
+    pub fn dump_failures(&self) -> Vec<(FunctionDefinitionIndex, String)> {
+        (0..self.module.function_defs().len())
+            .map(|idx| FunctionDefinitionIndex::new(idx as TableIndex))
+            .filter_map(|idx| self.dump_function(idx).map(|dump| (idx, dump)))
+            .collect()
+    }
+
+    /// Returns the dump of the function definition at `idx`, or `None` if the type and memory
+    /// safety analysis accepts it or can't analyze it.
+    pub fn dump_function(&self, idx: FunctionDefinitionIndex) -> Option<String> {
+        let function_definition = self.module.function_def_at(idx);
+        let code = &function_definition.code.code;
+        let ends_in_branch = code.last().map_or(false, Bytecode::is_unconditional_branch);
+        if function_definition.is_native() || !ends_in_branch {
+            return None;
+        }
+        let cfg = VMControlFlowGraph::new(code);
+        if !StackUsageVerifier::verify(self.module, function_definition, &cfg).is_empty() {
+            return None;
+        }
+        let locals_len = self
+            .module
+            .locals_signature_at(function_definition.code.locals)
+            .0
+            .len();
+        if !self.limits.function_complexity_ok(code.len(), locals_len) {
+            return None;
+        }
+
+        let (errors, inv_map) = TypeAndMemorySafetyAnalysis::analyze(
+            self.module,
+            function_definition,
+            &cfg,
+            &self.limits,
+        );
+        if errors.is_empty() {
+            return None;
+        }
+        let errors: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+        let mut blocks: Vec<_> = inv_map
+            .iter()
+            .map(|(block_id, invariant)| {
+                let block = BlockDump {
+                    id: *block_id,
+                    start: cfg.block_start(block_id),
+                    end: cfg.block_end(block_id),
+                    failed: match invariant.post() {
+                        BlockPostcondition::Success => false,
+                        BlockPostcondition::Error => true,
+                    },
+                };
+                let pre = match invariant.pre() {
+                    BlockPrecondition::State(state) => Some(state),
+                    BlockPrecondition::JoinFailure => None,
+                };
+                (block, pre)
+            })
+            .collect();
+        blocks.sort_by_key(|(block, _)| block.id);
+
+        Some(match self.format {
+            BorrowGraphFormat::Json => {
+                let blocks: Vec<_> = blocks
+                    .into_iter()
+                    .map(|(block, pre)| {
+                        json!({
+                            "block": block.id,
+                            "start": block.start,
+                            "end": block.end,
+                            "status": block.status(pre.is_some()),
+                            "pre": pre.map_or(Value::Null, |state| state.to_json()),
+                        })
+                    })
+                    .collect();
+                let dump = json!({ "function": idx.0, "errors": errors, "blocks": blocks });
+                serde_json::to_string_pretty(&dump).expect("JSON values always serialize")
+            }
+            BorrowGraphFormat::Dot => {
+                let mut lines = vec![
+                    format!("digraph \"function {}\" {{", idx),
+                    format!(
+                        "    label=\"function {}: {}\";",
+                        idx,
+                        escape_dot(&errors.join("\\n"))
+                    ),
+                ];
+                for (block, pre) in blocks {
+                    let prefix = format!("b{}_", block.id);
+                    lines.push(format!("    subgraph cluster_{} {{", block.id));
+                    lines.push(format!(
+                        "        label=\"block {} (offsets {}..={}): {}\";",
+                        block.id,
+                        block.start,
+                        block.end,
+                        block.status(pre.is_some())
+                    ));
+                    let statements = match pre {
+                        Some(state) => state.to_dot_statements(&prefix),
+                        None => vec![format!(
+                            "{}join_failure [label=\"join failure\", shape=plaintext];",
+                            prefix
+                        )],
+                    };
+                    lines.extend(statements.into_iter().map(|s| format!("        {}", s)));
+                    lines.push("    }".to_string());
+                }
+                lines.push("}".to_string());
+                lines.join("\n")
+            }
+        })
+    }
+}
+
+struct BlockDump {
+    id: BlockId,
+    start: CodeOffset,
+    end: CodeOffset,
+    failed: bool,
+}
+
+impl BlockDump {
+    fn status(&self, has_pre: bool) -> &'static str {
+        if !has_pre {
+            "join_failure"
+        } else if self.failed {
+            "error"
+        } else {
+            "ok"
+        }
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('"', "\\\"")
+}
@@ -0,0 +1,88 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines a cache of verification outcomes, so that modules that are loaded over and
+//! over again (like the standard library) are only verified once.
+use crate::config::VerifierConfig;
+use crypto::HashValue;
+use failure::prelude::*;
+use lru_cache::LruCache;
+use std::sync::Mutex;
+use vm::{errors::VerificationError, file_format::CompiledModule};
+
+/// Identifies a verification outcome: the same module verified with the same configuration always
+/// has the same outcome.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VerificationCacheKey {
+    /// The hash of the serialized module. Serialization is canonical, so two modules have the
+    /// same hash if and only if they are equal.
+    module_hash: HashValue,
+    /// See `VerifierConfig::fingerprint`.
+    config_fingerprint: u64,
+}
+
+impl VerificationCacheKey {
+    /// Returns the key for verifying `module` with `config`.
+    pub fn new(module: &CompiledModule, config: &VerifierConfig) -> Result<Self> {
+        let mut binary = vec![];
+        module.serialize(&mut binary)?;
+        Ok(Self {
+            module_hash: HashValue::from_sha3_256(&binary),
+            config_fingerprint: config.fingerprint(),
+        })
+    }
+}
+
+/// A cache of verification outcomes.
+///
+/// The outcome of verifying a module is the list of errors the verifier returned for it, which
+/// only contains warnings if the module passed verification.
+pub trait VerificationCache {
+    /// Returns the outcome cached for `key`, if any.
+    fn get(&self, key: &VerificationCacheKey) -> Option<Vec<VerificationError>>;
+
+    /// Caches `errors` as the outcome for `key`.
+    fn insert(&self, key: VerificationCacheKey, errors: Vec<VerificationError>);
+}
+
+/// A `VerificationCache` that keeps a bounded number of outcomes, evicting the least recently used
+/// one first.
+pub struct LruVerificationCache {
+    cache: Mutex<LruCache<VerificationCacheKey, Vec<VerificationError>>>,
+}
+
+impl LruVerificationCache {
+    /// Creates an empty cache holding at most `capacity` outcomes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns the number of outcomes currently cached.
+    pub fn len(&self) -> usize {
+        self.cache.lock().expect("poisoned lock").len()
+    }
+
+    /// Returns true if no outcomes are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl VerificationCache for LruVerificationCache {
+    fn get(&self, key: &VerificationCacheKey) -> Option<Vec<VerificationError>> {
+        self.cache
+            .lock()
+            .expect("poisoned lock")
+            .get_mut(key)
+            .cloned()
+    }
+
+    fn insert(&self, key: VerificationCacheKey, errors: Vec<VerificationError>) {
+        self.cache
+            .lock()
+            .expect("poisoned lock")
+            .insert(key, errors);
+    }
+}
@@ -0,0 +1,235 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module builds the call graph of a set of modules, for audits such as finding every function
+//! that can reach a privileged one.
+//!
+//! The nodes of the graph are the function definitions of the modules, and there is an edge from
+//! a function to every function it calls: the `Call` instructions of its code are resolved through
+//! their function handles to the definitions they refer to. Calls to functions defined outside of
+//! the set have no edge, but are kept as unresolved calls.
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+};
+use types::language_storage::ModuleId;
+use vm::{
+    access::ModuleAccess,
+    errors::short_address,
+    file_format::{Bytecode, CompiledModule, FunctionHandleIndex},
+};
+
+/// Identifies a function by its module and name.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct FunctionId {
+    pub module: ModuleId,
+    pub name: String,
+}
+
+impl FunctionId {
+    pub fn new(module: ModuleId, name: impl Into<String>) -> Self {
+        Self {
+            module,
+            name: name.into(),
+        }
+    }
+}
+
+/// Displays the function as `0x1::M::f`.
+impl fmt::Display for FunctionId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}::{}::{}",
+            short_address(self.module.address()),
+            self.module.name(),
+            self.name
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CallGraph {
+    /// The functions every function calls. Every function defined in the set is a key.
+    callees: BTreeMap<FunctionId, BTreeSet<FunctionId>>,
+    /// The functions that call every function. Every function defined in the set is a key.
+    callers: BTreeMap<FunctionId, BTreeSet<FunctionId>>,
+    /// The functions defined outside of the set that every function calls.
+    unresolved: BTreeMap<FunctionId, BTreeSet<FunctionId>>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of `modules`. If several modules have the same id, only the first
+    /// one is considered.
+    pub fn new<'a>(modules: impl IntoIterator<Item = &'a CompiledModule>) -> Self {
+        let mut module_map = BTreeMap::new();
+        for module in modules {
+            module_map.entry(module.self_id()).or_insert(module);
+        }
+
+        let mut graph = Self::default();
+        for (module_id, module) in &module_map {
+            for function_def in module.function_defs() {
+                let handle = module.function_handle_at(function_def.function);
+                let function = FunctionId::new(module_id.clone(), module.string_at(handle.name));
+                graph.callees.insert(function.clone(), BTreeSet::new());
+                graph.callers.insert(function, BTreeSet::new());
+            }
+        }
+
+        for (module_id, module) in &module_map {
+            for function_def in module.function_defs() {
+                let handle = module.function_handle_at(function_def.function);
+                let caller = FunctionId::new(module_id.clone(), module.string_at(handle.name));
+                for bytecode in &function_def.code.code {
+                    if let Bytecode::Call(idx, _) = bytecode {
+                        let callee = resolve(*module, *idx);
+                        if graph.callees.contains_key(&callee) {
+                            graph
+                                .callers
+                                .get_mut(&callee)
+                                .expect("callers and callees have the same keys")
+                                .insert(caller.clone());
+                            graph
+                                .callees
+                                .get_mut(&caller)
+                                .expect("the caller is defined in the set")
+                                .insert(callee);
+                        } else {
+                            graph
+                                .unresolved
+                                .entry(caller.clone())
+                                .or_insert_with(BTreeSet::new)
+                                .insert(callee);
+                        }
+                    }
+                }
+            }
+        }
+        graph
+    }
+
+    /// Returns every function defined in the set, in order.
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionId> {
+        self.callees.keys()
+    }
+
+    /// Returns true if `function` is defined in the set.
+    pub fn contains(&self, function: &FunctionId) -> bool {
+        self.callees.contains_key(function)
+    }
+
+    /// Returns the functions of the set that `function` calls directly.
+    pub fn callees(&self, function: &FunctionId) -> BTreeSet<&FunctionId> {
+        self.callees
+            .get(function)
+            .map_or_else(BTreeSet::new, |callees| callees.iter().collect())
+    }
+
+    /// Returns the functions of the set that call `function` directly.
+    pub fn callers(&self, function: &FunctionId) -> BTreeSet<&FunctionId> {
+        self.callers
+            .get(function)
+            .map_or_else(BTreeSet::new, |callers| callers.iter().collect())
+    }
+
+    /// Returns the functions defined outside of the set that `function` calls directly.
+    pub fn unresolved_callees(&self, function: &FunctionId) -> BTreeSet<&FunctionId> {
+        self.unresolved
+            .get(function)
+            .map_or_else(BTreeSet::new, |callees| callees.iter().collect())
+    }
+
+    /// Returns the functions of the set that `function` may call, directly or not. `function`
+    /// itself is included only if it is recursive.
+    pub fn reachable_from(&self, function: &FunctionId) -> BTreeSet<&FunctionId> {
+        reachable(&self.callees, function)
+    }
+
+    /// Returns the functions of the set that may call `function`, directly or not. `function`
+    /// itself is included only if it is recursive.
+    pub fn reaching(&self, function: &FunctionId) -> BTreeSet<&FunctionId> {
+        reachable(&self.callers, function)
+    }
+
+    /// Returns the graph as a Graphviz digraph, with a cluster per module. Unresolved calls are
+    /// drawn as dashed edges to nodes outside of any cluster.
+    pub fn to_dot(&self) -> String {
+        let node_ids: BTreeMap<&FunctionId, String> = self
+            .callees
+            .keys()
+            .chain(self.unresolved.values().flatten())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, function)| (function, format!("n{}", idx)))
+            .collect();
+
+        let mut lines = vec!["digraph \"call graph\" {".to_string()];
+        let mut modules: BTreeMap<&ModuleId, Vec<&FunctionId>> = BTreeMap::new();
+        for function in self.callees.keys() {
+            modules.entry(&function.module).or_default().push(function);
+        }
+        for (cluster, (module_id, functions)) in modules.into_iter().enumerate() {
+            lines.push(format!("    subgraph cluster_{} {{", cluster));
+            lines.push(format!(
+                "        label=\"{}::{}\";",
+                short_address(module_id.address()),
+                module_id.name()
+            ));
+            for function in functions {
+                lines.push(format!(
+                    "        {} [label=\"{}\"];",
+                    node_ids[function], function.name
+                ));
+            }
+            lines.push("    }".to_string());
+        }
+        for function in self.unresolved.values().flatten().collect::<BTreeSet<_>>() {
+            lines.push(format!(
+                "    {} [label=\"{}\", style=dashed];",
+                node_ids[function], function
+            ));
+        }
+        for (caller, callees) in &self.callees {
+            for callee in callees {
+                lines.push(format!("    {} -> {};", node_ids[caller], node_ids[callee]));
+            }
+        }
+        for (caller, callees) in &self.unresolved {
+            for callee in callees {
+                lines.push(format!(
+                    "    {} -> {} [style=dashed];",
+                    node_ids[caller], node_ids[callee]
+                ));
+            }
+        }
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
+/// Returns the function the function handle `idx` of `module` refers to.
+fn resolve(module: &CompiledModule, idx: FunctionHandleIndex) -> FunctionId {
+    let handle = module.function_handle_at(idx);
+    let module_id = module.module_id_for_handle(module.module_handle_at(handle.module));
+    FunctionId::new(module_id, module.string_at(handle.name))
+}
+
+/// Returns the nodes reachable from `start` through at least one edge of `edges`.
+fn reachable<'a>(
+    edges: &'a BTreeMap<FunctionId, BTreeSet<FunctionId>>,
+    start: &FunctionId,
+) -> BTreeSet<&'a FunctionId> {
+    let mut visited = BTreeSet::new();
+    let mut queue: VecDeque<&FunctionId> = VecDeque::new();
+    queue.push_back(start);
+    while let Some(function) = queue.pop_front() {
+        for next in edges.get(function).into_iter().flatten() {
+            if visited.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    visited
+}
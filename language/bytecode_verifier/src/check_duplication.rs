@@ -8,13 +8,16 @@
 //! - struct and field definitions are consistent
 //! - the handles in struct and function definitions point to IMPLEMENTED_MODULE_INDEX
 //! - all struct and function handles pointing to IMPLEMENTED_MODULE_INDEX have a definition
-use std::{collections::HashSet, hash::Hash};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    hash::Hash,
+};
 use vm::{
     access::ModuleAccess,
-    errors::{VMStaticViolation, VerificationError},
+    errors::{DuplicateKey, VMStaticViolation, VerificationError},
     file_format::{
-        CompiledModule, FieldDefinitionIndex, FunctionHandleIndex, ModuleHandleIndex,
-        StructFieldInformation, StructHandleIndex, TableIndex,
+        CompiledModule, FieldDefinitionIndex, FunctionHandleIndex, StructFieldInformation,
+        StructHandleIndex,
     },
     IndexKind,
 };
@@ -28,118 +31,122 @@ impl<'a> DuplicationChecker<'a> {
         Self { module }
     }
 
+    /// Reports at most one duplicate per table, located at the later of the two colliding
+    /// entries. The `DuplicateElement` violation carries the index of the earlier entry and what
+    /// the entries collide on.
     pub fn verify(self) -> Vec<VerificationError> {
         let mut errors = vec![];
 
-        if let Some(idx) = Self::first_duplicate_element(self.module.string_pool()) {
-            errors.push(VerificationError {
-                kind: IndexKind::StringPool,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.string_pool()) {
+            errors.push(VerificationError::new(
+                IndexKind::StringPool,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Value),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(self.module.byte_array_pool()) {
-            errors.push(VerificationError {
-                kind: IndexKind::ByteArrayPool,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.byte_array_pool()) {
+            errors.push(VerificationError::new(
+                IndexKind::ByteArrayPool,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Value),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(self.module.address_pool()) {
-            errors.push(VerificationError {
-                kind: IndexKind::AddressPool,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.address_pool()) {
+            errors.push(VerificationError::new(
+                IndexKind::AddressPool,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Value),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(self.module.type_signatures()) {
-            errors.push(VerificationError {
-                kind: IndexKind::TypeSignature,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.type_signatures()) {
+            errors.push(VerificationError::new(
+                IndexKind::TypeSignature,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Signature),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(self.module.function_signatures()) {
-            errors.push(VerificationError {
-                kind: IndexKind::FunctionSignature,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.function_signatures())
+        {
+            errors.push(VerificationError::new(
+                IndexKind::FunctionSignature,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Signature),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(self.module.locals_signatures()) {
-            errors.push(VerificationError {
-                kind: IndexKind::LocalsSignature,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.locals_signatures()) {
+            errors.push(VerificationError::new(
+                IndexKind::LocalsSignature,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Signature),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(self.module.module_handles()) {
-            errors.push(VerificationError {
-                kind: IndexKind::ModuleHandle,
+        if let Some((first, idx)) = Self::first_duplicate_element(self.module.module_handles()) {
+            errors.push(VerificationError::new(
+                IndexKind::ModuleHandle,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Value),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(
+        if let Some((first, idx)) = Self::first_duplicate_element(
             self.module
                 .struct_handles()
                 .iter()
                 .map(|x| (x.module, x.name)),
         ) {
-            errors.push(VerificationError {
-                kind: IndexKind::StructHandle,
+            errors.push(VerificationError::new(
+                IndexKind::StructHandle,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Name),
+            ))
         }
-        if let Some(idx) = Self::first_duplicate_element(
+        if let Some((first, idx)) = Self::first_duplicate_element(
             self.module
                 .function_handles()
                 .iter()
                 .map(|x| (x.module, x.name)),
         ) {
-            errors.push(VerificationError {
-                kind: IndexKind::FunctionHandle,
+            errors.push(VerificationError::new(
+                IndexKind::FunctionHandle,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Name),
+            ))
         }
-        if let Some(idx) =
+        if let Some((first, idx)) =
             Self::first_duplicate_element(self.module.struct_defs().iter().map(|x| x.struct_handle))
         {
-            errors.push(VerificationError {
-                kind: IndexKind::StructDefinition,
+            errors.push(VerificationError::new(
+                IndexKind::StructDefinition,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Handle),
+            ))
         }
-        if let Some(idx) =
+        if let Some((first, idx)) =
             Self::first_duplicate_element(self.module.function_defs().iter().map(|x| x.function))
         {
-            errors.push(VerificationError {
-                kind: IndexKind::FunctionDefinition,
+            errors.push(VerificationError::new(
+                IndexKind::FunctionDefinition,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Handle),
+            ))
         }
         for (idx, function_def) in self.module.function_defs().iter().enumerate() {
             let acquires = function_def.acquires_global_resources.iter();
             if Self::first_duplicate_element(acquires).is_some() {
-                errors.push(VerificationError {
-                    kind: IndexKind::FunctionDefinition,
+                errors.push(VerificationError::new(
+                    IndexKind::FunctionDefinition,
                     idx,
-                    err: VMStaticViolation::DuplicateAcquiresResourceAnnotationError,
-                })
+                    VMStaticViolation::DuplicateAcquiresResourceAnnotationError,
+                ))
             }
         }
-        if let Some(idx) = Self::first_duplicate_element(
+        if let Some((first, idx)) = Self::first_duplicate_element(
             self.module.field_defs().iter().map(|x| (x.struct_, x.name)),
         ) {
-            errors.push(VerificationError {
-                kind: IndexKind::FieldDefinition,
+            errors.push(VerificationError::new(
+                IndexKind::FieldDefinition,
                 idx,
-                err: VMStaticViolation::DuplicateElement,
-            })
+                VMStaticViolation::DuplicateElement(first, DuplicateKey::Name),
+            ))
         }
 
         // Check that:
@@ -161,13 +168,11 @@ impl<'a> DuplicationChecker<'a> {
                 break;
             }
             let next_start_field_index = start_field_index + field_count as usize;
-            let all_fields_match = (start_field_index..next_start_field_index).all(|i| {
-                struct_def.struct_handle
-                    == self
-                        .module
-                        .field_def_at(FieldDefinitionIndex::new(i as TableIndex))
-                        .struct_
-            });
+            let all_fields_match = self
+                .module
+                .field_def_range(field_count, fields)
+                .iter()
+                .all(|field_def| struct_def.struct_handle == field_def.struct_);
             if !all_fields_match {
                 idx_opt = Some(idx);
                 break;
@@ -175,42 +180,44 @@ impl<'a> DuplicationChecker<'a> {
             start_field_index = next_start_field_index;
         }
         if let Some(idx) = idx_opt {
-            errors.push(VerificationError {
-                kind: IndexKind::StructDefinition,
+            errors.push(VerificationError::new(
+                IndexKind::StructDefinition,
                 idx,
-                err: VMStaticViolation::InconsistentFields,
-            });
+                VMStaticViolation::InconsistentFields,
+            ));
         } else if start_field_index != self.module.field_defs().len() {
-            errors.push(VerificationError {
-                kind: IndexKind::FieldDefinition,
-                idx: start_field_index,
-                err: VMStaticViolation::UnusedFields,
-            });
+            errors.push(VerificationError::new(
+                IndexKind::FieldDefinition,
+                start_field_index,
+                VMStaticViolation::UnusedFields,
+            ));
         }
 
         // Check that each struct definition is pointing to module handle with index
         // IMPLEMENTED_MODULE_INDEX.
         if let Some(idx) = self.module.struct_defs().iter().position(|x| {
-            self.module.struct_handle_at(x.struct_handle).module
-                != ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX)
+            !self
+                .module
+                .is_self_handle(self.module.struct_handle_at(x.struct_handle).module)
         }) {
-            errors.push(VerificationError {
-                kind: IndexKind::StructDefinition,
+            errors.push(VerificationError::new(
+                IndexKind::StructDefinition,
                 idx,
-                err: VMStaticViolation::InvalidModuleHandle,
-            })
+                VMStaticViolation::InvalidModuleHandle,
+            ))
         }
         // Check that each function definition is pointing to module handle with index
         // IMPLEMENTED_MODULE_INDEX.
         if let Some(idx) = self.module.function_defs().iter().position(|x| {
-            self.module.function_handle_at(x.function).module
-                != ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX)
+            !self
+                .module
+                .is_self_handle(self.module.function_handle_at(x.function).module)
         }) {
-            errors.push(VerificationError {
-                kind: IndexKind::FunctionDefinition,
+            errors.push(VerificationError::new(
+                IndexKind::FunctionDefinition,
                 idx,
-                err: VMStaticViolation::InvalidModuleHandle,
-            })
+                VMStaticViolation::InvalidModuleHandle,
+            ))
         }
         // Check that each struct handle with module handle index IMPLEMENTED_MODULE_INDEX is
         // implemented.
@@ -222,15 +229,15 @@ impl<'a> DuplicationChecker<'a> {
             .collect();
         if let Some(idx) = (0..self.module.struct_handles().len()).position(|x| {
             let y = StructHandleIndex::new(x as u16);
-            self.module.struct_handle_at(y).module
-                == ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX)
+            self.module
+                .is_self_handle(self.module.struct_handle_at(y).module)
                 && !implemented_struct_handles.contains(&y)
         }) {
-            errors.push(VerificationError {
-                kind: IndexKind::StructHandle,
+            errors.push(VerificationError::new(
+                IndexKind::StructHandle,
                 idx,
-                err: VMStaticViolation::UnimplementedHandle,
-            })
+                VMStaticViolation::UnimplementedHandle,
+            ))
         }
         // Check that each function handle with module handle index IMPLEMENTED_MODULE_INDEX is
         // implemented.
@@ -242,29 +249,34 @@ impl<'a> DuplicationChecker<'a> {
             .collect();
         if let Some(idx) = (0..self.module.function_handles().len()).position(|x| {
             let y = FunctionHandleIndex::new(x as u16);
-            self.module.function_handle_at(y).module
-                == ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX)
+            self.module
+                .is_self_handle(self.module.function_handle_at(y).module)
                 && !implemented_function_handles.contains(&y)
         }) {
-            errors.push(VerificationError {
-                kind: IndexKind::FunctionHandle,
+            errors.push(VerificationError::new(
+                IndexKind::FunctionHandle,
                 idx,
-                err: VMStaticViolation::UnimplementedHandle,
-            })
+                VMStaticViolation::UnimplementedHandle,
+            ))
         }
 
         errors
     }
 
-    fn first_duplicate_element<T>(iter: T) -> Option<usize>
+    /// Returns the indices of the first entry of `iter` that is equal to an earlier one, and of
+    /// the earlier entry it collides with, as `(earlier, later)`.
+    fn first_duplicate_element<T>(iter: T) -> Option<(usize, usize)>
     where
         T: IntoIterator,
         T::Item: Eq + Hash,
     {
-        let mut uniq = HashSet::new();
+        let mut uniq = HashMap::new();
         for (i, x) in iter.into_iter().enumerate() {
-            if !uniq.insert(x) {
-                return Some(i);
+            match uniq.entry(x) {
+                Entry::Occupied(entry) => return Some((*entry.get(), i)),
+                Entry::Vacant(entry) => {
+                    entry.insert(i);
+                }
             }
         }
         None
@@ -0,0 +1,174 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a lenient mode of the verifier, which classifies arbitrary binaries by
+//! where they fail instead of accepting or rejecting them. It is meant for triaging fuzzer corpora
+//! and malformed data found on chain, so it never panics: every pass runs on its own, panics are
+//! caught and recorded, and a pass is only skipped if a pass it depends on didn't pass.
+//!
+//! Panics are still reported by the panic hook, which prints them to stderr by default.
+use crate::{
+    config::{ConfigurablePass, VerifierConfig},
+    verifier::verify_module_by_pass,
+};
+use std::{
+    any::Any,
+    collections::BTreeMap,
+    fmt,
+    panic::{self, AssertUnwindSafe},
+};
+use vm::{
+    errors::{has_errors, BinaryError, VerificationError},
+    file_format::{CompiledModule, CompiledModuleMut},
+};
+
+/// The outcome of a single pass in the lenient mode.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PassOutcome {
+    /// The pass ran and found no errors, though it may have found warnings.
+    Passed,
+    /// The pass ran and found errors, along with the warnings it found.
+    Failed(Vec<VerificationError>),
+    /// The pass panicked, with the panic message.
+    Panicked(String),
+    /// A pass this one depends on didn't pass, so it couldn't be run safely.
+    Skipped,
+}
+
+/// Where verifying a binary failed, if it did.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Classification {
+    /// The binary couldn't be deserialized.
+    Malformed(BinaryError),
+    /// The module refers to table entries that don't exist.
+    OutOfBounds(Vec<VerificationError>),
+    /// Deserializing or bounds checking the binary panicked, with the panic message.
+    Panicked(String),
+    /// The module was bounds checked, and every pass was given a chance to run.
+    Checked(BTreeMap<ConfigurablePass, PassOutcome>),
+}
+
+impl Classification {
+    /// Returns true if the binary made it through every pass, advisory ones included.
+    pub fn is_verified(&self) -> bool {
+        match self {
+            Classification::Checked(outcomes) => outcomes
+                .values()
+                .all(|outcome| *outcome == PassOutcome::Passed),
+            _ => false,
+        }
+    }
+
+    /// Returns the passes that failed or panicked, in the order they run.
+    pub fn failed_passes(&self) -> Vec<ConfigurablePass> {
+        match self {
+            Classification::Checked(outcomes) => outcomes
+                .iter()
+                .filter(|(_, outcome)| match outcome {
+                    PassOutcome::Failed(_) | PassOutcome::Panicked(_) => true,
+                    PassOutcome::Passed | PassOutcome::Skipped => false,
+                })
+                .map(|(pass, _)| *pass)
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Classification::Malformed(err) => write!(f, "malformed: {}", err),
+            Classification::OutOfBounds(errors) => {
+                write!(f, "out of bounds: {} errors", errors.len())
+            }
+            Classification::Panicked(message) => write!(f, "panicked while loading: {}", message),
+            Classification::Checked(outcomes) => {
+                let failed: Vec<_> = outcomes
+                    .iter()
+                    .filter_map(|(pass, outcome)| match outcome {
+                        PassOutcome::Failed(_) => Some(format!("{:?} failed", pass)),
+                        PassOutcome::Panicked(message) => {
+                            Some(format!("{:?} panicked: {}", pass, message))
+                        }
+                        PassOutcome::Passed | PassOutcome::Skipped => None,
+                    })
+                    .collect();
+                if failed.is_empty() {
+                    write!(f, "verified")
+                } else {
+                    write!(f, "{}", failed.join(", "))
+                }
+            }
+        }
+    }
+}
+
+/// Classifies `binary`, which doesn't need to be a well-formed module, by where verifying it
+/// fails. Never panics.
+pub fn classify_binary(binary: &[u8]) -> Classification {
+    let module = match catch_panic(|| CompiledModuleMut::deserialize_no_check_bounds(binary)) {
+        Ok(Ok(module)) => module,
+        Ok(Err(err)) => return Classification::Malformed(err),
+        Err(message) => return Classification::Panicked(message),
+    };
+    match catch_panic(|| module.freeze()) {
+        Ok(Ok(module)) => classify_module(&module),
+        Ok(Err(err)) => Classification::OutOfBounds(err.into_errors()),
+        Err(message) => Classification::Panicked(message),
+    }
+}
+
+/// Classifies `module` by the outcome of every pass of the verifier, advisory ones included.
+/// Never panics.
+///
+/// Unlike `verify_module_by_pass` with `collect_all` set, every pass runs on its own, so that a
+/// panic in one pass doesn't prevent the passes that don't depend on it from running.
+pub fn classify_module(module: &CompiledModule) -> Classification {
+    let mut outcomes = BTreeMap::new();
+    for pass in ConfigurablePass::all() {
+        let dependencies_passed = pass
+            .dependencies()
+            .iter()
+            .all(|dependency| outcomes.get(dependency) == Some(&PassOutcome::Passed));
+        let outcome = if dependencies_passed {
+            classify_pass(module, *pass)
+        } else {
+            PassOutcome::Skipped
+        };
+        outcomes.insert(*pass, outcome);
+    }
+    Classification::Checked(outcomes)
+}
+
+/// Runs `pass` and the passes it depends on, which are known to pass.
+fn classify_pass(module: &CompiledModule, pass: ConfigurablePass) -> PassOutcome {
+    let mut config = VerifierConfig::none();
+    for enabled in pass.dependencies().iter().chain(Some(&pass)) {
+        config.set_enabled(*enabled, true);
+    }
+    match catch_panic(|| verify_module_by_pass(module, &config).for_pass(pass)) {
+        Ok(errors) => {
+            if has_errors(&errors) {
+                PassOutcome::Failed(errors)
+            } else {
+                PassOutcome::Passed
+            }
+        }
+        Err(message) => PassOutcome::Panicked(message),
+    }
+}
+
+fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| panic_message(&*payload))
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
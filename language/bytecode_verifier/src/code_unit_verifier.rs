@@ -5,76 +5,288 @@
 //! The overall verification is split between stack_usage_verifier.rs and
 //! abstract_interpreter.rs. CodeUnitVerifier simply orchestrates calls into these two files.
 use crate::control_flow_graph::VMControlFlowGraph;
+use std::time::Instant;
 use vm::{
     access::ModuleAccess,
     errors::{VMStaticViolation, VerificationError},
-    file_format::{CompiledModule, FunctionDefinition},
-    IndexKind,
+    file_format::{
+        CodeOffset, CompiledModule, FunctionDefinition, FunctionDefinitionIndex, TableIndex,
+    },
 };
 
 use crate::{
-    acquires_list_verifier::AcquiresVerifier, stack_usage_verifier::StackUsageVerifier,
+    acquires_list_verifier::AcquiresVerifier,
+    config::{ConfigurablePass, VerifierConfig},
+    metrics::VerificationMetrics,
+    stack_height_verifier::StackHeightVerifier,
+    stack_usage_verifier::StackUsageVerifier,
+    trace::{self, Predecessors},
     type_memory_safety::TypeAndMemorySafetyAnalysis,
 };
 
 pub struct CodeUnitVerifier<'a> {
     module: &'a CompiledModule,
+    config: &'a VerifierConfig,
+    metrics: Option<&'a mut VerificationMetrics>,
+    /// The blocks through which the type safety analysis reached every block of the last function
+    /// it analyzed, for tracing its errors.
+    analysis_predecessors: Predecessors,
 }
 
 impl<'a> CodeUnitVerifier<'a> {
     pub fn verify(module: &'a CompiledModule) -> Vec<VerificationError> {
-        let verifier = Self { module };
-        verifier
-            .module
-            .function_defs()
-            .iter()
-            .enumerate()
-            .map(move |(idx, function_definition)| {
-                verifier
-                    .verify_function(function_definition)
-                    .into_iter()
-                    .map(move |err| VerificationError {
-                        kind: IndexKind::FunctionDefinition,
-                        idx,
-                        err,
-                    })
-            })
-            .flatten()
+        Self::verify_with_config(module, &VerifierConfig::all())
+    }
+
+    /// Verifies the code units of `module`, only running the passes that `config` runs.
+    pub fn verify_with_config(
+        module: &CompiledModule,
+        config: &VerifierConfig,
+    ) -> Vec<VerificationError> {
+        Self::verify_by_pass(module, config)
+            .into_iter()
+            .map(|(_, err)| err)
             .collect()
     }
 
-    fn verify_function(&self, function_definition: &FunctionDefinition) -> Vec<VMStaticViolation> {
+    /// Verifies the code units of `module` the same way as `verify_with_config`, also returning
+    /// the pass that found each error.
+    pub fn verify_by_pass(
+        module: &CompiledModule,
+        config: &VerifierConfig,
+    ) -> Vec<(ConfigurablePass, VerificationError)> {
+        Self::verify_functions_by_pass(module, config, &all_function_definitions(module))
+    }
+
+    /// Verifies the code units of the function definitions at `function_definitions` only, in
+    /// that order, the same way as `verify_by_pass`.
+    pub fn verify_functions_by_pass(
+        module: &CompiledModule,
+        config: &VerifierConfig,
+        function_definitions: &[FunctionDefinitionIndex],
+    ) -> Vec<(ConfigurablePass, VerificationError)> {
+        CodeUnitVerifier::verify_functions_impl(module, config, function_definitions, None)
+    }
+
+    /// Verifies the code units of `module` the same way as `verify_by_pass`, recording the cost of
+    /// every pass and function in `metrics`.
+    pub(crate) fn verify_by_pass_with_metrics(
+        module: &CompiledModule,
+        config: &VerifierConfig,
+        metrics: &mut VerificationMetrics,
+    ) -> Vec<(ConfigurablePass, VerificationError)> {
+        let function_definitions = all_function_definitions(module);
+        CodeUnitVerifier::verify_functions_impl(
+            module,
+            config,
+            &function_definitions,
+            Some(metrics),
+        )
+    }
+
+    fn verify_functions_impl(
+        module: &'a CompiledModule,
+        config: &'a VerifierConfig,
+        function_definitions: &[FunctionDefinitionIndex],
+        metrics: Option<&'a mut VerificationMetrics>,
+    ) -> Vec<(ConfigurablePass, VerificationError)> {
+        let mut verifier = CodeUnitVerifier {
+            module,
+            config,
+            metrics,
+            analysis_predecessors: Predecessors::new(),
+        };
+        let mut errors = vec![];
+        for idx in function_definitions {
+            let function_definition = module.function_def_at(*idx);
+            let start = Instant::now();
+            let function_errors = verifier.verify_function(function_definition);
+            if let Some(metrics) = &mut verifier.metrics {
+                if !function_definition.is_native() {
+                    metrics.record_function(*idx, start.elapsed(), function_errors.len());
+                }
+            }
+            if function_errors.is_empty() {
+                continue;
+            }
+            let budget_exceeded = function_errors
+                .iter()
+                .any(|(_, err)| *err == VMStaticViolation::VerificationBudgetExceeded);
+            let traces = if config.traces {
+                verifier.traces(function_definition, &function_errors)
+            } else {
+                vec![vec![]; function_errors.len()]
+            };
+            errors.extend(
+                function_errors
+                    .into_iter()
+                    .zip(traces)
+                    .map(|((pass, err), trace)| {
+                        let err = VerificationError::in_function(*idx, err).with_trace(trace);
+                        (pass, err)
+                    }),
+            );
+            if config.stop_at_first_function || budget_exceeded {
+                break;
+            }
+        }
+        errors
+    }
+
+    fn verify_function(
+        &mut self,
+        function_definition: &FunctionDefinition,
+    ) -> Vec<(ConfigurablePass, VMStaticViolation)> {
         if function_definition.is_native() {
             return vec![];
         }
 
         let code = &function_definition.code.code;
 
-        // Check to make sure that the bytecode vector ends with a branching instruction.
-        if let Some(bytecode) = code.last() {
-            if !bytecode.is_unconditional_branch() {
-                return vec![VMStaticViolation::InvalidFallThrough];
+        if self.config.runs(ConfigurablePass::ControlFlow) {
+            // Check to make sure that the bytecode vector ends with a branching instruction.
+            let mut errors = self.run_pass(ConfigurablePass::ControlFlow, |_| {
+                let falls_through = code
+                    .last()
+                    .map_or(true, |bytecode| !bytecode.is_unconditional_branch());
+                if falls_through {
+                    vec![VMStaticViolation::InvalidFallThrough]
+                } else {
+                    vec![]
+                }
+            });
+            if !errors.is_empty() {
+                // Every other pass but the acquires verifier relies on this one.
+                if self.config.collect_all {
+                    errors.extend(self.verify_acquires(function_definition));
+                }
+                return errors;
             }
-        } else {
-            return vec![VMStaticViolation::InvalidFallThrough];
         }
 
+        if !self.config.runs(ConfigurablePass::StackUsage) {
+            // The control flow graph is only needed by passes that depend on stack usage.
+            return self.verify_acquires(function_definition);
+        }
         self.verify_function_inner(function_definition, &VMControlFlowGraph::new(code))
     }
 
     fn verify_function_inner(
-        &self,
+        &mut self,
         function_definition: &FunctionDefinition,
         cfg: &VMControlFlowGraph,
-    ) -> Vec<VMStaticViolation> {
-        let errors = StackUsageVerifier::verify(self.module, function_definition, cfg);
-        if !errors.is_empty() {
-            return errors;
+    ) -> Vec<(ConfigurablePass, VMStaticViolation)> {
+        let config = self.config;
+        let mut errors = self.run_pass(ConfigurablePass::StackUsage, |module| {
+            StackUsageVerifier::verify(module, function_definition, cfg)
+        });
+        let stack_usage_verified = errors.is_empty();
+        if stack_usage_verified && config.runs(ConfigurablePass::StackHeight) {
+            errors.extend(self.run_pass(ConfigurablePass::StackHeight, |module| {
+                StackHeightVerifier::verify(
+                    module,
+                    function_definition,
+                    cfg,
+                    config.max_stack_height,
+                )
+            }));
         }
-        let errors = AcquiresVerifier::verify(self.module, function_definition);
-        if !errors.is_empty() {
-            return errors;
+        if errors.is_empty() || self.config.collect_all {
+            errors.extend(self.verify_acquires(function_definition));
         }
-        TypeAndMemorySafetyAnalysis::verify(self.module, function_definition, cfg)
+        // Type safety relies on stack usage, but not on stack height or acquires.
+        let run_type_safety = stack_usage_verified
+            && (errors.is_empty() || self.config.collect_all)
+            && config.runs(ConfigurablePass::TypeSafety);
+        if run_type_safety {
+            let mut analysis_predecessors = Predecessors::new();
+            errors.extend(self.run_pass(ConfigurablePass::TypeSafety, |module| {
+                let limits = &config.limits;
+                let code_len = function_definition.code.code.len();
+                let locals_len = module
+                    .locals_signature_at(function_definition.code.locals)
+                    .0
+                    .len();
+                if limits.function_complexity_ok(code_len, locals_len) {
+                    let (errors, inv_map) = TypeAndMemorySafetyAnalysis::analyze(
+                        module,
+                        function_definition,
+                        cfg,
+                        limits,
+                    );
+                    if config.traces {
+                        analysis_predecessors = trace::analysis_predecessors(&inv_map);
+                    }
+                    errors
+                } else {
+                    vec![VMStaticViolation::VerificationBudgetExceeded]
+                }
+            }));
+            self.analysis_predecessors = analysis_predecessors;
+        }
+        errors
     }
+
+    /// Returns the trace of every error in `errors`, in the same order. Errors of the type safety
+    /// pass are traced along the path the analysis took, and other errors along a shortest path.
+    fn traces(
+        &self,
+        function_definition: &FunctionDefinition,
+        errors: &[(ConfigurablePass, VMStaticViolation)],
+    ) -> Vec<Vec<CodeOffset>> {
+        let cfg = VMControlFlowGraph::new(&function_definition.code.code);
+        let shortest_path_predecessors = trace::shortest_path_predecessors(&cfg);
+        errors
+            .iter()
+            .map(|(pass, err)| {
+                let predecessors = match pass {
+                    ConfigurablePass::TypeSafety => &self.analysis_predecessors,
+                    _ => &shortest_path_predecessors,
+                };
+                err.code_offset().map_or_else(Vec::new, |offset| {
+                    trace::trace_to(&cfg, predecessors, offset)
+                })
+            })
+            .collect()
+    }
+
+    fn verify_acquires(
+        &mut self,
+        function_definition: &FunctionDefinition,
+    ) -> Vec<(ConfigurablePass, VMStaticViolation)> {
+        if self.config.runs(ConfigurablePass::Acquires) {
+            self.run_pass(ConfigurablePass::Acquires, |module| {
+                AcquiresVerifier::verify(module, function_definition)
+            })
+        } else {
+            vec![]
+        }
+    }
+
+    /// Runs `pass` with `verify`, recording its cost if metrics are being recorded.
+    fn run_pass(
+        &mut self,
+        pass: ConfigurablePass,
+        verify: impl FnOnce(&CompiledModule) -> Vec<VMStaticViolation>,
+    ) -> Vec<(ConfigurablePass, VMStaticViolation)> {
+        let start = Instant::now();
+        let errors = verify(self.module);
+        if let Some(metrics) = &mut self.metrics {
+            metrics.record_pass(pass, start.elapsed(), errors.len());
+        }
+        tag(pass, errors)
+    }
+}
+
+fn all_function_definitions(module: &CompiledModule) -> Vec<FunctionDefinitionIndex> {
+    (0..module.function_defs().len())
+        .map(|idx| FunctionDefinitionIndex::new(idx as TableIndex))
+        .collect()
+}
+
+fn tag(
+    pass: ConfigurablePass,
+    errors: Vec<VMStaticViolation>,
+) -> Vec<(ConfigurablePass, VMStaticViolation)> {
+    errors.into_iter().map(|err| (pass, err)).collect()
 }
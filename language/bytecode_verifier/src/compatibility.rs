@@ -0,0 +1,179 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a checker for verifying that a module can replace an earlier version
+//! of itself that is already published:
+//! - layout compatibility: every struct keeps its kind, type formals and fields, since values of it
+//!   may be stored in global storage, either as resources or inside resources;
+//! - API compatibility: every public function stays public and keeps its signature, since other
+//!   modules may call it. Structs, functions and non-public functions may be added freely.
+//!
+//! Modules are compared in their normalized form (see `vm::normalize`), so reordering the tables
+//! of a module is not a change. The file format has no friend declarations, so there is no friend
+//! set to keep monotonic: every module may call every public function.
+use std::fmt;
+use types::language_storage::ModuleId;
+use vm::{
+    access::ModuleAccess,
+    normalize::{NormalizedFunction, NormalizedModule},
+};
+
+/// A reason the new version of a module can't replace the old one.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Incompatibility {
+    /// The new module is not published under the same address and name.
+    ModuleIdChanged { old: ModuleId, new: ModuleId },
+    /// A struct of the old module was removed.
+    StructRemoved(String),
+    /// A struct of the old module changed its kind, type formals or fields.
+    StructLayoutChanged(String),
+    /// A public function of the old module was removed, or made non-public.
+    PublicFunctionRemoved(String),
+    /// A public function of the old module changed its type formals, arguments or return types.
+    PublicFunctionSignatureChanged(String),
+}
+
+impl Incompatibility {
+    /// Returns true if this breaks the layout of values that may be in global storage.
+    pub fn breaks_layout(&self) -> bool {
+        match self {
+            Incompatibility::ModuleIdChanged { .. }
+            | Incompatibility::StructRemoved(_)
+            | Incompatibility::StructLayoutChanged(_) => true,
+            Incompatibility::PublicFunctionRemoved(_)
+            | Incompatibility::PublicFunctionSignatureChanged(_) => false,
+        }
+    }
+
+    /// Returns true if this breaks modules calling the public functions of the module.
+    pub fn breaks_api(&self) -> bool {
+        match self {
+            Incompatibility::ModuleIdChanged { .. }
+            | Incompatibility::PublicFunctionRemoved(_)
+            | Incompatibility::PublicFunctionSignatureChanged(_) => true,
+            Incompatibility::StructRemoved(_) | Incompatibility::StructLayoutChanged(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Incompatibility::ModuleIdChanged { old, new } => {
+                write!(f, "module {:?} is replaced by module {:?}", old, new)
+            }
+            Incompatibility::StructRemoved(name) => write!(f, "struct {} is removed", name),
+            Incompatibility::StructLayoutChanged(name) => {
+                write!(f, "layout of struct {} is changed", name)
+            }
+            Incompatibility::PublicFunctionRemoved(name) => {
+                write!(f, "public function {} is removed", name)
+            }
+            Incompatibility::PublicFunctionSignatureChanged(name) => {
+                write!(f, "signature of public function {} is changed", name)
+            }
+        }
+    }
+}
+
+/// The outcome of comparing a module against the version of it it would replace.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompatibilityReport {
+    /// Every incompatibility, with the module id first, then structs and functions by name.
+    pub incompatibilities: Vec<Incompatibility>,
+}
+
+impl CompatibilityReport {
+    /// Returns true if the new module can replace the old one.
+    pub fn is_compatible(&self) -> bool {
+        self.incompatibilities.is_empty()
+    }
+
+    /// Returns true if values published by the old module can be read by the new one.
+    pub fn is_layout_compatible(&self) -> bool {
+        !self
+            .incompatibilities
+            .iter()
+            .any(Incompatibility::breaks_layout)
+    }
+
+    /// Returns true if modules calling the old module can call the new one.
+    pub fn is_api_compatible(&self) -> bool {
+        !self
+            .incompatibilities
+            .iter()
+            .any(Incompatibility::breaks_api)
+    }
+}
+
+impl fmt::Display for CompatibilityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_compatible() {
+            return write!(f, "compatible");
+        }
+        let incompatibilities: Vec<_> = self
+            .incompatibilities
+            .iter()
+            .map(|incompatibility| incompatibility.to_string())
+            .collect();
+        write!(f, "{}", incompatibilities.join(", "))
+    }
+}
+
+pub struct CompatibilityChecker {
+    old: NormalizedModule,
+    new: NormalizedModule,
+}
+
+impl CompatibilityChecker {
+    /// Both modules should have passed the verifier, so that definitions are identified by their
+    /// names.
+    pub fn new(old: &impl ModuleAccess, new: &impl ModuleAccess) -> Self {
+        Self {
+            old: NormalizedModule::new(old),
+            new: NormalizedModule::new(new),
+        }
+    }
+
+    pub fn verify(self) -> CompatibilityReport {
+        let mut incompatibilities = vec![];
+        if self.old.id != self.new.id {
+            incompatibilities.push(Incompatibility::ModuleIdChanged {
+                old: self.old.id.clone(),
+                new: self.new.id.clone(),
+            });
+        }
+        for (name, old_struct) in &self.old.structs {
+            match self.new.structs.get(name) {
+                None => incompatibilities.push(Incompatibility::StructRemoved(name.clone())),
+                Some(new_struct) if new_struct != old_struct => {
+                    incompatibilities.push(Incompatibility::StructLayoutChanged(name.clone()))
+                }
+                Some(_) => (),
+            }
+        }
+        for (name, old_function) in &self.old.functions {
+            if !old_function.is_public {
+                continue;
+            }
+            match self.new.functions.get(name) {
+                Some(new_function) if new_function.is_public => {
+                    if !same_signature(old_function, new_function) {
+                        incompatibilities.push(Incompatibility::PublicFunctionSignatureChanged(
+                            name.clone(),
+                        ))
+                    }
+                }
+                _ => incompatibilities.push(Incompatibility::PublicFunctionRemoved(name.clone())),
+            }
+        }
+        CompatibilityReport { incompatibilities }
+    }
+}
+
+/// Returns true if calls to `old` type check as calls to `new`.
+fn same_signature(old: &NormalizedFunction, new: &NormalizedFunction) -> bool {
+    old.type_formals == new.type_formals
+        && old.arg_types == new.arg_types
+        && old.return_types == new.return_types
+}
@@ -0,0 +1,116 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module computes complexity metrics for the functions of a module, so that reviewers can
+//! triage which functions deserve a closer look.
+//!
+//! The metrics only depend on the code of the functions:
+//! - the cyclomatic complexity is the number of conditional branches plus one, i.e. the number of
+//!   independent paths through the function;
+//! - the basic blocks include unreachable ones;
+//! - the loop depth is the number of natural loops nested around a block (see `LoopNest`);
+//! - the locals pressure is the largest number of locals live at the same time (see `Liveness`).
+use crate::{
+    control_flow_graph::{ControlFlowGraph, VMControlFlowGraph},
+    dataflow::Liveness,
+    dominators::{DominatorTree, LoopNest},
+};
+use std::collections::BTreeMap;
+use vm::{
+    access::ModuleAccess,
+    file_format::{Bytecode, CodeOffset, CompiledModule, FunctionDefinitionIndex},
+};
+
+/// The complexity metrics of a single function.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FunctionComplexity {
+    /// The number of conditional branches plus one.
+    pub cyclomatic_complexity: usize,
+    /// The number of basic blocks, including unreachable ones.
+    pub basic_blocks: usize,
+    /// The largest number of natural loops nested around a block. Functions without loops have
+    /// depth 0.
+    pub max_loop_depth: usize,
+    /// The number of locals, including arguments.
+    pub locals: usize,
+    /// The largest number of locals live at the same time.
+    pub max_live_locals: usize,
+}
+
+impl FunctionComplexity {
+    /// Computes the metrics of a function with `locals` locals and `code`, which must have passed
+    /// the control flow checks of the verifier.
+    pub fn new(code: &[Bytecode], locals: usize) -> Self {
+        let conditional_branches = code
+            .iter()
+            .filter(|bytecode| match bytecode {
+                Bytecode::BrTrue(_) | Bytecode::BrFalse(_) => true,
+                _ => false,
+            })
+            .count();
+        let cfg = VMControlFlowGraph::new(code);
+        let loop_nest = LoopNest::new(&cfg, &DominatorTree::new(&cfg));
+        let max_loop_depth = cfg
+            .blocks()
+            .into_iter()
+            .map(|block_id| loop_nest.depth(block_id))
+            .max()
+            .unwrap_or(0);
+        let liveness = Liveness::new(code, &cfg);
+        let max_live_locals = (0..code.len())
+            .map(|offset| liveness.live_before(offset as CodeOffset).len())
+            .max()
+            .unwrap_or(0);
+        Self {
+            cyclomatic_complexity: conditional_branches + 1,
+            basic_blocks: cfg.num_blocks() as usize,
+            max_loop_depth,
+            locals,
+            max_live_locals,
+        }
+    }
+}
+
+/// The complexity metrics of every function of a module.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ComplexityReport {
+    /// The metrics of every function definition. Native functions have no code, and are left out.
+    pub functions: BTreeMap<FunctionDefinitionIndex, FunctionComplexity>,
+}
+
+impl ComplexityReport {
+    /// Computes the metrics of every function of `module`, whose code must have passed the control
+    /// flow checks of the verifier.
+    pub fn new(module: &CompiledModule) -> Self {
+        let functions = module
+            .function_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| !function_def.is_native())
+            .map(|(idx, function_def)| {
+                let locals = module.locals_signature_at(function_def.code.locals).len();
+                (
+                    FunctionDefinitionIndex::new(idx as u16),
+                    FunctionComplexity::new(&function_def.code.code, locals),
+                )
+            })
+            .collect();
+        Self { functions }
+    }
+
+    /// Returns the `n` function definitions with the highest cyclomatic complexity, most complex
+    /// first. Ties are broken by the loop depth.
+    pub fn most_complex(&self, n: usize) -> Vec<(FunctionDefinitionIndex, &FunctionComplexity)> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(idx, complexity)| (*idx, complexity))
+            .collect();
+        functions.sort_by(|(_, a), (_, b)| {
+            (b.cyclomatic_complexity, b.max_loop_depth)
+                .cmp(&(a.cyclomatic_complexity, a.max_loop_depth))
+        });
+        functions.truncate(n);
+        functions
+    }
+}
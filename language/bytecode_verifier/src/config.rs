@@ -0,0 +1,397 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines the configuration used to run only some of the passes of the bytecode
+//! verifier, for embedders like simulators and tooling that don't need full verification.
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+/// Selects which passes of the bytecode verifier run on a module, and how.
+///
+/// Later passes rely on the guarantees of earlier ones to run safely, so a pass only runs if the
+/// passes it depends on are enabled as well (see `VerifierConfig::runs`). The bounds checker isn't
+/// part of this configuration: it runs whenever a `CompiledModuleMut` is frozen, and nothing else
+/// can be done with a module that fails it.
+///
+/// A module that was only partially verified must never be treated as a `VerifiedModule`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VerifierConfig {
+    /// Checks that tables don't contain duplicate entries, and that definitions are consistent
+    /// with their handles.
+    pub duplication: bool,
+    /// Checks that the module stays within `max_structure`. This rejects modules that used to
+    /// verify, so `all` doesn't enable it: turning it on for validators is a consensus change.
+    pub structural_limits: bool,
+    /// Checks that signatures are well formed.
+    pub signature: bool,
+    /// Checks that structs containing resources are resources themselves.
+    pub resources: bool,
+    /// Checks that struct definitions don't contain themselves.
+    pub recursive_structs: bool,
+    /// Checks that code units end with an unconditional branch.
+    pub control_flow: bool,
+    /// Checks that basic blocks don't underflow the stack and leave it balanced.
+    pub stack_usage: bool,
+    /// Checks that the operand stack never grows higher than `max_stack_height` within a function.
+    /// This rejects modules that used to verify, so `all` doesn't enable it.
+    pub stack_height: bool,
+    /// Checks the acquires annotations of function definitions.
+    pub acquires: bool,
+    /// Checks type safety and reference safety within code units.
+    pub type_safety: bool,
+    /// Flags code that can never be executed, with warnings. This pass is advisory, for tools like
+    /// compilers and auditors, so `all` doesn't enable it.
+    pub unreachable_code: bool,
+    /// Rejects code units with irreducible control flow, i.e. with cycles that can be entered at
+    /// more than one block. The VM doesn't need reducible control flow, but analyses built on the
+    /// loop structure of code units do, so `all` doesn't enable it.
+    pub reducibility: bool,
+    /// Checks every instruction accessing global storage against the struct it names and the
+    /// acquires list of its function. This overlaps with the acquires and type safety passes, but
+    /// reports every inconsistent instruction, so `all` doesn't enable it.
+    pub global_storage: bool,
+    /// Stops verifying code units after the first function definition that has errors, instead
+    /// of reporting the errors of every function definition.
+    pub stop_at_first_function: bool,
+    /// Keeps running passes after one of them reports errors, as long as none of the passes they
+    /// depend on did. By default verification stops at the first pass that reports errors.
+    pub collect_all: bool,
+    /// Attaches to every error found in a code unit by the stack usage, stack height, acquires and
+    /// type safety passes the path of code offsets leading to it (see `VerificationError::trace`).
+    pub traces: bool,
+    /// The bound enforced by the stack height pass.
+    pub max_stack_height: StackHeightLimit,
+    /// The bounds enforced by the structural limits pass.
+    pub max_structure: StructuralLimits,
+    /// Bounds the work the verifier does on a single module. `all` sets no bound, and callers opt
+    /// into one explicitly, e.g. with `VerifierLimits::recommended`.
+    pub limits: VerifierLimits,
+}
+
+/// The bound the stack height pass enforces on the operand stack height of every function.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StackHeightLimit {
+    /// No bound.
+    Unlimited,
+    /// The same bound for every function.
+    Fixed(u16),
+    /// The `max_stack_size` each function declares in its code unit.
+    Declared,
+}
+
+impl Default for StackHeightLimit {
+    fn default() -> Self {
+        StackHeightLimit::Unlimited
+    }
+}
+
+/// Bounds on the shape of a module, enforced by the structural limits pass. Unlike the limits of
+/// the deserializer, which bound the size of tables in the binary format, these bound what the
+/// module means: how its types nest and how large its definitions are.
+///
+/// Every bound has a violation of its own. A bound of `None` means there is no bound.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct StructuralLimits {
+    /// The maximum number of struct instantiations a signature token may be nested in, with
+    /// `u64` and `&u64` at depth 0 and `S<u64>` at depth 1.
+    pub max_type_instantiation_depth: Option<usize>,
+    /// The maximum number of type formals of a struct handle or function signature.
+    pub max_type_parameters: Option<usize>,
+    /// The maximum number of basic blocks in the code unit of a function definition.
+    pub max_basic_blocks: Option<usize>,
+    /// The maximum number of fields of a struct definition.
+    pub max_struct_fields: Option<usize>,
+}
+
+/// Bounds on the work the verifier is willing to do, so that modules crafted to make verification
+/// blow up can't be used to stall validators.
+///
+/// When a limit is exceeded, verification aborts with a single
+/// `VMStaticViolation::VerificationBudgetExceeded` error, even if `collect_all` is set. A limit of
+/// `None` means there is no bound.
+///
+/// There is deliberately no default: bounding the work of the verifier rejects modules that used
+/// to verify, so turning limits on for validators is a consensus change.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct VerifierLimits {
+    /// The maximum number of instructions times the number of locals of a code unit, which bounds
+    /// the size of the abstract states of the type safety analysis.
+    pub max_function_complexity: Option<u64>,
+    /// The maximum nesting depth of a signature token, with `u64` having depth 1.
+    pub max_signature_depth: Option<usize>,
+    /// The maximum number of basic blocks the type safety analysis may analyze in a single code
+    /// unit before reaching a fixed point, counting every time a block is analyzed again.
+    pub max_fixpoint_iterations: Option<u64>,
+}
+
+/// A pass that `VerifierConfig` can enable or disable.
+#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ConfigurablePass {
+    Duplication,
+    StructuralLimits,
+    Signature,
+    Resources,
+    RecursiveStructs,
+    ControlFlow,
+    StackUsage,
+    StackHeight,
+    Acquires,
+    TypeSafety,
+    UnreachableCode,
+    Reducibility,
+    GlobalStorage,
+}
+
+impl ConfigurablePass {
+    /// Returns every pass, in the order the verifier runs them.
+    pub fn all() -> &'static [ConfigurablePass] {
+        use ConfigurablePass::*;
+
+        &[
+            Duplication,
+            StructuralLimits,
+            Signature,
+            Resources,
+            RecursiveStructs,
+            ControlFlow,
+            StackUsage,
+            StackHeight,
+            Acquires,
+            TypeSafety,
+            UnreachableCode,
+            Reducibility,
+            GlobalStorage,
+        ]
+    }
+
+    /// The passes that must have run for this one to be run safely.
+    pub fn dependencies(self) -> &'static [ConfigurablePass] {
+        use ConfigurablePass::*;
+
+        match self {
+            Duplication | StructuralLimits => &[],
+            Signature | Resources | RecursiveStructs => &[Duplication],
+            ControlFlow | Acquires | GlobalStorage => &[Duplication, Signature, RecursiveStructs],
+            UnreachableCode | Reducibility => {
+                &[Duplication, Signature, RecursiveStructs, ControlFlow]
+            }
+            StackUsage => &[Duplication, Signature, RecursiveStructs, ControlFlow],
+            StackHeight => &[
+                Duplication,
+                Signature,
+                RecursiveStructs,
+                ControlFlow,
+                StackUsage,
+            ],
+            TypeSafety => &[
+                Duplication,
+                Signature,
+                RecursiveStructs,
+                ControlFlow,
+                StackUsage,
+            ],
+        }
+    }
+}
+
+impl VerifierConfig {
+    /// Returns a configuration that runs every pass that can fail verification, the same as
+    /// `VerifiedModule::new`.
+    pub fn all() -> Self {
+        Self {
+            duplication: true,
+            structural_limits: false,
+            signature: true,
+            resources: true,
+            recursive_structs: true,
+            control_flow: true,
+            stack_usage: true,
+            stack_height: false,
+            acquires: true,
+            type_safety: true,
+            unreachable_code: false,
+            reducibility: false,
+            global_storage: false,
+            stop_at_first_function: false,
+            collect_all: false,
+            traces: false,
+            max_stack_height: StackHeightLimit::default(),
+            max_structure: StructuralLimits::default(),
+            limits: VerifierLimits::unlimited(),
+        }
+    }
+
+    /// Returns `all`, with the advisory passes enabled as well: unreachable code, reducibility and
+    /// global storage. This is what tools reporting on modules to people, like the command line
+    /// verifier and the bindings for other languages, run.
+    pub fn advisory() -> Self {
+        Self {
+            unreachable_code: true,
+            reducibility: true,
+            global_storage: true,
+            ..Self::all()
+        }
+    }
+
+    /// Returns a configuration that runs no pass. Use this as a starting point to enable only a
+    /// few passes.
+    pub fn none() -> Self {
+        Self {
+            duplication: false,
+            structural_limits: false,
+            signature: false,
+            resources: false,
+            recursive_structs: false,
+            control_flow: false,
+            stack_usage: false,
+            stack_height: false,
+            acquires: false,
+            type_safety: false,
+            unreachable_code: false,
+            reducibility: false,
+            global_storage: false,
+            stop_at_first_function: false,
+            collect_all: false,
+            traces: false,
+            max_stack_height: StackHeightLimit::default(),
+            max_structure: StructuralLimits::default(),
+            limits: VerifierLimits::unlimited(),
+        }
+    }
+
+    /// Returns whether `pass` was enabled, regardless of its dependencies.
+    pub fn is_enabled(&self, pass: ConfigurablePass) -> bool {
+        use ConfigurablePass::*;
+
+        match pass {
+            Duplication => self.duplication,
+            StructuralLimits => self.structural_limits,
+            Signature => self.signature,
+            Resources => self.resources,
+            RecursiveStructs => self.recursive_structs,
+            ControlFlow => self.control_flow,
+            StackUsage => self.stack_usage,
+            StackHeight => self.stack_height,
+            Acquires => self.acquires,
+            TypeSafety => self.type_safety,
+            UnreachableCode => self.unreachable_code,
+            Reducibility => self.reducibility,
+            GlobalStorage => self.global_storage,
+        }
+    }
+
+    /// Enables or disables `pass`, leaving its dependencies alone.
+    pub fn set_enabled(&mut self, pass: ConfigurablePass, enabled: bool) {
+        use ConfigurablePass::*;
+
+        let flag = match pass {
+            Duplication => &mut self.duplication,
+            StructuralLimits => &mut self.structural_limits,
+            Signature => &mut self.signature,
+            Resources => &mut self.resources,
+            RecursiveStructs => &mut self.recursive_structs,
+            ControlFlow => &mut self.control_flow,
+            StackUsage => &mut self.stack_usage,
+            StackHeight => &mut self.stack_height,
+            Acquires => &mut self.acquires,
+            TypeSafety => &mut self.type_safety,
+            UnreachableCode => &mut self.unreachable_code,
+            Reducibility => &mut self.reducibility,
+            GlobalStorage => &mut self.global_storage,
+        };
+        *flag = enabled;
+    }
+
+    /// Returns whether `pass` actually runs, i.e. whether it and every pass it depends on were
+    /// enabled.
+    pub fn runs(&self, pass: ConfigurablePass) -> bool {
+        self.is_enabled(pass)
+            && pass
+                .dependencies()
+                .iter()
+                .all(|dependency| self.is_enabled(*dependency))
+    }
+
+    /// Returns a fingerprint of this configuration, which changes whenever the configuration
+    /// changes in a way that could change verification outcomes.
+    ///
+    /// Fingerprints are only meant to be compared within a single build of the verifier, and must
+    /// not be persisted.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Default for VerifierConfig {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl StructuralLimits {
+    /// Returns limits that accept every module.
+    pub fn unlimited() -> Self {
+        Self {
+            max_type_instantiation_depth: None,
+            max_type_parameters: None,
+            max_basic_blocks: None,
+            max_struct_fields: None,
+        }
+    }
+}
+
+/// The limits enforced when the structural limits pass is enabled. The standard library stays well
+/// within them.
+impl Default for StructuralLimits {
+    fn default() -> Self {
+        Self {
+            max_type_instantiation_depth: Some(32),
+            max_type_parameters: Some(32),
+            max_basic_blocks: Some(1024),
+            max_struct_fields: Some(255),
+        }
+    }
+}
+
+impl VerifierLimits {
+    /// Returns limits that never abort verification.
+    pub fn unlimited() -> Self {
+        Self {
+            max_function_complexity: None,
+            max_signature_depth: None,
+            max_fixpoint_iterations: None,
+        }
+    }
+
+    /// Returns true if a code unit with `code_len` instructions and `locals_len` locals is within
+    /// `max_function_complexity`.
+    pub fn function_complexity_ok(&self, code_len: usize, locals_len: usize) -> bool {
+        self.max_function_complexity.map_or(true, |max| {
+            // Functions without locals still have to be analyzed.
+            (code_len as u64).saturating_mul(locals_len.max(1) as u64) <= max
+        })
+    }
+
+    /// Returns limits far above what any reasonable module needs, which the standard library
+    /// stays well within:
+    ///
+    /// * A code unit has at most 65535 instructions and 256 locals, so its complexity is at most
+    ///   about `1 << 24`. `1 << 22` still admits 16384 instructions with every local used, or every
+    ///   instruction with 64 locals.
+    /// * Signatures written by hand or generated by the compiler are a few levels deep. 256 only
+    ///   stops tokens crafted to be deep.
+    /// * Each basic block is analyzed again only when the state at its entry grows, which takes a
+    ///   handful of iterations in practice. `1 << 20` lets each of the 65535 blocks a code unit can
+    ///   have at most be analyzed 16 times.
+    pub fn recommended() -> Self {
+        Self {
+            max_function_complexity: Some(1 << 22),
+            max_signature_depth: Some(256),
+            max_fixpoint_iterations: Some(1 << 20),
+        }
+    }
+}
@@ -3,7 +3,7 @@
 
 //! This module defines the control-flow graph uses for bytecode verification.
 use std::collections::{BTreeMap, BTreeSet};
-use vm::file_format::{Bytecode, CodeOffset};
+use vm::file_format::{Bytecode, CheckedCodeOffset, CodeOffset};
 
 // BTree/Hash agnostic type wrappers
 type Map<K, V> = BTreeMap<K, V>;
@@ -62,36 +62,54 @@ impl BasicBlock {
 
 const ENTRY_BLOCK_ID: BlockId = 0;
 
+const CODE_TOO_LONG: &str = "the bounds checker rejects code units a code offset can't address";
+
+/// Returns the offset of the instruction at `idx`.
+fn code_offset(idx: usize) -> CheckedCodeOffset {
+    CheckedCodeOffset::from_index(idx).expect(CODE_TOO_LONG)
+}
+
+/// Returns the offset just past the last instruction of `code`.
+fn code_end(code: &[Bytecode]) -> CheckedCodeOffset {
+    code_offset(code.len())
+}
+
+/// Returns the offset of the instruction after the one at `pc`.
+fn next_offset(pc: CheckedCodeOffset) -> CheckedCodeOffset {
+    pc.next().expect(CODE_TOO_LONG)
+}
+
 impl VMControlFlowGraph {
     pub fn new(code: &[Bytecode]) -> Self {
         // First go through and collect block ids, i.e., offsets that begin basic blocks.
         // Need to do this first in order to handle backwards edges.
         let mut block_ids = Set::new();
         block_ids.insert(ENTRY_BLOCK_ID);
+        let end = code_end(code);
         for pc in 0..code.len() {
-            VMControlFlowGraph::record_block_ids(pc as CodeOffset, code, &mut block_ids);
+            VMControlFlowGraph::record_block_ids(code_offset(pc), end, code, &mut block_ids);
         }
 
         // Create basic blocks
         let mut cfg = VMControlFlowGraph { blocks: Map::new() };
-        let mut entry = 0;
+        let mut entry = CheckedCodeOffset::default();
         for pc in 0..code.len() {
-            let co_pc: CodeOffset = pc as CodeOffset;
+            let co_pc = code_offset(pc);
 
             // Create a basic block
-            if VMControlFlowGraph::is_end_of_block(co_pc, code, &block_ids) {
-                let successors = Bytecode::get_successors(co_pc, code);
+            if VMControlFlowGraph::is_end_of_block(co_pc, end, &block_ids) {
+                let successors = Bytecode::get_successors(co_pc.into_operand(), code);
                 let bb = BasicBlock {
-                    entry,
-                    exit: co_pc,
+                    entry: entry.into_operand(),
+                    exit: co_pc.into_operand(),
                     successors,
                 };
-                cfg.blocks.insert(entry, bb);
-                entry = co_pc + 1;
+                cfg.blocks.insert(entry.into_operand(), bb);
+                entry = next_offset(co_pc);
             }
         }
 
-        assert_eq!(entry, code.len() as CodeOffset);
+        assert_eq!(entry, end);
         cfg
     }
 
@@ -101,19 +119,30 @@ impl VMControlFlowGraph {
         }
     }
 
-    fn is_end_of_block(pc: CodeOffset, code: &[Bytecode], block_ids: &Set<BlockId>) -> bool {
-        pc + 1 == (code.len() as CodeOffset) || block_ids.contains(&(pc + 1))
+    fn is_end_of_block(
+        pc: CheckedCodeOffset,
+        end: CheckedCodeOffset,
+        block_ids: &Set<BlockId>,
+    ) -> bool {
+        let next = next_offset(pc);
+        next == end || block_ids.contains(&next.into_operand())
     }
 
-    fn record_block_ids(pc: CodeOffset, code: &[Bytecode], block_ids: &mut Set<BlockId>) {
-        let bytecode = &code[pc as usize];
+    fn record_block_ids(
+        pc: CheckedCodeOffset,
+        end: CheckedCodeOffset,
+        code: &[Bytecode],
+        block_ids: &mut Set<BlockId>,
+    ) {
+        let bytecode = &code[pc.into_index()];
 
         if let Some(offset) = bytecode.offset() {
             block_ids.insert(*offset);
         }
 
-        if bytecode.is_branch() && pc + 1 < (code.len() as CodeOffset) {
-            block_ids.insert(pc + 1);
+        let next = next_offset(pc);
+        if bytecode.is_branch() && next < end {
+            block_ids.insert(next.into_operand());
         }
     }
 
@@ -0,0 +1,264 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the standard dataflow analyses over the locals of a code unit, liveness
+//! and reaching definitions, on top of a `ControlFlowGraph`, for use by optimizers and lints.
+//!
+//! Both analyses compute a fact for every offset of the code, before and after the instruction at
+//! that offset. A local is read by `CopyLoc`, `MoveLoc` and both borrows, since the value may be
+//! read through the reference, and written by `StLoc`. `MutBorrowLoc` may also write the local
+//! through the reference, so it is a definition as well, though one that doesn't replace the
+//! definitions reaching it.
+//!
+//! Every algorithm here is iterative, since the code may be untrusted.
+use crate::control_flow_graph::{BlockId, ControlFlowGraph};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use vm::file_format::{Bytecode, CodeOffset, LocalIndex};
+
+// BTree/Hash agnostic type wrappers
+type Map<K, V> = BTreeMap<K, V>;
+type Set<V> = BTreeSet<V>;
+
+/// The locals live before and after every instruction of a code unit: those whose current value
+/// may be read later on some path.
+pub struct Liveness {
+    before: Vec<Set<LocalIndex>>,
+    after: Vec<Set<LocalIndex>>,
+}
+
+impl Liveness {
+    /// Computes the live locals of `code`, whose control flow graph is `cfg`. Locals read by
+    /// unreachable code count as live.
+    pub fn new(code: &[Bytecode], cfg: &dyn ControlFlowGraph) -> Self {
+        let blocks = cfg.blocks();
+        let mut live_in: Map<BlockId, Set<LocalIndex>> = Map::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            // Going through blocks backwards makes straight-line code converge in one round.
+            for block_id in blocks.iter().rev() {
+                let mut live = live_out(cfg, &live_in, *block_id);
+                for offset in cfg
+                    .instr_indexes(block_id)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                {
+                    transfer_liveness(&code[offset as usize], &mut live);
+                }
+                if live_in.get(block_id) != Some(&live) {
+                    live_in.insert(*block_id, live);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut before = vec![Set::new(); code.len()];
+        let mut after = vec![Set::new(); code.len()];
+        for block_id in &blocks {
+            let mut live = live_out(cfg, &live_in, *block_id);
+            for offset in cfg
+                .instr_indexes(block_id)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+            {
+                after[offset as usize] = live.clone();
+                transfer_liveness(&code[offset as usize], &mut live);
+                before[offset as usize] = live.clone();
+            }
+        }
+        Self { before, after }
+    }
+
+    /// Returns the locals live before the instruction at `offset` runs.
+    pub fn live_before(&self, offset: CodeOffset) -> &BTreeSet<LocalIndex> {
+        &self.before[offset as usize]
+    }
+
+    /// Returns the locals live after the instruction at `offset` runs, on any of the paths
+    /// leaving it.
+    pub fn live_after(&self, offset: CodeOffset) -> &BTreeSet<LocalIndex> {
+        &self.after[offset as usize]
+    }
+
+    /// Returns true if the value of `local` may be read after the instruction at `offset` runs.
+    pub fn is_live_after(&self, offset: CodeOffset, local: LocalIndex) -> bool {
+        self.after[offset as usize].contains(&local)
+    }
+}
+
+fn live_out(
+    cfg: &dyn ControlFlowGraph,
+    live_in: &Map<BlockId, Set<LocalIndex>>,
+    block_id: BlockId,
+) -> Set<LocalIndex> {
+    cfg.successors(&block_id)
+        .iter()
+        .filter_map(|successor| live_in.get(successor))
+        .flatten()
+        .copied()
+        .collect()
+}
+
+fn transfer_liveness(bytecode: &Bytecode, live: &mut Set<LocalIndex>) {
+    match bytecode {
+        Bytecode::StLoc(idx) => {
+            live.remove(idx);
+        }
+        Bytecode::CopyLoc(idx)
+        | Bytecode::MoveLoc(idx)
+        | Bytecode::MutBorrowLoc(idx)
+        | Bytecode::ImmBorrowLoc(idx) => {
+            live.insert(*idx);
+        }
+        _ => (),
+    }
+}
+
+/// Where the value of a local may come from.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Definition {
+    /// The value the function was called with, for arguments.
+    Argument,
+    /// The `StLoc` or `MutBorrowLoc` at this offset.
+    At(CodeOffset),
+}
+
+/// The definitions of every local that reach each instruction of a code unit.
+pub struct ReachingDefinitions {
+    before: Vec<Map<LocalIndex, Set<Definition>>>,
+    after: Vec<Map<LocalIndex, Set<Definition>>>,
+}
+
+impl ReachingDefinitions {
+    /// Computes the definitions reaching the instructions of `code`, whose control flow graph is
+    /// `cfg`, for a function with `arg_count` arguments. No definition reaches unreachable code.
+    pub fn new(code: &[Bytecode], cfg: &dyn ControlFlowGraph, arg_count: usize) -> Self {
+        let entry = cfg.entry_block_id();
+        let reachable = reachable_blocks(cfg);
+        let mut predecessors: Map<BlockId, Vec<BlockId>> = Map::new();
+        for block_id in &reachable {
+            for successor in cfg.successors(block_id) {
+                predecessors.entry(*successor).or_default().push(*block_id);
+            }
+        }
+        let arguments: Map<LocalIndex, Set<Definition>> = (0..arg_count)
+            .map(|idx| {
+                (
+                    idx as LocalIndex,
+                    vec![Definition::Argument].into_iter().collect(),
+                )
+            })
+            .collect();
+
+        let mut block_out: Map<BlockId, Map<LocalIndex, Set<Definition>>> = Map::new();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block_id in &reachable {
+                let mut state = block_in(*block_id, entry, &arguments, &predecessors, &block_out);
+                for offset in cfg.instr_indexes(block_id) {
+                    transfer_definitions(&code[offset as usize], offset, &mut state);
+                }
+                if block_out.get(block_id) != Some(&state) {
+                    block_out.insert(*block_id, state);
+                    changed = true;
+                }
+            }
+        }
+
+        let mut before = vec![Map::new(); code.len()];
+        let mut after = vec![Map::new(); code.len()];
+        for block_id in &reachable {
+            let mut state = block_in(*block_id, entry, &arguments, &predecessors, &block_out);
+            for offset in cfg.instr_indexes(block_id) {
+                before[offset as usize] = state.clone();
+                transfer_definitions(&code[offset as usize], offset, &mut state);
+                after[offset as usize] = state.clone();
+            }
+        }
+        Self { before, after }
+    }
+
+    /// Returns the definitions of every local that reach the instruction at `offset`. Locals
+    /// without any are left out.
+    pub fn before(&self, offset: CodeOffset) -> &BTreeMap<LocalIndex, BTreeSet<Definition>> {
+        &self.before[offset as usize]
+    }
+
+    /// Returns the definitions of every local that reach the end of the instruction at `offset`.
+    /// Locals without any are left out.
+    pub fn after(&self, offset: CodeOffset) -> &BTreeMap<LocalIndex, BTreeSet<Definition>> {
+        &self.after[offset as usize]
+    }
+
+    /// Returns the definitions of `local` that reach the instruction at `offset`.
+    pub fn reaching(&self, offset: CodeOffset, local: LocalIndex) -> BTreeSet<Definition> {
+        self.before[offset as usize]
+            .get(&local)
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Returns the blocks reachable from the entry block, in the order of `cfg.blocks()`.
+fn reachable_blocks(cfg: &dyn ControlFlowGraph) -> Vec<BlockId> {
+    let mut reached = Set::new();
+    let mut queue = VecDeque::new();
+    reached.insert(cfg.entry_block_id());
+    queue.push_back(cfg.entry_block_id());
+    while let Some(block_id) = queue.pop_front() {
+        for successor in cfg.successors(&block_id) {
+            if reached.insert(*successor) {
+                queue.push_back(*successor);
+            }
+        }
+    }
+    cfg.blocks()
+        .into_iter()
+        .filter(|block_id| reached.contains(block_id))
+        .collect()
+}
+
+fn block_in(
+    block_id: BlockId,
+    entry: BlockId,
+    arguments: &Map<LocalIndex, Set<Definition>>,
+    predecessors: &Map<BlockId, Vec<BlockId>>,
+    block_out: &Map<BlockId, Map<LocalIndex, Set<Definition>>>,
+) -> Map<LocalIndex, Set<Definition>> {
+    let mut state = if block_id == entry {
+        arguments.clone()
+    } else {
+        Map::new()
+    };
+    for predecessor in predecessors.get(&block_id).into_iter().flatten() {
+        for (local, definitions) in block_out.get(predecessor).into_iter().flatten() {
+            state
+                .entry(*local)
+                .or_default()
+                .extend(definitions.iter().copied());
+        }
+    }
+    state
+}
+
+fn transfer_definitions(
+    bytecode: &Bytecode,
+    offset: CodeOffset,
+    state: &mut Map<LocalIndex, Set<Definition>>,
+) {
+    match bytecode {
+        Bytecode::StLoc(idx) => {
+            state.insert(*idx, vec![Definition::At(offset)].into_iter().collect());
+        }
+        Bytecode::MutBorrowLoc(idx) => {
+            state
+                .entry(*idx)
+                .or_default()
+                .insert(Definition::At(offset));
+        }
+        _ => (),
+    }
+}
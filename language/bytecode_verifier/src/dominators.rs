@@ -0,0 +1,288 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module computes dominator trees and natural loops over a `ControlFlowGraph`, for use by
+//! the verifier and by tools such as gas estimators and optimizers.
+//!
+//! Every algorithm here is iterative, since the graphs may be built from untrusted code.
+use crate::control_flow_graph::{BlockId, ControlFlowGraph};
+use std::collections::{BTreeMap, BTreeSet};
+
+// BTree/Hash agnostic type wrappers
+type Map<K, V> = BTreeMap<K, V>;
+type Set<V> = BTreeSet<V>;
+
+/// The dominator tree of the blocks reachable from the entry block of a control flow graph.
+///
+/// A block `a` dominates a block `b` if every path from the entry block to `b` goes through `a`.
+/// Blocks that can't be reached from the entry block are not part of the tree.
+pub struct DominatorTree {
+    /// The reachable blocks in reverse postorder, starting with the entry block.
+    reverse_postorder: Vec<BlockId>,
+    /// The immediate dominator of every reachable block. The entry block is its own.
+    immediate_dominators: Map<BlockId, BlockId>,
+}
+
+impl DominatorTree {
+    /// Computes the dominator tree of `cfg`, using the algorithm from "A Simple, Fast Dominance
+    /// Algorithm" by Cooper, Harvey and Kennedy.
+    pub fn new(cfg: &dyn ControlFlowGraph) -> Self {
+        let reverse_postorder = reverse_postorder(cfg);
+        let order: Map<BlockId, usize> = reverse_postorder
+            .iter()
+            .enumerate()
+            .map(|(idx, block_id)| (*block_id, idx))
+            .collect();
+        let predecessors = predecessors(cfg, &reverse_postorder);
+
+        let entry = cfg.entry_block_id();
+        let mut immediate_dominators = Map::new();
+        immediate_dominators.insert(entry, entry);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for block_id in reverse_postorder.iter().skip(1) {
+                // Some predecessor comes before the block in reverse postorder, so it was already
+                // given an immediate dominator.
+                let new_dominator = predecessors[block_id]
+                    .iter()
+                    .filter(|pred| immediate_dominators.contains_key(*pred))
+                    .fold(None, |dominator, pred| match dominator {
+                        None => Some(*pred),
+                        Some(dominator) => {
+                            Some(intersect(&immediate_dominators, &order, dominator, *pred))
+                        }
+                    })
+                    .expect("reachable blocks have a predecessor earlier in reverse postorder");
+                if immediate_dominators.insert(*block_id, new_dominator) != Some(new_dominator) {
+                    changed = true;
+                }
+            }
+        }
+
+        Self {
+            reverse_postorder,
+            immediate_dominators,
+        }
+    }
+
+    /// Returns the entry block, which is the root of the tree.
+    pub fn entry(&self) -> BlockId {
+        self.reverse_postorder[0]
+    }
+
+    /// Returns the reachable blocks in reverse postorder, starting with the entry block. Every
+    /// block comes after its dominators in this order.
+    pub fn reverse_postorder(&self) -> &[BlockId] {
+        &self.reverse_postorder
+    }
+
+    /// Returns true if `block_id` can be reached from the entry block.
+    pub fn is_reachable(&self, block_id: BlockId) -> bool {
+        self.immediate_dominators.contains_key(&block_id)
+    }
+
+    /// Returns the immediate dominator of `block_id`, or `None` for the entry block and for
+    /// unreachable blocks.
+    pub fn immediate_dominator(&self, block_id: BlockId) -> Option<BlockId> {
+        if block_id == self.entry() {
+            return None;
+        }
+        self.immediate_dominators.get(&block_id).cloned()
+    }
+
+    /// Returns the blocks immediately dominated by `block_id`, in ascending order.
+    pub fn children(&self, block_id: BlockId) -> Vec<BlockId> {
+        let entry = self.entry();
+        self.immediate_dominators
+            .iter()
+            .filter(|(child, dominator)| **dominator == block_id && **child != entry)
+            .map(|(child, _)| *child)
+            .collect()
+    }
+
+    /// Returns true if `dominator` dominates `block_id`. Every reachable block dominates itself,
+    /// and unreachable blocks neither dominate nor are dominated by anything.
+    pub fn dominates(&self, dominator: BlockId, block_id: BlockId) -> bool {
+        if !self.is_reachable(dominator) {
+            return false;
+        }
+        let mut current = Some(block_id);
+        while let Some(block_id) = current {
+            if block_id == dominator {
+                return true;
+            }
+            current = self.immediate_dominator(block_id);
+        }
+        false
+    }
+}
+
+/// A natural loop: a header block along with every block that can reach a back edge into the
+/// header without going through the header. A back edge is an edge whose target dominates its
+/// source.
+///
+/// All the back edges into the same header are part of the same loop.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NaturalLoop {
+    /// The only block of the loop that can be entered from outside of it.
+    pub header: BlockId,
+    /// The sources of the back edges into the header, in ascending order.
+    pub latches: Vec<BlockId>,
+    /// Every block of the loop, including the header.
+    pub blocks: BTreeSet<BlockId>,
+}
+
+/// The natural loops of a control flow graph, along with how they nest.
+///
+/// Two natural loops with different headers are either disjoint or one is nested in the other.
+/// Control flow that enters a cycle at several blocks (i.e. irreducible control flow) doesn't form
+/// a natural loop.
+pub struct LoopNest {
+    /// The loops, in ascending order of their headers.
+    loops: Vec<NaturalLoop>,
+    /// The index of the innermost loop containing each loop, if any.
+    parents: Vec<Option<usize>>,
+}
+
+impl LoopNest {
+    /// Finds the natural loops of `cfg`, whose dominator tree is `dominators`.
+    pub fn new(cfg: &dyn ControlFlowGraph, dominators: &DominatorTree) -> Self {
+        let reachable = dominators.reverse_postorder();
+        let predecessors = predecessors(cfg, reachable);
+
+        let mut latches: Map<BlockId, Vec<BlockId>> = Map::new();
+        for block_id in reachable {
+            for successor in cfg.successors(block_id) {
+                if dominators.dominates(*successor, *block_id) {
+                    latches.entry(*successor).or_default().push(*block_id);
+                }
+            }
+        }
+
+        let loops: Vec<_> = latches
+            .into_iter()
+            .map(|(header, mut latches)| {
+                latches.sort();
+                let mut blocks = Set::new();
+                blocks.insert(header);
+                let mut work_list = latches.clone();
+                while let Some(block_id) = work_list.pop() {
+                    if blocks.insert(block_id) {
+                        work_list.extend(&predecessors[&block_id]);
+                    }
+                }
+                NaturalLoop {
+                    header,
+                    latches,
+                    blocks,
+                }
+            })
+            .collect();
+
+        // The parent of a loop is the smallest other loop containing its header.
+        let parents = loops
+            .iter()
+            .enumerate()
+            .map(|(idx, inner)| {
+                loops
+                    .iter()
+                    .enumerate()
+                    .filter(|(outer_idx, outer)| {
+                        *outer_idx != idx && outer.blocks.contains(&inner.header)
+                    })
+                    .min_by_key(|(_, outer)| outer.blocks.len())
+                    .map(|(outer_idx, _)| outer_idx)
+            })
+            .collect();
+
+        Self { loops, parents }
+    }
+
+    /// Returns the loops, in ascending order of their headers.
+    pub fn loops(&self) -> &[NaturalLoop] {
+        &self.loops
+    }
+
+    /// Returns the index of the innermost loop containing the loop at `loop_idx`, if any.
+    pub fn parent(&self, loop_idx: usize) -> Option<usize> {
+        self.parents[loop_idx]
+    }
+
+    /// Returns the index of the innermost loop containing `block_id`, if any.
+    pub fn innermost_loop(&self, block_id: BlockId) -> Option<usize> {
+        self.loops
+            .iter()
+            .enumerate()
+            .filter(|(_, natural_loop)| natural_loop.blocks.contains(&block_id))
+            .min_by_key(|(_, natural_loop)| natural_loop.blocks.len())
+            .map(|(loop_idx, _)| loop_idx)
+    }
+
+    /// Returns the number of loops containing `block_id`. Blocks outside of any loop have depth 0.
+    pub fn depth(&self, block_id: BlockId) -> usize {
+        self.loops
+            .iter()
+            .filter(|natural_loop| natural_loop.blocks.contains(&block_id))
+            .count()
+    }
+}
+
+/// Returns the blocks reachable from the entry block of `cfg`, in reverse postorder.
+fn reverse_postorder(cfg: &dyn ControlFlowGraph) -> Vec<BlockId> {
+    let entry = cfg.entry_block_id();
+    let mut postorder = vec![];
+    let mut visited = Set::new();
+    visited.insert(entry);
+    // Blocks being visited, along with the index of the next successor to visit.
+    let mut stack = vec![(entry, 0)];
+    while let Some((block_id, next)) = stack.pop() {
+        let successors = cfg.successors(&block_id);
+        if next < successors.len() {
+            stack.push((block_id, next + 1));
+            let successor = successors[next];
+            if visited.insert(successor) {
+                stack.push((successor, 0));
+            }
+        } else {
+            postorder.push(block_id);
+        }
+    }
+    postorder.reverse();
+    postorder
+}
+
+/// Returns the predecessors of every block in `reachable`, only counting the predecessors that are
+/// in `reachable` as well.
+fn predecessors(cfg: &dyn ControlFlowGraph, reachable: &[BlockId]) -> Map<BlockId, Vec<BlockId>> {
+    let mut predecessors: Map<BlockId, Vec<BlockId>> = reachable
+        .iter()
+        .map(|block_id| (*block_id, vec![]))
+        .collect();
+    for block_id in reachable {
+        for successor in cfg.successors(block_id) {
+            if let Some(preds) = predecessors.get_mut(successor) {
+                preds.push(*block_id);
+            }
+        }
+    }
+    predecessors
+}
+
+/// Returns the closest common dominator of `a` and `b`, walking up the dominators found so far.
+fn intersect(
+    immediate_dominators: &Map<BlockId, BlockId>,
+    order: &Map<BlockId, usize>,
+    mut a: BlockId,
+    mut b: BlockId,
+) -> BlockId {
+    while a != b {
+        while order[&a] > order[&b] {
+            a = immediate_dominators[&a];
+        }
+        while order[&b] > order[&a] {
+            b = immediate_dominators[&b];
+        }
+    }
+    a
+}
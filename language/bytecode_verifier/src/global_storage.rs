@@ -0,0 +1,114 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a checker for verifying that every instruction accessing global storage
+//! is consistent with the struct it names and with the acquires list of its function:
+//! - `BorrowGlobal`, `MoveFrom`, `MoveToSender` and `Exists` must name a resource struct
+//! - `BorrowGlobal` and `MoveFrom` must name a resource in the acquires list of the function
+//! - `Call` must only call functions whose acquires list is included in that of the function
+//!
+//! Type safety and the acquires checker verify these properties as well, but type safety stops at
+//! the first error of a function and skips unreachable code. This checker visits every instruction
+//! and reports every inconsistent one at its code offset, for tools that want all of them at once.
+use std::collections::BTreeSet;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        Bytecode, CompiledModule, FunctionDefinition, FunctionDefinitionIndex,
+        StructDefinitionIndex, TableIndex,
+    },
+    views::{ModuleView, StructDefinitionView, ViewInternals},
+};
+
+pub struct GlobalStorageChecker<'a> {
+    module_view: ModuleView<'a, CompiledModule>,
+}
+
+impl<'a> GlobalStorageChecker<'a> {
+    pub fn new(module: &'a CompiledModule) -> Self {
+        Self {
+            module_view: ModuleView::new(module),
+        }
+    }
+
+    /// Returns an error for every global storage access and call inconsistent with the struct it
+    /// names or with the acquires list of its function, in the order of the function definitions
+    /// and code offsets.
+    pub fn verify(self) -> Vec<VerificationError> {
+        let module = self.module_view.as_inner();
+        module
+            .function_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, function_definition)| !function_definition.is_native())
+            .flat_map(|(idx, function_definition)| {
+                let idx = FunctionDefinitionIndex::new(idx as TableIndex);
+                self.verify_function(function_definition)
+                    .into_iter()
+                    .map(move |err| VerificationError::in_function(idx, err))
+            })
+            .collect()
+    }
+
+    fn verify_function(&self, function_definition: &FunctionDefinition) -> Vec<VMStaticViolation> {
+        let acquires: BTreeSet<_> = function_definition
+            .acquires_global_resources
+            .iter()
+            .collect();
+        let mut errors = vec![];
+        for (offset, bytecode) in function_definition.code.code.iter().enumerate() {
+            match bytecode {
+                Bytecode::BorrowGlobal(idx, _) => {
+                    if !self.is_resource(*idx) {
+                        errors.push(VMStaticViolation::BorrowGlobalNoResourceError(offset));
+                    } else if !acquires.contains(idx) {
+                        errors.push(VMStaticViolation::MissingAcquiresResourceAnnotationError(
+                            offset,
+                        ));
+                    }
+                }
+                Bytecode::MoveFrom(idx, _) => {
+                    if !self.is_resource(*idx) {
+                        errors.push(VMStaticViolation::MoveFromNoResourceError(offset));
+                    } else if !acquires.contains(idx) {
+                        errors.push(VMStaticViolation::MissingAcquiresResourceAnnotationError(
+                            offset,
+                        ));
+                    }
+                }
+                Bytecode::MoveToSender(idx, _) => {
+                    if !self.is_resource(*idx) {
+                        errors.push(VMStaticViolation::MoveToSenderNoResourceError(offset));
+                    }
+                }
+                Bytecode::Exists(idx, _) => {
+                    if !self.is_resource(*idx) {
+                        errors.push(VMStaticViolation::ExistsNoResourceError(offset));
+                    }
+                }
+                Bytecode::Call(idx, _) => {
+                    let function_handle = self.module_view.as_inner().function_handle_at(*idx);
+                    let callee_acquires = self
+                        .module_view
+                        .function_acquired_resources(function_handle);
+                    if callee_acquires
+                        .iter()
+                        .any(|resource| !acquires.contains(resource))
+                    {
+                        errors.push(VMStaticViolation::MissingAcquiresResourceAnnotationError(
+                            offset,
+                        ));
+                    }
+                }
+                _ => (),
+            }
+        }
+        errors
+    }
+
+    fn is_resource(&self, idx: StructDefinitionIndex) -> bool {
+        let module = self.module_view.as_inner();
+        StructDefinitionView::new(module, module.struct_def_at(idx)).is_nominal_resource()
+    }
+}
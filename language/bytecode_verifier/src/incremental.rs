@@ -0,0 +1,509 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module determines which definitions of a module changed between two versions of it, so
+//! that an upgraded module only has the code units that could verify differently re-verified (see
+//! `VerifiedModule::new_upgrade`).
+//!
+//! Definitions are compared in a canonical form, with every index resolved to what it refers to
+//! (names, signatures, constants), so that reordering the tables of a module doesn't count as a
+//! change. The verification of a code unit depends on more than the function definition itself:
+//! - the signatures of the functions it calls, and the acquires annotations of those defined in the
+//!   same module, which are part of the canonical form of the call;
+//! - the structs it refers to, including the structs their fields refer to, transitively.
+use std::collections::{BTreeMap, BTreeSet};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+use vm::{
+    access::ModuleAccess,
+    file_format::{
+        AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CompiledModule, FieldDefinitionIndex,
+        FunctionDefinition, FunctionHandleIndex, FunctionSignature, Kind, LocalsSignatureIndex,
+        SignatureToken, StringPoolIndex, StructDefinitionIndex, StructHandleIndex, TableIndex,
+        TypeParameterIndex,
+    },
+};
+
+/// The definitions of a module that changed since an earlier version of the module, and the code
+/// units that must be verified again as a result.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModuleChanges {
+    /// The names of the struct definitions that were added or changed.
+    pub changed_structs: BTreeSet<String>,
+    /// The names of the function definitions that were added or changed.
+    pub changed_functions: BTreeSet<String>,
+    /// The function definitions of the new module whose code units must be verified again:
+    /// those that changed, and those that refer to a struct that changed.
+    pub functions_to_verify: Vec<FunctionDefinitionIndex>,
+}
+
+impl ModuleChanges {
+    /// Compares `new` against `old`. Both modules must have passed the module-level passes of the
+    /// verifier (duplication, signatures, etc.), so that their indexes can be resolved.
+    pub fn new(old: &CompiledModule, new: &CompiledModule) -> Self {
+        let old_structs = canonical_structs(old);
+        let new_structs = canonical_structs(new);
+        let old_functions = canonical_functions(old);
+        let new_functions = canonical_functions(new);
+
+        let changed_structs: BTreeSet<String> = new_structs
+            .iter()
+            .filter(|(name, new_struct)| old_structs.get(*name) != Some(*new_struct))
+            .map(|(name, _)| name.clone())
+            .collect();
+        let changed_functions: BTreeSet<String> = new_functions
+            .iter()
+            .filter(|(name, new_function)| old_functions.get(*name) != Some(*new_function))
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        // Structs whose meaning changed: those whose definition or handle changed, and those
+        // with a field referring to such a struct.
+        let self_id = new.self_id();
+        let mut affected_structs: BTreeSet<StructName> = changed_structs
+            .iter()
+            .map(|name| (self_id.clone(), name.clone()))
+            .chain(changed_struct_handles(old, new))
+            .collect();
+        loop {
+            let newly_affected: Vec<_> = new_structs
+                .iter()
+                .map(|(name, canonical_struct)| ((self_id.clone(), name.clone()), canonical_struct))
+                .filter(|(name, canonical_struct)| {
+                    !affected_structs.contains(name)
+                        && !canonical_struct
+                            .referenced_structs()
+                            .is_disjoint(&affected_structs)
+                })
+                .map(|(name, _)| name)
+                .collect();
+            if newly_affected.is_empty() {
+                break;
+            }
+            affected_structs.extend(newly_affected);
+        }
+
+        let functions_to_verify = new
+            .function_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, function_definition)| {
+                let name = function_name(new, function_definition);
+                changed_functions.contains(name)
+                    || !new_functions[name]
+                        .referenced_structs()
+                        .is_disjoint(&affected_structs)
+            })
+            .map(|(idx, _)| FunctionDefinitionIndex::new(idx as TableIndex))
+            .collect();
+
+        Self {
+            changed_structs,
+            changed_functions,
+            functions_to_verify,
+        }
+    }
+
+    /// Returns true if no definition changed.
+    pub fn is_empty(&self) -> bool {
+        self.changed_structs.is_empty()
+            && self.changed_functions.is_empty()
+            && self.functions_to_verify.is_empty()
+    }
+}
+
+/// A struct, identified by the module declaring it and its name.
+type StructName = (ModuleId, String);
+
+/// A signature token with struct handles resolved to the structs they refer to.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum CanonicalToken {
+    Bool,
+    U64,
+    String,
+    ByteArray,
+    Address,
+    Struct(StructName, Vec<CanonicalToken>),
+    Reference(Box<CanonicalToken>),
+    MutableReference(Box<CanonicalToken>),
+    TypeParameter(TypeParameterIndex),
+}
+
+impl CanonicalToken {
+    fn collect_structs(&self, structs: &mut BTreeSet<StructName>) {
+        match self {
+            CanonicalToken::Struct(name, type_actuals) => {
+                structs.insert(name.clone());
+                for type_actual in type_actuals {
+                    type_actual.collect_structs(structs);
+                }
+            }
+            CanonicalToken::Reference(inner) | CanonicalToken::MutableReference(inner) => {
+                inner.collect_structs(structs)
+            }
+            CanonicalToken::Bool
+            | CanonicalToken::U64
+            | CanonicalToken::String
+            | CanonicalToken::ByteArray
+            | CanonicalToken::Address
+            | CanonicalToken::TypeParameter(_) => (),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct CanonicalSignature {
+    type_formals: Vec<Kind>,
+    arg_types: Vec<CanonicalToken>,
+    return_types: Vec<CanonicalToken>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct CanonicalStruct {
+    is_nominal_resource: bool,
+    type_formals: Vec<Kind>,
+    /// The names and types of the fields, or `None` for native structs.
+    fields: Option<Vec<(String, CanonicalToken)>>,
+}
+
+impl CanonicalStruct {
+    fn referenced_structs(&self) -> BTreeSet<StructName> {
+        let mut structs = BTreeSet::new();
+        for (_, token) in self.fields.iter().flatten() {
+            token.collect_structs(&mut structs);
+        }
+        structs
+    }
+}
+
+/// What an instruction refers to through its index operands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum CanonicalOperand {
+    None,
+    String(String),
+    ByteArray(Vec<u8>),
+    Address(AccountAddress),
+    Function {
+        module: ModuleId,
+        name: String,
+        signature: CanonicalSignature,
+        /// The acquires annotation of the callee, if it is defined in the same module.
+        acquires: Vec<StructName>,
+        type_actuals: Vec<CanonicalToken>,
+    },
+    Struct(StructName, Vec<CanonicalToken>),
+    Field(StructName, String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct CanonicalFunction {
+    flags: u8,
+    signature: CanonicalSignature,
+    locals: Vec<CanonicalToken>,
+    acquires: Vec<StructName>,
+    /// Every instruction, with its index operands zeroed and resolved into the operand.
+    code: Vec<(Bytecode, CanonicalOperand)>,
+}
+
+impl CanonicalFunction {
+    fn referenced_structs(&self) -> BTreeSet<StructName> {
+        let mut structs: BTreeSet<_> = self.acquires.iter().cloned().collect();
+        let signature = &self.signature;
+        for token in signature
+            .arg_types
+            .iter()
+            .chain(&signature.return_types)
+            .chain(&self.locals)
+        {
+            token.collect_structs(&mut structs);
+        }
+        for (_, operand) in &self.code {
+            match operand {
+                CanonicalOperand::Function {
+                    signature,
+                    acquires,
+                    type_actuals,
+                    ..
+                } => {
+                    structs.extend(acquires.iter().cloned());
+                    for token in signature
+                        .arg_types
+                        .iter()
+                        .chain(&signature.return_types)
+                        .chain(type_actuals)
+                    {
+                        token.collect_structs(&mut structs);
+                    }
+                }
+                CanonicalOperand::Struct(name, type_actuals) => {
+                    structs.insert(name.clone());
+                    for token in type_actuals {
+                        token.collect_structs(&mut structs);
+                    }
+                }
+                CanonicalOperand::Field(name, _) => {
+                    structs.insert(name.clone());
+                }
+                CanonicalOperand::None
+                | CanonicalOperand::String(_)
+                | CanonicalOperand::ByteArray(_)
+                | CanonicalOperand::Address(_) => (),
+            }
+        }
+        structs
+    }
+}
+
+fn function_name<'a>(
+    module: &'a CompiledModule,
+    function_definition: &FunctionDefinition,
+) -> &'a str {
+    let function_handle = module.function_handle_at(function_definition.function);
+    module.string_at(function_handle.name)
+}
+
+fn struct_name(module: &CompiledModule, idx: StructHandleIndex) -> StructName {
+    let struct_handle = module.struct_handle_at(idx);
+    let module_handle = module.module_handle_at(struct_handle.module);
+    (
+        module.module_id_for_handle(module_handle),
+        module.string_at(struct_handle.name).to_string(),
+    )
+}
+
+fn struct_definition_name(module: &CompiledModule, idx: StructDefinitionIndex) -> StructName {
+    struct_name(module, module.struct_def_at(idx).struct_handle)
+}
+
+fn canonical_token(module: &CompiledModule, token: &SignatureToken) -> CanonicalToken {
+    match token {
+        SignatureToken::Bool => CanonicalToken::Bool,
+        SignatureToken::U64 => CanonicalToken::U64,
+        SignatureToken::String => CanonicalToken::String,
+        SignatureToken::ByteArray => CanonicalToken::ByteArray,
+        SignatureToken::Address => CanonicalToken::Address,
+        SignatureToken::Struct(idx, type_actuals) => CanonicalToken::Struct(
+            struct_name(module, *idx),
+            canonical_tokens(module, type_actuals),
+        ),
+        SignatureToken::Reference(inner) => {
+            CanonicalToken::Reference(Box::new(canonical_token(module, inner)))
+        }
+        SignatureToken::MutableReference(inner) => {
+            CanonicalToken::MutableReference(Box::new(canonical_token(module, inner)))
+        }
+        SignatureToken::TypeParameter(idx) => CanonicalToken::TypeParameter(*idx),
+    }
+}
+
+fn canonical_tokens(module: &CompiledModule, tokens: &[SignatureToken]) -> Vec<CanonicalToken> {
+    tokens
+        .iter()
+        .map(|token| canonical_token(module, token))
+        .collect()
+}
+
+fn canonical_signature(
+    module: &CompiledModule,
+    signature: &FunctionSignature,
+) -> CanonicalSignature {
+    CanonicalSignature {
+        type_formals: signature.type_formals.clone(),
+        arg_types: canonical_tokens(module, &signature.arg_types),
+        return_types: canonical_tokens(module, &signature.return_types),
+    }
+}
+
+fn canonical_structs(module: &CompiledModule) -> BTreeMap<String, CanonicalStruct> {
+    module
+        .struct_defs()
+        .iter()
+        .map(|struct_definition| {
+            let struct_handle = module.struct_handle_at(struct_definition.struct_handle);
+            let fields = module.struct_def_fields(struct_definition).map(|fields| {
+                fields
+                    .iter()
+                    .map(|field_definition| {
+                        (
+                            module.string_at(field_definition.name).to_string(),
+                            canonical_token(
+                                module,
+                                &module.type_signature_at(field_definition.signature).0,
+                            ),
+                        )
+                    })
+                    .collect()
+            });
+            let canonical_struct = CanonicalStruct {
+                is_nominal_resource: struct_handle.is_nominal_resource,
+                type_formals: struct_handle.type_formals.clone(),
+                fields,
+            };
+            (
+                module.string_at(struct_handle.name).to_string(),
+                canonical_struct,
+            )
+        })
+        .collect()
+}
+
+/// Returns the structs whose handles declare them differently in `old` and `new`, including
+/// structs of other modules.
+fn changed_struct_handles(old: &CompiledModule, new: &CompiledModule) -> Vec<StructName> {
+    let declarations = |module: &CompiledModule| -> BTreeMap<StructName, (bool, Vec<Kind>)> {
+        (0..module.struct_handles().len())
+            .map(|idx| {
+                let idx = StructHandleIndex::new(idx as TableIndex);
+                let struct_handle = module.struct_handle_at(idx);
+                (
+                    struct_name(module, idx),
+                    (
+                        struct_handle.is_nominal_resource,
+                        struct_handle.type_formals.clone(),
+                    ),
+                )
+            })
+            .collect()
+    };
+    let old_declarations = declarations(old);
+    declarations(new)
+        .into_iter()
+        .filter(|(name, declaration)| old_declarations.get(name) != Some(declaration))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+fn canonical_functions(module: &CompiledModule) -> BTreeMap<String, CanonicalFunction> {
+    // The acquires annotations of the functions defined in the module, by handle.
+    let acquires: BTreeMap<FunctionHandleIndex, Vec<StructName>> = module
+        .function_defs()
+        .iter()
+        .map(|function_definition| {
+            let acquires = function_definition
+                .acquires_global_resources
+                .iter()
+                .map(|idx| struct_definition_name(module, *idx))
+                .collect();
+            (function_definition.function, acquires)
+        })
+        .collect();
+
+    module
+        .function_defs()
+        .iter()
+        .map(|function_definition| {
+            let function_handle = module.function_handle_at(function_definition.function);
+            let canonical_function = CanonicalFunction {
+                flags: function_definition.flags,
+                signature: canonical_signature(
+                    module,
+                    module.function_signature_at(function_handle.signature),
+                ),
+                locals: canonical_tokens(
+                    module,
+                    &module
+                        .locals_signature_at(function_definition.code.locals)
+                        .0,
+                ),
+                acquires: acquires[&function_definition.function].clone(),
+                code: function_definition
+                    .code
+                    .code
+                    .iter()
+                    .map(|bytecode| canonical_instruction(module, &acquires, bytecode))
+                    .collect(),
+            };
+            (
+                function_name(module, function_definition).to_string(),
+                canonical_function,
+            )
+        })
+        .collect()
+}
+
+fn canonical_instruction(
+    module: &CompiledModule,
+    acquires: &BTreeMap<FunctionHandleIndex, Vec<StructName>>,
+    bytecode: &Bytecode,
+) -> (Bytecode, CanonicalOperand) {
+    let type_actuals =
+        |idx: &LocalsSignatureIndex| canonical_tokens(module, &module.locals_signature_at(*idx).0);
+    let struct_operand = |idx: &StructDefinitionIndex, type_actuals_idx: &LocalsSignatureIndex| {
+        CanonicalOperand::Struct(
+            struct_definition_name(module, *idx),
+            type_actuals(type_actuals_idx),
+        )
+    };
+    let no_struct = StructDefinitionIndex::new(0);
+    let no_type_actuals = LocalsSignatureIndex::new(0);
+
+    match bytecode {
+        Bytecode::LdStr(idx) => (
+            Bytecode::LdStr(StringPoolIndex::new(0)),
+            CanonicalOperand::String(module.string_at(*idx).to_string()),
+        ),
+        Bytecode::LdByteArray(idx) => (
+            Bytecode::LdByteArray(ByteArrayPoolIndex::new(0)),
+            CanonicalOperand::ByteArray(module.byte_array_at(*idx).as_bytes().to_vec()),
+        ),
+        Bytecode::LdAddr(idx) => (
+            Bytecode::LdAddr(AddressPoolIndex::new(0)),
+            CanonicalOperand::Address(*module.address_at(*idx)),
+        ),
+        Bytecode::Call(idx, type_actuals_idx) => {
+            let function_handle = module.function_handle_at(*idx);
+            let module_handle = module.module_handle_at(function_handle.module);
+            let operand = CanonicalOperand::Function {
+                module: module.module_id_for_handle(module_handle),
+                name: module.string_at(function_handle.name).to_string(),
+                signature: canonical_signature(
+                    module,
+                    module.function_signature_at(function_handle.signature),
+                ),
+                acquires: acquires.get(idx).cloned().unwrap_or_default(),
+                type_actuals: type_actuals(type_actuals_idx),
+            };
+            (
+                Bytecode::Call(FunctionHandleIndex::new(0), no_type_actuals),
+                operand,
+            )
+        }
+        Bytecode::Pack(idx, type_actuals_idx) => (
+            Bytecode::Pack(no_struct, no_type_actuals),
+            struct_operand(idx, type_actuals_idx),
+        ),
+        Bytecode::Unpack(idx, type_actuals_idx) => (
+            Bytecode::Unpack(no_struct, no_type_actuals),
+            struct_operand(idx, type_actuals_idx),
+        ),
+        Bytecode::BorrowGlobal(idx, type_actuals_idx) => (
+            Bytecode::BorrowGlobal(no_struct, no_type_actuals),
+            struct_operand(idx, type_actuals_idx),
+        ),
+        Bytecode::Exists(idx, type_actuals_idx) => (
+            Bytecode::Exists(no_struct, no_type_actuals),
+            struct_operand(idx, type_actuals_idx),
+        ),
+        Bytecode::MoveFrom(idx, type_actuals_idx) => (
+            Bytecode::MoveFrom(no_struct, no_type_actuals),
+            struct_operand(idx, type_actuals_idx),
+        ),
+        Bytecode::MoveToSender(idx, type_actuals_idx) => (
+            Bytecode::MoveToSender(no_struct, no_type_actuals),
+            struct_operand(idx, type_actuals_idx),
+        ),
+        Bytecode::MutBorrowField(idx) | Bytecode::ImmBorrowField(idx) => {
+            let field_definition = module.field_def_at(*idx);
+            let operand = CanonicalOperand::Field(
+                struct_name(module, field_definition.struct_),
+                module.string_at(field_definition.name).to_string(),
+            );
+            let no_field = FieldDefinitionIndex::new(0);
+            let bytecode = match bytecode {
+                Bytecode::MutBorrowField(_) => Bytecode::MutBorrowField(no_field),
+                _ => Bytecode::ImmBorrowField(no_field),
+            };
+            (bytecode, operand)
+        }
+        _ => (bytecode.clone(), CanonicalOperand::None),
+    }
+}
@@ -7,27 +7,82 @@
 pub mod absint;
 pub mod abstract_state;
 pub mod acquires_list_verifier;
+pub mod borrow_graph_dump;
+pub mod cache;
+pub mod call_graph;
 pub mod check_duplication;
+pub mod classify;
 pub mod code_unit_verifier;
+pub mod compatibility;
+pub mod complexity;
+pub mod config;
 pub mod control_flow_graph;
+pub mod dataflow;
+pub mod dominators;
+pub mod global_storage;
+pub mod incremental;
+pub mod lint;
+pub mod meter;
+pub mod metrics;
+pub mod module_cycles;
+pub mod native_functions;
 pub mod nonce;
+pub mod package;
 pub mod partition;
+pub mod publish;
+pub mod reducibility;
+pub mod report;
 pub mod resources;
+pub mod script_signature;
 pub mod signature;
+pub mod stack_height_verifier;
 pub mod stack_usage_verifier;
 pub mod struct_defs;
+pub mod structural_limits;
+pub mod trace;
 pub mod type_memory_safety;
 #[cfg(test)]
 mod unit_tests;
+pub mod unreachable_code;
 pub mod verifier;
 
+pub use absint::{
+    AbstractDomain, AbstractInterpreter, BlockInvariant, BlockPostcondition, BlockPrecondition,
+    InvariantMap, IterationBudgetExceeded, JoinResult, TransferFunctions,
+};
+pub use borrow_graph_dump::{BorrowGraphDumper, BorrowGraphFormat};
+pub use cache::{LruVerificationCache, VerificationCache, VerificationCacheKey};
+pub use call_graph::{CallGraph, FunctionId};
 pub use check_duplication::DuplicationChecker;
+pub use classify::{classify_binary, classify_module, Classification, PassOutcome};
 pub use code_unit_verifier::CodeUnitVerifier;
+pub use compatibility::{CompatibilityChecker, CompatibilityReport, Incompatibility};
+pub use complexity::{ComplexityReport, FunctionComplexity};
+pub use config::{
+    ConfigurablePass, StackHeightLimit, StructuralLimits, VerifierConfig, VerifierLimits,
+};
+pub use dataflow::{Definition, Liveness, ReachingDefinitions};
+pub use global_storage::GlobalStorageChecker;
+pub use incremental::ModuleChanges;
+pub use lint::{Lint, Linter, ResourceReferenceLint, UnboundedLoopLint, UncheckedArithmeticLint};
+pub use metrics::{FunctionMetrics, PassMetrics, VerificationMetrics};
+pub use module_cycles::DependencyCycleChecker;
+pub use native_functions::{NativeFunctionChecker, NativeFunctionRegistry};
+pub use package::{PackageVerificationError, VerifiedPackage};
+pub use publish::{validate_publish, PublishIssue, PublishLimits, PublishReport};
+pub use reducibility::{LoopAnalysis, ReducibilityChecker};
+pub use report::{PassReport, PassStatus, VerificationReport};
 pub use resources::ResourceTransitiveChecker;
+pub use script_signature::ScriptSignatureChecker;
 pub use signature::SignatureChecker;
+pub use stack_height_verifier::StackHeightVerifier;
 pub use stack_usage_verifier::StackUsageVerifier;
 pub use struct_defs::RecursiveStructDefChecker;
+pub use structural_limits::StructuralLimitsChecker;
+pub use unreachable_code::UnreachableCodeChecker;
 pub use verifier::{
-    verify_main_signature, verify_module_dependencies, verify_script_dependencies, VerifiedModule,
-    VerifiedScript,
+    verify_main_signature, verify_module_address, verify_module_by_pass,
+    verify_module_dependencies, verify_module_dependencies_in, verify_module_with_config,
+    verify_module_with_metrics, verify_script_dependencies, verify_script_dependencies_in,
+    ErrorsByPass, ModuleCache, VerifiedModule, VerifiedScript,
 };
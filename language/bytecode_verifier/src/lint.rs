@@ -0,0 +1,245 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a framework for lints: advisory checks that flag code patterns which
+//! are well-formed, but deserve a review, such as arithmetic that user input can make abort.
+//!
+//! A lint implements `Lint`, and reports its findings as `VerificationError`s with severity
+//! `Warning`, so that they can be rendered and filtered like the warnings of the verifier. A
+//! `Linter` runs a set of lints over a module, starting either empty or with the built-in ones.
+//!
+//! Lints only make sense on modules that passed verification.
+use crate::{
+    call_graph::{CallGraph, FunctionId},
+    control_flow_graph::{ControlFlowGraph, VMControlFlowGraph},
+    dataflow::{Definition, ReachingDefinitions},
+    dominators::{DominatorTree, LoopNest},
+};
+use std::collections::BTreeSet;
+use vm::{
+    access::ModuleAccess,
+    errors::{sort_errors, Severity, VMStaticViolation, VerificationError},
+    file_format::{Bytecode, CompiledModule, FunctionDefinitionIndex, SignatureToken, TableIndex},
+    views::{FunctionDefinitionView, ModuleView, SignatureTokenView, ViewInternals},
+};
+
+/// An advisory check over a module.
+pub trait Lint {
+    /// Returns the name of the lint, e.g. to allow it with `Linter::allow`.
+    fn name(&self) -> &'static str;
+
+    /// Returns a warning for every finding of the lint in `module`.
+    fn check(&self, module: &ModuleView<CompiledModule>) -> Vec<VerificationError>;
+}
+
+/// Runs a set of lints over modules.
+#[derive(Default)]
+pub struct Linter {
+    lints: Vec<Box<dyn Lint>>,
+}
+
+impl Linter {
+    /// Creates a linter without any lints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a linter with every built-in lint.
+    pub fn with_builtin_lints() -> Self {
+        Self::new()
+            .with_lint(UncheckedArithmeticLint)
+            .with_lint(UnboundedLoopLint)
+            .with_lint(ResourceReferenceLint)
+    }
+
+    /// Adds `lint`, which is run after the lints already added.
+    pub fn with_lint(mut self, lint: impl Lint + 'static) -> Self {
+        self.lints.push(Box::new(lint));
+        self
+    }
+
+    /// Removes the lints named `name`.
+    pub fn allow(mut self, name: &str) -> Self {
+        self.lints.retain(|lint| lint.name() != name);
+        self
+    }
+
+    /// Returns the names of the lints, in the order they run.
+    pub fn lint_names(&self) -> Vec<&'static str> {
+        self.lints.iter().map(|lint| lint.name()).collect()
+    }
+
+    /// Runs every lint over `module`, and returns their warnings in canonical order (see
+    /// `sort_errors`).
+    pub fn run(&self, module: &CompiledModule) -> Vec<VerificationError> {
+        let view = ModuleView::new(module);
+        let mut errors: Vec<_> = self
+            .lints
+            .iter()
+            .flat_map(|lint| lint.check(&view))
+            .collect();
+        debug_assert!(errors
+            .iter()
+            .all(|error| error.severity() == Severity::Warning));
+        sort_errors(&mut errors);
+        errors
+    }
+}
+
+/// Flags arithmetic that may abort, in functions that public functions may call. The arguments of
+/// public functions are controlled by whoever calls them, who may then choose to make the
+/// arithmetic abort.
+///
+/// Every `Add`, `Sub` and `Mul` may abort on overflow, and every `Div` and `Mod` may abort on a
+/// zero divisor, unless the divisor is a constant loaded right before it.
+pub struct UncheckedArithmeticLint;
+
+impl Lint for UncheckedArithmeticLint {
+    fn name(&self) -> &'static str {
+        "unchecked_arithmetic"
+    }
+
+    fn check(&self, module: &ModuleView<CompiledModule>) -> Vec<VerificationError> {
+        let module_id = module.id();
+        let call_graph = CallGraph::new(vec![module.as_inner()]);
+        let function_id = |function: &FunctionDefinitionView<CompiledModule>| {
+            FunctionId::new(module_id.clone(), function.name())
+        };
+        let mut exposed: BTreeSet<FunctionId> = BTreeSet::new();
+        for function in module.functions().filter(|function| function.is_public()) {
+            let id = function_id(&function);
+            exposed.extend(call_graph.reachable_from(&id).into_iter().cloned());
+            exposed.insert(id);
+        }
+
+        let mut errors = vec![];
+        for (idx, function) in module.functions().enumerate() {
+            if function.is_native() || !exposed.contains(&function_id(&function)) {
+                continue;
+            }
+            let idx = FunctionDefinitionIndex::new(idx as TableIndex);
+            let code = &function.code().code;
+            for (offset, bytecode) in code.iter().enumerate() {
+                let may_abort = match bytecode {
+                    Bytecode::Add | Bytecode::Sub | Bytecode::Mul => true,
+                    Bytecode::Div | Bytecode::Mod => match offset.checked_sub(1) {
+                        Some(previous) => match code[previous] {
+                            Bytecode::LdConst(divisor) => divisor == 0,
+                            _ => true,
+                        },
+                        None => true,
+                    },
+                    _ => false,
+                };
+                if may_abort {
+                    errors.push(VerificationError::in_function(
+                        idx,
+                        VMStaticViolation::UncheckedArithmetic(offset),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Flags the loops of public functions whose exit condition reads an argument that is never
+/// reassigned before, such as `while (i < n)` for an argument `n`. Whoever calls the function
+/// decides how many times such a loop runs.
+///
+/// The warning is located at the first instruction of the loop header.
+pub struct UnboundedLoopLint;
+
+impl Lint for UnboundedLoopLint {
+    fn name(&self) -> &'static str {
+        "unbounded_loop"
+    }
+
+    fn check(&self, module: &ModuleView<CompiledModule>) -> Vec<VerificationError> {
+        let mut errors = vec![];
+        for (idx, function) in module.functions().enumerate() {
+            if function.is_native() || !function.is_public() {
+                continue;
+            }
+            let idx = FunctionDefinitionIndex::new(idx as TableIndex);
+            let code = &function.code().code;
+            let cfg = VMControlFlowGraph::new(code);
+            let loop_nest = LoopNest::new(&cfg, &DominatorTree::new(&cfg));
+            let reaching = ReachingDefinitions::new(code, &cfg, function.signature().arg_count());
+            for natural_loop in loop_nest.loops() {
+                let bounded_by_argument = natural_loop.blocks.iter().any(|block_id| {
+                    let exits = cfg
+                        .successors(block_id)
+                        .iter()
+                        .any(|successor| !natural_loop.blocks.contains(successor));
+                    let end = cfg.block_end(block_id);
+                    let conditional = match code[end as usize] {
+                        Bytecode::BrTrue(_) | Bytecode::BrFalse(_) => true,
+                        _ => false,
+                    };
+                    exits
+                        && conditional
+                        && cfg
+                            .instr_indexes(block_id)
+                            .any(|offset| match code[offset as usize] {
+                                Bytecode::CopyLoc(local)
+                                | Bytecode::MoveLoc(local)
+                                | Bytecode::ImmBorrowLoc(local)
+                                | Bytecode::MutBorrowLoc(local) => reaching
+                                    .reaching(offset, local)
+                                    .contains(&Definition::Argument),
+                                _ => false,
+                            })
+                });
+                if bounded_by_argument {
+                    let header = cfg.block_start(&natural_loop.header);
+                    errors.push(VerificationError::in_function(
+                        idx,
+                        VMStaticViolation::UnboundedLoop(header as usize),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Flags public functions that return a reference to a resource, through which other modules can
+/// read, or for mutable references modify, a value only the defining module should handle.
+///
+/// The warning is located at the function definition, and points at the return value.
+pub struct ResourceReferenceLint;
+
+impl Lint for ResourceReferenceLint {
+    fn name(&self) -> &'static str {
+        "resource_reference_return"
+    }
+
+    fn check(&self, module: &ModuleView<CompiledModule>) -> Vec<VerificationError> {
+        let mut errors = vec![];
+        for (idx, function) in module.functions().enumerate() {
+            if !function.is_public() {
+                continue;
+            }
+            let signature = function.signature();
+            let type_formals = &signature.as_inner().type_formals;
+            for (return_idx, token) in signature.return_tokens().enumerate() {
+                let referenced = match token.as_inner() {
+                    SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+                        inner
+                    }
+                    _ => continue,
+                };
+                if SignatureTokenView::new(module.as_inner(), referenced)
+                    .contains_nominal_resource(type_formals)
+                {
+                    errors.push(VerificationError::in_function(
+                        FunctionDefinitionIndex::new(idx as TableIndex),
+                        VMStaticViolation::ResourceReturnedByReference(return_idx),
+                    ));
+                }
+            }
+        }
+        errors
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the checks bounding the work of the verifier that don't belong to a
+//! single pass. See `VerifierLimits`.
+use crate::config::VerifierLimits;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{CompiledModule, SignatureToken},
+    IndexKind,
+};
+
+/// Checks that no signature in `module` is nested deeper than `limits.max_signature_depth`.
+///
+/// Only the first signature found to be too deep is reported, since verification aborts anyway.
+pub fn check_signature_depth(
+    module: &CompiledModule,
+    limits: &VerifierLimits,
+) -> Option<VerificationError> {
+    let max_depth = limits.max_signature_depth?;
+    let too_deep = |token: &SignatureToken| signature_depth(token) > max_depth;
+
+    let type_signatures = module
+        .type_signatures()
+        .iter()
+        .position(|signature| too_deep(&signature.0))
+        .map(|idx| (IndexKind::TypeSignature, idx));
+    let function_signatures = || {
+        module
+            .function_signatures()
+            .iter()
+            .position(|signature| {
+                signature
+                    .return_types
+                    .iter()
+                    .chain(&signature.arg_types)
+                    .any(too_deep)
+            })
+            .map(|idx| (IndexKind::FunctionSignature, idx))
+    };
+    let locals_signatures = || {
+        module
+            .locals_signatures()
+            .iter()
+            .position(|signature| signature.0.iter().any(too_deep))
+            .map(|idx| (IndexKind::LocalsSignature, idx))
+    };
+
+    type_signatures
+        .or_else(function_signatures)
+        .or_else(locals_signatures)
+        .map(|(kind, idx)| {
+            VerificationError::new(kind, idx, VMStaticViolation::VerificationBudgetExceeded)
+        })
+}
+
+/// Returns the nesting depth of `token`. This doesn't recurse, so that it is safe to call on
+/// arbitrarily deep tokens.
+fn signature_depth(token: &SignatureToken) -> usize {
+    let mut max_depth = 0;
+    let mut stack = vec![(token, 1)];
+    while let Some((token, depth)) = stack.pop() {
+        max_depth = max_depth.max(depth);
+        match token {
+            SignatureToken::Struct(_, type_actuals) => {
+                stack.extend(type_actuals.iter().map(|actual| (actual, depth + 1)))
+            }
+            SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+                stack.push((inner, depth + 1))
+            }
+            SignatureToken::Bool
+            | SignatureToken::U64
+            | SignatureToken::String
+            | SignatureToken::ByteArray
+            | SignatureToken::Address
+            | SignatureToken::TypeParameter(_) => (),
+        }
+    }
+    max_depth
+}
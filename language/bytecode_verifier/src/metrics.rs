@@ -0,0 +1,82 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines the metrics the verifier can record while verifying a module (see
+//! `verify_module_with_metrics`), so that the cost of verification can be monitored and modules
+//! that are pathologically expensive to verify can be spotted.
+use crate::config::ConfigurablePass;
+use std::{collections::BTreeMap, time::Duration};
+use vm::file_format::FunctionDefinitionIndex;
+
+/// What a single pass cost, summed over every time it ran.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PassMetrics {
+    /// The number of times the pass ran: once for module-level passes, and once per code unit for
+    /// code unit passes.
+    pub runs: u64,
+    /// The time spent in the pass.
+    pub time: Duration,
+    /// The number of errors the pass found, warnings included.
+    pub errors: usize,
+}
+
+/// What verifying the code unit of a single function definition cost, over every code unit pass.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FunctionMetrics {
+    /// The time spent verifying the code unit.
+    pub time: Duration,
+    /// The number of errors found in the code unit, warnings included.
+    pub errors: usize,
+}
+
+/// The metrics recorded while verifying a module.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VerificationMetrics {
+    /// The time spent verifying the module, from start to finish.
+    pub total_time: Duration,
+    /// The metrics of every pass that ran.
+    pub passes: BTreeMap<ConfigurablePass, PassMetrics>,
+    /// The metrics of every function definition whose code unit was verified. Native functions
+    /// have no code unit, and are left out.
+    pub functions: BTreeMap<FunctionDefinitionIndex, FunctionMetrics>,
+}
+
+impl VerificationMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `pass` ran once, taking `time` and finding `errors` errors.
+    pub fn record_pass(&mut self, pass: ConfigurablePass, time: Duration, errors: usize) {
+        let metrics = self.passes.entry(pass).or_default();
+        metrics.runs += 1;
+        metrics.time += time;
+        metrics.errors += errors;
+    }
+
+    /// Records that verifying the code unit of the function definition at `idx` took `time` and
+    /// found `errors` errors.
+    pub fn record_function(&mut self, idx: FunctionDefinitionIndex, time: Duration, errors: usize) {
+        let metrics = self.functions.entry(idx).or_default();
+        metrics.time += time;
+        metrics.errors += errors;
+    }
+
+    /// Returns the total number of errors found by every pass.
+    pub fn total_errors(&self) -> usize {
+        self.passes.values().map(|metrics| metrics.errors).sum()
+    }
+
+    /// Returns the `n` function definitions whose code units took the longest to verify, slowest
+    /// first.
+    pub fn slowest_functions(&self, n: usize) -> Vec<(FunctionDefinitionIndex, &FunctionMetrics)> {
+        let mut functions: Vec<_> = self
+            .functions
+            .iter()
+            .map(|(idx, metrics)| (*idx, metrics))
+            .collect();
+        functions.sort_by(|(_, a), (_, b)| b.time.cmp(&a.time));
+        functions.truncate(n);
+        functions
+    }
+}
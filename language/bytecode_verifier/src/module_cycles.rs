@@ -0,0 +1,130 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module provides a checker for verifying that a set of modules doesn't contain dependency
+//! cycles. Publishing checks each module against modules published before it, so cycles can't
+//! arise there, but sets of modules loaded from elsewhere (e.g. genesis or a test harness) have no
+//! such guarantee. A module whose handles refer back to itself counts as a cycle as well.
+use petgraph::{algo::kosaraju_scc, graph::NodeIndex, Directed, Graph};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use types::language_storage::ModuleId;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::CompiledModule,
+    IndexKind,
+};
+
+pub struct DependencyCycleChecker<'a> {
+    modules: BTreeMap<ModuleId, &'a CompiledModule>,
+}
+
+impl<'a> DependencyCycleChecker<'a> {
+    /// Creates a checker for `modules`. If several modules have the same id, only the first one is
+    /// considered.
+    pub fn new(modules: impl IntoIterator<Item = &'a CompiledModule>) -> Self {
+        let mut module_map = BTreeMap::new();
+        for module in modules {
+            module_map.entry(module.self_id()).or_insert(module);
+        }
+        Self {
+            modules: module_map,
+        }
+    }
+
+    /// Returns one error for every group of modules that depend on each other, reporting a
+    /// shortest cycle through the module with the smallest id in the group.
+    ///
+    /// The cycle starts with that module, and each module in it depends on the next one, with the
+    /// last one depending on the first. The error is located at the module handle through which
+    /// the first module depends on the next one. Dependencies on modules outside of the set are
+    /// ignored.
+    pub fn verify(self) -> Vec<VerificationError> {
+        let mut graph: Graph<ModuleId, (), Directed, u32> = Graph::new();
+        let nodes: BTreeMap<&ModuleId, NodeIndex> = self
+            .modules
+            .keys()
+            .map(|module_id| (module_id, graph.add_node(module_id.clone())))
+            .collect();
+
+        // The first module handle through which each module depends on another.
+        let mut handles = BTreeMap::new();
+        for (module_id, module) in &self.modules {
+            let from = nodes[module_id];
+            for (idx, module_handle) in module
+                .module_handles()
+                .iter()
+                .enumerate()
+                .skip(CompiledModule::IMPLEMENTED_MODULE_INDEX as usize + 1)
+            {
+                let dependency_id = module.module_id_for_handle(module_handle);
+                if let Some(to) = nodes.get(&dependency_id) {
+                    if !handles.contains_key(&(from, *to)) {
+                        handles.insert((from, *to), idx);
+                        graph.add_edge(from, *to, ());
+                    }
+                }
+            }
+        }
+
+        // kosaraju_scc is iterative, which matters since the modules may be untrusted.
+        let mut cycles: Vec<_> = kosaraju_scc(&graph)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || graph.contains_edge(scc[0], scc[0]))
+            .map(|scc| {
+                let start = *scc
+                    .iter()
+                    .min_by_key(|node| &graph[**node])
+                    .expect("strongly connected components are not empty");
+                shortest_cycle(&graph, start, &scc.into_iter().collect())
+            })
+            .collect();
+        cycles.sort_by(|a, b| graph[a[0]].cmp(&graph[b[0]]));
+
+        cycles
+            .into_iter()
+            .map(|cycle| {
+                let next = *cycle.get(1).unwrap_or(&cycle[0]);
+                VerificationError::new(
+                    IndexKind::ModuleHandle,
+                    handles[&(cycle[0], next)],
+                    VMStaticViolation::CyclicModuleDependency(
+                        cycle.iter().map(|node| graph[*node].clone()).collect(),
+                    ),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns a shortest cycle from `start` back to itself that stays within `scc`, without repeating
+/// `start` at the end.
+fn shortest_cycle(
+    graph: &Graph<ModuleId, (), Directed, u32>,
+    start: NodeIndex,
+    scc: &BTreeSet<NodeIndex>,
+) -> Vec<NodeIndex> {
+    // Breadth-first search, remembering the node each node was first reached from.
+    let mut parents = BTreeMap::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        for successor in graph.neighbors(node) {
+            if successor == start {
+                let mut cycle = vec![node];
+                let mut current = node;
+                while current != start {
+                    current = parents[&current];
+                    cycle.push(current);
+                }
+                cycle.reverse();
+                return cycle;
+            }
+            if scc.contains(&successor) && !parents.contains_key(&successor) {
+                parents.insert(successor, node);
+                queue.push_back(successor);
+            }
+        }
+    }
+    unreachable!("every node of a strongly connected component is part of a cycle")
+}
@@ -0,0 +1,98 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a checker for verifying that every native function a module defines is
+//! permitted by a registry supplied by the caller. Without it, a native function the VM doesn't
+//! implement is only noticed when it is called and can't be dispatched.
+use std::collections::BTreeMap;
+use types::language_storage::ModuleId;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{CompiledModule, FunctionSignature},
+    views::ModuleView,
+    IndexKind,
+};
+
+/// The native functions modules are permitted to define, keyed by the module that defines them and
+/// their name, along with the signature they must be declared with.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NativeFunctionRegistry {
+    functions: BTreeMap<(ModuleId, String), FunctionSignature>,
+}
+
+impl NativeFunctionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Permits the module `module_id` to define a native function `name` with `signature`,
+    /// replacing any signature previously registered for it.
+    ///
+    /// Struct handle indexes in `signature` refer to the struct handles of the defining module.
+    pub fn insert(
+        &mut self,
+        module_id: ModuleId,
+        name: impl Into<String>,
+        signature: FunctionSignature,
+    ) -> Option<FunctionSignature> {
+        self.functions.insert((module_id, name.into()), signature)
+    }
+
+    /// Returns the signature the native function `name` of the module `module_id` must be
+    /// declared with, or `None` if it isn't permitted.
+    pub fn get(&self, module_id: &ModuleId, name: &str) -> Option<&FunctionSignature> {
+        self.functions.get(&(module_id.clone(), name.to_string()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.functions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+}
+
+pub struct NativeFunctionChecker<'a> {
+    module: &'a CompiledModule,
+    registry: &'a NativeFunctionRegistry,
+}
+
+impl<'a> NativeFunctionChecker<'a> {
+    pub fn new(module: &'a CompiledModule, registry: &'a NativeFunctionRegistry) -> Self {
+        Self { module, registry }
+    }
+
+    /// Returns an error for every native function definition that isn't in the registry, or that
+    /// is declared with another signature than the registry expects. Errors are located at the
+    /// function definition.
+    pub fn verify(self) -> Vec<VerificationError> {
+        let module_view = ModuleView::new(self.module);
+        let module_id = self.module.self_id();
+        module_view
+            .functions()
+            .enumerate()
+            .filter(|(_, function_definition_view)| function_definition_view.is_native())
+            .filter_map(|(idx, function_definition_view)| {
+                let err = match self
+                    .registry
+                    .get(&module_id, function_definition_view.name())
+                {
+                    None => VMStaticViolation::UnknownNativeFunction,
+                    Some(signature) => {
+                        if function_definition_view.signature().as_inner() == signature {
+                            return None;
+                        }
+                        VMStaticViolation::NativeFunctionSignatureMismatch
+                    }
+                };
+                Some(VerificationError::new(
+                    IndexKind::FunctionDefinition,
+                    idx,
+                    err,
+                ))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,97 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module verifies packages as a whole: every module of a package is verified on its own,
+//! and then against its dependencies, which are either modules of the package verified before it
+//! or modules already published.
+use crate::verifier::{verify_module_dependencies, VerifiedModule};
+use std::fmt;
+use types::language_storage::ModuleId;
+use vm::{
+    access::ModuleAccess,
+    errors::{has_errors, VerificationError},
+    package::{Package, PackageError, PackageManifest},
+};
+
+/// Why a package failed verification.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PackageVerificationError {
+    /// The package is malformed.
+    Package(PackageError),
+    /// A module of the package failed verification, on its own or against its dependencies.
+    Module {
+        module: ModuleId,
+        errors: Vec<VerificationError>,
+    },
+}
+
+impl fmt::Display for PackageVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackageVerificationError::Package(err) => write!(f, "{}", err),
+            PackageVerificationError::Module { module, errors } => write!(
+                f,
+                "module {:?} failed verification with {} errors",
+                module,
+                errors.len()
+            ),
+        }
+    }
+}
+
+/// A package whose modules have all been verified, on their own and against each other and their
+/// dependencies outside the package.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerifiedPackage {
+    manifest: PackageManifest,
+    /// The modules, in topological order.
+    modules: Vec<VerifiedModule>,
+}
+
+impl VerifiedPackage {
+    /// Verifies the modules of `package` in topological order, each against the modules of the
+    /// package verified before it and `deps`, the modules outside the package. Verification stops
+    /// at the first module that fails.
+    pub fn new<'a>(
+        package: Package,
+        deps: impl IntoIterator<Item = &'a VerifiedModule>,
+    ) -> Result<Self, PackageVerificationError> {
+        let deps: Vec<&VerifiedModule> = deps.into_iter().collect();
+        let manifest = package.manifest().clone();
+        let modules = package
+            .into_topological_order()
+            .map_err(PackageVerificationError::Package)?;
+
+        let mut verified: Vec<VerifiedModule> = vec![];
+        for module in modules {
+            let module_id = module.self_id();
+            let failed = |errors| PackageVerificationError::Module {
+                module: module_id.clone(),
+                errors,
+            };
+            let module = VerifiedModule::new(module).map_err(|(_, errors)| failed(errors))?;
+            let errors = verify_module_dependencies(&module, deps.iter().copied().chain(&verified));
+            if has_errors(&errors) {
+                return Err(failed(errors));
+            }
+            verified.push(module);
+        }
+        Ok(Self {
+            manifest,
+            modules: verified,
+        })
+    }
+
+    pub fn manifest(&self) -> &PackageManifest {
+        &self.manifest
+    }
+
+    /// Returns the modules, in topological order.
+    pub fn modules(&self) -> &[VerifiedModule] {
+        &self.modules
+    }
+
+    pub fn into_inner(self) -> (PackageManifest, Vec<VerifiedModule>) {
+        (self.manifest, self.modules)
+    }
+}
@@ -0,0 +1,295 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module validates a package for publishing as a unit, rather than module by module:
+//! - every module is published under the address of the package, and under an id no published
+//!   module or dependency of the package has;
+//! - every dependency of a module is either a module of the package, or pinned by the manifest to
+//!   the digest of the module that is actually published;
+//! - the modules verify, on their own and against each other and their pinned dependencies;
+//! - the package stays within `PublishLimits`.
+//!
+//! All these are checked even after one fails, so that a single `PublishReport` lists everything
+//! that would keep the package from being published.
+use crate::verifier::{verify_module_dependencies_in, ModuleCache, VerifiedModule};
+use crypto::HashValue;
+use failure::prelude::*;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+use types::{
+    account_address::AccountAddress, language_storage::ModuleId,
+    transaction::MAX_TRANSACTION_SIZE_IN_BYTES,
+};
+use vm::{
+    access::ModuleAccess,
+    errors::{has_errors, VerificationError},
+    file_format::CompiledModule,
+    package::{module_digest, Package, PackageError},
+};
+
+/// Bounds on the size of a package. A bound of `None` means there is no bound.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PublishLimits {
+    /// The maximum size of a single module in its binary form, in bytes.
+    pub max_module_size: Option<usize>,
+    /// The maximum combined size of the modules of a package in their binary form, in bytes.
+    pub max_package_size: Option<usize>,
+}
+
+/// The limits of a package published by a single transaction.
+impl Default for PublishLimits {
+    fn default() -> Self {
+        Self {
+            max_module_size: None,
+            max_package_size: Some(MAX_TRANSACTION_SIZE_IN_BYTES),
+        }
+    }
+}
+
+/// A reason a package can't be published.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PublishIssue {
+    /// The modules of the package can't be ordered.
+    Malformed(PackageError),
+    /// The module is not published under the address of the package.
+    AddressMismatch {
+        module: ModuleId,
+        package_address: AccountAddress,
+    },
+    /// A module with this id is already published.
+    AlreadyPublished(ModuleId),
+    /// The manifest pins a dependency that is a module of the package itself.
+    PinnedPackageModule(ModuleId),
+    /// The module depends on a module that is neither in the package nor pinned by the manifest.
+    UnpinnedDependency {
+        module: ModuleId,
+        dependency: ModuleId,
+    },
+    /// The manifest pins a dependency that isn't published.
+    MissingDependency(ModuleId),
+    /// The published version of a pinned dependency is not the one the manifest pins.
+    DigestMismatch {
+        dependency: ModuleId,
+        pinned: HashValue,
+        published: HashValue,
+    },
+    /// The module fails verification, on its own or against its dependencies.
+    VerificationFailed {
+        module: ModuleId,
+        errors: Vec<VerificationError>,
+    },
+    ModuleTooLarge {
+        module: ModuleId,
+        size: usize,
+        max: usize,
+    },
+    PackageTooLarge {
+        size: usize,
+        max: usize,
+    },
+}
+
+impl fmt::Display for PublishIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PublishIssue::Malformed(err) => write!(f, "{}", err),
+            PublishIssue::AddressMismatch {
+                module,
+                package_address,
+            } => write!(
+                f,
+                "module {:?} is not published under the package address {}",
+                module, package_address
+            ),
+            PublishIssue::AlreadyPublished(module) => {
+                write!(f, "module {:?} is already published", module)
+            }
+            PublishIssue::PinnedPackageModule(module) => {
+                write!(f, "module {:?} is both in the package and pinned", module)
+            }
+            PublishIssue::UnpinnedDependency { module, dependency } => write!(
+                f,
+                "module {:?} depends on {:?}, which is not pinned",
+                module, dependency
+            ),
+            PublishIssue::MissingDependency(dependency) => {
+                write!(f, "dependency {:?} is not published", dependency)
+            }
+            PublishIssue::DigestMismatch {
+                dependency,
+                pinned,
+                published,
+            } => write!(
+                f,
+                "dependency {:?} is pinned to {:x} but {:x} is published",
+                dependency, pinned, published
+            ),
+            PublishIssue::VerificationFailed { module, errors } => write!(
+                f,
+                "module {:?} failed verification with {} errors",
+                module,
+                errors.len()
+            ),
+            PublishIssue::ModuleTooLarge { module, size, max } => write!(
+                f,
+                "module {:?} is {} bytes, over the limit of {}",
+                module, size, max
+            ),
+            PublishIssue::PackageTooLarge { size, max } => {
+                write!(f, "package is {} bytes, over the limit of {}", size, max)
+            }
+        }
+    }
+}
+
+/// The outcome of validating a package for publishing.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PublishReport {
+    /// Every issue, grouped by check in the order they are listed in the module documentation.
+    pub issues: Vec<PublishIssue>,
+}
+
+impl PublishReport {
+    /// Returns true if the package can be published.
+    pub fn is_publishable(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for PublishReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_publishable() {
+            return write!(f, "publishable");
+        }
+        let issues: Vec<_> = self.issues.iter().map(|issue| issue.to_string()).collect();
+        write!(f, "{}", issues.join(", "))
+    }
+}
+
+/// Validates `package` for publishing, with its dependencies looked up in `published`.
+///
+/// Fails only if a module can't be serialized to measure and digest it.
+pub fn validate_publish<C: ModuleCache>(
+    package: &Package,
+    published: &C,
+    limits: &PublishLimits,
+) -> Result<PublishReport> {
+    let mut issues = vec![];
+    let manifest = package.manifest();
+    let modules = match package.topological_order() {
+        Ok(modules) => modules,
+        Err(err) => {
+            issues.push(PublishIssue::Malformed(err));
+            package.modules().iter().collect()
+        }
+    };
+    let module_ids: BTreeSet<_> = modules.iter().map(|module| module.self_id()).collect();
+
+    // Collisions.
+    for module in &modules {
+        let module_id = module.self_id();
+        if module.address() != &manifest.address {
+            issues.push(PublishIssue::AddressMismatch {
+                module: module_id.clone(),
+                package_address: manifest.address,
+            });
+        }
+        if published.get_module(&module_id).is_some() {
+            issues.push(PublishIssue::AlreadyPublished(module_id));
+        }
+    }
+    for dependency in &manifest.dependencies {
+        if module_ids.contains(&dependency.module) {
+            issues.push(PublishIssue::PinnedPackageModule(dependency.module.clone()));
+        }
+    }
+
+    // Dependency resolution.
+    let pinned: BTreeSet<_> = manifest
+        .dependencies
+        .iter()
+        .map(|dependency| &dependency.module)
+        .collect();
+    for module in &modules {
+        for handle in module.module_handles().iter().skip(1) {
+            let dependency = module.module_id_for_handle(handle);
+            if !module_ids.contains(&dependency) && !pinned.contains(&dependency) {
+                issues.push(PublishIssue::UnpinnedDependency {
+                    module: module.self_id(),
+                    dependency,
+                });
+            }
+        }
+    }
+    for dependency in &manifest.dependencies {
+        match published.get_module(&dependency.module) {
+            Some(published_module) => {
+                let digest = module_digest(published_module.as_module())?;
+                if digest != dependency.digest {
+                    issues.push(PublishIssue::DigestMismatch {
+                        dependency: dependency.module.clone(),
+                        pinned: dependency.digest,
+                        published: digest,
+                    });
+                }
+            }
+            None => issues.push(PublishIssue::MissingDependency(dependency.module.clone())),
+        }
+    }
+
+    // Verification. Dependencies are checked against every module of the package, even those that
+    // fail, so that a failing module is only reported once.
+    let mut dependencies: BTreeMap<ModuleId, &CompiledModule> = BTreeMap::new();
+    for module in &modules {
+        for handle in module.module_handles().iter().skip(1) {
+            let dependency = module.module_id_for_handle(handle);
+            if let Some(published_module) = published.get_module(&dependency) {
+                dependencies.insert(dependency, published_module.as_module());
+            }
+        }
+    }
+    for module in &modules {
+        dependencies.insert(module.self_id(), module);
+    }
+    for module in &modules {
+        let errors = match VerifiedModule::new((*module).clone()) {
+            Ok(module) => verify_module_dependencies_in(&module, &dependencies),
+            Err((_, errors)) => errors,
+        };
+        if has_errors(&errors) {
+            issues.push(PublishIssue::VerificationFailed {
+                module: module.self_id(),
+                errors,
+            });
+        }
+    }
+
+    // Size limits.
+    let mut package_size = 0;
+    for module in &modules {
+        let mut binary = vec![];
+        module.serialize(&mut binary)?;
+        package_size += binary.len();
+        if let Some(max) = limits.max_module_size {
+            if binary.len() > max {
+                issues.push(PublishIssue::ModuleTooLarge {
+                    module: module.self_id(),
+                    size: binary.len(),
+                    max,
+                });
+            }
+        }
+    }
+    if let Some(max) = limits.max_package_size {
+        if package_size > max {
+            issues.push(PublishIssue::PackageTooLarge {
+                size: package_size,
+                max,
+            });
+        }
+    }
+
+    Ok(PublishReport { issues })
+}
@@ -0,0 +1,128 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements the analysis of the loops of a code unit, and a checker for verifying
+//! that control flow is reducible: that every cycle can only be entered through a single block,
+//! its header. Analyses built on the loop structure of code units (see `dominators::LoopNest`)
+//! assume reducible control flow, since irreducible cycles don't form natural loops.
+use crate::{
+    control_flow_graph::{BlockId, ControlFlowGraph, VMControlFlowGraph},
+    dominators::DominatorTree,
+};
+use std::collections::BTreeMap;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{CompiledModule, FunctionDefinitionIndex, TableIndex},
+};
+
+/// The edges of a control flow graph that close cycles, split by whether they go back to a loop
+/// header.
+///
+/// The edges closing cycles are the retreating edges of a depth-first traversal from the entry
+/// block. A retreating edge whose target dominates its source is a back edge into a loop header;
+/// control flow is reducible if and only if every retreating edge is a back edge. Blocks that
+/// can't be reached from the entry block are left out.
+pub struct LoopAnalysis {
+    /// The back edges, as (source, target) pairs in ascending order.
+    back_edges: Vec<(BlockId, BlockId)>,
+    /// The retreating edges that aren't back edges, as (source, target) pairs in ascending order.
+    irreducible_edges: Vec<(BlockId, BlockId)>,
+}
+
+impl LoopAnalysis {
+    pub fn new(cfg: &dyn ControlFlowGraph) -> Self {
+        let dominators = DominatorTree::new(cfg);
+        let order: BTreeMap<BlockId, usize> = dominators
+            .reverse_postorder()
+            .iter()
+            .enumerate()
+            .map(|(idx, block_id)| (*block_id, idx))
+            .collect();
+
+        let mut back_edges = vec![];
+        let mut irreducible_edges = vec![];
+        for block_id in dominators.reverse_postorder() {
+            for successor in cfg.successors(block_id) {
+                // Only retreating edges go back in reverse postorder.
+                if order[successor] > order[block_id] {
+                    continue;
+                }
+                if dominators.dominates(*successor, *block_id) {
+                    back_edges.push((*block_id, *successor));
+                } else {
+                    irreducible_edges.push((*block_id, *successor));
+                }
+            }
+        }
+        back_edges.sort();
+        irreducible_edges.sort();
+        Self {
+            back_edges,
+            irreducible_edges,
+        }
+    }
+
+    /// Returns the back edges, as (source, target) pairs in ascending order.
+    pub fn back_edges(&self) -> &[(BlockId, BlockId)] {
+        &self.back_edges
+    }
+
+    /// Returns the headers of the loops, i.e. the targets of back edges, in ascending order.
+    pub fn loop_headers(&self) -> Vec<BlockId> {
+        let mut headers: Vec<_> = self.back_edges.iter().map(|(_, header)| *header).collect();
+        headers.sort();
+        headers.dedup();
+        headers
+    }
+
+    /// Returns the edges that enter a cycle other than through a loop header, as (source, target)
+    /// pairs in ascending order.
+    pub fn irreducible_edges(&self) -> &[(BlockId, BlockId)] {
+        &self.irreducible_edges
+    }
+
+    /// Returns true if every cycle has a single entry block.
+    pub fn is_reducible(&self) -> bool {
+        self.irreducible_edges.is_empty()
+    }
+}
+
+pub struct ReducibilityChecker<'a> {
+    module: &'a CompiledModule,
+}
+
+impl<'a> ReducibilityChecker<'a> {
+    pub fn new(module: &'a CompiledModule) -> Self {
+        Self { module }
+    }
+
+    /// Returns an error for every edge that enters a cycle other than through a loop header,
+    /// located at the last instruction of the block the edge leaves.
+    pub fn verify(self) -> Vec<VerificationError> {
+        self.module
+            .function_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, function_definition)| !function_definition.is_native())
+            .flat_map(|(idx, function_definition)| {
+                let idx = FunctionDefinitionIndex::new(idx as TableIndex);
+                let cfg = VMControlFlowGraph::new(&function_definition.code.code);
+                // A block may leave through more than one irreducible edge, but it is reported
+                // once.
+                let mut offsets: Vec<_> = LoopAnalysis::new(&cfg)
+                    .irreducible_edges()
+                    .iter()
+                    .map(|(source, _)| cfg.block_end(source) as usize)
+                    .collect();
+                offsets.dedup();
+                offsets.into_iter().map(move |offset| {
+                    VerificationError::in_function(
+                        idx,
+                        VMStaticViolation::IrreducibleControlFlow(offset),
+                    )
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,108 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module defines a structured account of how a module fared in every pass of the verifier
+//! (see `VerifiedModule::new_with_report`), for tooling that presents verification results pass by
+//! pass instead of as a flat list of errors.
+use crate::{config::ConfigurablePass, metrics::VerificationMetrics, verifier::ErrorsByPass};
+use std::fmt;
+use vm::errors::{has_errors, VerificationError};
+
+/// The outcome of a single pass.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum PassStatus {
+    /// The pass ran and found no errors, though it may have found warnings.
+    Passed,
+    /// The pass ran and found errors that fail verification.
+    Failed,
+    /// The pass didn't run: it is disabled, a pass it depends on failed, or there was nothing for
+    /// it to check (e.g. code unit passes on a module without function definitions).
+    Skipped,
+}
+
+impl fmt::Display for PassStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self {
+            PassStatus::Passed => "passed",
+            PassStatus::Failed => "failed",
+            PassStatus::Skipped => "skipped",
+        };
+        f.write_str(status)
+    }
+}
+
+/// The outcome of a single pass, along with the errors it found.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PassReport {
+    pub pass: ConfigurablePass,
+    pub status: PassStatus,
+    /// The errors the pass found, warnings included, in the order they were found.
+    pub errors: Vec<VerificationError>,
+}
+
+/// The outcome of every pass of the verifier on a module, in the order the passes run.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VerificationReport {
+    pub passes: Vec<PassReport>,
+}
+
+impl VerificationReport {
+    /// Builds the report of a verification that found `errors`, telling the passes that ran from
+    /// those that didn't by the passes `metrics` were recorded for.
+    pub(crate) fn new(errors: &ErrorsByPass, metrics: &VerificationMetrics) -> Self {
+        let passes = ConfigurablePass::all()
+            .iter()
+            .map(|pass| {
+                let errors = errors.for_pass(*pass);
+                let status = if !metrics.passes.contains_key(pass) {
+                    PassStatus::Skipped
+                } else if has_errors(&errors) {
+                    PassStatus::Failed
+                } else {
+                    PassStatus::Passed
+                };
+                PassReport {
+                    pass: *pass,
+                    status,
+                    errors,
+                }
+            })
+            .collect();
+        Self { passes }
+    }
+
+    /// Returns true if no pass failed.
+    pub fn passed(&self) -> bool {
+        self.passes
+            .iter()
+            .all(|report| report.status != PassStatus::Failed)
+    }
+
+    /// Returns the outcome of `pass`.
+    pub fn status(&self, pass: ConfigurablePass) -> PassStatus {
+        self.passes
+            .iter()
+            .find(|report| report.pass == pass)
+            .map_or(PassStatus::Skipped, |report| report.status)
+    }
+
+    /// Returns the errors of every pass, in the order of the passes.
+    pub fn errors(&self) -> Vec<VerificationError> {
+        self.passes
+            .iter()
+            .flat_map(|report| report.errors.iter().cloned())
+            .collect()
+    }
+}
+
+impl fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for report in &self.passes {
+            writeln!(f, "{:?}: {}", report.pass, report.status)?;
+            for err in &report.errors {
+                writeln!(f, "    {}", err)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -34,11 +34,11 @@ impl<'a> ResourceTransitiveChecker<'a> {
                                 .contains_nominal_resource(struct_def.type_formals())
                         });
                         if any_resource_field {
-                            errors.push(VerificationError {
-                                kind: IndexKind::StructDefinition,
+                            errors.push(VerificationError::new(
+                                IndexKind::StructDefinition,
                                 idx,
-                                err: VMStaticViolation::InvalidResourceField,
-                            });
+                                VMStaticViolation::InvalidResourceField,
+                            ));
                         }
                     }
                 }
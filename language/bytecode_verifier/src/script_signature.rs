@@ -0,0 +1,50 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a checker for verifying that the main function of a script can be
+//! called by a transaction. Transactions can only pass arguments of a few primitive types, and have
+//! no use for return values.
+use vm::{
+    access::ScriptAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::CompiledScript,
+    IndexKind,
+};
+
+pub struct ScriptSignatureChecker<'a> {
+    script: &'a CompiledScript,
+}
+
+impl<'a> ScriptSignatureChecker<'a> {
+    pub fn new(script: &'a CompiledScript) -> Self {
+        Self { script }
+    }
+
+    /// Returns every problem with the signature of `main`, all located at that signature.
+    pub fn verify(self) -> Vec<VerificationError> {
+        let function_handle = self.script.function_handle_at(self.script.main().function);
+        let signature_idx = function_handle.signature;
+        let function_signature = self.script.function_signature_at(signature_idx);
+
+        let mut errors: Vec<_> = (0..function_signature.return_types.len())
+            .map(VMStaticViolation::InvalidMainFunctionReturn)
+            .collect();
+        errors.extend(
+            function_signature
+                .arg_types
+                .iter()
+                .enumerate()
+                .filter(|(_, arg_type)| !arg_type.is_primitive())
+                .map(|(arg_idx, arg_type)| {
+                    VMStaticViolation::InvalidMainFunctionArgument(arg_idx, arg_type.clone())
+                }),
+        );
+
+        errors
+            .into_iter()
+            .map(|err| {
+                VerificationError::new(IndexKind::FunctionSignature, signature_idx.0 as usize, err)
+            })
+            .collect()
+    }
+}
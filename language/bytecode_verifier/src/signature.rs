@@ -6,7 +6,7 @@
 //! top-level in all tokens.  Additionally, references cannot occur at all in field types.
 use vm::{
     access::ModuleAccess,
-    errors::{VMStaticViolation, VerificationError},
+    errors::{VMStaticViolation, VerificationError, WithContext},
     file_format::{CompiledModule, SignatureToken},
     views::{
         FieldDefinitionView, FunctionSignatureView, LocalsSignatureView, ModuleView,
@@ -47,10 +47,9 @@ impl<'a> SignatureChecker<'a> {
             .fields()
             .enumerate()
             .filter_map(move |(idx, view)| {
-                check_signature_refs(&view).map(move |err| VerificationError {
-                    kind: IndexKind::FieldDefinition,
-                    idx,
-                    err,
+                check_signature_refs(&view).map(move |err| {
+                    VerificationError::new(IndexKind::FieldDefinition, idx, err)
+                        .with_context(|| format!("field {}", view.name()))
                 })
             })
             .collect();
@@ -69,41 +68,57 @@ impl<'a> SignatureChecker<'a> {
             .map(move |(idx, view)| {
                 view.check_signatures()
                     .into_iter()
-                    .map(move |err| VerificationError { kind, idx, err })
+                    .map(move |(context, err)| {
+                        let err = VerificationError::new(kind, idx, err);
+                        match context {
+                            Some(context) => err.with_context(|| context),
+                            None => err,
+                        }
+                    })
             })
             .flatten()
             .collect()
     }
 }
 
+/// Checks the tokens of a signature, returning each violation along with the position of the
+/// offending token within the signature, if it has more than one token.
 trait SignatureCheck {
-    fn check_signatures(&self) -> Vec<VMStaticViolation>;
+    fn check_signatures(&self) -> Vec<(Option<String>, VMStaticViolation)>;
 }
 
 impl<'a, T: ModuleAccess> SignatureCheck for FunctionSignatureView<'a, T> {
-    fn check_signatures(&self) -> Vec<VMStaticViolation> {
+    fn check_signatures(&self) -> Vec<(Option<String>, VMStaticViolation)> {
         self.return_tokens()
-            .filter_map(|token| check_structure(token.as_inner()))
-            .chain(
-                self.arg_tokens()
-                    .filter_map(|token| check_structure(token.as_inner())),
-            )
+            .enumerate()
+            .filter_map(|(idx, token)| {
+                check_structure(token.as_inner())
+                    .map(|err| (Some(format!("return type {}", idx)), err))
+            })
+            .chain(self.arg_tokens().enumerate().filter_map(|(idx, token)| {
+                check_structure(token.as_inner())
+                    .map(|err| (Some(format!("argument {}", idx)), err))
+            }))
             .collect()
     }
 }
 
 impl<'a, T: ModuleAccess> SignatureCheck for TypeSignatureView<'a, T> {
-    fn check_signatures(&self) -> Vec<VMStaticViolation> {
+    fn check_signatures(&self) -> Vec<(Option<String>, VMStaticViolation)> {
         check_structure(self.token().as_inner())
+            .map(|err| (None, err))
             .into_iter()
             .collect()
     }
 }
 
 impl<'a, T: ModuleAccess> SignatureCheck for LocalsSignatureView<'a, T> {
-    fn check_signatures(&self) -> Vec<VMStaticViolation> {
+    fn check_signatures(&self) -> Vec<(Option<String>, VMStaticViolation)> {
         self.tokens()
-            .filter_map(|token| check_structure(token.as_inner()))
+            .enumerate()
+            .filter_map(|(idx, token)| {
+                check_structure(token.as_inner()).map(|err| (Some(format!("local {}", idx)), err))
+            })
             .collect()
     }
 }
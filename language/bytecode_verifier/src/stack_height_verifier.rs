@@ -0,0 +1,46 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a checker for verifying that the operand stack never grows higher than
+//! a limit within a function, so that runtimes can allocate bounded stacks. It relies on the stack
+//! usage verifier: since every basic block leaves the stack as high as it found it, every block
+//! starts with an empty stack, and the height of the stack only depends on the current block.
+use crate::{
+    config::StackHeightLimit,
+    control_flow_graph::{ControlFlowGraph, VMControlFlowGraph},
+    stack_usage_verifier::StackUsageVerifier,
+};
+use vm::{
+    errors::VMStaticViolation,
+    file_format::{CompiledModule, FunctionDefinition},
+};
+
+pub struct StackHeightVerifier;
+
+impl StackHeightVerifier {
+    pub fn verify(
+        module: &CompiledModule,
+        function_definition: &FunctionDefinition,
+        cfg: &VMControlFlowGraph,
+        limit: StackHeightLimit,
+    ) -> Vec<VMStaticViolation> {
+        let max_height = match limit {
+            StackHeightLimit::Unlimited => return vec![],
+            StackHeightLimit::Fixed(max_height) => max_height,
+            StackHeightLimit::Declared => function_definition.code.max_stack_size,
+        };
+        let stack_usage = StackUsageVerifier::new(module, function_definition);
+        let code = &function_definition.code.code;
+
+        for block_id in cfg.blocks() {
+            let mut height = 0;
+            for offset in cfg.instr_indexes(&block_id) {
+                height += stack_usage.instruction_effect(&code[offset as usize]);
+                if height > i32::from(max_height) {
+                    return vec![VMStaticViolation::StackHeightLimitExceeded(offset as usize)];
+                }
+            }
+        }
+        vec![]
+    }
+}
@@ -22,16 +22,22 @@ pub struct StackUsageVerifier<'a> {
 }
 
 impl<'a> StackUsageVerifier<'a> {
+    pub(crate) fn new(
+        module: &'a CompiledModule,
+        function_definition: &'a FunctionDefinition,
+    ) -> Self {
+        Self {
+            module,
+            function_definition_view: FunctionDefinitionView::new(module, function_definition),
+        }
+    }
+
     pub fn verify(
         module: &'a CompiledModule,
         function_definition: &'a FunctionDefinition,
         cfg: &'a VMControlFlowGraph,
     ) -> Vec<VMStaticViolation> {
-        let function_definition_view = FunctionDefinitionView::new(module, function_definition);
-        let verifier = Self {
-            module,
-            function_definition_view,
-        };
+        let verifier = Self::new(module, function_definition);
 
         let mut errors = vec![];
         for block_id in cfg.blocks() {
@@ -67,27 +73,17 @@ impl<'a> StackUsageVerifier<'a> {
         }
     }
 
-    fn instruction_effect(&self, instruction: &Bytecode) -> i32 {
+    /// Returns by how much `instruction` changes the height of the stack.
+    pub(crate) fn instruction_effect(&self, instruction: &Bytecode) -> i32 {
+        if let Some(net) = instruction.stack_effect().net() {
+            return net as i32;
+        }
         match instruction {
-            Bytecode::Pop | Bytecode::BrTrue(_) | Bytecode::BrFalse(_) | Bytecode::StLoc(_) => -1,
-
             Bytecode::Ret => {
                 let return_count = self.function_definition_view.signature().return_count() as i32;
                 -return_count
             }
 
-            Bytecode::Branch(_) | Bytecode::MutBorrowField(_) | Bytecode::ImmBorrowField(_) => 0,
-
-            Bytecode::LdConst(_)
-            | Bytecode::LdAddr(_)
-            | Bytecode::LdStr(_)
-            | Bytecode::LdTrue
-            | Bytecode::LdFalse
-            | Bytecode::CopyLoc(_)
-            | Bytecode::MoveLoc(_)
-            | Bytecode::MutBorrowLoc(_)
-            | Bytecode::ImmBorrowLoc(_) => 1,
-
             Bytecode::Call(idx, _) => {
                 let function_handle = self.module.function_handle_at(*idx);
                 let signature = self.module.function_signature_at(function_handle.signature);
@@ -118,45 +114,7 @@ impl<'a> StackUsageVerifier<'a> {
                 num_fields - 1
             }
 
-            Bytecode::ReadRef => 0,
-
-            Bytecode::WriteRef => -2,
-
-            Bytecode::Add
-            | Bytecode::Sub
-            | Bytecode::Mul
-            | Bytecode::Mod
-            | Bytecode::Div
-            | Bytecode::BitOr
-            | Bytecode::BitAnd
-            | Bytecode::Xor
-            | Bytecode::Or
-            | Bytecode::And
-            | Bytecode::Eq
-            | Bytecode::Neq
-            | Bytecode::Lt
-            | Bytecode::Gt
-            | Bytecode::Le
-            | Bytecode::Ge
-            | Bytecode::Abort => -1,
-
-            Bytecode::Not => 0,
-
-            Bytecode::FreezeRef => 0,
-            Bytecode::Exists(_, _) => 0,
-            Bytecode::BorrowGlobal(_, _) => 0,
-            Bytecode::MoveFrom(_, _) => 0,
-            Bytecode::MoveToSender(_, _) => -1,
-
-            Bytecode::GetTxnGasUnitPrice
-            | Bytecode::GetTxnMaxGasUnits
-            | Bytecode::GetGasRemaining
-            | Bytecode::GetTxnPublicKey
-            | Bytecode::GetTxnSequenceNumber
-            | Bytecode::GetTxnSenderAddress => 1,
-            Bytecode::CreateAccount => -1,
-
-            Bytecode::LdByteArray(_) => 1,
+            _ => unreachable!("{:?} has a fixed stack effect", instruction),
         }
     }
 }
@@ -4,11 +4,14 @@
 //! This module provides a checker for verifing that struct definitions in a module are not
 //! recursive. Since the module dependency graph is acylic by construction, applying this checker to
 //! each module in isolation guarantees that there is no structural recursion globally.
-use petgraph::{algo::toposort, Directed, Graph};
+use petgraph::{
+    algo::{kosaraju_scc, toposort},
+    Directed, Graph,
+};
 use std::collections::BTreeMap;
 use vm::{
     access::ModuleAccess,
-    errors::{VMStaticViolation, VerificationError},
+    errors::{VMStaticViolation, VerificationError, WithContext},
     file_format::{CompiledModule, StructDefinitionIndex, StructHandleIndex, TableIndex},
     internals::ModuleIndex,
     views::StructDefinitionView,
@@ -36,13 +39,27 @@ impl<'a> RecursiveStructDefChecker<'a> {
                 // Is the result of this useful elsewhere?
                 vec![]
             }
-            Err(cycle) => {
-                let sd_idx = graph[cycle.node_id()];
-                vec![VerificationError {
-                    kind: IndexKind::StructDefinition,
-                    idx: sd_idx.into_index(),
-                    err: VMStaticViolation::RecursiveStructDef,
-                }]
+            Err(_) => {
+                // Report the struct definition with the lowest index among all those that are part
+                // of a cycle, so that the error doesn't depend on the order the graph is traversed
+                // in. kosaraju_scc is iterative as well.
+                let sd_idx = kosaraju_scc(&graph)
+                    .into_iter()
+                    .filter(|scc| scc.len() > 1 || graph.contains_edge(scc[0], scc[0]))
+                    .flatten()
+                    .map(|node| graph[node])
+                    .min()
+                    .expect("toposort should only fail if there is a cycle");
+                let struct_def = self.module.struct_def_at(sd_idx);
+                let name = StructDefinitionView::new(self.module, struct_def)
+                    .handle()
+                    .name();
+                vec![VerificationError::new(
+                    IndexKind::StructDefinition,
+                    sd_idx.into_index(),
+                    VMStaticViolation::RecursiveStructDef,
+                )
+                .with_context(|| format!("struct {}", name))]
             }
         }
     }
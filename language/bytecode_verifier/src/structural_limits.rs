@@ -0,0 +1,177 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements a checker for verifying that a module stays within the bounds of
+//! `StructuralLimits`.
+use crate::{
+    config::StructuralLimits,
+    control_flow_graph::{ControlFlowGraph, VMControlFlowGraph},
+};
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{CompiledModule, FunctionDefinitionIndex, SignatureToken, TableIndex},
+    IndexKind,
+};
+
+pub struct StructuralLimitsChecker<'a> {
+    module: &'a CompiledModule,
+    limits: &'a StructuralLimits,
+}
+
+impl<'a> StructuralLimitsChecker<'a> {
+    pub fn new(module: &'a CompiledModule, limits: &'a StructuralLimits) -> Self {
+        Self { module, limits }
+    }
+
+    pub fn verify(self) -> Vec<VerificationError> {
+        let mut errors = vec![];
+        errors.append(&mut self.verify_type_parameters());
+        errors.append(&mut self.verify_type_instantiation_depth());
+        errors.append(&mut self.verify_struct_fields());
+        errors.append(&mut self.verify_basic_blocks());
+        errors
+    }
+
+    fn verify_type_parameters(&self) -> Vec<VerificationError> {
+        let max = match self.limits.max_type_parameters {
+            Some(max) => max,
+            None => return vec![],
+        };
+        let struct_handles = self
+            .module
+            .struct_handles()
+            .iter()
+            .enumerate()
+            .filter(|(_, struct_handle)| struct_handle.type_formals.len() > max)
+            .map(|(idx, _)| (IndexKind::StructHandle, idx));
+        let function_signatures = self
+            .module
+            .function_signatures()
+            .iter()
+            .enumerate()
+            .filter(|(_, signature)| signature.type_formals.len() > max)
+            .map(|(idx, _)| (IndexKind::FunctionSignature, idx));
+        struct_handles
+            .chain(function_signatures)
+            .map(|(kind, idx)| {
+                VerificationError::new(kind, idx, VMStaticViolation::TooManyTypeParameters)
+            })
+            .collect()
+    }
+
+    fn verify_type_instantiation_depth(&self) -> Vec<VerificationError> {
+        let max = match self.limits.max_type_instantiation_depth {
+            Some(max) => max,
+            None => return vec![],
+        };
+        let too_deep = |token: &SignatureToken| instantiation_depth(token) > max;
+
+        let type_signatures = self
+            .module
+            .type_signatures()
+            .iter()
+            .enumerate()
+            .filter(|(_, signature)| too_deep(&signature.0))
+            .map(|(idx, _)| (IndexKind::TypeSignature, idx));
+        let function_signatures = self
+            .module
+            .function_signatures()
+            .iter()
+            .enumerate()
+            .filter(|(_, signature)| {
+                signature
+                    .return_types
+                    .iter()
+                    .chain(&signature.arg_types)
+                    .any(too_deep)
+            })
+            .map(|(idx, _)| (IndexKind::FunctionSignature, idx));
+        let locals_signatures = self
+            .module
+            .locals_signatures()
+            .iter()
+            .enumerate()
+            .filter(|(_, signature)| signature.0.iter().any(too_deep))
+            .map(|(idx, _)| (IndexKind::LocalsSignature, idx));
+        type_signatures
+            .chain(function_signatures)
+            .chain(locals_signatures)
+            .map(|(kind, idx)| {
+                VerificationError::new(kind, idx, VMStaticViolation::TypeInstantiationTooDeep)
+            })
+            .collect()
+    }
+
+    fn verify_struct_fields(&self) -> Vec<VerificationError> {
+        let max = match self.limits.max_struct_fields {
+            Some(max) => max,
+            None => return vec![],
+        };
+        self.module
+            .struct_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, struct_def)| {
+                // Native structs have no fields.
+                struct_def
+                    .declared_field_count()
+                    .map_or(false, |field_count| field_count as usize > max)
+            })
+            .map(|(idx, _)| {
+                VerificationError::new(
+                    IndexKind::StructDefinition,
+                    idx,
+                    VMStaticViolation::TooManyStructFields,
+                )
+            })
+            .collect()
+    }
+
+    fn verify_basic_blocks(&self) -> Vec<VerificationError> {
+        let max = match self.limits.max_basic_blocks {
+            Some(max) => max,
+            None => return vec![],
+        };
+        self.module
+            .function_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, function_def)| {
+                !function_def.is_native()
+                    && VMControlFlowGraph::new(&function_def.code.code).num_blocks() as usize > max
+            })
+            .map(|(idx, _)| {
+                VerificationError::in_function(
+                    FunctionDefinitionIndex::new(idx as TableIndex),
+                    VMStaticViolation::TooManyBasicBlocks,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Returns the number of struct instantiations `token` is nested in at most. This doesn't recurse,
+/// so that it is safe to call on arbitrarily deep tokens.
+fn instantiation_depth(token: &SignatureToken) -> usize {
+    let mut max_depth = 0;
+    let mut stack = vec![(token, 0)];
+    while let Some((token, depth)) = stack.pop() {
+        max_depth = max_depth.max(depth);
+        match token {
+            SignatureToken::Struct(_, type_actuals) => {
+                stack.extend(type_actuals.iter().map(|actual| (actual, depth + 1)))
+            }
+            SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+                stack.push((inner, depth))
+            }
+            SignatureToken::Bool
+            | SignatureToken::U64
+            | SignatureToken::String
+            | SignatureToken::ByteArray
+            | SignatureToken::Address
+            | SignatureToken::TypeParameter(_) => (),
+        }
+    }
+    max_depth
+}
@@ -0,0 +1,88 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module reconstructs counterexample traces for violations found in code units: the code
+//! offsets of the instructions along a path from the start of the function to the offending
+//! instruction, so that a violation can be understood without reverse-engineering the analysis
+//! that found it.
+//!
+//! A trace follows the blocks through which the analysis first reached the offending block. For
+//! passes that check every block on its own, any path will do, and the shortest one is used.
+use crate::{
+    absint::InvariantMap,
+    control_flow_graph::{BlockId, ControlFlowGraph},
+};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use vm::file_format::CodeOffset;
+
+/// For every block reached from the entry block, the block it was reached through. The entry
+/// block has no predecessor.
+pub type Predecessors = BTreeMap<BlockId, BlockId>;
+
+/// Returns the blocks through which the abstract interpreter first reached every block it analyzed.
+pub fn analysis_predecessors<State>(inv_map: &InvariantMap<State>) -> Predecessors {
+    inv_map
+        .iter()
+        .filter_map(|(block_id, invariant)| {
+            invariant
+                .predecessor()
+                .map(|predecessor| (*block_id, predecessor))
+        })
+        .collect()
+}
+
+/// Returns the predecessors of every block reachable from the entry block along a shortest path.
+pub fn shortest_path_predecessors(cfg: &dyn ControlFlowGraph) -> Predecessors {
+    let entry_block_id = cfg.entry_block_id();
+    let mut predecessors = Predecessors::new();
+    let mut visited = BTreeSet::new();
+    visited.insert(entry_block_id);
+    let mut queue = VecDeque::new();
+    queue.push_back(entry_block_id);
+    while let Some(block_id) = queue.pop_front() {
+        for successor in cfg.successors(&block_id) {
+            if visited.insert(*successor) {
+                predecessors.insert(*successor, block_id);
+                queue.push_back(*successor);
+            }
+        }
+    }
+    predecessors
+}
+
+/// Returns the code offsets of the instructions executed along the path `predecessors` describes,
+/// from the start of the function up to and including the instruction at `offset`.
+///
+/// If the block containing `offset` can't be reached from the entry block, the trace starts at
+/// the beginning of that block.
+pub fn trace_to(
+    cfg: &dyn ControlFlowGraph,
+    predecessors: &Predecessors,
+    offset: CodeOffset,
+) -> Vec<CodeOffset> {
+    let contains_offset = |block_id: &BlockId| {
+        cfg.block_start(block_id) <= offset && offset <= cfg.block_end(block_id)
+    };
+    let target = match cfg.blocks().into_iter().find(contains_offset) {
+        Some(block_id) => block_id,
+        None => return vec![],
+    };
+
+    let mut path = vec![target];
+    let mut visited: BTreeSet<_> = path.iter().copied().collect();
+    while let Some(predecessor) = predecessors.get(path.last().unwrap()) {
+        // Predecessors never form a cycle, but a trace must not loop forever if they did.
+        if !visited.insert(*predecessor) {
+            break;
+        }
+        path.push(*predecessor);
+    }
+    path.reverse();
+
+    let mut trace = vec![];
+    for block_id in &path[..path.len() - 1] {
+        trace.extend(cfg.instr_indexes(block_id));
+    }
+    trace.extend(cfg.block_start(&target)..=offset);
+    trace
+}
@@ -4,8 +4,12 @@
 //! This module defines the transfer functions for verifying type and memory safety of a
 //! procedure body.
 use crate::{
-    absint::{AbstractInterpreter, BlockPrecondition, TransferFunctions},
+    absint::{
+        AbstractInterpreter, BlockPrecondition, InvariantMap, IterationBudgetExceeded,
+        TransferFunctions,
+    },
     abstract_state::{AbstractState, AbstractValue},
+    config::VerifierLimits,
     control_flow_graph::VMControlFlowGraph,
     nonce::Nonce,
 };
@@ -44,7 +48,19 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
         module: &'a CompiledModule,
         function_definition: &'a FunctionDefinition,
         cfg: &'a VMControlFlowGraph,
+        limits: &VerifierLimits,
     ) -> Vec<VMStaticViolation> {
+        Self::analyze(module, function_definition, cfg, limits).0
+    }
+
+    /// Runs the analysis the same way as `verify`, also returning the invariant of every block
+    /// that was reached. The invariant map is empty if the analysis exceeded its budget.
+    pub fn analyze(
+        module: &'a CompiledModule,
+        function_definition: &'a FunctionDefinition,
+        cfg: &'a VMControlFlowGraph,
+        limits: &VerifierLimits,
+    ) -> (Vec<VMStaticViolation>, InvariantMap<AbstractState>) {
         let module_view = ModuleView::new(module);
         let function_definition_view = FunctionDefinitionView::new(module, function_definition);
         let locals_signature_view = function_definition_view.locals_signature();
@@ -74,7 +90,21 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
             errors: vec![],
         };
 
-        let inv_map = verifier.analyze_function(initial_state, &function_definition_view, cfg);
+        let inv_map = match verifier.analyze_function(
+            initial_state,
+            &function_definition_view,
+            cfg,
+            limits.max_fixpoint_iterations,
+        ) {
+            Ok(inv_map) => inv_map,
+            // The errors found so far may depend on blocks that weren't analyzed to completion.
+            Err(IterationBudgetExceeded) => {
+                return (
+                    vec![VMStaticViolation::VerificationBudgetExceeded],
+                    InvariantMap::new(),
+                )
+            }
+        };
         // Report all the join failures
         for (block_id, inv) in inv_map.iter() {
             match inv.pre() {
@@ -84,7 +114,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
                 BlockPrecondition::State(_) => (),
             }
         }
-        verifier.errors
+        (verifier.errors, inv_map)
     }
 
     fn module(&self) -> &'a CompiledModule {
@@ -563,7 +593,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
                 let struct_definition = self.module().struct_def_at(*idx);
                 let type_actuals = &self.module().locals_signature_at(*type_actuals_idx).0;
                 let struct_type =
-                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.clone());
+                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.to_vec());
                 let kind =
                     SignatureTokenView::new(self.module(), &struct_type).kind(self.type_formals());
 
@@ -605,7 +635,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
                 let struct_definition = self.module().struct_def_at(*idx);
                 let type_actuals = &self.module().locals_signature_at(*type_actuals_idx).0;
                 let struct_type =
-                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.clone());
+                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.to_vec());
 
                 // Pop an abstract value from the stack and check if its type is equal to the one
                 // declared. TODO: is it safe to not call verify the kinds if the types are equal?
@@ -814,7 +844,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
 
                 let type_actuals = &self.module().locals_signature_at(*type_actuals_idx).0;
                 let struct_type =
-                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.clone());
+                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.to_vec());
                 SignatureTokenView::new(self.module(), &struct_type).kind(self.type_formals());
 
                 let operand = self.stack.pop().unwrap();
@@ -841,7 +871,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
 
                 let type_actuals = &self.module().locals_signature_at(*type_actuals_idx).0;
                 let struct_type =
-                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.clone());
+                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.to_vec());
                 SignatureTokenView::new(self.module(), &struct_type).kind(self.type_formals());
 
                 let operand = self.stack.pop().unwrap();
@@ -870,7 +900,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
 
                 let type_actuals = &self.module().locals_signature_at(*type_actuals_idx).0;
                 let struct_type =
-                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.clone());
+                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.to_vec());
                 SignatureTokenView::new(self.module(), &struct_type).kind(self.type_formals());
 
                 let operand = self.stack.pop().unwrap();
@@ -895,7 +925,7 @@ impl<'a> TypeAndMemorySafetyAnalysis<'a> {
 
                 let type_actuals = &self.module().locals_signature_at(*type_actuals_idx).0;
                 let struct_type =
-                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.clone());
+                    SignatureToken::Struct(struct_definition.struct_handle, type_actuals.to_vec());
                 SignatureTokenView::new(self.module(), &struct_type).kind(self.type_formals());
 
                 let value_operand = self.stack.pop().unwrap();
@@ -0,0 +1,75 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! This module implements an advisory checker that flags code that can never be executed.
+//! Unreachable code is harmless, so it is only reported with warnings, but it usually points at a
+//! bug in the compiler that emitted it.
+//!
+//! Every code unit must end with an unconditional branch, or the control flow graph would have
+//! edges past the end of the code.
+use crate::control_flow_graph::{ControlFlowGraph, VMControlFlowGraph};
+use std::collections::BTreeSet;
+use vm::{
+    access::ModuleAccess,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{CompiledModule, FunctionDefinition, FunctionDefinitionIndex, TableIndex},
+};
+
+pub struct UnreachableCodeChecker<'a> {
+    module: &'a CompiledModule,
+}
+
+impl<'a> UnreachableCodeChecker<'a> {
+    pub fn new(module: &'a CompiledModule) -> Self {
+        Self { module }
+    }
+
+    /// Returns a warning for every basic block that can't be reached from the entry block of its
+    /// function, located at the first instruction of the block.
+    ///
+    /// Blocks right after an unconditional branch that nothing branches to are reported as
+    /// `DeadCodeAfterBranch`, and other unreachable blocks as `UnreachableBlock`.
+    pub fn verify(self) -> Vec<VerificationError> {
+        self.module
+            .function_defs()
+            .iter()
+            .enumerate()
+            .filter(|(_, function_definition)| !function_definition.is_native())
+            .flat_map(|(idx, function_definition)| {
+                let idx = FunctionDefinitionIndex::new(idx as TableIndex);
+                Self::verify_function(function_definition)
+                    .into_iter()
+                    .map(move |err| VerificationError::in_function(idx, err))
+            })
+            .collect()
+    }
+
+    fn verify_function(function_definition: &FunctionDefinition) -> Vec<VMStaticViolation> {
+        let code = &function_definition.code.code;
+        let cfg = VMControlFlowGraph::new(code);
+        let reachable: BTreeSet<_> = cfg
+            .reachable_from(cfg.entry_block_id())
+            .into_iter()
+            .collect();
+        let branch_targets: BTreeSet<_> = code
+            .iter()
+            .filter_map(|bytecode| bytecode.offset())
+            .collect();
+
+        cfg.blocks()
+            .into_iter()
+            .filter(|block_id| !reachable.contains(block_id))
+            .map(|block_id| {
+                let start = cfg.block_start(&block_id);
+                let after_branch = start > 0
+                    && code[start as usize - 1].is_unconditional_branch()
+                    && !branch_targets.contains(&start);
+                if after_branch {
+                    VMStaticViolation::DeadCodeAfterBranch(start as usize)
+                } else {
+                    VMStaticViolation::UnreachableBlock(start as usize)
+                }
+            })
+            .collect()
+    }
+}
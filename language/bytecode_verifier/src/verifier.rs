@@ -3,19 +3,39 @@
 
 //! This module contains the public APIs supported by the bytecode verifier.
 use crate::{
-    check_duplication::DuplicationChecker, code_unit_verifier::CodeUnitVerifier,
-    resources::ResourceTransitiveChecker, signature::SignatureChecker,
+    cache::{VerificationCache, VerificationCacheKey},
+    check_duplication::DuplicationChecker,
+    code_unit_verifier::CodeUnitVerifier,
+    config::{ConfigurablePass, VerifierConfig},
+    global_storage::GlobalStorageChecker,
+    incremental::ModuleChanges,
+    meter::check_signature_depth,
+    metrics::VerificationMetrics,
+    reducibility::ReducibilityChecker,
+    report::VerificationReport,
+    resources::ResourceTransitiveChecker,
+    script_signature::ScriptSignatureChecker,
+    signature::SignatureChecker,
     struct_defs::RecursiveStructDefChecker,
+    structural_limits::StructuralLimitsChecker,
+    unreachable_code::UnreachableCodeChecker,
 };
 use failure::Error;
-use std::{collections::BTreeMap, fmt};
-use types::language_storage::ModuleId;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    time::Instant,
+};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
 use vm::{
     access::{ModuleAccess, ScriptAccess},
-    errors::{VMStaticViolation, VerificationError, VerificationStatus},
+    errors::{has_errors, VMStaticViolation, VerificationError, VerificationStatus},
     file_format::{CompiledModule, CompiledProgram, CompiledScript},
     resolver::Resolver,
-    views::{ModuleView, ViewInternals},
+    views::{
+        struct_handles_compatible, FunctionDefinitionView, ModuleView, StructDefinitionView,
+        ViewInternals,
+    },
     IndexKind,
 };
 use vm_runtime_types::{
@@ -71,7 +91,7 @@ impl<'a> VerifiedProgram<'a> {
                 // Verify against any modules compiled earlier as well.
                 let deps = deps.iter().copied().chain(&modules);
                 let errors = verify_module_dependencies(&module, deps);
-                if !errors.is_empty() {
+                if has_errors(&errors) {
                     return Err(to_statuses(errors));
                 }
             }
@@ -96,7 +116,7 @@ impl<'a> VerifiedProgram<'a> {
         {
             let deps = deps.iter().copied().chain(&modules);
             let errors = verify_script_dependencies(&script, deps);
-            if !errors.is_empty() {
+            if has_errors(&errors) {
                 return Err(to_statuses(errors));
             }
         }
@@ -163,23 +183,123 @@ impl VerifiedModule {
     /// There is a partial order on the checks. For example, the duplication check must precede the
     /// structural recursion check. In general, later checks are more expensive.
     pub fn new(module: CompiledModule) -> Result<Self, (CompiledModule, Vec<VerificationError>)> {
-        // All CompiledModule instances are statically guaranteed to be bounds checked, so there's
-        // no need for more checking.
-        let mut errors = DuplicationChecker::new(&module).verify();
-        if errors.is_empty() {
-            errors.append(&mut SignatureChecker::new(&module).verify());
-            errors.append(&mut ResourceTransitiveChecker::new(&module).verify());
-        }
-        if errors.is_empty() {
-            errors.append(&mut RecursiveStructDefChecker::new(&module).verify());
+        Self::new_with_warnings(module).map(|(module, _)| module)
+    }
+
+    /// Verifies this `CompiledModule` the same way as `new`, but also returns the violations that
+    /// were found without failing verification (i.e. those with a severity below
+    /// `Severity::Error`).
+    ///
+    /// On failure, the returned list contains every violation found, regardless of severity.
+    pub fn new_with_warnings(
+        module: CompiledModule,
+    ) -> Result<(Self, Vec<VerificationError>), (CompiledModule, Vec<VerificationError>)> {
+        let errors = verify_module_with_config(&module, &VerifierConfig::all());
+        if has_errors(&errors) {
+            Err((module, errors))
+        } else {
+            Ok((VerifiedModule(module), errors))
         }
-        if errors.is_empty() {
-            errors.append(&mut CodeUnitVerifier::verify(&module));
+    }
+
+    /// Verifies this `CompiledModule` the same way as `new`, but reports the outcome of every pass
+    /// (passed, failed or skipped) along with the errors it found, instead of a flat list of
+    /// errors.
+    ///
+    /// On failure, returns the original `CompiledModule` along with the report.
+    pub fn new_with_report(
+        module: CompiledModule,
+    ) -> Result<(Self, VerificationReport), (CompiledModule, VerificationReport)> {
+        let (errors, metrics) = verify_module_with_metrics(&module, &VerifierConfig::all());
+        let report = VerificationReport::new(&errors, &metrics);
+        if report.passed() {
+            Ok((VerifiedModule(module), report))
+        } else {
+            Err((module, report))
         }
-        if errors.is_empty() {
-            Ok(VerifiedModule(module))
+    }
+
+    /// Verifies this `CompiledModule` the same way as `new`, also returning the metrics recorded
+    /// while verifying it, whether it passed or not.
+    pub fn new_with_metrics(
+        module: CompiledModule,
+    ) -> (
+        Result<Self, (CompiledModule, Vec<VerificationError>)>,
+        VerificationMetrics,
+    ) {
+        let (errors, metrics) = verify_module_with_metrics(&module, &VerifierConfig::all());
+        let result = if errors.has_errors() {
+            Err((module, errors.into_errors()))
         } else {
+            Ok(VerifiedModule(module))
+        };
+        (result, metrics)
+    }
+
+    /// Verifies this `CompiledModule` the same way as `new`, but looks up the outcome in `cache`
+    /// first, and caches it on a miss.
+    pub fn new_with_cache(
+        module: CompiledModule,
+        cache: &dyn VerificationCache,
+    ) -> Result<Self, (CompiledModule, Vec<VerificationError>)> {
+        let config = VerifierConfig::all();
+        // A module that can't be serialized can't be hashed either, so just verify it.
+        let key = match VerificationCacheKey::new(&module, &config) {
+            Ok(key) => key,
+            Err(_) => return Self::new(module),
+        };
+        let errors = match cache.get(&key) {
+            Some(errors) => errors,
+            None => {
+                let errors = verify_module_with_config(&module, &config);
+                cache.insert(key, errors.clone());
+                errors
+            }
+        };
+        if has_errors(&errors) {
             Err((module, errors))
+        } else {
+            Ok(VerifiedModule(module))
+        }
+    }
+
+    /// Verifies `module` as an upgrade of `old`, returning a `VerifiedModule` on success.
+    ///
+    /// The module-level passes run on the whole of `module`, but code units only get verified
+    /// again if they changed since `old` or depend on something that did (see `ModuleChanges`).
+    /// A module with another identity than `old` is verified from scratch.
+    ///
+    /// On failure, returns the original `CompiledModule` and a list of verification errors.
+    pub fn new_upgrade(
+        old: &VerifiedModule,
+        module: CompiledModule,
+    ) -> Result<Self, (CompiledModule, Vec<VerificationError>)> {
+        if old.self_id() != module.self_id() {
+            return Self::new(module);
+        }
+        let module_config = VerifierConfig {
+            control_flow: false,
+            stack_usage: false,
+            stack_height: false,
+            acquires: false,
+            type_safety: false,
+            ..VerifierConfig::all()
+        };
+        let mut errors = verify_module_by_pass(&module, &module_config);
+        if !errors.has_errors() {
+            let changes = ModuleChanges::new(old.as_inner(), &module);
+            errors
+                .errors
+                .extend(CodeUnitVerifier::verify_functions_by_pass(
+                    &module,
+                    &VerifierConfig::all(),
+                    &changes.functions_to_verify,
+                ));
+        }
+        if errors.has_errors() {
+            Err((module, errors.into_errors()))
+        } else {
+            Ok(VerifiedModule(module))
         }
     }
 
@@ -240,7 +360,7 @@ impl VerifiedScript {
     ///
     /// Verification of a script is done in two steps:
     /// - Convert the script into a module and run all the usual verification performed on a module
-    /// - Check the signature of the main function of the script
+    /// - Check that the signature of the main function of the script lets transactions call it
     ///
     /// This approach works because critical operations such as MoveFrom, MoveToSender, and
     /// BorrowGlobal that are not allowed in the script function take a StructDefinitionIndex as an
@@ -254,17 +374,8 @@ impl VerifiedScript {
             Err((module, errors)) => (module, errors),
         };
         let script = fake_module.into_script();
-        errors.append(
-            &mut verify_main_signature(&script)
-                .into_iter()
-                .map(move |err| VerificationError {
-                    kind: IndexKind::FunctionDefinition,
-                    idx: 0,
-                    err,
-                })
-                .collect(),
-        );
-        if errors.is_empty() {
+        errors.append(&mut ScriptSignatureChecker::new(&script).verify());
+        if !has_errors(&errors) {
             Ok(VerifiedScript(script))
         } else {
             Err((script, errors))
@@ -313,19 +424,251 @@ impl fmt::Display for VerifiedScript {
     }
 }
 
+/// This function runs the passes of the bytecode verifier that `config` runs on `module`, in the
+/// same order as `VerifiedModule::new`. Like there, later passes only run if the earlier ones
+/// didn't find any errors, unless `config.collect_all` is set.
+///
+/// Unlike `VerifiedModule::new`, this can be used to verify a module only partially. Since a pass
+/// only runs if every pass it depends on runs as well, each pass is still only given modules that
+/// it can handle.
+pub fn verify_module_with_config(
+    module: &CompiledModule,
+    config: &VerifierConfig,
+) -> Vec<VerificationError> {
+    verify_module_by_pass(module, config).into_errors()
+}
+
+/// This function runs the same passes as `verify_module_with_config`, but also records which pass
+/// found each error.
+///
+/// With `config.collect_all` set, a pass runs as long as none of the passes it depends on found
+/// errors, so that every problem that can be soundly detected is reported at once. Exceeding
+/// `config.limits` still aborts verification.
+pub fn verify_module_by_pass(module: &CompiledModule, config: &VerifierConfig) -> ErrorsByPass {
+    verify_module_impl(module, config, None)
+}
+
+/// This function runs the same passes as `verify_module_by_pass`, also recording the time spent
+/// in and the errors found by every pass and every code unit.
+pub fn verify_module_with_metrics(
+    module: &CompiledModule,
+    config: &VerifierConfig,
+) -> (ErrorsByPass, VerificationMetrics) {
+    let mut metrics = VerificationMetrics::new();
+    let start = Instant::now();
+    let errors = verify_module_impl(module, config, Some(&mut metrics));
+    metrics.total_time = start.elapsed();
+    (errors, metrics)
+}
+
+fn verify_module_impl(
+    module: &CompiledModule,
+    config: &VerifierConfig,
+    mut metrics: Option<&mut VerificationMetrics>,
+) -> ErrorsByPass {
+    // All CompiledModule instances are statically guaranteed to be bounds checked, so there's
+    // no need for more checking.
+    let mut errors = ErrorsByPass::default();
+    if config.runs(ConfigurablePass::Duplication) {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::Duplication,
+            || DuplicationChecker::new(module).verify(),
+        );
+    }
+    if errors.may_run(config, ConfigurablePass::StructuralLimits) {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::StructuralLimits,
+            || StructuralLimitsChecker::new(module, &config.max_structure).verify(),
+        );
+    }
+
+    let run_signature = errors.may_run(config, ConfigurablePass::Signature);
+    let run_resources = errors.may_run(config, ConfigurablePass::Resources);
+    if run_signature {
+        // Every later pass walks signatures, so make sure that's cheap first.
+        if let Some(err) = check_signature_depth(module, &config.limits) {
+            run_pass(
+                &mut errors,
+                &mut metrics,
+                ConfigurablePass::Signature,
+                || vec![err],
+            );
+            return errors;
+        }
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::Signature,
+            || SignatureChecker::new(module).verify(),
+        );
+    }
+    if run_resources {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::Resources,
+            || ResourceTransitiveChecker::new(module).verify(),
+        );
+    }
+
+    if errors.may_run(config, ConfigurablePass::RecursiveStructs) {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::RecursiveStructs,
+            || RecursiveStructDefChecker::new(module).verify(),
+        );
+    }
+
+    // Every code unit pass depends on at least the passes that control flow depends on, and
+    // `CodeUnitVerifier` takes care of the dependencies between code unit passes.
+    if errors.may_run(config, ConfigurablePass::ControlFlow)
+        || errors.may_run(config, ConfigurablePass::Acquires)
+    {
+        let code_unit_errors = match &mut metrics {
+            Some(metrics) => CodeUnitVerifier::verify_by_pass_with_metrics(module, config, metrics),
+            None => CodeUnitVerifier::verify_by_pass(module, config),
+        };
+        errors.errors.extend(code_unit_errors);
+    }
+
+    if errors.may_run(config, ConfigurablePass::UnreachableCode) {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::UnreachableCode,
+            || UnreachableCodeChecker::new(module).verify(),
+        );
+    }
+    if errors.may_run(config, ConfigurablePass::Reducibility) {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::Reducibility,
+            || ReducibilityChecker::new(module).verify(),
+        );
+    }
+    if errors.may_run(config, ConfigurablePass::GlobalStorage) {
+        run_pass(
+            &mut errors,
+            &mut metrics,
+            ConfigurablePass::GlobalStorage,
+            || GlobalStorageChecker::new(module).verify(),
+        );
+    }
+    errors
+}
+
+/// Runs `pass` with `verify`, recording its cost if metrics are being recorded.
+fn run_pass(
+    errors: &mut ErrorsByPass,
+    metrics: &mut Option<&mut VerificationMetrics>,
+    pass: ConfigurablePass,
+    verify: impl FnOnce() -> Vec<VerificationError>,
+) {
+    let start = Instant::now();
+    let pass_errors = verify();
+    if let Some(metrics) = metrics {
+        metrics.record_pass(pass, start.elapsed(), pass_errors.len());
+    }
+    errors.extend(pass, pass_errors);
+}
+
+/// The errors found by the bytecode verifier, along with the pass that found each of them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ErrorsByPass {
+    /// Errors in the order they were found.
+    errors: Vec<(ConfigurablePass, VerificationError)>,
+}
+
+impl ErrorsByPass {
+    /// Returns true if no pass found anything, not even warnings.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns true if any pass found errors that must fail verification.
+    pub fn has_errors(&self) -> bool {
+        self.errors.iter().any(|(_, err)| err.is_error())
+    }
+
+    /// Returns the errors found by `pass`, in the order they were found.
+    pub fn for_pass(&self, pass: ConfigurablePass) -> Vec<VerificationError> {
+        self.errors
+            .iter()
+            .filter(|(err_pass, _)| *err_pass == pass)
+            .map(|(_, err)| err.clone())
+            .collect()
+    }
+
+    /// Returns the errors grouped by the pass that found them. Passes that found nothing, or
+    /// didn't run, are left out.
+    pub fn grouped(&self) -> BTreeMap<ConfigurablePass, Vec<VerificationError>> {
+        let mut grouped: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (pass, err) in &self.errors {
+            grouped.entry(*pass).or_default().push(err.clone());
+        }
+        grouped
+    }
+
+    /// Returns every error, in the order they were found.
+    pub fn into_errors(self) -> Vec<VerificationError> {
+        self.errors.into_iter().map(|(_, err)| err).collect()
+    }
+
+    fn extend(&mut self, pass: ConfigurablePass, errors: Vec<VerificationError>) {
+        self.errors
+            .extend(errors.into_iter().map(|err| (pass, err)));
+    }
+
+    /// Returns whether `pass` may run after the passes that ran so far.
+    fn may_run(&self, config: &VerifierConfig, pass: ConfigurablePass) -> bool {
+        if !config.runs(pass) {
+            return false;
+        }
+        if config.collect_all {
+            pass.dependencies().iter().all(|dependency| {
+                !self
+                    .errors
+                    .iter()
+                    .any(|(err_pass, err)| err_pass == dependency && err.is_error())
+            })
+        } else {
+            !self.has_errors()
+        }
+    }
+}
+
 /// This function checks the extra requirements on the signature of the main function of a script.
+/// See `ScriptSignatureChecker`, which also locates the violations.
 pub fn verify_main_signature(script: &CompiledScript) -> Vec<VMStaticViolation> {
-    let function_handle = &script.function_handle_at(script.main().function);
-    let function_signature = &script.function_signature_at(function_handle.signature);
-    if !function_signature.return_types.is_empty() {
-        return vec![VMStaticViolation::InvalidMainFunctionSignature];
-    }
-    for arg_type in &function_signature.arg_types {
-        if !arg_type.is_primitive() {
-            return vec![VMStaticViolation::InvalidMainFunctionSignature];
-        }
+    ScriptSignatureChecker::new(script)
+        .verify()
+        .into_iter()
+        .map(|err| err.err)
+        .collect()
+}
+
+/// This function checks that a module identifies itself as being published at `sender_address`,
+/// the account of the transaction publishing it. If this wasn't checked, the sender could publish
+/// a module under anyone's account.
+pub fn verify_module_address(
+    module: &CompiledModule,
+    sender_address: &AccountAddress,
+) -> Vec<VerificationError> {
+    if module.address() != sender_address {
+        vec![VerificationError::new(
+            IndexKind::AddressPool,
+            CompiledModule::IMPLEMENTED_MODULE_INDEX as usize,
+            VMStaticViolation::ModuleAddressDoesNotMatchSender,
+        )]
+    } else {
+        vec![]
     }
-    vec![]
 }
 
 /// Verification of a module in isolation (using `VerifiedModule::new`) trusts that struct and
@@ -335,28 +678,48 @@ pub fn verify_main_signature(script: &CompiledScript) -> Vec<VMStaticViolation>
 /// error is included in the returned list of errors.  If found, usage of types and functions of the
 /// dependency in 'module' is checked against the declarations in the found module and mismatch
 /// errors are returned.
-pub fn verify_module_dependencies<'a>(
+///
+/// Struct handles must agree with their definitions on being resources and on the kinds of their
+/// type formals, and function handles must refer to public definitions with exactly the same
+/// signature. The definitions they refer to must also be native exactly when the VM implements
+/// them. Mismatch errors are located at the local handle, and carry the index of the definition
+/// in the dependency.
+///
+/// The dependencies are only borrowed, so they can be any modules that were verified before, like
+/// `VerifiedModule`s or `CompiledModule`s loaded from storage. Callers keeping their modules in a
+/// collection of their own can use `verify_module_dependencies_in` instead.
+pub fn verify_module_dependencies<'a, M: ModuleAccess + 'a>(
     module: &VerifiedModule,
-    dependencies: impl IntoIterator<Item = &'a VerifiedModule>,
+    dependencies: impl IntoIterator<Item = &'a M>,
 ) -> Vec<VerificationError> {
-    let module_id = module.self_id();
-    let mut dependency_map = BTreeMap::new();
-    for dependency in dependencies {
-        let dependency_id = dependency.self_id();
-        if module_id != dependency_id {
-            dependency_map.insert(dependency_id, dependency);
-        }
-    }
+    let dependency_map: BTreeMap<_, _> = dependencies
+        .into_iter()
+        .map(|dependency| (dependency.self_id(), dependency))
+        .collect();
+    verify_module_dependencies_in(module, &dependency_map)
+}
+
+/// This function verifies the dependencies of `module` the same way as
+/// `verify_module_dependencies`, looking them up in `cache`. If `cache` holds a module with the
+/// same id as `module`, e.g. the version it upgrades, it is ignored.
+pub fn verify_module_dependencies_in(
+    module: &VerifiedModule,
+    cache: &impl ModuleCache,
+) -> Vec<VerificationError> {
+    let dependencies = Dependencies {
+        module_id: module.self_id(),
+        cache,
+    };
     let mut errors = vec![];
     let module_view = ModuleView::new(module);
-    errors.append(&mut verify_struct_kind(&module_view, &dependency_map));
+    errors.append(&mut verify_struct_kind(&module_view, &dependencies));
     errors.append(&mut verify_function_visibility_and_type(
         &module_view,
-        &dependency_map,
+        &dependencies,
     ));
     errors.append(&mut verify_all_dependencies_provided(
         &module_view,
-        &dependency_map,
+        &dependencies,
     ));
     errors.append(&mut verify_native_functions(&module_view));
     errors.append(&mut verify_native_structs(&module_view));
@@ -368,14 +731,64 @@ pub fn verify_module_dependencies<'a>(
 /// looked up in 'dependencies'.  If not found, an error is included in the returned list of errors.
 /// If found, usage of types and functions of the dependency in 'script' is checked against the
 /// declarations in the found module and mismatch errors are returned.
-pub fn verify_script_dependencies<'a>(
+pub fn verify_script_dependencies<'a, M: ModuleAccess + 'a>(
     script: &VerifiedScript,
-    dependencies: impl IntoIterator<Item = &'a VerifiedModule>,
+    dependencies: impl IntoIterator<Item = &'a M>,
 ) -> Vec<VerificationError> {
     let fake_module = script.clone().into_module();
     verify_module_dependencies(&fake_module, dependencies)
 }
 
+/// This function verifies the dependencies of `script` the same way as
+/// `verify_script_dependencies`, looking them up in `cache`.
+pub fn verify_script_dependencies_in(
+    script: &VerifiedScript,
+    cache: &impl ModuleCache,
+) -> Vec<VerificationError> {
+    let fake_module = script.clone().into_module();
+    verify_module_dependencies_in(&fake_module, cache)
+}
+
+/// A collection of modules that the dependencies of a module are looked up in by id.
+pub trait ModuleCache {
+    type Module: ModuleAccess;
+
+    /// Returns the module with id `id`, if there is one.
+    fn get_module(&self, id: &ModuleId) -> Option<&Self::Module>;
+}
+
+impl<'a, M: ModuleAccess> ModuleCache for BTreeMap<ModuleId, &'a M> {
+    type Module = M;
+
+    fn get_module(&self, id: &ModuleId) -> Option<&M> {
+        self.get(id).copied()
+    }
+}
+
+impl<M: ModuleAccess> ModuleCache for HashMap<ModuleId, M> {
+    type Module = M;
+
+    fn get_module(&self, id: &ModuleId) -> Option<&M> {
+        self.get(id)
+    }
+}
+
+/// The dependencies of the module `module_id`, which never include the module itself.
+struct Dependencies<'a, C> {
+    module_id: ModuleId,
+    cache: &'a C,
+}
+
+impl<'a, C: ModuleCache> Dependencies<'a, C> {
+    fn get(&self, id: &ModuleId) -> Option<&'a C::Module> {
+        if *id == self.module_id {
+            None
+        } else {
+            self.cache.get_module(id)
+        }
+    }
+}
+
 fn verify_native_functions(module_view: &ModuleView<VerifiedModule>) -> Vec<VerificationError> {
     let mut errors = vec![];
 
@@ -387,21 +800,21 @@ fn verify_native_functions(module_view: &ModuleView<VerifiedModule>) -> Vec<Veri
     {
         let function_name = native_function_definition_view.name();
         match dispatch_native_function(&module_id, function_name) {
-            None => errors.push(VerificationError {
-                kind: IndexKind::FunctionHandle,
+            None => errors.push(VerificationError::new(
+                IndexKind::FunctionHandle,
                 idx,
-                err: VMStaticViolation::MissingDependency,
-            }),
+                VMStaticViolation::MissingDependency,
+            )),
             Some(vm_native_function) => {
                 let declared_function_signature =
                     native_function_definition_view.signature().as_inner();
                 let expected_function_signature = &vm_native_function.expected_signature;
                 if declared_function_signature != expected_function_signature {
-                    errors.push(VerificationError {
-                        kind: IndexKind::FunctionHandle,
+                    errors.push(VerificationError::new(
+                        IndexKind::FunctionHandle,
                         idx,
-                        err: VMStaticViolation::TypeMismatch,
-                    })
+                        VMStaticViolation::TypeMismatch,
+                    ))
                 }
             }
         }
@@ -421,11 +834,11 @@ fn verify_native_structs(module_view: &ModuleView<VerifiedModule>) -> Vec<Verifi
         let struct_name = native_struct_definition_view.name();
 
         match dispatch_native_struct(&module_id, struct_name) {
-            None => errors.push(VerificationError {
-                kind: IndexKind::StructHandle,
+            None => errors.push(VerificationError::new(
+                IndexKind::StructHandle,
                 idx,
-                err: VMStaticViolation::MissingDependency,
-            }),
+                VMStaticViolation::MissingDependency,
+            )),
             Some(vm_native_struct) => {
                 let declared_index = idx as u16;
                 let declared_is_nominal_resource =
@@ -439,11 +852,11 @@ fn verify_native_structs(module_view: &ModuleView<VerifiedModule>) -> Vec<Verifi
                     || declared_is_nominal_resource != expected_is_nominal_resource
                     || declared_type_formals != expected_type_formals
                 {
-                    errors.push(VerificationError {
-                        kind: IndexKind::StructHandle,
+                    errors.push(VerificationError::new(
+                        IndexKind::StructHandle,
                         idx,
-                        err: VMStaticViolation::TypeMismatch,
-                    })
+                        VMStaticViolation::TypeMismatch,
+                    ))
                 }
             }
         }
@@ -453,19 +866,19 @@ fn verify_native_structs(module_view: &ModuleView<VerifiedModule>) -> Vec<Verifi
 
 fn verify_all_dependencies_provided(
     module_view: &ModuleView<VerifiedModule>,
-    dependency_map: &BTreeMap<ModuleId, &VerifiedModule>,
+    dependencies: &Dependencies<impl ModuleCache>,
 ) -> Vec<VerificationError> {
     let mut errors = vec![];
     for (idx, module_handle_view) in module_view.module_handles().enumerate() {
         let module_id = module_handle_view.module_id();
         if idx != CompiledModule::IMPLEMENTED_MODULE_INDEX as usize
-            && !dependency_map.contains_key(&module_id)
+            && dependencies.get(&module_id).is_none()
         {
-            errors.push(VerificationError {
-                kind: IndexKind::ModuleHandle,
+            errors.push(VerificationError::new(
+                IndexKind::ModuleHandle,
                 idx,
-                err: VMStaticViolation::MissingDependency,
-            });
+                VMStaticViolation::MissingDependency,
+            ));
         }
     }
     errors
@@ -473,34 +886,39 @@ fn verify_all_dependencies_provided(
 
 fn verify_struct_kind(
     module_view: &ModuleView<VerifiedModule>,
-    dependency_map: &BTreeMap<ModuleId, &VerifiedModule>,
+    dependencies: &Dependencies<impl ModuleCache>,
 ) -> Vec<VerificationError> {
     let mut errors = vec![];
     for (idx, struct_handle_view) in module_view.struct_handles().enumerate() {
         let owner_module_id = struct_handle_view.module_id();
-        if !dependency_map.contains_key(&owner_module_id) {
-            continue;
-        }
+        let owner_module = match dependencies.get(&owner_module_id) {
+            Some(owner_module) => owner_module,
+            None => continue,
+        };
         let struct_name = struct_handle_view.name();
-        let owner_module = &dependency_map[&owner_module_id];
-        let owner_module_view = ModuleView::new(*owner_module);
-        if let Some(struct_definition_view) = owner_module_view.struct_definition(struct_name) {
-            if struct_handle_view.is_nominal_resource()
-                != struct_definition_view.is_nominal_resource()
-                || struct_handle_view.type_formals() != struct_definition_view.type_formals()
-            {
-                errors.push(VerificationError {
-                    kind: IndexKind::StructHandle,
+        if let Some((def_idx, struct_definition_view)) =
+            find_struct_definition(owner_module, struct_name)
+        {
+            if !struct_handles_compatible(&struct_handle_view, &struct_definition_view.handle()) {
+                errors.push(VerificationError::new(
+                    IndexKind::StructHandle,
+                    idx,
+                    VMStaticViolation::StructKindMismatch(def_idx),
+                ));
+            }
+            if !native_struct_agrees(&owner_module_id, def_idx, &struct_definition_view) {
+                errors.push(VerificationError::new(
+                    IndexKind::StructHandle,
                     idx,
-                    err: VMStaticViolation::TypeMismatch,
-                });
+                    VMStaticViolation::NativeFlagMismatch(def_idx),
+                ));
             }
         } else {
-            errors.push(VerificationError {
-                kind: IndexKind::StructHandle,
+            errors.push(VerificationError::new(
+                IndexKind::StructHandle,
                 idx,
-                err: VMStaticViolation::LookupFailed,
-            });
+                VMStaticViolation::LookupFailed,
+            ));
         }
     }
     errors
@@ -508,57 +926,124 @@ fn verify_struct_kind(
 
 fn verify_function_visibility_and_type(
     module_view: &ModuleView<VerifiedModule>,
-    dependency_map: &BTreeMap<ModuleId, &VerifiedModule>,
+    dependencies: &Dependencies<impl ModuleCache>,
 ) -> Vec<VerificationError> {
     let resolver = Resolver::new(module_view.as_inner());
     let mut errors = vec![];
     for (idx, function_handle_view) in module_view.function_handles().enumerate() {
         let owner_module_id = function_handle_view.module_id();
-        if !dependency_map.contains_key(&owner_module_id) {
-            continue;
-        }
+        let owner_module = match dependencies.get(&owner_module_id) {
+            Some(owner_module) => owner_module,
+            None => continue,
+        };
         let function_name = function_handle_view.name();
-        let owner_module = dependency_map[&owner_module_id];
-        let owner_module_view = ModuleView::new(owner_module);
-        if let Some(function_definition_view) = owner_module_view.function_definition(function_name)
+        if let Some((def_idx, function_definition_view)) =
+            find_function_definition(owner_module, function_name)
         {
-            if function_definition_view.is_public() {
-                let function_definition_signature = function_definition_view.signature().as_inner();
-                match resolver
-                    .import_function_signature(owner_module, &function_definition_signature)
-                {
-                    Ok(imported_function_signature) => {
-                        let function_handle_signature = function_handle_view.signature().as_inner();
-                        if imported_function_signature != *function_handle_signature {
-                            errors.push(VerificationError {
-                                kind: IndexKind::FunctionHandle,
-                                idx,
-                                err: VMStaticViolation::TypeMismatch,
-                            });
-                        }
-                    }
-                    Err(err) => {
-                        errors.push(VerificationError {
-                            kind: IndexKind::FunctionHandle,
+            if !function_definition_view.is_public() {
+                errors.push(VerificationError::new(
+                    IndexKind::FunctionHandle,
+                    idx,
+                    VMStaticViolation::FunctionVisibilityMismatch(def_idx),
+                ));
+            }
+            let function_definition_signature = function_definition_view.signature().as_inner();
+            match resolver.import_function_signature(owner_module, &function_definition_signature) {
+                Ok(imported_function_signature) => {
+                    let function_handle_signature = function_handle_view.signature().as_inner();
+                    if imported_function_signature != *function_handle_signature {
+                        errors.push(VerificationError::new(
+                            IndexKind::FunctionHandle,
                             idx,
-                            err,
-                        });
+                            VMStaticViolation::FunctionSignatureMismatch(def_idx),
+                        ));
                     }
                 }
-            } else {
-                errors.push(VerificationError {
-                    kind: IndexKind::FunctionHandle,
+                Err(err) => {
+                    errors.push(VerificationError::new(IndexKind::FunctionHandle, idx, err));
+                }
+            }
+            if !native_function_agrees(&owner_module_id, &function_definition_view) {
+                errors.push(VerificationError::new(
+                    IndexKind::FunctionHandle,
                     idx,
-                    err: VMStaticViolation::VisibilityMismatch,
-                });
+                    VMStaticViolation::NativeFlagMismatch(def_idx),
+                ));
             }
         } else {
-            errors.push(VerificationError {
-                kind: IndexKind::FunctionHandle,
+            errors.push(VerificationError::new(
+                IndexKind::FunctionHandle,
                 idx,
-                err: VMStaticViolation::LookupFailed,
-            });
+                VMStaticViolation::LookupFailed,
+            ));
         }
     }
     errors
 }
+
+/// Returns the index of the struct definition named `name` in `module`, along with a view of it.
+fn find_struct_definition<'a, M: ModuleAccess>(
+    module: &'a M,
+    name: &str,
+) -> Option<(usize, StructDefinitionView<'a, M>)> {
+    module
+        .struct_defs()
+        .iter()
+        .map(|struct_def| StructDefinitionView::new(module, struct_def))
+        .enumerate()
+        .find(|(_, struct_definition_view)| struct_definition_view.name() == name)
+}
+
+/// Returns the index of the function definition named `name` in `module`, along with a view of it.
+fn find_function_definition<'a, M: ModuleAccess>(
+    module: &'a M,
+    name: &str,
+) -> Option<(usize, FunctionDefinitionView<'a, M>)> {
+    module
+        .function_defs()
+        .iter()
+        .map(|function_def| FunctionDefinitionView::new(module, function_def))
+        .enumerate()
+        .find(|(_, function_definition_view)| function_definition_view.name() == name)
+}
+
+/// Returns true if the struct definition at `def_idx` in the module `module_id` is native exactly
+/// when the VM implements it, and agrees with the implementation if so.
+fn native_struct_agrees(
+    module_id: &ModuleId,
+    def_idx: usize,
+    struct_definition_view: &StructDefinitionView<impl ModuleAccess>,
+) -> bool {
+    match (
+        struct_definition_view.is_native(),
+        dispatch_native_struct(module_id, struct_definition_view.name()),
+    ) {
+        (true, Some(vm_native_struct)) => {
+            vm_native_struct.expected_index.0 as usize == def_idx
+                && vm_native_struct.expected_nominal_resource
+                    == struct_definition_view.is_nominal_resource()
+                && &vm_native_struct.expected_type_formals == struct_definition_view.type_formals()
+        }
+        (false, None) => true,
+        (true, None) | (false, Some(_)) => false,
+    }
+}
+
+/// Returns true if the function definition in the module `module_id` is native exactly when the VM
+/// implements it, and has the signature of the implementation if so.
+fn native_function_agrees(
+    module_id: &ModuleId,
+    function_definition_view: &FunctionDefinitionView<impl ModuleAccess>,
+) -> bool {
+    match (
+        function_definition_view.is_native(),
+        dispatch_native_function(module_id, function_definition_view.name()),
+    ) {
+        (true, Some(vm_native_function)) => {
+            function_definition_view.signature().as_inner()
+                == &vm_native_function.expected_signature
+        }
+        (false, None) => true,
+        (true, None) | (false, Some(_)) => false,
+    }
+}
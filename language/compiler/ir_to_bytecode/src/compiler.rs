@@ -21,6 +21,7 @@ use std::{
         hash_map::Entry::{Occupied, Vacant},
         HashMap, VecDeque,
     },
+    iter::FromIterator,
 };
 use types::account_address::AccountAddress;
 use vm::{
@@ -310,7 +311,7 @@ pub fn compile_script<'a, T: 'a + ModuleAccess>(
     };
     compiled_script
         .freeze()
-        .map_err(|errs| InternalCompilerError::BoundsCheckErrors(errs).into())
+        .map_err(|err| InternalCompilerError::BoundsCheckErrors(err).into())
 }
 
 /// Compile a module.
@@ -376,7 +377,7 @@ pub fn compile_module<'a, T: 'a + ModuleAccess>(
     };
     compiled_module
         .freeze()
-        .map_err(|errs| InternalCompilerError::BoundsCheckErrors(errs).into())
+        .map_err(|err| InternalCompilerError::BoundsCheckErrors(err).into())
 }
 
 fn compile_imports(
@@ -415,7 +416,10 @@ fn kind(ast_k: &ast::Kind) -> Kind {
     }
 }
 
-fn compile_types(context: &mut Context, tys: &[Type]) -> Result<Vec<SignatureToken>> {
+fn compile_types<T: FromIterator<SignatureToken>>(
+    context: &mut Context,
+    tys: &[Type],
+) -> Result<T> {
     tys.iter()
         .map(|ty| compile_type(context, ty))
         .collect::<Result<_>>()
@@ -580,7 +584,7 @@ fn compile_function_body(
     block: Block,
 ) -> Result<CodeUnit> {
     let mut function_frame = FunctionFrame::new();
-    let mut locals_signature = LocalsSignature(vec![]);
+    let mut locals_signature = LocalsSignature::default();
     for (var, t) in formals {
         let sig = compile_type(context, &t)?;
         function_frame.define_local(&var, sig.clone())?;
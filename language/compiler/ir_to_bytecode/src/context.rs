@@ -18,6 +18,7 @@ use vm::{
         StructDefinitionIndex, StructHandle, StructHandleIndex, TableIndex, TypeSignature,
         TypeSignatureIndex,
     },
+    identifier::Identifier,
 };
 
 type TypeFormalMap = HashMap<TypeVar, TableIndex>;
@@ -58,7 +59,7 @@ struct CompiledDependency<'a> {
     module_pool: &'a [ModuleHandle],
     struct_pool: &'a [StructHandle],
     function_signatuire_pool: &'a [FunctionSignature],
-    string_pool: &'a [String],
+    string_pool: &'a [Identifier],
     address_pool: &'a [AccountAddress],
 }
 
@@ -105,13 +106,17 @@ impl<'a> CompiledDependency<'a> {
         let handle = self.struct_pool.get(idx.0 as usize)?;
         let module_handle = self.module_pool.get(handle.module.0 as usize)?;
         let address = *self.address_pool.get(module_handle.address.0 as usize)?;
-        let module = ModuleName::new(self.string_pool.get(module_handle.name.0 as usize)?.clone());
+        let module = ModuleName::new(
+            self.string_pool
+                .get(module_handle.name.0 as usize)?
+                .to_string(),
+        );
         assert!(module.as_inner() != ModuleName::SELF);
         let ident = QualifiedModuleIdent {
             address,
             name: module,
         };
-        let name = StructName::new(self.string_pool.get(handle.name.0 as usize)?.clone());
+        let name = StructName::new(self.string_pool.get(handle.name.0 as usize)?.to_string());
         Some((ident, name))
     }
 
@@ -144,7 +149,7 @@ pub struct MaterializedPools {
     /// Locals signatures pool
     pub locals_signatures: Vec<LocalsSignature>,
     /// String pool
-    pub string_pool: Vec<String>,
+    pub string_pool: Vec<Identifier>,
     /// Byte array pool
     pub byte_array_pool: Vec<ByteArray>,
     /// Address pool
@@ -260,7 +265,10 @@ impl<'a> Context<'a> {
             struct_handles: Self::materialize_map(self.struct_handles),
             type_signatures: Self::materialize_map(self.type_signatures),
             locals_signatures: Self::materialize_map(self.locals_signatures),
-            string_pool: Self::materialize_map(self.string_pool),
+            string_pool: Self::materialize_map(self.string_pool)
+                .into_iter()
+                .map(Identifier::from)
+                .collect(),
             byte_array_pool: Self::materialize_map(self.byte_array_pool),
             address_pool: Self::materialize_map(self.address_pool),
         }
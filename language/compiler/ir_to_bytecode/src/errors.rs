@@ -2,10 +2,10 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use failure::Fail;
-use vm::errors::VerificationError;
+use vm::check_bounds::FreezeError;
 
 #[derive(Clone, Debug, Eq, Fail, Ord, PartialEq, PartialOrd)]
 pub enum InternalCompilerError {
-    #[fail(display = "Post-compile bounds check errors: {:?}", _0)]
-    BoundsCheckErrors(Vec<VerificationError>),
+    #[fail(display = "Post-compile bounds check errors: {}", _0)]
+    BoundsCheckErrors(FreezeError),
 }
@@ -10,8 +10,8 @@ use std::collections::{BTreeMap, BTreeSet};
 use vm::{
     access::ModuleAccess,
     file_format::{
-        FieldDefinitionIndex, FunctionHandleIndex, ModuleHandleIndex, SignatureToken,
-        StructDefinitionIndex, StructHandleIndex,
+        FieldDefinitionIndex, FunctionHandleIndex, SignatureToken, StructDefinitionIndex,
+        StructHandleIndex,
     },
     internals::ModuleIndex,
     views::{
@@ -38,9 +38,7 @@ impl BoogieTranslator {
         let mut struct_defs: BTreeMap<String, usize> = BTreeMap::new();
         let mut module_name_to_idx: BTreeMap<String, usize> = BTreeMap::new();
         for (module_idx, module) in modules.iter().enumerate() {
-            let module_name = module
-                .string_at(module.module_handle_at(ModuleHandleIndex::new(0)).name)
-                .to_string();
+            let module_name = module.name().to_string();
             module_name_to_idx.insert(module_name.clone(), module_idx);
             for (idx, struct_def) in module.struct_defs().iter().enumerate() {
                 let struct_name = format!(
@@ -22,6 +22,7 @@ use vm::{
         StructDefinition, StructFieldInformation, StructHandle, StructHandleIndex, TableIndex,
         TypeSignature, TypeSignatureIndex,
     },
+    identifier::Identifier,
     internals::ModuleIndex,
 };
 
@@ -83,7 +84,10 @@ impl ModuleBuilder {
         let mut strs = (0..self.table_size)
             .map(|_| {
                 let len = self.gen.gen_range(1, MAX_STRING_SIZE);
-                (0..len).map(|_| self.gen.gen::<char>()).collect()
+                (0..len)
+                    .map(|_| self.gen.gen::<char>())
+                    .collect::<String>()
+                    .into()
             })
             .collect();
         self.module.string_pool.append(&mut strs);
@@ -102,10 +106,10 @@ impl ModuleBuilder {
     // Add the functions with locals given by the first part of the tuple, and with function
     // signature `FunctionSignature`.
     fn with_functions(&mut self, sigs: Vec<(Vec<SignatureToken>, FunctionSignature)>) {
-        let mut names: Vec<String> = sigs
+        let mut names: Vec<Identifier> = sigs
             .iter()
             .enumerate()
-            .map(|(i, _)| format!("func{}", i))
+            .map(|(i, _)| format!("func{}", i).into())
             .collect();
         // Grab the offset before adding the generated names to the string pool; we'll need this
         // later on when we generate the function handles in order to know where we should have the
@@ -125,9 +129,12 @@ impl ModuleBuilder {
             .collect();
         let (local_sigs, mut function_sigs): (Vec<_>, Vec<_>) = sigs.clone().into_iter().unzip();
         self.module.function_signatures.append(&mut function_sigs);
-        self.module
-            .locals_signatures
-            .append(&mut local_sigs.into_iter().map(LocalsSignature).collect());
+        self.module.locals_signatures.append(
+            &mut local_sigs
+                .into_iter()
+                .map(|locals| LocalsSignature(locals.into()))
+                .collect(),
+        );
 
         self.module.function_defs = sigs
             .iter()
@@ -159,8 +166,8 @@ impl ModuleBuilder {
     // The overall logic of this function follows very similarly to that for function generation.
     fn with_structs(&mut self) {
         // Generate struct names.
-        let mut names: Vec<String> = (0..self.table_size)
-            .map(|i| format!("struct{}", i))
+        let mut names: Vec<Identifier> = (0..self.table_size)
+            .map(|i| format!("struct{}", i).into())
             .collect();
         let offset = self.module.string_pool.len() as TableIndex;
         self.module.string_pool.append(&mut names);
@@ -298,8 +305,8 @@ impl ModuleBuilder {
                 .function_signature_at(callee_function_handle.signature)
                 .clone();
             let callee_name = callee_module
-                .string_at(callee_function_handle.name)
-                .to_string();
+                .identifier_at(callee_function_handle.name)
+                .clone();
             let callee_name_idx = self.module.string_pool.len() as TableIndex;
             let callee_type_sig_idx = self.module.function_signatures.len() as TableIndex;
             let func_handle = FunctionHandle {
@@ -319,7 +326,7 @@ impl ModuleBuilder {
     fn with_callee_modules(&mut self) {
         // Add the SELF module
         let module_name: String = (0..10).map(|_| self.gen.gen::<char>()).collect();
-        self.module.string_pool.insert(0, module_name);
+        self.module.string_pool.insert(0, module_name.into());
         self.module.address_pool.insert(0, AccountAddress::random());
         // Recall that we inserted the module name at index 0 in the string pool.
         let self_module_handle = ModuleHandle {
@@ -331,7 +338,7 @@ impl ModuleBuilder {
         let (mut names, mut addresses) = self
             .known_modules
             .keys()
-            .map(|key| (key.name().to_string(), key.address()))
+            .map(|key| (Identifier::new(key.name()), key.address()))
             .unzip();
 
         let address_pool_offset = self.module.address_pool.len() as TableIndex;
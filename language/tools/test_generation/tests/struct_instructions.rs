@@ -31,7 +31,7 @@ fn generate_module_with_struct(resource: bool) -> CompiledModuleMut {
     let struct_index = 0;
     let num_fields = 5;
     let offset = module.string_pool.len() as TableIndex;
-    module.string_pool.push("struct0".to_string());
+    module.string_pool.push("struct0".into());
 
     let field_information = StructFieldInformation::Declared {
         field_count: num_fields as MemberCount,
@@ -44,7 +44,7 @@ fn generate_module_with_struct(resource: bool) -> CompiledModuleMut {
     module.struct_defs.push(struct_def);
 
     for i in 0..num_fields {
-        module.string_pool.push(format!("string{}", i));
+        module.string_pool.push(format!("string{}", i).into());
         let struct_handle_idx = StructHandleIndex::new(struct_index);
         let typ_idx = TypeSignatureIndex::new(0);
         let str_pool_idx = StringPoolIndex::new(i + 1 as TableIndex);
@@ -10,26 +10,44 @@ use crate::{
         FieldDefinition, FieldDefinitionIndex, FunctionDefinition, FunctionDefinitionIndex,
         FunctionHandle, FunctionHandleIndex, FunctionSignature, FunctionSignatureIndex,
         LocalsSignature, LocalsSignatureIndex, MemberCount, ModuleHandle, ModuleHandleIndex,
-        StringPoolIndex, StructDefinition, StructDefinitionIndex, StructHandle, StructHandleIndex,
-        TypeSignature, TypeSignatureIndex,
+        StringPoolIndex, StructDefinition, StructDefinitionIndex, StructFieldInformation,
+        StructHandle, StructHandleIndex, TypeSignature, TypeSignatureIndex,
     },
+    identifier::Identifier,
     internals::ModuleIndex,
     IndexKind,
 };
+use std::sync::Arc;
 use types::{account_address::AccountAddress, byte_array::ByteArray, language_storage::ModuleId};
 
 /// Represents accessors for a compiled module.
 ///
-/// This is a trait to allow working across different wrappers for `CompiledModule`.
+/// This is a trait to allow working across different wrappers for `CompiledModule`. It is object
+/// safe, so module providers of different types can be held as `Box<dyn ModuleAccess>` or
+/// `Arc<dyn ModuleAccess + Send>`. Those pointer types (and references) implement the trait too,
+/// so they can be passed to generic code such as `ModuleView`.
 pub trait ModuleAccess: Sync {
     /// Returns the `CompiledModule` that will be used for accesses.
     fn as_module(&self) -> &CompiledModule;
 
     /// Returns the `ModuleHandle` for `self`.
     fn self_handle(&self) -> &ModuleHandle {
-        self.module_handle_at(ModuleHandleIndex::new(
-            CompiledModule::IMPLEMENTED_MODULE_INDEX,
-        ))
+        self.as_module()
+            .as_inner()
+            .module_handles
+            .get(CompiledModule::IMPLEMENTED_MODULE_INDEX as usize)
+            .expect("bounds-checked modules have a handle to themselves")
+    }
+
+    /// Returns the index of the handle to the module itself, which is always the first one.
+    fn self_handle_idx(&self) -> ModuleHandleIndex {
+        ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX)
+    }
+
+    /// Returns true if `idx` is the handle to the module itself, i.e. if struct and function
+    /// handles pointing to it refer to definitions in this module.
+    fn is_self_handle(&self, idx: ModuleHandleIndex) -> bool {
+        idx == self.self_handle_idx()
     }
 
     /// Returns the name of the module.
@@ -70,6 +88,10 @@ pub trait ModuleAccess: Sync {
         self.as_module().as_inner().string_pool[idx.into_index()].as_str()
     }
 
+    fn identifier_at(&self, idx: StringPoolIndex) -> &Identifier {
+        &self.as_module().as_inner().string_pool[idx.into_index()]
+    }
+
     fn byte_array_at(&self, idx: ByteArrayPoolIndex) -> &ByteArray {
         &self.as_module().as_inner().byte_array_pool[idx.into_index()]
     }
@@ -90,12 +112,96 @@ pub trait ModuleAccess: Sync {
         &self.as_module().as_inner().function_defs[idx.into_index()]
     }
 
+    // The `get_` accessors below return an error instead of panicking if the index is out of
+    // bounds, for code that may be handed malformed modules.
+
+    fn get_module_handle_at(
+        &self,
+        idx: ModuleHandleIndex,
+    ) -> Result<&ModuleHandle, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().module_handles, idx)
+    }
+
+    fn get_struct_handle_at(
+        &self,
+        idx: StructHandleIndex,
+    ) -> Result<&StructHandle, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().struct_handles, idx)
+    }
+
+    fn get_function_handle_at(
+        &self,
+        idx: FunctionHandleIndex,
+    ) -> Result<&FunctionHandle, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().function_handles, idx)
+    }
+
+    fn get_type_signature_at(
+        &self,
+        idx: TypeSignatureIndex,
+    ) -> Result<&TypeSignature, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().type_signatures, idx)
+    }
+
+    fn get_function_signature_at(
+        &self,
+        idx: FunctionSignatureIndex,
+    ) -> Result<&FunctionSignature, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().function_signatures, idx)
+    }
+
+    fn get_locals_signature_at(
+        &self,
+        idx: LocalsSignatureIndex,
+    ) -> Result<&LocalsSignature, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().locals_signatures, idx)
+    }
+
+    fn get_string_at(&self, idx: StringPoolIndex) -> Result<&str, VMStaticViolation> {
+        self.get_identifier_at(idx).map(Identifier::as_str)
+    }
+
+    fn get_identifier_at(&self, idx: StringPoolIndex) -> Result<&Identifier, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().string_pool, idx)
+    }
+
+    fn get_byte_array_at(&self, idx: ByteArrayPoolIndex) -> Result<&ByteArray, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().byte_array_pool, idx)
+    }
+
+    fn get_address_at(&self, idx: AddressPoolIndex) -> Result<&AccountAddress, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().address_pool, idx)
+    }
+
+    fn get_struct_def_at(
+        &self,
+        idx: StructDefinitionIndex,
+    ) -> Result<&StructDefinition, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().struct_defs, idx)
+    }
+
+    fn get_field_def_at(
+        &self,
+        idx: FieldDefinitionIndex,
+    ) -> Result<&FieldDefinition, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().field_defs, idx)
+    }
+
+    fn get_function_def_at(
+        &self,
+        idx: FunctionDefinitionIndex,
+    ) -> Result<&FunctionDefinition, VMStaticViolation> {
+        get_entry(&self.as_module().as_inner().function_defs, idx)
+    }
+
     fn get_field_signature(&self, field_definition_index: FieldDefinitionIndex) -> &TypeSignature {
         let field_definition = self.field_def_at(field_definition_index);
         self.type_signature_at(field_definition.signature)
     }
 
-    // XXX is a partial range required here?
+    // The accessors below return whole tables, so that code iterating over a table can walk the
+    // slice directly instead of looking up each entry by index.
+
     fn module_handles(&self) -> &[ModuleHandle] {
         &self.as_module().as_inner().module_handles
     }
@@ -128,7 +234,7 @@ pub trait ModuleAccess: Sync {
         &self.as_module().as_inner().address_pool
     }
 
-    fn string_pool(&self) -> &[String] {
+    fn string_pool(&self) -> &[Identifier] {
         &self.as_module().as_inner().string_pool
     }
 
@@ -165,6 +271,34 @@ pub trait ModuleAccess: Sync {
         &self.as_module().as_inner().field_defs[first_field..last_field]
     }
 
+    /// Like `field_def_range`, but returns an error instead of panicking if the range is out of
+    /// bounds.
+    fn get_field_def_range(
+        &self,
+        field_count: MemberCount,
+        first_field: FieldDefinitionIndex,
+    ) -> Result<&[FieldDefinition], VMStaticViolation> {
+        let inner = self.as_module().as_inner();
+        match inner.check_field_range(field_count, first_field) {
+            Some(err) => Err(err),
+            None => {
+                let first_field = first_field.into_index();
+                Ok(&inner.field_defs[first_field..first_field + field_count as usize])
+            }
+        }
+    }
+
+    /// Returns the fields declared by `struct_def`, or `None` if it is a native struct.
+    fn struct_def_fields(&self, struct_def: &StructDefinition) -> Option<&[FieldDefinition]> {
+        match struct_def.field_information {
+            StructFieldInformation::Native => None,
+            StructFieldInformation::Declared {
+                field_count,
+                fields,
+            } => Some(self.field_def_range(field_count, fields)),
+        }
+    }
+
     fn is_field_in_struct(
         &self,
         field_definition_index: FieldDefinitionIndex,
@@ -177,7 +311,8 @@ pub trait ModuleAccess: Sync {
 
 /// Represents accessors for a compiled script.
 ///
-/// This is a trait to allow working across different wrappers for `CompiledScript`.
+/// This is a trait to allow working across different wrappers for `CompiledScript`. Like
+/// `ModuleAccess`, it is object safe.
 pub trait ScriptAccess: Sync {
     /// Returns the `CompiledScript` that will be used for accesses.
     fn as_script(&self) -> &CompiledScript;
@@ -210,6 +345,10 @@ pub trait ScriptAccess: Sync {
         self.as_script().as_inner().string_pool[idx.into_index()].as_str()
     }
 
+    fn identifier_at(&self, idx: StringPoolIndex) -> &Identifier {
+        &self.as_script().as_inner().string_pool[idx.into_index()]
+    }
+
     fn byte_array_at(&self, idx: ByteArrayPoolIndex) -> &ByteArray {
         &self.as_script().as_inner().byte_array_pool[idx.into_index()]
     }
@@ -218,6 +357,67 @@ pub trait ScriptAccess: Sync {
         &self.as_script().as_inner().address_pool[idx.into_index()]
     }
 
+    // The `get_` accessors below return an error instead of panicking if the index is out of
+    // bounds, for code that may be handed malformed modules.
+
+    fn get_module_handle_at(
+        &self,
+        idx: ModuleHandleIndex,
+    ) -> Result<&ModuleHandle, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().module_handles, idx)
+    }
+
+    fn get_struct_handle_at(
+        &self,
+        idx: StructHandleIndex,
+    ) -> Result<&StructHandle, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().struct_handles, idx)
+    }
+
+    fn get_function_handle_at(
+        &self,
+        idx: FunctionHandleIndex,
+    ) -> Result<&FunctionHandle, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().function_handles, idx)
+    }
+
+    fn get_type_signature_at(
+        &self,
+        idx: TypeSignatureIndex,
+    ) -> Result<&TypeSignature, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().type_signatures, idx)
+    }
+
+    fn get_function_signature_at(
+        &self,
+        idx: FunctionSignatureIndex,
+    ) -> Result<&FunctionSignature, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().function_signatures, idx)
+    }
+
+    fn get_locals_signature_at(
+        &self,
+        idx: LocalsSignatureIndex,
+    ) -> Result<&LocalsSignature, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().locals_signatures, idx)
+    }
+
+    fn get_string_at(&self, idx: StringPoolIndex) -> Result<&str, VMStaticViolation> {
+        self.get_identifier_at(idx).map(Identifier::as_str)
+    }
+
+    fn get_identifier_at(&self, idx: StringPoolIndex) -> Result<&Identifier, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().string_pool, idx)
+    }
+
+    fn get_byte_array_at(&self, idx: ByteArrayPoolIndex) -> Result<&ByteArray, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().byte_array_pool, idx)
+    }
+
+    fn get_address_at(&self, idx: AddressPoolIndex) -> Result<&AccountAddress, VMStaticViolation> {
+        get_entry(&self.as_script().as_inner().address_pool, idx)
+    }
+
     fn module_handles(&self) -> &[ModuleHandle] {
         &self.as_script().as_inner().module_handles
     }
@@ -250,7 +450,7 @@ pub trait ScriptAccess: Sync {
         &self.as_script().as_inner().address_pool
     }
 
-    fn string_pool(&self) -> &[String] {
+    fn string_pool(&self) -> &[Identifier] {
         &self.as_script().as_inner().string_pool
     }
 
@@ -259,6 +459,14 @@ pub trait ScriptAccess: Sync {
     }
 }
 
+/// Returns the entry of `table` at `idx`, or an error if `idx` is out of bounds.
+fn get_entry<I: ModuleIndex, T>(table: &[T], idx: I) -> Result<&T, VMStaticViolation> {
+    let idx = idx.into_index();
+    table
+        .get(idx)
+        .ok_or_else(|| VMStaticViolation::IndexOutOfBounds(I::KIND, table.len(), idx))
+}
+
 impl ModuleAccess for CompiledModule {
     fn as_module(&self) -> &CompiledModule {
         self
@@ -271,6 +479,42 @@ impl ScriptAccess for CompiledScript {
     }
 }
 
+impl<T: ModuleAccess + ?Sized> ModuleAccess for &T {
+    fn as_module(&self) -> &CompiledModule {
+        (**self).as_module()
+    }
+}
+
+impl<T: ModuleAccess + ?Sized> ModuleAccess for Box<T> {
+    fn as_module(&self) -> &CompiledModule {
+        (**self).as_module()
+    }
+}
+
+impl<T: ModuleAccess + Send + ?Sized> ModuleAccess for Arc<T> {
+    fn as_module(&self) -> &CompiledModule {
+        (**self).as_module()
+    }
+}
+
+impl<T: ScriptAccess + ?Sized> ScriptAccess for &T {
+    fn as_script(&self) -> &CompiledScript {
+        (**self).as_script()
+    }
+}
+
+impl<T: ScriptAccess + ?Sized> ScriptAccess for Box<T> {
+    fn as_script(&self) -> &CompiledScript {
+        (**self).as_script()
+    }
+}
+
+impl<T: ScriptAccess + Send + ?Sized> ScriptAccess for Arc<T> {
+    fn as_script(&self) -> &CompiledScript {
+        (**self).as_script()
+    }
+}
+
 impl CompiledModuleMut {
     #[inline]
     pub(crate) fn check_field_range(
@@ -0,0 +1,415 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Incremental construction of modules.
+//!
+//! Assembling a `CompiledModuleMut` by hand means keeping every table index in sync: pools must
+//! not contain duplicates, the fields of a struct must be consecutive, and branch offsets must be
+//! recomputed whenever code is added. `CompiledModuleBuilder` takes care of this bookkeeping: it
+//! interns strings, addresses, signatures and handles as they are used, and `CodeBuilder` lets
+//! code branch to labels that are resolved to offsets once the code is complete.
+//!
+//! The builder only guarantees that the module is bounds-valid. It is still up to the caller to
+//! build a module that passes the bytecode verifier.
+
+use crate::{
+    check_bounds::FreezeError,
+    file_format::{
+        AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CheckedCodeOffset, CodeOffset, CodeUnit,
+        CompiledModule, CompiledModuleMut, FieldDefinition, FieldDefinitionIndex,
+        FunctionDefinition, FunctionDefinitionIndex, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, Kind, LocalsSignature, LocalsSignatureIndex,
+        MemberCount, ModuleHandle, ModuleHandleIndex, SignatureToken, StringPoolIndex,
+        StructDefinition, StructDefinitionIndex, StructFieldInformation, StructHandle,
+        StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex,
+    },
+    identifier::Identifier,
+};
+use std::{collections::HashMap, hash::Hash};
+use types::{account_address::AccountAddress, byte_array::ByteArray};
+
+/// Builds a `CompiledModule` one definition at a time.
+///
+/// ```
+/// use types::account_address::AccountAddress;
+/// use vm::{
+///     builder::{CodeBuilder, CompiledModuleBuilder},
+///     file_format::{Bytecode, CodeUnit, FunctionSignature, SignatureToken},
+/// };
+///
+/// let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+/// builder.add_struct("S", false, vec![], vec![("value", SignatureToken::U64)]);
+///
+/// let mut code = CodeBuilder::new();
+/// let done = code.new_label();
+/// code.emit(Bytecode::CopyLoc(0));
+/// code.emit_branch(Bytecode::BrFalse, done);
+/// code.emit(Bytecode::LdConst(1));
+/// code.emit(Bytecode::StLoc(0));
+/// code.bind(done);
+/// code.emit(Bytecode::Ret);
+/// let signature = FunctionSignature {
+///     arg_types: vec![SignatureToken::Bool].into(),
+///     return_types: vec![].into(),
+///     type_formals: vec![],
+/// };
+/// builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+///
+/// let module = builder.build().expect("the module is bounds-valid");
+/// ```
+#[derive(Clone, Debug)]
+pub struct CompiledModuleBuilder {
+    module: CompiledModuleMut,
+    strings: Interner<Identifier, StringPoolIndex>,
+    byte_arrays: Interner<ByteArray, ByteArrayPoolIndex>,
+    addresses: Interner<AccountAddress, AddressPoolIndex>,
+    type_signatures: Interner<TypeSignature, TypeSignatureIndex>,
+    function_signatures: Interner<FunctionSignature, FunctionSignatureIndex>,
+    locals_signatures: Interner<LocalsSignature, LocalsSignatureIndex>,
+    module_handles: Interner<ModuleHandle, ModuleHandleIndex>,
+    struct_handles: HashMap<(ModuleHandleIndex, StringPoolIndex), StructHandleIndex>,
+    function_handles: HashMap<(ModuleHandleIndex, StringPoolIndex), FunctionHandleIndex>,
+}
+
+impl CompiledModuleBuilder {
+    /// Returns a builder for the module `name` published under `address`, with no definitions.
+    pub fn new(address: AccountAddress, name: &str) -> Self {
+        let mut builder = Self {
+            module: CompiledModuleMut::default(),
+            strings: Interner::default(),
+            byte_arrays: Interner::default(),
+            addresses: Interner::default(),
+            type_signatures: Interner::default(),
+            function_signatures: Interner::default(),
+            locals_signatures: Interner::default(),
+            module_handles: Interner::default(),
+            struct_handles: HashMap::new(),
+            function_handles: HashMap::new(),
+        };
+        // The module handle of the module itself comes first, and the empty locals signature
+        // must be `NO_TYPE_ACTUALS`.
+        builder.add_module_handle(address, name);
+        builder.intern_locals_signature(LocalsSignature::default());
+        builder
+    }
+
+    /// Returns the index of `string` in the string pool, adding it if needed.
+    pub fn intern_string(&mut self, string: &str) -> StringPoolIndex {
+        self.strings.intern(
+            Identifier::new(string),
+            &mut self.module.string_pool,
+            StringPoolIndex::new,
+        )
+    }
+
+    /// Returns the index of `byte_array` in the byte array pool, adding it if needed.
+    pub fn intern_byte_array(&mut self, byte_array: ByteArray) -> ByteArrayPoolIndex {
+        self.byte_arrays.intern(
+            byte_array,
+            &mut self.module.byte_array_pool,
+            ByteArrayPoolIndex::new,
+        )
+    }
+
+    /// Returns the index of `address` in the address pool, adding it if needed.
+    pub fn intern_address(&mut self, address: AccountAddress) -> AddressPoolIndex {
+        self.addresses.intern(
+            address,
+            &mut self.module.address_pool,
+            AddressPoolIndex::new,
+        )
+    }
+
+    /// Returns the index of `token` in the type signature pool, adding it if needed.
+    pub fn intern_type_signature(&mut self, token: SignatureToken) -> TypeSignatureIndex {
+        self.type_signatures.intern(
+            TypeSignature(token),
+            &mut self.module.type_signatures,
+            TypeSignatureIndex::new,
+        )
+    }
+
+    /// Returns the index of `signature` in the function signature pool, adding it if needed.
+    pub fn intern_function_signature(
+        &mut self,
+        signature: FunctionSignature,
+    ) -> FunctionSignatureIndex {
+        self.function_signatures.intern(
+            signature,
+            &mut self.module.function_signatures,
+            FunctionSignatureIndex::new,
+        )
+    }
+
+    /// Returns the index of `signature` in the locals signature pool, adding it if needed. Type
+    /// actuals are locals signatures as well.
+    pub fn intern_locals_signature(&mut self, signature: LocalsSignature) -> LocalsSignatureIndex {
+        self.locals_signatures.intern(
+            signature,
+            &mut self.module.locals_signatures,
+            LocalsSignatureIndex::new,
+        )
+    }
+
+    /// Returns the handle to the module `name` published under `address`, adding it if needed.
+    pub fn add_module_handle(&mut self, address: AccountAddress, name: &str) -> ModuleHandleIndex {
+        let handle = ModuleHandle {
+            address: self.intern_address(address),
+            name: self.intern_string(name),
+        };
+        self.module_handles.intern(
+            handle,
+            &mut self.module.module_handles,
+            ModuleHandleIndex::new,
+        )
+    }
+
+    /// Returns the handle to the struct `name` of the module `module`, adding it if needed. The
+    /// kind and type formals of an existing handle are left alone.
+    pub fn add_struct_handle(
+        &mut self,
+        module: ModuleHandleIndex,
+        name: &str,
+        is_nominal_resource: bool,
+        type_formals: Vec<Kind>,
+    ) -> StructHandleIndex {
+        let name = self.intern_string(name);
+        let struct_handles = &mut self.module.struct_handles;
+        *self
+            .struct_handles
+            .entry((module, name))
+            .or_insert_with(|| {
+                struct_handles.push(StructHandle {
+                    module,
+                    name,
+                    is_nominal_resource,
+                    type_formals,
+                });
+                StructHandleIndex::new((struct_handles.len() - 1) as TableIndex)
+            })
+    }
+
+    /// Returns the handle to the function `name` of the module `module`, adding it if needed. The
+    /// signature of an existing handle is left alone.
+    pub fn add_function_handle(
+        &mut self,
+        module: ModuleHandleIndex,
+        name: &str,
+        signature: FunctionSignature,
+    ) -> FunctionHandleIndex {
+        let name = self.intern_string(name);
+        if let Some(idx) = self.function_handles.get(&(module, name)) {
+            return *idx;
+        }
+        let signature = self.intern_function_signature(signature);
+        self.module.function_handles.push(FunctionHandle {
+            module,
+            name,
+            signature,
+        });
+        let idx = FunctionHandleIndex::new((self.module.function_handles.len() - 1) as TableIndex);
+        self.function_handles.insert((module, name), idx);
+        idx
+    }
+
+    /// Defines the struct `name` with `fields`, given as names and types, in that order.
+    pub fn add_struct(
+        &mut self,
+        name: &str,
+        is_nominal_resource: bool,
+        type_formals: Vec<Kind>,
+        fields: Vec<(&str, SignatureToken)>,
+    ) -> StructDefinitionIndex {
+        let struct_handle =
+            self.add_struct_handle(Self::self_handle(), name, is_nominal_resource, type_formals);
+        let first_field = FieldDefinitionIndex::new(self.module.field_defs.len() as TableIndex);
+        let field_count = fields.len() as MemberCount;
+        for (field_name, token) in fields {
+            let field = FieldDefinition {
+                struct_: struct_handle,
+                name: self.intern_string(field_name),
+                signature: self.intern_type_signature(token),
+            };
+            self.module.field_defs.push(field);
+        }
+        self.push_struct_definition(
+            struct_handle,
+            StructFieldInformation::Declared {
+                field_count,
+                fields: first_field,
+            },
+        )
+    }
+
+    /// Declares the native struct `name`.
+    pub fn add_native_struct(
+        &mut self,
+        name: &str,
+        is_nominal_resource: bool,
+        type_formals: Vec<Kind>,
+    ) -> StructDefinitionIndex {
+        let struct_handle =
+            self.add_struct_handle(Self::self_handle(), name, is_nominal_resource, type_formals);
+        self.push_struct_definition(struct_handle, StructFieldInformation::Native)
+    }
+
+    /// Defines the function `name` with `code`. `locals` are the locals of the function past its
+    /// arguments, and `flags` are the flags of `CodeUnit` (e.g. `CodeUnit::PUBLIC`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label `code` branches to was never bound.
+    pub fn add_function(
+        &mut self,
+        name: &str,
+        flags: u8,
+        signature: FunctionSignature,
+        locals: Vec<SignatureToken>,
+        acquires_global_resources: Vec<StructDefinitionIndex>,
+        code: CodeBuilder,
+    ) -> FunctionDefinitionIndex {
+        let all_locals = signature.arg_types.iter().cloned().chain(locals).collect();
+        let function = self.add_function_handle(Self::self_handle(), name, signature);
+        let locals = self.intern_locals_signature(LocalsSignature(all_locals));
+        self.module.function_defs.push(FunctionDefinition {
+            function,
+            flags,
+            acquires_global_resources,
+            code: CodeUnit {
+                max_stack_size: 0,
+                locals,
+                code: code.into_code(),
+            },
+        });
+        FunctionDefinitionIndex::new((self.module.function_defs.len() - 1) as TableIndex)
+    }
+
+    /// Returns the module built so far, without checking its bounds.
+    pub fn as_inner(&self) -> &CompiledModuleMut {
+        &self.module
+    }
+
+    /// Returns the module, or the bounds errors that prevent it from being a `CompiledModule`.
+    ///
+    /// The builder only creates valid indexes itself, so errors can only come from indexes given
+    /// to it, like the operands of instructions.
+    pub fn build(self) -> Result<CompiledModule, FreezeError> {
+        self.module.freeze()
+    }
+
+    fn self_handle() -> ModuleHandleIndex {
+        ModuleHandleIndex::new(CompiledModule::IMPLEMENTED_MODULE_INDEX)
+    }
+
+    fn push_struct_definition(
+        &mut self,
+        struct_handle: StructHandleIndex,
+        field_information: StructFieldInformation,
+    ) -> StructDefinitionIndex {
+        self.module.struct_defs.push(StructDefinition {
+            struct_handle,
+            field_information,
+        });
+        StructDefinitionIndex::new((self.module.struct_defs.len() - 1) as TableIndex)
+    }
+}
+
+/// A label in the code of a `CodeBuilder`, which branches can target before it is bound to an
+/// offset.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct Label(usize);
+
+/// Builds the code of a function, resolving the labels branches target to offsets.
+#[derive(Clone, Debug, Default)]
+pub struct CodeBuilder {
+    code: Vec<Bytecode>,
+    /// The offset each label is bound to, if it was bound already.
+    labels: Vec<Option<CodeOffset>>,
+    /// The branches to patch once every label is bound, along with the labels they target.
+    branches: Vec<(usize, Label)>,
+}
+
+impl CodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a new label, to be bound with `bind`.
+    pub fn new_label(&mut self) -> Label {
+        self.labels.push(None);
+        Label(self.labels.len() - 1)
+    }
+
+    /// Binds `label` to the offset of the next instruction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `label` was bound already, or if the code is too long for a branch to reach the
+    /// next instruction.
+    pub fn bind(&mut self, label: Label) {
+        let next = CheckedCodeOffset::from_index(self.code.len()).unwrap_or_else(|| {
+            panic!(
+                "{:?} is bound past the last offset a branch can reach",
+                label
+            )
+        });
+        let offset = &mut self.labels[label.0];
+        assert!(offset.is_none(), "{:?} is bound twice", label);
+        *offset = Some(next.into_operand());
+    }
+
+    /// Appends `bytecode`, whose branch offset is left alone if it is a branch.
+    pub fn emit(&mut self, bytecode: Bytecode) {
+        self.code.push(bytecode);
+    }
+
+    /// Appends a branch to `label`, e.g. `emit_branch(Bytecode::BrTrue, label)`.
+    pub fn emit_branch(&mut self, branch: fn(CodeOffset) -> Bytecode, label: Label) {
+        self.branches.push((self.code.len(), label));
+        self.code.push(branch(0));
+    }
+
+    /// Returns the code, with the offsets of branches to labels resolved.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a label was branched to but never bound.
+    pub fn into_code(mut self) -> Vec<Bytecode> {
+        for (idx, label) in self.branches {
+            let target = self.labels[label.0]
+                .unwrap_or_else(|| panic!("{:?} is branched to but never bound", label));
+            match &mut self.code[idx] {
+                Bytecode::BrTrue(offset) | Bytecode::BrFalse(offset) | Bytecode::Branch(offset) => {
+                    *offset = target
+                }
+                bytecode => panic!("{:?} is not a branch", bytecode),
+            }
+        }
+        self.code
+    }
+}
+
+/// Maps the entries of a pool to their indexes, so that every entry is only added once.
+#[derive(Clone, Debug)]
+struct Interner<T, I> {
+    indexes: HashMap<T, I>,
+}
+
+impl<T, I> Default for Interner<T, I> {
+    fn default() -> Self {
+        Self {
+            indexes: HashMap::new(),
+        }
+    }
+}
+
+impl<T: Clone + Eq + Hash, I: Copy> Interner<T, I> {
+    /// Returns the index of `entry` in `pool`, pushing it first if it isn't there yet. `index`
+    /// builds an index from its position in the pool.
+    fn intern(&mut self, entry: T, pool: &mut Vec<T>, index: fn(TableIndex) -> I) -> I {
+        *self.indexes.entry(entry.clone()).or_insert_with(|| {
+            pool.push(entry);
+            index((pool.len() - 1) as TableIndex)
+        })
+    }
+}
@@ -2,122 +2,143 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    errors::{VMStaticViolation, VerificationError},
+    errors::{CappedErrors, VMStaticViolation, VerificationError},
     file_format::{
-        Bytecode, CompiledModuleMut, FieldDefinition, FunctionDefinition, FunctionHandle,
-        FunctionSignature, LocalsSignature, ModuleHandle, SignatureToken, StructDefinition,
-        StructFieldInformation, StructHandle, TypeSignature,
+        Bytecode, CodeOffset, CompiledModuleMut, FieldDefinition, FunctionDefinition,
+        FunctionDefinitionIndex, FunctionHandle, FunctionSignature, LocalsSignature, ModuleHandle,
+        SignatureToken, StructDefinition, StructFieldInformation, StructHandle, TableIndex,
+        TypeSignature,
     },
     internals::ModuleIndex,
     IndexKind,
 };
+use std::fmt;
 
 pub struct BoundsChecker<'a> {
     module: &'a CompiledModuleMut,
+    max_errors: usize,
 }
 
 impl<'a> BoundsChecker<'a> {
     pub fn new(module: &'a CompiledModuleMut) -> Self {
-        Self { module }
+        Self {
+            module,
+            max_errors: usize::max_value(),
+        }
+    }
+
+    /// Stores at most `max_errors` errors. Any further errors are only counted (see
+    /// `CappedErrors`).
+    pub fn with_max_errors(mut self, max_errors: usize) -> Self {
+        self.max_errors = max_errors;
+        self
     }
 
     pub fn verify(self) -> Vec<VerificationError> {
-        let mut errors: Vec<Vec<_>> = vec![];
+        self.verify_capped().into_errors()
+    }
+
+    /// Like `verify`, but also reports how many errors weren't stored because of the limit set with
+    /// `with_max_errors`.
+    pub fn verify_capped(self) -> CappedErrors {
+        let mut errors = CappedErrors::new(self.max_errors);
 
         // A module (or script) must always have at least one module handle. (For modules the first
         // handle should be the same as the sender -- the bytecode verifier is unaware of
         // transactions so it does not perform this check.
         if self.module.module_handles.is_empty() {
-            errors.push(vec![VerificationError {
-                kind: IndexKind::ModuleHandle,
-                idx: 0,
-                err: VMStaticViolation::NoModuleHandles,
-            }]);
+            errors.push(VerificationError::new(
+                IndexKind::ModuleHandle,
+                0,
+                VMStaticViolation::NoModuleHandles,
+            ));
         }
 
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::ModuleHandle,
             self.module.module_handles.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::StructHandle,
             self.module.struct_handles.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::FunctionHandle,
             self.module.function_handles.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::StructDefinition,
             self.module.struct_defs.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::FieldDefinition,
             self.module.field_defs.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::FunctionDefinition,
             self.module.function_defs.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::TypeSignature,
             self.module.type_signatures.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::FunctionSignature,
             self.module.function_signatures.iter(),
             self.module,
         ));
-        errors.push(Self::verify_impl(
+        errors.extend(Self::verify_impl(
             IndexKind::LocalsSignature,
             self.module.locals_signatures.iter(),
             self.module,
         ));
 
-        let errors: Vec<_> = errors.into_iter().flatten().collect();
         if !errors.is_empty() {
             return errors;
         }
 
         // Code unit checking needs to be done once the rest of the module is validated.
-        self.module
-            .function_defs
-            .iter()
-            .enumerate()
-            .map(|(idx, elem)| {
-                elem.check_code_unit_bounds(self.module)
-                    .into_iter()
-                    .map(move |err| VerificationError {
-                        kind: IndexKind::FunctionDefinition,
-                        idx,
-                        err,
-                    })
-            })
-            .flatten()
-            .collect()
+        let module = self.module;
+        errors.extend(
+            module
+                .function_defs
+                .iter()
+                .enumerate()
+                .map(move |(idx, elem)| {
+                    elem.check_code_unit_bounds(module)
+                        .into_iter()
+                        .map(move |err| {
+                            VerificationError::in_function(
+                                FunctionDefinitionIndex::new(idx as TableIndex),
+                                err,
+                            )
+                        })
+                })
+                .flatten(),
+        );
+        errors
     }
 
     #[inline]
-    fn verify_impl(
+    fn verify_impl<'b>(
         kind: IndexKind,
-        iter: impl Iterator<Item = impl BoundsCheck>,
-        module: &CompiledModuleMut,
-    ) -> Vec<VerificationError> {
+        iter: impl Iterator<Item = impl BoundsCheck> + 'b,
+        module: &'b CompiledModuleMut,
+    ) -> impl Iterator<Item = VerificationError> + 'b {
         iter.enumerate()
             .map(move |(idx, elem)| {
                 elem.check_bounds(module)
                     .into_iter()
-                    .map(move |err| VerificationError { kind, idx, err })
+                    .map(move |err| VerificationError::new(kind, idx, err))
             })
             .flatten()
-            .collect()
     }
 }
 
@@ -313,6 +334,10 @@ impl FunctionDefinition {
 
         let code = &self.code.code;
         let code_len = code.len();
+        // Every instruction, and the end of the code unit, must have a code offset.
+        if code_len > CodeOffset::max_value() as usize {
+            return vec![VMStaticViolation::CodeUnitTooLong(code_len)];
+        }
 
         code.iter()
             .enumerate()
@@ -386,3 +411,133 @@ impl FunctionDefinition {
             .collect()
     }
 }
+
+/// Why `CompiledModuleMut::freeze` or `CompiledScriptMut::freeze` rejected a module: the
+/// violations found by the `BoundsChecker`.
+///
+/// Unlike a list of `VerificationError`s, a `FreezeError` displays (and debug-prints, so that
+/// `freeze().unwrap()` is readable) one line per broken invariant, naming the table, the entry
+/// and, when it can be told apart, the field of the entry at fault.
+#[derive(Clone, Eq, Ord, PartialEq, PartialOrd)]
+pub struct FreezeError {
+    errors: Vec<VerificationError>,
+}
+
+impl FreezeError {
+    pub(crate) fn new(errors: Vec<VerificationError>) -> Self {
+        Self { errors }
+    }
+
+    /// Returns the violations, as reported by the `BoundsChecker`.
+    pub fn errors(&self) -> &[VerificationError] {
+        &self.errors
+    }
+
+    /// Returns the violations, as reported by the `BoundsChecker`.
+    pub fn into_errors(self) -> Vec<VerificationError> {
+        self.errors
+    }
+
+    /// Returns a description of every broken invariant, in the order they were found.
+    pub fn diagnostics(&self) -> Vec<FreezeDiagnostic> {
+        self.errors.iter().map(FreezeDiagnostic::new).collect()
+    }
+}
+
+impl From<FreezeError> for Vec<VerificationError> {
+    fn from(err: FreezeError) -> Self {
+        err.into_errors()
+    }
+}
+
+impl fmt::Display for FreezeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "module fails {} bounds checks", self.errors.len())?;
+        for diagnostic in self.diagnostics() {
+            write!(f, "\n  {}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for FreezeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for FreezeError {}
+
+/// An invariant of a module broken by one entry of one of its tables.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FreezeDiagnostic {
+    /// The table holding the entry at fault.
+    pub table: IndexKind,
+    /// The index of the entry in `table`.
+    pub index: usize,
+    /// The field of the entry at fault (e.g. `"signature"` for a function handle), or `None` if
+    /// the violation doesn't identify a single field.
+    pub field: Option<&'static str>,
+    /// The offset of the instruction at fault, for violations in the code of a function.
+    pub code_offset: Option<CodeOffset>,
+    /// The invariant that is broken.
+    pub reason: VMStaticViolation,
+}
+
+impl FreezeDiagnostic {
+    fn new(error: &VerificationError) -> Self {
+        Self {
+            table: error.kind,
+            index: error.idx,
+            field: field_at_fault(error.kind, &error.err),
+            code_offset: error.code_offset,
+            reason: error.err.clone(),
+        }
+    }
+}
+
+impl fmt::Display for FreezeDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} #{}", self.table, self.index)?;
+        if let Some(field) = self.field {
+            write!(f, ", field `{}`", field)?;
+        }
+        if let Some(code_offset) = self.code_offset {
+            write!(f, ", code offset {}", code_offset)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// Returns the field of an entry of `table` that the bounds checks report `reason` for, if only
+/// one field can have caused it. This mirrors the `BoundsCheck` implementations above.
+fn field_at_fault(table: IndexKind, reason: &VMStaticViolation) -> Option<&'static str> {
+    let target = match reason {
+        VMStaticViolation::IndexOutOfBounds(target, _, _)
+        | VMStaticViolation::RangeOutOfBounds(target, _, _, _) => *target,
+        VMStaticViolation::CodeUnitIndexOutOfBounds(..) | VMStaticViolation::CodeUnitTooLong(_) => {
+            return Some("code")
+        }
+        _ => return None,
+    };
+    let field = match (table, target) {
+        (IndexKind::ModuleHandle, IndexKind::AddressPool) => "address",
+        (IndexKind::ModuleHandle, IndexKind::StringPool)
+        | (IndexKind::StructHandle, IndexKind::StringPool)
+        | (IndexKind::FunctionHandle, IndexKind::StringPool)
+        | (IndexKind::FieldDefinition, IndexKind::StringPool) => "name",
+        (IndexKind::StructHandle, IndexKind::ModuleHandle)
+        | (IndexKind::FunctionHandle, IndexKind::ModuleHandle) => "module",
+        (IndexKind::FunctionHandle, IndexKind::FunctionSignature)
+        | (IndexKind::FieldDefinition, IndexKind::TypeSignature) => "signature",
+        (IndexKind::StructDefinition, IndexKind::StructHandle) => "struct_handle",
+        (IndexKind::StructDefinition, IndexKind::FieldDefinition) => "field_information",
+        (IndexKind::FieldDefinition, IndexKind::StructHandle) => "struct_",
+        (IndexKind::FunctionDefinition, IndexKind::FunctionHandle) => "function",
+        (IndexKind::FunctionDefinition, IndexKind::LocalsSignature) => "code.locals",
+        (IndexKind::FunctionDefinition, IndexKind::StructDefinition) => "acquires_global_resources",
+        (IndexKind::TypeSignature, IndexKind::StructHandle) => "0",
+        _ => return None,
+    };
+    Some(field)
+}
@@ -0,0 +1,203 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines coverage maps, which record the instructions a test suite executed, so that the
+//! coverage of Move modules can be measured at the bytecode level.
+//!
+//! Modules are identified by the hash of their serialized form rather than by their ID, so that
+//! coverage recorded against one version of a module is never attributed to another version
+//! published under the same ID.
+
+use crate::{
+    access::ModuleAccess,
+    errors::short_address,
+    file_format::{CodeOffset, CompiledModule, FunctionDefinitionIndex, TableIndex},
+    package::module_digest,
+    printers::display_function_definition,
+    source_map::SourceMap,
+    views::ModuleView,
+};
+use crypto::HashValue;
+use failure::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// The offsets executed in every function of a module, by function name.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ModuleCoverage {
+    pub functions: BTreeMap<String, BTreeSet<CodeOffset>>,
+}
+
+impl ModuleCoverage {
+    /// Returns true if the instruction at `offset` in function `function` was executed.
+    pub fn is_covered(&self, function: &str, offset: CodeOffset) -> bool {
+        self.functions
+            .get(function)
+            .map_or(false, |offsets| offsets.contains(&offset))
+    }
+
+    /// Adds the offsets executed according to `other`.
+    pub fn merge(&mut self, other: ModuleCoverage) {
+        for (function, offsets) in other.functions {
+            self.functions
+                .entry(function)
+                .or_insert_with(BTreeSet::new)
+                .extend(offsets);
+        }
+    }
+}
+
+/// The offsets executed in every function of every module, by module hash.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CoverageMap {
+    modules: BTreeMap<HashValue, ModuleCoverage>,
+}
+
+impl CoverageMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the hash `module` is identified by in coverage maps, which is its digest.
+    pub fn module_hash(module: &CompiledModule) -> Result<HashValue> {
+        module_digest(module)
+    }
+
+    /// Records that the instruction at `offset` in function `function` of the module with hash
+    /// `module_hash` was executed.
+    pub fn record(&mut self, module_hash: HashValue, function: &str, offset: CodeOffset) {
+        self.modules
+            .entry(module_hash)
+            .or_insert_with(ModuleCoverage::default)
+            .functions
+            .entry(function.to_string())
+            .or_insert_with(BTreeSet::new)
+            .insert(offset);
+    }
+
+    /// Returns the coverage of the module with hash `module_hash`, if any of its instructions was
+    /// executed.
+    pub fn module(&self, module_hash: &HashValue) -> Option<&ModuleCoverage> {
+        self.modules.get(module_hash)
+    }
+
+    /// Adds the offsets executed according to `other`, e.g. to combine the coverage of several
+    /// test runs.
+    pub fn merge(&mut self, other: CoverageMap) {
+        for (module_hash, coverage) in other.modules {
+            self.modules
+                .entry(module_hash)
+                .or_insert_with(ModuleCoverage::default)
+                .merge(coverage);
+        }
+    }
+
+    /// Serializes the map to JSON. Modules are keyed by the hex encoding of their hash.
+    pub fn to_json(&self) -> Result<String> {
+        let modules: BTreeMap<_, _> = self
+            .modules
+            .iter()
+            .map(|(module_hash, coverage)| (format!("{:x}", module_hash), coverage))
+            .collect();
+        Ok(serde_json::to_string(&modules)?)
+    }
+
+    /// Parses a map serialized with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let modules: BTreeMap<String, ModuleCoverage> = serde_json::from_str(json)?;
+        let modules = modules
+            .into_iter()
+            .map(|(module_hash, coverage)| {
+                Ok((HashValue::from_slice(&hex::decode(module_hash)?)?, coverage))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { modules })
+    }
+
+    /// Returns a printer of the disassembly of `module`, with every instruction marked as
+    /// executed (`+`) or not (`-`).
+    pub fn display<'a>(&'a self, module: &'a CompiledModule) -> Result<CoverageDisplay<'a>> {
+        Ok(CoverageDisplay {
+            module,
+            coverage: self.module(&Self::module_hash(module)?),
+            source_map: None,
+        })
+    }
+}
+
+/// Prints the disassembly of a module overlaid with its coverage. See `CoverageMap::display`.
+pub struct CoverageDisplay<'a> {
+    module: &'a CompiledModule,
+    coverage: Option<&'a ModuleCoverage>,
+    source_map: Option<&'a SourceMap>,
+}
+
+impl<'a> CoverageDisplay<'a> {
+    /// Also displays the source location of the instructions `source_map` maps.
+    pub fn with_source_map(mut self, source_map: &'a SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+}
+
+impl<'a> fmt::Display for CoverageDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let module = self.module;
+        let tables = module.as_inner();
+        let view = ModuleView::new(module);
+        let covered = |function: &str, offset: CodeOffset| {
+            self.coverage
+                .map_or(false, |coverage| coverage.is_covered(function, offset))
+        };
+
+        let total: usize = module
+            .function_defs()
+            .iter()
+            .map(|function_def| function_def.code.code.len())
+            .sum();
+        let mut total_covered = 0;
+        for function_def in module.function_defs() {
+            let name = module.string_at(module.function_handle_at(function_def.function).name);
+            total_covered += (0..function_def.code.code.len())
+                .filter(|offset| covered(name, *offset as CodeOffset))
+                .count();
+        }
+        let id = module.self_id();
+        writeln!(
+            f,
+            "module {}::{}: {}/{} instructions executed",
+            short_address(id.address()),
+            id.name(),
+            total_covered,
+            total
+        )?;
+
+        for (idx, function_def) in module.function_defs().iter().enumerate() {
+            let name = module.string_at(module.function_handle_at(function_def.function).name);
+            let function_source = self.source_map.and_then(|source_map| {
+                source_map.function(FunctionDefinitionIndex::new(idx as TableIndex))
+            });
+            writeln!(f)?;
+            display_function_definition(function_def, tables, f)?;
+            writeln!(f)?;
+            for (offset, bytecode) in function_def.code.code.iter().enumerate() {
+                let marker = if covered(name, offset as CodeOffset) {
+                    '+'
+                } else {
+                    '-'
+                };
+                write!(f, "  {} {:>4}: {}", marker, offset, bytecode.display(&view))?;
+                let location = function_source
+                    .and_then(|function_source| function_source.code.get(&(offset as CodeOffset)));
+                if let (Some(source_map), Some(location)) = (self.source_map, location) {
+                    write!(f, "  // {}", source_map.display_location(*location))?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,12 +1,21 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{errors::*, file_format::*, file_format_common::*};
+use crate::{
+    errors::*,
+    file_format::*,
+    file_format_common::*,
+    identifier::Identifier,
+    raw_code::{RawCode, RawCodeModule, RawCodeUnit, RawFunctionDefinition},
+};
 use byteorder::{LittleEndian, ReadBytesExt};
+use bytes::Bytes;
+use failure::prelude::*;
 use std::{
     collections::HashSet,
-    convert::TryInto,
+    convert::{TryFrom, TryInto},
     io::{Cursor, Read},
+    str::FromStr,
 };
 use types::{account_address::ADDRESS_LENGTH, byte_array::ByteArray};
 
@@ -18,6 +27,25 @@ impl CompiledScript {
     }
 }
 
+impl TryFrom<&[u8]> for CompiledScript {
+    type Error = BinaryError;
+
+    fn try_from(binary: &[u8]) -> BinaryLoaderResult<Self> {
+        Self::deserialize(binary)
+    }
+}
+
+/// Parses a script binary written in hex, as in test fixtures and configs. Whitespace is ignored
+/// and a leading `0x` is allowed.
+impl FromStr for CompiledScript {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let binary = decode_hex_binary(s)?;
+        Self::deserialize(&binary).map_err(|err| format_err!("invalid script binary: {:?}", err))
+    }
+}
+
 impl CompiledScriptMut {
     // exposed as a public function to enable testing the deserializer
     #[doc(hidden)]
@@ -32,12 +60,253 @@ impl CompiledModule {
         let deserialized = CompiledModuleMut::deserialize_no_check_bounds(binary)?;
         deserialized.freeze().map_err(|_| BinaryError::Malformed)
     }
+
+    /// Deserializes a `CompiledModule` whose byte array pool shares the memory of `binary`
+    /// instead of copying it.
+    ///
+    /// The whole binary is kept alive for as long as any of the byte arrays is, so this is best
+    /// suited to binaries that are themselves kept around, such as the ones stored in a cache.
+    pub fn deserialize_shared(binary: &Bytes) -> BinaryLoaderResult<Self> {
+        let deserialized = CompiledModuleMut::deserialize_shared_no_check_bounds(binary)?;
+        deserialized.freeze().map_err(|_| BinaryError::Malformed)
+    }
+}
+
+impl TryFrom<&[u8]> for CompiledModule {
+    type Error = BinaryError;
+
+    fn try_from(binary: &[u8]) -> BinaryLoaderResult<Self> {
+        Self::deserialize(binary)
+    }
+}
+
+/// Parses a module binary written in hex, as in test fixtures and configs. Whitespace is ignored
+/// and a leading `0x` is allowed.
+impl FromStr for CompiledModule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let binary = decode_hex_binary(s)?;
+        Self::deserialize(&binary).map_err(|err| format_err!("invalid module binary: {:?}", err))
+    }
+}
+
+/// Decodes a binary written in hex, ignoring whitespace and an optional `0x` prefix.
+fn decode_hex_binary(s: &str) -> Result<Vec<u8>> {
+    let s = s.trim_start();
+    let s = if s.starts_with("0x") { &s[2..] } else { s };
+    let digits: String = s.chars().filter(|c| !c.is_whitespace()).collect();
+    Ok(hex::decode(digits)?)
 }
 
 impl CompiledModuleMut {
     // exposed as a public function to enable testing the deserializer
     pub fn deserialize_no_check_bounds(binary: &[u8]) -> BinaryLoaderResult<Self> {
-        deserialize_compiled_module(binary)
+        deserialize_compiled_module(binary, None, None)
+    }
+
+    /// Like `deserialize_no_check_bounds`, but the byte array pool shares the memory of `binary`.
+    pub fn deserialize_shared_no_check_bounds(binary: &Bytes) -> BinaryLoaderResult<Self> {
+        deserialize_compiled_module(binary, Some(binary), None)
+    }
+}
+
+impl CompiledModuleMut {
+    /// Deserializes as much of a damaged or truncated module binary as possible, for analysis of
+    /// corrupt data rather than for loading.
+    ///
+    /// Parsing stops at the first table header that can't be read. Every table is then read up to
+    /// its first entry that fails to deserialize, or up to the end of the binary if it is cut
+    /// short, and the failure is recorded before moving on to the next table. Gaps and overlaps
+    /// between tables are not reported, so a binary read without failures may still be rejected
+    /// by `deserialize_no_check_bounds`.
+    pub fn deserialize_partial(binary: &[u8]) -> PartialModule {
+        let mut partial = PartialModule {
+            module: CompiledModuleMut::default(),
+            complete_tables: vec![],
+            failures: vec![],
+        };
+        let mut cursor = Cursor::new(binary);
+        let table_count = match check_binary(&mut cursor) {
+            Ok(table_count) => table_count,
+            Err(error) => {
+                partial.fail(None, 0, error);
+                return partial;
+            }
+        };
+        let mut tables = vec![];
+        for _count in 0..table_count {
+            let offset = cursor.position();
+            match read_table(&mut cursor) {
+                Ok(table) => tables.push(table),
+                Err(error) => {
+                    partial.fail(None, offset, error);
+                    break;
+                }
+            }
+        }
+
+        tables.sort_by_key(|table| table.offset);
+        let mut table_types = HashSet::new();
+        for table in tables {
+            let offset = u64::from(table.offset);
+            if !table_types.insert(table.kind) {
+                partial.fail(Some(table.kind), offset, BinaryError::DuplicateTable);
+                continue;
+            }
+            // Read the part of the table that is in the binary. Addresses have a fixed size, so
+            // only whole ones are read.
+            let available = (binary.len() as u64).saturating_sub(offset);
+            let mut count = u64::from(table.count).min(available) as u32;
+            if table.kind == TableType::ADDRESS_POOL {
+                count -= count % ADDRESS_LENGTH as u32;
+            }
+            let read = Table::new(table.kind, table.offset, count);
+            let read = std::slice::from_ref(&read);
+            let module = &mut partial.module;
+            let result = build_common_tables(binary, None, read, module)
+                .and_then(|()| build_module_tables(binary, read, module, &mut vec![], None));
+            match result {
+                Err(error) => partial.fail(Some(table.kind), offset, error),
+                Ok(()) if count < table.count => {
+                    partial.fail(Some(table.kind), offset, BinaryError::BadHeaderTable)
+                }
+                Ok(()) => partial.complete_tables.push(table.kind),
+            }
+        }
+        partial
+    }
+}
+
+/// What `CompiledModuleMut::deserialize_partial` recovered from a damaged module binary.
+#[derive(Clone, Debug)]
+pub struct PartialModule {
+    /// The entries that were read. Tables end at the first entry that failed to deserialize, and
+    /// tables that weren't read are empty.
+    pub module: CompiledModuleMut,
+    /// The tables that were read in full, in the order they appear in the binary.
+    pub complete_tables: Vec<TableType>,
+    /// Where and why parsing stopped, in the order parsing got there.
+    pub failures: Vec<PartialModuleFailure>,
+}
+
+/// A part of a module binary that `CompiledModuleMut::deserialize_partial` failed to read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PartialModuleFailure {
+    /// The table that failed to be read, or `None` for the header of the binary.
+    pub table: Option<TableType>,
+    /// The offset in the binary of the table or of the table header that failed to be read.
+    pub offset: u64,
+    pub error: BinaryError,
+}
+
+impl PartialModule {
+    fn fail(&mut self, table: Option<TableType>, offset: u64, error: BinaryError) {
+        self.failures.push(PartialModuleFailure {
+            table,
+            offset,
+            error,
+        });
+    }
+}
+
+impl RawCode {
+    /// Indexes a serialized code stream, such as the one returned by `RawCode::as_bytes`, without
+    /// decoding it.
+    pub fn deserialize(bytes: Bytes) -> BinaryLoaderResult<Self> {
+        let mut cursor = Cursor::new(bytes.as_ref());
+        let offsets = index_code(&mut cursor)?;
+        if cursor.position() != bytes.len() as u64 {
+            return Err(BinaryError::Malformed);
+        }
+        Ok(RawCode::new(bytes, offsets))
+    }
+}
+
+impl RawCodeModule {
+    /// Deserializes a module without decoding the code of its functions, like
+    /// `CompiledModuleMut::deserialize_no_check_bounds` otherwise.
+    ///
+    /// The instructions are still checked to be well-formed, so decoding them later can't fail.
+    pub fn deserialize(binary: &[u8]) -> BinaryLoaderResult<Self> {
+        let mut function_defs = vec![];
+        let module = deserialize_compiled_module(binary, None, Some(&mut function_defs))?;
+        Ok(RawCodeModule {
+            module,
+            function_defs,
+        })
+    }
+}
+
+/// Deserializes many modules one after the other, such as the modules loaded at startup or by a
+/// fuzzer, with fewer allocations than `CompiledModuleMut::deserialize_no_check_bounds`.
+///
+/// A `Deserializer` keeps its scratch space from one call to the next, and `deserialize_into`
+/// reuses the tables of the module it deserializes into, along with the code buffers of its
+/// functions, instead of allocating new ones. Names are interned, so the names shared by the
+/// modules are only allocated once.
+#[derive(Debug, Default)]
+pub struct Deserializer {
+    tables: Vec<Table>,
+    table_types: HashSet<TableType>,
+    /// Code buffers taken from the functions of previously deserialized modules.
+    spare_code: Vec<Vec<Bytecode>>,
+}
+
+impl Deserializer {
+    /// Creates a deserializer with empty scratch space.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Deserializes `binary` into `module`, replacing its contents, like
+    /// `CompiledModuleMut::deserialize_no_check_bounds`.
+    ///
+    /// The contents of `module` are unspecified if an error is returned, but `module` can still be
+    /// passed to the next call.
+    pub fn deserialize_into(
+        &mut self,
+        binary: &[u8],
+        module: &mut CompiledModuleMut,
+    ) -> BinaryLoaderResult<()> {
+        self.recycle(module);
+
+        self.tables.clear();
+        self.table_types.clear();
+        load_table_headers(binary, &mut self.tables, &mut self.table_types)?;
+
+        build_common_tables(binary, None, &self.tables, module)?;
+        build_module_tables(binary, &self.tables, module, &mut self.spare_code, None)
+    }
+
+    /// Deserializes and bounds checks `binary`, like `CompiledModule::deserialize`. Only the
+    /// scratch space is reused, since the returned module owns its tables.
+    pub fn deserialize(&mut self, binary: &[u8]) -> BinaryLoaderResult<CompiledModule> {
+        let mut module = CompiledModuleMut::default();
+        self.deserialize_into(binary, &mut module)?;
+        module.freeze().map_err(|_| BinaryError::Malformed)
+    }
+
+    /// Empties every table of `module`, keeping their capacity, and sets aside the buffers owned
+    /// by their entries for the next tables.
+    fn recycle(&mut self, module: &mut CompiledModuleMut) {
+        module.module_handles.clear();
+        module.struct_handles.clear();
+        module.function_handles.clear();
+        module.type_signatures.clear();
+        module.function_signatures.clear();
+        module.locals_signatures.clear();
+        module.string_pool.clear();
+        module.byte_array_pool.clear();
+        module.address_pool.clear();
+        module.struct_defs.clear();
+        module.field_defs.clear();
+        self.spare_code.extend(
+            module
+                .function_defs
+                .drain(..)
+                .map(|function_def| function_def.code.code),
+        );
     }
 }
 
@@ -62,26 +331,39 @@ impl Table {
 
 /// Module internal function that manages deserialization of transactions.
 fn deserialize_compiled_script(binary: &[u8]) -> BinaryLoaderResult<CompiledScriptMut> {
-    let binary_len = binary.len() as u64;
-    let mut cursor = Cursor::new(binary);
-    let table_count = check_binary(&mut cursor)?;
     let mut tables: Vec<Table> = Vec::new();
-    read_tables(&mut cursor, table_count, &mut tables)?;
-    check_tables(&mut tables, cursor.position(), binary_len)?;
-
+    load_table_headers(binary, &mut tables, &mut HashSet::new())?;
     build_compiled_script(binary, &tables)
 }
 
 /// Module internal function that manages deserialization of modules.
-fn deserialize_compiled_module(binary: &[u8]) -> BinaryLoaderResult<CompiledModuleMut> {
-    let binary_len = binary.len() as u64;
-    let mut cursor = Cursor::new(binary);
-    let table_count = check_binary(&mut cursor)?;
+///
+/// If `shared` is set, it holds the same bytes as `binary` and byte arrays are sliced out of it.
+/// If `raw_function_defs` is set, the function definitions are pushed to it with their code kept
+/// in serialized form, instead of being decoded into the module.
+fn deserialize_compiled_module(
+    binary: &[u8],
+    shared: Option<&Bytes>,
+    raw_function_defs: Option<&mut Vec<RawFunctionDefinition>>,
+) -> BinaryLoaderResult<CompiledModuleMut> {
     let mut tables: Vec<Table> = Vec::new();
-    read_tables(&mut cursor, table_count, &mut tables)?;
-    check_tables(&mut tables, cursor.position(), binary_len)?;
+    load_table_headers(binary, &mut tables, &mut HashSet::new())?;
+    build_compiled_module(binary, shared, &tables, raw_function_defs)
+}
 
-    build_compiled_module(binary, &tables)
+/// Reads the header of `binary` and its table headers into `tables`, checking that the tables
+/// cover the rest of the binary.
+///
+/// `tables` and `table_types` must be empty, and are filled as described in `check_tables`.
+fn load_table_headers(
+    binary: &[u8],
+    tables: &mut Vec<Table>,
+    table_types: &mut HashSet<TableType>,
+) -> BinaryLoaderResult<()> {
+    let mut cursor = Cursor::new(binary);
+    let table_count = check_binary(&mut cursor)?;
+    read_tables(&mut cursor, table_count, tables)?;
+    check_tables(tables, table_types, cursor.position(), binary.len() as u64)
 }
 
 /// Verifies the correctness of the "static" part of the binary's header.
@@ -150,12 +432,17 @@ fn read_table(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<Table> {
 /// Verify correctness of tables.
 ///
 /// Tables cannot have duplicates, must cover the entire blob and must be disjoint.
-fn check_tables(tables: &mut Vec<Table>, end_tables: u64, length: u64) -> BinaryLoaderResult<()> {
+/// `table_types` must be empty, and is filled with the types of the tables.
+fn check_tables(
+    tables: &mut Vec<Table>,
+    table_types: &mut HashSet<TableType>,
+    end_tables: u64,
+    length: u64,
+) -> BinaryLoaderResult<()> {
     // there is no real reason to pass a mutable reference but we are sorting next line
     tables.sort_by(|t1, t2| t1.offset.cmp(&t2.offset));
 
     let mut current_offset = end_tables;
-    let mut table_types = HashSet::new();
     for table in tables {
         let offset = u64::from(table.offset);
         if offset != current_offset {
@@ -278,22 +565,28 @@ impl CommonTables for CompiledModuleMut {
 /// Builds and returns a `CompiledScriptMut`.
 fn build_compiled_script(binary: &[u8], tables: &[Table]) -> BinaryLoaderResult<CompiledScriptMut> {
     let mut script = CompiledScriptMut::default();
-    build_common_tables(binary, tables, &mut script)?;
+    build_common_tables(binary, None, tables, &mut script)?;
     build_script_tables(binary, tables, &mut script)?;
     Ok(script)
 }
 
 /// Builds and returns a `CompiledModuleMut`.
-fn build_compiled_module(binary: &[u8], tables: &[Table]) -> BinaryLoaderResult<CompiledModuleMut> {
+fn build_compiled_module(
+    binary: &[u8],
+    shared: Option<&Bytes>,
+    tables: &[Table],
+    raw_function_defs: Option<&mut Vec<RawFunctionDefinition>>,
+) -> BinaryLoaderResult<CompiledModuleMut> {
     let mut module = CompiledModuleMut::default();
-    build_common_tables(binary, tables, &mut module)?;
-    build_module_tables(binary, tables, &mut module)?;
+    build_common_tables(binary, shared, tables, &mut module)?;
+    build_module_tables(binary, tables, &mut module, &mut vec![], raw_function_defs)?;
     Ok(module)
 }
 
 /// Builds the common tables in a compiled unit.
 fn build_common_tables(
     binary: &[u8],
+    shared: Option<&Bytes>,
     tables: &[Table],
     common: &mut impl CommonTables,
 ) -> BinaryLoaderResult<()> {
@@ -315,7 +608,7 @@ fn build_common_tables(
                 load_string_pool(binary, table, common.get_string_pool())?;
             }
             TableType::BYTE_ARRAY_POOL => {
-                load_byte_array_pool(binary, table, common.get_byte_array_pool())?;
+                load_byte_array_pool(binary, shared, table, common.get_byte_array_pool())?;
             }
             TableType::TYPE_SIGNATURES => {
                 load_type_signatures(binary, table, common.get_type_signatures())?;
@@ -340,6 +633,8 @@ fn build_module_tables(
     binary: &[u8],
     tables: &[Table],
     module: &mut CompiledModuleMut,
+    spare_code: &mut Vec<Vec<Bytecode>>,
+    mut raw_function_defs: Option<&mut Vec<RawFunctionDefinition>>,
 ) -> BinaryLoaderResult<()> {
     for table in tables {
         match table.kind {
@@ -350,7 +645,13 @@ fn build_module_tables(
                 load_field_defs(binary, table, &mut module.field_defs)?;
             }
             TableType::FUNCTION_DEFS => {
-                load_function_defs(binary, table, &mut module.function_defs)?;
+                load_function_defs(
+                    binary,
+                    table,
+                    &mut module.function_defs,
+                    spare_code,
+                    raw_function_defs.as_mut().map(|raw_defs| &mut **raw_defs),
+                )?;
             }
             TableType::MODULE_HANDLES
             | TableType::STRUCT_HANDLES
@@ -383,7 +684,7 @@ fn build_script_tables(
                 assume!(start <= usize::max_value() - (table.count as usize));
                 let end: usize = start + table.count as usize;
                 let mut cursor = Cursor::new(&binary[start..end]);
-                let main = load_function_def(&mut cursor)?;
+                let main = load_function_def(&mut cursor, vec![])?;
                 script.main = main;
             }
             TableType::MODULE_HANDLES
@@ -517,25 +818,25 @@ fn load_string_pool(
         if size > std::u16::MAX as usize {
             return Err(BinaryError::Malformed);
         }
-        let mut buffer: Vec<u8> = vec![0u8; size];
-        if let Ok(count) = cursor.read(&mut buffer) {
-            if count != size {
-                return Err(BinaryError::Malformed);
-            }
-            let s = match String::from_utf8(buffer) {
-                Ok(bytes) => bytes,
-                Err(_) => return Err(BinaryError::Malformed),
-            };
-
-            strings.push(s);
-        }
+        let position = cursor.position() as usize;
+        let bytes = match cursor.get_ref().get(position..position + size) {
+            Some(bytes) => bytes,
+            None => return Err(BinaryError::Malformed),
+        };
+        let s = match std::str::from_utf8(bytes) {
+            Ok(s) => s,
+            Err(_) => return Err(BinaryError::Malformed),
+        };
+        cursor.set_position((position + size) as u64);
+        strings.push(Identifier::new(s));
     }
     Ok(())
 }
 
-/// Builds the `ByteArrayPool`.
+/// Builds the `ByteArrayPool`, slicing the byte arrays out of `shared` if it is set.
 fn load_byte_array_pool(
     binary: &[u8],
+    shared: Option<&Bytes>,
     table: &Table,
     byte_arrays: &mut ByteArrayPool,
 ) -> BinaryLoaderResult<()> {
@@ -547,14 +848,18 @@ fn load_byte_array_pool(
         if size > std::u16::MAX as usize {
             return Err(BinaryError::Malformed);
         }
-        let mut byte_array: Vec<u8> = vec![0u8; size];
-        if let Ok(count) = cursor.read(&mut byte_array) {
-            if count != size {
-                return Err(BinaryError::Malformed);
-            }
-
-            byte_arrays.push(ByteArray::new(byte_array));
+        let position = cursor.position() as usize;
+        if position + size > cursor.get_ref().len() {
+            return Err(BinaryError::Malformed);
         }
+        cursor.set_position((position + size) as u64);
+        let byte_array = match shared {
+            Some(shared) => {
+                ByteArray::from_bytes(shared.slice(start + position, start + position + size))
+            }
+            None => ByteArray::new(cursor.get_ref()[position..position + size].to_vec()),
+        };
+        byte_arrays.push(byte_array);
     }
     Ok(())
 }
@@ -598,7 +903,7 @@ fn load_function_signatures(
 
         // Return signature
         let token_count = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-        let mut returns_signature: Vec<SignatureToken> = Vec::new();
+        let mut returns_signature = SignatureTokens::new();
         for _i in 0..token_count {
             let token = load_signature_token(&mut cursor)?;
             returns_signature.push(token);
@@ -606,7 +911,7 @@ fn load_function_signatures(
 
         // Arguments signature
         let token_count = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-        let mut args_signature: Vec<SignatureToken> = Vec::new();
+        let mut args_signature = SignatureTokens::new();
         for _i in 0..token_count {
             let token = load_signature_token(&mut cursor)?;
             args_signature.push(token);
@@ -638,7 +943,7 @@ fn load_locals_signatures(
         }
 
         let token_count = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-        let mut local_signature: Vec<SignatureToken> = Vec::new();
+        let mut local_signature = SignatureTokens::new();
         for _i in 0..token_count {
             let token = load_signature_token(&mut cursor)?;
             local_signature.push(token);
@@ -789,29 +1094,40 @@ fn load_field_defs(
     Ok(())
 }
 
-/// Builds the `FunctionDefinition` table.
+/// Builds the `FunctionDefinition` table, or the `RawFunctionDefinition` table instead if
+/// `raw_func_defs` is set.
 fn load_function_defs(
     binary: &[u8],
     table: &Table,
     func_defs: &mut Vec<FunctionDefinition>,
+    spare_code: &mut Vec<Vec<Bytecode>>,
+    mut raw_func_defs: Option<&mut Vec<RawFunctionDefinition>>,
 ) -> BinaryLoaderResult<()> {
     let start = table.offset as usize;
     let end = start + table.count as usize;
     let mut cursor = Cursor::new(&binary[start..end]);
     while cursor.position() < u64::from(table.count) {
-        let func_def = load_function_def(&mut cursor)?;
-        func_defs.push(func_def);
+        match raw_func_defs {
+            Some(ref mut raw_func_defs) => raw_func_defs.push(load_raw_function_def(&mut cursor)?),
+            None => func_defs.push(load_function_def(
+                &mut cursor,
+                spare_code.pop().unwrap_or_default(),
+            )?),
+        }
     }
     Ok(())
 }
 
-/// Deserializes a `FunctionDefinition`.
-fn load_function_def(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<FunctionDefinition> {
+/// Deserializes a `FunctionDefinition`, reading its code into `code`.
+fn load_function_def(
+    cursor: &mut Cursor<&[u8]>,
+    code: Vec<Bytecode>,
+) -> BinaryLoaderResult<FunctionDefinition> {
     let function = read_uleb_u16_internal(cursor)?;
 
     let flags = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
     let acquires_global_resources = load_struct_definition_indices(cursor)?;
-    let code_unit = load_code_unit(cursor)?;
+    let code_unit = load_code_unit(cursor, code)?;
     Ok(FunctionDefinition {
         function: FunctionHandleIndex(function),
         flags,
@@ -820,6 +1136,21 @@ fn load_function_def(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<FunctionD
     })
 }
 
+/// Deserializes a `RawFunctionDefinition`, keeping its code in serialized form.
+fn load_raw_function_def(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<RawFunctionDefinition> {
+    let function = read_uleb_u16_internal(cursor)?;
+
+    let flags = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+    let acquires_global_resources = load_struct_definition_indices(cursor)?;
+    let code = load_raw_code_unit(cursor)?;
+    Ok(RawFunctionDefinition {
+        function: FunctionHandleIndex(function),
+        flags,
+        acquires_global_resources,
+        code,
+    })
+}
+
 /// Deserializes a `Vec<StructDefinitionIndex>`.
 fn load_struct_definition_indices(
     cursor: &mut Cursor<&[u8]>,
@@ -832,156 +1163,192 @@ fn load_struct_definition_indices(
     Ok(indices)
 }
 
-/// Deserializes a `CodeUnit`.
-fn load_code_unit(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<CodeUnit> {
+/// Deserializes a `CodeUnit`, reading its code into `code`.
+fn load_code_unit(
+    cursor: &mut Cursor<&[u8]>,
+    mut code: Vec<Bytecode>,
+) -> BinaryLoaderResult<CodeUnit> {
     let max_stack_size = read_uleb_u16_internal(cursor)?;
     let locals = read_uleb_u16_internal(cursor)?;
 
-    let mut code_unit = CodeUnit {
+    code.clear();
+    load_code(cursor, &mut code)?;
+    Ok(CodeUnit {
         max_stack_size,
         locals: LocalsSignatureIndex(locals),
-        code: vec![],
-    };
+        code,
+    })
+}
+
+/// Deserializes a `RawCodeUnit`, copying its code stream without decoding it.
+fn load_raw_code_unit(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<RawCodeUnit> {
+    let max_stack_size = read_uleb_u16_internal(cursor)?;
+    let locals = read_uleb_u16_internal(cursor)?;
+
+    let start = cursor.position() as usize;
+    let offsets = index_code(cursor)?;
+    let end = cursor.position() as usize;
+    let bytes = Bytes::from(&cursor.get_ref()[start..end]);
+    Ok(RawCodeUnit {
+        max_stack_size,
+        locals: LocalsSignatureIndex(locals),
+        code: RawCode::new(bytes, offsets),
+    })
+}
 
-    load_code(cursor, &mut code_unit.code)?;
-    Ok(code_unit)
+/// Checks the instructions of a code stream and returns their positions relative to the start of
+/// the stream.
+fn index_code(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<Vec<u32>> {
+    let start = cursor.position();
+    let bytecode_count = read_u16_internal(cursor)?;
+    let mut offsets = Vec::with_capacity(bytecode_count as usize);
+    while offsets.len() < bytecode_count as usize {
+        offsets.push((cursor.position() - start) as u32);
+        load_instruction(cursor)?;
+    }
+    Ok(offsets)
 }
 
 /// Deserializes a code stream (`Bytecode`s).
 fn load_code(cursor: &mut Cursor<&[u8]>, code: &mut Vec<Bytecode>) -> BinaryLoaderResult<()> {
     let bytecode_count = read_u16_internal(cursor)?;
     while code.len() < bytecode_count as usize {
-        let byte = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-        let bytecode = match Opcodes::from_u8(byte)? {
-            Opcodes::POP => Bytecode::Pop,
-            Opcodes::RET => Bytecode::Ret,
-            Opcodes::BR_TRUE => {
-                let jump = read_u16_internal(cursor)?;
-                Bytecode::BrTrue(jump)
-            }
-            Opcodes::BR_FALSE => {
-                let jump = read_u16_internal(cursor)?;
-                Bytecode::BrFalse(jump)
-            }
-            Opcodes::BRANCH => {
-                let jump = read_u16_internal(cursor)?;
-                Bytecode::Branch(jump)
-            }
-            Opcodes::LD_CONST => {
-                let value = read_u64_internal(cursor)?;
-                Bytecode::LdConst(value)
-            }
-            Opcodes::LD_ADDR => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::LdAddr(AddressPoolIndex(idx))
-            }
-            Opcodes::LD_STR => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::LdStr(StringPoolIndex(idx))
-            }
-            Opcodes::LD_TRUE => Bytecode::LdTrue,
-            Opcodes::LD_FALSE => Bytecode::LdFalse,
-            Opcodes::COPY_LOC => {
-                let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-                Bytecode::CopyLoc(idx)
-            }
-            Opcodes::MOVE_LOC => {
-                let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-                Bytecode::MoveLoc(idx)
-            }
-            Opcodes::ST_LOC => {
-                let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-                Bytecode::StLoc(idx)
-            }
-            Opcodes::MUT_BORROW_LOC => {
-                let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-                Bytecode::MutBorrowLoc(idx)
-            }
-            Opcodes::IMM_BORROW_LOC => {
-                let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
-                Bytecode::ImmBorrowLoc(idx)
-            }
-            Opcodes::MUT_BORROW_FIELD => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::MutBorrowField(FieldDefinitionIndex(idx))
-            }
-            Opcodes::IMM_BORROW_FIELD => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::ImmBorrowField(FieldDefinitionIndex(idx))
-            }
-            Opcodes::LD_BYTEARRAY => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::LdByteArray(ByteArrayPoolIndex(idx))
-            }
-            Opcodes::CALL => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::Call(FunctionHandleIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::PACK => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::Pack(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::UNPACK => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::Unpack(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::READ_REF => Bytecode::ReadRef,
-            Opcodes::WRITE_REF => Bytecode::WriteRef,
-            Opcodes::ADD => Bytecode::Add,
-            Opcodes::SUB => Bytecode::Sub,
-            Opcodes::MUL => Bytecode::Mul,
-            Opcodes::MOD => Bytecode::Mod,
-            Opcodes::DIV => Bytecode::Div,
-            Opcodes::BIT_OR => Bytecode::BitOr,
-            Opcodes::BIT_AND => Bytecode::BitAnd,
-            Opcodes::XOR => Bytecode::Xor,
-            Opcodes::OR => Bytecode::Or,
-            Opcodes::AND => Bytecode::And,
-            Opcodes::NOT => Bytecode::Not,
-            Opcodes::EQ => Bytecode::Eq,
-            Opcodes::NEQ => Bytecode::Neq,
-            Opcodes::LT => Bytecode::Lt,
-            Opcodes::GT => Bytecode::Gt,
-            Opcodes::LE => Bytecode::Le,
-            Opcodes::GE => Bytecode::Ge,
-            Opcodes::ABORT => Bytecode::Abort,
-            Opcodes::GET_TXN_GAS_UNIT_PRICE => Bytecode::GetTxnGasUnitPrice,
-            Opcodes::GET_TXN_MAX_GAS_UNITS => Bytecode::GetTxnMaxGasUnits,
-            Opcodes::GET_GAS_REMAINING => Bytecode::GetGasRemaining,
-            Opcodes::GET_TXN_SENDER => Bytecode::GetTxnSenderAddress,
-            Opcodes::EXISTS => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::Exists(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::BORROW_GLOBAL => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::BorrowGlobal(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::MOVE_FROM => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::MoveFrom(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::MOVE_TO => {
-                let idx = read_uleb_u16_internal(cursor)?;
-                let types_idx = read_uleb_u16_internal(cursor)?;
-                Bytecode::MoveToSender(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
-            }
-            Opcodes::CREATE_ACCOUNT => Bytecode::CreateAccount,
-            Opcodes::GET_TXN_SEQUENCE_NUMBER => Bytecode::GetTxnSequenceNumber,
-            Opcodes::GET_TXN_PUBLIC_KEY => Bytecode::GetTxnPublicKey,
-            Opcodes::FREEZE_REF => Bytecode::FreezeRef,
-        };
-        code.push(bytecode);
+        code.push(load_instruction(cursor)?);
     }
     Ok(())
 }
 
+/// Deserializes a single `Bytecode` instruction.
+pub(crate) fn load_instruction(cursor: &mut Cursor<&[u8]>) -> BinaryLoaderResult<Bytecode> {
+    let byte = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+    let bytecode = match Opcodes::from_u8(byte)? {
+        Opcodes::POP => Bytecode::Pop,
+        Opcodes::RET => Bytecode::Ret,
+        Opcodes::BR_TRUE => {
+            let jump = read_u16_internal(cursor)?;
+            Bytecode::BrTrue(jump)
+        }
+        Opcodes::BR_FALSE => {
+            let jump = read_u16_internal(cursor)?;
+            Bytecode::BrFalse(jump)
+        }
+        Opcodes::BRANCH => {
+            let jump = read_u16_internal(cursor)?;
+            Bytecode::Branch(jump)
+        }
+        Opcodes::LD_CONST => {
+            let value = read_u64_internal(cursor)?;
+            Bytecode::LdConst(value)
+        }
+        Opcodes::LD_ADDR => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::LdAddr(AddressPoolIndex(idx))
+        }
+        Opcodes::LD_STR => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::LdStr(StringPoolIndex(idx))
+        }
+        Opcodes::LD_TRUE => Bytecode::LdTrue,
+        Opcodes::LD_FALSE => Bytecode::LdFalse,
+        Opcodes::COPY_LOC => {
+            let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+            Bytecode::CopyLoc(idx)
+        }
+        Opcodes::MOVE_LOC => {
+            let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+            Bytecode::MoveLoc(idx)
+        }
+        Opcodes::ST_LOC => {
+            let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+            Bytecode::StLoc(idx)
+        }
+        Opcodes::MUT_BORROW_LOC => {
+            let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+            Bytecode::MutBorrowLoc(idx)
+        }
+        Opcodes::IMM_BORROW_LOC => {
+            let idx = cursor.read_u8().map_err(|_| BinaryError::Malformed)?;
+            Bytecode::ImmBorrowLoc(idx)
+        }
+        Opcodes::MUT_BORROW_FIELD => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::MutBorrowField(FieldDefinitionIndex(idx))
+        }
+        Opcodes::IMM_BORROW_FIELD => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::ImmBorrowField(FieldDefinitionIndex(idx))
+        }
+        Opcodes::LD_BYTEARRAY => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::LdByteArray(ByteArrayPoolIndex(idx))
+        }
+        Opcodes::CALL => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::Call(FunctionHandleIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::PACK => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::Pack(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::UNPACK => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::Unpack(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::READ_REF => Bytecode::ReadRef,
+        Opcodes::WRITE_REF => Bytecode::WriteRef,
+        Opcodes::ADD => Bytecode::Add,
+        Opcodes::SUB => Bytecode::Sub,
+        Opcodes::MUL => Bytecode::Mul,
+        Opcodes::MOD => Bytecode::Mod,
+        Opcodes::DIV => Bytecode::Div,
+        Opcodes::BIT_OR => Bytecode::BitOr,
+        Opcodes::BIT_AND => Bytecode::BitAnd,
+        Opcodes::XOR => Bytecode::Xor,
+        Opcodes::OR => Bytecode::Or,
+        Opcodes::AND => Bytecode::And,
+        Opcodes::NOT => Bytecode::Not,
+        Opcodes::EQ => Bytecode::Eq,
+        Opcodes::NEQ => Bytecode::Neq,
+        Opcodes::LT => Bytecode::Lt,
+        Opcodes::GT => Bytecode::Gt,
+        Opcodes::LE => Bytecode::Le,
+        Opcodes::GE => Bytecode::Ge,
+        Opcodes::ABORT => Bytecode::Abort,
+        Opcodes::GET_TXN_GAS_UNIT_PRICE => Bytecode::GetTxnGasUnitPrice,
+        Opcodes::GET_TXN_MAX_GAS_UNITS => Bytecode::GetTxnMaxGasUnits,
+        Opcodes::GET_GAS_REMAINING => Bytecode::GetGasRemaining,
+        Opcodes::GET_TXN_SENDER => Bytecode::GetTxnSenderAddress,
+        Opcodes::EXISTS => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::Exists(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::BORROW_GLOBAL => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::BorrowGlobal(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::MOVE_FROM => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::MoveFrom(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::MOVE_TO => {
+            let idx = read_uleb_u16_internal(cursor)?;
+            let types_idx = read_uleb_u16_internal(cursor)?;
+            Bytecode::MoveToSender(StructDefinitionIndex(idx), LocalsSignatureIndex(types_idx))
+        }
+        Opcodes::CREATE_ACCOUNT => Bytecode::CreateAccount,
+        Opcodes::GET_TXN_SEQUENCE_NUMBER => Bytecode::GetTxnSequenceNumber,
+        Opcodes::GET_TXN_PUBLIC_KEY => Bytecode::GetTxnPublicKey,
+        Opcodes::FREEZE_REF => Bytecode::FreezeRef,
+    };
+    Ok(bytecode)
+}
+
 //
 // Helpers to read uleb128 and uncompressed integers
 //
@@ -0,0 +1,378 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Computes the structural differences between two versions of a module, for reviewing upgrades.
+//!
+//! Modules are compared in their normalized form (see `normalize`), so that only changes to what
+//! the module declares and does are reported, and every change is reported as a typed record
+//! rather than as lines of text. Function bodies are only compared as a whole: a change to the
+//! locals or code of a function is reported as `Change::CodeChanged`.
+
+use crate::{
+    access::ModuleAccess,
+    errors::short_address,
+    file_format::Kind,
+    normalize::{
+        NormalizedFunction, NormalizedModule, NormalizedSignature, NormalizedStruct, NormalizedType,
+    },
+};
+use std::{collections::BTreeSet, fmt};
+use types::language_storage::ModuleId;
+
+/// A difference between two versions of a module.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change {
+    /// The module is published under another address or name.
+    ModuleIdChanged {
+        old: ModuleId,
+        new: ModuleId,
+    },
+    StructAdded(String),
+    StructRemoved(String),
+    /// The struct became a resource, or stopped being one.
+    StructKindChanged {
+        name: String,
+        is_nominal_resource: bool,
+    },
+    StructTypeFormalsChanged {
+        name: String,
+        old: Vec<Kind>,
+        new: Vec<Kind>,
+    },
+    /// The struct became native, or stopped being native.
+    StructNativeChanged {
+        name: String,
+        is_native: bool,
+    },
+    FieldAdded {
+        struct_name: String,
+        field_name: String,
+    },
+    FieldRemoved {
+        struct_name: String,
+        field_name: String,
+    },
+    FieldTypeChanged {
+        struct_name: String,
+        field_name: String,
+        old: NormalizedType,
+        new: NormalizedType,
+    },
+    /// The fields kept are declared in another order, which changes the layout of the struct.
+    FieldReordered {
+        struct_name: String,
+        old: Vec<String>,
+        new: Vec<String>,
+    },
+    FunctionAdded(String),
+    FunctionRemoved(String),
+    /// The function became public, or stopped being public.
+    VisibilityChanged {
+        name: String,
+        is_public: bool,
+    },
+    /// The function became native, or stopped being native.
+    FunctionNativeChanged {
+        name: String,
+        is_native: bool,
+    },
+    SignatureChanged {
+        name: String,
+        old: NormalizedSignature,
+        new: NormalizedSignature,
+    },
+    AcquiresChanged {
+        name: String,
+        old: BTreeSet<String>,
+        new: BTreeSet<String>,
+    },
+    /// The locals or code of the function changed.
+    CodeChanged(String),
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::ModuleIdChanged { old, new } => write!(
+                f,
+                "module moved from {}::{} to {}::{}",
+                short_address(old.address()),
+                old.name(),
+                short_address(new.address()),
+                new.name()
+            ),
+            Change::StructAdded(name) => write!(f, "struct {} added", name),
+            Change::StructRemoved(name) => write!(f, "struct {} removed", name),
+            Change::StructKindChanged {
+                name,
+                is_nominal_resource,
+            } => {
+                let kind = if *is_nominal_resource {
+                    "a resource"
+                } else {
+                    "not a resource"
+                };
+                write!(f, "struct {} is now {}", name, kind)
+            }
+            Change::StructTypeFormalsChanged { name, old, new } => write!(
+                f,
+                "struct {} type formals changed from {:?} to {:?}",
+                name, old, new
+            ),
+            Change::StructNativeChanged { name, is_native } => {
+                let native = if *is_native { "native" } else { "not native" };
+                write!(f, "struct {} is now {}", name, native)
+            }
+            Change::FieldAdded {
+                struct_name,
+                field_name,
+            } => write!(f, "field {}.{} added", struct_name, field_name),
+            Change::FieldRemoved {
+                struct_name,
+                field_name,
+            } => write!(f, "field {}.{} removed", struct_name, field_name),
+            Change::FieldTypeChanged {
+                struct_name,
+                field_name,
+                old,
+                new,
+            } => write!(
+                f,
+                "field {}.{} type changed from {} to {}",
+                struct_name, field_name, old, new
+            ),
+            Change::FieldReordered {
+                struct_name,
+                old,
+                new,
+            } => write!(
+                f,
+                "fields of {} reordered from ({}) to ({})",
+                struct_name,
+                old.join(", "),
+                new.join(", ")
+            ),
+            Change::FunctionAdded(name) => write!(f, "function {} added", name),
+            Change::FunctionRemoved(name) => write!(f, "function {} removed", name),
+            Change::VisibilityChanged { name, is_public } => {
+                let visibility = if *is_public { "public" } else { "private" };
+                write!(f, "function {} is now {}", name, visibility)
+            }
+            Change::FunctionNativeChanged { name, is_native } => {
+                let native = if *is_native { "native" } else { "not native" };
+                write!(f, "function {} is now {}", name, native)
+            }
+            Change::SignatureChanged { name, old, new } => write!(
+                f,
+                "function {} signature changed from {} to {}",
+                name, old, new
+            ),
+            Change::AcquiresChanged { name, old, new } => {
+                let old: Vec<_> = old.iter().map(String::as_str).collect();
+                let new: Vec<_> = new.iter().map(String::as_str).collect();
+                write!(
+                    f,
+                    "function {} acquires changed from [{}] to [{}]",
+                    name,
+                    old.join(", "),
+                    new.join(", ")
+                )
+            }
+            Change::CodeChanged(name) => write!(f, "function {} code changed", name),
+        }
+    }
+}
+
+/// The changes between two versions of a module, ordered by module, struct and function, and
+/// then by name.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModuleDiff {
+    pub changes: Vec<Change>,
+}
+
+impl ModuleDiff {
+    /// Returns the changes from `old` to `new`.
+    pub fn new(old: &impl ModuleAccess, new: &impl ModuleAccess) -> Self {
+        Self::between(&NormalizedModule::new(old), &NormalizedModule::new(new))
+    }
+
+    /// Returns the changes from `old` to `new`, which are already normalized.
+    pub fn between(old: &NormalizedModule, new: &NormalizedModule) -> Self {
+        let mut changes = vec![];
+        if old.id != new.id {
+            changes.push(Change::ModuleIdChanged {
+                old: old.id.clone(),
+                new: new.id.clone(),
+            });
+        }
+
+        for (name, old_struct) in &old.structs {
+            match new.structs.get(name) {
+                Some(new_struct) => diff_structs(name, old_struct, new_struct, &mut changes),
+                None => changes.push(Change::StructRemoved(name.clone())),
+            }
+        }
+        for name in new.structs.keys() {
+            if !old.structs.contains_key(name) {
+                changes.push(Change::StructAdded(name.clone()));
+            }
+        }
+
+        for (name, old_function) in &old.functions {
+            match new.functions.get(name) {
+                Some(new_function) => {
+                    diff_functions(name, old_function, new_function, &mut changes)
+                }
+                None => changes.push(Change::FunctionRemoved(name.clone())),
+            }
+        }
+        for name in new.functions.keys() {
+            if !old.functions.contains_key(name) {
+                changes.push(Change::FunctionAdded(name.clone()));
+            }
+        }
+        Self { changes }
+    }
+
+    /// Returns true if the modules are the same, up to normalization.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+/// Displays one change per line.
+impl fmt::Display for ModuleDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for change in &self.changes {
+            writeln!(f, "{}", change)?;
+        }
+        Ok(())
+    }
+}
+
+fn diff_structs(
+    name: &str,
+    old: &NormalizedStruct,
+    new: &NormalizedStruct,
+    changes: &mut Vec<Change>,
+) {
+    if old.is_nominal_resource != new.is_nominal_resource {
+        changes.push(Change::StructKindChanged {
+            name: name.to_string(),
+            is_nominal_resource: new.is_nominal_resource,
+        });
+    }
+    if old.type_formals != new.type_formals {
+        changes.push(Change::StructTypeFormalsChanged {
+            name: name.to_string(),
+            old: old.type_formals.clone(),
+            new: new.type_formals.clone(),
+        });
+    }
+    let (old_fields, new_fields) = match (&old.fields, &new.fields) {
+        (Some(old_fields), Some(new_fields)) => (old_fields, new_fields),
+        (None, None) => return,
+        (_, new_fields) => {
+            changes.push(Change::StructNativeChanged {
+                name: name.to_string(),
+                is_native: new_fields.is_none(),
+            });
+            return;
+        }
+    };
+
+    let field_change = |field_name: &str| (name.to_string(), field_name.to_string());
+    for (field_name, old_type) in old_fields {
+        match new_fields
+            .iter()
+            .find(|(new_name, _)| new_name == field_name)
+        {
+            Some((_, new_type)) if new_type != old_type => {
+                let (struct_name, field_name) = field_change(field_name);
+                changes.push(Change::FieldTypeChanged {
+                    struct_name,
+                    field_name,
+                    old: old_type.clone(),
+                    new: new_type.clone(),
+                });
+            }
+            Some(_) => (),
+            None => {
+                let (struct_name, field_name) = field_change(field_name);
+                changes.push(Change::FieldRemoved {
+                    struct_name,
+                    field_name,
+                });
+            }
+        }
+    }
+    for (field_name, _) in new_fields {
+        if !old_fields
+            .iter()
+            .any(|(old_name, _)| old_name == field_name)
+        {
+            let (struct_name, field_name) = field_change(field_name);
+            changes.push(Change::FieldAdded {
+                struct_name,
+                field_name,
+            });
+        }
+    }
+
+    // Compare the order of the fields both versions have.
+    let kept = |fields: &[(String, NormalizedType)], other: &[(String, NormalizedType)]| {
+        fields
+            .iter()
+            .map(|(field_name, _)| field_name.clone())
+            .filter(|field_name| other.iter().any(|(other_name, _)| other_name == field_name))
+            .collect::<Vec<_>>()
+    };
+    let old_order = kept(old_fields, new_fields);
+    let new_order = kept(new_fields, old_fields);
+    if old_order != new_order {
+        changes.push(Change::FieldReordered {
+            struct_name: name.to_string(),
+            old: old_order,
+            new: new_order,
+        });
+    }
+}
+
+fn diff_functions(
+    name: &str,
+    old: &NormalizedFunction,
+    new: &NormalizedFunction,
+    changes: &mut Vec<Change>,
+) {
+    if old.is_public != new.is_public {
+        changes.push(Change::VisibilityChanged {
+            name: name.to_string(),
+            is_public: new.is_public,
+        });
+    }
+    if old.is_native != new.is_native {
+        changes.push(Change::FunctionNativeChanged {
+            name: name.to_string(),
+            is_native: new.is_native,
+        });
+    }
+    let (old_signature, new_signature) =
+        (NormalizedSignature::new(old), NormalizedSignature::new(new));
+    if old_signature != new_signature {
+        changes.push(Change::SignatureChanged {
+            name: name.to_string(),
+            old: old_signature,
+            new: new_signature,
+        });
+    }
+    if old.acquires != new.acquires {
+        changes.push(Change::AcquiresChanged {
+            name: name.to_string(),
+            old: old.acquires.clone(),
+            new: new.acquires.clone(),
+        });
+    }
+    if old.locals != new.locals || old.code != new.code {
+        changes.push(Change::CodeChanged(name.to_string()));
+    }
+}
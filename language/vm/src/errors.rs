@@ -1,8 +1,16 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{file_format::SignatureToken, IndexKind, SignatureTokenKind};
+use crate::{
+    access::ModuleAccess,
+    file_format::{CodeOffset, FunctionDefinitionIndex, SignatureToken, StructDefinitionIndex},
+    internals::ModuleIndex,
+    source_map::{SourceLocation, SourceMap},
+    views::ModuleView,
+    IndexKind, SignatureTokenKind,
+};
 use failure::Fail;
+use serde::{Deserialize, Serialize};
 use std::{fmt, iter::FromIterator};
 use types::{
     account_address::AccountAddress,
@@ -57,7 +65,7 @@ pub enum VMErrorKind {
     CallStackOverflow,
 }
 
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum VerificationStatus {
     /// A verification error was detected in a transaction script.
     Script(VerificationError),
@@ -68,7 +76,7 @@ pub enum VerificationStatus {
     Dependency(ModuleId, VerificationError),
 }
 
-#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct VerificationError {
     /// Where the violation occurred.
     pub kind: IndexKind,
@@ -76,15 +84,355 @@ pub struct VerificationError {
     pub idx: usize,
     /// The actual violation that occurred.
     pub err: VMStaticViolation,
+    /// The function whose code contains the violation, for violations found in a code unit.
+    #[serde(default)]
+    pub function_definition_index: Option<FunctionDefinitionIndex>,
+    /// The offset of the offending instruction, for violations found in a code unit.
+    #[serde(default)]
+    pub code_offset: Option<CodeOffset>,
+    /// A trail of what was being checked when the violation was found, outermost first (e.g.
+    /// `["struct_defs[4]", "field 2", "type signature"]`). See `WithContext`.
+    #[serde(default)]
+    pub context: Vec<String>,
+    /// For violations found by analyzing a code unit, the code offsets of the instructions along
+    /// a path from the start of the function to the offending instruction, in execution order.
+    /// Empty unless the verifier was asked to record traces. See `with_trace`.
+    #[serde(default)]
+    pub trace: Vec<CodeOffset>,
+}
+
+impl VerificationError {
+    /// Creates an error for a violation that is not attributed to a particular instruction.
+    pub fn new(kind: IndexKind, idx: usize, err: VMStaticViolation) -> Self {
+        Self {
+            kind,
+            idx,
+            err,
+            function_definition_index: None,
+            code_offset: None,
+            context: vec![],
+            trace: vec![],
+        }
+    }
+
+    /// Creates an error for a violation found in the code unit of the function definition at
+    /// `function_definition_index`. The code offset is taken from the violation, if it has one.
+    pub fn in_function(
+        function_definition_index: FunctionDefinitionIndex,
+        err: VMStaticViolation,
+    ) -> Self {
+        Self {
+            kind: IndexKind::FunctionDefinition,
+            idx: function_definition_index.into_index(),
+            code_offset: err.code_offset(),
+            err,
+            function_definition_index: Some(function_definition_index),
+            context: vec![],
+            trace: vec![],
+        }
+    }
+
+    /// Attaches `trace`, the path of code offsets that leads to this violation.
+    pub fn with_trace(mut self, trace: Vec<CodeOffset>) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Returns a value that displays this error with handles and indexes resolved to fully
+    /// qualified names in `view`, for surfacing errors to the authors of the module.
+    ///
+    /// `view` must be the module that produced this error. Errors whose location can't be
+    /// resolved to a name are displayed the same way as with `Display`.
+    pub fn display_with<'a, T: ModuleAccess>(
+        &'a self,
+        view: &'a ModuleView<'a, T>,
+    ) -> VerificationErrorDisplay<'a, T> {
+        VerificationErrorDisplay {
+            error: self,
+            view,
+            source_map: None,
+        }
+    }
+
+    /// Returns where in `source_map` the code, function or struct definition this error occurred
+    /// in comes from, if it occurred in one.
+    pub fn source_location(&self, source_map: &SourceMap) -> Option<SourceLocation> {
+        match (self.function_definition_index, self.code_offset) {
+            (Some(idx), Some(code_offset)) => source_map.code_location(idx, code_offset),
+            (Some(idx), None) => source_map.function(idx)?.location,
+            (None, _) => match self.kind {
+                IndexKind::FunctionDefinition => {
+                    let idx = FunctionDefinitionIndex::new(self.idx as u16);
+                    source_map.function(idx)?.location
+                }
+                IndexKind::StructDefinition => {
+                    let idx = StructDefinitionIndex::new(self.idx as u16);
+                    source_map.struct_(idx)?.location
+                }
+                _ => None,
+            },
+        }
+    }
 }
 
 impl fmt::Display for VerificationError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "at '{}' index {}: {}", self.kind, self.idx, self.err)
+        write!(f, "at '{}' index {}", self.kind, self.idx)?;
+        if let Some(code_offset) = self.code_offset {
+            write!(f, " code offset {}", code_offset)?;
+        }
+        write!(f, ": {}", self.err)?;
+        fmt_context(&self.context, f)?;
+        fmt_trace(&self.trace, f)
     }
 }
 
-#[derive(Clone, Debug, Eq, Fail, Ord, PartialEq, PartialOrd)]
+fn fmt_context(context: &[String], f: &mut fmt::Formatter) -> fmt::Result {
+    if context.is_empty() {
+        Ok(())
+    } else {
+        write!(f, " (while checking {})", context.join(" > "))
+    }
+}
+
+/// Attaches breadcrumbs to verification errors as they propagate out of nested checks, so that
+/// the final error says what was being checked, not just what went wrong.
+///
+/// Context is accumulated outermost first: the innermost check attaches its context first, and
+/// every caller's context is put in front of it.
+pub trait WithContext: Sized {
+    /// Prepends the context produced by `f`. `f` is only called if there is an error to attach
+    /// the context to.
+    fn with_context<C, F>(self, f: F) -> Self
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C;
+}
+
+impl WithContext for VerificationError {
+    fn with_context<C, F>(mut self, f: F) -> Self
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C,
+    {
+        self.context.insert(0, f().to_string());
+        self
+    }
+}
+
+impl WithContext for Vec<VerificationError> {
+    fn with_context<C, F>(mut self, f: F) -> Self
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C,
+    {
+        if !self.is_empty() {
+            let context = f().to_string();
+            for error in &mut self {
+                error.context.insert(0, context.clone());
+            }
+        }
+        self
+    }
+}
+
+impl<T, E: WithContext> WithContext for ::std::result::Result<T, E> {
+    fn with_context<C, F>(self, f: F) -> Self
+    where
+        C: fmt::Display,
+        F: FnOnce() -> C,
+    {
+        self.map_err(|err| err.with_context(f))
+    }
+}
+
+/// Displays a `VerificationError` with handles and indexes resolved to fully qualified names
+/// (e.g. `0x1::Coin::mint`). Created by `VerificationError::display_with`.
+pub struct VerificationErrorDisplay<'a, T> {
+    error: &'a VerificationError,
+    view: &'a ModuleView<'a, T>,
+    source_map: Option<&'a SourceMap>,
+}
+
+impl<'a, T> VerificationErrorDisplay<'a, T> {
+    /// Also displays the source location of the error, if `source_map` has one.
+    pub fn with_source_map(mut self, source_map: &'a SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+}
+
+/// Resolves the location of `error` to the kind of item it occurred in (e.g. `"function"`) and the
+/// fully qualified name of that item, or `None` if it can't be resolved in `view`.
+pub(crate) fn resolve_location<T: ModuleAccess>(
+    error: &VerificationError,
+    view: &ModuleView<T>,
+) -> Option<(&'static str, String)> {
+    let self_id = view.id();
+    if let Some(idx) = error.function_definition_index {
+        let function = view.function_definition_at(idx)?;
+        return Some(("function", qualified_name(&self_id, function.name())));
+    }
+    let idx = error.idx;
+    let location = match error.kind {
+        IndexKind::ModuleHandle => {
+            let module_id = view.module_handles().nth(idx)?.module_id();
+            (
+                "module handle",
+                format!(
+                    "{}::{}",
+                    short_address(module_id.address()),
+                    module_id.name()
+                ),
+            )
+        }
+        IndexKind::StructHandle => {
+            let handle = view.struct_handles().nth(idx)?;
+            (
+                "struct handle",
+                qualified_name(&handle.module_id(), handle.name()),
+            )
+        }
+        IndexKind::FunctionHandle => {
+            let handle = view.function_handles().nth(idx)?;
+            (
+                "function handle",
+                qualified_name(&handle.module_id(), handle.name()),
+            )
+        }
+        IndexKind::StructDefinition => {
+            let struct_def = view.structs().nth(idx)?;
+            ("struct", qualified_name(&self_id, struct_def.name()))
+        }
+        IndexKind::FieldDefinition => {
+            let field_def = view.fields().nth(idx)?;
+            let owner = field_def.member_of();
+            (
+                "field",
+                format!(
+                    "{}.{}",
+                    qualified_name(&owner.module_id(), owner.name()),
+                    field_def.name()
+                ),
+            )
+        }
+        IndexKind::FunctionDefinition => {
+            let function = view.functions().nth(idx)?;
+            ("function", qualified_name(&self_id, function.name()))
+        }
+        _ => return None,
+    };
+    Some(location)
+}
+
+impl<'a, T: ModuleAccess> fmt::Display for VerificationErrorDisplay<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(source_map) = self.source_map {
+            if let Some(location) = self.error.source_location(source_map) {
+                write!(f, "{}: ", source_map.display_location(location))?;
+            }
+        }
+        match resolve_location(self.error, self.view) {
+            Some((kind, name)) => {
+                let code = self
+                    .error
+                    .function_definition_index
+                    .and_then(|idx| self.view.function_definition_at(idx))
+                    .map_or(&[][..], |function| &function.code().code[..]);
+                let instruction = |code_offset: CodeOffset| {
+                    code.get(code_offset as usize)
+                        .map(|bytecode| bytecode.display(self.view).to_string())
+                };
+
+                write!(f, "in {} {}", kind, name)?;
+                if let Some(code_offset) = self.error.code_offset {
+                    write!(f, " at code offset {}", code_offset)?;
+                    if let Some(instruction) = instruction(code_offset) {
+                        write!(f, " ({})", instruction)?;
+                    }
+                }
+                write!(f, ": {}", self.error.err)?;
+                fmt_context(&self.error.context, f)?;
+                if self.error.trace.is_empty() {
+                    return Ok(());
+                }
+                let steps: Vec<_> = self
+                    .error
+                    .trace
+                    .iter()
+                    .map(|code_offset| match instruction(*code_offset) {
+                        Some(instruction) => format!("{}: {}", code_offset, instruction),
+                        None => code_offset.to_string(),
+                    })
+                    .collect();
+                write!(f, " (reached through {})", steps.join(" -> "))
+            }
+            None => self.error.fmt(f),
+        }
+    }
+}
+
+fn fmt_trace(trace: &[CodeOffset], f: &mut fmt::Formatter) -> fmt::Result {
+    if trace.is_empty() {
+        Ok(())
+    } else {
+        let offsets: Vec<_> = trace.iter().map(|offset| offset.to_string()).collect();
+        write!(
+            f,
+            " (reached through code offsets {})",
+            offsets.join(" -> ")
+        )
+    }
+}
+
+/// Formats `name` qualified by its module, e.g. `0x1::Coin::mint`.
+fn qualified_name(module_id: &ModuleId, name: &str) -> String {
+    format!(
+        "{}::{}::{}",
+        short_address(module_id.address()),
+        module_id.name(),
+        name
+    )
+}
+
+/// Formats an address in hex without leading zeros, e.g. `0x1`.
+pub fn short_address(address: &AccountAddress) -> String {
+    let hex = format!("{:x}", address);
+    let digits = hex.trim_start_matches('0');
+    if digits.is_empty() {
+        "0x0".to_string()
+    } else {
+        format!("0x{}", digits)
+    }
+}
+
+/// What two entries of a module table collide on, as reported by `DuplicateElement`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum DuplicateKey {
+    /// The entries are identical: pool entries, or module handles with the same address and name.
+    Value,
+    /// The entries are identical type, function or locals signatures.
+    Signature,
+    /// The entries have the same name within the same module (struct and function handles) or
+    /// the same struct (field definitions).
+    Name,
+    /// The definitions implement the same struct or function handle.
+    Handle,
+}
+
+impl fmt::Display for DuplicateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match self {
+            DuplicateKey::Value => "value",
+            DuplicateKey::Signature => "signature",
+            DuplicateKey::Name => "name",
+            DuplicateKey::Handle => "handle",
+        };
+        f.write_str(desc)
+    }
+}
+
+#[derive(Clone, Debug, Eq, Fail, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum VMStaticViolation {
     #[fail(
         display = "Index out of bounds for '{}' (expected 0..{}, found {})",
@@ -104,6 +452,12 @@ pub enum VMStaticViolation {
     )]
     RangeOutOfBounds(IndexKind, usize, usize, usize),
 
+    #[fail(
+        display = "Code unit has {} instructions, more than a code offset can address",
+        _0
+    )]
+    CodeUnitTooLong(usize),
+
     #[fail(display = "Module must have at least one module handle")]
     NoModuleHandles,
 
@@ -116,8 +470,11 @@ pub enum VMStaticViolation {
     )]
     InvalidSignatureToken(SignatureToken, SignatureTokenKind, SignatureTokenKind),
 
-    #[fail(display = "Duplicate element")]
-    DuplicateElement,
+    #[fail(
+        display = "Duplicate element: same {} as the entry at index {}",
+        _1, _0
+    )]
+    DuplicateElement(usize, DuplicateKey),
 
     #[fail(display = "Invalid module handle")]
     InvalidModuleHandle,
@@ -327,6 +684,578 @@ pub enum VMStaticViolation {
 
     #[fail(display = "Expected {} type actuals got {}", _0, _1)]
     NumberOfTypeActualsMismatch(usize, usize),
+
+    #[fail(display = "Verification exceeded its complexity budget")]
+    VerificationBudgetExceeded,
+
+    #[fail(display = "Operand stack too high at offset {}", _0)]
+    StackHeightLimitExceeded(usize),
+
+    #[fail(display = "Main function of script returns a value at position {}", _0)]
+    InvalidMainFunctionReturn(usize),
+
+    #[fail(
+        display = "Argument {} of main function of script can't be a transaction argument: {:?}",
+        _0, _1
+    )]
+    InvalidMainFunctionArgument(usize, SignatureToken),
+
+    #[fail(
+        display = "Kind of struct handle doesn't match struct definition {} in module dependency",
+        _0
+    )]
+    StructKindMismatch(usize),
+
+    #[fail(
+        display = "Signature of function handle doesn't match function definition {} in module \
+                   dependency",
+        _0
+    )]
+    FunctionSignatureMismatch(usize),
+
+    #[fail(
+        display = "Function handle refers to private function definition {} in module dependency",
+        _0
+    )]
+    FunctionVisibilityMismatch(usize),
+
+    #[fail(
+        display = "Native flag of definition {} in module dependency doesn't match the VM",
+        _0
+    )]
+    NativeFlagMismatch(usize),
+
+    #[fail(display = "Cyclic module dependency: {:?}", _0)]
+    CyclicModuleDependency(Vec<ModuleId>),
+
+    #[fail(display = "Too many type parameters")]
+    TooManyTypeParameters,
+
+    #[fail(display = "Type instantiations nested too deeply")]
+    TypeInstantiationTooDeep,
+
+    #[fail(display = "Too many fields in struct definition")]
+    TooManyStructFields,
+
+    #[fail(display = "Too many basic blocks in function definition")]
+    TooManyBasicBlocks,
+
+    #[fail(display = "Basic block at offset {} is unreachable", _0)]
+    UnreachableBlock(usize),
+
+    #[fail(display = "Dead code after unconditional branch at offset {}", _0)]
+    DeadCodeAfterBranch(usize),
+
+    #[fail(display = "Native function is not in the registry of permitted natives")]
+    UnknownNativeFunction,
+
+    #[fail(
+        display = "Signature of native function doesn't match the registry of permitted natives"
+    )]
+    NativeFunctionSignatureMismatch,
+
+    #[fail(
+        display = "Irreducible control flow: the instruction at offset {} enters a loop other \
+                   than through its header",
+        _0
+    )]
+    IrreducibleControlFlow(usize),
+
+    #[fail(
+        display = "Arithmetic at offset {} may abort, and is reachable from a public function",
+        _0
+    )]
+    UncheckedArithmetic(usize),
+
+    #[fail(
+        display = "Loop with header at offset {} is bounded by an argument of a public function",
+        _0
+    )]
+    UnboundedLoop(usize),
+
+    #[fail(display = "Return value {} is a reference to a resource", _0)]
+    ResourceReturnedByReference(usize),
+}
+
+/// A coarse classification of VM errors, used by external systems to group errors without
+/// matching on individual variants.
+///
+/// Every category owns a block of 1000 numeric codes starting at `base_code()`; the codes of the
+/// errors in a category fall within that block.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum ErrorCategory {
+    /// The binary could not be deserialized.
+    Binary,
+    /// An index or range points outside of its table.
+    Bounds,
+    /// The tables of a module are inconsistent with each other (duplicates, bad handles, etc.)
+    Structure,
+    /// A signature or type is malformed or used with the wrong kind.
+    Signature,
+    /// A handle does not match the module dependency it refers to.
+    Dependency,
+    /// The code of a function is ill-formed (control flow, stack usage, types).
+    Code,
+    /// A reference is used unsafely.
+    Reference,
+    /// A resource is used unsafely, or global storage is accessed incorrectly.
+    Resource,
+    /// An internal invariant of the VM was violated.
+    Invariant,
+}
+
+impl ErrorCategory {
+    /// Returns the first numeric code reserved for this category.
+    pub fn base_code(self) -> u32 {
+        match self {
+            ErrorCategory::Binary => 1000,
+            ErrorCategory::Bounds => 2000,
+            ErrorCategory::Structure => 3000,
+            ErrorCategory::Signature => 4000,
+            ErrorCategory::Dependency => 5000,
+            ErrorCategory::Code => 6000,
+            ErrorCategory::Reference => 7000,
+            ErrorCategory::Resource => 8000,
+            ErrorCategory::Invariant => 9000,
+        }
+    }
+
+    /// Returns the category owning `code`, if any.
+    pub fn of_code(code: u32) -> Option<Self> {
+        use ErrorCategory::*;
+
+        [
+            Binary, Bounds, Structure, Signature, Dependency, Code, Reference, Resource, Invariant,
+        ]
+        .iter()
+        .find(|category| code / 1000 == category.base_code() / 1000)
+        .cloned()
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match self {
+            ErrorCategory::Binary => "binary",
+            ErrorCategory::Bounds => "bounds",
+            ErrorCategory::Structure => "structure",
+            ErrorCategory::Signature => "signature",
+            ErrorCategory::Dependency => "dependency",
+            ErrorCategory::Code => "code",
+            ErrorCategory::Reference => "reference",
+            ErrorCategory::Resource => "resource",
+            ErrorCategory::Invariant => "invariant",
+        };
+        f.write_str(desc)
+    }
+}
+
+// The numeric codes below are part of the external interface of the VM: once assigned, a code
+// must never be changed or reused, even if the variant it belongs to is renamed or removed. New
+// variants get the next unused code in the block of their category.
+
+impl VMStaticViolation {
+    /// Returns the stable numeric code of this violation.
+    pub fn code(&self) -> u32 {
+        use VMStaticViolation::*;
+
+        match self {
+            IndexOutOfBounds(_, _, _) => 2001,
+            CodeUnitIndexOutOfBounds(_, _, _, _) => 2002,
+            RangeOutOfBounds(_, _, _, _) => 2003,
+            CodeUnitTooLong(_) => 2004,
+
+            NoModuleHandles => 3001,
+            ModuleAddressDoesNotMatchSender => 3002,
+            DuplicateElement(_, _) => 3003,
+            InvalidModuleHandle => 3004,
+            UnimplementedHandle => 3005,
+            InconsistentFields => 3006,
+            UnusedFields => 3007,
+            RecursiveStructDef => 3008,
+            TooManyStructFields => 3009,
+
+            InvalidSignatureToken(_, _, _) => 4001,
+            InvalidFieldDefReference(_, _) => 4002,
+            InvalidMainFunctionSignature => 4003,
+            ConstraintKindMismatch => 4004,
+            NumberOfTypeActualsMismatch(_, _) => 4005,
+            InvalidMainFunctionReturn(_) => 4006,
+            InvalidMainFunctionArgument(_, _) => 4007,
+            TooManyTypeParameters => 4008,
+            TypeInstantiationTooDeep => 4009,
+
+            LookupFailed => 5001,
+            VisibilityMismatch => 5002,
+            TypeResolutionFailure => 5003,
+            TypeMismatch => 5004,
+            MissingDependency => 5005,
+            StructKindMismatch(_) => 5006,
+            FunctionSignatureMismatch(_) => 5007,
+            FunctionVisibilityMismatch(_) => 5008,
+            NativeFlagMismatch(_) => 5009,
+            CyclicModuleDependency(_) => 5010,
+            UnknownNativeFunction => 5011,
+            NativeFunctionSignatureMismatch => 5012,
+
+            InvalidFallThrough => 6001,
+            JoinFailure(_) => 6002,
+            NegativeStackSizeInsideBlock(_, _) => 6003,
+            PositiveStackSizeAtBlockEnd(_) => 6004,
+            ReleaseRefTypeMismatchError(_) => 6005,
+            BrTypeMismatchError(_) => 6006,
+            AbortTypeMismatchError(_) => 6007,
+            StLocTypeMismatchError(_) => 6008,
+            RetTypeMismatchError(_) => 6009,
+            FreezeRefTypeMismatchError(_) => 6010,
+            BorrowFieldTypeMismatchError(_) => 6011,
+            BorrowFieldBadFieldError(_) => 6012,
+            CopyLocUnavailableError(_) => 6013,
+            MoveLocUnavailableError(_) => 6014,
+            BorrowLocUnavailableError(_) => 6015,
+            CallTypeMismatchError(_) => 6016,
+            PackTypeMismatchError(_) => 6017,
+            UnpackTypeMismatchError(_) => 6018,
+            ReadRefTypeMismatchError(_) => 6019,
+            WriteRefTypeMismatchError(_) => 6020,
+            IntegerOpTypeMismatchError(_) => 6021,
+            BooleanOpTypeMismatchError(_) => 6022,
+            EqualityOpTypeMismatchError(_) => 6023,
+            ExistsResourceTypeMismatchError(_) => 6024,
+            BorrowGlobalTypeMismatchError(_) => 6025,
+            MoveFromTypeMismatchError(_) => 6026,
+            MoveToSenderTypeMismatchError(_) => 6027,
+            CreateAccountTypeMismatchError(_) => 6028,
+            VerificationBudgetExceeded => 6029,
+            StackHeightLimitExceeded(_) => 6030,
+            TooManyBasicBlocks => 6031,
+            UnreachableBlock(_) => 6032,
+            DeadCodeAfterBranch(_) => 6033,
+            IrreducibleControlFlow(_) => 6034,
+            UncheckedArithmetic(_) => 6035,
+            UnboundedLoop(_) => 6036,
+
+            PopReferenceError(_) => 7001,
+            FreezeRefExistsMutableBorrowError(_) => 7002,
+            BorrowFieldExistsMutableBorrowError(_) => 7003,
+            CopyLocExistsBorrowError(_) => 7004,
+            MoveLocExistsBorrowError(_) => 7005,
+            BorrowLocReferenceError(_) => 7006,
+            BorrowLocExistsBorrowError(_) => 7007,
+            CallBorrowedMutableReferenceError(_) => 7008,
+            ReadRefExistsMutableBorrowError(_) => 7009,
+            WriteRefExistsBorrowError(_) => 7010,
+            WriteRefNoMutableReferenceError(_) => 7011,
+
+            InvalidResourceField => 8001,
+            PopResourceError(_) => 8002,
+            StLocUnsafeToDestroyError(_) => 8003,
+            RetUnsafeToDestroyError(_) => 8004,
+            CopyLocResourceError(_) => 8005,
+            ReadRefResourceError(_) => 8006,
+            WriteRefResourceError(_) => 8007,
+            ExistsNoResourceError(_) => 8008,
+            BorrowGlobalNoResourceError(_) => 8009,
+            MoveFromNoResourceError(_) => 8010,
+            MoveToSenderNoResourceError(_) => 8011,
+            GlobalReferenceError(_) => 8012,
+            MissingAcquiresResourceAnnotationError(_) => 8013,
+            ExtraneousAcquiresResourceAnnotationError => 8014,
+            DuplicateAcquiresResourceAnnotationError => 8015,
+            InvalidAcquiresResourceAnnotationError => 8016,
+            ResourceReturnedByReference(_) => 8017,
+        }
+    }
+
+    /// Returns the category of this violation.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::of_code(self.code()).expect("every violation code belongs to a category")
+    }
+
+    /// Returns the severity of this violation.
+    pub fn severity(&self) -> Severity {
+        use VMStaticViolation::*;
+
+        match self {
+            // Unreachable code is never executed, so it can't be unsafe.
+            UnreachableBlock(_) | DeadCodeAfterBranch(_) => Severity::Warning,
+            // Lints flag code that deserves a review, but is well-formed.
+            UncheckedArithmetic(_) | UnboundedLoop(_) | ResourceReturnedByReference(_) => {
+                Severity::Warning
+            }
+            _ => Severity::Error,
+        }
+    }
+
+    /// Returns the offset of the offending instruction, for violations that point at one.
+    pub fn code_offset(&self) -> Option<CodeOffset> {
+        use VMStaticViolation::*;
+
+        let offset = match self {
+            CodeUnitIndexOutOfBounds(_, offset, _, _)
+            | NegativeStackSizeInsideBlock(_, offset)
+            | PopReferenceError(offset)
+            | PopResourceError(offset)
+            | ReleaseRefTypeMismatchError(offset)
+            | BrTypeMismatchError(offset)
+            | AbortTypeMismatchError(offset)
+            | StLocTypeMismatchError(offset)
+            | StLocUnsafeToDestroyError(offset)
+            | RetUnsafeToDestroyError(offset)
+            | RetTypeMismatchError(offset)
+            | FreezeRefTypeMismatchError(offset)
+            | FreezeRefExistsMutableBorrowError(offset)
+            | BorrowFieldTypeMismatchError(offset)
+            | BorrowFieldBadFieldError(offset)
+            | BorrowFieldExistsMutableBorrowError(offset)
+            | CopyLocUnavailableError(offset)
+            | CopyLocResourceError(offset)
+            | CopyLocExistsBorrowError(offset)
+            | MoveLocUnavailableError(offset)
+            | MoveLocExistsBorrowError(offset)
+            | BorrowLocReferenceError(offset)
+            | BorrowLocUnavailableError(offset)
+            | BorrowLocExistsBorrowError(offset)
+            | CallTypeMismatchError(offset)
+            | CallBorrowedMutableReferenceError(offset)
+            | PackTypeMismatchError(offset)
+            | UnpackTypeMismatchError(offset)
+            | ReadRefTypeMismatchError(offset)
+            | ReadRefResourceError(offset)
+            | ReadRefExistsMutableBorrowError(offset)
+            | WriteRefTypeMismatchError(offset)
+            | WriteRefResourceError(offset)
+            | WriteRefExistsBorrowError(offset)
+            | WriteRefNoMutableReferenceError(offset)
+            | IntegerOpTypeMismatchError(offset)
+            | BooleanOpTypeMismatchError(offset)
+            | EqualityOpTypeMismatchError(offset)
+            | ExistsResourceTypeMismatchError(offset)
+            | ExistsNoResourceError(offset)
+            | BorrowGlobalTypeMismatchError(offset)
+            | BorrowGlobalNoResourceError(offset)
+            | MoveFromTypeMismatchError(offset)
+            | MoveFromNoResourceError(offset)
+            | MoveToSenderTypeMismatchError(offset)
+            | MoveToSenderNoResourceError(offset)
+            | CreateAccountTypeMismatchError(offset)
+            | GlobalReferenceError(offset)
+            | MissingAcquiresResourceAnnotationError(offset)
+            | StackHeightLimitExceeded(offset)
+            | UnreachableBlock(offset)
+            | DeadCodeAfterBranch(offset)
+            | IrreducibleControlFlow(offset)
+            | UncheckedArithmetic(offset)
+            | UnboundedLoop(offset) => *offset,
+            IndexOutOfBounds(_, _, _)
+            | RangeOutOfBounds(_, _, _, _)
+            | CodeUnitTooLong(_)
+            | NoModuleHandles
+            | ModuleAddressDoesNotMatchSender
+            | InvalidSignatureToken(_, _, _)
+            | DuplicateElement(_, _)
+            | InvalidModuleHandle
+            | UnimplementedHandle
+            | InconsistentFields
+            | UnusedFields
+            | InvalidFieldDefReference(_, _)
+            | RecursiveStructDef
+            | InvalidResourceField
+            | InvalidFallThrough
+            | JoinFailure(_)
+            | PositiveStackSizeAtBlockEnd(_)
+            | InvalidMainFunctionSignature
+            | LookupFailed
+            | VisibilityMismatch
+            | TypeResolutionFailure
+            | TypeMismatch
+            | MissingDependency
+            | ExtraneousAcquiresResourceAnnotationError
+            | DuplicateAcquiresResourceAnnotationError
+            | InvalidAcquiresResourceAnnotationError
+            | ConstraintKindMismatch
+            | NumberOfTypeActualsMismatch(_, _)
+            | VerificationBudgetExceeded
+            | InvalidMainFunctionReturn(_)
+            | InvalidMainFunctionArgument(_, _)
+            | StructKindMismatch(_)
+            | FunctionSignatureMismatch(_)
+            | FunctionVisibilityMismatch(_)
+            | NativeFlagMismatch(_)
+            | CyclicModuleDependency(_)
+            | TooManyTypeParameters
+            | TypeInstantiationTooDeep
+            | TooManyStructFields
+            | TooManyBasicBlocks
+            | UnknownNativeFunction
+            | NativeFunctionSignatureMismatch
+            | ResourceReturnedByReference(_) => return None,
+        };
+        Some(offset as CodeOffset)
+    }
+}
+
+impl VerificationError {
+    /// Returns the stable numeric code of the underlying violation.
+    pub fn code(&self) -> u32 {
+        self.err.code()
+    }
+
+    /// Returns the category of the underlying violation.
+    pub fn category(&self) -> ErrorCategory {
+        self.err.category()
+    }
+
+    /// Returns the severity of the underlying violation.
+    pub fn severity(&self) -> Severity {
+        self.err.severity()
+    }
+
+    /// Returns true if this error must fail verification.
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+/// Sorts `errors` into a canonical order: by location (index kind, then index), then by error
+/// code. Errors that agree on all three are ordered by their remaining fields, so the result never
+/// depends on the order in which the checks happened to find the errors.
+pub fn sort_errors(errors: &mut [VerificationError]) {
+    errors.sort_by(|a, b| {
+        (a.kind, a.idx, a.code())
+            .cmp(&(b.kind, b.idx, b.code()))
+            .then_with(|| a.cmp(b))
+    });
+}
+
+/// Sorts `errors` with `sort_errors`, then keeps only the first error for every combination of
+/// location and error code.
+pub fn sort_and_dedup_errors(errors: &mut Vec<VerificationError>) {
+    sort_errors(errors);
+    errors.dedup_by(|a, b| (a.kind, a.idx, a.code()) == (b.kind, b.idx, b.code()));
+}
+
+/// A list of verification errors that stores at most a fixed number of errors.
+///
+/// Checks that collect every error they find can report thousands of errors on garbage input.
+/// Once the cap is reached, further errors are counted but not stored, which bounds memory usage
+/// while keeping the total number of errors accurate.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CappedErrors {
+    errors: Vec<VerificationError>,
+    max_errors: usize,
+    suppressed: usize,
+}
+
+impl CappedErrors {
+    /// Creates an empty list that stores at most `max_errors` errors.
+    pub fn new(max_errors: usize) -> Self {
+        Self {
+            errors: vec![],
+            max_errors,
+            suppressed: 0,
+        }
+    }
+
+    /// Adds `error`, or only counts it if the cap has been reached.
+    pub fn push(&mut self, error: VerificationError) {
+        if self.errors.len() < self.max_errors {
+            self.errors.push(error);
+        } else {
+            self.suppressed += 1;
+        }
+    }
+
+    /// Returns true if no errors were added, including suppressed ones.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.suppressed == 0
+    }
+
+    /// Returns the stored errors.
+    pub fn errors(&self) -> &[VerificationError] {
+        &self.errors
+    }
+
+    /// Returns the number of errors that were added after the cap was reached.
+    pub fn suppressed(&self) -> usize {
+        self.suppressed
+    }
+
+    /// Returns the number of errors that were added, including suppressed ones.
+    pub fn total(&self) -> usize {
+        self.errors.len() + self.suppressed
+    }
+
+    /// Returns true if any errors were suppressed.
+    pub fn is_truncated(&self) -> bool {
+        self.suppressed > 0
+    }
+
+    /// Returns the stored errors, dropping the count of suppressed errors.
+    pub fn into_errors(self) -> Vec<VerificationError> {
+        self.errors
+    }
+}
+
+impl Extend<VerificationError> for CappedErrors {
+    fn extend<I: IntoIterator<Item = VerificationError>>(&mut self, iter: I) {
+        for error in iter {
+            self.push(error);
+        }
+    }
+}
+
+impl fmt::Display for CappedErrors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for error in &self.errors {
+            writeln!(f, "{}", error)?;
+        }
+        if self.is_truncated() {
+            writeln!(f, "... {} more errors suppressed", self.suppressed)?;
+        }
+        Ok(())
+    }
+}
+
+/// How serious a static violation is.
+///
+/// Only violations with severity `Error` fail verification. Advisory checks (e.g. unreachable
+/// code) report their findings with a lower severity, so that they can be surfaced alongside
+/// errors without rejecting the module. Severities are ordered from least to most serious.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Severity {
+    /// Purely informational.
+    Note,
+    /// Likely a mistake, but safe to execute.
+    Warning,
+    /// Unsafe to execute; verification fails.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let desc = match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        f.write_str(desc)
+    }
+}
+
+/// Returns the errors in `errors` whose severity is at least `min_severity`.
+pub fn filter_by_severity<'a>(
+    errors: &'a [VerificationError],
+    min_severity: Severity,
+) -> impl Iterator<Item = &'a VerificationError> + 'a {
+    errors
+        .iter()
+        .filter(move |error| error.severity() >= min_severity)
+}
+
+/// Returns true if any of `errors` must fail verification.
+pub fn has_errors(errors: &[VerificationError]) -> bool {
+    errors.iter().any(VerificationError::is_error)
 }
 
 #[derive(Clone, Debug, Eq, Fail, Ord, PartialEq, PartialOrd)]
@@ -359,6 +1288,32 @@ pub enum VMInvariantViolation {
     EventKeyMismatch,
 }
 
+impl VMInvariantViolation {
+    /// Returns the stable numeric code of this invariant violation.
+    pub fn code(&self) -> u32 {
+        use VMInvariantViolation::*;
+
+        match self {
+            IndexOutOfBounds(_, _, _) => 9001,
+            RangeOutOfBounds(_, _, _, _) => 9002,
+            EmptyValueStack => 9003,
+            EmptyCallStack => 9004,
+            ProgramCounterOverflow => 9005,
+            LinkerError => 9006,
+            LocalReferenceError => 9007,
+            StorageError => 9008,
+            InternalTypeError => 9009,
+            EventKeyMismatch => 9010,
+        }
+    }
+
+    /// Returns the category of this invariant violation, which is always
+    /// `ErrorCategory::Invariant`.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::Invariant
+    }
+}
+
 /// Error codes that can be emitted by the prologue. These have special significance to the VM when
 /// they are raised during the prologue. However, they can also be raised by user code during
 /// execution of a transaction script. They have no significance to the VM in that case.
@@ -411,6 +1366,32 @@ pub enum BinaryError {
     DuplicateTable,
 }
 
+impl BinaryError {
+    /// Returns the stable numeric code of this deserialization error.
+    pub fn code(&self) -> u32 {
+        use BinaryError::*;
+
+        match self {
+            Malformed => 1001,
+            BadMagic => 1002,
+            UnknownVersion => 1003,
+            UnknownTableType => 1004,
+            UnknownSignatureType => 1005,
+            UnexpectedSignatureType => 1006,
+            UnknownSerializedType => 1007,
+            UnknownOpcode => 1008,
+            BadHeaderTable => 1009,
+            DuplicateTable => 1010,
+        }
+    }
+
+    /// Returns the category of this deserialization error, which is always
+    /// `ErrorCategory::Binary`.
+    pub fn category(&self) -> ErrorCategory {
+        ErrorCategory::Binary
+    }
+}
+
 #[macro_export]
 macro_rules! try_runtime {
     ($e:expr) => {
@@ -546,222 +1527,277 @@ impl From<&VMInvariantViolation> for VMStatus {
 
 impl From<&VerificationError> for VMVerificationError {
     fn from(error: &VerificationError) -> Self {
-        let message = format!("{}", error);
-        match error.err {
-            VMStaticViolation::IndexOutOfBounds(_, _, _) => {
-                VMVerificationError::IndexOutOfBounds(message)
-            }
-            VMStaticViolation::CodeUnitIndexOutOfBounds(_, _, _, _) => {
-                VMVerificationError::CodeUnitIndexOutOfBounds(message)
-            }
-            VMStaticViolation::RangeOutOfBounds(_, _, _, _) => {
-                VMVerificationError::RangeOutOfBounds(message)
-            }
-            VMStaticViolation::NoModuleHandles => VMVerificationError::NoModuleHandles(message),
-            VMStaticViolation::ModuleAddressDoesNotMatchSender => {
-                VMVerificationError::ModuleAddressDoesNotMatchSender(message)
-            }
-            VMStaticViolation::InvalidSignatureToken(_, _, _) => {
-                VMVerificationError::InvalidSignatureToken(message)
-            }
-            VMStaticViolation::DuplicateElement => VMVerificationError::DuplicateElement(message),
-            VMStaticViolation::InvalidModuleHandle => {
-                VMVerificationError::InvalidModuleHandle(message)
-            }
-            VMStaticViolation::UnimplementedHandle => {
-                VMVerificationError::UnimplementedHandle(message)
-            }
-            VMStaticViolation::InconsistentFields => {
-                VMVerificationError::InconsistentFields(message)
-            }
-            VMStaticViolation::UnusedFields => VMVerificationError::UnusedFields(message),
-            VMStaticViolation::InvalidFieldDefReference(_, _) => {
-                VMVerificationError::InvalidFieldDefReference(message)
-            }
-            VMStaticViolation::RecursiveStructDef => {
-                VMVerificationError::RecursiveStructDefinition(message)
-            }
-            VMStaticViolation::InvalidResourceField => {
-                VMVerificationError::InvalidResourceField(message)
-            }
-            VMStaticViolation::InvalidFallThrough => {
-                VMVerificationError::InvalidFallThrough(message)
-            }
-            VMStaticViolation::JoinFailure(_) => VMVerificationError::JoinFailure(message),
-            VMStaticViolation::NegativeStackSizeInsideBlock(_, _) => {
-                VMVerificationError::NegativeStackSizeWithinBlock(message)
-            }
-            VMStaticViolation::PositiveStackSizeAtBlockEnd(_) => {
-                VMVerificationError::UnbalancedStack(message)
-            }
-            VMStaticViolation::InvalidMainFunctionSignature => {
-                VMVerificationError::InvalidMainFunctionSignature(message)
-            }
-            VMStaticViolation::LookupFailed => VMVerificationError::LookupFailed(message),
-            VMStaticViolation::VisibilityMismatch => {
-                VMVerificationError::VisibilityMismatch(message)
-            }
-            VMStaticViolation::TypeResolutionFailure => {
-                VMVerificationError::TypeResolutionFailure(message)
-            }
-            VMStaticViolation::TypeMismatch => VMVerificationError::TypeMismatch(message),
-            VMStaticViolation::MissingDependency => VMVerificationError::MissingDependency(message),
-            VMStaticViolation::PopReferenceError(_) => {
-                VMVerificationError::PopReferenceError(message)
-            }
-            VMStaticViolation::PopResourceError(_) => {
-                VMVerificationError::PopResourceError(message)
-            }
-            VMStaticViolation::ReleaseRefTypeMismatchError(_) => {
-                VMVerificationError::ReleaseRefTypeMismatchError(message)
-            }
-            VMStaticViolation::BrTypeMismatchError(_) => {
-                VMVerificationError::BrTypeMismatchError(message)
-            }
-            VMStaticViolation::AbortTypeMismatchError(_) => {
-                VMVerificationError::AbortTypeMismatchError(message)
-            }
-            VMStaticViolation::StLocTypeMismatchError(_) => {
-                VMVerificationError::StLocTypeMismatchError(message)
-            }
-            VMStaticViolation::StLocUnsafeToDestroyError(_) => {
-                VMVerificationError::StLocUnsafeToDestroyError(message)
-            }
-            VMStaticViolation::RetUnsafeToDestroyError(_) => {
-                VMVerificationError::RetUnsafeToDestroyError(message)
-            }
-            VMStaticViolation::RetTypeMismatchError(_) => {
-                VMVerificationError::RetTypeMismatchError(message)
-            }
-            VMStaticViolation::FreezeRefTypeMismatchError(_) => {
-                VMVerificationError::FreezeRefTypeMismatchError(message)
-            }
-            VMStaticViolation::FreezeRefExistsMutableBorrowError(_) => {
-                VMVerificationError::FreezeRefExistsMutableBorrowError(message)
-            }
-            VMStaticViolation::BorrowFieldTypeMismatchError(_) => {
-                VMVerificationError::BorrowFieldTypeMismatchError(message)
-            }
-            VMStaticViolation::BorrowFieldBadFieldError(_) => {
-                VMVerificationError::BorrowFieldBadFieldError(message)
-            }
-            VMStaticViolation::BorrowFieldExistsMutableBorrowError(_) => {
-                VMVerificationError::BorrowFieldExistsMutableBorrowError(message)
-            }
-            VMStaticViolation::CopyLocUnavailableError(_) => {
-                VMVerificationError::CopyLocUnavailableError(message)
-            }
-            VMStaticViolation::CopyLocResourceError(_) => {
-                VMVerificationError::CopyLocResourceError(message)
-            }
-            VMStaticViolation::CopyLocExistsBorrowError(_) => {
-                VMVerificationError::CopyLocExistsBorrowError(message)
-            }
-            VMStaticViolation::MoveLocUnavailableError(_) => {
-                VMVerificationError::MoveLocUnavailableError(message)
-            }
-            VMStaticViolation::MoveLocExistsBorrowError(_) => {
-                VMVerificationError::MoveLocExistsBorrowError(message)
-            }
-            VMStaticViolation::BorrowLocReferenceError(_) => {
-                VMVerificationError::BorrowLocReferenceError(message)
-            }
-            VMStaticViolation::BorrowLocUnavailableError(_) => {
-                VMVerificationError::BorrowLocUnavailableError(message)
-            }
-            VMStaticViolation::BorrowLocExistsBorrowError(_) => {
-                VMVerificationError::BorrowLocExistsBorrowError(message)
-            }
-            VMStaticViolation::CallTypeMismatchError(_) => {
-                VMVerificationError::CallTypeMismatchError(message)
-            }
-            VMStaticViolation::CallBorrowedMutableReferenceError(_) => {
-                VMVerificationError::CallBorrowedMutableReferenceError(message)
-            }
-            VMStaticViolation::PackTypeMismatchError(_) => {
-                VMVerificationError::PackTypeMismatchError(message)
-            }
-            VMStaticViolation::UnpackTypeMismatchError(_) => {
-                VMVerificationError::UnpackTypeMismatchError(message)
-            }
-            VMStaticViolation::ReadRefTypeMismatchError(_) => {
-                VMVerificationError::ReadRefTypeMismatchError(message)
-            }
-            VMStaticViolation::ReadRefResourceError(_) => {
-                VMVerificationError::ReadRefResourceError(message)
-            }
-            VMStaticViolation::ReadRefExistsMutableBorrowError(_) => {
-                VMVerificationError::ReadRefExistsMutableBorrowError(message)
-            }
-            VMStaticViolation::WriteRefTypeMismatchError(_) => {
-                VMVerificationError::WriteRefTypeMismatchError(message)
-            }
-            VMStaticViolation::WriteRefResourceError(_) => {
-                VMVerificationError::WriteRefResourceError(message)
-            }
-            VMStaticViolation::WriteRefExistsBorrowError(_) => {
-                VMVerificationError::WriteRefExistsBorrowError(message)
-            }
-            VMStaticViolation::WriteRefNoMutableReferenceError(_) => {
-                VMVerificationError::WriteRefNoMutableReferenceError(message)
-            }
-            VMStaticViolation::IntegerOpTypeMismatchError(_) => {
-                VMVerificationError::IntegerOpTypeMismatchError(message)
-            }
-            VMStaticViolation::BooleanOpTypeMismatchError(_) => {
-                VMVerificationError::BooleanOpTypeMismatchError(message)
-            }
-            VMStaticViolation::EqualityOpTypeMismatchError(_) => {
-                VMVerificationError::EqualityOpTypeMismatchError(message)
-            }
-            VMStaticViolation::ExistsResourceTypeMismatchError(_) => {
-                VMVerificationError::ExistsResourceTypeMismatchError(message)
-            }
-            VMStaticViolation::ExistsNoResourceError(_) => {
-                VMVerificationError::ExistsNoResourceError(message)
-            }
-            VMStaticViolation::BorrowGlobalTypeMismatchError(_) => {
-                VMVerificationError::BorrowGlobalTypeMismatchError(message)
-            }
-            VMStaticViolation::BorrowGlobalNoResourceError(_) => {
-                VMVerificationError::BorrowGlobalNoResourceError(message)
-            }
-            VMStaticViolation::MoveFromTypeMismatchError(_) => {
-                VMVerificationError::MoveFromTypeMismatchError(message)
-            }
-            VMStaticViolation::MoveFromNoResourceError(_) => {
-                VMVerificationError::MoveFromNoResourceError(message)
-            }
-            VMStaticViolation::MoveToSenderTypeMismatchError(_) => {
-                VMVerificationError::MoveToSenderTypeMismatchError(message)
-            }
-            VMStaticViolation::MoveToSenderNoResourceError(_) => {
-                VMVerificationError::MoveToSenderNoResourceError(message)
-            }
-            VMStaticViolation::CreateAccountTypeMismatchError(_) => {
-                VMVerificationError::CreateAccountTypeMismatchError(message)
-            }
-            VMStaticViolation::GlobalReferenceError(_) => {
-                VMVerificationError::GlobalReferenceError(message)
-            }
-            VMStaticViolation::MissingAcquiresResourceAnnotationError(_) => {
-                VMVerificationError::MissingAcquiresResourceAnnotationError(message)
-            }
-            VMStaticViolation::ExtraneousAcquiresResourceAnnotationError => {
-                VMVerificationError::ExtraneousAcquiresResourceAnnotationError(message)
-            }
-            VMStaticViolation::InvalidAcquiresResourceAnnotationError => {
-                VMVerificationError::InvalidAcquiresResourceAnnotationError(message)
-            }
-            VMStaticViolation::DuplicateAcquiresResourceAnnotationError => {
-                VMVerificationError::DuplicateAcquiresResourceAnnotationError(message)
-            }
-            VMStaticViolation::ConstraintKindMismatch => {
-                VMVerificationError::ConstraintKindMismatch(message)
-            }
-            VMStaticViolation::NumberOfTypeActualsMismatch(_, _) => {
-                VMVerificationError::NumberOfTypeActualsMismatch(message)
-            }
+        static_violation_to_vm_error(&error.err, format!("{}", error))
+    }
+}
+
+/// Maps a static violation to the verification error reported to clients in `VMStatus`, with
+/// `message` as the human-readable description.
+///
+/// This is the canonical table used by the runtime and clients to agree on what a violation means
+/// externally, so it must stay an exhaustive match: every new `VMStaticViolation` variant needs an
+/// explicit entry here. Note that a `VMStatus` containing verification errors always results in
+/// the transaction being discarded (see `TransactionStatus`).
+pub fn static_violation_to_vm_error(
+    violation: &VMStaticViolation,
+    message: String,
+) -> VMVerificationError {
+    match violation {
+        VMStaticViolation::IndexOutOfBounds(_, _, _) => {
+            VMVerificationError::IndexOutOfBounds(message)
+        }
+        VMStaticViolation::CodeUnitIndexOutOfBounds(_, _, _, _) => {
+            VMVerificationError::CodeUnitIndexOutOfBounds(message)
+        }
+        VMStaticViolation::RangeOutOfBounds(_, _, _, _) => {
+            VMVerificationError::RangeOutOfBounds(message)
+        }
+        VMStaticViolation::CodeUnitTooLong(_) => VMVerificationError::CodeUnitTooLong(message),
+        VMStaticViolation::NoModuleHandles => VMVerificationError::NoModuleHandles(message),
+        VMStaticViolation::ModuleAddressDoesNotMatchSender => {
+            VMVerificationError::ModuleAddressDoesNotMatchSender(message)
+        }
+        VMStaticViolation::InvalidSignatureToken(_, _, _) => {
+            VMVerificationError::InvalidSignatureToken(message)
+        }
+        VMStaticViolation::DuplicateElement(_, _) => VMVerificationError::DuplicateElement(message),
+        VMStaticViolation::InvalidModuleHandle => VMVerificationError::InvalidModuleHandle(message),
+        VMStaticViolation::UnimplementedHandle => VMVerificationError::UnimplementedHandle(message),
+        VMStaticViolation::InconsistentFields => VMVerificationError::InconsistentFields(message),
+        VMStaticViolation::UnusedFields => VMVerificationError::UnusedFields(message),
+        VMStaticViolation::InvalidFieldDefReference(_, _) => {
+            VMVerificationError::InvalidFieldDefReference(message)
+        }
+        VMStaticViolation::RecursiveStructDef => {
+            VMVerificationError::RecursiveStructDefinition(message)
+        }
+        VMStaticViolation::InvalidResourceField => {
+            VMVerificationError::InvalidResourceField(message)
+        }
+        VMStaticViolation::InvalidFallThrough => VMVerificationError::InvalidFallThrough(message),
+        VMStaticViolation::JoinFailure(_) => VMVerificationError::JoinFailure(message),
+        VMStaticViolation::NegativeStackSizeInsideBlock(_, _) => {
+            VMVerificationError::NegativeStackSizeWithinBlock(message)
+        }
+        VMStaticViolation::PositiveStackSizeAtBlockEnd(_) => {
+            VMVerificationError::UnbalancedStack(message)
+        }
+        VMStaticViolation::InvalidMainFunctionSignature => {
+            VMVerificationError::InvalidMainFunctionSignature(message)
+        }
+        VMStaticViolation::LookupFailed => VMVerificationError::LookupFailed(message),
+        VMStaticViolation::VisibilityMismatch => VMVerificationError::VisibilityMismatch(message),
+        VMStaticViolation::TypeResolutionFailure => {
+            VMVerificationError::TypeResolutionFailure(message)
+        }
+        VMStaticViolation::TypeMismatch => VMVerificationError::TypeMismatch(message),
+        VMStaticViolation::MissingDependency => VMVerificationError::MissingDependency(message),
+        VMStaticViolation::PopReferenceError(_) => VMVerificationError::PopReferenceError(message),
+        VMStaticViolation::PopResourceError(_) => VMVerificationError::PopResourceError(message),
+        VMStaticViolation::ReleaseRefTypeMismatchError(_) => {
+            VMVerificationError::ReleaseRefTypeMismatchError(message)
+        }
+        VMStaticViolation::BrTypeMismatchError(_) => {
+            VMVerificationError::BrTypeMismatchError(message)
+        }
+        VMStaticViolation::AbortTypeMismatchError(_) => {
+            VMVerificationError::AbortTypeMismatchError(message)
+        }
+        VMStaticViolation::StLocTypeMismatchError(_) => {
+            VMVerificationError::StLocTypeMismatchError(message)
+        }
+        VMStaticViolation::StLocUnsafeToDestroyError(_) => {
+            VMVerificationError::StLocUnsafeToDestroyError(message)
+        }
+        VMStaticViolation::RetUnsafeToDestroyError(_) => {
+            VMVerificationError::RetUnsafeToDestroyError(message)
+        }
+        VMStaticViolation::RetTypeMismatchError(_) => {
+            VMVerificationError::RetTypeMismatchError(message)
+        }
+        VMStaticViolation::FreezeRefTypeMismatchError(_) => {
+            VMVerificationError::FreezeRefTypeMismatchError(message)
+        }
+        VMStaticViolation::FreezeRefExistsMutableBorrowError(_) => {
+            VMVerificationError::FreezeRefExistsMutableBorrowError(message)
+        }
+        VMStaticViolation::BorrowFieldTypeMismatchError(_) => {
+            VMVerificationError::BorrowFieldTypeMismatchError(message)
+        }
+        VMStaticViolation::BorrowFieldBadFieldError(_) => {
+            VMVerificationError::BorrowFieldBadFieldError(message)
+        }
+        VMStaticViolation::BorrowFieldExistsMutableBorrowError(_) => {
+            VMVerificationError::BorrowFieldExistsMutableBorrowError(message)
+        }
+        VMStaticViolation::CopyLocUnavailableError(_) => {
+            VMVerificationError::CopyLocUnavailableError(message)
+        }
+        VMStaticViolation::CopyLocResourceError(_) => {
+            VMVerificationError::CopyLocResourceError(message)
+        }
+        VMStaticViolation::CopyLocExistsBorrowError(_) => {
+            VMVerificationError::CopyLocExistsBorrowError(message)
+        }
+        VMStaticViolation::MoveLocUnavailableError(_) => {
+            VMVerificationError::MoveLocUnavailableError(message)
+        }
+        VMStaticViolation::MoveLocExistsBorrowError(_) => {
+            VMVerificationError::MoveLocExistsBorrowError(message)
+        }
+        VMStaticViolation::BorrowLocReferenceError(_) => {
+            VMVerificationError::BorrowLocReferenceError(message)
+        }
+        VMStaticViolation::BorrowLocUnavailableError(_) => {
+            VMVerificationError::BorrowLocUnavailableError(message)
+        }
+        VMStaticViolation::BorrowLocExistsBorrowError(_) => {
+            VMVerificationError::BorrowLocExistsBorrowError(message)
+        }
+        VMStaticViolation::CallTypeMismatchError(_) => {
+            VMVerificationError::CallTypeMismatchError(message)
+        }
+        VMStaticViolation::CallBorrowedMutableReferenceError(_) => {
+            VMVerificationError::CallBorrowedMutableReferenceError(message)
+        }
+        VMStaticViolation::PackTypeMismatchError(_) => {
+            VMVerificationError::PackTypeMismatchError(message)
+        }
+        VMStaticViolation::UnpackTypeMismatchError(_) => {
+            VMVerificationError::UnpackTypeMismatchError(message)
+        }
+        VMStaticViolation::ReadRefTypeMismatchError(_) => {
+            VMVerificationError::ReadRefTypeMismatchError(message)
+        }
+        VMStaticViolation::ReadRefResourceError(_) => {
+            VMVerificationError::ReadRefResourceError(message)
+        }
+        VMStaticViolation::ReadRefExistsMutableBorrowError(_) => {
+            VMVerificationError::ReadRefExistsMutableBorrowError(message)
+        }
+        VMStaticViolation::WriteRefTypeMismatchError(_) => {
+            VMVerificationError::WriteRefTypeMismatchError(message)
+        }
+        VMStaticViolation::WriteRefResourceError(_) => {
+            VMVerificationError::WriteRefResourceError(message)
+        }
+        VMStaticViolation::WriteRefExistsBorrowError(_) => {
+            VMVerificationError::WriteRefExistsBorrowError(message)
+        }
+        VMStaticViolation::WriteRefNoMutableReferenceError(_) => {
+            VMVerificationError::WriteRefNoMutableReferenceError(message)
+        }
+        VMStaticViolation::IntegerOpTypeMismatchError(_) => {
+            VMVerificationError::IntegerOpTypeMismatchError(message)
+        }
+        VMStaticViolation::BooleanOpTypeMismatchError(_) => {
+            VMVerificationError::BooleanOpTypeMismatchError(message)
+        }
+        VMStaticViolation::EqualityOpTypeMismatchError(_) => {
+            VMVerificationError::EqualityOpTypeMismatchError(message)
+        }
+        VMStaticViolation::ExistsResourceTypeMismatchError(_) => {
+            VMVerificationError::ExistsResourceTypeMismatchError(message)
+        }
+        VMStaticViolation::ExistsNoResourceError(_) => {
+            VMVerificationError::ExistsNoResourceError(message)
+        }
+        VMStaticViolation::BorrowGlobalTypeMismatchError(_) => {
+            VMVerificationError::BorrowGlobalTypeMismatchError(message)
+        }
+        VMStaticViolation::BorrowGlobalNoResourceError(_) => {
+            VMVerificationError::BorrowGlobalNoResourceError(message)
+        }
+        VMStaticViolation::MoveFromTypeMismatchError(_) => {
+            VMVerificationError::MoveFromTypeMismatchError(message)
+        }
+        VMStaticViolation::MoveFromNoResourceError(_) => {
+            VMVerificationError::MoveFromNoResourceError(message)
+        }
+        VMStaticViolation::MoveToSenderTypeMismatchError(_) => {
+            VMVerificationError::MoveToSenderTypeMismatchError(message)
+        }
+        VMStaticViolation::MoveToSenderNoResourceError(_) => {
+            VMVerificationError::MoveToSenderNoResourceError(message)
+        }
+        VMStaticViolation::CreateAccountTypeMismatchError(_) => {
+            VMVerificationError::CreateAccountTypeMismatchError(message)
+        }
+        VMStaticViolation::GlobalReferenceError(_) => {
+            VMVerificationError::GlobalReferenceError(message)
+        }
+        VMStaticViolation::MissingAcquiresResourceAnnotationError(_) => {
+            VMVerificationError::MissingAcquiresResourceAnnotationError(message)
+        }
+        VMStaticViolation::ExtraneousAcquiresResourceAnnotationError => {
+            VMVerificationError::ExtraneousAcquiresResourceAnnotationError(message)
+        }
+        VMStaticViolation::InvalidAcquiresResourceAnnotationError => {
+            VMVerificationError::InvalidAcquiresResourceAnnotationError(message)
+        }
+        VMStaticViolation::DuplicateAcquiresResourceAnnotationError => {
+            VMVerificationError::DuplicateAcquiresResourceAnnotationError(message)
+        }
+        VMStaticViolation::ConstraintKindMismatch => {
+            VMVerificationError::ConstraintKindMismatch(message)
+        }
+        VMStaticViolation::NumberOfTypeActualsMismatch(_, _) => {
+            VMVerificationError::NumberOfTypeActualsMismatch(message)
+        }
+        VMStaticViolation::VerificationBudgetExceeded => {
+            VMVerificationError::VerificationBudgetExceeded(message)
+        }
+        VMStaticViolation::StackHeightLimitExceeded(_) => {
+            VMVerificationError::StackHeightLimitExceeded(message)
+        }
+        VMStaticViolation::InvalidMainFunctionReturn(_) => {
+            VMVerificationError::InvalidMainFunctionReturn(message)
+        }
+        VMStaticViolation::InvalidMainFunctionArgument(_, _) => {
+            VMVerificationError::InvalidMainFunctionArgument(message)
+        }
+        VMStaticViolation::StructKindMismatch(_) => {
+            VMVerificationError::StructKindMismatch(message)
+        }
+        VMStaticViolation::FunctionSignatureMismatch(_) => {
+            VMVerificationError::FunctionSignatureMismatch(message)
+        }
+        VMStaticViolation::FunctionVisibilityMismatch(_) => {
+            VMVerificationError::FunctionVisibilityMismatch(message)
+        }
+        VMStaticViolation::NativeFlagMismatch(_) => {
+            VMVerificationError::NativeFlagMismatch(message)
+        }
+        VMStaticViolation::CyclicModuleDependency(_) => {
+            VMVerificationError::CyclicModuleDependency(message)
+        }
+        VMStaticViolation::TooManyTypeParameters => {
+            VMVerificationError::TooManyTypeParameters(message)
+        }
+        VMStaticViolation::TypeInstantiationTooDeep => {
+            VMVerificationError::TypeInstantiationTooDeep(message)
+        }
+        VMStaticViolation::TooManyStructFields => VMVerificationError::TooManyStructFields(message),
+        VMStaticViolation::TooManyBasicBlocks => VMVerificationError::TooManyBasicBlocks(message),
+        VMStaticViolation::UnreachableBlock(_) => VMVerificationError::UnreachableBlock(message),
+        VMStaticViolation::DeadCodeAfterBranch(_) => {
+            VMVerificationError::DeadCodeAfterBranch(message)
+        }
+        VMStaticViolation::UnknownNativeFunction => {
+            VMVerificationError::UnknownNativeFunction(message)
+        }
+        VMStaticViolation::NativeFunctionSignatureMismatch => {
+            VMVerificationError::NativeFunctionSignatureMismatch(message)
+        }
+        VMStaticViolation::IrreducibleControlFlow(_) => {
+            VMVerificationError::IrreducibleControlFlow(message)
+        }
+        VMStaticViolation::UncheckedArithmetic(_) => {
+            VMVerificationError::UncheckedArithmetic(message)
+        }
+        VMStaticViolation::UnboundedLoop(_) => VMVerificationError::UnboundedLoop(message),
+        VMStaticViolation::ResourceReturnedByReference(_) => {
+            VMVerificationError::ResourceReturnedByReference(message)
         }
     }
 }
@@ -28,8 +28,9 @@
 
 use crate::{
     access::ModuleAccess,
-    check_bounds::BoundsChecker,
-    errors::{VMInvariantViolation, VerificationError},
+    check_bounds::{BoundsChecker, FreezeError},
+    errors::{VMInvariantViolation, VMStaticViolation},
+    identifier::Identifier,
     internals::ModuleIndex,
     IndexKind, SignatureTokenKind,
 };
@@ -37,6 +38,9 @@ use crate::{
 use proptest::{collection::vec, prelude::*, strategy::BoxedStrategy};
 #[cfg(any(test, feature = "testing"))]
 use proptest_derive::Arbitrary;
+use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
+use std::{convert::TryFrom, sync::Arc};
 use types::{account_address::AccountAddress, byte_array::ByteArray, language_storage::ModuleId};
 
 /// Generic index into one of the tables in the binary format.
@@ -48,7 +52,9 @@ macro_rules! define_index {
         kind: $kind: ident,
         doc: $comment: literal,
     } => {
-        #[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+        #[derive(
+            Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize,
+        )]
         #[cfg_attr(any(test, feature = "testing"), derive(Arbitrary))]
         #[cfg_attr(any(test, feature = "testing"), proptest(no_params))]
         #[doc=$comment]
@@ -59,11 +65,32 @@ macro_rules! define_index {
             pub fn new(idx: TableIndex) -> Self {
                 Self(idx)
             }
+
+            /// Returns an index to entry `idx` of a table with `table_len` entries, or an error if
+            /// `idx` is out of bounds for the table or doesn't fit in a `TableIndex`.
+            pub fn try_new(idx: usize, table_len: usize) -> Result<Self, VMStaticViolation> {
+                let len = table_len.min(TableIndex::max_value() as usize + 1);
+                if idx < len {
+                    Ok(Self(idx as TableIndex))
+                } else {
+                    Err(VMStaticViolation::IndexOutOfBounds(IndexKind::$kind, len, idx))
+                }
+            }
+        }
+
+        /// Converts a `usize` to an index, checking that it fits in a `TableIndex`. Use `try_new`
+        /// to also check it against the length of the table.
+        impl TryFrom<usize> for $name {
+            type Error = VMStaticViolation;
+
+            fn try_from(idx: usize) -> Result<Self, VMStaticViolation> {
+                Self::try_new(idx, TableIndex::max_value() as usize + 1)
+            }
         }
 
         impl ::std::fmt::Display for $name {
             fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-                write!(f, "{}", self.0)
+                write!(f, "{} #{}", IndexKind::$kind, self.0)
             }
         }
 
@@ -155,8 +182,81 @@ pub type MemberCount = u16;
 /// the instruction stream.
 pub type CodeOffset = u16;
 
+/// A position in a code stream whose arithmetic never wraps.
+///
+/// Branch operands stay plain `CodeOffset`s so that `Bytecode` keeps its layout and wire format.
+/// Code that computes offsets from positions in a `Vec<Bytecode>`, or from other offsets, goes
+/// through `CheckedCodeOffset` instead: a code stream longer than a branch can address is
+/// reported rather than turned into a branch to the wrong instruction.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CheckedCodeOffset(CodeOffset);
+
+impl CheckedCodeOffset {
+    /// Returns the offset carried by a branch operand.
+    pub fn new(offset: CodeOffset) -> Self {
+        CheckedCodeOffset(offset)
+    }
+
+    /// Returns the offset of the instruction at `idx` in a code stream, or `None` if a branch
+    /// can't address it.
+    pub fn from_index(idx: usize) -> Option<Self> {
+        CodeOffset::try_from(idx).ok().map(CheckedCodeOffset)
+    }
+
+    /// Returns the offset `count` instructions after this one, or `None` on overflow.
+    pub fn checked_add(self, count: CodeOffset) -> Option<Self> {
+        self.0.checked_add(count).map(CheckedCodeOffset)
+    }
+
+    /// Returns the offset `count` instructions before this one, or `None` on underflow.
+    pub fn checked_sub(self, count: CodeOffset) -> Option<Self> {
+        self.0.checked_sub(count).map(CheckedCodeOffset)
+    }
+
+    /// Returns the offset of the next instruction, or `None` on overflow.
+    pub fn next(self) -> Option<Self> {
+        self.checked_add(1)
+    }
+
+    /// Returns the offset as a branch operand.
+    pub fn into_operand(self) -> CodeOffset {
+        self.0
+    }
+
+    /// Returns the offset as a position in a code stream.
+    pub fn into_index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl From<CodeOffset> for CheckedCodeOffset {
+    fn from(offset: CodeOffset) -> Self {
+        Self::new(offset)
+    }
+}
+
+impl From<CheckedCodeOffset> for CodeOffset {
+    fn from(offset: CheckedCodeOffset) -> Self {
+        offset.into_operand()
+    }
+}
+
+impl TryFrom<usize> for CheckedCodeOffset {
+    type Error = ::std::num::TryFromIntError;
+
+    fn try_from(idx: usize) -> Result<Self, Self::Error> {
+        CodeOffset::try_from(idx).map(CheckedCodeOffset)
+    }
+}
+
+impl ::std::fmt::Display for CheckedCodeOffset {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "offset {}", self.0)
+    }
+}
+
 /// The pool of identifiers and string literals.
-pub type StringPool = Vec<String>;
+pub type StringPool = Vec<Identifier>;
 /// The pool of `ByteArray` literals.
 pub type ByteArrayPool = Vec<ByteArray>;
 /// The pool of `AccountAddress` literals.
@@ -369,6 +469,14 @@ impl FunctionDefinition {
 #[cfg_attr(any(test, feature = "testing"), proptest(no_params))]
 pub struct TypeSignature(pub SignatureToken);
 
+/// The number of tokens a `SignatureTokens` holds without allocating. Most functions have at
+/// most this many arguments, return values and locals.
+pub const INLINE_SIGNATURE_TOKENS: usize = 4;
+
+/// The tokens of a function or locals signature. Signatures are small, so their tokens are kept
+/// inline up to `INLINE_SIGNATURE_TOKENS` of them, and only longer signatures allocate.
+pub type SignatureTokens = SmallVec<[SignatureToken; INLINE_SIGNATURE_TOKENS]>;
+
 /// A `FunctionSignature` describes the types of a function.
 ///
 /// The `FunctionSignature` is polymorphic: it can have type parameters in the argument and return
@@ -381,15 +489,15 @@ pub struct FunctionSignature {
     /// The list of return types.
     #[cfg_attr(
         any(test, feature = "testing"),
-        proptest(strategy = "vec(any::<SignatureToken>(), 0..=params)")
+        proptest(strategy = "vec(any::<SignatureToken>(), 0..=params).prop_map_into()")
     )]
-    pub return_types: Vec<SignatureToken>,
+    pub return_types: SignatureTokens,
     /// The list of arguments to the function.
     #[cfg_attr(
         any(test, feature = "testing"),
-        proptest(strategy = "vec(any::<SignatureToken>(), 0..=params)")
+        proptest(strategy = "vec(any::<SignatureToken>(), 0..=params).prop_map_into()")
     )]
-    pub arg_types: Vec<SignatureToken>,
+    pub arg_types: SignatureTokens,
     /// The type formals (identified by their index into the vec) and their kind constraints
     pub type_formals: Vec<Kind>,
 }
@@ -404,9 +512,9 @@ pub struct FunctionSignature {
 pub struct LocalsSignature(
     #[cfg_attr(
         any(test, feature = "testing"),
-        proptest(strategy = "vec(any::<SignatureToken>(), 0..=params)")
+        proptest(strategy = "vec(any::<SignatureToken>(), 0..=params).prop_map_into()")
     )]
-    pub Vec<SignatureToken>,
+    pub SignatureTokens,
 );
 
 impl LocalsSignature {
@@ -468,7 +576,14 @@ impl Kind {
 ///
 /// A SignatureToken can express more types than the VM can handle safely, and correctness is
 /// enforced by the verifier.
-#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+///
+/// Tokens are ordered and hashed structurally, so they can key maps and sets directly. The order
+/// is total and the same across runs: tokens of different variants are ordered as the variants
+/// are declared below (`Bool` first, `TypeParameter` last), and tokens of the same variant are
+/// ordered lexicographically by their contents, struct handle index first for `Struct`. Code
+/// may rely on this order, so new variants must only be added at the end. Hashing only depends
+/// on the structure of a token, so with a deterministic hasher it is the same across runs too.
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum SignatureToken {
     /// Boolean, `true` or `false`.
     Bool,
@@ -1119,25 +1234,25 @@ impl ::std::fmt::Debug for Bytecode {
             Bytecode::BrFalse(a) => write!(f, "BrFalse({})", a),
             Bytecode::Branch(a) => write!(f, "Branch({})", a),
             Bytecode::LdConst(a) => write!(f, "LdConst({})", a),
-            Bytecode::LdStr(a) => write!(f, "LdStr({})", a),
-            Bytecode::LdByteArray(a) => write!(f, "LdByteArray({})", a),
-            Bytecode::LdAddr(a) => write!(f, "LdAddr({})", a),
+            Bytecode::LdStr(a) => write!(f, "LdStr({})", a.0),
+            Bytecode::LdByteArray(a) => write!(f, "LdByteArray({})", a.0),
+            Bytecode::LdAddr(a) => write!(f, "LdAddr({})", a.0),
             Bytecode::LdTrue => write!(f, "LdTrue"),
             Bytecode::LdFalse => write!(f, "LdFalse"),
             Bytecode::CopyLoc(a) => write!(f, "CopyLoc({})", a),
             Bytecode::MoveLoc(a) => write!(f, "MoveLoc({})", a),
             Bytecode::StLoc(a) => write!(f, "StLoc({})", a),
-            Bytecode::Call(a, b) => write!(f, "Call({}, {:?})", a, b),
-            Bytecode::Pack(a, b) => write!(f, "Pack({}, {:?})", a, b),
-            Bytecode::Unpack(a, b) => write!(f, "Unpack({}, {:?})", a, b),
+            Bytecode::Call(a, b) => write!(f, "Call({}, {:?})", a.0, b),
+            Bytecode::Pack(a, b) => write!(f, "Pack({}, {:?})", a.0, b),
+            Bytecode::Unpack(a, b) => write!(f, "Unpack({}, {:?})", a.0, b),
             Bytecode::ReadRef => write!(f, "ReadRef"),
             Bytecode::WriteRef => write!(f, "WriteRef"),
             Bytecode::FreezeRef => write!(f, "FreezeRef"),
             Bytecode::MutBorrowLoc(a) => write!(f, "MutBorrowLoc({})", a),
             Bytecode::ImmBorrowLoc(a) => write!(f, "ImmBorrowLoc({})", a),
-            Bytecode::MutBorrowField(a) => write!(f, "MutBorrowField({})", a),
-            Bytecode::ImmBorrowField(a) => write!(f, "ImmBorrowField({})", a),
-            Bytecode::BorrowGlobal(a, b) => write!(f, "BorrowGlobal({}, {:?})", a, b),
+            Bytecode::MutBorrowField(a) => write!(f, "MutBorrowField({})", a.0),
+            Bytecode::ImmBorrowField(a) => write!(f, "ImmBorrowField({})", a.0),
+            Bytecode::BorrowGlobal(a, b) => write!(f, "BorrowGlobal({}, {:?})", a.0, b),
             Bytecode::Add => write!(f, "Add"),
             Bytecode::Sub => write!(f, "Sub"),
             Bytecode::Mul => write!(f, "Mul"),
@@ -1160,9 +1275,9 @@ impl ::std::fmt::Debug for Bytecode {
             Bytecode::GetTxnMaxGasUnits => write!(f, "GetTxnMaxGasUnits"),
             Bytecode::GetGasRemaining => write!(f, "GetGasRemaining"),
             Bytecode::GetTxnSenderAddress => write!(f, "GetTxnSenderAddress"),
-            Bytecode::Exists(a, b) => write!(f, "Exists({}, {:?})", a, b),
-            Bytecode::MoveFrom(a, b) => write!(f, "MoveFrom({}, {:?})", a, b),
-            Bytecode::MoveToSender(a, b) => write!(f, "MoveToSender({}, {:?})", a, b),
+            Bytecode::Exists(a, b) => write!(f, "Exists({}, {:?})", a.0, b),
+            Bytecode::MoveFrom(a, b) => write!(f, "MoveFrom({}, {:?})", a.0, b),
+            Bytecode::MoveToSender(a, b) => write!(f, "MoveToSender({}, {:?})", a.0, b),
             Bytecode::CreateAccount => write!(f, "CreateAccount"),
             Bytecode::GetTxnSequenceNumber => write!(f, "GetTxnSequenceNumber"),
             Bytecode::GetTxnPublicKey => write!(f, "GetTxnPublicKey"),
@@ -1236,6 +1351,66 @@ impl Bytecode {
 
         v
     }
+
+    /// Returns how many values this instruction pops from the operand stack and pushes onto it.
+    ///
+    /// The effect of `Call`, `Pack`, `Unpack` and `Ret` depends on a signature: the called
+    /// function's, the struct's fields, or the returning function's. Every other instruction has a
+    /// fixed effect, so a sequence of them can be checked without looking anything up.
+    pub fn stack_effect(&self) -> StackEffect {
+        use Bytecode::*;
+
+        let (pops, pushes) = match self {
+            Call(_, _) | Pack(_, _) | Unpack(_, _) | Ret => return StackEffect::SignatureDependent,
+
+            Branch(_) => (0, 0),
+            Pop
+            | BrTrue(_)
+            | BrFalse(_)
+            | StLoc(_)
+            | Abort
+            | MoveToSender(_, _)
+            | CreateAccount => (1, 0),
+            WriteRef => (2, 0),
+
+            LdConst(_) | LdStr(_) | LdByteArray(_) | LdAddr(_) | LdTrue | LdFalse | CopyLoc(_)
+            | MoveLoc(_) | MutBorrowLoc(_) | ImmBorrowLoc(_) | GetTxnGasUnitPrice
+            | GetTxnMaxGasUnits | GetGasRemaining | GetTxnSenderAddress | GetTxnSequenceNumber
+            | GetTxnPublicKey => (0, 1),
+
+            MutBorrowField(_)
+            | ImmBorrowField(_)
+            | ReadRef
+            | FreezeRef
+            | Not
+            | Exists(_, _)
+            | BorrowGlobal(_, _)
+            | MoveFrom(_, _) => (1, 1),
+
+            Add | Sub | Mul | Mod | Div | BitOr | BitAnd | Xor | Or | And | Eq | Neq | Lt | Gt
+            | Le | Ge => (2, 1),
+        };
+        StackEffect::Fixed { pops, pushes }
+    }
+}
+
+/// How an instruction changes the operand stack. See `Bytecode::stack_effect`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StackEffect {
+    /// The instruction pops `pops` values, then pushes `pushes` values.
+    Fixed { pops: usize, pushes: usize },
+    /// The number of values popped and pushed depends on a signature.
+    SignatureDependent,
+}
+
+impl StackEffect {
+    /// Returns by how much the instruction changes the height of the stack, if it is fixed.
+    pub fn net(self) -> Option<isize> {
+        match self {
+            StackEffect::Fixed { pops, pushes } => Some(pushes as isize - pops as isize),
+            StackEffect::SignatureDependent => None,
+        }
+    }
 }
 
 /// A `CompiledProgram` defines the structure of a transaction to execute.
@@ -1358,14 +1533,14 @@ impl CompiledScript {
     /// If a `CompiledScript` has been bounds checked, the corresponding `CompiledModule` can be
     /// assumed to pass the bounds checker as well.
     pub fn into_module(self) -> CompiledModule {
-        CompiledModule(self.0.into_module())
+        CompiledModule(Arc::new(self.0.into_module()))
     }
 }
 
 impl CompiledScriptMut {
     /// Converts this instance into `CompiledScript` after verifying it for basic internal
     /// consistency. This includes bounds checks but no others.
-    pub fn freeze(self) -> Result<CompiledScript, Vec<VerificationError>> {
+    pub fn freeze(self) -> Result<CompiledScript, FreezeError> {
         let fake_module = self.into_module();
         Ok(fake_module.freeze()?.into_script())
     }
@@ -1399,8 +1574,11 @@ impl CompiledScriptMut {
 /// It is a unit of code that can be used by transactions or other modules.
 ///
 /// A module is published as a single entry and it is retrieved as a single blob.
+///
+/// A `CompiledModule` is immutable, so its tables are shared between clones: cloning a module is
+/// cheap, and loaded modules can be handed to other threads without copying them.
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct CompiledModule(CompiledModuleMut);
+pub struct CompiledModule(Arc<CompiledModuleMut>);
 
 /// A mutable version of `CompiledModule`. Converting to a `CompiledModule` requires this to pass
 /// the bounds checker.
@@ -1459,7 +1637,7 @@ impl Arbitrary for CompiledModuleMut {
                 vec(any_with::<LocalsSignature>(size), 0..=size),
             ),
             (
-                vec(any::<String>(), 0..=size),
+                vec(any::<String>().prop_map_into(), 0..=size),
                 vec(any::<ByteArray>(), 0..=size),
                 vec(any::<AccountAddress>(), 0..=size),
             ),
@@ -1512,7 +1690,7 @@ impl CompiledModuleMut {
             IndexKind::StringPool => self.string_pool.len(),
             IndexKind::ByteArrayPool => self.byte_array_pool.len(),
             IndexKind::AddressPool => self.address_pool.len(),
-            // XXX these two don't seem to belong here
+            // These are local to a function; see `FunctionDefinitionView::kind_count`.
             other @ IndexKind::LocalPool
             | other @ IndexKind::CodeDefinition
             | other @ IndexKind::TypeParameter => panic!("invalid kind for count: {:?}", other),
@@ -1521,12 +1699,39 @@ impl CompiledModuleMut {
 
     /// Converts this instance into `CompiledModule` after verifying it for basic internal
     /// consistency. This includes bounds checks but no others.
-    pub fn freeze(self) -> Result<CompiledModule, Vec<VerificationError>> {
+    ///
+    /// On failure, the `FreezeError` lists every entry that breaks an invariant, with the table
+    /// and field it is in; see `FreezeError::diagnostics`.
+    pub fn freeze(self) -> Result<CompiledModule, FreezeError> {
         let errors = BoundsChecker::new(&self).verify();
         if errors.is_empty() {
-            Ok(CompiledModule(self))
+            Ok(CompiledModule(Arc::new(self)))
         } else {
-            Err(errors)
+            Err(FreezeError::new(errors))
+        }
+    }
+
+    /// Converts a `CompiledModuleMut` obtained through `CompiledScriptMut::into_module` back into
+    /// a `CompiledScriptMut`, with the first function definition as `main`.
+    ///
+    /// Struct, field and other function definitions can't be represented in a script and are
+    /// dropped.
+    pub fn into_script(mut self) -> CompiledScriptMut {
+        let main = self.function_defs.remove(0);
+        CompiledScriptMut {
+            module_handles: self.module_handles,
+            struct_handles: self.struct_handles,
+            function_handles: self.function_handles,
+
+            type_signatures: self.type_signatures,
+            function_signatures: self.function_signatures,
+            locals_signatures: self.locals_signatures,
+
+            string_pool: self.string_pool,
+            byte_array_pool: self.byte_array_pool,
+            address_pool: self.address_pool,
+
+            main,
         }
     }
 }
@@ -1540,10 +1745,11 @@ impl CompiledModule {
         &self.0
     }
 
-    /// Converts this instance into the inner `CompiledModuleMut`. Converting back to a
-    /// `CompiledModule` would require it to be verified again.
+    /// Converts this instance into the inner `CompiledModuleMut`, copying it if it is shared with
+    /// clones of this instance. Converting back to a `CompiledModule` would require it to be
+    /// verified again.
     pub fn into_inner(self) -> CompiledModuleMut {
-        self.0
+        Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone())
     }
 
     /// Returns the number of items of a specific `IndexKind`.
@@ -1568,23 +1774,7 @@ impl CompiledModule {
     /// into_module on some instance of CompiledScript. This function is the inverse of
     /// into_module, i.e., script.into_module().into_script() == script.
     pub fn into_script(self) -> CompiledScript {
-        let mut inner = self.into_inner();
-        let main = inner.function_defs.remove(0);
-        CompiledScript(CompiledScriptMut {
-            module_handles: inner.module_handles,
-            struct_handles: inner.struct_handles,
-            function_handles: inner.function_handles,
-
-            type_signatures: inner.type_signatures,
-            function_signatures: inner.function_signatures,
-            locals_signatures: inner.locals_signatures,
-
-            string_pool: inner.string_pool,
-            byte_array_pool: inner.byte_array_pool,
-            address_pool: inner.address_pool,
-
-            main,
-        })
+        CompiledScript(self.into_inner().into_script())
     }
 }
 
@@ -1596,7 +1786,7 @@ pub fn empty_module() -> CompiledModuleMut {
             name: StringPoolIndex::new(0),
         }],
         address_pool: vec![AccountAddress::default()],
-        string_pool: vec![SELF_MODULE_NAME.to_string()],
+        string_pool: vec![SELF_MODULE_NAME.into()],
         function_defs: vec![],
         struct_defs: vec![],
         field_defs: vec![],
@@ -1604,7 +1794,7 @@ pub fn empty_module() -> CompiledModuleMut {
         function_handles: vec![],
         type_signatures: vec![],
         function_signatures: vec![],
-        locals_signatures: vec![LocalsSignature(vec![])],
+        locals_signatures: vec![LocalsSignature::default()],
         byte_array_pool: vec![],
     }
 }
@@ -1618,8 +1808,8 @@ pub fn dummy_procedure_module(code: Vec<Bytecode>) -> CompiledModule {
     fun_def.code = code_unit;
 
     module.function_signatures.push(FunctionSignature {
-        arg_types: vec![],
-        return_types: vec![],
+        arg_types: SignatureTokens::new(),
+        return_types: SignatureTokens::new(),
         type_formals: vec![],
     });
     let fun_handle = FunctionHandle {
@@ -14,6 +14,7 @@ use crate::{
     serializer::serialize_instruction,
 };
 use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashMap,
     ops::{Add, Div, Mul, Sub},
@@ -105,7 +106,7 @@ macro_rules! define_gas_unit {
         carrier: $carrier: ty,
         doc: $comment: literal
     } => {
-        #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
+        #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
         #[doc=$comment]
         pub struct $name<GasCarrier>(GasCarrier);
         impl GasAlgebra<$carrier> for $name<$carrier> {
@@ -259,7 +260,8 @@ impl CostTable {
 }
 
 lazy_static! {
-    static ref GAS_SCHEDULE: CostTable = {
+    /// The cost table that instructions are currently metered with.
+    pub static ref GAS_SCHEDULE: CostTable = {
         use Bytecode::*;
         // Arguments to the instructions don't matter -- these will be removed in the
         // `encode_instruction` function.
@@ -327,6 +329,222 @@ lazy_static! {
     };
 }
 
+/// The gas parameters of a single transaction, together with the cost table its instructions are
+/// metered with. This is everything an execution entry point needs to set up gas metering and to
+/// compute the fee charged at the end of the transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct GasContext<'a> {
+    cost_table: &'a CostTable,
+    max_gas_amount: GasUnits<GasCarrier>,
+    gas_unit_price: GasPrice<GasCarrier>,
+    transaction_size: AbstractMemorySize<GasCarrier>,
+}
+
+impl<'a> GasContext<'a> {
+    pub fn new(
+        cost_table: &'a CostTable,
+        max_gas_amount: GasUnits<GasCarrier>,
+        gas_unit_price: GasPrice<GasCarrier>,
+        transaction_size: AbstractMemorySize<GasCarrier>,
+    ) -> Self {
+        Self {
+            cost_table,
+            max_gas_amount,
+            gas_unit_price,
+            transaction_size,
+        }
+    }
+
+    pub fn cost_table(&self) -> &'a CostTable {
+        self.cost_table
+    }
+
+    /// The amount of gas the transaction starts executing with.
+    pub fn max_gas_amount(&self) -> GasUnits<GasCarrier> {
+        self.max_gas_amount
+    }
+
+    pub fn gas_unit_price(&self) -> GasPrice<GasCarrier> {
+        self.gas_unit_price
+    }
+
+    pub fn transaction_size(&self) -> AbstractMemorySize<GasCarrier> {
+        self.transaction_size
+    }
+
+    /// The gas charged up front for the size of the transaction.
+    pub fn intrinsic_gas(&self) -> GasUnits<GasCarrier> {
+        calculate_intrinsic_gas(self.transaction_size)
+    }
+
+    /// The fee for the gas consumed by the transaction, given the gas it has left.
+    pub fn gas_fee(&self, remaining_gas: GasUnits<GasCarrier>) -> GasUnits<GasCarrier> {
+        self.max_gas_amount
+            .sub(remaining_gas)
+            .mul(self.gas_unit_price)
+    }
+}
+
+/// A charge made against a gas meter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GasCharge {
+    /// The instruction charged for, or `None` for other charges, such as the intrinsic gas of a
+    /// transaction or the cost of a native function.
+    pub instruction: Option<InstructionKey>,
+    /// The size the charge was computed for.
+    pub size: AbstractMemorySize<GasCarrier>,
+    pub amount: GasUnits<GasCarrier>,
+}
+
+impl GasCharge {
+    /// A charge of `amount` for executing `instruction` over `size`.
+    pub fn for_instruction(
+        instruction: &Bytecode,
+        size: AbstractMemorySize<GasCarrier>,
+        amount: GasUnits<GasCarrier>,
+    ) -> Self {
+        Self {
+            instruction: Some(InstructionKey::new(instruction)),
+            size,
+            amount,
+        }
+    }
+
+    /// A charge of `amount` that isn't for an instruction.
+    pub fn other(size: AbstractMemorySize<GasCarrier>, amount: GasUnits<GasCarrier>) -> Self {
+        Self {
+            instruction: None,
+            size,
+            amount,
+        }
+    }
+}
+
+/// The interface gas is charged through while executing a transaction.
+pub trait GasMetering {
+    /// Deducts `charge` from the gas left. Returns false, leaving no gas, if there isn't enough
+    /// gas left for it.
+    fn charge(&mut self, charge: GasCharge) -> bool;
+
+    /// Returns the amount of gas left.
+    fn remaining_gas(&self) -> GasUnits<GasCarrier>;
+}
+
+impl<M: GasMetering + ?Sized> GasMetering for &mut M {
+    fn charge(&mut self, charge: GasCharge) -> bool {
+        (**self).charge(charge)
+    }
+
+    fn remaining_gas(&self) -> GasUnits<GasCarrier> {
+        (**self).remaining_gas()
+    }
+}
+
+/// A gas meter with a fixed amount of gas, the way the VM meters transactions.
+#[derive(Clone, Copy, Debug)]
+pub struct BoundedGasMeter {
+    gas_left: GasUnits<GasCarrier>,
+}
+
+impl BoundedGasMeter {
+    /// Returns a meter holding `gas_amount` units of gas.
+    pub fn new(gas_amount: GasUnits<GasCarrier>) -> Self {
+        Self {
+            gas_left: gas_amount,
+        }
+    }
+}
+
+impl GasMetering for BoundedGasMeter {
+    fn charge(&mut self, charge: GasCharge) -> bool {
+        if self
+            .gas_left
+            .app(&charge.amount, |gas_left, amount| gas_left >= amount)
+        {
+            self.gas_left = self.gas_left.sub(charge.amount);
+            true
+        } else {
+            self.gas_left = GasUnits::new(0);
+            false
+        }
+    }
+
+    fn remaining_gas(&self) -> GasUnits<GasCarrier> {
+        self.gas_left
+    }
+}
+
+/// A gas meter that accepts every charge, for simulations and dry runs.
+///
+/// It always reports `MAXIMUM_NUMBER_OF_GAS_UNITS` as the gas left.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UnmeteredGasMeter;
+
+impl GasMetering for UnmeteredGasMeter {
+    fn charge(&mut self, _charge: GasCharge) -> bool {
+        true
+    }
+
+    fn remaining_gas(&self) -> GasUnits<GasCarrier> {
+        *MAXIMUM_NUMBER_OF_GAS_UNITS
+    }
+}
+
+/// A gas meter that records every charge before passing it on to another meter, for debuggers
+/// and gas profiling.
+#[derive(Clone, Debug)]
+pub struct RecordingGasMeter<M = UnmeteredGasMeter> {
+    meter: M,
+    charges: Vec<GasCharge>,
+    total_charged: GasUnits<GasCarrier>,
+}
+
+impl<M: GasMetering> RecordingGasMeter<M> {
+    /// Returns a meter that records charges and passes them on to `meter`.
+    pub fn new(meter: M) -> Self {
+        Self {
+            meter,
+            charges: vec![],
+            total_charged: GasUnits::new(0),
+        }
+    }
+
+    /// Returns the charges made so far, in order, including any the underlying meter refused.
+    pub fn charges(&self) -> &[GasCharge] {
+        &self.charges
+    }
+
+    /// Returns the sum of the charges the underlying meter accepted so far.
+    pub fn total_charged(&self) -> GasUnits<GasCarrier> {
+        self.total_charged
+    }
+
+    pub fn into_inner(self) -> M {
+        self.meter
+    }
+}
+
+impl<M: GasMetering + Default> Default for RecordingGasMeter<M> {
+    fn default() -> Self {
+        Self::new(M::default())
+    }
+}
+
+impl<M: GasMetering> GasMetering for RecordingGasMeter<M> {
+    fn charge(&mut self, charge: GasCharge) -> bool {
+        self.charges.push(charge);
+        let accepted = self.meter.charge(charge);
+        if accepted {
+            self.total_charged = self.total_charged.add(charge.amount);
+        }
+        accepted
+    }
+
+    fn remaining_gas(&self) -> GasUnits<GasCarrier> {
+        self.meter.remaining_gas()
+    }
+}
+
 /// The  `GasCost` tracks:
 /// - instruction cost: how much time/computational power is needed to perform the instruction
 /// - memory cost: how much memory is required for the instruction, and storage overhead
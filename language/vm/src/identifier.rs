@@ -0,0 +1,183 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines `Identifier`, the interned names held in the `StringPool` of compiled units.
+//!
+//! Names of modules, structs, fields and functions are compared over and over again while
+//! resolving and linking modules. Interning them makes those comparisons (and hashing) constant
+//! time: two identifiers are equal if and only if they point to the same interned name.
+
+use lazy_static::lazy_static;
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, HashSet},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+/// The number of independently locked shards the interner is split into, so that threads
+/// interning or releasing different names rarely contend for the same lock.
+const SHARD_COUNT: usize = 64;
+
+type Shard = Mutex<HashSet<Arc<str>>>;
+
+lazy_static! {
+    /// The names currently held by an `Identifier`, each shared by every identifier for it. A name
+    /// is always kept in the shard picked by `shard`.
+    static ref INTERNER: Vec<Shard> = (0..SHARD_COUNT)
+        .map(|_| Mutex::new(HashSet::new()))
+        .collect();
+}
+
+/// Returns the shard of the interner that holds `name`, if it is interned.
+fn shard(name: &str) -> &'static Shard {
+    let mut hasher = DefaultHasher::new();
+    name.hash(&mut hasher);
+    &INTERNER[(hasher.finish() as usize) % SHARD_COUNT]
+}
+
+/// An interned name. Cloning, comparing and hashing identifiers doesn't look at the name itself.
+///
+/// Identifiers are ordered by name, so that ordered collections of identifiers stay deterministic.
+pub struct Identifier(Arc<str>);
+
+impl Identifier {
+    /// Returns the identifier for `name`, interning `name` if no identifier holds it yet.
+    pub fn new(name: &str) -> Self {
+        let mut interner = shard(name).lock().expect("poisoned lock");
+        if let Some(interned) = interner.get(name) {
+            return Identifier(Arc::clone(interned));
+        }
+        let interned: Arc<str> = Arc::from(name);
+        interner.insert(Arc::clone(&interned));
+        Identifier(interned)
+    }
+
+    /// Returns the identifier for `name` if one exists already, without interning `name`.
+    pub fn find(name: &str) -> Option<Self> {
+        let interner = shard(name).lock().expect("poisoned lock");
+        interner
+            .get(name)
+            .map(|interned| Identifier(Arc::clone(interned)))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for Identifier {
+    fn drop(&mut self) {
+        // The interner holds the other reference: release the name along with the last identifier.
+        // Two identifiers dropped concurrently may both miss this, which only keeps the name
+        // interned until it is interned and released again.
+        if Arc::strong_count(&self.0) == 2 {
+            let mut interner = shard(&self.0).lock().expect("poisoned lock");
+            // Only the interner could hand out another reference to the name, and its shard is
+            // locked.
+            if Arc::strong_count(&self.0) == 2 {
+                interner.remove(&*self.0);
+            }
+        }
+    }
+}
+
+impl Clone for Identifier {
+    fn clone(&self) -> Self {
+        Identifier(Arc::clone(&self.0))
+    }
+}
+
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Identifier {}
+
+impl Hash for Identifier {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state)
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Identifier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.as_str().cmp(other.as_str())
+        }
+    }
+}
+
+impl PartialEq<str> for Identifier {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl<'a> PartialEq<&'a str> for Identifier {
+    fn eq(&self, other: &&'a str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Identifier {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Deref for Identifier {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Identifier {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<'a> From<&'a str> for Identifier {
+    fn from(name: &'a str) -> Self {
+        Identifier::new(name)
+    }
+}
+
+impl From<String> for Identifier {
+    fn from(name: String) -> Self {
+        Identifier::new(&name)
+    }
+}
+
+impl From<Identifier> for String {
+    fn from(identifier: Identifier) -> Self {
+        identifier.as_str().to_string()
+    }
+}
+
+impl fmt::Debug for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -7,33 +7,83 @@ extern crate mirai_annotations;
 #[cfg(feature = "mirai-contracts")]
 pub mod foreign_contracts;
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 pub mod access;
+pub mod builder;
 pub mod check_bounds;
+pub mod coverage;
 #[macro_use]
 pub mod errors;
 pub mod deserializer;
+pub mod diff;
 pub mod file_format;
 pub mod file_format_common;
 pub mod gas_schedule;
+pub mod identifier;
 pub mod internals;
+pub mod memory_usage;
+pub mod module_cache;
+pub mod module_registry;
+pub mod normalize;
+pub mod package;
+#[cfg(feature = "parallel")]
+pub mod parallel;
 pub mod printers;
 #[cfg(any(test, feature = "testing"))]
+pub mod reference_interpreter;
+#[cfg(any(test, feature = "testing"))]
 pub mod proptest_types;
+pub mod query;
+pub mod raw_code;
 pub mod resolver;
+pub mod sarif;
+pub mod script_allowlist;
+pub mod script_arguments;
 pub mod serializer;
+pub mod signed_module;
+pub mod source_map;
+#[cfg(any(test, feature = "symbolic-execution"))]
+pub mod symbolic_execution;
+#[cfg(any(test, feature = "testing"))]
+pub mod test_helpers;
 pub mod transaction_metadata;
 pub mod views;
 
 #[cfg(test)]
 mod unit_tests;
 
+pub use deserializer::Deserializer;
 pub use file_format::CompiledModule;
+pub use identifier::Identifier;
+pub use memory_usage::MemoryUsage;
+pub use module_cache::ModuleCache;
+pub use module_registry::ModuleRegistry;
+pub use types::language_storage::ModuleId;
+
+/// Defines `IndexKind` along with `IndexKind::variants`, so that the list of variants can't get
+/// out of date.
+macro_rules! define_index_kind {
+    ($($variant: ident,)*) => {
+        /// Represents a kind of index -- useful for error messages.
+        #[derive(
+            Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize,
+        )]
+        pub enum IndexKind {
+            $($variant,)*
+        }
+
+        impl IndexKind {
+            /// Returns every kind of index, in the order they are declared.
+            pub fn variants() -> &'static [IndexKind] {
+                &[$(IndexKind::$variant,)*]
+            }
+        }
+    };
+}
 
-/// Represents a kind of index -- useful for error messages.
-#[derive(Copy, Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
-pub enum IndexKind {
+define_index_kind! {
     ModuleHandle,
     StructHandle,
     FunctionHandle,
@@ -51,30 +101,6 @@ pub enum IndexKind {
     TypeParameter,
 }
 
-impl IndexKind {
-    pub fn variants() -> &'static [IndexKind] {
-        use IndexKind::*;
-
-        // XXX ensure this list stays up to date!
-        &[
-            ModuleHandle,
-            StructHandle,
-            FunctionHandle,
-            StructDefinition,
-            FieldDefinition,
-            FunctionDefinition,
-            TypeSignature,
-            FunctionSignature,
-            LocalsSignature,
-            StringPool,
-            AddressPool,
-            LocalPool,
-            CodeDefinition,
-            TypeParameter,
-        ]
-    }
-}
-
 impl fmt::Display for IndexKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use IndexKind::*;
@@ -103,7 +129,7 @@ impl fmt::Display for IndexKind {
 
 // TODO: is this outdated?
 /// Represents the kind of a signature token.
-#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub enum SignatureTokenKind {
     /// Any sort of owned value that isn't an array (Integer, Bool, Struct etc).
     Value,
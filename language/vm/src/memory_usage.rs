@@ -0,0 +1,158 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Accounting for the heap memory held by compiled modules.
+//!
+//! The size of a module binary is a poor predictor of the memory it takes once loaded: a few
+//! bytes of signature or code expand into much larger Rust values. `MemoryUsage` reports the heap
+//! bytes behind each kind of table so that module caches can be sized by memory rather than by
+//! count, and so that modules engineered to be small on chain but large in memory can be spotted.
+
+use crate::file_format::{
+    CompiledModule, CompiledModuleMut, FunctionSignature, LocalsSignature, SignatureToken,
+    SignatureTokens, TypeSignature,
+};
+use std::mem::size_of;
+
+/// The heap bytes held by the tables of a module, grouped by kind of table.
+///
+/// Strings and byte arrays may be shared with other modules, so the numbers are an upper bound
+/// on what dropping a module would free.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MemoryUsage {
+    /// The string pool, including the text of every string.
+    pub strings: usize,
+    /// The byte array pool, including the contents of every byte array.
+    pub byte_arrays: usize,
+    /// The function definitions, including their code.
+    pub code: usize,
+    /// The type, function and locals signature pools.
+    pub signatures: usize,
+    /// Every other table: handles, addresses, and struct and field definitions.
+    pub other: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the heap bytes held by all the tables.
+    pub fn total(&self) -> usize {
+        self.strings + self.byte_arrays + self.code + self.signatures + self.other
+    }
+}
+
+impl CompiledModuleMut {
+    /// Returns the heap bytes held by the tables of this module.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        // An `Arc<str>` keeps its strong and weak counts next to the text.
+        let strings = vec_bytes(&self.string_pool)
+            + self
+                .string_pool
+                .iter()
+                .map(|string| 2 * size_of::<usize>() + string.len())
+                .sum::<usize>();
+        let byte_arrays = vec_bytes(&self.byte_array_pool)
+            + self
+                .byte_array_pool
+                .iter()
+                .map(|byte_array| byte_array.len())
+                .sum::<usize>();
+        let code = vec_bytes(&self.function_defs)
+            + self
+                .function_defs
+                .iter()
+                .map(|function_def| {
+                    vec_bytes(&function_def.acquires_global_resources)
+                        + vec_bytes(&function_def.code.code)
+                })
+                .sum::<usize>();
+        let signatures = vec_bytes(&self.type_signatures)
+            + self
+                .type_signatures
+                .iter()
+                .map(|TypeSignature(token)| token_bytes(token))
+                .sum::<usize>()
+            + vec_bytes(&self.function_signatures)
+            + self
+                .function_signatures
+                .iter()
+                .map(
+                    |FunctionSignature {
+                         return_types,
+                         arg_types,
+                         type_formals,
+                     }| {
+                        tokens_bytes(return_types)
+                            + tokens_bytes(arg_types)
+                            + vec_bytes(type_formals)
+                    },
+                )
+                .sum::<usize>()
+            + vec_bytes(&self.locals_signatures)
+            + self
+                .locals_signatures
+                .iter()
+                .map(|LocalsSignature(tokens)| tokens_bytes(tokens))
+                .sum::<usize>();
+        let other = vec_bytes(&self.module_handles)
+            + vec_bytes(&self.struct_handles)
+            + self
+                .struct_handles
+                .iter()
+                .map(|handle| vec_bytes(&handle.type_formals))
+                .sum::<usize>()
+            + vec_bytes(&self.function_handles)
+            + vec_bytes(&self.address_pool)
+            + vec_bytes(&self.struct_defs)
+            + vec_bytes(&self.field_defs);
+        MemoryUsage {
+            strings,
+            byte_arrays,
+            code,
+            signatures,
+            other,
+        }
+    }
+}
+
+impl CompiledModule {
+    /// Returns the heap bytes held by the tables of this module.
+    ///
+    /// The tables are shared between clones, so clones of a module don't add to its usage.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.as_inner().memory_usage()
+    }
+}
+
+/// Returns the bytes allocated by `vec` itself, not counting what its elements point to.
+// The capacity of a `Vec` isn't visible through a slice.
+#[allow(clippy::ptr_arg)]
+fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * size_of::<T>()
+}
+
+/// Returns the bytes allocated for `tokens`, which are stored inline while they are few enough.
+fn tokens_bytes(tokens: &SignatureTokens) -> usize {
+    let spilled = if tokens.spilled() {
+        tokens.capacity() * size_of::<SignatureToken>()
+    } else {
+        0
+    };
+    spilled + tokens.iter().map(token_bytes).sum::<usize>()
+}
+
+/// Returns the heap bytes held by `token`.
+fn token_bytes(token: &SignatureToken) -> usize {
+    match token {
+        SignatureToken::Bool
+        | SignatureToken::U64
+        | SignatureToken::String
+        | SignatureToken::ByteArray
+        | SignatureToken::Address
+        | SignatureToken::TypeParameter(_) => 0,
+        SignatureToken::Struct(_, actuals) => {
+            vec_bytes(actuals) + actuals.iter().map(token_bytes).sum::<usize>()
+        }
+        SignatureToken::Reference(inner) | SignatureToken::MutableReference(inner) => {
+            size_of::<SignatureToken>() + token_bytes(inner)
+        }
+    }
+}
@@ -0,0 +1,111 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines a cache of deserialized modules, so that modules read over and over again from storage
+//! (like the standard library during block execution) are only deserialized once.
+//!
+//! Modules are keyed by the hash of their binary, and the least recently used one is evicted first
+//! when the cache is full. Binaries that fail to deserialize are not cached.
+
+use crate::{
+    errors::{BinaryError, BinaryLoaderResult},
+    file_format::{CompiledModule, CompiledModuleMut},
+};
+use crypto::HashValue;
+use lru_cache::LruCache;
+use std::sync::Mutex;
+
+#[derive(Clone, Debug)]
+enum CachedModule {
+    /// The module passed the bounds checker.
+    Checked(CompiledModule),
+    /// The module was only deserialized.
+    Unchecked(CompiledModuleMut),
+}
+
+/// A thread-safe cache of deserialized modules, holding a bounded number of modules.
+pub struct ModuleCache {
+    cache: Mutex<LruCache<HashValue, CachedModule>>,
+}
+
+impl ModuleCache {
+    /// Creates an empty cache holding at most `capacity` modules.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            cache: Mutex::new(LruCache::new(capacity)),
+        }
+    }
+
+    /// Returns the module in `binary`, like `CompiledModule::deserialize`, deserializing and
+    /// bounds checking it only if it isn't cached already.
+    pub fn deserialize(&self, binary: &[u8]) -> BinaryLoaderResult<CompiledModule> {
+        let key = HashValue::from_sha3_256(binary);
+        let unchecked = match self.get(&key) {
+            Some(CachedModule::Checked(module)) => return Ok(module),
+            Some(CachedModule::Unchecked(module)) => module,
+            None => CompiledModuleMut::deserialize_no_check_bounds(binary)?,
+        };
+        let module = unchecked.freeze().map_err(|_| BinaryError::Malformed)?;
+        self.insert(key, CachedModule::Checked(module.clone()));
+        Ok(module)
+    }
+
+    /// Returns the module in `binary`, like `CompiledModuleMut::deserialize_no_check_bounds`,
+    /// deserializing it only if it isn't cached already.
+    pub fn deserialize_no_check_bounds(
+        &self,
+        binary: &[u8],
+    ) -> BinaryLoaderResult<CompiledModuleMut> {
+        let key = HashValue::from_sha3_256(binary);
+        match self.get(&key) {
+            Some(CachedModule::Checked(module)) => Ok(module.into_inner()),
+            Some(CachedModule::Unchecked(module)) => Ok(module),
+            None => {
+                let module = CompiledModuleMut::deserialize_no_check_bounds(binary)?;
+                self.insert(key, CachedModule::Unchecked(module.clone()));
+                Ok(module)
+            }
+        }
+    }
+
+    /// Returns true if the module in `binary` is cached, without marking it as used.
+    pub fn contains(&self, binary: &[u8]) -> bool {
+        let key = HashValue::from_sha3_256(binary);
+        self.cache.lock().expect("poisoned lock").contains_key(&key)
+    }
+
+    /// Returns the number of modules currently cached.
+    pub fn len(&self) -> usize {
+        self.cache.lock().expect("poisoned lock").len()
+    }
+
+    /// Returns true if no modules are cached.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the maximum number of modules the cache holds.
+    pub fn capacity(&self) -> usize {
+        self.cache.lock().expect("poisoned lock").capacity()
+    }
+
+    /// Evicts every module.
+    pub fn clear(&self) {
+        self.cache.lock().expect("poisoned lock").clear();
+    }
+
+    fn get(&self, key: &HashValue) -> Option<CachedModule> {
+        self.cache
+            .lock()
+            .expect("poisoned lock")
+            .get_mut(key)
+            .cloned()
+    }
+
+    fn insert(&self, key: HashValue, module: CachedModule) {
+        self.cache
+            .lock()
+            .expect("poisoned lock")
+            .insert(key, module);
+    }
+}
@@ -0,0 +1,131 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines a registry of modules keyed by their `ModuleId`, for components such as resolvers and
+//! caches that look modules up by ID.
+//!
+//! A module ID can be pinned to the digest of one version of the module (see `module_digest`).
+//! The registry then refuses every other version of the module, so that a component that was
+//! checked against one version never sees another one.
+
+use crate::{access::ModuleAccess, file_format::CompiledModule, package::module_digest};
+use crypto::HashValue;
+use failure::prelude::*;
+use std::collections::BTreeMap;
+use types::language_storage::ModuleId;
+
+/// Why a module was refused by a registry.
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+pub enum RegistryError {
+    #[fail(
+        display = "module {} has digest {}, but is pinned to digest {}",
+        module, actual, pinned
+    )]
+    DigestMismatch {
+        module: ModuleId,
+        pinned: HashValue,
+        actual: HashValue,
+    },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct RegisteredModule {
+    module: CompiledModule,
+    digest: HashValue,
+}
+
+/// Modules keyed by their IDs, in ID order.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ModuleRegistry {
+    modules: BTreeMap<ModuleId, RegisteredModule>,
+    pins: BTreeMap<ModuleId, HashValue>,
+}
+
+impl ModuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `module`, and returns the module it replaces, if any. Fails with
+    /// `RegistryError::DigestMismatch` if the ID of the module is pinned to another digest.
+    pub fn insert(&mut self, module: CompiledModule) -> Result<Option<CompiledModule>> {
+        let id = module.self_id();
+        let digest = module_digest(&module)?;
+        if let Some(pinned) = self.pins.get(&id) {
+            if *pinned != digest {
+                bail_err!(RegistryError::DigestMismatch {
+                    module: id,
+                    pinned: *pinned,
+                    actual: digest,
+                });
+            }
+        }
+        Ok(self
+            .modules
+            .insert(id, RegisteredModule { module, digest })
+            .map(|registered| registered.module))
+    }
+
+    /// Pins `id` to `digest`, replacing any previous pin. Fails with
+    /// `RegistryError::DigestMismatch` if a module with another digest is registered under `id`.
+    pub fn pin(&mut self, id: ModuleId, digest: HashValue) -> Result<()> {
+        if let Some(registered) = self.modules.get(&id) {
+            if registered.digest != digest {
+                bail_err!(RegistryError::DigestMismatch {
+                    module: id,
+                    pinned: digest,
+                    actual: registered.digest,
+                });
+            }
+        }
+        self.pins.insert(id, digest);
+        Ok(())
+    }
+
+    /// Removes the pin of `id`, and returns the digest it was pinned to, if any.
+    pub fn unpin(&mut self, id: &ModuleId) -> Option<HashValue> {
+        self.pins.remove(id)
+    }
+
+    /// Returns the digest `id` is pinned to, if any.
+    pub fn pinned_digest(&self, id: &ModuleId) -> Option<&HashValue> {
+        self.pins.get(id)
+    }
+
+    /// Returns the module registered under `id`.
+    pub fn get(&self, id: &ModuleId) -> Option<&CompiledModule> {
+        self.modules.get(id).map(|registered| &registered.module)
+    }
+
+    /// Returns the digest of the module registered under `id`.
+    pub fn digest(&self, id: &ModuleId) -> Option<&HashValue> {
+        self.modules.get(id).map(|registered| &registered.digest)
+    }
+
+    pub fn contains(&self, id: &ModuleId) -> bool {
+        self.modules.contains_key(id)
+    }
+
+    /// Removes the module registered under `id`, and returns it. A pin of `id` is kept.
+    pub fn remove(&mut self, id: &ModuleId) -> Option<CompiledModule> {
+        self.modules.remove(id).map(|registered| registered.module)
+    }
+
+    /// Returns the IDs of the registered modules, in order.
+    pub fn ids(&self) -> impl Iterator<Item = &ModuleId> {
+        self.modules.keys()
+    }
+
+    /// Returns the registered modules, in ID order.
+    pub fn modules(&self) -> impl Iterator<Item = &CompiledModule> {
+        self.modules.values().map(|registered| &registered.module)
+    }
+
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.modules.is_empty()
+    }
+}
@@ -0,0 +1,511 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines a canonical form of modules, for comparing them semantically.
+//!
+//! Two modules can mean the same thing and still differ in their binary form: compilers are free
+//! to order tables and pools as they see fit, and to leave unused or duplicate entries in them. A
+//! `NormalizedModule` resolves every index to the names and values it stands for, and keys
+//! definitions by name, so that it only depends on what the module declares and does. Normalized
+//! modules can be compared and hashed.
+//!
+//! What isn't part of the semantics of a module is left out: the order of definitions and pool
+//! entries, the order of the acquires list of a function, and the maximum stack size of its code,
+//! which is derived from the code itself.
+
+use crate::{
+    access::ModuleAccess,
+    errors::short_address,
+    file_format::{
+        Bytecode, FieldDefinitionIndex, FunctionDefinition, FunctionHandleIndex, Kind,
+        LocalsSignatureIndex, SignatureToken, StructDefinition, StructDefinitionIndex,
+        StructFieldInformation, StructHandleIndex, TypeParameterIndex,
+    },
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+use types::{account_address::AccountAddress, byte_array::ByteArray, language_storage::ModuleId};
+
+/// A module in canonical form.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NormalizedModule {
+    pub id: ModuleId,
+    /// The struct definitions, by name.
+    pub structs: BTreeMap<String, NormalizedStruct>,
+    /// The function definitions, by name.
+    pub functions: BTreeMap<String, NormalizedFunction>,
+}
+
+/// A struct or function of any module, identified by its module and name.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct QualifiedName {
+    pub module: ModuleId,
+    pub name: String,
+}
+
+/// A `SignatureToken` with struct handles resolved to the structs they name.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum NormalizedType {
+    Bool,
+    U64,
+    String,
+    ByteArray,
+    Address,
+    Struct(QualifiedName, Vec<NormalizedType>),
+    Reference(Box<NormalizedType>),
+    MutableReference(Box<NormalizedType>),
+    TypeParameter(TypeParameterIndex),
+}
+
+/// Displays types the way they are written in source, e.g. `&mut 0x1::Coin::T<u64, T0>`.
+impl fmt::Display for NormalizedType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NormalizedType::Bool => write!(f, "bool"),
+            NormalizedType::U64 => write!(f, "u64"),
+            NormalizedType::String => write!(f, "string"),
+            NormalizedType::ByteArray => write!(f, "bytearray"),
+            NormalizedType::Address => write!(f, "address"),
+            NormalizedType::Struct(name, type_actuals) => {
+                write!(
+                    f,
+                    "{}::{}::{}",
+                    short_address(name.module.address()),
+                    name.module.name(),
+                    name.name
+                )?;
+                if !type_actuals.is_empty() {
+                    let type_actuals: Vec<_> = type_actuals.iter().map(|t| t.to_string()).collect();
+                    write!(f, "<{}>", type_actuals.join(", "))?;
+                }
+                Ok(())
+            }
+            NormalizedType::Reference(inner) => write!(f, "&{}", inner),
+            NormalizedType::MutableReference(inner) => write!(f, "&mut {}", inner),
+            NormalizedType::TypeParameter(idx) => write!(f, "T{}", idx),
+        }
+    }
+}
+
+/// A struct definition in canonical form.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NormalizedStruct {
+    pub is_nominal_resource: bool,
+    pub type_formals: Vec<Kind>,
+    /// The fields, as names and types in declaration order, or `None` if the struct is native.
+    pub fields: Option<Vec<(String, NormalizedType)>>,
+}
+
+/// A function definition in canonical form.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NormalizedFunction {
+    pub is_public: bool,
+    pub is_native: bool,
+    pub type_formals: Vec<Kind>,
+    pub arg_types: Vec<NormalizedType>,
+    pub return_types: Vec<NormalizedType>,
+    /// The names of the resources the function acquires.
+    pub acquires: BTreeSet<String>,
+    /// The types of the locals, arguments included.
+    pub locals: Vec<NormalizedType>,
+    pub code: Vec<NormalizedBytecode>,
+}
+
+/// The type formals, argument types and return types of a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NormalizedSignature {
+    pub type_formals: Vec<Kind>,
+    pub arg_types: Vec<NormalizedType>,
+    pub return_types: Vec<NormalizedType>,
+}
+
+impl fmt::Display for NormalizedSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.type_formals.is_empty() {
+            let type_formals: Vec<_> = self
+                .type_formals
+                .iter()
+                .enumerate()
+                .map(|(idx, kind)| format!("T{}: {:?}", idx, kind))
+                .collect();
+            write!(f, "<{}>", type_formals.join(", "))?;
+        }
+        write!(f, "({})", join(&self.arg_types))?;
+        if !self.return_types.is_empty() {
+            write!(f, ": {}", join(&self.return_types))?;
+        }
+        Ok(())
+    }
+}
+
+fn join(types: &[NormalizedType]) -> String {
+    let types: Vec<_> = types.iter().map(|t| t.to_string()).collect();
+    types.join(", ")
+}
+
+/// A field of a struct of the module, identified by the names of the struct and field.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NormalizedField {
+    pub struct_name: String,
+    pub field_name: String,
+}
+
+/// An instruction with its table indexes resolved. Structs of the module are identified by name,
+/// since instructions can only name structs of the module they are in.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum NormalizedBytecode {
+    LdStr(String),
+    LdByteArray(ByteArray),
+    LdAddr(AccountAddress),
+    Call(QualifiedName, Vec<NormalizedType>),
+    Pack(String, Vec<NormalizedType>),
+    Unpack(String, Vec<NormalizedType>),
+    MutBorrowField(NormalizedField),
+    ImmBorrowField(NormalizedField),
+    BorrowGlobal(String, Vec<NormalizedType>),
+    Exists(String, Vec<NormalizedType>),
+    MoveFrom(String, Vec<NormalizedType>),
+    MoveToSender(String, Vec<NormalizedType>),
+    /// An instruction without table indexes, which is already canonical.
+    Other(Bytecode),
+}
+
+impl NormalizedModule {
+    /// Returns the canonical form of `module`.
+    ///
+    /// Definitions are keyed by name, so `module` should pass the duplication checker: if two
+    /// definitions have the same name, only the last one is kept.
+    pub fn new(module: &impl ModuleAccess) -> Self {
+        let normalizer = Normalizer { module };
+        let structs = module
+            .struct_defs()
+            .iter()
+            .map(|struct_def| normalizer.struct_definition(struct_def))
+            .collect();
+        let functions = module
+            .function_defs()
+            .iter()
+            .map(|function_def| normalizer.function_definition(function_def))
+            .collect();
+        Self {
+            id: module.self_id(),
+            structs,
+            functions,
+        }
+    }
+}
+
+impl NormalizedSignature {
+    /// Returns the signature of `function`.
+    pub fn new(function: &NormalizedFunction) -> Self {
+        Self {
+            type_formals: function.type_formals.clone(),
+            arg_types: function.arg_types.clone(),
+            return_types: function.return_types.clone(),
+        }
+    }
+
+    /// Returns the signature of the function `idx` refers to in `module`. Struct handles are
+    /// resolved to the structs they name, so signatures taken from different modules -- say, a
+    /// function handle and the definition it is imported from -- can be compared.
+    pub fn from_handle(module: &impl ModuleAccess, idx: FunctionHandleIndex) -> Self {
+        let normalizer = Normalizer { module };
+        let handle = module.function_handle_at(idx);
+        let signature = module.function_signature_at(handle.signature);
+        Self {
+            type_formals: signature.type_formals.clone(),
+            arg_types: normalizer.types(&signature.arg_types),
+            return_types: normalizer.types(&signature.return_types),
+        }
+    }
+
+    /// Returns true if `self` and `other` are equal up to a consistent renaming of their type
+    /// parameters. See `type_parameter_renaming`.
+    pub fn is_equivalent(&self, other: &Self) -> bool {
+        self.type_parameter_renaming(other).is_some()
+    }
+
+    /// Returns a renaming of the type parameters of `self` that makes it equal to `other`, if there
+    /// is one: `T{i}` of `self` becomes `T{renaming[i]}` of `other`, with the same kind.
+    ///
+    /// Callers bind type actuals to type formals by position, so two signatures are only
+    /// interchangeable for calls if the renaming is the identity. Any other renaming says how to
+    /// reorder type actuals to go from one to the other.
+    pub fn type_parameter_renaming(&self, other: &Self) -> Option<Vec<TypeParameterIndex>> {
+        if self.type_formals.len() != other.type_formals.len()
+            || self.arg_types.len() != other.arg_types.len()
+            || self.return_types.len() != other.return_types.len()
+        {
+            return None;
+        }
+
+        let mut renaming = Renaming {
+            forward: vec![None; self.type_formals.len()],
+            backward: vec![None; other.type_formals.len()],
+        };
+        let pairs = self
+            .arg_types
+            .iter()
+            .zip(&other.arg_types)
+            .chain(self.return_types.iter().zip(&other.return_types));
+        for (ty, other_ty) in pairs {
+            if !renaming.unify(ty, other_ty) {
+                return None;
+            }
+        }
+
+        // Type parameters that appear in neither arguments nor return types can be renamed to any
+        // unused type parameter of the same kind.
+        for idx in 0..self.type_formals.len() {
+            if renaming.forward[idx].is_some() {
+                continue;
+            }
+            let kind = self.type_formals[idx];
+            let other_idx = (0..other.type_formals.len()).find(|other_idx| {
+                renaming.backward[*other_idx].is_none() && other.type_formals[*other_idx] == kind
+            })?;
+            renaming.forward[idx] = Some(other_idx as TypeParameterIndex);
+            renaming.backward[other_idx] = Some(idx as TypeParameterIndex);
+        }
+
+        let renaming: Vec<_> = renaming
+            .forward
+            .into_iter()
+            .map(|other_idx| other_idx.expect("every type parameter is renamed"))
+            .collect();
+        let kinds_match = renaming.iter().enumerate().all(|(idx, other_idx)| {
+            self.type_formals[idx] == other.type_formals[*other_idx as usize]
+        });
+        if kinds_match {
+            Some(renaming)
+        } else {
+            None
+        }
+    }
+}
+
+/// A partial bijection between the type parameters of two signatures.
+struct Renaming {
+    forward: Vec<Option<TypeParameterIndex>>,
+    backward: Vec<Option<TypeParameterIndex>>,
+}
+
+impl Renaming {
+    /// Extends the renaming so that `ty` renames to `other`, returning false if it can't.
+    fn unify(&mut self, ty: &NormalizedType, other: &NormalizedType) -> bool {
+        use NormalizedType::*;
+
+        match (ty, other) {
+            (Bool, Bool) | (U64, U64) | (String, String) | (ByteArray, ByteArray) => true,
+            (Address, Address) => true,
+            (Struct(name, type_actuals), Struct(other_name, other_type_actuals)) => {
+                name == other_name
+                    && type_actuals.len() == other_type_actuals.len()
+                    && type_actuals
+                        .iter()
+                        .zip(other_type_actuals)
+                        .all(|(ty, other)| self.unify(ty, other))
+            }
+            (Reference(inner), Reference(other_inner))
+            | (MutableReference(inner), MutableReference(other_inner)) => {
+                self.unify(inner, other_inner)
+            }
+            (TypeParameter(idx), TypeParameter(other_idx)) => {
+                let (idx, other_idx) = (*idx, *other_idx);
+                match (
+                    self.forward.get(idx as usize),
+                    self.backward.get(other_idx as usize),
+                ) {
+                    (Some(None), Some(None)) => {
+                        self.forward[idx as usize] = Some(other_idx);
+                        self.backward[other_idx as usize] = Some(idx);
+                        true
+                    }
+                    (Some(forward), Some(backward)) => {
+                        *forward == Some(other_idx) && *backward == Some(idx)
+                    }
+                    // A type parameter that isn't declared.
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+struct Normalizer<'a, M> {
+    module: &'a M,
+}
+
+impl<'a, M: ModuleAccess> Normalizer<'a, M> {
+    fn struct_definition(&self, struct_def: &StructDefinition) -> (String, NormalizedStruct) {
+        let handle = self.module.struct_handle_at(struct_def.struct_handle);
+        let fields = match struct_def.field_information {
+            StructFieldInformation::Native => None,
+            StructFieldInformation::Declared {
+                field_count,
+                fields,
+            } => Some(
+                self.module
+                    .field_def_range(field_count, fields)
+                    .iter()
+                    .map(|field_def| {
+                        let signature = &self.module.type_signature_at(field_def.signature).0;
+                        (
+                            self.module.string_at(field_def.name).to_string(),
+                            self.type_(signature),
+                        )
+                    })
+                    .collect(),
+            ),
+        };
+        let struct_ = NormalizedStruct {
+            is_nominal_resource: handle.is_nominal_resource,
+            type_formals: handle.type_formals.clone(),
+            fields,
+        };
+        (self.module.string_at(handle.name).to_string(), struct_)
+    }
+
+    fn function_definition(
+        &self,
+        function_def: &FunctionDefinition,
+    ) -> (String, NormalizedFunction) {
+        let handle = self.module.function_handle_at(function_def.function);
+        let signature = self.module.function_signature_at(handle.signature);
+        let (locals, code) = if function_def.is_native() {
+            (vec![], vec![])
+        } else {
+            let locals = &self.module.locals_signature_at(function_def.code.locals).0;
+            (
+                self.types(locals),
+                function_def
+                    .code
+                    .code
+                    .iter()
+                    .map(|bytecode| self.bytecode(bytecode))
+                    .collect(),
+            )
+        };
+        let function = NormalizedFunction {
+            is_public: function_def.is_public(),
+            is_native: function_def.is_native(),
+            type_formals: signature.type_formals.clone(),
+            arg_types: self.types(&signature.arg_types),
+            return_types: self.types(&signature.return_types),
+            acquires: function_def
+                .acquires_global_resources
+                .iter()
+                .map(|idx| self.struct_name(*idx))
+                .collect(),
+            locals,
+            code,
+        };
+        (self.module.string_at(handle.name).to_string(), function)
+    }
+
+    fn bytecode(&self, bytecode: &Bytecode) -> NormalizedBytecode {
+        match bytecode {
+            Bytecode::LdStr(idx) => NormalizedBytecode::LdStr(self.module.string_at(*idx).into()),
+            Bytecode::LdByteArray(idx) => {
+                NormalizedBytecode::LdByteArray(self.module.byte_array_at(*idx).clone())
+            }
+            Bytecode::LdAddr(idx) => NormalizedBytecode::LdAddr(*self.module.address_at(*idx)),
+            Bytecode::Call(idx, type_actuals) => {
+                NormalizedBytecode::Call(self.function_name(*idx), self.type_actuals(*type_actuals))
+            }
+            Bytecode::Pack(idx, type_actuals) => {
+                NormalizedBytecode::Pack(self.struct_name(*idx), self.type_actuals(*type_actuals))
+            }
+            Bytecode::Unpack(idx, type_actuals) => {
+                NormalizedBytecode::Unpack(self.struct_name(*idx), self.type_actuals(*type_actuals))
+            }
+            Bytecode::MutBorrowField(idx) => NormalizedBytecode::MutBorrowField(self.field(*idx)),
+            Bytecode::ImmBorrowField(idx) => NormalizedBytecode::ImmBorrowField(self.field(*idx)),
+            Bytecode::BorrowGlobal(idx, type_actuals) => NormalizedBytecode::BorrowGlobal(
+                self.struct_name(*idx),
+                self.type_actuals(*type_actuals),
+            ),
+            Bytecode::Exists(idx, type_actuals) => {
+                NormalizedBytecode::Exists(self.struct_name(*idx), self.type_actuals(*type_actuals))
+            }
+            Bytecode::MoveFrom(idx, type_actuals) => NormalizedBytecode::MoveFrom(
+                self.struct_name(*idx),
+                self.type_actuals(*type_actuals),
+            ),
+            Bytecode::MoveToSender(idx, type_actuals) => NormalizedBytecode::MoveToSender(
+                self.struct_name(*idx),
+                self.type_actuals(*type_actuals),
+            ),
+            bytecode => NormalizedBytecode::Other(bytecode.clone()),
+        }
+    }
+
+    fn type_(&self, token: &SignatureToken) -> NormalizedType {
+        match token {
+            SignatureToken::Bool => NormalizedType::Bool,
+            SignatureToken::U64 => NormalizedType::U64,
+            SignatureToken::String => NormalizedType::String,
+            SignatureToken::ByteArray => NormalizedType::ByteArray,
+            SignatureToken::Address => NormalizedType::Address,
+            SignatureToken::Struct(idx, type_actuals) => {
+                NormalizedType::Struct(self.struct_handle_name(*idx), self.types(type_actuals))
+            }
+            SignatureToken::Reference(inner) => {
+                NormalizedType::Reference(Box::new(self.type_(inner)))
+            }
+            SignatureToken::MutableReference(inner) => {
+                NormalizedType::MutableReference(Box::new(self.type_(inner)))
+            }
+            SignatureToken::TypeParameter(idx) => NormalizedType::TypeParameter(*idx),
+        }
+    }
+
+    fn types(&self, tokens: &[SignatureToken]) -> Vec<NormalizedType> {
+        tokens.iter().map(|token| self.type_(token)).collect()
+    }
+
+    fn type_actuals(&self, idx: LocalsSignatureIndex) -> Vec<NormalizedType> {
+        self.types(&self.module.locals_signature_at(idx).0)
+    }
+
+    fn struct_name(&self, idx: StructDefinitionIndex) -> String {
+        let handle = self.module.struct_def_at(idx).struct_handle;
+        self.module
+            .string_at(self.module.struct_handle_at(handle).name)
+            .to_string()
+    }
+
+    fn struct_handle_name(&self, idx: StructHandleIndex) -> QualifiedName {
+        let handle = self.module.struct_handle_at(idx);
+        QualifiedName {
+            module: self
+                .module
+                .module_id_for_handle(self.module.module_handle_at(handle.module)),
+            name: self.module.string_at(handle.name).to_string(),
+        }
+    }
+
+    fn function_name(&self, idx: FunctionHandleIndex) -> QualifiedName {
+        let handle = self.module.function_handle_at(idx);
+        QualifiedName {
+            module: self
+                .module
+                .module_id_for_handle(self.module.module_handle_at(handle.module)),
+            name: self.module.string_at(handle.name).to_string(),
+        }
+    }
+
+    fn field(&self, idx: FieldDefinitionIndex) -> NormalizedField {
+        let field_def = self.module.field_def_at(idx);
+        NormalizedField {
+            struct_name: self
+                .module
+                .string_at(self.module.struct_handle_at(field_def.struct_).name)
+                .to_string(),
+            field_name: self.module.string_at(field_def.name).to_string(),
+        }
+    }
+}
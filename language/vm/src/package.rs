@@ -0,0 +1,238 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines packages, which bundle the modules that are built, verified and published together
+//! with a manifest describing them.
+//!
+//! The manifest pins every module outside the package that the package depends on to the digest
+//! of the version it was built against, so that a package can be checked against the modules
+//! actually published before it is published itself.
+
+use crate::{access::ModuleAccess, file_format::CompiledModule, file_format_common::*};
+use byteorder::ReadBytesExt;
+use crypto::HashValue;
+use failure::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    io::{Cursor, Read},
+};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+/// The first bytes of a package in binary form.
+const MAGIC: &[u8] = b"MVPACKGE";
+/// The version of the binary form written by `Package::serialize`.
+const VERSION: u8 = 1;
+
+/// Returns the digest of `module`, which identifies one version of it: the hash of its
+/// serialized form.
+pub fn module_digest(module: &CompiledModule) -> Result<HashValue> {
+    let mut binary = vec![];
+    module.serialize(&mut binary)?;
+    Ok(HashValue::from_sha3_256(&binary))
+}
+
+/// A module outside a package that the package depends on.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PackageDependency {
+    pub module: ModuleId,
+    /// The digest of the version of the module the package was built against.
+    pub digest: HashValue,
+}
+
+/// Describes a package.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PackageManifest {
+    pub name: String,
+    /// The address the modules of the package are published under.
+    pub address: AccountAddress,
+    pub dependencies: Vec<PackageDependency>,
+}
+
+/// Why a package is malformed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PackageError {
+    /// Two modules of the package have this ID.
+    DuplicateModule(ModuleId),
+    /// These modules of the package can't be ordered, because they are in a dependency cycle or
+    /// depend on a module that is.
+    DependencyCycle(Vec<ModuleId>),
+}
+
+impl fmt::Display for PackageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PackageError::DuplicateModule(id) => write!(f, "duplicate module {:?}", id),
+            PackageError::DependencyCycle(ids) => write!(f, "dependency cycle among {:?}", ids),
+        }
+    }
+}
+
+/// Modules bundled with a manifest.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Package {
+    manifest: PackageManifest,
+    modules: Vec<CompiledModule>,
+}
+
+impl Package {
+    /// Bundles `modules` with `manifest`. Every module must have a different ID.
+    pub fn new(
+        manifest: PackageManifest,
+        modules: Vec<CompiledModule>,
+    ) -> std::result::Result<Self, PackageError> {
+        let mut ids = BTreeSet::new();
+        for module in &modules {
+            let id = module.self_id();
+            if !ids.insert(id.clone()) {
+                return Err(PackageError::DuplicateModule(id));
+            }
+        }
+        Ok(Self { manifest, modules })
+    }
+
+    pub fn manifest(&self) -> &PackageManifest {
+        &self.manifest
+    }
+
+    /// Returns the modules, in the order they were bundled.
+    pub fn modules(&self) -> &[CompiledModule] {
+        &self.modules
+    }
+
+    /// Returns the module of the package with ID `id`.
+    pub fn module(&self, id: &ModuleId) -> Option<&CompiledModule> {
+        self.modules.iter().find(|module| module.self_id() == *id)
+    }
+
+    pub fn into_inner(self) -> (PackageManifest, Vec<CompiledModule>) {
+        (self.manifest, self.modules)
+    }
+
+    /// Returns the modules ordered so that every module comes after the modules of the package it
+    /// depends on. Modules that don't depend on each other keep the order they were bundled in.
+    pub fn topological_order(&self) -> std::result::Result<Vec<&CompiledModule>, PackageError> {
+        Ok(self
+            .order()?
+            .into_iter()
+            .map(|idx| &self.modules[idx])
+            .collect())
+    }
+
+    /// Returns the modules ordered as with `topological_order`.
+    pub fn into_topological_order(self) -> std::result::Result<Vec<CompiledModule>, PackageError> {
+        let order = self.order()?;
+        let mut modules: Vec<_> = self.modules.into_iter().map(Some).collect();
+        Ok(order
+            .into_iter()
+            .map(|idx| modules[idx].take().expect("order is a permutation"))
+            .collect())
+    }
+
+    /// Returns the indexes of the modules in topological order.
+    fn order(&self) -> std::result::Result<Vec<usize>, PackageError> {
+        let ids: Vec<_> = self.modules.iter().map(|module| module.self_id()).collect();
+        // The indexes of the modules of the package each module depends on.
+        let dependencies: Vec<BTreeSet<usize>> = self
+            .modules
+            .iter()
+            .map(|module| {
+                module
+                    .module_handles()
+                    .iter()
+                    .skip(1)
+                    .filter_map(|handle| {
+                        let id = module.module_id_for_handle(handle);
+                        ids.iter().position(|package_id| *package_id == id)
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut order = Vec::with_capacity(self.modules.len());
+        let mut placed = vec![false; self.modules.len()];
+        while order.len() < self.modules.len() {
+            let next = (0..self.modules.len())
+                .find(|idx| !placed[*idx] && dependencies[*idx].iter().all(|dep| placed[*dep]));
+            match next {
+                Some(idx) => {
+                    placed[idx] = true;
+                    order.push(idx);
+                }
+                None => {
+                    let cycle = (0..self.modules.len())
+                        .filter(|idx| !placed[*idx])
+                        .map(|idx| ids[idx].clone())
+                        .collect();
+                    return Err(PackageError::DependencyCycle(cycle));
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Serializes the package to its binary form: the manifest as JSON, followed by the modules
+    /// in their binary form.
+    pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
+        let mut data = BinaryData::new();
+        data.extend(MAGIC)?;
+        data.push(VERSION)?;
+        write_bytes(&mut data, serde_json::to_string(&self.manifest)?.as_bytes())?;
+        write_len(&mut data, self.modules.len())?;
+        for module in &self.modules {
+            let mut module_binary = vec![];
+            module.serialize(&mut module_binary)?;
+            write_bytes(&mut data, &module_binary)?;
+        }
+        binary.extend(data.into_inner());
+        Ok(())
+    }
+
+    /// Parses a package serialized with `serialize`. Modules are bounds checked, but not
+    /// verified.
+    pub fn deserialize(binary: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(binary);
+        let mut magic = [0u8; 8];
+        cursor.read_exact(&mut magic)?;
+        ensure!(&magic[..] == MAGIC, "bad package magic");
+        let version = cursor.read_u8()?;
+        ensure!(
+            version == VERSION,
+            "unsupported package version {}",
+            version
+        );
+
+        let manifest = serde_json::from_slice(&read_bytes(&mut cursor)?)?;
+        let mut modules = vec![];
+        for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+            let module = CompiledModule::deserialize(&read_bytes(&mut cursor)?)
+                .map_err(|err| format_err!("invalid module in package: {:?}", err))?;
+            modules.push(module);
+        }
+        ensure!(
+            cursor.position() as usize == binary.len(),
+            "trailing bytes after package"
+        );
+        Self::new(manifest, modules).map_err(|err| format_err!("{}", err))
+    }
+}
+
+fn write_len(data: &mut BinaryData, len: usize) -> Result<()> {
+    ensure!(len <= u32::max_value() as usize, "package table too long");
+    write_u32_as_uleb128(data, len as u32)
+}
+
+fn write_bytes(data: &mut BinaryData, bytes: &[u8]) -> Result<()> {
+    write_len(data, bytes.len())?;
+    data.extend(bytes)
+}
+
+fn read_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Vec<u8>> {
+    let len = read_uleb128_as_u32(cursor)? as usize;
+    let remaining = cursor.get_ref().len() - cursor.position() as usize;
+    ensure!(len <= remaining, "package entry past the end of the binary");
+    let mut bytes = vec![0u8; len];
+    cursor.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
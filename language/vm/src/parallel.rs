@@ -0,0 +1,53 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Parallel iterators over the definitions of a module, enabled by the `parallel` feature.
+//!
+//! Every method here is the parallel counterpart of a sequential one, with a `par_` prefix:
+//! `CompiledModule::par_function_defs` for `function_defs().iter()`, `ModuleView::par_functions`
+//! for `ModuleView::functions`, and so on. Analyses that look at each definition on its own, like
+//! most passes over the standard library, are switched to run in parallel by renaming the call.
+//! The iterators are indexed, so collecting them keeps the order of the definitions.
+
+use crate::{
+    access::ModuleAccess,
+    file_format::{CompiledModule, FunctionDefinition, StructDefinition},
+    views::{FunctionDefinitionView, ModuleView, StructDefinitionView, ViewInternals},
+};
+use rayon::{prelude::*, slice};
+
+impl CompiledModule {
+    /// Returns a parallel iterator over the function definitions.
+    pub fn par_function_defs(&self) -> slice::Iter<'_, FunctionDefinition> {
+        self.function_defs().par_iter()
+    }
+
+    /// Returns a parallel iterator over the struct definitions.
+    pub fn par_struct_defs(&self) -> slice::Iter<'_, StructDefinition> {
+        self.struct_defs().par_iter()
+    }
+}
+
+impl<'a, T: ModuleAccess> ModuleView<'a, T> {
+    /// Returns a parallel iterator over views of the function definitions.
+    pub fn par_functions(
+        &self,
+    ) -> impl IndexedParallelIterator<Item = FunctionDefinitionView<'a, T>> + 'a {
+        let module = self.module();
+        module
+            .function_defs()
+            .par_iter()
+            .map(move |function_def| FunctionDefinitionView::new(module, function_def))
+    }
+
+    /// Returns a parallel iterator over views of the struct definitions.
+    pub fn par_structs(
+        &self,
+    ) -> impl IndexedParallelIterator<Item = StructDefinitionView<'a, T>> + 'a {
+        let module = self.module();
+        module
+            .struct_defs()
+            .par_iter()
+            .map(move |struct_def| StructDefinitionView::new(module, struct_def))
+    }
+}
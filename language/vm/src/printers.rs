@@ -1,7 +1,7 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::file_format::*;
+use crate::{file_format::*, views::ModuleView};
 use failure::*;
 use hex;
 use std::{collections::VecDeque, fmt};
@@ -89,56 +89,56 @@ impl TableAccess for CompiledScriptMut {
 
     fn get_module_at(&self, idx: ModuleHandleIndex) -> Result<&ModuleHandle> {
         match self.module_handles.get(idx.0 as usize) {
-            None => bail!("bad module handle index {}", idx),
+            None => bail!("bad {}", idx),
             Some(m) => Ok(m),
         }
     }
 
     fn get_struct_at(&self, idx: StructHandleIndex) -> Result<&StructHandle> {
         match self.struct_handles.get(idx.0 as usize) {
-            None => bail!("bad struct handle index {}", idx),
+            None => bail!("bad {}", idx),
             Some(s) => Ok(s),
         }
     }
 
     fn get_function_at(&self, idx: FunctionHandleIndex) -> Result<&FunctionHandle> {
         match self.function_handles.get(idx.0 as usize) {
-            None => bail!("bad function handle index {}", idx),
+            None => bail!("bad {}", idx),
             Some(m) => Ok(m),
         }
     }
 
     fn get_string_at(&self, idx: StringPoolIndex) -> Result<&str> {
         match self.string_pool.get(idx.0 as usize) {
-            None => bail!("bad string index {}", idx),
+            None => bail!("bad {}", idx),
             Some(s) => Ok(s),
         }
     }
 
     fn get_address_at(&self, idx: AddressPoolIndex) -> Result<&AccountAddress> {
         match self.address_pool.get(idx.0 as usize) {
-            None => bail!("bad address index {}", idx),
+            None => bail!("bad {}", idx),
             Some(addr) => Ok(addr),
         }
     }
 
     fn get_type_signature_at(&self, idx: TypeSignatureIndex) -> Result<&TypeSignature> {
         match self.type_signatures.get(idx.0 as usize) {
-            None => bail!("bad signature index {}", idx),
+            None => bail!("bad {}", idx),
             Some(sig) => Ok(sig),
         }
     }
 
     fn get_function_signature_at(&self, idx: FunctionSignatureIndex) -> Result<&FunctionSignature> {
         match self.function_signatures.get(idx.0 as usize) {
-            None => bail!("bad signature index {}", idx),
+            None => bail!("bad {}", idx),
             Some(sig) => Ok(sig),
         }
     }
 
     fn get_locals_signature_at(&self, idx: LocalsSignatureIndex) -> Result<&LocalsSignature> {
         match self.locals_signatures.get(idx.0 as usize) {
-            None => bail!("bad signature index {}", idx),
+            None => bail!("bad {}", idx),
             Some(sig) => Ok(sig),
         }
     }
@@ -147,63 +147,63 @@ impl TableAccess for CompiledScriptMut {
 impl TableAccess for CompiledModuleMut {
     fn get_field_def_at(&self, idx: FieldDefinitionIndex) -> Result<&FieldDefinition> {
         match self.field_defs.get(idx.0 as usize) {
-            None => bail!("bad field definition index {}", idx),
+            None => bail!("bad {}", idx),
             Some(f) => Ok(f),
         }
     }
 
     fn get_module_at(&self, idx: ModuleHandleIndex) -> Result<&ModuleHandle> {
         match self.module_handles.get(idx.0 as usize) {
-            None => bail!("bad module handle index {}", idx),
+            None => bail!("bad {}", idx),
             Some(m) => Ok(m),
         }
     }
 
     fn get_struct_at(&self, idx: StructHandleIndex) -> Result<&StructHandle> {
         match self.struct_handles.get(idx.0 as usize) {
-            None => bail!("bad struct handle index {}", idx),
+            None => bail!("bad {}", idx),
             Some(s) => Ok(s),
         }
     }
 
     fn get_function_at(&self, idx: FunctionHandleIndex) -> Result<&FunctionHandle> {
         match self.function_handles.get(idx.0 as usize) {
-            None => bail!("bad function handle index {}", idx),
+            None => bail!("bad {}", idx),
             Some(m) => Ok(m),
         }
     }
 
     fn get_string_at(&self, idx: StringPoolIndex) -> Result<&str> {
         match self.string_pool.get(idx.0 as usize) {
-            None => bail!("bad string index {}", idx),
+            None => bail!("bad {}", idx),
             Some(s) => Ok(s),
         }
     }
 
     fn get_address_at(&self, idx: AddressPoolIndex) -> Result<&AccountAddress> {
         match self.address_pool.get(idx.0 as usize) {
-            None => bail!("bad address index {}", idx),
+            None => bail!("bad {}", idx),
             Some(addr) => Ok(addr),
         }
     }
 
     fn get_type_signature_at(&self, idx: TypeSignatureIndex) -> Result<&TypeSignature> {
         match self.type_signatures.get(idx.0 as usize) {
-            None => bail!("bad signature index {}", idx),
+            None => bail!("bad {}", idx),
             Some(sig) => Ok(sig),
         }
     }
 
     fn get_function_signature_at(&self, idx: FunctionSignatureIndex) -> Result<&FunctionSignature> {
         match self.function_signatures.get(idx.0 as usize) {
-            None => bail!("bad signature index {}", idx),
+            None => bail!("bad {}", idx),
             Some(sig) => Ok(sig),
         }
     }
 
     fn get_locals_signature_at(&self, idx: LocalsSignatureIndex) -> Result<&LocalsSignature> {
         match self.locals_signatures.get(idx.0 as usize) {
-            None => bail!("bad signature index {}", idx),
+            None => bail!("bad {}", idx),
             Some(sig) => Ok(sig),
         }
     }
@@ -224,7 +224,13 @@ impl fmt::Display for CompiledScript {
         let inner = self.as_inner();
         write!(f, "CompiledScript: {{\nMain:\n\t")?;
         display_function_definition(&inner.main, inner, f)?;
-        display_code(&inner.main.code, inner, "\n\t\t", f)?;
+        display_code(
+            &inner.main.code,
+            inner,
+            "\n\t\t",
+            |bytecode, f| display_bytecode(bytecode, inner, f),
+            f,
+        )?;
         write!(f, "\nStruct Handles: [")?;
         for struct_handle in &inner.struct_handles {
             write!(f, "\n\t")?;
@@ -293,6 +299,7 @@ impl fmt::Display for CompiledScript {
 impl fmt::Display for CompiledModule {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let inner = self.as_inner();
+        let view = ModuleView::new(self);
         writeln!(f, "CompiledModule: {{")?;
         write!(f, "Module Handles: [")?;
         for module_handle in &inner.module_handles {
@@ -352,7 +359,13 @@ impl fmt::Display for CompiledModule {
             write!(f, "\n\t")?;
             display_function_definition(function_def, inner, f)?;
             if function_def.flags & CodeUnit::NATIVE == 0 {
-                display_code(&function_def.code, inner, "\n\t\t", f)?;
+                display_code(
+                    &function_def.code,
+                    inner,
+                    "\n\t\t",
+                    |bytecode, f| write!(f, "{}", bytecode.display(&view)),
+                    f,
+                )?;
             }
             write!(f, ",")?;
         }
@@ -470,7 +483,7 @@ fn display_field_definition<T: TableAccess>(
     )
 }
 
-fn display_function_definition<T: TableAccess>(
+pub(crate) fn display_function_definition<T: TableAccess>(
     function: &FunctionDefinition,
     tables: &T,
     f: &mut fmt::Formatter,
@@ -487,6 +500,7 @@ fn display_code<T: TableAccess>(
     code: &CodeUnit,
     tables: &T,
     indentation: &str,
+    display_instruction: impl Fn(&Bytecode, &mut fmt::Formatter) -> fmt::Result,
     f: &mut fmt::Formatter,
 ) -> fmt::Result {
     write!(f, "{}locals({}): ", indentation, code.locals,)?;
@@ -498,7 +512,7 @@ fn display_code<T: TableAccess>(
     write!(f, ",")?;
     for bytecode in &code.code {
         write!(f, "{}", indentation)?;
-        display_bytecode(bytecode, tables, f)?;
+        display_instruction(bytecode, f)?;
     }
     Ok(())
 }
@@ -624,7 +638,7 @@ fn display_function_flags(flags: u8, f: &mut fmt::Formatter) -> fmt::Result {
     Ok(())
 }
 
-fn display_bytecode<T: TableAccess>(
+pub(crate) fn display_bytecode<T: TableAccess>(
     bytecode: &Bytecode,
     tables: &T,
     f: &mut fmt::Formatter,
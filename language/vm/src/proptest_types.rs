@@ -3,11 +3,14 @@
 
 //! Utilities for property-based testing.
 
-use crate::file_format::{
-    AddressPoolIndex, CompiledModule, CompiledModuleMut, FieldDefinition, FieldDefinitionIndex,
-    FunctionHandle, FunctionSignatureIndex, Kind, MemberCount, ModuleHandle, ModuleHandleIndex,
-    SignatureToken, StringPoolIndex, StructDefinition, StructFieldInformation, StructHandle,
-    StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex,
+use crate::{
+    file_format::{
+        AddressPoolIndex, CompiledModule, CompiledModuleMut, FieldDefinition, FieldDefinitionIndex,
+        FunctionHandle, FunctionSignatureIndex, Kind, MemberCount, ModuleHandle, ModuleHandleIndex,
+        SignatureToken, StringPoolIndex, StructDefinition, StructFieldInformation, StructHandle,
+        StructHandleIndex, TableIndex, TypeSignature, TypeSignatureIndex,
+    },
+    identifier::Identifier,
 };
 use proptest::{
     collection::{vec, SizeRange},
@@ -43,8 +46,8 @@ impl CompiledModule {
 /// pointers among those nodes. This graph has some properties:
 ///
 /// 1. The graph has cycles. Generating DAGs is often simpler, but is not an option in this case.
-/// 2. The actual structure of the graph is well-defined in terms of the kinds of nodes and
-///    pointers that exist.
+/// 2. The actual structure of the graph is well-defined in terms of the kinds of nodes and pointers
+///    that exist.
 ///
 /// TODO: the graph also has pointers *out* of it, via address references to other modules.
 /// This doesn't need to be handled when viewing modules in isolation, but some verification passes
@@ -106,7 +109,7 @@ impl CompiledModuleStrategyGen {
         // This ensures that there are no empty ByteArrays
         // TODO: Should we enable empty ByteArrays in Move, e.g. let byte_array = b"";
         let byte_array_pool_strat = vec(any::<ByteArray>(), 1..=self.size);
-        let string_pool_strat = vec(".*", 1..=self.size);
+        let string_pool_strat = vec(".*".prop_map(Identifier::from), 1..=self.size);
 
         let type_signatures_strat = vec(SignatureTokenGen::strategy(), 1..=self.size);
         // Ensure at least one owned non-struct type signature.
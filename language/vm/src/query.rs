@@ -0,0 +1,385 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A small query language over the definitions of a module, for ad-hoc audits and search.
+//!
+//! A query selects either functions or structs, optionally filtered by predicates that must all
+//! hold:
+//!
+//! ```text
+//! functions where public and args > 3
+//! functions where calls 0x1::Account::withdraw
+//! functions where not native and instructions >= 100
+//! structs where resource and references
+//! structs where name = T
+//! ```
+//!
+//! Functions can be filtered with `public`, `native`, `args`, `returns`, `locals` (arguments
+//! included), `instructions`, `calls <address>::<module>::<function>` and `name = <name>`;
+//! structs with `resource`, `native`, `fields`, `references` (a field is a reference) and
+//! `name = <name>`. Counts are compared with `<`, `<=`, `=`, `!=`, `>=` or `>`, and any predicate
+//! can be negated with `not`.
+
+use crate::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CompiledModule, FunctionDefinitionIndex, StructDefinitionIndex, TableIndex,
+    },
+    views::{FunctionDefinitionView, FunctionHandleView, ModuleView, StructDefinitionView},
+};
+use failure::prelude::*;
+use std::{iter::Peekable, str::FromStr, vec::IntoIter};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+/// Why a query couldn't be parsed.
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+pub enum QueryError {
+    #[fail(display = "expected {}, found end of query", expected)]
+    UnexpectedEnd { expected: &'static str },
+    #[fail(display = "expected {}, found '{}'", expected, found)]
+    UnexpectedToken {
+        expected: &'static str,
+        found: String,
+    },
+}
+
+/// How a count is compared to a bound.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Comparison {
+    /// Returns whether `count` compares to `bound` this way.
+    pub fn holds(self, count: usize, bound: usize) -> bool {
+        match self {
+            Comparison::Lt => count < bound,
+            Comparison::Le => count <= bound,
+            Comparison::Eq => count == bound,
+            Comparison::Ne => count != bound,
+            Comparison::Ge => count >= bound,
+            Comparison::Gt => count > bound,
+        }
+    }
+}
+
+/// A condition on a function definition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FunctionPredicate {
+    Public,
+    Native,
+    Arguments(Comparison, usize),
+    Returns(Comparison, usize),
+    /// Compares the number of locals, arguments included.
+    Locals(Comparison, usize),
+    Instructions(Comparison, usize),
+    /// The function calls the function named by the string in the given module.
+    Calls(ModuleId, String),
+    Named(String),
+    Not(Box<FunctionPredicate>),
+}
+
+impl FunctionPredicate {
+    /// Returns whether the predicate holds for `function`, defined in `module`.
+    pub fn holds<T: ModuleAccess>(&self, module: &T, function: &FunctionDefinitionView<T>) -> bool {
+        match self {
+            FunctionPredicate::Public => function.is_public(),
+            FunctionPredicate::Native => function.is_native(),
+            FunctionPredicate::Arguments(comparison, bound) => {
+                comparison.holds(function.signature().arg_count(), *bound)
+            }
+            FunctionPredicate::Returns(comparison, bound) => {
+                comparison.holds(function.signature().return_count(), *bound)
+            }
+            FunctionPredicate::Locals(comparison, bound) => {
+                comparison.holds(function.locals_signature().len(), *bound)
+            }
+            FunctionPredicate::Instructions(comparison, bound) => {
+                comparison.holds(function.code().code.len(), *bound)
+            }
+            FunctionPredicate::Calls(module_id, name) => {
+                function.code().code.iter().any(|bytecode| match bytecode {
+                    Bytecode::Call(idx, _) => {
+                        let callee =
+                            FunctionHandleView::new(module, module.function_handle_at(*idx));
+                        callee.name() == name && callee.module_id() == *module_id
+                    }
+                    _ => false,
+                })
+            }
+            FunctionPredicate::Named(name) => function.name() == name,
+            FunctionPredicate::Not(predicate) => !predicate.holds(module, function),
+        }
+    }
+}
+
+/// A condition on a struct definition.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StructPredicate {
+    Resource,
+    Native,
+    Fields(Comparison, usize),
+    /// A field of the struct is a reference.
+    References,
+    Named(String),
+    Not(Box<StructPredicate>),
+}
+
+impl StructPredicate {
+    pub fn holds<T: ModuleAccess>(&self, struct_def: &StructDefinitionView<T>) -> bool {
+        match self {
+            StructPredicate::Resource => struct_def.is_nominal_resource(),
+            StructPredicate::Native => struct_def.is_native(),
+            StructPredicate::Fields(comparison, bound) => {
+                let fields = struct_def.fields().map_or(0, |fields| fields.count());
+                comparison.holds(fields, *bound)
+            }
+            StructPredicate::References => struct_def.fields().map_or(false, |mut fields| {
+                fields.any(|field| field.signature_token().is_reference())
+            }),
+            StructPredicate::Named(name) => struct_def.name() == name,
+            StructPredicate::Not(predicate) => !predicate.holds(struct_def),
+        }
+    }
+}
+
+/// A query over the definitions of a module.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Query {
+    Functions(Vec<FunctionPredicate>),
+    Structs(Vec<StructPredicate>),
+}
+
+/// A definition selected by a query.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum QueryMatch {
+    Function(FunctionDefinitionIndex),
+    Struct(StructDefinitionIndex),
+}
+
+impl Query {
+    /// Parses a query; see the module documentation for the syntax.
+    pub fn parse(query: &str) -> Result<Self> {
+        Parser::new(query).query()
+    }
+
+    /// Returns the definitions of `module` for which every predicate holds, in definition order.
+    pub fn run(&self, module: &CompiledModule) -> Vec<QueryMatch> {
+        let view = ModuleView::new(module);
+        match self {
+            Query::Functions(predicates) => view
+                .functions()
+                .enumerate()
+                .filter(|(_, function)| predicates.iter().all(|p| p.holds(module, function)))
+                .map(|(idx, _)| {
+                    QueryMatch::Function(FunctionDefinitionIndex::new(idx as TableIndex))
+                })
+                .collect(),
+            Query::Structs(predicates) => view
+                .structs()
+                .enumerate()
+                .filter(|(_, struct_def)| predicates.iter().all(|p| p.holds(struct_def)))
+                .map(|(idx, _)| QueryMatch::Struct(StructDefinitionIndex::new(idx as TableIndex)))
+                .collect(),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(query: &str) -> Result<Self> {
+        Self::parse(query)
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == ':'
+}
+
+fn is_comparison(c: char) -> bool {
+    c == '<' || c == '>' || c == '=' || c == '!'
+}
+
+struct Parser {
+    tokens: Peekable<IntoIter<String>>,
+}
+
+impl Parser {
+    fn new(query: &str) -> Self {
+        // A token is either a run of word characters, which include the `::` and `0x` of
+        // function names, or a run of comparison characters.
+        let mut tokens = vec![];
+        let mut chars = query.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let mut token = c.to_string();
+            let class: Option<fn(char) -> bool> = if is_word(c) {
+                Some(is_word)
+            } else if is_comparison(c) {
+                Some(is_comparison)
+            } else {
+                None
+            };
+            if let Some(class) = class {
+                while let Some(&next) = chars.peek() {
+                    if !class(next) {
+                        break;
+                    }
+                    token.push(next);
+                    chars.next();
+                }
+            }
+            tokens.push(token);
+        }
+        Self {
+            tokens: tokens.into_iter().peekable(),
+        }
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<String> {
+        match self.tokens.next() {
+            Some(token) => Ok(token),
+            None => bail_err!(QueryError::UnexpectedEnd { expected }),
+        }
+    }
+
+    fn expect(&mut self, expected: &'static str) -> Result<()> {
+        let found = self.next(expected)?;
+        if found != expected {
+            bail_err!(QueryError::UnexpectedToken { expected, found });
+        }
+        Ok(())
+    }
+
+    fn query(mut self) -> Result<Query> {
+        let expected = "'functions' or 'structs'";
+        let query = match self.next(expected)?.as_str() {
+            "functions" => Query::Functions(self.predicates(Self::function_predicate)?),
+            "structs" => Query::Structs(self.predicates(Self::struct_predicate)?),
+            found => bail_err!(QueryError::UnexpectedToken {
+                expected,
+                found: found.to_string(),
+            }),
+        };
+        if let Some(found) = self.tokens.next() {
+            bail_err!(QueryError::UnexpectedToken {
+                expected: "'and' or end of query",
+                found,
+            });
+        }
+        Ok(query)
+    }
+
+    fn predicates<P>(&mut self, predicate: fn(&mut Self) -> Result<P>) -> Result<Vec<P>> {
+        let mut predicates = vec![];
+        if self.tokens.peek().is_none() {
+            return Ok(predicates);
+        }
+        self.expect("where")?;
+        predicates.push(predicate(self)?);
+        while self.tokens.peek().map(String::as_str) == Some("and") {
+            self.tokens.next();
+            predicates.push(predicate(self)?);
+        }
+        Ok(predicates)
+    }
+
+    fn function_predicate(&mut self) -> Result<FunctionPredicate> {
+        let expected = "a function predicate";
+        Ok(match self.next(expected)?.as_str() {
+            "not" => FunctionPredicate::Not(Box::new(self.function_predicate()?)),
+            "public" => FunctionPredicate::Public,
+            "native" => FunctionPredicate::Native,
+            "args" => FunctionPredicate::Arguments(self.comparison()?, self.count()?),
+            "returns" => FunctionPredicate::Returns(self.comparison()?, self.count()?),
+            "locals" => FunctionPredicate::Locals(self.comparison()?, self.count()?),
+            "instructions" => FunctionPredicate::Instructions(self.comparison()?, self.count()?),
+            "calls" => {
+                let (module_id, name) = self.function_name()?;
+                FunctionPredicate::Calls(module_id, name)
+            }
+            "name" => {
+                self.expect("=")?;
+                FunctionPredicate::Named(self.next("a name")?)
+            }
+            found => bail_err!(QueryError::UnexpectedToken {
+                expected,
+                found: found.to_string(),
+            }),
+        })
+    }
+
+    fn struct_predicate(&mut self) -> Result<StructPredicate> {
+        let expected = "a struct predicate";
+        Ok(match self.next(expected)?.as_str() {
+            "not" => StructPredicate::Not(Box::new(self.struct_predicate()?)),
+            "resource" => StructPredicate::Resource,
+            "native" => StructPredicate::Native,
+            "fields" => StructPredicate::Fields(self.comparison()?, self.count()?),
+            "references" => StructPredicate::References,
+            "name" => {
+                self.expect("=")?;
+                StructPredicate::Named(self.next("a name")?)
+            }
+            found => bail_err!(QueryError::UnexpectedToken {
+                expected,
+                found: found.to_string(),
+            }),
+        })
+    }
+
+    fn comparison(&mut self) -> Result<Comparison> {
+        let expected = "a comparison";
+        Ok(match self.next(expected)?.as_str() {
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            "=" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            ">=" => Comparison::Ge,
+            ">" => Comparison::Gt,
+            found => bail_err!(QueryError::UnexpectedToken {
+                expected,
+                found: found.to_string(),
+            }),
+        })
+    }
+
+    fn count(&mut self) -> Result<usize> {
+        let expected = "a count";
+        let found = self.next(expected)?;
+        match found.parse() {
+            Ok(count) => Ok(count),
+            Err(_) => bail_err!(QueryError::UnexpectedToken { expected, found }),
+        }
+    }
+
+    /// Parses `<address>::<module>::<function>`.
+    fn function_name(&mut self) -> Result<(ModuleId, String)> {
+        let expected = "a function such as 0x1::Account::withdraw";
+        let found = self.next(expected)?;
+        let parts: Vec<_> = found.split("::").collect();
+        let address = match parts.as_slice() {
+            [address, module, function]
+                if address.starts_with("0x") && !module.is_empty() && !function.is_empty() =>
+            {
+                AccountAddress::from_hex_literal(address).ok()
+            }
+            _ => None,
+        };
+        match address {
+            Some(address) => Ok((
+                ModuleId::new(address, parts[1].to_string()),
+                parts[2].to_string(),
+            )),
+            None => bail_err!(QueryError::UnexpectedToken { expected, found }),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Function bodies kept in their serialized form.
+//!
+//! Decoding the code of a module is the bulk of the work of deserializing it, yet many analyses
+//! only look at handles and signatures. A `RawCode` keeps the serialized instructions along with
+//! the position of each of them, and decodes an instruction only when it is accessed. Serializing
+//! a `RawCode` again copies its bytes as they are.
+//!
+//! `RawCodeModule::deserialize` deserializes a module this way, and `RawCode::serialize` turns
+//! decoded code back into raw code.
+
+use crate::{
+    deserializer::load_instruction,
+    file_format::{
+        Bytecode, CodeOffset, CodeUnit, CompiledModuleMut, FunctionDefinition, FunctionHandleIndex,
+        LocalsSignatureIndex, StructDefinitionIndex,
+    },
+};
+use bytes::Bytes;
+use std::io::Cursor;
+
+/// A code stream in its serialized form, with an index of where each instruction starts.
+///
+/// The bytes are reference counted, so cloning a `RawCode` doesn't copy them.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RawCode {
+    /// The serialized code stream: the number of instructions followed by the instructions.
+    bytes: Bytes,
+    /// The position in `bytes` of every instruction, in order.
+    offsets: Vec<u32>,
+}
+
+impl RawCode {
+    /// Creates a `RawCode` from a code stream whose instructions have been checked to start at
+    /// `offsets`.
+    pub(crate) fn new(bytes: Bytes, offsets: Vec<u32>) -> Self {
+        RawCode { bytes, offsets }
+    }
+
+    /// Returns the number of instructions.
+    pub fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns true if there are no instructions.
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    /// Decodes the instruction at `offset`, or returns `None` if it is out of bounds.
+    pub fn get(&self, offset: CodeOffset) -> Option<Bytecode> {
+        self.offsets
+            .get(offset as usize)
+            .map(|position| self.decode_at(*position))
+    }
+
+    /// Returns an iterator decoding the instructions one at a time.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = Bytecode> + 'a {
+        self.offsets
+            .iter()
+            .map(move |position| self.decode_at(*position))
+    }
+
+    /// Decodes all the instructions.
+    pub fn decode(&self) -> Vec<Bytecode> {
+        self.iter().collect()
+    }
+
+    /// Returns the serialized code stream, as it appears in the code of a `FunctionDefinition`.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    fn decode_at(&self, position: u32) -> Bytecode {
+        let mut cursor = Cursor::new(self.bytes.as_ref());
+        cursor.set_position(u64::from(position));
+        load_instruction(&mut cursor).expect("instructions are checked when the code is indexed")
+    }
+}
+
+/// A `CodeUnit` whose code is kept in its serialized form.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RawCodeUnit {
+    /// Max stack size for the function - currently unused.
+    pub max_stack_size: u16,
+    /// List of locals type. All locals are typed.
+    pub locals: LocalsSignatureIndex,
+    /// Code stream, function body.
+    pub code: RawCode,
+}
+
+impl RawCodeUnit {
+    /// Decodes the code of this unit into a `CodeUnit`.
+    pub fn decode(&self) -> CodeUnit {
+        CodeUnit {
+            max_stack_size: self.max_stack_size,
+            locals: self.locals,
+            code: self.code.decode(),
+        }
+    }
+}
+
+/// A `FunctionDefinition` whose code is kept in its serialized form.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RawFunctionDefinition {
+    /// The prototype of the function (module, name, signature).
+    pub function: FunctionHandleIndex,
+    /// Flags for this function (private, public, native, etc.)
+    pub flags: u8,
+    /// List of nominal resources (declared in this module) that the procedure might access.
+    pub acquires_global_resources: Vec<StructDefinitionIndex>,
+    /// Code for this function.
+    pub code: RawCodeUnit,
+}
+
+impl RawFunctionDefinition {
+    /// Decodes the code of this definition into a `FunctionDefinition`.
+    pub fn decode(&self) -> FunctionDefinition {
+        FunctionDefinition {
+            function: self.function,
+            flags: self.flags,
+            acquires_global_resources: self.acquires_global_resources.clone(),
+            code: self.code.decode(),
+        }
+    }
+}
+
+/// A module whose function definitions keep their code in serialized form.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RawCodeModule {
+    /// Every table of the module except the function definitions, which are in `function_defs`.
+    /// Its own `function_defs` is always empty.
+    pub module: CompiledModuleMut,
+    /// The function definitions of the module, in order.
+    pub function_defs: Vec<RawFunctionDefinition>,
+}
+
+impl RawCodeModule {
+    /// Decodes the code of every function definition, returning the whole module.
+    pub fn decode(self) -> CompiledModuleMut {
+        let RawCodeModule {
+            mut module,
+            function_defs,
+        } = self;
+        module.function_defs = function_defs
+            .iter()
+            .map(RawFunctionDefinition::decode)
+            .collect();
+        module
+    }
+}
@@ -0,0 +1,514 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A reference interpreter for the code of a single module.
+//!
+//! The interpreter is meant as an oracle for testing, so it favors being obviously correct over
+//! being fast: values are plain Rust values, references are paths from a local to the value they
+//! point to, and every instruction is a direct translation of its semantics. It has no global
+//! state and no gas meter, and only calls functions defined in the module, so instructions
+//! accessing global storage, the gas remaining or other modules are reported as unsupported.
+//!
+//! The interpreter doesn't assume that the module was verified: ill-typed code is reported as an
+//! invalid instruction instead of causing a panic, so generated modules can be executed as is.
+
+use crate::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CodeOffset, CompiledModule, FieldDefinitionIndex, FunctionDefinitionIndex,
+        FunctionHandleIndex, LocalIndex, StructFieldInformation, TableIndex,
+    },
+    gas_schedule::GasAlgebra,
+    transaction_metadata::TransactionMetadata,
+};
+use std::mem;
+use types::{account_address::AccountAddress, byte_array::ByteArray};
+
+/// The number of instructions executed before execution is assumed not to terminate, by default.
+pub const DEFAULT_STEP_LIMIT: u64 = 1_000_000;
+
+/// A value of the reference interpreter.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    U64(u64),
+    String(String),
+    ByteArray(ByteArray),
+    Address(AccountAddress),
+    /// A struct, with its fields in declaration order.
+    Struct(Vec<Value>),
+    Reference(Reference),
+}
+
+/// A reference to a local of a frame, or to a field nested in it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Reference {
+    /// The frame of the local, identified by the call that pushed it, so that a reference
+    /// outliving its frame can't refer to a later frame.
+    frame: usize,
+    local: LocalIndex,
+    /// The positions of the fields leading from the local to the value referred to.
+    path: Vec<usize>,
+}
+
+/// Why an instruction failed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FailureReason {
+    /// Arithmetic overflowed, underflowed or divided by zero.
+    ArithmeticError,
+    /// The instruction is outside of what the interpreter models: global storage, gas, native
+    /// code or other modules.
+    Unsupported,
+    /// The instruction doesn't apply to its operands, which verified code rules out.
+    Invalid(&'static str),
+}
+
+/// Why executing a function didn't return.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExecutionError {
+    /// `Abort` was executed with this code.
+    Aborted(u64),
+    /// More instructions were executed than the step limit allows.
+    StepLimitExceeded,
+    /// An instruction failed.
+    Failed {
+        function: FunctionDefinitionIndex,
+        offset: CodeOffset,
+        reason: FailureReason,
+    },
+}
+
+/// Executes the functions of a module, see the module documentation.
+pub struct ReferenceInterpreter<'a> {
+    module: &'a CompiledModule,
+    metadata: TransactionMetadata,
+    step_limit: u64,
+}
+
+impl<'a> ReferenceInterpreter<'a> {
+    pub fn new(module: &'a CompiledModule) -> Self {
+        Self {
+            module,
+            metadata: TransactionMetadata::default(),
+            step_limit: DEFAULT_STEP_LIMIT,
+        }
+    }
+
+    /// Sets the metadata of the transaction the instructions querying it return.
+    pub fn with_metadata(mut self, metadata: TransactionMetadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Sets the number of instructions executed before execution is assumed not to terminate.
+    pub fn with_step_limit(mut self, step_limit: u64) -> Self {
+        self.step_limit = step_limit;
+        self
+    }
+
+    /// Executes `function` with `args`, and returns its return values.
+    pub fn execute(
+        &self,
+        function: FunctionDefinitionIndex,
+        args: Vec<Value>,
+    ) -> Result<Vec<Value>, ExecutionError> {
+        let mut execution = Execution {
+            interpreter: self,
+            frames: vec![],
+            next_frame_id: 0,
+            stack: args,
+        };
+        execution
+            .call(function)
+            .map_err(|fault| fault.at(function, 0))?;
+        execution.run()
+    }
+}
+
+struct Frame {
+    id: usize,
+    function: FunctionDefinitionIndex,
+    pc: CodeOffset,
+    locals: Vec<Option<Value>>,
+}
+
+/// How an instruction ended, before it is attributed to its location.
+enum Fault {
+    Aborted(u64),
+    Failed(FailureReason),
+}
+
+impl Fault {
+    fn at(self, function: FunctionDefinitionIndex, offset: CodeOffset) -> ExecutionError {
+        match self {
+            Fault::Aborted(code) => ExecutionError::Aborted(code),
+            Fault::Failed(reason) => ExecutionError::Failed {
+                function,
+                offset,
+                reason,
+            },
+        }
+    }
+}
+
+fn invalid(reason: &'static str) -> Fault {
+    Fault::Failed(FailureReason::Invalid(reason))
+}
+
+fn unsupported() -> Fault {
+    Fault::Failed(FailureReason::Unsupported)
+}
+
+fn arithmetic_error() -> Fault {
+    Fault::Failed(FailureReason::ArithmeticError)
+}
+
+struct Execution<'i, 'a> {
+    interpreter: &'i ReferenceInterpreter<'a>,
+    frames: Vec<Frame>,
+    next_frame_id: usize,
+    /// The operand stack, shared by all frames.
+    stack: Vec<Value>,
+}
+
+impl<'i, 'a> Execution<'i, 'a> {
+    fn run(&mut self) -> Result<Vec<Value>, ExecutionError> {
+        let module = self.interpreter.module;
+        let mut steps = 0;
+        loop {
+            steps += 1;
+            if steps > self.interpreter.step_limit {
+                return Err(ExecutionError::StepLimitExceeded);
+            }
+            let frame = self.frame_mut();
+            let (function, offset) = (frame.function, frame.pc);
+            frame.pc += 1;
+            let code = &module.function_def_at(function).code.code;
+            let result = match code.get(offset as usize) {
+                Some(instruction) => self.step(instruction),
+                None => Err(invalid("execution falls off the end of the code")),
+            };
+            match result {
+                Ok(()) => {
+                    if self.frames.is_empty() {
+                        return Ok(mem::replace(&mut self.stack, vec![]));
+                    }
+                }
+                Err(fault) => return Err(fault.at(function, offset)),
+            }
+        }
+    }
+
+    fn step(&mut self, instruction: &Bytecode) -> Result<(), Fault> {
+        let interpreter = self.interpreter;
+        let module = interpreter.module;
+        let metadata = &interpreter.metadata;
+        match instruction {
+            Bytecode::Pop => {
+                self.pop()?;
+            }
+            Bytecode::Ret => {
+                self.frames.pop();
+            }
+            Bytecode::BrTrue(offset) => {
+                if self.pop_bool()? {
+                    self.frame_mut().pc = *offset;
+                }
+            }
+            Bytecode::BrFalse(offset) => {
+                if !self.pop_bool()? {
+                    self.frame_mut().pc = *offset;
+                }
+            }
+            Bytecode::Branch(offset) => self.frame_mut().pc = *offset,
+            Bytecode::LdConst(value) => self.stack.push(Value::U64(*value)),
+            Bytecode::LdStr(idx) => self
+                .stack
+                .push(Value::String(module.string_at(*idx).to_string())),
+            Bytecode::LdByteArray(idx) => self
+                .stack
+                .push(Value::ByteArray(module.byte_array_at(*idx).clone())),
+            Bytecode::LdAddr(idx) => self.stack.push(Value::Address(*module.address_at(*idx))),
+            Bytecode::LdTrue => self.stack.push(Value::Bool(true)),
+            Bytecode::LdFalse => self.stack.push(Value::Bool(false)),
+            Bytecode::CopyLoc(idx) => {
+                let value = self.local_mut(*idx)?.clone();
+                self.stack.push(value);
+            }
+            Bytecode::MoveLoc(idx) => {
+                let value = self.local_slot(*idx)?.take();
+                self.stack
+                    .push(value.ok_or_else(|| invalid("local is unavailable"))?);
+            }
+            Bytecode::StLoc(idx) => {
+                let value = self.pop()?;
+                *self.local_slot(*idx)? = Some(value);
+            }
+            Bytecode::Call(idx, _) => {
+                let function = self.resolve_function(*idx)?;
+                self.call(function)?;
+            }
+            Bytecode::Pack(idx, _) => {
+                let field_count = match module.struct_def_at(*idx).field_information {
+                    StructFieldInformation::Native => return Err(unsupported()),
+                    StructFieldInformation::Declared { field_count, .. } => field_count as usize,
+                };
+                let fields = self.pop_n(field_count)?;
+                self.stack.push(Value::Struct(fields));
+            }
+            Bytecode::Unpack(_, _) => match self.pop()? {
+                Value::Struct(fields) => self.stack.extend(fields),
+                _ => return Err(invalid("unpacked a value that isn't a struct")),
+            },
+            Bytecode::ReadRef => {
+                let reference = self.pop_reference()?;
+                let value = self.borrow(&reference)?.clone();
+                self.stack.push(value);
+            }
+            Bytecode::WriteRef => {
+                let reference = self.pop_reference()?;
+                let value = self.pop()?;
+                *self.borrow(&reference)? = value;
+            }
+            Bytecode::FreezeRef => {
+                // Mutable and immutable references are the same at runtime.
+            }
+            Bytecode::MutBorrowLoc(idx) | Bytecode::ImmBorrowLoc(idx) => {
+                self.local_mut(*idx)?;
+                let reference = Reference {
+                    frame: self.frame_mut().id,
+                    local: *idx,
+                    path: vec![],
+                };
+                self.stack.push(Value::Reference(reference));
+            }
+            Bytecode::MutBorrowField(idx) | Bytecode::ImmBorrowField(idx) => {
+                let field = self.field_position(*idx)?;
+                let mut reference = self.pop_reference()?;
+                reference.path.push(field);
+                self.borrow(&reference)?;
+                self.stack.push(Value::Reference(reference));
+            }
+            Bytecode::Add => self.arithmetic(u64::checked_add)?,
+            Bytecode::Sub => self.arithmetic(u64::checked_sub)?,
+            Bytecode::Mul => self.arithmetic(u64::checked_mul)?,
+            Bytecode::Mod => self.arithmetic(u64::checked_rem)?,
+            Bytecode::Div => self.arithmetic(u64::checked_div)?,
+            Bytecode::BitOr => self.arithmetic(|lhs, rhs| Some(lhs | rhs))?,
+            Bytecode::BitAnd => self.arithmetic(|lhs, rhs| Some(lhs & rhs))?,
+            Bytecode::Xor => self.arithmetic(|lhs, rhs| Some(lhs ^ rhs))?,
+            Bytecode::Or => {
+                let rhs = self.pop_bool()?;
+                let lhs = self.pop_bool()?;
+                self.stack.push(Value::Bool(lhs || rhs));
+            }
+            Bytecode::And => {
+                let rhs = self.pop_bool()?;
+                let lhs = self.pop_bool()?;
+                self.stack.push(Value::Bool(lhs && rhs));
+            }
+            Bytecode::Not => {
+                let value = self.pop_bool()?;
+                self.stack.push(Value::Bool(!value));
+            }
+            Bytecode::Eq => {
+                let equal = self.pop_equal()?;
+                self.stack.push(Value::Bool(equal));
+            }
+            Bytecode::Neq => {
+                let equal = self.pop_equal()?;
+                self.stack.push(Value::Bool(!equal));
+            }
+            Bytecode::Lt => self.comparison(|lhs, rhs| lhs < rhs)?,
+            Bytecode::Gt => self.comparison(|lhs, rhs| lhs > rhs)?,
+            Bytecode::Le => self.comparison(|lhs, rhs| lhs <= rhs)?,
+            Bytecode::Ge => self.comparison(|lhs, rhs| lhs >= rhs)?,
+            Bytecode::Abort => return Err(Fault::Aborted(self.pop_u64()?)),
+            Bytecode::GetTxnGasUnitPrice => {
+                self.stack.push(Value::U64(metadata.gas_unit_price().get()))
+            }
+            Bytecode::GetTxnMaxGasUnits => {
+                self.stack.push(Value::U64(metadata.max_gas_amount().get()))
+            }
+            Bytecode::GetTxnSenderAddress => self.stack.push(Value::Address(metadata.sender())),
+            Bytecode::GetTxnSequenceNumber => {
+                self.stack.push(Value::U64(metadata.sequence_number()))
+            }
+            Bytecode::GetTxnPublicKey => self.stack.push(Value::ByteArray(ByteArray::new(
+                metadata.public_key().to_bytes().to_vec(),
+            ))),
+            Bytecode::GetGasRemaining
+            | Bytecode::BorrowGlobal(_, _)
+            | Bytecode::Exists(_, _)
+            | Bytecode::MoveFrom(_, _)
+            | Bytecode::MoveToSender(_, _)
+            | Bytecode::CreateAccount => return Err(unsupported()),
+        }
+        Ok(())
+    }
+
+    /// Pushes a frame for `function`, with its arguments popped from the operand stack.
+    fn call(&mut self, function: FunctionDefinitionIndex) -> Result<(), Fault> {
+        let module = self.interpreter.module;
+        let function_def = module.function_def_at(function);
+        if function_def.is_native() {
+            return Err(unsupported());
+        }
+        let function_handle = module.function_handle_at(function_def.function);
+        let arg_count = module
+            .function_signature_at(function_handle.signature)
+            .arg_types
+            .len();
+        let local_count = module.locals_signature_at(function_def.code.locals).0.len();
+        if local_count < arg_count {
+            return Err(invalid("function has fewer locals than arguments"));
+        }
+        let mut locals: Vec<_> = self.pop_n(arg_count)?.into_iter().map(Some).collect();
+        locals.resize(local_count, None);
+        self.frames.push(Frame {
+            id: self.next_frame_id,
+            function,
+            pc: 0,
+            locals,
+        });
+        self.next_frame_id += 1;
+        Ok(())
+    }
+
+    fn resolve_function(&self, idx: FunctionHandleIndex) -> Result<FunctionDefinitionIndex, Fault> {
+        let module = self.interpreter.module;
+        if !module.is_self_handle(module.function_handle_at(idx).module) {
+            return Err(unsupported());
+        }
+        module
+            .function_defs()
+            .iter()
+            .position(|function_def| function_def.function == idx)
+            .map(|position| FunctionDefinitionIndex::new(position as TableIndex))
+            .ok_or_else(|| invalid("called function has no definition"))
+    }
+
+    /// Returns the position of field `idx` among the fields of its struct.
+    fn field_position(&self, idx: FieldDefinitionIndex) -> Result<usize, Fault> {
+        self.interpreter
+            .module
+            .struct_defs()
+            .iter()
+            .find_map(|struct_def| match struct_def.field_information {
+                StructFieldInformation::Declared {
+                    field_count,
+                    fields,
+                } if fields.0 <= idx.0 && idx.0 - fields.0 < field_count => {
+                    Some((idx.0 - fields.0) as usize)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| invalid("field belongs to no struct"))
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .expect("instructions only execute in a frame")
+    }
+
+    fn local_slot(&mut self, idx: LocalIndex) -> Result<&mut Option<Value>, Fault> {
+        self.frame_mut()
+            .locals
+            .get_mut(idx as usize)
+            .ok_or_else(|| invalid("local doesn't exist"))
+    }
+
+    fn local_mut(&mut self, idx: LocalIndex) -> Result<&mut Value, Fault> {
+        self.local_slot(idx)?
+            .as_mut()
+            .ok_or_else(|| invalid("local is unavailable"))
+    }
+
+    /// Returns the value `reference` refers to.
+    fn borrow(&mut self, reference: &Reference) -> Result<&mut Value, Fault> {
+        let mut value = self
+            .frames
+            .iter_mut()
+            .find(|frame| frame.id == reference.frame)
+            .and_then(|frame| frame.locals.get_mut(reference.local as usize))
+            .and_then(Option::as_mut)
+            .ok_or_else(|| invalid("reference is dangling"))?;
+        for field in &reference.path {
+            value = match value {
+                Value::Struct(fields) => fields
+                    .get_mut(*field)
+                    .ok_or_else(|| invalid("borrowed field doesn't exist"))?,
+                _ => return Err(invalid("borrowed a field of a value that isn't a struct")),
+            };
+        }
+        Ok(value)
+    }
+
+    fn pop(&mut self) -> Result<Value, Fault> {
+        self.stack
+            .pop()
+            .ok_or_else(|| invalid("operand stack is empty"))
+    }
+
+    /// Pops the top `n` values, the deepest one first.
+    fn pop_n(&mut self, n: usize) -> Result<Vec<Value>, Fault> {
+        if self.stack.len() < n {
+            return Err(invalid("operand stack is too short"));
+        }
+        Ok(self.stack.split_off(self.stack.len() - n))
+    }
+
+    fn pop_bool(&mut self) -> Result<bool, Fault> {
+        match self.pop()? {
+            Value::Bool(value) => Ok(value),
+            _ => Err(invalid("expected a bool")),
+        }
+    }
+
+    fn pop_u64(&mut self) -> Result<u64, Fault> {
+        match self.pop()? {
+            Value::U64(value) => Ok(value),
+            _ => Err(invalid("expected a u64")),
+        }
+    }
+
+    fn pop_reference(&mut self) -> Result<Reference, Fault> {
+        match self.pop()? {
+            Value::Reference(reference) => Ok(reference),
+            _ => Err(invalid("expected a reference")),
+        }
+    }
+
+    /// Pops two values and returns whether they are equal. References are equal if the values
+    /// they refer to are.
+    fn pop_equal(&mut self) -> Result<bool, Fault> {
+        let rhs = self.pop()?;
+        let lhs = self.pop()?;
+        match (lhs, rhs) {
+            (Value::Reference(lhs), Value::Reference(rhs)) => {
+                let lhs = self.borrow(&lhs)?.clone();
+                Ok(lhs == *self.borrow(&rhs)?)
+            }
+            (Value::Reference(_), _) | (_, Value::Reference(_)) => {
+                Err(invalid("compared a reference to a value"))
+            }
+            (lhs, rhs) => Ok(lhs == rhs),
+        }
+    }
+
+    fn arithmetic(&mut self, op: impl FnOnce(u64, u64) -> Option<u64>) -> Result<(), Fault> {
+        let rhs = self.pop_u64()?;
+        let lhs = self.pop_u64()?;
+        let result = op(lhs, rhs).ok_or_else(arithmetic_error)?;
+        self.stack.push(Value::U64(result));
+        Ok(())
+    }
+
+    fn comparison(&mut self, op: impl FnOnce(u64, u64) -> bool) -> Result<(), Fault> {
+        let rhs = self.pop_u64()?;
+        let lhs = self.pop_u64()?;
+        self.stack.push(Value::Bool(op(lhs, rhs)));
+        Ok(())
+    }
+}
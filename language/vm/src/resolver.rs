@@ -8,16 +8,17 @@ use crate::{
     errors::VMStaticViolation,
     file_format::{
         AddressPoolIndex, FunctionSignature, ModuleHandle, ModuleHandleIndex, SignatureToken,
-        StringPoolIndex, StructHandle, StructHandleIndex,
+        SignatureTokens, StringPoolIndex, StructHandle, StructHandleIndex,
     },
+    identifier::Identifier,
 };
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use types::account_address::AccountAddress;
 
 /// Resolution context for importing types
 pub struct Resolver {
     address_map: BTreeMap<AccountAddress, AddressPoolIndex>,
-    string_map: BTreeMap<String, StringPoolIndex>,
+    string_map: HashMap<Identifier, StringPoolIndex>,
     module_handle_map: BTreeMap<ModuleHandle, ModuleHandleIndex>,
     struct_handle_map: BTreeMap<StructHandle, StructHandleIndex>,
 }
@@ -29,7 +30,7 @@ impl Resolver {
         for (idx, address) in module.address_pool().iter().enumerate() {
             address_map.insert(address.clone(), AddressPoolIndex(idx as u16));
         }
-        let mut string_map = BTreeMap::new();
+        let mut string_map = HashMap::new();
         for (idx, name) in module.string_pool().iter().enumerate() {
             string_map.insert(name.clone(), StringPoolIndex(idx as u16));
         }
@@ -67,7 +68,7 @@ impl Resolver {
                 let struct_handle = dependency.struct_handle_at(*sh_idx);
                 let defining_module_handle = dependency.module_handle_at(struct_handle.module);
                 let defining_module_address = dependency.address_at(defining_module_handle.address);
-                let defining_module_name = dependency.string_at(defining_module_handle.name);
+                let defining_module_name = dependency.identifier_at(defining_module_handle.name);
                 let local_module_handle = ModuleHandle {
                     address: *self
                         .address_map
@@ -78,7 +79,7 @@ impl Resolver {
                         .get(defining_module_name)
                         .ok_or(VMStaticViolation::TypeResolutionFailure)?,
                 };
-                let struct_name = dependency.string_at(struct_handle.name);
+                let struct_name = dependency.identifier_at(struct_handle.name);
                 let local_struct_handle = StructHandle {
                     module: *self
                         .module_handle_map
@@ -120,8 +121,8 @@ impl Resolver {
         dependency: &impl ModuleAccess,
         func_sig: &FunctionSignature,
     ) -> Result<FunctionSignature, VMStaticViolation> {
-        let mut return_types = Vec::<SignatureToken>::new();
-        let mut arg_types = Vec::<SignatureToken>::new();
+        let mut return_types = SignatureTokens::new();
+        let mut arg_types = SignatureTokens::new();
         for e in &func_sig.return_types {
             return_types.push(self.import_signature_token(dependency, e)?);
         }
@@ -0,0 +1,131 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Formats verification errors as [SARIF](https://sarifweb.azurewebsites.net/) (Static Analysis
+//! Results Interchange Format) logs, so that CI systems and code review tools can display
+//! bytecode verification findings natively.
+//!
+//! Bytecode has no source text to point into, so results are reported with logical locations
+//! only: the fully qualified name of the module, struct, field or function the error occurred in.
+//! The code offset of code-level errors is recorded in the properties of the result.
+
+use crate::{
+    access::ModuleAccess,
+    errors::{resolve_location, short_address, Severity, VerificationError},
+    views::ModuleView,
+};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+const TOOL_NAME: &str = "bytecode-verifier";
+
+/// Accumulates the verification errors of one or more modules into a single SARIF log.
+#[derive(Debug, Default)]
+pub struct SarifLog {
+    results: Vec<Value>,
+    // Rules are keyed by error code, so that they are listed in a deterministic order.
+    rules: BTreeMap<u32, Value>,
+}
+
+impl SarifLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the errors that were reported for `module`.
+    pub fn add_module<T: ModuleAccess>(&mut self, module: &T, errors: &[VerificationError]) {
+        let view = ModuleView::new(module);
+        let module_id = view.id();
+        let module_name = format!(
+            "{}::{}",
+            short_address(module_id.address()),
+            module_id.name()
+        );
+        for error in errors {
+            self.rules.entry(error.code()).or_insert_with(|| {
+                json!({
+                    "id": rule_id(error),
+                    "properties": { "category": error.category().to_string() },
+                })
+            });
+
+            let mut logical_locations = vec![json!({
+                "kind": "module",
+                "fullyQualifiedName": module_name,
+            })];
+            if let Some((kind, name)) = resolve_location(error, &view) {
+                logical_locations.push(json!({
+                    "kind": sarif_location_kind(kind),
+                    "fullyQualifiedName": name,
+                }));
+            }
+
+            let mut properties = json!({
+                "indexKind": error.kind.to_string(),
+                "index": error.idx,
+            });
+            if let Some(code_offset) = error.code_offset {
+                properties["codeOffset"] = json!(code_offset);
+            }
+            if !error.context.is_empty() {
+                properties["context"] = json!(error.context);
+            }
+
+            self.results.push(json!({
+                "ruleId": rule_id(error),
+                "level": sarif_level(error.severity()),
+                "message": { "text": error.display_with(&view).to_string() },
+                "locations": [{ "logicalLocations": logical_locations }],
+                "properties": properties,
+            }));
+        }
+    }
+
+    /// Returns the SARIF log as a JSON value.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": TOOL_NAME,
+                        "rules": self.rules.values().collect::<Vec<_>>(),
+                    },
+                },
+                "results": self.results,
+            }],
+        })
+    }
+}
+
+/// Formats the errors reported for a single module as a SARIF log.
+pub fn to_sarif<T: ModuleAccess>(module: &T, errors: &[VerificationError]) -> Value {
+    let mut log = SarifLog::new();
+    log.add_module(module, errors);
+    log.to_json()
+}
+
+fn rule_id(error: &VerificationError) -> String {
+    format!("V{}", error.code())
+}
+
+fn sarif_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+    }
+}
+
+/// Maps the kinds returned by `resolve_location` to SARIF logical location kinds.
+fn sarif_location_kind(kind: &str) -> &'static str {
+    match kind {
+        "function" | "function handle" => "function",
+        "struct" | "struct handle" => "type",
+        "field" => "member",
+        _ => "module",
+    }
+}
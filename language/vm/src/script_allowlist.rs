@@ -0,0 +1,116 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines script hashes and allowlists of them, for networks that only execute a known set of
+//! scripts.
+//!
+//! A script is identified by the SHA3-256 hash of its serialized form, the same hash the VM
+//! computes over the script bytes of a transaction when the publishing option is locked. An
+//! allowlist serializes as a sorted list of hex-encoded hashes, the format used for the whitelist
+//! in node configs.
+
+use crate::file_format::CompiledScript;
+use crypto::HashValue;
+use failure::prelude::*;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::BTreeSet, iter::FromIterator};
+
+impl CompiledScript {
+    /// Returns the hash that identifies this script: the SHA3-256 hash of its serialized form.
+    pub fn hash(&self) -> Result<HashValue> {
+        let mut binary = vec![];
+        self.serialize(&mut binary)?;
+        Ok(script_binary_hash(&binary))
+    }
+}
+
+/// Returns the hash that identifies a script binary, as sent in a transaction.
+pub fn script_binary_hash(binary: &[u8]) -> HashValue {
+    HashValue::from_sha3_256(binary)
+}
+
+/// A set of script hashes that are allowed to run.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ScriptAllowlist {
+    hashes: BTreeSet<HashValue>,
+}
+
+impl ScriptAllowlist {
+    /// Returns an empty allowlist, which allows no script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `hash` to the allowlist, returning false if it was already in it.
+    pub fn insert(&mut self, hash: HashValue) -> bool {
+        self.hashes.insert(hash)
+    }
+
+    /// Adds the hash of `script` to the allowlist, and returns it.
+    pub fn insert_script(&mut self, script: &CompiledScript) -> Result<HashValue> {
+        let hash = script.hash()?;
+        self.hashes.insert(hash);
+        Ok(hash)
+    }
+
+    /// Removes `hash` from the allowlist, returning false if it wasn't in it.
+    pub fn remove(&mut self, hash: &HashValue) -> bool {
+        self.hashes.remove(hash)
+    }
+
+    pub fn contains(&self, hash: &HashValue) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    /// Returns true if the script binary `binary` is allowed to run. The binary is hashed as is,
+    /// so it must be the exact bytes the hash was computed over.
+    pub fn allows_binary(&self, binary: &[u8]) -> bool {
+        self.contains(&script_binary_hash(binary))
+    }
+
+    /// Returns true if `script` is allowed to run.
+    pub fn allows(&self, script: &CompiledScript) -> Result<bool> {
+        Ok(self.contains(&script.hash()?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// Returns the hashes in the allowlist, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = &HashValue> {
+        self.hashes.iter()
+    }
+}
+
+impl FromIterator<HashValue> for ScriptAllowlist {
+    fn from_iter<I: IntoIterator<Item = HashValue>>(iter: I) -> Self {
+        Self {
+            hashes: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Serialize for ScriptAllowlist {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let hashes: Vec<_> = self.iter().map(|hash| format!("{:x}", hash)).collect();
+        hashes.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ScriptAllowlist {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let hashes: Vec<String> = Deserialize::deserialize(deserializer)?;
+        hashes
+            .iter()
+            .map(|hash| {
+                let bytes = hex::decode(hash).map_err(de::Error::custom)?;
+                HashValue::from_slice(&bytes).map_err(de::Error::custom)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Checks of transaction arguments against the signature of the script they are passed to.
+//!
+//! The VM rejects a transaction whose arguments don't match the main function of its script only
+//! once it executes it. `check_arguments` performs the same checks ahead of time, along with a
+//! limit on the length of byte arrays, and reports every mismatch, so that clients and mempools
+//! can turn malformed transactions away with a precise reason.
+
+use crate::{access::ScriptAccess, file_format::SignatureToken, views::ScriptView};
+use failure::Fail;
+use types::transaction::TransactionArgument;
+
+/// The longest byte array that can be passed as an argument, the same limit as for the byte
+/// arrays of a binary.
+pub const MAX_BYTE_ARRAY_ARGUMENT_LENGTH: usize = std::u16::MAX as usize;
+
+/// A way in which transaction arguments don't match the script they are passed to. Arguments are
+/// numbered from 0.
+#[derive(Clone, Debug, Eq, Fail, PartialEq)]
+pub enum ArgumentError {
+    #[fail(
+        display = "the script takes {} arguments but {} were given",
+        expected, actual
+    )]
+    WrongCount { expected: usize, actual: usize },
+    #[fail(
+        display = "argument {} has type {:?} but the script expects {:?}",
+        index, actual, expected
+    )]
+    TypeMismatch {
+        index: usize,
+        expected: SignatureToken,
+        actual: SignatureToken,
+    },
+    #[fail(
+        display = "argument {} is a byte array of {} bytes, more than the limit of {}",
+        index, length, MAX_BYTE_ARRAY_ARGUMENT_LENGTH
+    )]
+    ByteArrayTooLong { index: usize, length: usize },
+}
+
+/// Returns the type of `argument`, as it appears in signatures.
+pub fn argument_type(argument: &TransactionArgument) -> SignatureToken {
+    match argument {
+        TransactionArgument::U64(_) => SignatureToken::U64,
+        TransactionArgument::Address(_) => SignatureToken::Address,
+        TransactionArgument::ByteArray(_) => SignatureToken::ByteArray,
+        TransactionArgument::String(_) => SignatureToken::String,
+    }
+}
+
+/// Checks that `arguments` can be passed to the main function of `script`, returning every
+/// mismatch otherwise. If the number of arguments is wrong, that is the only mismatch reported.
+pub fn check_arguments<T: ScriptAccess>(
+    script: &ScriptView<T>,
+    arguments: &[TransactionArgument],
+) -> Result<(), Vec<ArgumentError>> {
+    if script.arg_count() != arguments.len() {
+        return Err(vec![ArgumentError::WrongCount {
+            expected: script.arg_count(),
+            actual: arguments.len(),
+        }]);
+    }
+    let mut errors = vec![];
+    for (index, (expected, argument)) in script.arg_tokens().zip(arguments).enumerate() {
+        let actual = argument_type(argument);
+        if &actual != expected {
+            errors.push(ArgumentError::TypeMismatch {
+                index,
+                expected: expected.clone(),
+                actual,
+            });
+        } else if let TransactionArgument::ByteArray(byte_array) = argument {
+            if byte_array.len() > MAX_BYTE_ARRAY_ARGUMENT_LENGTH {
+                errors.push(ArgumentError::ByteArrayTooLong {
+                    index,
+                    length: byte_array.len(),
+                });
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
@@ -7,9 +7,15 @@
 //! `CompiledModule`. The entry points are exposed on the main structs `CompiledScript` and
 //! `CompiledModule`.
 
-use crate::{file_format::*, file_format_common::*};
+use crate::{
+    file_format::*,
+    file_format_common::*,
+    identifier::Identifier,
+    raw_code::{RawCode, RawCodeUnit},
+};
+use bytes::Bytes;
 use failure::*;
-use std::ops::Deref;
+use std::{mem, ops::Deref};
 use types::{account_address::AccountAddress, byte_array::ByteArray};
 
 impl CompiledScript {
@@ -62,6 +68,98 @@ impl CompiledModuleMut {
     }
 }
 
+/// The bytes each part of a serialized module takes, as written by `CompiledModule::serialize`.
+///
+/// The parts add up to the length of the binary. Empty tables aren't written and take no bytes.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SerializedSizeBreakdown {
+    /// The magic, the version, and the header of every table written.
+    pub header: usize,
+    pub module_handles: usize,
+    pub struct_handles: usize,
+    pub function_handles: usize,
+    pub type_signatures: usize,
+    pub function_signatures: usize,
+    pub locals_signatures: usize,
+    pub string_pool: usize,
+    pub address_pool: usize,
+    pub byte_array_pool: usize,
+    pub struct_defs: usize,
+    pub field_defs: usize,
+    /// The function definitions, not counting their code.
+    pub function_defs: usize,
+    /// The code units of the function definitions: their stack size, locals and bytecode.
+    pub code: usize,
+}
+
+impl SerializedSizeBreakdown {
+    /// Returns the length of the binary.
+    pub fn total(&self) -> usize {
+        self.header
+            + self.module_handles
+            + self.struct_handles
+            + self.function_handles
+            + self.type_signatures
+            + self.function_signatures
+            + self.locals_signatures
+            + self.string_pool
+            + self.address_pool
+            + self.byte_array_pool
+            + self.struct_defs
+            + self.field_defs
+            + self.function_defs
+            + self.code
+    }
+}
+
+/// Returns the bytes each table of `module` takes once serialized, from a single serialization.
+pub fn serialized_size_breakdown(module: &CompiledModule) -> Result<SerializedSizeBreakdown> {
+    let mut ser = ModuleSerializer::new(1, 0);
+    ser.serialize(&mut BinaryData::new(), module.as_inner())?;
+    let mut header = BinaryData::new();
+    ser.serialize_header(&mut header)?;
+    let common = &ser.common;
+    Ok(SerializedSizeBreakdown {
+        header: header.len(),
+        module_handles: common.module_handles.1 as usize,
+        struct_handles: common.struct_handles.1 as usize,
+        function_handles: common.function_handles.1 as usize,
+        type_signatures: common.type_signatures.1 as usize,
+        function_signatures: common.function_signatures.1 as usize,
+        locals_signatures: common.locals_signatures.1 as usize,
+        string_pool: common.string_pool.1 as usize,
+        address_pool: common.address_pool.1 as usize,
+        byte_array_pool: common.byte_array_pool.1 as usize,
+        struct_defs: ser.struct_defs.1 as usize,
+        field_defs: ser.field_defs.1 as usize,
+        function_defs: (ser.function_defs.1 - ser.code_size) as usize,
+        code: ser.code_size as usize,
+    })
+}
+
+impl RawCode {
+    /// Serializes `code` into a `RawCode`.
+    pub fn serialize(code: &[Bytecode]) -> Result<Self> {
+        let mut binary = BinaryData::new();
+        let mut offsets = Vec::with_capacity(code.len());
+        serialize_code_with(&mut binary, code, |position| offsets.push(position as u32))?;
+        Ok(RawCode::new(Bytes::from(binary.into_inner()), offsets))
+    }
+}
+
+impl RawCodeUnit {
+    /// Serializes this the same way as the `CodeUnit` it decodes to. The code stream is copied
+    /// without being decoded.
+    pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
+        let mut binary_data = BinaryData::from(mem::replace(binary, vec![]));
+        write_u16_as_uleb128(&mut binary_data, self.max_stack_size)?;
+        write_u16_as_uleb128(&mut binary_data, self.locals.0)?;
+        binary_data.extend(self.code.as_bytes())?;
+        *binary = binary_data.into_inner();
+        Ok(())
+    }
+}
+
 /// Holds data to compute the header of a generic binary.
 ///
 /// A binary header contains information about the tables serialized.
@@ -92,6 +190,8 @@ struct ModuleSerializer {
     struct_defs: (u32, u32),
     field_defs: (u32, u32),
     function_defs: (u32, u32),
+    /// The bytes taken by code units within the function definitions.
+    code_size: u32,
 }
 
 /// Holds data to compute the header of a transaction script binary.
@@ -161,7 +261,7 @@ trait CommonTables {
     fn get_module_handles(&self) -> &[ModuleHandle];
     fn get_struct_handles(&self) -> &[StructHandle];
     fn get_function_handles(&self) -> &[FunctionHandle];
-    fn get_string_pool(&self) -> &[String];
+    fn get_string_pool(&self) -> &[Identifier];
     fn get_address_pool(&self) -> &[AccountAddress];
     fn get_byte_array_pool(&self) -> &[ByteArray];
     fn get_type_signatures(&self) -> &[TypeSignature];
@@ -182,7 +282,7 @@ impl CommonTables for CompiledScriptMut {
         &self.function_handles
     }
 
-    fn get_string_pool(&self) -> &[String] {
+    fn get_string_pool(&self) -> &[Identifier] {
         &self.string_pool
     }
 
@@ -220,7 +320,7 @@ impl CommonTables for CompiledModuleMut {
         &self.function_handles
     }
 
-    fn get_string_pool(&self) -> &[String] {
+    fn get_string_pool(&self) -> &[Identifier] {
         &self.string_pool
     }
 
@@ -390,11 +490,19 @@ fn serialize_field_definition(
 fn serialize_function_definition(
     binary: &mut BinaryData,
     function_definition: &FunctionDefinition,
+) -> Result<()> {
+    serialize_function_definition_prefix(binary, function_definition)?;
+    serialize_code_unit(binary, &function_definition.code)
+}
+
+/// Serializes the part of a `FunctionDefinition` before its `CodeUnit`.
+fn serialize_function_definition_prefix(
+    binary: &mut BinaryData,
+    function_definition: &FunctionDefinition,
 ) -> Result<()> {
     write_u16_as_uleb128(binary, function_definition.function.0)?;
     binary.push(function_definition.flags)?;
-    serialize_struct_definition_indices(binary, &function_definition.acquires_global_resources)?;
-    serialize_code_unit(binary, &function_definition.code)
+    serialize_struct_definition_indices(binary, &function_definition.acquires_global_resources)
 }
 
 /// Serializes a `Vec<StructDefinitionIndex>`.
@@ -685,6 +793,17 @@ fn serialize_instruction_inner(binary: &mut BinaryData, opcode: &Bytecode) -> Re
 
 /// Serializes a `Bytecode` stream. Serialization of the function body.
 fn serialize_code(binary: &mut BinaryData, code: &[Bytecode]) -> Result<()> {
+    serialize_code_with(binary, code, |_| ())
+}
+
+/// Serializes a `Bytecode` stream, calling `on_instruction` with the position of every
+/// instruction relative to the start of the stream.
+fn serialize_code_with(
+    binary: &mut BinaryData,
+    code: &[Bytecode],
+    mut on_instruction: impl FnMut(usize),
+) -> Result<()> {
+    let start = binary.len();
     let code_size = code.len();
     if code_size > u16::max_value() as usize {
         bail!(
@@ -695,6 +814,7 @@ fn serialize_code(binary: &mut BinaryData, code: &[Bytecode]) -> Result<()> {
     }
     write_u16(binary, code_size as u16)?;
     for opcode in code {
+        on_instruction(binary.len() - start);
         serialize_instruction_inner(binary, opcode)?;
     }
     Ok(())
@@ -889,7 +1009,7 @@ impl CommonSerializer {
     }
 
     /// Serializes `StringPool`.
-    fn serialize_strings(&mut self, binary: &mut BinaryData, strings: &[String]) -> Result<()> {
+    fn serialize_strings(&mut self, binary: &mut BinaryData, strings: &[Identifier]) -> Result<()> {
         if !strings.is_empty() {
             self.table_count += 1;
             self.string_pool.0 = check_index_in_binary(binary.len())?;
@@ -996,6 +1116,7 @@ impl ModuleSerializer {
             struct_defs: (0, 0),
             field_defs: (0, 0),
             function_defs: (0, 0),
+            code_size: 0,
         }
     }
 
@@ -1076,7 +1197,10 @@ impl ModuleSerializer {
             self.common.table_count += 1;
             self.function_defs.0 = check_index_in_binary(binary.len())?;
             for function_definition in function_definitions {
-                serialize_function_definition(binary, function_definition)?;
+                serialize_function_definition_prefix(binary, function_definition)?;
+                let code_start = check_index_in_binary(binary.len())?;
+                serialize_code_unit(binary, &function_definition.code)?;
+                self.code_size += checked_calculate_table_size(binary, code_start)?;
             }
             self.function_defs.1 = checked_calculate_table_size(binary, self.function_defs.0)?;
         }
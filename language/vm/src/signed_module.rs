@@ -0,0 +1,103 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines signed modules, which attest who produced a module binary.
+//!
+//! A `SignedModule` carries a module binary along with the public key of its signer and a
+//! signature over the hash of the binary. The signature is detached from the module itself, so
+//! the binary that is published is the very one that was signed, and it can be checked by
+//! anyone holding the envelope without trusting the channel it came through.
+
+use crate::file_format::CompiledModule;
+use crypto::{
+    ed25519::*,
+    hash::{CryptoHasher, ModuleHasher},
+    traits::*,
+    HashValue,
+};
+use failure::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Returns the hash of a module binary that a `SignedModule` signs.
+pub fn module_hash(binary: &[u8]) -> HashValue {
+    let mut hasher = ModuleHasher::default();
+    hasher.write(binary);
+    hasher.finish()
+}
+
+/// A module binary signed by the holder of `public_key`.
+///
+/// The signature isn't checked when a `SignedModule` is deserialized: use `verify` or
+/// `verified_module` before trusting the binary.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SignedModule {
+    binary: Vec<u8>,
+    public_key: Ed25519PublicKey,
+    signature: Ed25519Signature,
+}
+
+impl SignedModule {
+    /// Assembles an envelope from a binary and a signature produced separately. The signature
+    /// isn't checked.
+    pub fn new(binary: Vec<u8>, public_key: Ed25519PublicKey, signature: Ed25519Signature) -> Self {
+        SignedModule {
+            binary,
+            public_key,
+            signature,
+        }
+    }
+
+    /// Signs `binary` with `private_key`, whose public key is `public_key`.
+    pub fn sign(
+        binary: Vec<u8>,
+        private_key: &Ed25519PrivateKey,
+        public_key: Ed25519PublicKey,
+    ) -> Self {
+        let signature = private_key.sign_message(&module_hash(&binary));
+        Self::new(binary, public_key, signature)
+    }
+
+    /// Serializes `module` and signs the result.
+    pub fn sign_module(
+        module: &CompiledModule,
+        private_key: &Ed25519PrivateKey,
+        public_key: Ed25519PublicKey,
+    ) -> Result<Self> {
+        let mut binary = vec![];
+        module.serialize(&mut binary)?;
+        Ok(Self::sign(binary, private_key, public_key))
+    }
+
+    /// Returns the signed module binary.
+    pub fn binary(&self) -> &[u8] {
+        &self.binary
+    }
+
+    /// Returns the public key of the signer.
+    pub fn public_key(&self) -> &Ed25519PublicKey {
+        &self.public_key
+    }
+
+    /// Returns the signature over the hash of the binary.
+    pub fn signature(&self) -> &Ed25519Signature {
+        &self.signature
+    }
+
+    /// Checks that the signature is valid for the binary and the public key.
+    pub fn verify(&self) -> Result<()> {
+        self.public_key
+            .verify_signature(&module_hash(&self.binary), &self.signature)
+    }
+
+    /// Checks the signature, then deserializes the binary.
+    pub fn verified_module(&self) -> Result<CompiledModule> {
+        self.verify()?;
+        CompiledModule::deserialize(&self.binary)
+            .map_err(|err| format_err!("invalid signed module: {:?}", err))
+    }
+
+    /// Returns the binary, without checking the signature.
+    pub fn into_binary(self) -> Vec<u8> {
+        self.binary
+    }
+}
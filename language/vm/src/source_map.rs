@@ -0,0 +1,332 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Defines source maps, which map the definitions and instructions of a compiled module back to
+//! the source they were compiled from, along with the source names of locals, so that printers
+//! and error formatters can point at source rather than at bytecode.
+//!
+//! A source map is produced by the compiler alongside a module and stored next to it, so it has
+//! a JSON form for tools and a compact binary form of its own, which doesn't change the binary
+//! format of modules.
+
+use crate::{
+    file_format::{CodeOffset, FunctionDefinitionIndex, LocalIndex, StructDefinitionIndex},
+    file_format_common::{
+        read_uleb128_as_u16, read_uleb128_as_u32, write_u16_as_uleb128, write_u32_as_uleb128,
+        BinaryData,
+    },
+};
+use byteorder::ReadBytesExt;
+use failure::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{Cursor, Read},
+};
+
+/// The first bytes of a source map in binary form.
+const MAGIC: &[u8] = b"MVSRCMAP";
+/// The version of the binary form written by `SourceMap::serialize`.
+const VERSION: u8 = 1;
+
+/// A position in a source file.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub struct SourceLocation {
+    /// The index of the file in the files of the source map.
+    pub file: u32,
+    /// The line, starting at 1.
+    pub line: u32,
+    /// The column, starting at 1.
+    pub column: u32,
+}
+
+/// The source of a function definition.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct FunctionSourceMap {
+    /// Where the function is defined.
+    pub location: Option<SourceLocation>,
+    /// Where the instructions starting at each offset come from. An instruction with no entry
+    /// comes from the same place as the closest instruction before it that has one.
+    pub code: BTreeMap<CodeOffset, SourceLocation>,
+    /// The source names of the locals, arguments first.
+    pub local_names: BTreeMap<LocalIndex, String>,
+}
+
+/// The source of a struct definition.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct StructSourceMap {
+    /// Where the struct is defined.
+    pub location: Option<SourceLocation>,
+    /// The source names of the type formals of the struct.
+    pub type_formal_names: Vec<String>,
+}
+
+/// Maps the definitions of a module to their source.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct SourceMap {
+    files: Vec<String>,
+    functions: BTreeMap<FunctionDefinitionIndex, FunctionSourceMap>,
+    structs: BTreeMap<StructDefinitionIndex, StructSourceMap>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the index of the file at `path`, adding it if needed.
+    pub fn add_file(&mut self, path: &str) -> u32 {
+        let position = match self.files.iter().position(|file| file == path) {
+            Some(position) => position,
+            None => {
+                self.files.push(path.to_string());
+                self.files.len() - 1
+            }
+        };
+        position as u32
+    }
+
+    /// Returns the path of the file with index `file`.
+    pub fn file(&self, file: u32) -> Option<&str> {
+        self.files.get(file as usize).map(String::as_str)
+    }
+
+    /// Returns the source of function `idx`, adding an empty one if needed.
+    pub fn function_mut(&mut self, idx: FunctionDefinitionIndex) -> &mut FunctionSourceMap {
+        self.functions.entry(idx).or_insert_with(Default::default)
+    }
+
+    /// Returns the source of struct `idx`, adding an empty one if needed.
+    pub fn struct_mut(&mut self, idx: StructDefinitionIndex) -> &mut StructSourceMap {
+        self.structs.entry(idx).or_insert_with(Default::default)
+    }
+
+    pub fn function(&self, idx: FunctionDefinitionIndex) -> Option<&FunctionSourceMap> {
+        self.functions.get(&idx)
+    }
+
+    pub fn struct_(&self, idx: StructDefinitionIndex) -> Option<&StructSourceMap> {
+        self.structs.get(&idx)
+    }
+
+    /// Returns where the instruction at `offset` of function `idx` comes from.
+    pub fn code_location(
+        &self,
+        idx: FunctionDefinitionIndex,
+        offset: CodeOffset,
+    ) -> Option<SourceLocation> {
+        let function = self.function(idx)?;
+        function
+            .code
+            .range(..=offset)
+            .next_back()
+            .map(|(_, location)| *location)
+            .or(function.location)
+    }
+
+    /// Returns the source name of local `local` of function `idx`.
+    pub fn local_name(&self, idx: FunctionDefinitionIndex, local: LocalIndex) -> Option<&str> {
+        self.function(idx)?
+            .local_names
+            .get(&local)
+            .map(String::as_str)
+    }
+
+    /// Returns a value that displays `location` as `path:line:column`.
+    pub fn display_location(&self, location: SourceLocation) -> SourceLocationDisplay {
+        SourceLocationDisplay {
+            path: self.file(location.file),
+            location,
+        }
+    }
+
+    /// Serializes the map to JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parses a map serialized with `to_json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let map: Self = serde_json::from_str(json)?;
+        map.check_files()?;
+        Ok(map)
+    }
+
+    /// Serializes the map to its binary form.
+    pub fn serialize(&self, binary: &mut Vec<u8>) -> Result<()> {
+        let mut data = BinaryData::new();
+        data.extend(MAGIC)?;
+        data.push(VERSION)?;
+        write_len(&mut data, self.files.len())?;
+        for file in &self.files {
+            write_string(&mut data, file)?;
+        }
+        write_len(&mut data, self.functions.len())?;
+        for (idx, function) in &self.functions {
+            write_u16_as_uleb128(&mut data, idx.0)?;
+            write_optional_location(&mut data, function.location)?;
+            write_len(&mut data, function.code.len())?;
+            for (offset, location) in &function.code {
+                write_u16_as_uleb128(&mut data, *offset)?;
+                write_location(&mut data, *location)?;
+            }
+            write_len(&mut data, function.local_names.len())?;
+            for (local, name) in &function.local_names {
+                data.push(*local)?;
+                write_string(&mut data, name)?;
+            }
+        }
+        write_len(&mut data, self.structs.len())?;
+        for (idx, struct_) in &self.structs {
+            write_u16_as_uleb128(&mut data, idx.0)?;
+            write_optional_location(&mut data, struct_.location)?;
+            write_len(&mut data, struct_.type_formal_names.len())?;
+            for name in &struct_.type_formal_names {
+                write_string(&mut data, name)?;
+            }
+        }
+        binary.extend(data.into_inner());
+        Ok(())
+    }
+
+    /// Parses a map serialized with `serialize`.
+    pub fn deserialize(binary: &[u8]) -> Result<Self> {
+        let mut cursor = Cursor::new(binary);
+        let mut magic = [0u8; 8];
+        cursor.read_exact(&mut magic)?;
+        ensure!(&magic[..] == MAGIC, "bad source map magic");
+        let version = cursor.read_u8()?;
+        ensure!(
+            version == VERSION,
+            "unsupported source map version {}",
+            version
+        );
+
+        let mut map = Self::new();
+        for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+            map.files.push(read_string(&mut cursor)?);
+        }
+        for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+            let idx = FunctionDefinitionIndex::new(read_uleb128_as_u16(&mut cursor)?);
+            let mut function = FunctionSourceMap::default();
+            function.location = read_optional_location(&mut cursor)?;
+            for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+                let offset = read_uleb128_as_u16(&mut cursor)?;
+                function.code.insert(offset, read_location(&mut cursor)?);
+            }
+            for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+                let local = cursor.read_u8()?;
+                function
+                    .local_names
+                    .insert(local, read_string(&mut cursor)?);
+            }
+            map.functions.insert(idx, function);
+        }
+        for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+            let idx = StructDefinitionIndex::new(read_uleb128_as_u16(&mut cursor)?);
+            let mut struct_ = StructSourceMap::default();
+            struct_.location = read_optional_location(&mut cursor)?;
+            for _ in 0..read_uleb128_as_u32(&mut cursor)? {
+                struct_.type_formal_names.push(read_string(&mut cursor)?);
+            }
+            map.structs.insert(idx, struct_);
+        }
+        ensure!(
+            cursor.position() as usize == binary.len(),
+            "trailing bytes after source map"
+        );
+        map.check_files()?;
+        Ok(map)
+    }
+
+    /// Checks that every location refers to a file of the map.
+    fn check_files(&self) -> Result<()> {
+        let locations = self
+            .functions
+            .values()
+            .flat_map(|function| function.location.iter().chain(function.code.values()))
+            .chain(self.structs.values().flat_map(|struct_| &struct_.location));
+        for location in locations {
+            ensure!(
+                (location.file as usize) < self.files.len(),
+                "source location refers to unknown file {}",
+                location.file
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Displays a source location. See `SourceMap::display_location`.
+pub struct SourceLocationDisplay<'a> {
+    path: Option<&'a str>,
+    location: SourceLocation,
+}
+
+impl<'a> fmt::Display for SourceLocationDisplay<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.path {
+            Some(path) => write!(f, "{}", path)?,
+            None => write!(f, "<file {}>", self.location.file)?,
+        }
+        write!(f, ":{}:{}", self.location.line, self.location.column)
+    }
+}
+
+fn write_len(data: &mut BinaryData, len: usize) -> Result<()> {
+    ensure!(
+        len <= u32::max_value() as usize,
+        "source map table too long"
+    );
+    write_u32_as_uleb128(data, len as u32)
+}
+
+fn write_string(data: &mut BinaryData, string: &str) -> Result<()> {
+    write_len(data, string.len())?;
+    data.extend(string.as_bytes())
+}
+
+fn write_location(data: &mut BinaryData, location: SourceLocation) -> Result<()> {
+    write_u32_as_uleb128(data, location.file)?;
+    write_u32_as_uleb128(data, location.line)?;
+    write_u32_as_uleb128(data, location.column)
+}
+
+fn write_optional_location(data: &mut BinaryData, location: Option<SourceLocation>) -> Result<()> {
+    match location {
+        Some(location) => {
+            data.push(1)?;
+            write_location(data, location)
+        }
+        None => data.push(0),
+    }
+}
+
+fn read_string(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+    let len = read_uleb128_as_u32(cursor)? as usize;
+    let remaining = cursor.get_ref().len() - cursor.position() as usize;
+    ensure!(
+        len <= remaining,
+        "source map string past the end of the binary"
+    );
+    let mut bytes = vec![0u8; len];
+    cursor.read_exact(&mut bytes)?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+fn read_location(cursor: &mut Cursor<&[u8]>) -> Result<SourceLocation> {
+    Ok(SourceLocation {
+        file: read_uleb128_as_u32(cursor)?,
+        line: read_uleb128_as_u32(cursor)?,
+        column: read_uleb128_as_u32(cursor)?,
+    })
+}
+
+fn read_optional_location(cursor: &mut Cursor<&[u8]>) -> Result<Option<SourceLocation>> {
+    match cursor.read_u8()? {
+        0 => Ok(None),
+        1 => Ok(Some(read_location(cursor)?)),
+        tag => bail!("bad source location tag {}", tag),
+    }
+}
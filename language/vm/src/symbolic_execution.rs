@@ -0,0 +1,576 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A bounded symbolic execution engine for the code of a single function, as a foundation for
+//! lightweight bug finding. It is opt-in: the verifier never runs it, and it is only built with
+//! the `symbolic-execution` feature.
+//!
+//! The engine explores the paths through a function, up to a bound on the instructions per path
+//! and on the number of paths. The `u64` and `bool` values computed from the arguments and
+//! constants are tracked as terms, and every branch on a term the engine can't decide forks the
+//! path, with the branch condition added to the path condition of each side. Everything else, such
+//! as call results, values read from global storage or through references, and locals borrowed
+//! mutably, is an opaque value about which nothing is known. Deciding conditions doesn't involve
+//! a solver: terms are only simplified when their operands are constants, and compared to the
+//! conditions already on the path.
+//!
+//! The engine assumes that the module was verified. Paths through ill-typed code are dropped.
+
+use crate::{
+    access::ModuleAccess,
+    file_format::{
+        Bytecode, CodeOffset, CompiledModule, FunctionDefinitionIndex, LocalIndex,
+        StructFieldInformation,
+    },
+};
+use std::{collections::BTreeSet, fmt};
+
+/// The number of instructions executed on a path before it is cut, by default.
+pub const DEFAULT_DEPTH_BOUND: usize = 1024;
+/// The number of paths explored before exploration stops, by default.
+pub const DEFAULT_PATH_BOUND: usize = 256;
+
+/// An operation on two terms.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Mod,
+    Div,
+    BitOr,
+    BitAnd,
+    Xor,
+    Or,
+    And,
+    Eq,
+    Neq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+impl BinaryOp {
+    fn symbol(self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Mod => "%",
+            BinaryOp::Div => "/",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::Xor => "^",
+            BinaryOp::Or => "||",
+            BinaryOp::And => "&&",
+            BinaryOp::Eq => "==",
+            BinaryOp::Neq => "!=",
+            BinaryOp::Lt => "<",
+            BinaryOp::Gt => ">",
+            BinaryOp::Le => "<=",
+            BinaryOp::Ge => ">=",
+        }
+    }
+}
+
+/// A symbolic value.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Term {
+    U64(u64),
+    Bool(bool),
+    /// The value the function was called with, for an argument.
+    Arg(LocalIndex),
+    /// A value the engine doesn't model. Every one has a different id.
+    Opaque(usize),
+    Binary(BinaryOp, Box<Term>, Box<Term>),
+    Not(Box<Term>),
+}
+
+/// Displays the term as an expression, e.g. `(arg0 + 1)`.
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::U64(value) => write!(f, "{}", value),
+            Term::Bool(value) => write!(f, "{}", value),
+            Term::Arg(idx) => write!(f, "arg{}", idx),
+            Term::Opaque(id) => write!(f, "opaque{}", id),
+            Term::Binary(op, lhs, rhs) => write!(f, "({} {} {})", lhs, op.symbol(), rhs),
+            Term::Not(term) => write!(f, "!{}", term),
+        }
+    }
+}
+
+/// The arithmetic of the operation fails for every value of the operands.
+struct ArithmeticError;
+
+impl Term {
+    /// Returns `lhs op rhs`, simplified when the operands make the result known.
+    fn binary(op: BinaryOp, lhs: Term, rhs: Term) -> Result<Term, ArithmeticError> {
+        use BinaryOp::*;
+
+        let checked = |result: Option<u64>| result.map(Term::U64).ok_or(ArithmeticError);
+        Ok(match (op, &lhs, &rhs) {
+            (Add, Term::U64(l), Term::U64(r)) => checked(l.checked_add(*r))?,
+            (Sub, Term::U64(l), Term::U64(r)) => checked(l.checked_sub(*r))?,
+            (Mul, Term::U64(l), Term::U64(r)) => checked(l.checked_mul(*r))?,
+            (Mod, _, Term::U64(0)) | (Div, _, Term::U64(0)) => return Err(ArithmeticError),
+            (Mod, Term::U64(l), Term::U64(r)) => Term::U64(l % r),
+            (Div, Term::U64(l), Term::U64(r)) => Term::U64(l / r),
+            (BitOr, Term::U64(l), Term::U64(r)) => Term::U64(l | r),
+            (BitAnd, Term::U64(l), Term::U64(r)) => Term::U64(l & r),
+            (Xor, Term::U64(l), Term::U64(r)) => Term::U64(l ^ r),
+            (Lt, Term::U64(l), Term::U64(r)) => Term::Bool(l < r),
+            (Gt, Term::U64(l), Term::U64(r)) => Term::Bool(l > r),
+            (Le, Term::U64(l), Term::U64(r)) => Term::Bool(l <= r),
+            (Ge, Term::U64(l), Term::U64(r)) => Term::Bool(l >= r),
+            (Or, Term::Bool(true), _) | (Or, _, Term::Bool(true)) => Term::Bool(true),
+            (Or, Term::Bool(false), _) => rhs,
+            (Or, _, Term::Bool(false)) => lhs,
+            (And, Term::Bool(false), _) | (And, _, Term::Bool(false)) => Term::Bool(false),
+            (And, Term::Bool(true), _) => rhs,
+            (And, _, Term::Bool(true)) => lhs,
+            // Terms have no side effects, so identical terms have the same value.
+            (Eq, _, _) if lhs == rhs => Term::Bool(true),
+            (Neq, _, _) if lhs == rhs => Term::Bool(false),
+            (Eq, Term::U64(_), Term::U64(_)) | (Eq, Term::Bool(_), Term::Bool(_)) => {
+                Term::Bool(false)
+            }
+            (Neq, Term::U64(_), Term::U64(_)) | (Neq, Term::Bool(_), Term::Bool(_)) => {
+                Term::Bool(true)
+            }
+            _ => Term::Binary(op, Box::new(lhs), Box::new(rhs)),
+        })
+    }
+
+    /// Returns `!term`, simplified when `term` is a constant or a negation.
+    fn not(term: Term) -> Term {
+        match term {
+            Term::Bool(value) => Term::Bool(!value),
+            Term::Not(term) => *term,
+            term => Term::Not(Box::new(term)),
+        }
+    }
+}
+
+/// How a path through the function ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PathOutcome {
+    /// The function returned.
+    Returned,
+    /// `Abort` was executed at `offset`, with its code if it is known.
+    Aborted {
+        offset: CodeOffset,
+        code: Option<u64>,
+    },
+    /// The arithmetic instruction at `offset` failed.
+    ArithmeticError { offset: CodeOffset },
+    /// The path was cut by the depth bound.
+    DepthBoundExceeded,
+}
+
+/// A path through the function, as the conditions its branches assumed and how it ended.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Path {
+    /// The conditions assumed true by the branches of the path, in order.
+    pub condition: Vec<Term>,
+    pub outcome: PathOutcome,
+}
+
+/// A likely bug.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Finding {
+    /// The `Abort` at this offset is reached without depending on the arguments or on any value
+    /// the engine doesn't model: every execution of the function aborts there.
+    DefiniteAbort {
+        offset: CodeOffset,
+        code: Option<u64>,
+    },
+    /// The arithmetic instruction at this offset is reached without depending on the arguments or
+    /// on any value the engine doesn't model, and fails: every execution of the function fails
+    /// there.
+    DefiniteArithmeticError { offset: CodeOffset },
+    /// No execution reaches the `Abort` at this offset. Only reported when every path was
+    /// explored.
+    UnreachableAbort { offset: CodeOffset },
+}
+
+/// The outcome of exploring a function.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SymbolicReport {
+    /// The paths explored, in depth-first order, taking the branch before the fall through.
+    pub paths: Vec<Path>,
+    /// Whether every path was explored to its end: no path was cut, dropped or left unexplored.
+    pub complete: bool,
+    /// The likely bugs, ordered by kind and then by offset.
+    pub findings: Vec<Finding>,
+}
+
+/// Explores the paths through the functions of a module, see the module documentation.
+pub struct SymbolicExecutor<'a> {
+    module: &'a CompiledModule,
+    depth_bound: usize,
+    path_bound: usize,
+}
+
+impl<'a> SymbolicExecutor<'a> {
+    pub fn new(module: &'a CompiledModule) -> Self {
+        Self {
+            module,
+            depth_bound: DEFAULT_DEPTH_BOUND,
+            path_bound: DEFAULT_PATH_BOUND,
+        }
+    }
+
+    /// Sets the number of instructions executed on a path before it is cut.
+    pub fn with_depth_bound(mut self, depth_bound: usize) -> Self {
+        self.depth_bound = depth_bound;
+        self
+    }
+
+    /// Sets the number of paths explored before exploration stops.
+    pub fn with_path_bound(mut self, path_bound: usize) -> Self {
+        self.path_bound = path_bound;
+        self
+    }
+
+    /// Explores the paths through `function`. Native functions have no paths.
+    pub fn execute(&self, function: FunctionDefinitionIndex) -> SymbolicReport {
+        let module = self.module;
+        let function_def = module.function_def_at(function);
+        if function_def.is_native() {
+            return SymbolicReport {
+                complete: true,
+                ..SymbolicReport::default()
+            };
+        }
+        let code = &function_def.code.code;
+        let arg_count = module
+            .function_signature_at(module.function_handle_at(function_def.function).signature)
+            .arg_types
+            .len();
+        let local_count = module.locals_signature_at(function_def.code.locals).0.len();
+        let mut locals: Vec<_> = (0..arg_count as LocalIndex)
+            .map(|idx| Some(Term::Arg(idx)))
+            .collect();
+        locals.resize(local_count.max(arg_count), None);
+
+        let mut exploration = Exploration {
+            executor: self,
+            code,
+            next_opaque: 0,
+            paths: vec![],
+            complete: true,
+        };
+        let mut states = vec![State {
+            pc: 0,
+            depth: 0,
+            stack: vec![],
+            locals,
+            borrowed: BTreeSet::new(),
+            condition: vec![],
+        }];
+        while let Some(state) = states.pop() {
+            if exploration.paths.len() >= self.path_bound {
+                exploration.complete = false;
+                break;
+            }
+            exploration.run(state, &mut states);
+        }
+
+        let mut findings = BTreeSet::new();
+        let mut reached = BTreeSet::new();
+        for path in &exploration.paths {
+            let unconditional = path.condition.is_empty();
+            match path.outcome {
+                PathOutcome::Aborted { offset, code } => {
+                    reached.insert(offset);
+                    if unconditional {
+                        findings.insert(Finding::DefiniteAbort { offset, code });
+                    }
+                }
+                PathOutcome::ArithmeticError { offset } if unconditional => {
+                    findings.insert(Finding::DefiniteArithmeticError { offset });
+                }
+                _ => (),
+            }
+        }
+        if exploration.complete {
+            for (offset, bytecode) in code.iter().enumerate() {
+                let offset = offset as CodeOffset;
+                if *bytecode == Bytecode::Abort && !reached.contains(&offset) {
+                    findings.insert(Finding::UnreachableAbort { offset });
+                }
+            }
+        }
+        SymbolicReport {
+            paths: exploration.paths,
+            complete: exploration.complete,
+            findings: findings.into_iter().collect(),
+        }
+    }
+}
+
+/// The state of a path at an instruction.
+#[derive(Clone)]
+struct State {
+    pc: CodeOffset,
+    depth: usize,
+    stack: Vec<Term>,
+    /// The locals, `None` when unavailable.
+    locals: Vec<Option<Term>>,
+    /// The locals borrowed mutably, which may have been written through the reference.
+    borrowed: BTreeSet<LocalIndex>,
+    condition: Vec<Term>,
+}
+
+impl State {
+    fn pop(&mut self) -> Option<Term> {
+        self.stack.pop()
+    }
+
+    fn pop_n(&mut self, n: usize) -> Option<()> {
+        if self.stack.len() < n {
+            return None;
+        }
+        self.stack.truncate(self.stack.len() - n);
+        Some(())
+    }
+
+    /// Returns whether `condition` holds on the path, if that is known.
+    fn decide(&self, condition: &Term) -> Option<bool> {
+        match condition {
+            Term::Bool(value) => Some(*value),
+            _ if self.condition.contains(condition) => Some(true),
+            _ if self.condition.contains(&Term::not(condition.clone())) => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// How an instruction ended its path.
+enum End {
+    Outcome(PathOutcome),
+    /// The instruction doesn't apply to its operands.
+    Invalid,
+}
+
+struct Exploration<'e, 'a> {
+    executor: &'e SymbolicExecutor<'a>,
+    code: &'e [Bytecode],
+    next_opaque: usize,
+    paths: Vec<Path>,
+    complete: bool,
+}
+
+impl<'e, 'a> Exploration<'e, 'a> {
+    /// Runs `state` until its path ends or forks, pushing the states of the forked paths to
+    /// `states`.
+    fn run(&mut self, mut state: State, states: &mut Vec<State>) {
+        loop {
+            if state.depth >= self.executor.depth_bound {
+                self.complete = false;
+                return self.end(state, PathOutcome::DepthBoundExceeded);
+            }
+            state.depth += 1;
+            let offset = state.pc;
+            let bytecode = match self.code.get(offset as usize) {
+                Some(bytecode) => bytecode,
+                None => return self.drop_path(),
+            };
+            state.pc += 1;
+            match self.step(&mut state, offset, bytecode) {
+                Ok(None) => (),
+                Ok(Some(branch)) => {
+                    let (target, taken) = branch;
+                    let condition = match state.pop() {
+                        Some(condition) => condition,
+                        None => return self.drop_path(),
+                    };
+                    let condition = if taken {
+                        condition
+                    } else {
+                        Term::not(condition)
+                    };
+                    match state.decide(&condition) {
+                        Some(true) => state.pc = target,
+                        Some(false) => (),
+                        None => {
+                            let mut branch_state = state.clone();
+                            branch_state.condition.push(condition.clone());
+                            branch_state.pc = target;
+                            state.condition.push(Term::not(condition));
+                            // The fall through is explored after the branch.
+                            states.push(state);
+                            states.push(branch_state);
+                            return;
+                        }
+                    }
+                }
+                Err(End::Outcome(outcome)) => return self.end(state, outcome),
+                Err(End::Invalid) => return self.drop_path(),
+            }
+        }
+    }
+
+    fn end(&mut self, state: State, outcome: PathOutcome) {
+        self.paths.push(Path {
+            condition: state.condition,
+            outcome,
+        });
+    }
+
+    fn drop_path(&mut self) {
+        self.complete = false;
+    }
+
+    fn opaque(&mut self) -> Term {
+        self.next_opaque += 1;
+        Term::Opaque(self.next_opaque - 1)
+    }
+
+    /// Executes `bytecode`. Returns the target of a conditional branch and whether it is taken
+    /// when the condition on top of the stack is true, leaving the condition on the stack.
+    fn step(
+        &mut self,
+        state: &mut State,
+        offset: CodeOffset,
+        bytecode: &Bytecode,
+    ) -> Result<Option<(CodeOffset, bool)>, End> {
+        let module = self.executor.module;
+        match bytecode {
+            Bytecode::BrTrue(target) => return Ok(Some((*target, true))),
+            Bytecode::BrFalse(target) => return Ok(Some((*target, false))),
+            Bytecode::Branch(target) => state.pc = *target,
+            Bytecode::Ret => return Err(End::Outcome(PathOutcome::Returned)),
+            Bytecode::Abort => {
+                let code = match state.pop().ok_or(End::Invalid)? {
+                    Term::U64(code) => Some(code),
+                    _ => None,
+                };
+                return Err(End::Outcome(PathOutcome::Aborted { offset, code }));
+            }
+            Bytecode::Pop => {
+                state.pop().ok_or(End::Invalid)?;
+            }
+            Bytecode::LdConst(value) => state.stack.push(Term::U64(*value)),
+            Bytecode::LdTrue => state.stack.push(Term::Bool(true)),
+            Bytecode::LdFalse => state.stack.push(Term::Bool(false)),
+            Bytecode::CopyLoc(idx) | Bytecode::MoveLoc(idx) => {
+                let local = state.locals.get_mut(*idx as usize).ok_or(End::Invalid)?;
+                let value = if let Bytecode::MoveLoc(_) = bytecode {
+                    local.take()
+                } else {
+                    local.clone()
+                };
+                let value = value.ok_or(End::Invalid)?;
+                let value = if state.borrowed.contains(idx) {
+                    self.opaque()
+                } else {
+                    value
+                };
+                state.stack.push(value);
+            }
+            Bytecode::StLoc(idx) => {
+                let value = state.pop().ok_or(End::Invalid)?;
+                *state.locals.get_mut(*idx as usize).ok_or(End::Invalid)? = Some(value);
+                state.borrowed.remove(idx);
+            }
+            Bytecode::MutBorrowLoc(idx) => {
+                state.borrowed.insert(*idx);
+                let value = self.opaque();
+                state.stack.push(value);
+            }
+            Bytecode::Call(idx, _) => {
+                let signature =
+                    module.function_signature_at(module.function_handle_at(*idx).signature);
+                state.pop_n(signature.arg_types.len()).ok_or(End::Invalid)?;
+                for _ in 0..signature.return_types.len() {
+                    let value = self.opaque();
+                    state.stack.push(value);
+                }
+            }
+            Bytecode::Pack(idx, _) => {
+                let field_count = match module.struct_def_at(*idx).field_information {
+                    StructFieldInformation::Native => return Err(End::Invalid),
+                    StructFieldInformation::Declared { field_count, .. } => field_count,
+                };
+                state.pop_n(field_count as usize).ok_or(End::Invalid)?;
+                let value = self.opaque();
+                state.stack.push(value);
+            }
+            Bytecode::Unpack(idx, _) => {
+                let field_count = match module.struct_def_at(*idx).field_information {
+                    StructFieldInformation::Native => return Err(End::Invalid),
+                    StructFieldInformation::Declared { field_count, .. } => field_count,
+                };
+                state.pop().ok_or(End::Invalid)?;
+                for _ in 0..field_count {
+                    let value = self.opaque();
+                    state.stack.push(value);
+                }
+            }
+            Bytecode::Add => self.binary(state, BinaryOp::Add, offset)?,
+            Bytecode::Sub => self.binary(state, BinaryOp::Sub, offset)?,
+            Bytecode::Mul => self.binary(state, BinaryOp::Mul, offset)?,
+            Bytecode::Mod => self.binary(state, BinaryOp::Mod, offset)?,
+            Bytecode::Div => self.binary(state, BinaryOp::Div, offset)?,
+            Bytecode::BitOr => self.binary(state, BinaryOp::BitOr, offset)?,
+            Bytecode::BitAnd => self.binary(state, BinaryOp::BitAnd, offset)?,
+            Bytecode::Xor => self.binary(state, BinaryOp::Xor, offset)?,
+            Bytecode::Or => self.binary(state, BinaryOp::Or, offset)?,
+            Bytecode::And => self.binary(state, BinaryOp::And, offset)?,
+            Bytecode::Eq => self.binary(state, BinaryOp::Eq, offset)?,
+            Bytecode::Neq => self.binary(state, BinaryOp::Neq, offset)?,
+            Bytecode::Lt => self.binary(state, BinaryOp::Lt, offset)?,
+            Bytecode::Gt => self.binary(state, BinaryOp::Gt, offset)?,
+            Bytecode::Le => self.binary(state, BinaryOp::Le, offset)?,
+            Bytecode::Ge => self.binary(state, BinaryOp::Ge, offset)?,
+            Bytecode::Not => {
+                let value = state.pop().ok_or(End::Invalid)?;
+                state.stack.push(Term::not(value));
+            }
+            // The values these push aren't modeled.
+            Bytecode::LdStr(_)
+            | Bytecode::LdByteArray(_)
+            | Bytecode::LdAddr(_)
+            | Bytecode::ImmBorrowLoc(_)
+            | Bytecode::GetTxnGasUnitPrice
+            | Bytecode::GetTxnMaxGasUnits
+            | Bytecode::GetGasRemaining
+            | Bytecode::GetTxnSenderAddress
+            | Bytecode::GetTxnSequenceNumber
+            | Bytecode::GetTxnPublicKey => {
+                let value = self.opaque();
+                state.stack.push(value);
+            }
+            Bytecode::ReadRef
+            | Bytecode::FreezeRef
+            | Bytecode::MutBorrowField(_)
+            | Bytecode::ImmBorrowField(_)
+            | Bytecode::BorrowGlobal(_, _)
+            | Bytecode::Exists(_, _)
+            | Bytecode::MoveFrom(_, _) => {
+                state.pop().ok_or(End::Invalid)?;
+                let value = self.opaque();
+                state.stack.push(value);
+            }
+            Bytecode::MoveToSender(_, _) | Bytecode::CreateAccount => {
+                state.pop().ok_or(End::Invalid)?;
+            }
+            Bytecode::WriteRef => {
+                state.pop_n(2).ok_or(End::Invalid)?;
+            }
+        }
+        Ok(None)
+    }
+
+    fn binary(&mut self, state: &mut State, op: BinaryOp, offset: CodeOffset) -> Result<(), End> {
+        let rhs = state.pop().ok_or(End::Invalid)?;
+        let lhs = state.pop().ok_or(End::Invalid)?;
+        let value = Term::binary(op, lhs, rhs)
+            .map_err(|_| End::Outcome(PathOutcome::ArithmeticError { offset }))?;
+        state.stack.push(value);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Helpers for turning stored binaries (e.g. past fuzzer finds) into regression tests, for
+//! snapshot testing of bytecode output against checked-in golden files, and for building packages
+//! of modules that call each other.
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    errors::BinaryLoaderResult,
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature, NO_TYPE_ACTUALS},
+    package::{PackageDependency, PackageManifest},
+};
+use failure::prelude::*;
+use std::{
+    env, fmt, fs, io,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+/// Deserializes every file stored under `dir` (recursively, in sorted order) as a
+/// [`CompiledModule`] and passes the result to `f`.
+///
+/// Deserialization errors are not failures by themselves -- most fuzzer finds are malformed
+/// binaries -- so `f` receives the raw result and decides what is expected. A file is reported as
+/// failed if it can't be read, if `f` returns an error, or if deserialization or `f` panics.
+/// Hidden files (such as `.gitkeep`) are skipped.
+///
+/// Returns an error only if the directory itself can't be listed.
+pub fn replay_corpus<P, F>(dir: P, mut f: F) -> io::Result<CorpusReport>
+where
+    P: AsRef<Path>,
+    F: FnMut(&Path, BinaryLoaderResult<CompiledModule>) -> Result<()>,
+{
+    let mut paths = vec![];
+    collect_files(dir.as_ref(), &mut paths)?;
+    paths.sort();
+
+    let mut report = CorpusReport::default();
+    for path in paths {
+        let outcome = match fs::read(&path) {
+            Ok(binary) => {
+                match panic::catch_unwind(AssertUnwindSafe(|| {
+                    f(&path, CompiledModule::deserialize(&binary))
+                })) {
+                    Ok(Ok(())) => None,
+                    Ok(Err(err)) => Some(CorpusFailureReason::Check(err)),
+                    Err(payload) => Some(CorpusFailureReason::Panic(panic_message(&*payload))),
+                }
+            }
+            Err(err) => Some(CorpusFailureReason::Io(err)),
+        };
+        report.replayed += 1;
+        if let Some(reason) = outcome {
+            report.failures.push(CorpusFailure { path, reason });
+        }
+    }
+    Ok(report)
+}
+
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        (*msg).to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+/// The outcome of a [`replay_corpus`] run.
+#[derive(Debug, Default)]
+pub struct CorpusReport {
+    /// The number of files that were replayed.
+    pub replayed: usize,
+    /// The files that failed, in replay order.
+    pub failures: Vec<CorpusFailure>,
+}
+
+impl CorpusReport {
+    /// Returns true if every file in the corpus replayed successfully.
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Panics with a listing of every failed file if any file failed. Intended to be called at the
+    /// end of a unit test.
+    pub fn assert_success(&self) {
+        assert!(self.is_success(), "{}", self);
+    }
+}
+
+impl fmt::Display for CorpusReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} corpus files failed",
+            self.failures.len(),
+            self.replayed
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n  {}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// A single corpus file that failed to replay.
+#[derive(Debug)]
+pub struct CorpusFailure {
+    pub path: PathBuf,
+    pub reason: CorpusFailureReason,
+}
+
+impl fmt::Display for CorpusFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.reason)
+    }
+}
+
+#[derive(Debug)]
+pub enum CorpusFailureReason {
+    /// The file could not be read.
+    Io(io::Error),
+    /// The user closure returned an error.
+    Check(Error),
+    /// Deserialization or the user closure panicked.
+    Panic(String),
+}
+
+impl fmt::Display for CorpusFailureReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CorpusFailureReason::Io(err) => write!(f, "I/O error: {}", err),
+            CorpusFailureReason::Check(err) => write!(f, "check failed: {}", err),
+            CorpusFailureReason::Panic(msg) => write!(f, "panicked: {}", msg),
+        }
+    }
+}
+
+/// The environment variable that switches the golden-file checks into update mode. When it is set
+/// (to anything), [`check_golden`] and [`check_golden_module`] rewrite the golden files with the
+/// actual output instead of comparing against them.
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+
+/// The number of bytes written per line in hex golden files, so that diffs stay readable.
+const HEX_BYTES_PER_LINE: usize = 32;
+
+/// Compares `module` against the golden files `<name>.mv.hex` and `<name>.disasm` in `dir`.
+///
+/// The first file holds the serialized module in hex, wrapped across lines (it can be read back
+/// with `CompiledModule::from_str`); the second holds its disassembly as printed by `Display`.
+/// Both files are checked, and the error lists the differences in each.
+pub fn check_golden_module<P: AsRef<Path>>(
+    dir: P,
+    name: &str,
+    module: &CompiledModule,
+) -> Result<()> {
+    check_golden_module_with(dir.as_ref(), name, module, update_golden())
+}
+
+pub(crate) fn check_golden_module_with(
+    dir: &Path,
+    name: &str,
+    module: &CompiledModule,
+    update: bool,
+) -> Result<()> {
+    let mut binary = vec![];
+    module.serialize(&mut binary)?;
+    let hex = binary
+        .chunks(HEX_BYTES_PER_LINE)
+        .map(|chunk| format!("{}\n", hex::encode(chunk)))
+        .collect::<String>();
+    let disassembly = format!("{}\n", module);
+
+    let errors: Vec<_> = vec![
+        check_golden_with(&dir.join(format!("{}.mv.hex", name)), &hex, update),
+        check_golden_with(&dir.join(format!("{}.disasm", name)), &disassembly, update),
+    ]
+    .into_iter()
+    .filter_map(|res| res.err())
+    .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        let messages: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+        bail!("{}", messages.join("\n"))
+    }
+}
+
+/// Compares `actual` against the contents of the golden file at `path`, failing with a line diff
+/// if they differ. If [`UPDATE_GOLDEN_ENV`] is set, the file is (re)written with `actual` instead.
+pub fn check_golden<P: AsRef<Path>>(path: P, actual: &str) -> Result<()> {
+    check_golden_with(path.as_ref(), actual, update_golden())
+}
+
+fn update_golden() -> bool {
+    env::var_os(UPDATE_GOLDEN_ENV).is_some()
+}
+
+pub(crate) fn check_golden_with(path: &Path, actual: &str, update: bool) -> Result<()> {
+    if update {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, actual)?;
+        return Ok(());
+    }
+
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => bail!(
+            "golden file {} does not exist; rerun with {}=1 to create it",
+            path.display(),
+            UPDATE_GOLDEN_ENV
+        ),
+        Err(err) => bail!("could not read golden file {}: {}", path.display(), err),
+    };
+    if expected == actual {
+        return Ok(());
+    }
+    bail!(
+        "output does not match golden file {} (rerun with {}=1 to update it):\n--- expected\n+++ actual\n{}",
+        path.display(),
+        UPDATE_GOLDEN_ENV,
+        line_diff(&expected, actual)
+    )
+}
+
+/// Renders a line-by-line diff from `expected` to `actual`, prefixing removed lines with `-`,
+/// added lines with `+` and unchanged lines with a space.
+pub fn line_diff(expected: &str, actual: &str) -> String {
+    let old: Vec<_> = expected.lines().collect();
+    let new: Vec<_> = actual.lines().collect();
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            diff.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        } else {
+            diff.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        }
+    }
+    diff
+}
+
+/// Builds module `name` under the default address, with a public function `f` calling `f` of every
+/// module in `callees`, which are also under the default address.
+pub fn package_module(name: &str, callees: &[&str]) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), name);
+    let signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    let mut code = CodeBuilder::new();
+    for callee in callees {
+        let callee = builder.add_module_handle(AccountAddress::default(), callee);
+        let f = builder.add_function_handle(callee, "f", signature.clone());
+        code.emit(Bytecode::Call(f, NO_TYPE_ACTUALS));
+    }
+    code.emit(Bytecode::Ret);
+    builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    builder.build().expect("module is bounds-valid")
+}
+
+/// Returns the ID of the module `package_module(name, ..)` builds.
+pub fn package_module_id(name: &str) -> ModuleId {
+    ModuleId::new(AccountAddress::default(), name.to_string())
+}
+
+/// Returns the manifest of package `P` under the default address, pinning `dependencies`.
+pub fn package_manifest(dependencies: Vec<PackageDependency>) -> PackageManifest {
+    PackageManifest {
+        name: "P".to_string(),
+        address: AccountAddress::default(),
+        dependencies,
+    }
+}
@@ -1,20 +1,55 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::gas_schedule::{AbstractMemorySize, GasAlgebra, GasCarrier, GasPrice, GasUnits};
-use crypto::ed25519::{compat, Ed25519PublicKey};
-use types::{account_address::AccountAddress, transaction::SignedTransaction};
+use crate::gas_schedule::{
+    AbstractMemorySize, CostTable, GasAlgebra, GasCarrier, GasContext, GasPrice, GasUnits,
+    MAX_PRICE_PER_GAS_UNIT, MIN_PRICE_PER_GAS_UNIT,
+};
+use crypto::{
+    ed25519::{compat, Ed25519PublicKey},
+    traits::ValidKeyStringExt,
+};
+use failure::prelude::*;
+use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+use std::{collections::HashSet, time::Duration};
+use types::{
+    account_address::AccountAddress,
+    transaction::{SignedTransaction, MAX_TRANSACTION_SIZE_IN_BYTES},
+};
 
+/// The chain ID of transactions that don't specify one.
+pub const DEFAULT_CHAIN_ID: u8 = 0;
+
+/// The metadata of a transaction, as seen by the VM.
+///
+/// Metadata can be (de)serialized so that fixtures can be stored as JSON or TOML files, with
+/// public keys encoded as hex strings. Fields missing from a fixture are taken from
+/// `TransactionMetadata::default()`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
 pub struct TransactionMetadata {
-    pub sender: AccountAddress,
-    pub public_key: Ed25519PublicKey,
-    pub sequence_number: u64,
-    pub max_gas_amount: GasUnits<GasCarrier>,
-    pub gas_unit_price: GasPrice<GasCarrier>,
-    pub transaction_size: AbstractMemorySize<GasCarrier>,
+    sender: AccountAddress,
+    #[serde(serialize_with = "serialize_key")]
+    #[serde(deserialize_with = "deserialize_key")]
+    public_key: Ed25519PublicKey,
+    sequence_number: u64,
+    max_gas_amount: GasUnits<GasCarrier>,
+    gas_unit_price: GasPrice<GasCarrier>,
+    transaction_size: AbstractMemorySize<GasCarrier>,
+    secondary_signers: Vec<AccountAddress>,
+    #[serde(serialize_with = "serialize_keys")]
+    #[serde(deserialize_with = "deserialize_keys")]
+    secondary_signer_public_keys: Vec<Ed25519PublicKey>,
+    chain_id: u8,
+    expiration_timestamp: Duration,
 }
 
 impl TransactionMetadata {
+    /// Creates the metadata of a submitted transaction.
+    ///
+    /// No invariants are checked here: the values of a submitted transaction are validated by
+    /// the prologue, which reports a specific status for every violation. Use
+    /// `TransactionMetadataBuilder` to create metadata for any other purpose.
     pub fn new(txn: &SignedTransaction) -> Self {
         Self {
             sender: txn.sender(),
@@ -23,9 +58,29 @@ impl TransactionMetadata {
             max_gas_amount: GasUnits::new(txn.max_gas_amount()),
             gas_unit_price: GasPrice::new(txn.gas_unit_price()),
             transaction_size: AbstractMemorySize::new(txn.raw_txn_bytes_len() as u64),
+            secondary_signers: vec![],
+            secondary_signer_public_keys: vec![],
+            chain_id: DEFAULT_CHAIN_ID,
+            expiration_timestamp: txn.expiration_time(),
         }
     }
 
+    /// Parses metadata from a JSON fixture, checking the same invariants as
+    /// `TransactionMetadataBuilder::build`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let metadata: Self = serde_json::from_str(json)?;
+        metadata.check_invariants()?;
+        Ok(metadata)
+    }
+
+    /// Parses metadata from a TOML fixture, checking the same invariants as
+    /// `TransactionMetadataBuilder::build`.
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let metadata: Self = toml::from_str(toml)?;
+        metadata.check_invariants()?;
+        Ok(metadata)
+    }
+
     pub fn max_gas_amount(&self) -> GasUnits<GasCarrier> {
         self.max_gas_amount
     }
@@ -34,6 +89,16 @@ impl TransactionMetadata {
         self.gas_unit_price
     }
 
+    /// Returns the gas parameters of the transaction, to be metered with `cost_table`.
+    pub fn gas_context<'a>(&self, cost_table: &'a CostTable) -> GasContext<'a> {
+        GasContext::new(
+            cost_table,
+            self.max_gas_amount,
+            self.gas_unit_price,
+            self.transaction_size,
+        )
+    }
+
     pub fn sender(&self) -> AccountAddress {
         self.sender.to_owned()
     }
@@ -49,6 +114,61 @@ impl TransactionMetadata {
     pub fn transaction_size(&self) -> AbstractMemorySize<GasCarrier> {
         self.transaction_size
     }
+
+    /// Returns the addresses of the secondary signers of a multi-agent transaction, in signing
+    /// order. Empty for single-signer transactions.
+    pub fn secondary_signers(&self) -> &[AccountAddress] {
+        &self.secondary_signers
+    }
+
+    /// Returns the public keys of the secondary signers, in the same order as
+    /// `secondary_signers`.
+    pub fn secondary_signer_public_keys(&self) -> &[Ed25519PublicKey] {
+        &self.secondary_signer_public_keys
+    }
+
+    /// Returns true if the transaction has secondary signers.
+    pub fn is_multi_agent(&self) -> bool {
+        !self.secondary_signers.is_empty()
+    }
+
+    /// Returns the addresses of all the signers of the transaction: the sender followed by the
+    /// secondary signers.
+    pub fn signers(&self) -> Vec<AccountAddress> {
+        let mut signers = Vec::with_capacity(1 + self.secondary_signers.len());
+        signers.push(self.sender);
+        signers.extend_from_slice(&self.secondary_signers);
+        signers
+    }
+
+    /// Returns the public keys of all the signers of the transaction, in the same order as
+    /// `signers`.
+    pub fn signer_public_keys(&self) -> Vec<&Ed25519PublicKey> {
+        let mut public_keys = Vec::with_capacity(1 + self.secondary_signer_public_keys.len());
+        public_keys.push(&self.public_key);
+        public_keys.extend(self.secondary_signer_public_keys.iter());
+        public_keys
+    }
+
+    /// Returns the ID of the chain the transaction is meant for.
+    pub fn chain_id(&self) -> u8 {
+        self.chain_id
+    }
+
+    /// Returns the time, since the Unix epoch, at which the transaction expires.
+    pub fn expiration_timestamp(&self) -> Duration {
+        self.expiration_timestamp
+    }
+
+    /// Returns true if the transaction has expired at `now`, the time since the Unix epoch.
+    pub fn is_expired(&self, now: Duration) -> bool {
+        now >= self.expiration_timestamp
+    }
+
+    /// Returns true if the transaction may be executed on the chain identified by `chain_id`.
+    pub fn is_for_chain(&self, chain_id: u8) -> bool {
+        self.chain_id == chain_id
+    }
 }
 
 impl Default for TransactionMetadata {
@@ -61,6 +181,171 @@ impl Default for TransactionMetadata {
             max_gas_amount: GasUnits::new(100_000_000),
             gas_unit_price: GasPrice::new(0),
             transaction_size: AbstractMemorySize::new(0),
+            secondary_signers: vec![],
+            secondary_signer_public_keys: vec![],
+            chain_id: DEFAULT_CHAIN_ID,
+            expiration_timestamp: Duration::from_secs(u64::max_value()),
         }
     }
 }
+
+/// Builds a `TransactionMetadata`, checking its invariants once all the values are set.
+///
+/// Values that aren't set are taken from `TransactionMetadata::default()`.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionMetadataBuilder {
+    metadata: TransactionMetadata,
+}
+
+impl TransactionMetadataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_sender(&mut self, sender: AccountAddress) -> &mut Self {
+        self.metadata.sender = sender;
+        self
+    }
+
+    pub fn with_public_key(&mut self, public_key: Ed25519PublicKey) -> &mut Self {
+        self.metadata.public_key = public_key;
+        self
+    }
+
+    pub fn with_sequence_number(&mut self, sequence_number: u64) -> &mut Self {
+        self.metadata.sequence_number = sequence_number;
+        self
+    }
+
+    pub fn with_max_gas_amount(&mut self, max_gas_amount: GasUnits<GasCarrier>) -> &mut Self {
+        self.metadata.max_gas_amount = max_gas_amount;
+        self
+    }
+
+    pub fn with_gas_unit_price(&mut self, gas_unit_price: GasPrice<GasCarrier>) -> &mut Self {
+        self.metadata.gas_unit_price = gas_unit_price;
+        self
+    }
+
+    pub fn with_transaction_size(
+        &mut self,
+        transaction_size: AbstractMemorySize<GasCarrier>,
+    ) -> &mut Self {
+        self.metadata.transaction_size = transaction_size;
+        self
+    }
+
+    pub fn with_chain_id(&mut self, chain_id: u8) -> &mut Self {
+        self.metadata.chain_id = chain_id;
+        self
+    }
+
+    pub fn with_expiration_timestamp(&mut self, expiration_timestamp: Duration) -> &mut Self {
+        self.metadata.expiration_timestamp = expiration_timestamp;
+        self
+    }
+
+    /// Sets the secondary signers of a multi-agent transaction, as pairs of an address and its
+    /// public key.
+    pub fn with_secondary_signers(
+        &mut self,
+        secondary_signers: Vec<(AccountAddress, Ed25519PublicKey)>,
+    ) -> &mut Self {
+        let (addresses, public_keys) = secondary_signers.into_iter().unzip();
+        self.metadata.secondary_signers = addresses;
+        self.metadata.secondary_signer_public_keys = public_keys;
+        self
+    }
+
+    /// Returns the metadata, or an error if any of its invariants is violated:
+    ///
+    /// * the maximum amount of gas must be non-zero,
+    /// * the gas unit price must be within `MIN_PRICE_PER_GAS_UNIT..=MAX_PRICE_PER_GAS_UNIT`,
+    /// * the transaction size must not exceed `MAX_TRANSACTION_SIZE_IN_BYTES`,
+    /// * the expiration timestamp must be non-zero, as such a transaction could never execute,
+    /// * no address may sign the transaction more than once.
+    pub fn build(&self) -> Result<TransactionMetadata> {
+        self.metadata.check_invariants()?;
+        Ok(self.metadata.clone())
+    }
+}
+
+impl TransactionMetadata {
+    fn check_invariants(&self) -> Result<()> {
+        ensure!(
+            self.max_gas_amount.get() > 0,
+            "max gas amount must be non-zero"
+        );
+        ensure!(
+            self.gas_unit_price.get() >= MIN_PRICE_PER_GAS_UNIT.get()
+                && self.gas_unit_price.get() <= MAX_PRICE_PER_GAS_UNIT.get(),
+            "gas unit price {} is outside of {}..={}",
+            self.gas_unit_price.get(),
+            MIN_PRICE_PER_GAS_UNIT.get(),
+            MAX_PRICE_PER_GAS_UNIT.get()
+        );
+        ensure!(
+            self.transaction_size.get() <= MAX_TRANSACTION_SIZE_IN_BYTES as GasCarrier,
+            "transaction size {} exceeds the maximum of {} bytes",
+            self.transaction_size.get(),
+            MAX_TRANSACTION_SIZE_IN_BYTES
+        );
+        ensure!(
+            self.expiration_timestamp > Duration::from_secs(0),
+            "expiration timestamp must be non-zero"
+        );
+        ensure!(
+            self.secondary_signers.len() == self.secondary_signer_public_keys.len(),
+            "{} secondary signers but {} secondary signer public keys",
+            self.secondary_signers.len(),
+            self.secondary_signer_public_keys.len()
+        );
+        let mut seen = HashSet::new();
+        for signer in self.signers() {
+            ensure!(
+                seen.insert(signer),
+                "{} signs the transaction more than once",
+                signer
+            );
+        }
+        Ok(())
+    }
+}
+
+fn serialize_key<S: Serializer>(
+    key: &Ed25519PublicKey,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let encoded_key = key.to_encoded_string().map_err(ser::Error::custom)?;
+    serializer.serialize_str(&encoded_key)
+}
+
+fn deserialize_key<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Ed25519PublicKey, D::Error> {
+    let encoded_key = String::deserialize(deserializer)?;
+    Ed25519PublicKey::from_encoded_string(&encoded_key).map_err(de::Error::custom)
+}
+
+fn serialize_keys<S: Serializer>(
+    keys: &[Ed25519PublicKey],
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    let encoded_keys = keys
+        .iter()
+        .map(|key| key.to_encoded_string().map_err(ser::Error::custom))
+        .collect::<std::result::Result<Vec<_>, S::Error>>()?;
+    encoded_keys.serialize(serializer)
+}
+
+fn deserialize_keys<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Vec<Ed25519PublicKey>, D::Error> {
+    let encoded_keys = Vec::<String>::deserialize(deserializer)?;
+    encoded_keys
+        .iter()
+        .map(|encoded_key| {
+            Ed25519PublicKey::from_encoded_string(encoded_key).map_err(de::Error::custom)
+        })
+        .collect()
+}
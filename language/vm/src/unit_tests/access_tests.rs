@@ -0,0 +1,127 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    access::ModuleAccess,
+    builder::CompiledModuleBuilder,
+    errors::VMStaticViolation,
+    file_format::{
+        empty_module, FieldDefinitionIndex, ModuleHandleIndex, SignatureToken, StringPoolIndex,
+        StructDefinitionIndex,
+    },
+    views::ModuleView,
+    IndexKind,
+};
+use std::sync::Arc;
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+#[test]
+fn fallible_accessors_report_out_of_bounds_indexes() {
+    let module = empty_module().freeze().unwrap();
+    assert_eq!(
+        module.get_module_handle_at(ModuleHandleIndex::new(0)),
+        Ok(module.self_handle())
+    );
+    assert_eq!(
+        module.get_string_at(StringPoolIndex::new(0)),
+        Ok(module.name())
+    );
+
+    assert_eq!(
+        module.get_string_at(StringPoolIndex::new(1)),
+        Err(VMStaticViolation::IndexOutOfBounds(
+            IndexKind::StringPool,
+            1,
+            1
+        ))
+    );
+    assert_eq!(
+        module.get_struct_def_at(StructDefinitionIndex::new(0)),
+        Err(VMStaticViolation::IndexOutOfBounds(
+            IndexKind::StructDefinition,
+            0,
+            0
+        ))
+    );
+}
+
+#[test]
+fn struct_fields_are_returned_as_slices() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let s = builder.add_struct("S", false, vec![], vec![("x", SignatureToken::U64)]);
+    let t = builder.add_struct(
+        "T",
+        false,
+        vec![],
+        vec![("x", SignatureToken::U64), ("y", SignatureToken::Bool)],
+    );
+    let n = builder.add_native_struct("N", false, vec![]);
+    let module = builder.build().expect("module is bounds-valid");
+
+    let s_fields = module.struct_def_fields(module.struct_def_at(s)).unwrap();
+    assert_eq!(s_fields, &module.field_defs()[..1]);
+    let t_fields = module.struct_def_fields(module.struct_def_at(t)).unwrap();
+    assert_eq!(t_fields, &module.field_defs()[1..]);
+    assert_eq!(module.struct_def_fields(module.struct_def_at(n)), None);
+
+    assert_eq!(
+        module.get_field_def_range(2, FieldDefinitionIndex::new(1)),
+        Ok(t_fields)
+    );
+    assert_eq!(
+        module.get_field_def_range(2, FieldDefinitionIndex::new(2)),
+        Err(VMStaticViolation::RangeOutOfBounds(
+            IndexKind::FieldDefinition,
+            3,
+            2,
+            4
+        ))
+    );
+}
+
+#[test]
+fn module_access_is_object_safe() {
+    let first = CompiledModuleBuilder::new(AccountAddress::default(), "First")
+        .build()
+        .unwrap();
+    let second = CompiledModuleBuilder::new(AccountAddress::random(), "Second")
+        .build()
+        .unwrap();
+    let providers: Vec<Box<dyn ModuleAccess>> = vec![
+        Box::new(first.clone()),
+        Box::new(Arc::new(second.clone())),
+        Box::new(Box::new(first)),
+    ];
+    let names: Vec<_> = providers.iter().map(|provider| provider.name()).collect();
+    assert_eq!(names, vec!["First", "Second", "First"]);
+    let by_ref: &dyn ModuleAccess = &providers[1];
+    assert_eq!(by_ref.name(), "Second");
+    assert_eq!(providers[1].self_id(), second.self_id());
+
+    // Pointers to providers work with code that is generic over `ModuleAccess`.
+    let shared: Arc<dyn ModuleAccess + Send> = Arc::new(second);
+    let view = ModuleView::new(&shared);
+    let module_ids: Vec<_> = view
+        .module_handles()
+        .map(|handle| handle.module_id())
+        .collect();
+    assert_eq!(module_ids, vec![shared.self_id()]);
+}
+
+#[test]
+fn self_handle_accessors() {
+    let address = AccountAddress::random();
+    let mut builder = CompiledModuleBuilder::new(address, "M");
+    let other = builder.add_module_handle(AccountAddress::default(), "Other");
+    let module = builder.build().unwrap();
+
+    assert!(module.is_self_handle(module.self_handle_idx()));
+    assert!(!module.is_self_handle(other));
+    assert_eq!(
+        module.module_handle_at(module.self_handle_idx()),
+        module.self_handle()
+    );
+    assert_eq!(module.self_id(), ModuleId::new(address, "M".to_string()));
+    assert_eq!(module.name(), "M");
+    assert_eq!(module.address(), &address);
+}
@@ -0,0 +1,163 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, FieldDefinitionIndex, FunctionHandleIndex, FunctionSignature,
+        LocalsSignature, LocalsSignatureIndex, SignatureToken, StructFieldInformation,
+        NO_TYPE_ACTUALS, SELF_MODULE_NAME,
+    },
+};
+use types::account_address::AccountAddress;
+
+fn unit_signature() -> FunctionSignature {
+    FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+#[test]
+fn new_builder_is_empty_module() {
+    let module = CompiledModuleBuilder::new(AccountAddress::default(), "M")
+        .build()
+        .expect("an empty module is bounds-valid");
+    assert_eq!(module.module_handles().len(), 1);
+    assert_eq!(module.string_at(module.self_handle().name), "M");
+    assert_eq!(
+        module.locals_signature_at(NO_TYPE_ACTUALS),
+        &LocalsSignature::default()
+    );
+}
+
+#[test]
+fn pools_are_interned() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let first = builder.intern_string(SELF_MODULE_NAME);
+    assert_eq!(builder.intern_string(SELF_MODULE_NAME), first);
+    assert_eq!(builder.intern_string("M").0, 0);
+    assert_eq!(
+        builder.intern_locals_signature(LocalsSignature::default()),
+        NO_TYPE_ACTUALS
+    );
+
+    let s = builder.add_struct("S", false, vec![], vec![("x", SignatureToken::U64)]);
+    let t = builder.add_struct(
+        "T",
+        false,
+        vec![],
+        vec![("x", SignatureToken::U64), ("y", SignatureToken::Bool)],
+    );
+    let module = builder.build().expect("module is bounds-valid");
+    // "M", "<SELF>", "S", "x", "T" and "y".
+    assert_eq!(module.string_pool().len(), 6);
+    // u64 and bool.
+    assert_eq!(module.type_signatures().len(), 2);
+    assert_eq!(module.address_pool().len(), 1);
+    assert_eq!(
+        module.struct_def_at(s).field_information,
+        StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        }
+    );
+    assert_eq!(
+        module.struct_def_at(t).field_information,
+        StructFieldInformation::Declared {
+            field_count: 2,
+            fields: FieldDefinitionIndex::new(1),
+        }
+    );
+}
+
+#[test]
+fn handles_are_deduplicated() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let other = builder.add_module_handle(AccountAddress::random(), "N");
+    assert_eq!(
+        builder.add_module_handle(AccountAddress::default(), "M").0,
+        0
+    );
+    let g = builder.add_function_handle(other, "g", unit_signature());
+    assert_eq!(builder.add_function_handle(other, "g", unit_signature()), g);
+
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Call(g, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Ret);
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        unit_signature(),
+        vec![],
+        vec![],
+        code,
+    );
+    let module = builder.build().expect("module is bounds-valid");
+    assert_eq!(module.module_handles().len(), 2);
+    assert_eq!(module.function_handles().len(), 2);
+    assert_eq!(module.function_signatures().len(), 1);
+}
+
+#[test]
+fn function_locals_start_with_arguments() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let signature = FunctionSignature {
+        arg_types: vec![SignatureToken::Bool].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    let f = builder.add_function("f", 0, signature, vec![SignatureToken::U64], vec![], code);
+    let module = builder.build().expect("module is bounds-valid");
+    let locals = module.function_def_at(f).code.locals;
+    assert_eq!(locals, LocalsSignatureIndex::new(1));
+    assert_eq!(
+        module.locals_signature_at(locals),
+        &LocalsSignature(vec![SignatureToken::Bool, SignatureToken::U64].into())
+    );
+}
+
+#[test]
+fn labels_resolve_to_offsets() {
+    let mut code = CodeBuilder::new();
+    let head = code.new_label();
+    let exit = code.new_label();
+    code.bind(head);
+    code.emit(Bytecode::LdTrue);
+    code.emit_branch(Bytecode::BrFalse, exit);
+    code.emit_branch(Bytecode::Branch, head);
+    code.bind(exit);
+    code.emit(Bytecode::Ret);
+    assert_eq!(
+        code.into_code(),
+        vec![
+            Bytecode::LdTrue,
+            Bytecode::BrFalse(3),
+            Bytecode::Branch(0),
+            Bytecode::Ret,
+        ]
+    );
+}
+
+#[test]
+#[should_panic]
+fn unbound_label_panics() {
+    let mut code = CodeBuilder::new();
+    let label = code.new_label();
+    code.emit_branch(Bytecode::Branch, label);
+    code.into_code();
+}
+
+#[test]
+fn build_reports_bad_operands() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Call(FunctionHandleIndex::new(7), NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Ret);
+    builder.add_function("f", 0, unit_signature(), vec![], vec![], code);
+    assert!(builder.build().is_err());
+}
@@ -0,0 +1,85 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    coverage::CoverageMap,
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature},
+};
+use crypto::HashValue;
+use types::account_address::AccountAddress;
+
+fn module() -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::LdTrue);
+    code.emit(Bytecode::Pop);
+    code.emit(Bytecode::Ret);
+    builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    builder.build().expect("module is bounds-valid")
+}
+
+#[test]
+fn merge_unions_offsets() {
+    let a = HashValue::new([1; HashValue::LENGTH]);
+    let b = HashValue::new([2; HashValue::LENGTH]);
+    let mut map = CoverageMap::new();
+    map.record(a, "f", 0);
+    map.record(a, "f", 3);
+    let mut other = CoverageMap::new();
+    other.record(a, "f", 1);
+    other.record(a, "g", 0);
+    other.record(b, "f", 2);
+    map.merge(other);
+
+    let coverage = map.module(&a).unwrap();
+    assert_eq!(
+        coverage.functions["f"].iter().cloned().collect::<Vec<_>>(),
+        vec![0, 1, 3]
+    );
+    assert!(coverage.is_covered("g", 0));
+    assert!(!coverage.is_covered("g", 1));
+    assert!(!coverage.is_covered("h", 0));
+    assert!(map.module(&b).unwrap().is_covered("f", 2));
+    assert!(map.module(&HashValue::zero()).is_none());
+}
+
+#[test]
+fn json_round_trip() {
+    let mut map = CoverageMap::new();
+    map.record(HashValue::new([0xab; HashValue::LENGTH]), "f", 4);
+    map.record(HashValue::zero(), "g", 0);
+    let json = map.to_json().unwrap();
+    assert!(json.contains(&"ab".repeat(HashValue::LENGTH)));
+    assert_eq!(CoverageMap::from_json(&json).unwrap(), map);
+
+    assert!(CoverageMap::from_json(r#"{"abc": {"functions": {}}}"#).is_err());
+    assert!(CoverageMap::from_json(r#"{"00": {"functions": {}}}"#).is_err());
+}
+
+#[test]
+fn display_marks_executed_instructions() {
+    let module = module();
+    let module_hash = CoverageMap::module_hash(&module).unwrap();
+    let mut map = CoverageMap::new();
+    map.record(module_hash, "f", 0);
+    map.record(module_hash, "f", 2);
+    // Coverage of another version of the module is ignored.
+    map.record(HashValue::zero(), "f", 1);
+
+    let output = map.display(&module).unwrap().to_string();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines[0], "module 0x0::M: 2/3 instructions executed");
+    assert_eq!(
+        &lines[lines.len() - 3..],
+        &["  +    0: LdTrue", "  -    1: Pop", "  +    2: Ret"]
+    );
+
+    let output = CoverageMap::new().display(&module).unwrap().to_string();
+    assert!(output.starts_with("module 0x0::M: 0/3 instructions executed"));
+}
@@ -2,10 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    deserializer::{Deserializer, PartialModuleFailure},
     errors::*,
-    file_format::{CompiledModule, CompiledScript},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, CompiledModuleMut, CompiledScript, FunctionSignature,
+    },
     file_format_common::*,
 };
+use bytes::Bytes;
+use std::convert::TryFrom;
+use types::{account_address::AccountAddress, byte_array::ByteArray};
 
 #[test]
 fn malformed_simple() {
@@ -62,3 +69,149 @@ fn malformed_simple() {
         BinaryError::UnknownVersion
     );
 }
+
+fn module_binary(name: &str, functions: usize) -> Vec<u8> {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), name);
+    for i in 0..functions {
+        let mut code = CodeBuilder::new();
+        for _ in 0..i {
+            code.emit(Bytecode::LdTrue);
+            code.emit(Bytecode::Pop);
+        }
+        code.emit(Bytecode::Ret);
+        let signature = FunctionSignature {
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        };
+        let name = format!("f{}", i);
+        builder.add_function(&name, CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    }
+    let module = builder.build().expect("module is bounds-valid");
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+    binary
+}
+
+#[test]
+fn deserializer_reuses_modules() {
+    let binaries = vec![
+        module_binary("Large", 5),
+        module_binary("Small", 1),
+        module_binary("Medium", 3),
+    ];
+    let mut deserializer = Deserializer::new();
+    let mut module = CompiledModuleMut::default();
+    for binary in binaries.iter().chain(binaries.iter().rev()) {
+        deserializer.deserialize_into(binary, &mut module).unwrap();
+        assert_eq!(
+            module,
+            CompiledModuleMut::deserialize_no_check_bounds(binary).unwrap()
+        );
+        assert_eq!(
+            deserializer.deserialize(binary).unwrap(),
+            CompiledModule::deserialize(binary).unwrap()
+        );
+    }
+}
+
+#[test]
+fn deserializer_recovers_from_errors() {
+    let binary = module_binary("M", 2);
+    let mut deserializer = Deserializer::new();
+    let mut module = CompiledModuleMut::default();
+    deserializer.deserialize_into(&binary, &mut module).unwrap();
+
+    let truncated = &binary[..binary.len() - 1];
+    assert_eq!(
+        deserializer
+            .deserialize_into(truncated, &mut module)
+            .expect_err("Expected bad header table"),
+        BinaryError::BadHeaderTable
+    );
+
+    deserializer.deserialize_into(&binary, &mut module).unwrap();
+    assert_eq!(
+        module,
+        CompiledModuleMut::deserialize_no_check_bounds(&binary).unwrap()
+    );
+}
+
+#[test]
+fn shared_byte_arrays_point_into_the_binary() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "Constants");
+    builder.intern_byte_array(ByteArray::new(vec![7u8; 40_000]));
+    let module = builder.build().expect("module is bounds-valid");
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+    let binary = Bytes::from(binary);
+
+    let shared = CompiledModule::deserialize_shared(&binary).unwrap();
+    assert_eq!(shared, CompiledModule::deserialize(&binary).unwrap());
+
+    let byte_array = shared.as_inner().byte_array_pool[0].as_bytes();
+    let range = binary.as_ptr() as usize..binary.as_ptr() as usize + binary.len();
+    assert!(range.contains(&(byte_array.as_ptr() as usize)));
+}
+
+#[test]
+fn modules_parse_from_bytes_and_hex() {
+    let binary = module_binary("M", 1);
+    let module = CompiledModule::deserialize(&binary).unwrap();
+    assert_eq!(CompiledModule::try_from(&binary[..]).unwrap(), module);
+
+    let hex = hex::encode(&binary);
+    let (first, second) = hex.split_at(hex.len() / 2);
+    let spaced = format!("  0x{}\n    {}\n", first, second);
+    assert_eq!(spaced.parse::<CompiledModule>().unwrap(), module);
+
+    assert!("0xzz".parse::<CompiledModule>().is_err());
+    assert!(hex::encode(&binary[..binary.len() - 1])
+        .parse::<CompiledModule>()
+        .is_err());
+}
+
+#[test]
+fn partial_deserialization_of_intact_binaries() {
+    let binary = module_binary("M", 3);
+    let partial = CompiledModuleMut::deserialize_partial(&binary);
+    assert_eq!(partial.failures, vec![]);
+    assert_eq!(
+        partial.module,
+        CompiledModuleMut::deserialize_no_check_bounds(&binary).unwrap()
+    );
+    assert_eq!(
+        partial.complete_tables.len(),
+        binary[BinaryConstants::HEADER_SIZE - 1] as usize
+    );
+}
+
+#[test]
+fn partial_deserialization_of_truncated_binaries() {
+    let binary = module_binary("M", 3);
+    let module = CompiledModuleMut::deserialize_no_check_bounds(&binary).unwrap();
+
+    // The function definitions come last, and the last instruction of the last one is cut off.
+    let partial = CompiledModuleMut::deserialize_partial(&binary[..binary.len() - 1]);
+    assert_eq!(partial.module.function_defs, module.function_defs[..2]);
+    assert_eq!(partial.module.string_pool, module.string_pool);
+    assert_eq!(partial.module.function_handles, module.function_handles);
+    assert!(!partial.complete_tables.contains(&TableType::FUNCTION_DEFS));
+    assert_eq!(partial.failures.len(), 1);
+    let failure = &partial.failures[0];
+    assert_eq!(failure.table, Some(TableType::FUNCTION_DEFS));
+    assert_eq!(failure.error, BinaryError::Malformed);
+    assert!(failure.offset < binary.len() as u64);
+
+    // Nothing can be read past a damaged header.
+    let partial = CompiledModuleMut::deserialize_partial(&binary[..5]);
+    assert_eq!(partial.module, CompiledModuleMut::default());
+    assert_eq!(
+        partial.failures,
+        vec![PartialModuleFailure {
+            table: None,
+            offset: 0,
+            error: BinaryError::Malformed,
+        }]
+    );
+}
@@ -0,0 +1,169 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    diff::{Change, ModuleDiff},
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature, SignatureToken},
+    normalize::NormalizedType,
+};
+use types::account_address::AccountAddress;
+
+fn signature(arg_types: Vec<SignatureToken>) -> FunctionSignature {
+    FunctionSignature {
+        arg_types: arg_types.into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+fn ret() -> CodeBuilder {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    code
+}
+
+fn old_module() -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_struct(
+        "S",
+        false,
+        vec![],
+        vec![
+            ("a", SignatureToken::U64),
+            ("b", SignatureToken::Bool),
+            ("c", SignatureToken::Address),
+        ],
+    );
+    builder.add_struct("R", true, vec![], vec![("v", SignatureToken::U64)]);
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature(vec![]),
+        vec![],
+        vec![],
+        ret(),
+    );
+    builder.add_function("g", 0, signature(vec![]), vec![], vec![], ret());
+    builder.add_function("h", 0, signature(vec![]), vec![], vec![], ret());
+    builder.build().expect("module is bounds-valid")
+}
+
+#[test]
+fn reordering_definitions_is_not_a_change() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_function("h", 0, signature(vec![]), vec![], vec![], ret());
+    builder.add_function("g", 0, signature(vec![]), vec![], vec![], ret());
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature(vec![]),
+        vec![],
+        vec![],
+        ret(),
+    );
+    builder.add_struct("R", true, vec![], vec![("v", SignatureToken::U64)]);
+    builder.add_struct(
+        "S",
+        false,
+        vec![],
+        vec![
+            ("a", SignatureToken::U64),
+            ("b", SignatureToken::Bool),
+            ("c", SignatureToken::Address),
+        ],
+    );
+    let new = builder.build().expect("module is bounds-valid");
+    let diff = ModuleDiff::new(&old_module(), &new);
+    assert!(diff.is_empty(), "unexpected changes:\n{}", diff);
+}
+
+#[test]
+fn changes_are_typed_and_ordered() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_struct(
+        "S",
+        false,
+        vec![],
+        vec![
+            ("b", SignatureToken::Bool),
+            ("a", SignatureToken::Bool),
+            ("d", SignatureToken::U64),
+        ],
+    );
+    builder.add_struct("R", false, vec![], vec![("v", SignatureToken::U64)]);
+    builder.add_struct("T", false, vec![], vec![("v", SignatureToken::U64)]);
+    let f_signature = signature(vec![SignatureToken::U64]);
+    builder.add_function("f", CodeUnit::PUBLIC, f_signature, vec![], vec![], ret());
+    builder.add_function(
+        "g",
+        CodeUnit::PUBLIC,
+        signature(vec![]),
+        vec![],
+        vec![],
+        ret(),
+    );
+    builder.add_function("k", 0, signature(vec![]), vec![], vec![], ret());
+    let new = builder.build().expect("module is bounds-valid");
+
+    let diff = ModuleDiff::new(&old_module(), &new);
+    let s = |s: &str| s.to_string();
+    assert_eq!(
+        &diff.changes[..4],
+        &[
+            Change::StructKindChanged {
+                name: s("R"),
+                is_nominal_resource: false,
+            },
+            Change::FieldTypeChanged {
+                struct_name: s("S"),
+                field_name: s("a"),
+                old: NormalizedType::U64,
+                new: NormalizedType::Bool,
+            },
+            Change::FieldRemoved {
+                struct_name: s("S"),
+                field_name: s("c"),
+            },
+            Change::FieldAdded {
+                struct_name: s("S"),
+                field_name: s("d"),
+            },
+        ]
+    );
+    assert_eq!(
+        diff.changes[4],
+        Change::FieldReordered {
+            struct_name: s("S"),
+            old: vec![s("a"), s("b")],
+            new: vec![s("b"), s("a")],
+        }
+    );
+    assert_eq!(diff.changes[5], Change::StructAdded(s("T")));
+    match &diff.changes[6] {
+        Change::SignatureChanged { name, old, new } => {
+            assert_eq!(name, "f");
+            assert!(old.arg_types.is_empty());
+            assert_eq!(new.arg_types, vec![NormalizedType::U64]);
+        }
+        change => panic!("unexpected change: {:?}", change),
+    }
+    // The arguments are locals too.
+    assert_eq!(
+        &diff.changes[7..],
+        &[
+            Change::CodeChanged(s("f")),
+            Change::VisibilityChanged {
+                name: s("g"),
+                is_public: true,
+            },
+            Change::FunctionRemoved(s("h")),
+            Change::FunctionAdded(s("k")),
+        ]
+    );
+
+    let lines: Vec<_> = diff.to_string().lines().map(String::from).collect();
+    assert_eq!(lines[1], "field S.a type changed from u64 to bool");
+    assert_eq!(lines[4], "fields of S reordered from (a, b) to (b, a)");
+    assert_eq!(lines[6], "function f signature changed from () to (u64)");
+}
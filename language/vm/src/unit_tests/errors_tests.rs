@@ -0,0 +1,592 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    errors::{
+        filter_by_severity, has_errors, sort_and_dedup_errors, sort_errors,
+        static_violation_to_vm_error, BinaryError, CappedErrors, DuplicateKey, ErrorCategory,
+        Severity, VMInvariantViolation, VMStaticViolation, VerificationError, VerificationStatus,
+        WithContext,
+    },
+    file_format::{
+        dummy_procedure_module, empty_module, Bytecode, FieldDefinition, FieldDefinitionIndex,
+        FunctionDefinitionIndex, ModuleHandleIndex, SignatureToken, StringPoolIndex,
+        StructDefinition, StructFieldInformation, StructHandle, StructHandleIndex, TypeSignature,
+        TypeSignatureIndex,
+    },
+    views::ModuleView,
+    IndexKind, SignatureTokenKind,
+};
+use std::{
+    collections::{BTreeSet, HashSet},
+    mem,
+};
+use types::{
+    account_address::AccountAddress,
+    language_storage::ModuleId,
+    transaction::TransactionStatus,
+    vm_error::{VMStatus, VMVerificationStatus},
+};
+
+/// Builds one instance of every variant of `$enum` from `pattern => instance` entries.
+///
+/// The patterns also make up a match with no wildcard arm, so adding a variant fails to compile
+/// until it is listed here, and each instance is checked against its own pattern.
+macro_rules! every_variant {
+    ($enum: ident { $($pattern: pat => $instance: expr,)* }) => {{
+        use $enum::*;
+
+        fn exhaustive(value: &$enum) {
+            use $enum::*;
+
+            match value {
+                $($pattern => (),)*
+            }
+        }
+
+        vec![$({
+            let instance = $instance;
+            exhaustive(&instance);
+            assert!(
+                match &instance {
+                    $pattern => true,
+                    _ => false,
+                },
+                "{:?} doesn't match {}",
+                instance,
+                stringify!($pattern),
+            );
+            instance
+        },)*]
+    }};
+}
+
+/// One instance of every `VMStaticViolation` variant.
+pub(crate) fn all_static_violations() -> Vec<VMStaticViolation> {
+    every_variant!(VMStaticViolation {
+        IndexOutOfBounds(..) => IndexOutOfBounds(IndexKind::ModuleHandle, 0, 0),
+        CodeUnitIndexOutOfBounds(..) => {
+            CodeUnitIndexOutOfBounds(IndexKind::ModuleHandle, 0, 0, 0)
+        }
+        RangeOutOfBounds(..) => RangeOutOfBounds(IndexKind::ModuleHandle, 0, 0, 0),
+        CodeUnitTooLong(..) => CodeUnitTooLong(0),
+        NoModuleHandles => NoModuleHandles,
+        ModuleAddressDoesNotMatchSender => ModuleAddressDoesNotMatchSender,
+        InvalidSignatureToken(..) => InvalidSignatureToken(
+            SignatureToken::Bool,
+            SignatureTokenKind::Value,
+            SignatureTokenKind::Value,
+        ),
+        DuplicateElement(..) => DuplicateElement(0, DuplicateKey::Value),
+        InvalidModuleHandle => InvalidModuleHandle,
+        UnimplementedHandle => UnimplementedHandle,
+        InconsistentFields => InconsistentFields,
+        UnusedFields => UnusedFields,
+        InvalidFieldDefReference(..) => {
+            InvalidFieldDefReference(SignatureToken::Bool, SignatureTokenKind::Value)
+        }
+        RecursiveStructDef => RecursiveStructDef,
+        InvalidResourceField => InvalidResourceField,
+        InvalidFallThrough => InvalidFallThrough,
+        JoinFailure(..) => JoinFailure(0),
+        NegativeStackSizeInsideBlock(..) => NegativeStackSizeInsideBlock(0, 0),
+        PositiveStackSizeAtBlockEnd(..) => PositiveStackSizeAtBlockEnd(0),
+        InvalidMainFunctionSignature => InvalidMainFunctionSignature,
+        LookupFailed => LookupFailed,
+        VisibilityMismatch => VisibilityMismatch,
+        TypeResolutionFailure => TypeResolutionFailure,
+        TypeMismatch => TypeMismatch,
+        MissingDependency => MissingDependency,
+        PopReferenceError(..) => PopReferenceError(0),
+        PopResourceError(..) => PopResourceError(0),
+        ReleaseRefTypeMismatchError(..) => ReleaseRefTypeMismatchError(0),
+        BrTypeMismatchError(..) => BrTypeMismatchError(0),
+        AbortTypeMismatchError(..) => AbortTypeMismatchError(0),
+        StLocTypeMismatchError(..) => StLocTypeMismatchError(0),
+        StLocUnsafeToDestroyError(..) => StLocUnsafeToDestroyError(0),
+        RetUnsafeToDestroyError(..) => RetUnsafeToDestroyError(0),
+        RetTypeMismatchError(..) => RetTypeMismatchError(0),
+        FreezeRefTypeMismatchError(..) => FreezeRefTypeMismatchError(0),
+        FreezeRefExistsMutableBorrowError(..) => FreezeRefExistsMutableBorrowError(0),
+        BorrowFieldTypeMismatchError(..) => BorrowFieldTypeMismatchError(0),
+        BorrowFieldBadFieldError(..) => BorrowFieldBadFieldError(0),
+        BorrowFieldExistsMutableBorrowError(..) => BorrowFieldExistsMutableBorrowError(0),
+        CopyLocUnavailableError(..) => CopyLocUnavailableError(0),
+        CopyLocResourceError(..) => CopyLocResourceError(0),
+        CopyLocExistsBorrowError(..) => CopyLocExistsBorrowError(0),
+        MoveLocUnavailableError(..) => MoveLocUnavailableError(0),
+        MoveLocExistsBorrowError(..) => MoveLocExistsBorrowError(0),
+        BorrowLocReferenceError(..) => BorrowLocReferenceError(0),
+        BorrowLocUnavailableError(..) => BorrowLocUnavailableError(0),
+        BorrowLocExistsBorrowError(..) => BorrowLocExistsBorrowError(0),
+        CallTypeMismatchError(..) => CallTypeMismatchError(0),
+        CallBorrowedMutableReferenceError(..) => CallBorrowedMutableReferenceError(0),
+        PackTypeMismatchError(..) => PackTypeMismatchError(0),
+        UnpackTypeMismatchError(..) => UnpackTypeMismatchError(0),
+        ReadRefTypeMismatchError(..) => ReadRefTypeMismatchError(0),
+        ReadRefResourceError(..) => ReadRefResourceError(0),
+        ReadRefExistsMutableBorrowError(..) => ReadRefExistsMutableBorrowError(0),
+        WriteRefTypeMismatchError(..) => WriteRefTypeMismatchError(0),
+        WriteRefResourceError(..) => WriteRefResourceError(0),
+        WriteRefExistsBorrowError(..) => WriteRefExistsBorrowError(0),
+        WriteRefNoMutableReferenceError(..) => WriteRefNoMutableReferenceError(0),
+        IntegerOpTypeMismatchError(..) => IntegerOpTypeMismatchError(0),
+        BooleanOpTypeMismatchError(..) => BooleanOpTypeMismatchError(0),
+        EqualityOpTypeMismatchError(..) => EqualityOpTypeMismatchError(0),
+        ExistsResourceTypeMismatchError(..) => ExistsResourceTypeMismatchError(0),
+        ExistsNoResourceError(..) => ExistsNoResourceError(0),
+        BorrowGlobalTypeMismatchError(..) => BorrowGlobalTypeMismatchError(0),
+        BorrowGlobalNoResourceError(..) => BorrowGlobalNoResourceError(0),
+        MoveFromTypeMismatchError(..) => MoveFromTypeMismatchError(0),
+        MoveFromNoResourceError(..) => MoveFromNoResourceError(0),
+        MoveToSenderTypeMismatchError(..) => MoveToSenderTypeMismatchError(0),
+        MoveToSenderNoResourceError(..) => MoveToSenderNoResourceError(0),
+        CreateAccountTypeMismatchError(..) => CreateAccountTypeMismatchError(0),
+        GlobalReferenceError(..) => GlobalReferenceError(0),
+        MissingAcquiresResourceAnnotationError(..) => MissingAcquiresResourceAnnotationError(0),
+        ExtraneousAcquiresResourceAnnotationError => ExtraneousAcquiresResourceAnnotationError,
+        DuplicateAcquiresResourceAnnotationError => DuplicateAcquiresResourceAnnotationError,
+        InvalidAcquiresResourceAnnotationError => InvalidAcquiresResourceAnnotationError,
+        ConstraintKindMismatch => ConstraintKindMismatch,
+        NumberOfTypeActualsMismatch(..) => NumberOfTypeActualsMismatch(0, 0),
+        VerificationBudgetExceeded => VerificationBudgetExceeded,
+        StackHeightLimitExceeded(..) => StackHeightLimitExceeded(0),
+        InvalidMainFunctionReturn(..) => InvalidMainFunctionReturn(0),
+        InvalidMainFunctionArgument(..) => InvalidMainFunctionArgument(0, SignatureToken::Bool),
+        StructKindMismatch(..) => StructKindMismatch(0),
+        FunctionSignatureMismatch(..) => FunctionSignatureMismatch(0),
+        FunctionVisibilityMismatch(..) => FunctionVisibilityMismatch(0),
+        NativeFlagMismatch(..) => NativeFlagMismatch(0),
+        CyclicModuleDependency(..) => CyclicModuleDependency(vec![]),
+        TooManyTypeParameters => TooManyTypeParameters,
+        TypeInstantiationTooDeep => TypeInstantiationTooDeep,
+        TooManyStructFields => TooManyStructFields,
+        TooManyBasicBlocks => TooManyBasicBlocks,
+        UnreachableBlock(..) => UnreachableBlock(0),
+        DeadCodeAfterBranch(..) => DeadCodeAfterBranch(0),
+        UnknownNativeFunction => UnknownNativeFunction,
+        NativeFunctionSignatureMismatch => NativeFunctionSignatureMismatch,
+        IrreducibleControlFlow(..) => IrreducibleControlFlow(0),
+        UncheckedArithmetic(..) => UncheckedArithmetic(0),
+        UnboundedLoop(..) => UnboundedLoop(0),
+        ResourceReturnedByReference(..) => ResourceReturnedByReference(0),
+    })
+}
+
+fn all_invariant_violations() -> Vec<VMInvariantViolation> {
+    every_variant!(VMInvariantViolation {
+        IndexOutOfBounds(..) => IndexOutOfBounds(IndexKind::ModuleHandle, 0, 0),
+        RangeOutOfBounds(..) => RangeOutOfBounds(IndexKind::ModuleHandle, 0, 0, 0),
+        EmptyValueStack => EmptyValueStack,
+        EmptyCallStack => EmptyCallStack,
+        ProgramCounterOverflow => ProgramCounterOverflow,
+        LinkerError => LinkerError,
+        LocalReferenceError => LocalReferenceError,
+        StorageError => StorageError,
+        InternalTypeError => InternalTypeError,
+        EventKeyMismatch => EventKeyMismatch,
+    })
+}
+
+fn all_binary_errors() -> Vec<BinaryError> {
+    every_variant!(BinaryError {
+        Malformed => Malformed,
+        BadMagic => BadMagic,
+        UnknownVersion => UnknownVersion,
+        UnknownTableType => UnknownTableType,
+        UnknownSignatureType => UnknownSignatureType,
+        UnexpectedSignatureType => UnexpectedSignatureType,
+        UnknownSerializedType => UnknownSerializedType,
+        UnknownOpcode => UnknownOpcode,
+        BadHeaderTable => BadHeaderTable,
+        DuplicateTable => DuplicateTable,
+    })
+}
+
+#[test]
+fn error_codes_are_unique() {
+    let codes: Vec<_> = all_static_violations()
+        .iter()
+        .map(VMStaticViolation::code)
+        .chain(
+            all_invariant_violations()
+                .iter()
+                .map(VMInvariantViolation::code),
+        )
+        .chain(all_binary_errors().iter().map(BinaryError::code))
+        .collect();
+    let unique: BTreeSet<_> = codes.iter().collect();
+    assert_eq!(codes.len(), unique.len(), "duplicate error codes");
+}
+
+#[test]
+fn error_codes_match_categories() {
+    for err in all_static_violations() {
+        let category = err.category();
+        assert!(
+            category != ErrorCategory::Binary && category != ErrorCategory::Invariant,
+            "{:?} has category {}",
+            err,
+            category
+        );
+        assert_eq!(err.code() / 1000, category.base_code() / 1000);
+    }
+    for err in all_invariant_violations() {
+        assert_eq!(ErrorCategory::of_code(err.code()), Some(err.category()));
+    }
+    for err in all_binary_errors() {
+        assert_eq!(ErrorCategory::of_code(err.code()), Some(err.category()));
+    }
+}
+
+#[test]
+fn error_codes_are_stable() {
+    // Spot-check a few codes: these are part of the external interface and must never change.
+    assert_eq!(BinaryError::Malformed.code(), 1001);
+    assert_eq!(
+        VMStaticViolation::IndexOutOfBounds(IndexKind::StringPool, 0, 0).code(),
+        2001
+    );
+    assert_eq!(
+        VMStaticViolation::DuplicateElement(0, DuplicateKey::Value).code(),
+        3003
+    );
+    assert_eq!(VMStaticViolation::RetTypeMismatchError(4).code(), 6009);
+    assert_eq!(
+        VMStaticViolation::GlobalReferenceError(4).category(),
+        ErrorCategory::Resource
+    );
+    assert_eq!(VMInvariantViolation::LinkerError.code(), 9006);
+    assert_eq!(ErrorCategory::of_code(42), None);
+}
+
+#[test]
+fn verification_errors_roundtrip_through_serde() {
+    for (idx, err) in all_static_violations().into_iter().enumerate() {
+        let err = VerificationError::new(IndexKind::FunctionDefinition, idx, err);
+        let statuses = vec![
+            VerificationStatus::Script(err.clone()),
+            VerificationStatus::Module(idx as u16, err.clone()),
+            VerificationStatus::Dependency(ModuleId::new(Default::default(), "M".into()), err),
+        ];
+        for status in statuses {
+            let json = serde_json::to_string(&status).expect("serialization should work");
+            let decoded: VerificationStatus =
+                serde_json::from_str(&json).expect("deserialization should work");
+            assert_eq!(decoded, status);
+        }
+    }
+}
+
+#[test]
+fn signature_tokens_roundtrip_through_serde() {
+    let token = SignatureToken::MutableReference(Box::new(SignatureToken::Struct(
+        StructHandleIndex::new(3),
+        vec![SignatureToken::TypeParameter(0), SignatureToken::ByteArray],
+    )));
+    let json = serde_json::to_string(&token).expect("serialization should work");
+    let decoded: SignatureToken = serde_json::from_str(&json).expect("deserialization should work");
+    assert_eq!(decoded, token);
+}
+
+#[test]
+fn code_errors_are_attributed_to_functions() {
+    let module = dummy_procedure_module(vec![Bytecode::Pop, Bytecode::Ret]);
+    let view = ModuleView::new(&module);
+
+    let err = VerificationError::in_function(
+        FunctionDefinitionIndex::new(0),
+        VMStaticViolation::PopReferenceError(0),
+    );
+    assert_eq!(err.code_offset, Some(0));
+    assert_eq!(
+        err.display_with(&view).to_string(),
+        "in function 0x0::<SELF>::<SELF> at code offset 0 (Pop): Unable to verify Pop at offset 0"
+    );
+
+    // Violations without an instruction offset are still attributed to the function.
+    let err = VerificationError::in_function(
+        FunctionDefinitionIndex::new(0),
+        VMStaticViolation::InvalidFallThrough,
+    );
+    assert_eq!(err.code_offset, None);
+    assert_eq!(
+        err.display_with(&view).to_string(),
+        "in function 0x0::<SELF>::<SELF>: Invalid fall through"
+    );
+
+    // Errors that can't be resolved fall back to the plain format.
+    let err = VerificationError::in_function(
+        FunctionDefinitionIndex::new(5),
+        VMStaticViolation::PopReferenceError(3),
+    );
+    assert_eq!(err.display_with(&view).to_string(), err.to_string());
+    assert_eq!(
+        err.to_string(),
+        "at 'function definition' index 5 code offset 3: Unable to verify Pop at offset 3"
+    );
+}
+
+#[test]
+fn severity_filtering() {
+    assert!(Severity::Note < Severity::Warning);
+    assert!(Severity::Warning < Severity::Error);
+
+    let errors: Vec<_> = all_static_violations()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, err)| VerificationError::new(IndexKind::FunctionDefinition, idx, err))
+        .collect();
+    // Only the unreachable code violations and the lints are advisory.
+    let warnings: Vec<_> = errors.iter().filter(|err| !err.is_error()).collect();
+    assert_eq!(warnings.len(), 5);
+    assert!(warnings
+        .iter()
+        .all(|err| err.severity() == Severity::Warning));
+    assert!(has_errors(&errors));
+    assert!(!has_errors(&[]));
+    assert!(!has_errors(&[warnings[0].clone()]));
+    for min_severity in &[Severity::Note, Severity::Warning] {
+        assert_eq!(
+            filter_by_severity(&errors, *min_severity).count(),
+            errors.len()
+        );
+    }
+    assert_eq!(
+        filter_by_severity(&errors, Severity::Error).count(),
+        errors.len() - warnings.len()
+    );
+}
+
+#[test]
+fn context_is_chained_outermost_first() {
+    let errors = vec![VerificationError::new(
+        IndexKind::StructDefinition,
+        4,
+        VMStaticViolation::InvalidResourceField,
+    )];
+    let errors = errors
+        .with_context(|| "type signature")
+        .with_context(|| "field 2")
+        .with_context(|| format!("struct_defs[{}]", 4));
+    assert_eq!(
+        errors[0].context,
+        vec!["struct_defs[4]", "field 2", "type signature"]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "at 'struct definition' index 4: Resource field in non-resource struct \
+         (while checking struct_defs[4] > field 2 > type signature)"
+    );
+
+    let json = serde_json::to_string(&errors).expect("serialization should work");
+    let decoded: Vec<VerificationError> =
+        serde_json::from_str(&json).expect("deserialization should work");
+    assert_eq!(decoded, errors);
+
+    // Context is only computed if there is an error to attach it to.
+    let ok: Result<(), Vec<VerificationError>> = Ok(());
+    let _ = ok.with_context(|| -> String { panic!("context should not be computed") });
+}
+
+#[test]
+fn display_with_resolves_qualified_names() {
+    let mut address = [0u8; 32];
+    address[31] = 1;
+    let mut module = empty_module();
+    module.address_pool[0] = AccountAddress::new(address);
+    module.string_pool = vec!["Coin".into(), "T".into(), "value".into()];
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(1),
+        is_nominal_resource: false,
+        type_formals: vec![],
+    });
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    module.field_defs.push(FieldDefinition {
+        struct_: StructHandleIndex::new(0),
+        name: StringPoolIndex::new(2),
+        signature: TypeSignatureIndex::new(0),
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+    let module = module.freeze().expect("module should be bounds checked");
+    let view = ModuleView::new(&module);
+
+    let render = |kind, idx| {
+        VerificationError::new(
+            kind,
+            idx,
+            VMStaticViolation::DuplicateElement(1, DuplicateKey::Name),
+        )
+        .display_with(&view)
+        .to_string()
+    };
+    assert_eq!(
+        render(IndexKind::ModuleHandle, 0),
+        "in module handle 0x1::Coin: Duplicate element: same name as the entry at index 1"
+    );
+    assert_eq!(
+        render(IndexKind::StructDefinition, 0),
+        "in struct 0x1::Coin::T: Duplicate element: same name as the entry at index 1"
+    );
+    assert_eq!(
+        render(IndexKind::FieldDefinition, 0),
+        "in field 0x1::Coin::T.value: Duplicate element: same name as the entry at index 1"
+    );
+    // Indexes that don't resolve to a name are left as is.
+    assert_eq!(
+        render(IndexKind::TypeSignature, 0),
+        "at 'type signature' index 0: Duplicate element: same name as the entry at index 1"
+    );
+    assert_eq!(
+        render(IndexKind::StructDefinition, 1),
+        "at 'struct definition' index 1: Duplicate element: same name as the entry at index 1"
+    );
+}
+
+#[test]
+fn static_violations_map_to_distinct_vm_statuses() {
+    // Names that differ between `VMStaticViolation` and `VMVerificationError` for historical
+    // reasons.
+    let renames = [
+        ("RecursiveStructDef", "RecursiveStructDefinition"),
+        (
+            "NegativeStackSizeInsideBlock",
+            "NegativeStackSizeWithinBlock",
+        ),
+        ("PositiveStackSizeAtBlockEnd", "UnbalancedStack"),
+    ];
+    let variant_name = |debug: String| debug.split('(').next().unwrap().to_string();
+
+    // `all_static_violations` has every variant, so this covers the whole mapping.
+    let violations = all_static_violations();
+    let mut seen = HashSet::new();
+    let mut renamed = HashSet::new();
+    for violation in &violations {
+        let error = static_violation_to_vm_error(violation, "message".to_string());
+        assert!(
+            seen.insert(mem::discriminant(&error)),
+            "{:?} maps to a status shared with another violation",
+            violation
+        );
+
+        let violation_name = variant_name(format!("{:?}", violation));
+        let expected_name = renames
+            .iter()
+            .find(|(from, _)| *from == violation_name)
+            .map_or(violation_name.clone(), |(from, to)| {
+                renamed.insert(*from);
+                to.to_string()
+            });
+        assert_eq!(variant_name(format!("{:?}", error)), expected_name);
+
+        // Verification failures are never charged for.
+        let status = VMStatus::Verification(vec![VMVerificationStatus::Script(error)]);
+        assert_eq!(
+            TransactionStatus::from(status.clone()),
+            TransactionStatus::Discard(status)
+        );
+    }
+    assert_eq!(renamed.len(), renames.len(), "stale entries in `renames`");
+}
+
+#[test]
+fn errors_sort_and_dedup_deterministically() {
+    let error = |kind, idx, err| VerificationError::new(kind, idx, err);
+    let errors = vec![
+        error(
+            IndexKind::StructDefinition,
+            1,
+            VMStaticViolation::UnusedFields,
+        ),
+        error(
+            IndexKind::FieldDefinition,
+            0,
+            VMStaticViolation::DuplicateElement(0, DuplicateKey::Handle),
+        ),
+        error(
+            IndexKind::StructDefinition,
+            0,
+            VMStaticViolation::UnusedFields,
+        ),
+        error(
+            IndexKind::StructDefinition,
+            0,
+            VMStaticViolation::DuplicateElement(0, DuplicateKey::Handle),
+        ),
+        error(
+            IndexKind::StructDefinition,
+            1,
+            VMStaticViolation::UnusedFields,
+        ),
+    ];
+
+    let mut sorted = errors.clone();
+    sort_errors(&mut sorted);
+    let mut reversed: Vec<_> = errors.iter().rev().cloned().collect();
+    sort_errors(&mut reversed);
+    assert_eq!(sorted, reversed);
+
+    let mut deduped = errors;
+    sort_and_dedup_errors(&mut deduped);
+    assert_eq!(
+        deduped,
+        vec![
+            error(
+                IndexKind::StructDefinition,
+                0,
+                VMStaticViolation::DuplicateElement(0, DuplicateKey::Handle),
+            ),
+            error(
+                IndexKind::StructDefinition,
+                0,
+                VMStaticViolation::UnusedFields
+            ),
+            error(
+                IndexKind::StructDefinition,
+                1,
+                VMStaticViolation::UnusedFields
+            ),
+            error(
+                IndexKind::FieldDefinition,
+                0,
+                VMStaticViolation::DuplicateElement(0, DuplicateKey::Handle)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn capped_errors_count_suppressed_errors() {
+    let errors: Vec<_> = all_static_violations()
+        .into_iter()
+        .enumerate()
+        .map(|(idx, err)| VerificationError::new(IndexKind::FunctionDefinition, idx, err))
+        .collect();
+
+    let mut capped = CappedErrors::new(3);
+    assert!(capped.is_empty());
+    capped.extend(errors.iter().cloned());
+    assert!(capped.is_truncated());
+    assert_eq!(capped.errors(), &errors[..3]);
+    assert_eq!(capped.suppressed(), errors.len() - 3);
+    assert_eq!(capped.total(), errors.len());
+    assert!(capped.to_string().ends_with(&format!(
+        "... {} more errors suppressed\n",
+        errors.len() - 3
+    )));
+
+    // Suppressed errors still count as errors.
+    let mut capped = CappedErrors::new(0);
+    capped.push(errors[0].clone());
+    assert!(!capped.is_empty());
+    assert_eq!(capped.into_errors(), vec![]);
+}
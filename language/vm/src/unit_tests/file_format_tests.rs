@@ -0,0 +1,197 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    access::ModuleAccess,
+    check_bounds::FreezeDiagnostic,
+    errors::VMStaticViolation,
+    file_format::{
+        empty_module, Bytecode, CheckedCodeOffset, CodeOffset, FunctionHandle, FunctionHandleIndex,
+        FunctionSignatureIndex, LocalsSignatureIndex, ModuleHandleIndex, SignatureToken,
+        StackEffect, StringPoolIndex, StructDefinitionIndex, StructHandleIndex, SELF_MODULE_NAME,
+    },
+    IndexKind,
+};
+use std::{collections::HashSet, convert::TryFrom};
+
+#[test]
+fn indexes_display_their_kind() {
+    assert_eq!(StructHandleIndex::new(3).to_string(), "struct handle #3");
+    assert_eq!(
+        format!(
+            "{:?}",
+            Bytecode::Call(FunctionHandleIndex::new(2), LocalsSignatureIndex::new(0))
+        ),
+        "Call(2, LocalsSignatureIndex(0))"
+    );
+}
+
+#[test]
+fn checked_indexes() {
+    assert_eq!(
+        StructHandleIndex::try_new(2, 3),
+        Ok(StructHandleIndex::new(2))
+    );
+    assert_eq!(
+        StructHandleIndex::try_new(3, 3),
+        Err(VMStaticViolation::IndexOutOfBounds(
+            IndexKind::StructHandle,
+            3,
+            3
+        ))
+    );
+    assert_eq!(
+        StructHandleIndex::try_new(1 << 16, 1 << 20),
+        Err(VMStaticViolation::IndexOutOfBounds(
+            IndexKind::StructHandle,
+            1 << 16,
+            1 << 16
+        ))
+    );
+
+    assert_eq!(
+        FunctionHandleIndex::try_from(65535),
+        Ok(FunctionHandleIndex::new(65535))
+    );
+    assert!(FunctionHandleIndex::try_from(65536).is_err());
+}
+
+#[test]
+fn signature_tokens_are_ordered_structurally() {
+    use SignatureToken::*;
+
+    let struct_of = |idx, actuals| Struct(StructHandleIndex::new(idx), actuals);
+    let ordered = vec![
+        Bool,
+        U64,
+        String,
+        ByteArray,
+        Address,
+        struct_of(0, vec![]),
+        struct_of(0, vec![Bool]),
+        struct_of(0, vec![U64]),
+        struct_of(1, vec![]),
+        Reference(Box::new(Bool)),
+        Reference(Box::new(struct_of(0, vec![]))),
+        MutableReference(Box::new(Bool)),
+        TypeParameter(0),
+        TypeParameter(1),
+    ];
+    let mut sorted = ordered.clone();
+    sorted.reverse();
+    sorted.sort();
+    assert_eq!(sorted, ordered);
+
+    let set: HashSet<_> = ordered.iter().cloned().chain(ordered.clone()).collect();
+    assert_eq!(set.len(), ordered.len());
+}
+
+#[test]
+fn code_offsets_dont_wrap() {
+    let last = CheckedCodeOffset::new(CodeOffset::max_value());
+    assert_eq!(last.next(), None);
+    assert_eq!(last.checked_add(0), Some(last));
+    assert_eq!(CheckedCodeOffset::new(0).checked_sub(1), None);
+    assert_eq!(
+        CheckedCodeOffset::new(5).checked_sub(2),
+        Some(CheckedCodeOffset::new(3))
+    );
+
+    let max_index = CodeOffset::max_value() as usize;
+    assert_eq!(CheckedCodeOffset::from_index(max_index), Some(last));
+    assert_eq!(CheckedCodeOffset::from_index(max_index + 1), None);
+    assert!(CheckedCodeOffset::try_from(max_index + 1).is_err());
+
+    let offset = CheckedCodeOffset::try_from(7).unwrap();
+    assert_eq!(offset.into_index(), 7);
+    assert_eq!(CodeOffset::from(offset), 7);
+    assert_eq!(offset.to_string(), "offset 7");
+}
+
+#[test]
+fn freeze_reports_the_field_at_fault() {
+    let mut module = empty_module();
+    module.function_handles.push(FunctionHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(0),
+        signature: FunctionSignatureIndex::new(3),
+    });
+    let err = module.freeze().unwrap_err();
+
+    let reason = VMStaticViolation::IndexOutOfBounds(IndexKind::FunctionSignature, 0, 3);
+    assert_eq!(
+        err.diagnostics(),
+        vec![FreezeDiagnostic {
+            table: IndexKind::FunctionHandle,
+            index: 0,
+            field: Some("signature"),
+            code_offset: None,
+            reason: reason.clone(),
+        }]
+    );
+    assert_eq!(err.errors().len(), 1);
+    assert_eq!(err.errors()[0].err, reason);
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "module fails 1 bounds checks\n  function handle #0, field `signature`: {}",
+            reason
+        )
+    );
+    // Debug-printing, e.g. by `unwrap`, shows the same description.
+    assert_eq!(format!("{:?}", err), err.to_string());
+}
+
+#[test]
+fn stack_effects() {
+    assert_eq!(
+        Bytecode::Add.stack_effect(),
+        StackEffect::Fixed { pops: 2, pushes: 1 }
+    );
+    assert_eq!(Bytecode::WriteRef.stack_effect().net(), Some(-2));
+    assert_eq!(
+        Bytecode::ImmBorrowField(Default::default())
+            .stack_effect()
+            .net(),
+        Some(0)
+    );
+    assert_eq!(Bytecode::LdTrue.stack_effect().net(), Some(1));
+    for bytecode in &[
+        Bytecode::Ret,
+        Bytecode::Call(FunctionHandleIndex::new(0), LocalsSignatureIndex::new(0)),
+        Bytecode::Pack(StructDefinitionIndex::new(0), LocalsSignatureIndex::new(0)),
+        Bytecode::Unpack(StructDefinitionIndex::new(0), LocalsSignatureIndex::new(0)),
+    ] {
+        assert_eq!(bytecode.stack_effect(), StackEffect::SignatureDependent);
+        assert_eq!(bytecode.stack_effect().net(), None);
+    }
+
+    // A sequence of fixed effects can be checked without a module.
+    let code = [
+        Bytecode::LdConst(1),
+        Bytecode::LdConst(2),
+        Bytecode::Add,
+        Bytecode::LdConst(3),
+        Bytecode::Lt,
+        Bytecode::BrTrue(0),
+    ];
+    let mut height = 0;
+    for bytecode in &code {
+        height += bytecode.stack_effect().net().unwrap();
+        assert!(height >= 0);
+    }
+    assert_eq!(height, 0);
+}
+
+#[test]
+fn module_clones_share_tables() {
+    let module = empty_module()
+        .freeze()
+        .expect("an empty module is bounds-valid");
+    let clone = module.clone();
+    assert!(std::ptr::eq(module.as_inner(), clone.as_inner()));
+    // A shared module is copied on conversion, leaving the clone intact.
+    let mut inner = module.into_inner();
+    inner.string_pool.clear();
+    assert_eq!(clone.string_at(clone.self_handle().name), SELF_MODULE_NAME);
+}
@@ -0,0 +1,45 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    file_format::Bytecode,
+    gas_schedule::{
+        AbstractMemorySize, BoundedGasMeter, GasAlgebra, GasCharge, GasMetering, GasUnits,
+        InstructionKey, RecordingGasMeter, UnmeteredGasMeter, MAXIMUM_NUMBER_OF_GAS_UNITS,
+    },
+};
+
+#[test]
+fn unmetered_meters_accept_every_charge() {
+    let mut meter = UnmeteredGasMeter;
+    let charge = GasCharge::other(AbstractMemorySize::new(0), GasUnits::new(u64::max_value()));
+    assert!(meter.charge(charge));
+    assert!(meter.charge(charge));
+    assert_eq!(meter.remaining_gas(), *MAXIMUM_NUMBER_OF_GAS_UNITS);
+}
+
+#[test]
+fn recording_meters_record_every_charge() {
+    let mut meter = RecordingGasMeter::new(BoundedGasMeter::new(GasUnits::new(10)));
+    let add =
+        GasCharge::for_instruction(&Bytecode::Add, AbstractMemorySize::new(1), GasUnits::new(4));
+    let intrinsic = GasCharge::other(AbstractMemorySize::new(100), GasUnits::new(5));
+    assert!(meter.charge(add));
+    assert!(meter.charge(intrinsic));
+    assert_eq!(meter.remaining_gas(), GasUnits::new(1));
+    // A charge the meter can't cover is recorded as well, but not counted as charged.
+    assert!(!meter.charge(add));
+    assert_eq!(meter.remaining_gas(), GasUnits::new(0));
+
+    assert_eq!(meter.charges(), &[add, intrinsic, add][..]);
+    assert_eq!(
+        meter.charges()[0].instruction,
+        Some(InstructionKey::new(&Bytecode::Add))
+    );
+    assert_eq!(meter.charges()[1].instruction, None);
+    assert_eq!(meter.total_charged(), GasUnits::new(9));
+
+    let mut unmetered = RecordingGasMeter::<UnmeteredGasMeter>::default();
+    assert!(unmetered.charge(intrinsic));
+    assert_eq!(unmetered.charges(), &[intrinsic][..]);
+}
@@ -0,0 +1,69 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::identifier::Identifier;
+use std::{collections::HashSet, thread};
+
+#[test]
+fn equal_names_share_an_identifier() {
+    let first = Identifier::new("shared_name");
+    let second = Identifier::from("shared_name".to_string());
+    assert_eq!(first, second);
+    assert_eq!(first.as_str().as_ptr(), second.as_str().as_ptr());
+    assert_ne!(first, Identifier::new("other_name"));
+
+    let set: HashSet<_> = vec![first.clone(), second, Identifier::new("other_name")]
+        .into_iter()
+        .collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&first));
+}
+
+#[test]
+fn identifiers_compare_to_strings() {
+    let identifier = Identifier::new("Coin");
+    assert_eq!(identifier, "Coin");
+    assert_eq!(identifier.to_string(), "Coin");
+    assert_eq!(format!("{:?}", identifier), "\"Coin\"");
+    assert!(Identifier::new("A") < Identifier::new("B"));
+}
+
+#[test]
+fn names_are_released_with_their_last_identifier() {
+    assert_eq!(Identifier::find("released_name"), None);
+    let identifier = Identifier::new("released_name");
+    let clone = identifier.clone();
+    drop(identifier);
+    assert_eq!(Identifier::find("released_name"), Some(clone.clone()));
+    drop(clone);
+    assert_eq!(Identifier::find("released_name"), None);
+}
+
+#[test]
+fn concurrent_interning_shares_identifiers() {
+    let names: Vec<_> = (0..100)
+        .map(|idx| format!("concurrent_name_{}", idx))
+        .collect();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let names = names.clone();
+            thread::spawn(move || {
+                names
+                    .iter()
+                    .map(|name| Identifier::new(name.as_str()))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let interned: Vec<_> = handles
+        .into_iter()
+        .map(|handle| handle.join().expect("interning thread should not panic"))
+        .collect();
+    for identifiers in &interned[1..] {
+        assert_eq!(identifiers, &interned[0]);
+    }
+    for (identifier, name) in interned[0].iter().zip(&names) {
+        assert_eq!(identifier, name);
+        assert_eq!(Identifier::find(name).as_ref(), Some(identifier));
+    }
+}
@@ -0,0 +1,41 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::file_format::{empty_module, SignatureToken, StructHandleIndex, TypeSignature};
+use types::byte_array::ByteArray;
+
+#[test]
+fn byte_arrays_are_counted_by_length() {
+    let mut module = empty_module();
+    let before = module.memory_usage();
+    module.byte_array_pool = vec![ByteArray::new(vec![0u8; 1000])];
+    let after = module.memory_usage();
+
+    assert!(after.byte_arrays >= 1000);
+    assert_eq!(after.strings, before.strings);
+    assert_eq!(
+        after.total() - before.total(),
+        after.byte_arrays - before.byte_arrays
+    );
+}
+
+#[test]
+fn nested_signatures_are_counted() {
+    let mut module = empty_module();
+    let mut token = SignatureToken::U64;
+    for _ in 0..100 {
+        token = SignatureToken::Struct(StructHandleIndex::new(0), vec![token]);
+    }
+    module.type_signatures = vec![TypeSignature(token)];
+
+    let usage = module.memory_usage();
+    assert!(usage.signatures >= 100 * std::mem::size_of::<SignatureToken>());
+}
+
+#[test]
+fn clones_report_the_same_usage() {
+    let module = empty_module().freeze().unwrap();
+    let clone = module.clone();
+    assert_eq!(module.memory_usage(), clone.memory_usage());
+    assert_eq!(module.memory_usage(), module.as_inner().memory_usage());
+}
@@ -1,7 +1,35 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+mod access_tests;
 mod binary_tests;
+mod builder_tests;
+mod coverage_tests;
 mod deserializer_tests;
+mod diff_tests;
+mod errors_tests;
+mod file_format_tests;
 mod fixture_tests;
+mod gas_schedule_tests;
+mod identifier_tests;
+mod memory_usage_tests;
+mod module_cache_tests;
+mod module_registry_tests;
+mod normalize_tests;
 mod number_tests;
+mod package_tests;
+#[cfg(feature = "parallel")]
+mod parallel_tests;
+mod query_tests;
+mod raw_code_tests;
+mod reference_interpreter_tests;
+mod sarif_tests;
+mod script_allowlist_tests;
+mod script_arguments_tests;
+mod serializer_tests;
+mod signed_module_tests;
+mod source_map_tests;
+mod symbolic_execution_tests;
+mod test_helpers_tests;
+mod transaction_metadata_tests;
+mod views_tests;
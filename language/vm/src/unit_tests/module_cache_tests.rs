@@ -0,0 +1,82 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature},
+    module_cache::ModuleCache,
+};
+use types::account_address::AccountAddress;
+
+fn binary(name: &str) -> Vec<u8> {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), name);
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    let signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    let module = builder.build().expect("module is bounds-valid");
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+    binary
+}
+
+#[test]
+fn modules_are_deserialized_once() {
+    let cache = ModuleCache::new(2);
+    assert!(cache.is_empty());
+    let a = binary("A");
+    let module = cache.deserialize(&a).unwrap();
+    assert_eq!(module, CompiledModule::deserialize(&a).unwrap());
+    assert!(cache.contains(&a));
+    assert_eq!(cache.deserialize(&a).unwrap(), module);
+    assert_eq!(
+        cache.deserialize_no_check_bounds(&a).unwrap(),
+        module.into_inner()
+    );
+    assert_eq!(cache.len(), 1);
+    cache.clear();
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn unchecked_modules_are_checked_on_demand() {
+    let cache = ModuleCache::new(2);
+    let a = binary("A");
+    let unchecked = cache.deserialize_no_check_bounds(&a).unwrap();
+    assert!(cache.contains(&a));
+    assert_eq!(cache.deserialize(&a).unwrap().into_inner(), unchecked);
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn least_recently_used_modules_are_evicted() {
+    let cache = ModuleCache::new(2);
+    assert_eq!(cache.capacity(), 2);
+    let (a, b, c) = (binary("A"), binary("B"), binary("C"));
+    cache.deserialize(&a).unwrap();
+    cache.deserialize(&b).unwrap();
+    // Using A makes B the least recently used module.
+    cache.deserialize(&a).unwrap();
+    cache.deserialize(&c).unwrap();
+    assert!(cache.contains(&a));
+    assert!(!cache.contains(&b));
+    assert!(cache.contains(&c));
+}
+
+#[test]
+fn malformed_binaries_are_not_cached() {
+    let cache = ModuleCache::new(2);
+    let mut truncated = binary("A");
+    truncated.truncate(truncated.len() / 2);
+    assert!(cache.deserialize(&truncated).is_err());
+    assert!(cache.deserialize_no_check_bounds(&truncated).is_err());
+    assert!(cache.is_empty());
+    assert_eq!(
+        cache.deserialize(&[]).unwrap_err(),
+        CompiledModule::deserialize(&[]).unwrap_err()
+    );
+}
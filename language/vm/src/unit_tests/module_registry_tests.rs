@@ -0,0 +1,101 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledModule, FunctionSignature, SignatureToken},
+    module_registry::{ModuleRegistry, RegistryError},
+    package::module_digest,
+};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+/// Builds module `name` with a public function `f`, returning `returns` constants.
+fn module(name: &str, returns: usize) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), name);
+    let mut code = CodeBuilder::new();
+    for value in 0..returns {
+        code.emit(Bytecode::LdConst(value as u64));
+    }
+    code.emit(Bytecode::Ret);
+    let signature = FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![SignatureToken::U64; returns].into(),
+        type_formals: vec![],
+    };
+    builder.add_function("f", CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    builder.build().expect("module is bounds-valid")
+}
+
+fn id(name: &str) -> ModuleId {
+    ModuleId::new(AccountAddress::default(), name.to_string())
+}
+
+#[test]
+fn modules_are_keyed_by_id() {
+    let mut registry = ModuleRegistry::new();
+    assert!(registry.is_empty());
+    assert_eq!(registry.insert(module("B", 0)).unwrap(), None);
+    assert_eq!(registry.insert(module("A", 0)).unwrap(), None);
+    assert_eq!(registry.len(), 2);
+    assert_eq!(
+        registry.ids().cloned().collect::<Vec<_>>(),
+        vec![id("A"), id("B")]
+    );
+    assert_eq!(registry.get(&id("A")), Some(&module("A", 0)));
+    assert_eq!(
+        registry.digest(&id("A")),
+        Some(&module_digest(&module("A", 0)).unwrap())
+    );
+    assert!(!registry.contains(&id("C")));
+
+    // Inserting another version replaces the first one.
+    assert_eq!(
+        registry.insert(module("A", 1)).unwrap(),
+        Some(module("A", 0))
+    );
+    assert_eq!(registry.get(&id("A")), Some(&module("A", 1)));
+    assert_eq!(registry.remove(&id("A")), Some(module("A", 1)));
+    assert_eq!(
+        registry.modules().collect::<Vec<_>>(),
+        vec![&module("B", 0)]
+    );
+}
+
+#[test]
+fn pinned_modules_refuse_other_versions() {
+    let pinned = module_digest(&module("A", 0)).unwrap();
+    let other = module_digest(&module("A", 1)).unwrap();
+    let mut registry = ModuleRegistry::new();
+    registry.pin(id("A"), pinned).unwrap();
+    assert_eq!(registry.pinned_digest(&id("A")), Some(&pinned));
+
+    let err = registry.insert(module("A", 1)).unwrap_err();
+    assert_eq!(
+        err.downcast::<RegistryError>().unwrap(),
+        RegistryError::DigestMismatch {
+            module: id("A"),
+            pinned,
+            actual: other,
+        }
+    );
+    assert!(registry.is_empty());
+    registry.insert(module("A", 0)).unwrap();
+
+    // A registered module can't be pinned to another version either.
+    let err = registry.pin(id("A"), other).unwrap_err();
+    assert_eq!(
+        err.downcast::<RegistryError>().unwrap(),
+        RegistryError::DigestMismatch {
+            module: id("A"),
+            pinned: other,
+            actual: pinned,
+        }
+    );
+    assert_eq!(registry.pinned_digest(&id("A")), Some(&pinned));
+
+    // Pins outlive the modules they pin.
+    registry.remove(&id("A"));
+    assert!(registry.insert(module("A", 1)).is_err());
+    assert_eq!(registry.unpin(&id("A")), Some(pinned));
+    registry.insert(module("A", 1)).unwrap();
+}
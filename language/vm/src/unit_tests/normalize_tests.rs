@@ -0,0 +1,257 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, FunctionSignature, Kind, ModuleHandleIndex,
+        SignatureToken, StructDefinitionIndex, NO_TYPE_ACTUALS,
+    },
+    normalize::{NormalizedBytecode, NormalizedModule, NormalizedSignature, NormalizedType},
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+fn unit_signature() -> FunctionSignature {
+    FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+fn add_struct(builder: &mut CompiledModuleBuilder, name: &str) -> StructDefinitionIndex {
+    builder.add_struct(
+        name,
+        true,
+        vec![],
+        vec![("x", SignatureToken::U64), ("b", SignatureToken::Bool)],
+    )
+}
+
+fn add_function(builder: &mut CompiledModuleBuilder, resource: StructDefinitionIndex) {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::GetTxnSenderAddress);
+    code.emit(Bytecode::MoveFrom(resource, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Unpack(resource, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Pop);
+    code.emit(Bytecode::Pop);
+    code.emit(Bytecode::Ret);
+    builder.add_function(
+        "take",
+        CodeUnit::PUBLIC,
+        unit_signature(),
+        vec![],
+        vec![resource],
+        code,
+    );
+}
+
+/// Builds the same module with its definitions and pools in either order.
+fn build(reversed: bool) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    if reversed {
+        // Fills the pools in another order, and defines the function first.
+        builder.intern_string("b");
+        builder.intern_type_signature(SignatureToken::Bool);
+        builder.intern_address(AccountAddress::random());
+        builder.add_function("noop", 0, unit_signature(), vec![], vec![], ret());
+        add_struct(&mut builder, "Other");
+        let resource = add_struct(&mut builder, "R");
+        add_function(&mut builder, resource);
+    } else {
+        let resource = add_struct(&mut builder, "R");
+        add_struct(&mut builder, "Other");
+        add_function(&mut builder, resource);
+        builder.add_function("noop", 0, unit_signature(), vec![], vec![], ret());
+    }
+    builder.build().expect("module is bounds-valid")
+}
+
+fn ret() -> CodeBuilder {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    code
+}
+
+fn hash(module: &NormalizedModule) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    module.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn table_order_is_irrelevant() {
+    let module = build(false);
+    let reversed = build(true);
+    assert_ne!(module, reversed);
+
+    let normalized = NormalizedModule::new(&module);
+    let normalized_reversed = NormalizedModule::new(&reversed);
+    assert_eq!(normalized, normalized_reversed);
+    assert_eq!(hash(&normalized), hash(&normalized_reversed));
+}
+
+#[test]
+fn names_are_resolved() {
+    let normalized = NormalizedModule::new(&build(false));
+    let self_id = ModuleId::new(AccountAddress::default(), "M".to_string());
+    assert_eq!(normalized.id, self_id);
+    assert_eq!(
+        normalized.structs.keys().collect::<Vec<_>>(),
+        vec!["Other", "R"]
+    );
+    assert_eq!(
+        normalized.structs["R"].fields,
+        Some(vec![
+            ("x".to_string(), NormalizedType::U64),
+            ("b".to_string(), NormalizedType::Bool),
+        ])
+    );
+
+    let take = &normalized.functions["take"];
+    assert!(take.is_public);
+    assert!(take.acquires.contains("R"));
+    assert_eq!(
+        take.code[1],
+        NormalizedBytecode::MoveFrom("R".to_string(), vec![])
+    );
+    assert_eq!(take.code[3], NormalizedBytecode::Other(Bytecode::Pop));
+}
+
+#[test]
+fn semantic_changes_are_detected() {
+    let normalized = NormalizedModule::new(&build(false));
+
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let resource = builder.add_struct(
+        "R",
+        true,
+        vec![],
+        vec![("b", SignatureToken::Bool), ("x", SignatureToken::U64)],
+    );
+    add_struct(&mut builder, "Other");
+    add_function(&mut builder, resource);
+    builder.add_function("noop", 0, unit_signature(), vec![], vec![], ret());
+    let swapped_fields = NormalizedModule::new(&builder.build().unwrap());
+
+    assert_ne!(normalized, swapped_fields);
+    assert_eq!(normalized.functions, swapped_fields.functions);
+    assert_ne!(normalized.structs["R"], swapped_fields.structs["R"]);
+}
+
+fn signature(
+    type_formals: Vec<Kind>,
+    arg_types: Vec<NormalizedType>,
+    return_types: Vec<NormalizedType>,
+) -> NormalizedSignature {
+    NormalizedSignature {
+        type_formals,
+        arg_types,
+        return_types,
+    }
+}
+
+#[test]
+fn signatures_are_compared_up_to_type_parameter_renaming() {
+    use NormalizedType::TypeParameter as T;
+
+    let two_alls = vec![Kind::All, Kind::All];
+    let pair = signature(two_alls.clone(), vec![T(0), T(1)], vec![]);
+    let swapped = signature(two_alls.clone(), vec![T(1), T(0)], vec![]);
+    assert_eq!(pair.type_parameter_renaming(&pair), Some(vec![0, 1]));
+    assert_eq!(pair.type_parameter_renaming(&swapped), Some(vec![1, 0]));
+    assert!(pair.is_equivalent(&swapped));
+
+    // The renaming must be a bijection.
+    let same = signature(two_alls.clone(), vec![T(0), T(0)], vec![]);
+    assert!(!pair.is_equivalent(&same));
+    assert!(!same.is_equivalent(&pair));
+
+    // Type parameters that don't appear in the types are matched by kind.
+    let unused = signature(
+        vec![Kind::Resource, Kind::All],
+        vec![T(1)],
+        vec![NormalizedType::U64],
+    );
+    let unused_swapped = signature(
+        vec![Kind::All, Kind::Resource],
+        vec![T(0)],
+        vec![NormalizedType::U64],
+    );
+    assert_eq!(
+        unused.type_parameter_renaming(&unused_swapped),
+        Some(vec![1, 0])
+    );
+    let all_kinds = signature(two_alls, vec![T(0)], vec![NormalizedType::U64]);
+    assert!(!unused.is_equivalent(&all_kinds));
+
+    // Arguments and return types are not interchangeable.
+    let returned = signature(vec![Kind::All], vec![], vec![T(0)]);
+    let taken = signature(vec![Kind::All], vec![T(0)], vec![]);
+    assert!(!returned.is_equivalent(&taken));
+}
+
+#[test]
+fn signatures_are_compared_across_modules() {
+    let a = AccountAddress::random();
+    let mut builder = CompiledModuleBuilder::new(a, "A");
+    add_struct(&mut builder, "S");
+    let s = builder.add_struct_handle(ModuleHandleIndex::new(0), "S", true, vec![]);
+    let f = builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        FunctionSignature {
+            arg_types: vec![
+                SignatureToken::TypeParameter(0),
+                SignatureToken::Reference(Box::new(SignatureToken::Struct(s, vec![]))),
+            ]
+            .into(),
+            return_types: vec![SignatureToken::TypeParameter(1)].into(),
+            type_formals: vec![Kind::All, Kind::Resource],
+        },
+        vec![],
+        vec![],
+        ret(),
+    );
+    let module_a = builder.build().unwrap();
+    let definition =
+        NormalizedSignature::from_handle(&module_a, module_a.function_def_at(f).function);
+
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "B");
+    let handle_to_a = builder.add_module_handle(a, "A");
+    let s = builder.add_struct_handle(handle_to_a, "S", true, vec![]);
+    let handle_to_other = builder.add_module_handle(a, "Other");
+    let other_s = builder.add_struct_handle(handle_to_other, "S", true, vec![]);
+    let mut import = |name, s| {
+        builder.add_function_handle(
+            handle_to_a,
+            name,
+            FunctionSignature {
+                arg_types: vec![
+                    SignatureToken::TypeParameter(1),
+                    SignatureToken::Reference(Box::new(SignatureToken::Struct(s, vec![]))),
+                ]
+                .into(),
+                return_types: vec![SignatureToken::TypeParameter(0)].into(),
+                type_formals: vec![Kind::Resource, Kind::All],
+            },
+        )
+    };
+    let f = import("f", s);
+    let g = import("g", other_s);
+    let module_b = builder.build().unwrap();
+
+    let imported = NormalizedSignature::from_handle(&module_b, f);
+    assert_eq!(
+        definition.type_parameter_renaming(&imported),
+        Some(vec![1, 0])
+    );
+    assert_ne!(definition, imported);
+    let other = NormalizedSignature::from_handle(&module_b, g);
+    assert!(!definition.is_equivalent(&other));
+}
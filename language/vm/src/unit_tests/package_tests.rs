@@ -0,0 +1,105 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    access::ModuleAccess,
+    coverage::CoverageMap,
+    file_format::CompiledModule,
+    package::{module_digest, Package, PackageDependency, PackageError, PackageManifest},
+    test_helpers::{package_manifest, package_module, package_module_id},
+};
+use crypto::HashValue;
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+fn manifest() -> PackageManifest {
+    package_manifest(vec![PackageDependency {
+        module: ModuleId::new(AccountAddress::new([1; 32]), "Lib".to_string()),
+        digest: HashValue::zero(),
+    }])
+}
+
+fn names(modules: &[&CompiledModule]) -> Vec<String> {
+    modules
+        .iter()
+        .map(|module| module.self_id().name().to_string())
+        .collect()
+}
+
+#[test]
+fn modules_are_ordered_after_their_dependencies() {
+    let modules = vec![
+        package_module("A", &["B", "C"]),
+        package_module("B", &["C"]),
+        package_module("C", &[]),
+        package_module("D", &[]),
+    ];
+    let package = Package::new(manifest(), modules).unwrap();
+    assert_eq!(
+        names(&package.topological_order().unwrap()),
+        vec!["C", "B", "A", "D"]
+    );
+    let a = package_module_id("A");
+    assert_eq!(package.module(&a).unwrap().self_id(), a);
+
+    let ordered = package.into_topological_order().unwrap();
+    assert_eq!(
+        names(&ordered.iter().collect::<Vec<_>>()),
+        vec!["C", "B", "A", "D"]
+    );
+}
+
+#[test]
+fn malformed_packages_are_rejected() {
+    assert_eq!(
+        Package::new(
+            manifest(),
+            vec![package_module("A", &[]), package_module("A", &["B"])]
+        ),
+        Err(PackageError::DuplicateModule(package_module_id("A")))
+    );
+
+    let modules = vec![
+        package_module("A", &["B"]),
+        package_module("B", &["C"]),
+        package_module("C", &["B"]),
+        package_module("D", &[]),
+    ];
+    let package = Package::new(manifest(), modules).unwrap();
+    assert_eq!(
+        package.topological_order(),
+        Err(PackageError::DependencyCycle(vec![
+            package_module_id("A"),
+            package_module_id("B"),
+            package_module_id("C")
+        ]))
+    );
+}
+
+#[test]
+fn serialization_round_trips() {
+    let package = Package::new(
+        manifest(),
+        vec![package_module("A", &["B"]), package_module("B", &[])],
+    )
+    .unwrap();
+    let mut binary = vec![];
+    package.serialize(&mut binary).unwrap();
+    assert_eq!(Package::deserialize(&binary).unwrap(), package);
+
+    assert!(Package::deserialize(&binary[..binary.len() - 1]).is_err());
+    let mut extended = binary.clone();
+    extended.push(0);
+    assert!(Package::deserialize(&extended).is_err());
+    let mut corrupted = binary;
+    corrupted[8] = 2;
+    assert!(Package::deserialize(&corrupted).is_err());
+}
+
+#[test]
+fn digests_identify_module_versions() {
+    let a = package_module("A", &[]);
+    let digest = module_digest(&a).unwrap();
+    assert_eq!(digest, module_digest(&a.clone()).unwrap());
+    assert_eq!(digest, CoverageMap::module_hash(&a).unwrap());
+    assert_ne!(digest, module_digest(&package_module("A", &["B"])).unwrap());
+}
@@ -0,0 +1,45 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CompiledModule, FunctionSignature},
+    views::ModuleView,
+};
+use rayon::prelude::*;
+use types::account_address::AccountAddress;
+
+fn module_with_functions(count: usize) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    for idx in 0..count {
+        let signature = FunctionSignature {
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        };
+        let mut code = CodeBuilder::new();
+        code.emit(Bytecode::Ret);
+        builder.add_function(&format!("f{}", idx), 0, signature, vec![], vec![], code);
+    }
+    builder.build().expect("module is bounds-valid")
+}
+
+#[test]
+fn parallel_iterators_match_sequential_ones() {
+    let module = module_with_functions(64);
+    let defs: Vec<_> = module.par_function_defs().collect();
+    assert_eq!(
+        defs,
+        module.as_inner().function_defs.iter().collect::<Vec<_>>()
+    );
+    assert_eq!(module.par_struct_defs().count(), 0);
+
+    let view = ModuleView::new(&module);
+    let names: Vec<_> = view
+        .par_functions()
+        .map(|function| function.name())
+        .collect();
+    let expected: Vec<_> = view.functions().map(|function| function.name()).collect();
+    assert_eq!(names, expected);
+    assert_eq!(view.par_structs().count(), 0);
+}
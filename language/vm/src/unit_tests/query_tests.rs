@@ -0,0 +1,170 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, FunctionDefinitionIndex, FunctionSignature,
+        LocalsSignature, SignatureToken, StructDefinitionIndex,
+    },
+    query::{Comparison, FunctionPredicate, Query, QueryError, QueryMatch, StructPredicate},
+};
+use types::{account_address::AccountAddress, language_storage::ModuleId};
+
+fn signature(args: usize) -> FunctionSignature {
+    FunctionSignature {
+        arg_types: vec![SignatureToken::U64; args].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    }
+}
+
+/// Builds a module with:
+/// - a resource `R` with a field, a struct `S` with a reference field, and a native struct `N`;
+/// - a public function `wide` with 4 arguments, a private function `narrow` with 1, and a public
+///   function `withdraw` calling `0x1::Account::withdraw`.
+fn module() -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_struct("R", true, vec![], vec![("value", SignatureToken::U64)]);
+    let reference = SignatureToken::Reference(Box::new(SignatureToken::U64));
+    builder.add_struct("S", false, vec![], vec![("value", reference)]);
+    builder.add_native_struct("N", false, vec![]);
+
+    for (name, flags, args) in &[("wide", CodeUnit::PUBLIC, 4), ("narrow", 0, 1)] {
+        let mut code = CodeBuilder::new();
+        code.emit(Bytecode::Ret);
+        builder.add_function(name, *flags, signature(*args), vec![], vec![], code);
+    }
+
+    let account =
+        builder.add_module_handle(AccountAddress::from_hex_literal("0x1").unwrap(), "Account");
+    let callee = builder.add_function_handle(account, "withdraw", signature(1));
+    let type_actuals = builder.intern_locals_signature(LocalsSignature::default());
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::MoveLoc(0));
+    code.emit(Bytecode::Call(callee, type_actuals));
+    code.emit(Bytecode::Ret);
+    builder.add_function(
+        "withdraw",
+        CodeUnit::PUBLIC,
+        signature(1),
+        vec![],
+        vec![],
+        code,
+    );
+    builder.build().expect("module is bounds-valid")
+}
+
+fn function(idx: u16) -> QueryMatch {
+    QueryMatch::Function(FunctionDefinitionIndex::new(idx))
+}
+
+fn struct_(idx: u16) -> QueryMatch {
+    QueryMatch::Struct(StructDefinitionIndex::new(idx))
+}
+
+fn run(query: &str) -> Vec<QueryMatch> {
+    Query::parse(query).unwrap().run(&module())
+}
+
+#[test]
+fn function_queries() {
+    assert_eq!(
+        run("functions"),
+        vec![function(0), function(1), function(2)]
+    );
+    assert_eq!(
+        run("functions where public and args > 3"),
+        vec![function(0)]
+    );
+    assert_eq!(
+        run("functions where args<=1"),
+        vec![function(1), function(2)]
+    );
+    assert_eq!(run("functions where not public"), vec![function(1)]);
+    assert_eq!(run("functions where instructions = 3"), vec![function(2)]);
+    assert_eq!(run("functions where name = narrow"), vec![function(1)]);
+    assert_eq!(
+        run("functions where calls 0x1::Account::withdraw"),
+        vec![function(2)]
+    );
+    // The module itself is at address 0x0, so its own `withdraw` is not a match.
+    assert_eq!(run("functions where calls 0x0::M::withdraw"), vec![]);
+}
+
+#[test]
+fn struct_queries() {
+    assert_eq!(run("structs"), vec![struct_(0), struct_(1), struct_(2)]);
+    assert_eq!(run("structs where resource"), vec![struct_(0)]);
+    assert_eq!(run("structs where references"), vec![struct_(1)]);
+    assert_eq!(run("structs where native"), vec![struct_(2)]);
+    assert_eq!(
+        run("structs where fields >= 1 and not resource"),
+        vec![struct_(1)]
+    );
+}
+
+#[test]
+fn queries_parse_to_predicates() {
+    assert_eq!(
+        Query::parse("functions where public and not calls 0x1::Account::withdraw").unwrap(),
+        Query::Functions(vec![
+            FunctionPredicate::Public,
+            FunctionPredicate::Not(Box::new(FunctionPredicate::Calls(
+                ModuleId::new(
+                    AccountAddress::from_hex_literal("0x1").unwrap(),
+                    "Account".to_string()
+                ),
+                "withdraw".to_string()
+            ))),
+        ])
+    );
+    assert_eq!(
+        "structs where fields != 2".parse::<Query>().unwrap(),
+        Query::Structs(vec![StructPredicate::Fields(Comparison::Ne, 2)])
+    );
+}
+
+#[test]
+fn malformed_queries() {
+    let error = |query: &str| {
+        Query::parse(query)
+            .unwrap_err()
+            .downcast::<QueryError>()
+            .unwrap()
+    };
+    assert_eq!(
+        error("modules"),
+        QueryError::UnexpectedToken {
+            expected: "'functions' or 'structs'",
+            found: "modules".to_string(),
+        }
+    );
+    assert_eq!(
+        error("functions where args >"),
+        QueryError::UnexpectedEnd {
+            expected: "a count"
+        }
+    );
+    assert_eq!(
+        error("structs where public"),
+        QueryError::UnexpectedToken {
+            expected: "a struct predicate",
+            found: "public".to_string(),
+        }
+    );
+    assert_eq!(
+        error("functions where calls Account::withdraw"),
+        QueryError::UnexpectedToken {
+            expected: "a function such as 0x1::Account::withdraw",
+            found: "Account::withdraw".to_string(),
+        }
+    );
+    assert_eq!(
+        error("functions where public or native"),
+        QueryError::UnexpectedToken {
+            expected: "'and' or end of query",
+            found: "or".to_string(),
+        }
+    );
+}
@@ -0,0 +1,76 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    errors::BinaryError,
+    file_format::{Bytecode, CodeUnit, CompiledModuleMut, FunctionSignature},
+    raw_code::{RawCode, RawCodeModule},
+};
+use bytes::Bytes;
+use types::account_address::AccountAddress;
+
+fn sample_code() -> Vec<Bytecode> {
+    vec![
+        Bytecode::LdConst(1 << 40),
+        Bytecode::BrTrue(3),
+        Bytecode::LdTrue,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]
+}
+
+#[test]
+fn raw_code_decodes_on_access() {
+    let code = sample_code();
+    let raw_code = RawCode::serialize(&code).unwrap();
+    assert_eq!(raw_code.len(), code.len());
+    assert_eq!(raw_code.get(1), Some(Bytecode::BrTrue(3)));
+    assert_eq!(raw_code.get(5), None);
+    assert_eq!(raw_code.decode(), code);
+
+    let reindexed = RawCode::deserialize(Bytes::from(raw_code.as_bytes())).unwrap();
+    assert_eq!(reindexed, raw_code);
+}
+
+#[test]
+fn raw_code_rejects_trailing_bytes() {
+    let raw_code = RawCode::serialize(&sample_code()).unwrap();
+    let mut bytes = raw_code.as_bytes().to_vec();
+    bytes.push(0);
+    assert_eq!(
+        RawCode::deserialize(Bytes::from(bytes)).expect_err("Expected malformed code"),
+        BinaryError::Malformed
+    );
+}
+
+#[test]
+fn raw_code_modules_match_decoded_modules() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    for (i, length) in [0, 1, 4].iter().enumerate() {
+        let mut code = CodeBuilder::new();
+        for bytecode in sample_code().into_iter().take(*length) {
+            code.emit(bytecode);
+        }
+        code.emit(Bytecode::Ret);
+        let signature = FunctionSignature {
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        };
+        let name = format!("f{}", i);
+        builder.add_function(&name, CodeUnit::PUBLIC, signature, vec![], vec![], code);
+    }
+    let module = builder.build().expect("module is bounds-valid");
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+
+    let decoded = CompiledModuleMut::deserialize_no_check_bounds(&binary).unwrap();
+    let raw_module = RawCodeModule::deserialize(&binary).unwrap();
+    assert!(raw_module.module.function_defs.is_empty());
+    assert_eq!(raw_module.function_defs.len(), decoded.function_defs.len());
+    for (raw_def, func_def) in raw_module.function_defs.iter().zip(&decoded.function_defs) {
+        assert_eq!(&raw_def.decode(), func_def);
+    }
+    assert_eq!(raw_module.decode(), decoded);
+}
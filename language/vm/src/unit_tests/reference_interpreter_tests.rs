@@ -0,0 +1,202 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        Bytecode, CodeUnit, CompiledModule, FieldDefinitionIndex, FunctionDefinitionIndex,
+        FunctionSignature, ModuleHandleIndex, SignatureToken, StructHandleIndex, NO_TYPE_ACTUALS,
+    },
+    reference_interpreter::{ExecutionError, FailureReason, ReferenceInterpreter, Value},
+};
+use types::account_address::AccountAddress;
+
+fn signature(
+    arg_types: Vec<SignatureToken>,
+    return_types: Vec<SignatureToken>,
+) -> FunctionSignature {
+    FunctionSignature {
+        arg_types: arg_types.into(),
+        return_types: return_types.into(),
+        type_formals: vec![],
+    }
+}
+
+/// Builds a module with a single function `f(u64): u64` with `code` and extra `locals`.
+fn single_function(locals: Vec<SignatureToken>, code: CodeBuilder) -> CompiledModule {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature(vec![SignatureToken::U64], vec![SignatureToken::U64]),
+        locals,
+        vec![],
+        code,
+    );
+    builder.build().expect("module is bounds-valid")
+}
+
+fn execute(module: &CompiledModule, arg: u64) -> Result<Vec<Value>, ExecutionError> {
+    ReferenceInterpreter::new(module)
+        .execute(FunctionDefinitionIndex::new(0), vec![Value::U64(arg)])
+}
+
+/// Returns the sum of 1 to n, with a loop.
+fn sum() -> CodeBuilder {
+    let mut code = CodeBuilder::new();
+    let head = code.new_label();
+    let exit = code.new_label();
+    code.emit(Bytecode::LdConst(0));
+    code.emit(Bytecode::StLoc(1));
+    code.bind(head);
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::LdConst(0));
+    code.emit(Bytecode::Eq);
+    code.emit_branch(Bytecode::BrTrue, exit);
+    code.emit(Bytecode::MoveLoc(1));
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::Add);
+    code.emit(Bytecode::StLoc(1));
+    code.emit(Bytecode::MoveLoc(0));
+    code.emit(Bytecode::LdConst(1));
+    code.emit(Bytecode::Sub);
+    code.emit(Bytecode::StLoc(0));
+    code.emit_branch(Bytecode::Branch, head);
+    code.bind(exit);
+    code.emit(Bytecode::MoveLoc(1));
+    code.emit(Bytecode::Ret);
+    code
+}
+
+#[test]
+fn loops_execute() {
+    let module = single_function(vec![SignatureToken::U64], sum());
+    assert_eq!(execute(&module, 0), Ok(vec![Value::U64(0)]));
+    assert_eq!(execute(&module, 10), Ok(vec![Value::U64(55)]));
+}
+
+#[test]
+fn step_limit_is_enforced() {
+    let module = single_function(vec![SignatureToken::U64], sum());
+    let result = ReferenceInterpreter::new(&module)
+        .with_step_limit(100)
+        .execute(FunctionDefinitionIndex::new(0), vec![Value::U64(1000)]);
+    assert_eq!(result, Err(ExecutionError::StepLimitExceeded));
+}
+
+#[test]
+fn arithmetic_errors_and_aborts() {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::LdConst(10));
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::Div);
+    code.emit(Bytecode::Abort);
+    let module = single_function(vec![], code);
+    assert_eq!(execute(&module, 5), Err(ExecutionError::Aborted(2)));
+    assert_eq!(
+        execute(&module, 0),
+        Err(ExecutionError::Failed {
+            function: FunctionDefinitionIndex::new(0),
+            offset: 2,
+            reason: FailureReason::ArithmeticError,
+        })
+    );
+}
+
+#[test]
+fn references_and_structs() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let s = builder.add_struct(
+        "S",
+        false,
+        vec![],
+        vec![("a", SignatureToken::U64), ("b", SignatureToken::U64)],
+    );
+    let s_type = SignatureToken::Struct(StructHandleIndex::new(0), vec![]);
+
+    // increment(r: &mut u64) { *r = *r + 1 }
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::ReadRef);
+    code.emit(Bytecode::LdConst(1));
+    code.emit(Bytecode::Add);
+    code.emit(Bytecode::MoveLoc(0));
+    code.emit(Bytecode::WriteRef);
+    code.emit(Bytecode::Ret);
+    let increment_signature = signature(
+        vec![SignatureToken::MutableReference(Box::new(
+            SignatureToken::U64,
+        ))],
+        vec![],
+    );
+    builder.add_function(
+        "increment",
+        0,
+        increment_signature.clone(),
+        vec![],
+        vec![],
+        code,
+    );
+    let increment =
+        builder.add_function_handle(ModuleHandleIndex::new(0), "increment", increment_signature);
+
+    // f(x: u64): u64 { let s = S { a: 0, b: x }; increment(&mut s.b); let S { a, b } = s; a + b }
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::LdConst(0));
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::Pack(s, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::StLoc(1));
+    code.emit(Bytecode::MutBorrowLoc(1));
+    code.emit(Bytecode::MutBorrowField(FieldDefinitionIndex::new(1)));
+    code.emit(Bytecode::Call(increment, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::MoveLoc(1));
+    code.emit(Bytecode::Unpack(s, NO_TYPE_ACTUALS));
+    code.emit(Bytecode::Add);
+    code.emit(Bytecode::Ret);
+    let f = builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature(vec![SignatureToken::U64], vec![SignatureToken::U64]),
+        vec![s_type],
+        vec![],
+        code,
+    );
+    let module = builder.build().expect("module is bounds-valid");
+
+    let result = ReferenceInterpreter::new(&module).execute(f, vec![Value::U64(41)]);
+    assert_eq!(result, Ok(vec![Value::U64(42)]));
+}
+
+#[test]
+fn global_state_is_unsupported() {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::GetGasRemaining);
+    code.emit(Bytecode::Ret);
+    let module = single_function(vec![], code);
+    assert_eq!(
+        execute(&module, 0),
+        Err(ExecutionError::Failed {
+            function: FunctionDefinitionIndex::new(0),
+            offset: 0,
+            reason: FailureReason::Unsupported,
+        })
+    );
+}
+
+#[test]
+fn ill_typed_code_is_invalid() {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::LdTrue);
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::Add);
+    code.emit(Bytecode::Ret);
+    let module = single_function(vec![], code);
+    match execute(&module, 0) {
+        Err(ExecutionError::Failed {
+            offset: 2,
+            reason: FailureReason::Invalid(_),
+            ..
+        }) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
@@ -0,0 +1,89 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    errors::{DuplicateKey, VMStaticViolation, VerificationError, WithContext},
+    file_format::{dummy_procedure_module, Bytecode, FunctionDefinitionIndex},
+    sarif::{to_sarif, SarifLog},
+    IndexKind,
+};
+use serde_json::json;
+
+#[test]
+fn sarif_results_have_logical_locations() {
+    let module = dummy_procedure_module(vec![Bytecode::Pop, Bytecode::Ret]);
+    let errors = vec![
+        VerificationError::in_function(
+            FunctionDefinitionIndex::new(0),
+            VMStaticViolation::PopReferenceError(0),
+        )
+        .with_context(|| "locals"),
+        VerificationError::new(
+            IndexKind::TypeSignature,
+            3,
+            VMStaticViolation::DuplicateElement(0, DuplicateKey::Signature),
+        ),
+    ];
+
+    let log = to_sarif(&module, &errors);
+    assert_eq!(log["version"], "2.1.0");
+    let run = &log["runs"][0];
+    assert_eq!(
+        run["tool"]["driver"]["rules"],
+        json!([
+            { "id": "V3003", "properties": { "category": "structure" } },
+            { "id": "V7001", "properties": { "category": "reference" } },
+        ])
+    );
+
+    let results = run["results"]
+        .as_array()
+        .expect("results should be an array");
+    assert_eq!(results.len(), 2);
+    assert_eq!(
+        results[0],
+        json!({
+            "ruleId": "V7001",
+            "level": "error",
+            "message": {
+                "text": "in function 0x0::<SELF>::<SELF> at code offset 0 (Pop): \
+                         Unable to verify Pop at offset 0 (while checking locals)",
+            },
+            "locations": [{
+                "logicalLocations": [
+                    { "kind": "module", "fullyQualifiedName": "0x0::<SELF>" },
+                    { "kind": "function", "fullyQualifiedName": "0x0::<SELF>::<SELF>" },
+                ],
+            }],
+            "properties": {
+                "indexKind": "function definition",
+                "index": 0,
+                "codeOffset": 0,
+                "context": ["locals"],
+            },
+        })
+    );
+    // Locations that can't be named are only attributed to the module.
+    assert_eq!(
+        results[1]["locations"][0]["logicalLocations"],
+        json!([{ "kind": "module", "fullyQualifiedName": "0x0::<SELF>" }])
+    );
+}
+
+#[test]
+fn sarif_log_merges_modules() {
+    let module = dummy_procedure_module(vec![Bytecode::Ret]);
+    let error = VerificationError::new(
+        IndexKind::FunctionDefinition,
+        0,
+        VMStaticViolation::InvalidFallThrough,
+    );
+    let mut log = SarifLog::new();
+    log.add_module(&module, std::slice::from_ref(&error));
+    log.add_module(&module, &[error]);
+
+    let log = log.to_json();
+    let run = &log["runs"][0];
+    assert_eq!(run["results"].as_array().unwrap().len(), 2);
+    assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 1);
+}
@@ -0,0 +1,86 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledScript, FunctionSignature},
+    script_allowlist::ScriptAllowlist,
+};
+use crypto::HashValue;
+use types::account_address::AccountAddress;
+
+fn script(code: &[Bytecode]) -> CompiledScript {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "<SELF>");
+    let mut code_builder = CodeBuilder::new();
+    for bytecode in code {
+        code_builder.emit(bytecode.clone());
+    }
+    builder.add_function(
+        "main",
+        CodeUnit::PUBLIC,
+        FunctionSignature {
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        },
+        vec![],
+        vec![],
+        code_builder,
+    );
+    builder.build().unwrap().into_script()
+}
+
+#[test]
+fn scripts_are_identified_by_the_hash_of_their_binary() {
+    let noop = script(&[Bytecode::Ret]);
+    let mut binary = vec![];
+    noop.serialize(&mut binary).unwrap();
+    assert_eq!(noop.hash().unwrap(), HashValue::from_sha3_256(&binary));
+    assert_ne!(
+        noop.hash().unwrap(),
+        script(&[Bytecode::LdTrue, Bytecode::Pop, Bytecode::Ret])
+            .hash()
+            .unwrap()
+    );
+}
+
+#[test]
+fn allowlist_membership() {
+    let noop = script(&[Bytecode::Ret]);
+    let other = script(&[Bytecode::LdTrue, Bytecode::Pop, Bytecode::Ret]);
+    let mut allowlist = ScriptAllowlist::new();
+    assert!(allowlist.is_empty());
+
+    let hash = allowlist.insert_script(&noop).unwrap();
+    assert!(!allowlist.insert(hash));
+    assert_eq!(allowlist.len(), 1);
+    assert!(allowlist.allows(&noop).unwrap());
+    assert!(!allowlist.allows(&other).unwrap());
+
+    let mut binary = vec![];
+    noop.serialize(&mut binary).unwrap();
+    assert!(allowlist.allows_binary(&binary));
+    binary.push(0);
+    assert!(!allowlist.allows_binary(&binary));
+
+    assert!(allowlist.remove(&hash));
+    assert!(!allowlist.allows(&noop).unwrap());
+}
+
+#[test]
+fn allowlist_serializes_as_hex_hashes() {
+    let hashes = vec![HashValue::random(), HashValue::random()];
+    let allowlist: ScriptAllowlist = hashes.iter().cloned().collect();
+
+    let json = serde_json::to_string(&allowlist).unwrap();
+    let mut expected: Vec<_> = hashes.iter().map(|hash| format!("{:x}", hash)).collect();
+    expected.sort();
+    assert_eq!(json, serde_json::to_string(&expected).unwrap());
+    assert_eq!(
+        serde_json::from_str::<ScriptAllowlist>(&json).unwrap(),
+        allowlist
+    );
+
+    assert!(serde_json::from_str::<ScriptAllowlist>("[\"abcd\"]").is_err());
+    assert!(serde_json::from_str::<ScriptAllowlist>("[\"not hex\"]").is_err());
+}
@@ -0,0 +1,89 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, CompiledScript, FunctionSignature, SignatureToken},
+    script_arguments::{check_arguments, ArgumentError, MAX_BYTE_ARRAY_ARGUMENT_LENGTH},
+    views::ScriptView,
+};
+use types::{
+    account_address::AccountAddress, byte_array::ByteArray, transaction::TransactionArgument,
+};
+
+fn script(arg_types: Vec<SignatureToken>) -> CompiledScript {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "<SELF>");
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::Ret);
+    builder.add_function(
+        "main",
+        CodeUnit::PUBLIC,
+        FunctionSignature {
+            arg_types: arg_types.into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        },
+        vec![],
+        vec![],
+        code,
+    );
+    builder.build().unwrap().into_script()
+}
+
+#[test]
+fn matching_arguments_are_accepted() {
+    let script = script(vec![SignatureToken::Address, SignatureToken::ByteArray]);
+    let view = ScriptView::new(&script);
+    assert_eq!(view.arg_count(), 2);
+    let arguments = vec![
+        TransactionArgument::Address(AccountAddress::default()),
+        TransactionArgument::ByteArray(ByteArray::new(vec![0u8; 32])),
+    ];
+    assert_eq!(check_arguments(&view, &arguments), Ok(()));
+}
+
+#[test]
+fn every_mismatch_is_reported() {
+    let script = script(vec![
+        SignatureToken::U64,
+        SignatureToken::ByteArray,
+        SignatureToken::String,
+    ]);
+    let view = ScriptView::new(&script);
+
+    assert_eq!(
+        check_arguments(&view, &[TransactionArgument::U64(1)]),
+        Err(vec![ArgumentError::WrongCount {
+            expected: 3,
+            actual: 1
+        }])
+    );
+
+    let arguments = vec![
+        TransactionArgument::Address(AccountAddress::default()),
+        TransactionArgument::ByteArray(ByteArray::new(vec![
+            0u8;
+            MAX_BYTE_ARRAY_ARGUMENT_LENGTH + 1
+        ])),
+        TransactionArgument::String("hello".to_string()),
+    ];
+    let errors = check_arguments(&view, &arguments).unwrap_err();
+    assert_eq!(
+        errors,
+        vec![
+            ArgumentError::TypeMismatch {
+                index: 0,
+                expected: SignatureToken::U64,
+                actual: SignatureToken::Address,
+            },
+            ArgumentError::ByteArrayTooLong {
+                index: 1,
+                length: MAX_BYTE_ARRAY_ARGUMENT_LENGTH + 1,
+            },
+        ]
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "argument 0 has type Address but the script expects U64"
+    );
+}
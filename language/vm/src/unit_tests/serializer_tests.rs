@@ -0,0 +1,69 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{empty_module, Bytecode, FunctionSignature, SignatureToken},
+    file_format_common::BinaryConstants,
+    serializer::serialized_size_breakdown,
+};
+use types::account_address::AccountAddress;
+
+#[test]
+fn size_breakdown_adds_up_to_the_binary() {
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::LdConst(1));
+    code.emit(Bytecode::Pop);
+    code.emit(Bytecode::Ret);
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    builder.add_struct("S", false, vec![], vec![("x", SignatureToken::U64)]);
+    builder.add_function(
+        "f",
+        0,
+        FunctionSignature {
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
+            type_formals: vec![],
+        },
+        vec![],
+        vec![],
+        code,
+    );
+    let module = builder.build().unwrap();
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+
+    let breakdown = serialized_size_breakdown(&module).unwrap();
+    assert_eq!(breakdown.total(), binary.len());
+    assert_eq!(breakdown.struct_defs, 4);
+    assert_eq!(breakdown.field_defs, 3);
+    // max_stack_size, locals, the code length, then 9 + 1 + 1 bytes of instructions.
+    assert_eq!(breakdown.code, 1 + 1 + 2 + 11);
+    // The function handle index, the flags and the empty acquires list.
+    assert_eq!(breakdown.function_defs, 3);
+}
+
+#[test]
+fn empty_tables_take_no_bytes() {
+    let module = empty_module().freeze().unwrap();
+    let mut binary = vec![];
+    module.serialize(&mut binary).unwrap();
+
+    let breakdown = serialized_size_breakdown(&module).unwrap();
+    assert_eq!(breakdown.total(), binary.len());
+    assert_eq!(breakdown.struct_defs, 0);
+    assert_eq!(breakdown.function_defs, 0);
+    assert_eq!(breakdown.code, 0);
+    // The magic, the version, the table count, and 9 bytes per table written.
+    let tables = [
+        breakdown.module_handles,
+        breakdown.locals_signatures,
+        breakdown.string_pool,
+        breakdown.address_pool,
+    ];
+    assert!(tables.iter().all(|size| *size > 0));
+    assert_eq!(
+        breakdown.header,
+        BinaryConstants::HEADER_SIZE + 9 * tables.len()
+    );
+}
@@ -0,0 +1,42 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{file_format::empty_module, signed_module::SignedModule};
+use crypto::ed25519::*;
+use rand::{rngs::StdRng, SeedableRng};
+
+#[test]
+fn signed_modules_verify() {
+    let (private_key, public_key) = compat::generate_keypair(None);
+    let module = empty_module().freeze().unwrap();
+    let signed = SignedModule::sign_module(&module, &private_key, public_key.clone()).unwrap();
+
+    signed.verify().unwrap();
+    assert_eq!(signed.public_key(), &public_key);
+    assert_eq!(signed.verified_module().unwrap(), module);
+}
+
+#[test]
+fn tampered_modules_fail_to_verify() {
+    let (private_key, public_key) = compat::generate_keypair(None);
+    let module = empty_module().freeze().unwrap();
+    let signed = SignedModule::sign_module(&module, &private_key, public_key).unwrap();
+
+    let mut binary = signed.clone().into_binary();
+    *binary.last_mut().unwrap() ^= 1;
+    let tampered = SignedModule::new(
+        binary,
+        signed.public_key().clone(),
+        signed.signature().clone(),
+    );
+    assert!(tampered.verify().is_err());
+    assert!(tampered.verified_module().is_err());
+
+    let (_, other_key) = compat::generate_keypair(&mut StdRng::from_seed([1u8; 32]));
+    let wrong_signer = SignedModule::new(
+        signed.binary().to_vec(),
+        other_key,
+        signed.signature().clone(),
+    );
+    assert!(wrong_signer.verify().is_err());
+}
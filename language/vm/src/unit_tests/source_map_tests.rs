@@ -0,0 +1,136 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    coverage::CoverageMap,
+    errors::{VMStaticViolation, VerificationError},
+    file_format::{
+        dummy_procedure_module, Bytecode, FunctionDefinitionIndex, StructDefinitionIndex,
+    },
+    source_map::{SourceLocation, SourceMap},
+    views::ModuleView,
+    IndexKind,
+};
+
+fn location(file: u32, line: u32) -> SourceLocation {
+    SourceLocation {
+        file,
+        line,
+        column: 5,
+    }
+}
+
+fn source_map() -> SourceMap {
+    let mut source_map = SourceMap::new();
+    let file = source_map.add_file("modules/m.mvir");
+    assert_eq!(source_map.add_file("modules/m.mvir"), file);
+    let function = source_map.function_mut(FunctionDefinitionIndex::new(0));
+    function.location = Some(location(file, 3));
+    function.code.insert(1, location(file, 4));
+    function.code.insert(3, location(file, 6));
+    function.local_names.insert(0, "amount".to_string());
+    let struct_ = source_map.struct_mut(StructDefinitionIndex::new(0));
+    struct_.location = Some(location(file, 1));
+    struct_.type_formal_names.push("Token".to_string());
+    source_map
+}
+
+#[test]
+fn lookups_fall_back_to_enclosing_locations() {
+    let source_map = source_map();
+    let f = FunctionDefinitionIndex::new(0);
+    assert_eq!(source_map.code_location(f, 0), Some(location(0, 3)));
+    assert_eq!(source_map.code_location(f, 1), Some(location(0, 4)));
+    assert_eq!(source_map.code_location(f, 2), Some(location(0, 4)));
+    assert_eq!(source_map.code_location(f, 7), Some(location(0, 6)));
+    assert_eq!(
+        source_map.code_location(FunctionDefinitionIndex::new(1), 0),
+        None
+    );
+    assert_eq!(source_map.local_name(f, 0), Some("amount"));
+    assert_eq!(source_map.local_name(f, 1), None);
+    assert_eq!(
+        source_map.display_location(location(0, 4)).to_string(),
+        "modules/m.mvir:4:5"
+    );
+    assert_eq!(
+        source_map.display_location(location(2, 4)).to_string(),
+        "<file 2>:4:5"
+    );
+}
+
+#[test]
+fn serialization_round_trips() {
+    let source_map = source_map();
+    let json = source_map.to_json().unwrap();
+    assert_eq!(SourceMap::from_json(&json).unwrap(), source_map);
+
+    let mut binary = vec![];
+    source_map.serialize(&mut binary).unwrap();
+    assert_eq!(SourceMap::deserialize(&binary).unwrap(), source_map);
+
+    // Truncated, extended and corrupted binaries are rejected.
+    assert!(SourceMap::deserialize(&binary[..binary.len() - 1]).is_err());
+    let mut extended = binary.clone();
+    extended.push(0);
+    assert!(SourceMap::deserialize(&extended).is_err());
+    let mut corrupted = binary;
+    corrupted[0] = b'X';
+    assert!(SourceMap::deserialize(&corrupted).is_err());
+
+    // Locations must refer to a file of the map.
+    let mut source_map = SourceMap::new();
+    source_map
+        .function_mut(FunctionDefinitionIndex::new(0))
+        .location = Some(location(1, 1));
+    let mut binary = vec![];
+    source_map.serialize(&mut binary).unwrap();
+    assert!(SourceMap::deserialize(&binary).is_err());
+    assert!(SourceMap::from_json(&source_map.to_json().unwrap()).is_err());
+}
+
+#[test]
+fn errors_and_coverage_show_source_locations() {
+    let source_map = source_map();
+    let module = dummy_procedure_module(vec![Bytecode::Pop, Bytecode::Pop, Bytecode::Ret]);
+    let view = ModuleView::new(&module);
+
+    let err = VerificationError::in_function(
+        FunctionDefinitionIndex::new(0),
+        VMStaticViolation::PopReferenceError(2),
+    );
+    assert_eq!(
+        err.display_with(&view)
+            .with_source_map(&source_map)
+            .to_string(),
+        "modules/m.mvir:4:5: in function 0x0::<SELF>::<SELF> at code offset 2 (Ret): \
+         Unable to verify Pop at offset 2"
+    );
+    let err = VerificationError::new(
+        IndexKind::StructDefinition,
+        0,
+        VMStaticViolation::InvalidFallThrough,
+    );
+    assert_eq!(err.source_location(&source_map), Some(location(0, 1)));
+    let err = VerificationError::new(
+        IndexKind::StringPool,
+        0,
+        VMStaticViolation::InvalidFallThrough,
+    );
+    assert_eq!(err.source_location(&source_map), None);
+
+    let output = CoverageMap::new()
+        .display(&module)
+        .unwrap()
+        .with_source_map(&source_map)
+        .to_string();
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(
+        &lines[lines.len() - 3..],
+        &[
+            "  -    0: Pop",
+            "  -    1: Pop  // modules/m.mvir:4:5",
+            "  -    2: Ret",
+        ]
+    );
+}
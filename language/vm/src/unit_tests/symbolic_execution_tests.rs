@@ -0,0 +1,201 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{Bytecode, CodeUnit, FunctionDefinitionIndex, FunctionSignature, SignatureToken},
+    symbolic_execution::{
+        BinaryOp, Finding, Path, PathOutcome, SymbolicExecutor, SymbolicReport, Term,
+    },
+};
+use types::account_address::AccountAddress;
+
+/// Explores a function `f(u64)` with `code` and extra `locals`.
+fn explore_with(
+    locals: Vec<SignatureToken>,
+    code: Vec<Bytecode>,
+    executor: impl FnOnce(SymbolicExecutor) -> SymbolicExecutor,
+) -> SymbolicReport {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let mut code_builder = CodeBuilder::new();
+    for bytecode in code {
+        code_builder.emit(bytecode);
+    }
+    let signature = FunctionSignature {
+        arg_types: vec![SignatureToken::U64].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        signature,
+        locals,
+        vec![],
+        code_builder,
+    );
+    let module = builder.build().expect("module is bounds-valid");
+    executor(SymbolicExecutor::new(&module)).execute(FunctionDefinitionIndex::new(0))
+}
+
+fn explore(code: Vec<Bytecode>) -> SymbolicReport {
+    explore_with(vec![], code, |executor| executor)
+}
+
+fn arg_gt_10() -> Term {
+    Term::Binary(
+        BinaryOp::Gt,
+        Box::new(Term::Arg(0)),
+        Box::new(Term::U64(10)),
+    )
+}
+
+#[test]
+fn branches_on_arguments_fork() {
+    let report = explore(vec![
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(10),
+        Bytecode::Gt,
+        Bytecode::BrFalse(6),
+        Bytecode::LdConst(1),
+        Bytecode::Abort,
+        Bytecode::Ret,
+    ]);
+    assert_eq!(
+        report.paths,
+        vec![
+            Path {
+                condition: vec![Term::Not(Box::new(arg_gt_10()))],
+                outcome: PathOutcome::Returned,
+            },
+            Path {
+                condition: vec![arg_gt_10()],
+                outcome: PathOutcome::Aborted {
+                    offset: 5,
+                    code: Some(1),
+                },
+            },
+        ]
+    );
+    assert!(report.complete);
+    assert!(report.findings.is_empty());
+    assert_eq!(report.paths[1].condition[0].to_string(), "(arg0 > 10)");
+}
+
+#[test]
+fn definite_failures() {
+    // assert(2 < 1, 7)
+    let report = explore(vec![
+        Bytecode::LdConst(2),
+        Bytecode::LdConst(1),
+        Bytecode::Lt,
+        Bytecode::BrTrue(6),
+        Bytecode::LdConst(7),
+        Bytecode::Abort,
+        Bytecode::Ret,
+    ]);
+    assert_eq!(
+        report.findings,
+        vec![Finding::DefiniteAbort {
+            offset: 5,
+            code: Some(7),
+        }]
+    );
+
+    let report = explore(vec![
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(0),
+        Bytecode::Div,
+        Bytecode::Pop,
+        Bytecode::Ret,
+    ]);
+    assert_eq!(
+        report.findings,
+        vec![Finding::DefiniteArithmeticError { offset: 2 }]
+    );
+}
+
+#[test]
+fn aborts_ruled_out_by_the_path_condition_are_unreachable() {
+    // if (x > 10) { assert(x > 10, 4) }
+    let report = explore(vec![
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(10),
+        Bytecode::Gt,
+        Bytecode::BrFalse(10),
+        Bytecode::CopyLoc(0),
+        Bytecode::LdConst(10),
+        Bytecode::Gt,
+        Bytecode::BrTrue(10),
+        Bytecode::LdConst(4),
+        Bytecode::Abort,
+        Bytecode::Ret,
+    ]);
+    assert_eq!(report.paths.len(), 2);
+    assert!(report.complete);
+    assert_eq!(
+        report.findings,
+        vec![Finding::UnreachableAbort { offset: 9 }]
+    );
+}
+
+#[test]
+fn mutably_borrowed_locals_are_opaque() {
+    let report = explore_with(
+        vec![SignatureToken::U64],
+        vec![
+            Bytecode::LdConst(5),
+            Bytecode::StLoc(1),
+            Bytecode::MutBorrowLoc(1),
+            Bytecode::Pop,
+            Bytecode::CopyLoc(1),
+            Bytecode::LdConst(5),
+            Bytecode::Eq,
+            Bytecode::BrTrue(10),
+            Bytecode::LdConst(1),
+            Bytecode::Abort,
+            Bytecode::Ret,
+        ],
+        |executor| executor,
+    );
+    assert_eq!(report.paths.len(), 2);
+    assert!(report.findings.is_empty());
+}
+
+#[test]
+fn bounds_make_exploration_incomplete() {
+    let report = explore_with(
+        vec![],
+        vec![Bytecode::Branch(0), Bytecode::LdConst(0), Bytecode::Abort],
+        |executor| executor.with_depth_bound(10),
+    );
+    assert_eq!(
+        report.paths,
+        vec![Path {
+            condition: vec![],
+            outcome: PathOutcome::DepthBoundExceeded,
+        }]
+    );
+    assert!(!report.complete);
+    // The abort isn't reported as unreachable, since exploration was cut.
+    assert!(report.findings.is_empty());
+
+    // Both branches fork on a value the engine doesn't model, making four paths.
+    let code = vec![
+        Bytecode::GetGasRemaining,
+        Bytecode::LdConst(0),
+        Bytecode::Gt,
+        Bytecode::BrTrue(4),
+        Bytecode::GetGasRemaining,
+        Bytecode::LdConst(0),
+        Bytecode::Gt,
+        Bytecode::BrTrue(8),
+        Bytecode::Ret,
+    ];
+    let report = explore_with(vec![], code.clone(), |executor| executor);
+    assert_eq!(report.paths.len(), 4);
+    assert!(report.complete);
+    let report = explore_with(vec![], code, |executor| executor.with_path_bound(3));
+    assert_eq!(report.paths.len(), 3);
+    assert!(!report.complete);
+}
@@ -0,0 +1,82 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    file_format::{empty_module, CompiledModule},
+    test_helpers::{check_golden_module_with, line_diff, replay_corpus, CorpusFailureReason},
+};
+use failure::prelude::*;
+use std::fs;
+
+#[test]
+fn replay_corpus_reports_per_file_failures() {
+    let dir = tempfile::tempdir().expect("tempdir should be created");
+    let mut binary = vec![];
+    empty_module()
+        .serialize(&mut binary)
+        .expect("serialization should work");
+    fs::write(dir.path().join("valid"), &binary).unwrap();
+    fs::create_dir(dir.path().join("nested")).unwrap();
+    fs::write(dir.path().join("nested").join("garbage"), b"\xde\xad").unwrap();
+    fs::write(dir.path().join("panics"), &binary).unwrap();
+    fs::write(dir.path().join(".gitkeep"), b"").unwrap();
+
+    let report = replay_corpus(dir.path(), |path, res| {
+        if path.ends_with("panics") {
+            panic!("boom");
+        }
+        match res {
+            Ok(_) => Ok(()),
+            Err(err) => bail!("{}", err),
+        }
+    })
+    .expect("corpus dir should be readable");
+
+    assert_eq!(report.replayed, 3);
+    assert!(!report.is_success());
+    assert_eq!(report.failures.len(), 2);
+    // Failures are reported in sorted path order.
+    assert!(report.failures[0].path.ends_with("nested/garbage"));
+    match &report.failures[0].reason {
+        CorpusFailureReason::Check(_) => (),
+        other => panic!("unexpected failure reason: {}", other),
+    }
+    assert!(report.failures[1].path.ends_with("panics"));
+    match &report.failures[1].reason {
+        CorpusFailureReason::Panic(msg) => assert_eq!(msg, "boom"),
+        other => panic!("unexpected failure reason: {}", other),
+    }
+}
+
+#[test]
+fn golden_files_are_created_compared_and_diffed() {
+    let dir = tempfile::tempdir().expect("tempdir should be created");
+    let module = empty_module().freeze().unwrap();
+
+    let err = check_golden_module_with(dir.path(), "empty", &module, false).unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+
+    check_golden_module_with(dir.path(), "empty", &module, true)
+        .expect("update mode should write the golden files");
+    check_golden_module_with(dir.path(), "empty", &module, false)
+        .expect("output should match freshly written golden files");
+
+    // The hex golden file reads back as the same module.
+    let hex = fs::read_to_string(dir.path().join("empty.mv.hex")).unwrap();
+    assert_eq!(hex.parse::<CompiledModule>().unwrap(), module);
+
+    let disasm = dir.path().join("empty.disasm");
+    fs::write(&disasm, "stale\n").unwrap();
+    let err = check_golden_module_with(dir.path(), "empty", &module, false).unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("empty.disasm"), "{}", message);
+    assert!(!message.contains("empty.mv.hex"), "{}", message);
+    assert!(message.contains("\n-stale\n"), "{}", message);
+}
+
+#[test]
+fn line_diffs_keep_common_lines() {
+    assert_eq!(line_diff("a\nb\nc\n", "a\nx\nc\n"), " a\n-b\n+x\n c\n");
+    assert_eq!(line_diff("", "a\n"), "+a\n");
+    assert_eq!(line_diff("a\n", "a\n"), " a\n");
+}
@@ -0,0 +1,174 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    gas_schedule::{
+        calculate_intrinsic_gas, AbstractMemorySize, GasAlgebra, GasPrice, GasUnits, GAS_SCHEDULE,
+        MAX_PRICE_PER_GAS_UNIT,
+    },
+    transaction_metadata::{TransactionMetadata, TransactionMetadataBuilder, DEFAULT_CHAIN_ID},
+};
+use crypto::ed25519::compat;
+use std::time::Duration;
+use types::{account_address::AccountAddress, transaction::MAX_TRANSACTION_SIZE_IN_BYTES};
+
+#[test]
+fn builder_sets_values() {
+    let sender = AccountAddress::random();
+    let metadata = TransactionMetadataBuilder::new()
+        .with_sender(sender)
+        .with_sequence_number(7)
+        .with_max_gas_amount(GasUnits::new(1_000))
+        .with_gas_unit_price(GasPrice::new(2))
+        .with_transaction_size(AbstractMemorySize::new(300))
+        .build()
+        .expect("metadata should be valid");
+    assert_eq!(metadata.sender(), sender);
+    assert_eq!(metadata.sequence_number(), 7);
+    assert_eq!(metadata.max_gas_amount().get(), 1_000);
+    assert_eq!(metadata.gas_unit_price().get(), 2);
+    assert_eq!(metadata.transaction_size().get(), 300);
+}
+
+#[test]
+fn builder_checks_invariants() {
+    assert!(TransactionMetadataBuilder::new().build().is_ok());
+    assert!(TransactionMetadataBuilder::new()
+        .with_max_gas_amount(GasUnits::new(0))
+        .build()
+        .is_err());
+    assert!(TransactionMetadataBuilder::new()
+        .with_gas_unit_price(MAX_PRICE_PER_GAS_UNIT.add(GasPrice::new(1)))
+        .build()
+        .is_err());
+    assert!(TransactionMetadataBuilder::new()
+        .with_transaction_size(AbstractMemorySize::new(
+            MAX_TRANSACTION_SIZE_IN_BYTES as u64 + 1
+        ))
+        .build()
+        .is_err());
+}
+
+#[test]
+fn secondary_signers() {
+    let sender = AccountAddress::random();
+    let (_, sender_key) = compat::generate_keypair(None);
+    let secondary = AccountAddress::random();
+    let (_, secondary_key) = compat::generate_keypair(None);
+    let metadata = TransactionMetadataBuilder::new()
+        .with_sender(sender)
+        .with_public_key(sender_key.clone())
+        .with_secondary_signers(vec![(secondary, secondary_key.clone())])
+        .build()
+        .expect("metadata should be valid");
+    assert!(metadata.is_multi_agent());
+    assert_eq!(metadata.secondary_signers(), &[secondary]);
+    assert_eq!(
+        metadata.secondary_signer_public_keys(),
+        std::slice::from_ref(&secondary_key)
+    );
+    assert_eq!(metadata.signers(), vec![sender, secondary]);
+    assert_eq!(
+        metadata.signer_public_keys(),
+        vec![&sender_key, &secondary_key]
+    );
+
+    // The sender can't also be a secondary signer.
+    assert!(TransactionMetadataBuilder::new()
+        .with_sender(sender)
+        .with_secondary_signers(vec![(sender, secondary_key)])
+        .build()
+        .is_err());
+}
+
+#[test]
+fn chain_id_and_expiration() {
+    let metadata = TransactionMetadataBuilder::new()
+        .build()
+        .expect("metadata should be valid");
+    assert!(metadata.is_for_chain(DEFAULT_CHAIN_ID));
+    assert!(!metadata.is_expired(Duration::from_secs(1_000_000)));
+
+    let metadata = TransactionMetadataBuilder::new()
+        .with_chain_id(2)
+        .with_expiration_timestamp(Duration::from_secs(100))
+        .build()
+        .expect("metadata should be valid");
+    assert_eq!(metadata.chain_id(), 2);
+    assert!(metadata.is_for_chain(2));
+    assert!(!metadata.is_for_chain(DEFAULT_CHAIN_ID));
+    assert_eq!(metadata.expiration_timestamp(), Duration::from_secs(100));
+    assert!(!metadata.is_expired(Duration::from_secs(99)));
+    assert!(metadata.is_expired(Duration::from_secs(100)));
+
+    assert!(TransactionMetadataBuilder::new()
+        .with_expiration_timestamp(Duration::from_secs(0))
+        .build()
+        .is_err());
+}
+
+#[test]
+fn metadata_round_trips_through_json() {
+    let (_, secondary_key) = compat::generate_keypair(None);
+    let metadata = TransactionMetadataBuilder::new()
+        .with_sender(AccountAddress::random())
+        .with_sequence_number(3)
+        .with_gas_unit_price(GasPrice::new(1))
+        .with_secondary_signers(vec![(AccountAddress::random(), secondary_key)])
+        .build()
+        .expect("metadata should be valid");
+    let json = serde_json::to_string(&metadata).expect("metadata should serialize");
+    let parsed = TransactionMetadata::from_json(&json).expect("metadata should deserialize");
+    assert_eq!(parsed.sender(), metadata.sender());
+    assert_eq!(parsed.public_key(), metadata.public_key());
+    assert_eq!(parsed.sequence_number(), 3);
+    assert_eq!(parsed.gas_unit_price().get(), 1);
+    assert_eq!(parsed.signers(), metadata.signers());
+    assert_eq!(parsed.signer_public_keys(), metadata.signer_public_keys());
+    assert_eq!(
+        parsed.expiration_timestamp(),
+        metadata.expiration_timestamp()
+    );
+}
+
+#[test]
+fn metadata_loads_from_partial_toml() {
+    let metadata = TransactionMetadata::from_toml(
+        r#"
+        sequence_number = 12
+        max_gas_amount = 5000
+        chain_id = 4
+        "#,
+    )
+    .expect("fixture should load");
+    assert_eq!(metadata.sequence_number(), 12);
+    assert_eq!(metadata.max_gas_amount().get(), 5000);
+    assert_eq!(metadata.chain_id(), 4);
+    // Missing fields are taken from the default metadata.
+    assert_eq!(metadata.sender(), AccountAddress::default());
+    assert!(!metadata.is_multi_agent());
+
+    // Fixtures are subject to the same checks as the builder.
+    assert!(TransactionMetadata::from_toml("max_gas_amount = 0").is_err());
+    assert!(TransactionMetadata::from_toml(r#"public_key = "not hex""#).is_err());
+}
+
+#[test]
+fn gas_context_from_metadata() {
+    let metadata = TransactionMetadataBuilder::new()
+        .with_max_gas_amount(GasUnits::new(1_000))
+        .with_gas_unit_price(GasPrice::new(3))
+        .with_transaction_size(AbstractMemorySize::new(100))
+        .build()
+        .expect("metadata should be valid");
+    let context = metadata.gas_context(&GAS_SCHEDULE);
+    assert_eq!(context.max_gas_amount().get(), 1_000);
+    assert_eq!(context.gas_unit_price().get(), 3);
+    assert_eq!(context.transaction_size().get(), 100);
+    assert_eq!(
+        context.intrinsic_gas(),
+        calculate_intrinsic_gas(AbstractMemorySize::new(100))
+    );
+    // 400 units of gas were consumed, at 3 per unit.
+    assert_eq!(context.gas_fee(GasUnits::new(600)).get(), 1_200);
+}
@@ -0,0 +1,250 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    access::ModuleAccess,
+    builder::{CodeBuilder, CompiledModuleBuilder},
+    file_format::{
+        empty_module, AddressPoolIndex, ByteArrayPoolIndex, Bytecode, CodeUnit, CompiledModule,
+        FieldDefinition, FieldDefinitionIndex, FunctionHandle, FunctionHandleIndex,
+        FunctionSignature, FunctionSignatureIndex, Kind, LocalsSignature, LocalsSignatureIndex,
+        ModuleHandleIndex, SignatureToken, StringPoolIndex, StructDefinition,
+        StructDefinitionIndex, StructFieldInformation, StructHandle, StructHandleIndex,
+        TypeSignature, TypeSignatureIndex, NO_TYPE_ACTUALS,
+    },
+    views::{
+        struct_handles_compatible, FunctionDefinitionView, InstructionMatch, ModuleView,
+        StructHandleView,
+    },
+    IndexKind,
+};
+use types::{account_address::AccountAddress, byte_array::ByteArray};
+
+/// Returns module `0x1::Coin`, with a struct `T { value: u64 }` and a handle to a function `mint`.
+fn coin_module() -> CompiledModule {
+    let mut address = [0u8; 32];
+    address[31] = 1;
+    let mut module = empty_module();
+    module.address_pool[0] = AccountAddress::new(address);
+    module.string_pool = vec!["Coin".into(), "T".into(), "value".into(), "mint".into()];
+    module
+        .byte_array_pool
+        .push(ByteArray::new(vec![0xab, 0xcd]));
+    module.struct_handles.push(StructHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(1),
+        is_nominal_resource: false,
+        type_formals: vec![],
+    });
+    module
+        .type_signatures
+        .push(TypeSignature(SignatureToken::U64));
+    module.field_defs.push(FieldDefinition {
+        struct_: StructHandleIndex::new(0),
+        name: StringPoolIndex::new(2),
+        signature: TypeSignatureIndex::new(0),
+    });
+    module.struct_defs.push(StructDefinition {
+        struct_handle: StructHandleIndex::new(0),
+        field_information: StructFieldInformation::Declared {
+            field_count: 1,
+            fields: FieldDefinitionIndex::new(0),
+        },
+    });
+    module.function_signatures.push(FunctionSignature {
+        return_types: vec![].into(),
+        arg_types: vec![].into(),
+        type_formals: vec![],
+    });
+    module.function_handles.push(FunctionHandle {
+        module: ModuleHandleIndex::new(0),
+        name: StringPoolIndex::new(3),
+        signature: FunctionSignatureIndex::new(0),
+    });
+    module.locals_signatures.push(LocalsSignature(
+        vec![
+            SignatureToken::U64,
+            SignatureToken::Reference(Box::new(SignatureToken::Struct(
+                StructHandleIndex::new(0),
+                vec![],
+            ))),
+        ]
+        .into(),
+    ));
+    module.freeze().expect("module should be bounds checked")
+}
+
+#[test]
+fn bytecode_operands_resolve_to_names() {
+    let module = coin_module();
+    let view = ModuleView::new(&module);
+    let render = |bytecode: Bytecode| bytecode.display(&view).to_string();
+
+    let no_actuals = LocalsSignatureIndex::new(0);
+    assert_eq!(
+        render(Bytecode::Call(FunctionHandleIndex::new(0), no_actuals)),
+        "Call 0x1::Coin::mint"
+    );
+    assert_eq!(
+        render(Bytecode::Pack(StructDefinitionIndex::new(0), no_actuals)),
+        "Pack Coin::T"
+    );
+    assert_eq!(
+        render(Bytecode::BorrowGlobal(
+            StructDefinitionIndex::new(0),
+            LocalsSignatureIndex::new(1)
+        )),
+        "BorrowGlobal Coin::T<U64, &Coin::T>"
+    );
+    assert_eq!(
+        render(Bytecode::ImmBorrowField(FieldDefinitionIndex::new(0))),
+        "ImmBorrowField Coin::T.value"
+    );
+    assert_eq!(
+        render(Bytecode::LdStr(StringPoolIndex::new(3))),
+        "LdStr \"mint\""
+    );
+    assert_eq!(
+        render(Bytecode::LdByteArray(ByteArrayPoolIndex::new(0))),
+        "LdByteArray 0xabcd"
+    );
+    assert_eq!(
+        render(Bytecode::LdAddr(AddressPoolIndex::new(0))),
+        "LdAddr 0x1"
+    );
+
+    // Instructions without indexes, and indexes that don't resolve, are shown as with `Debug`.
+    assert_eq!(render(Bytecode::BrTrue(3)), "BrTrue(3)");
+    let unresolved = Bytecode::Call(FunctionHandleIndex::new(7), no_actuals);
+    assert_eq!(render(unresolved.clone()), format!("{:?}", unresolved));
+}
+
+#[test]
+fn function_kind_counts_include_function_local_kinds() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let mut code = CodeBuilder::new();
+    code.emit(Bytecode::CopyLoc(0));
+    code.emit(Bytecode::StLoc(1));
+    code.emit(Bytecode::Ret);
+    let f = builder.add_function(
+        "f",
+        CodeUnit::PUBLIC,
+        FunctionSignature {
+            arg_types: vec![SignatureToken::TypeParameter(0)].into(),
+            return_types: vec![].into(),
+            type_formals: vec![Kind::All],
+        },
+        vec![SignatureToken::TypeParameter(0)],
+        vec![],
+        code,
+    );
+    let module = builder.build().expect("module is bounds-valid");
+
+    let function = FunctionDefinitionView::new(&module, module.function_def_at(f));
+    assert_eq!(function.kind_count(IndexKind::LocalPool), 2);
+    assert_eq!(function.kind_count(IndexKind::CodeDefinition), 3);
+    assert_eq!(function.kind_count(IndexKind::TypeParameter), 1);
+    for kind in &[IndexKind::FunctionDefinition, IndexKind::StringPool] {
+        assert_eq!(function.kind_count(*kind), module.kind_count(*kind));
+    }
+}
+
+#[test]
+fn struct_handles_are_compatible_across_modules() {
+    let a = AccountAddress::random();
+    let mut builder = CompiledModuleBuilder::new(a, "A");
+    builder.add_struct("S", true, vec![Kind::All], vec![]);
+    let module_a = builder.build().unwrap();
+    let definition = ModuleView::new(&module_a)
+        .structs()
+        .next()
+        .expect("A defines S");
+
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "B");
+    let handle_to_a = builder.add_module_handle(a, "A");
+    let handle_to_other = builder.add_module_handle(a, "Other");
+    let same = builder.add_struct_handle(handle_to_a, "S", true, vec![Kind::All]);
+    let other_name = builder.add_struct_handle(handle_to_a, "T", true, vec![Kind::All]);
+    let other_module = builder.add_struct_handle(handle_to_other, "S", true, vec![Kind::All]);
+    let module_b = builder.build().unwrap();
+    let handle = |idx| StructHandleView::new(&module_b, module_b.struct_handle_at(idx));
+
+    assert!(struct_handles_compatible(
+        &definition.handle(),
+        &handle(same)
+    ));
+    assert!(!struct_handles_compatible(
+        &definition.handle(),
+        &handle(other_name)
+    ));
+    assert!(!struct_handles_compatible(
+        &definition.handle(),
+        &handle(other_module)
+    ));
+
+    // Handles must agree on the kind of the struct, and on the kinds of its type formals rather
+    // than only on their number.
+    let with_handle = |change: fn(&mut StructHandle)| {
+        let mut module = module_b.clone().into_inner();
+        change(&mut module.struct_handles[same.0 as usize]);
+        module.freeze().unwrap()
+    };
+    let not_resource = with_handle(|handle| handle.is_nominal_resource = false);
+    let resource_formal = with_handle(|handle| handle.type_formals = vec![Kind::Resource]);
+    for module in &[not_resource, resource_formal] {
+        let changed = StructHandleView::new(module, module.struct_handle_at(same));
+        assert!(!struct_handles_compatible(&definition.handle(), &changed));
+    }
+}
+
+#[test]
+fn instructions_are_found_across_functions() {
+    let mut builder = CompiledModuleBuilder::new(AccountAddress::default(), "M");
+    let r = builder.add_struct("R", true, vec![], vec![("x", SignatureToken::U64)]);
+    let other = builder.add_struct("Other", true, vec![], vec![("x", SignatureToken::U64)]);
+    let unit = || FunctionSignature {
+        arg_types: vec![].into(),
+        return_types: vec![].into(),
+        type_formals: vec![],
+    };
+    let publish = |builder: &mut CompiledModuleBuilder, name, resource| {
+        let mut code = CodeBuilder::new();
+        code.emit(Bytecode::LdConst(0));
+        code.emit(Bytecode::Pack(resource, NO_TYPE_ACTUALS));
+        code.emit(Bytecode::MoveToSender(resource, NO_TYPE_ACTUALS));
+        code.emit(Bytecode::Ret);
+        builder.add_function(name, CodeUnit::PUBLIC, unit(), vec![], vec![], code)
+    };
+    let first = publish(&mut builder, "first", r);
+    publish(&mut builder, "other", other);
+    let second = publish(&mut builder, "second", r);
+    let module = builder.build().unwrap();
+    let view = ModuleView::new(&module);
+
+    let move_to_sender_r = Bytecode::MoveToSender(r, NO_TYPE_ACTUALS);
+    let matches = view.find_instructions(|bytecode| match bytecode {
+        Bytecode::MoveToSender(idx, _) => *idx == r,
+        _ => false,
+    });
+    assert_eq!(
+        matches,
+        vec![
+            InstructionMatch {
+                function: first,
+                offset: 2,
+                instruction: &move_to_sender_r,
+            },
+            InstructionMatch {
+                function: second,
+                offset: 2,
+                instruction: &move_to_sender_r,
+            },
+        ]
+    );
+    assert_eq!(
+        view.find_instructions(|bytecode| bytecode == &Bytecode::Ret)
+            .len(),
+        3
+    );
+    assert!(view.find_instructions(|_| false).is_empty());
+}
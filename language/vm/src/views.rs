@@ -14,41 +14,45 @@
 use std::iter::DoubleEndedIterator;
 
 use crate::{
-    access::ModuleAccess,
+    access::{ModuleAccess, ScriptAccess},
+    errors::short_address,
     file_format::{
-        CodeUnit, CompiledModule, FieldDefinition, FunctionDefinition, FunctionHandle,
-        FunctionSignature, Kind, LocalIndex, LocalsSignature, ModuleHandle, SignatureToken,
-        StructDefinition, StructDefinitionIndex, StructFieldInformation, StructHandle,
-        StructHandleIndex, TypeSignature,
+        Bytecode, CodeOffset, CodeUnit, FieldDefinition, FunctionDefinition,
+        FunctionDefinitionIndex, FunctionHandle, FunctionSignature, Kind, LocalIndex,
+        LocalsSignature, LocalsSignatureIndex, ModuleHandle, SignatureToken, StructDefinition,
+        StructDefinitionIndex, StructFieldInformation, StructHandle, StructHandleIndex, TableIndex,
+        TypeSignature,
     },
-    SignatureTokenKind,
+    identifier::Identifier,
+    IndexKind, SignatureTokenKind,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt,
 };
-use std::collections::BTreeSet;
 
 use types::language_storage::ModuleId;
 
-use std::collections::BTreeMap;
-
 /// Represents a lazily evaluated abstraction over a module.
 ///
 /// `T` here is any sort of `ModuleAccess`. See the documentation in access.rs for more.
 pub struct ModuleView<'a, T> {
     module: &'a T,
-    name_to_function_definition_view: BTreeMap<&'a str, FunctionDefinitionView<'a, T>>,
-    name_to_struct_definition_view: BTreeMap<&'a str, StructDefinitionView<'a, T>>,
+    name_to_function_definition_view: HashMap<&'a Identifier, FunctionDefinitionView<'a, T>>,
+    name_to_struct_definition_view: HashMap<&'a Identifier, StructDefinitionView<'a, T>>,
 }
 
 impl<'a, T: ModuleAccess> ModuleView<'a, T> {
     pub fn new(module: &'a T) -> Self {
-        let mut name_to_function_definition_view = BTreeMap::new();
+        let mut name_to_function_definition_view = HashMap::new();
         for function_def in module.function_defs() {
             let view = FunctionDefinitionView::new(module, function_def);
-            name_to_function_definition_view.insert(view.name(), view);
+            name_to_function_definition_view.insert(view.identifier(), view);
         }
-        let mut name_to_struct_definition_view = BTreeMap::new();
+        let mut name_to_struct_definition_view = HashMap::new();
         for struct_def in module.struct_defs() {
             let view = StructDefinitionView::new(module, struct_def);
-            name_to_struct_definition_view.insert(view.name(), view);
+            name_to_struct_definition_view.insert(view.identifier(), view);
         }
         Self {
             module,
@@ -143,11 +147,22 @@ impl<'a, T: ModuleAccess> ModuleView<'a, T> {
             .map(move |locals_signature| LocalsSignatureView::new(module, locals_signature))
     }
 
-    pub fn function_definition(&self, name: &'a str) -> Option<&FunctionDefinitionView<'a, T>> {
+    /// Returns the function definition at `idx`, or `None` if `idx` is out of bounds.
+    pub fn function_definition_at(
+        &self,
+        idx: FunctionDefinitionIndex,
+    ) -> Option<FunctionDefinitionView<'a, T>> {
+        self.module
+            .get_function_def_at(idx)
+            .ok()
+            .map(|function_def| FunctionDefinitionView::new(self.module, function_def))
+    }
+
+    pub fn function_definition(&self, name: &Identifier) -> Option<&FunctionDefinitionView<'a, T>> {
         self.name_to_function_definition_view.get(name)
     }
 
-    pub fn struct_definition(&self, name: &'a str) -> Option<&StructDefinitionView<'a, T>> {
+    pub fn struct_definition(&self, name: &Identifier) -> Option<&StructDefinitionView<'a, T>> {
         self.name_to_struct_definition_view.get(name)
     }
 
@@ -155,16 +170,12 @@ impl<'a, T: ModuleAccess> ModuleView<'a, T> {
         &self,
         function_handle: &FunctionHandle,
     ) -> BTreeSet<StructDefinitionIndex> {
-        if function_handle.module.0 != CompiledModule::IMPLEMENTED_MODULE_INDEX {
+        if !self.module.is_self_handle(function_handle.module) {
             return BTreeSet::new();
         }
 
         // TODO these unwraps should be VMInvariantViolations
-        let function_name = self
-            .as_inner()
-            .string_pool()
-            .get(function_handle.name.0 as usize)
-            .unwrap();
+        let function_name = self.module.get_identifier_at(function_handle.name).unwrap();
         let function_def = self.function_definition(function_name).unwrap();
         function_def
             .as_inner()
@@ -177,6 +188,70 @@ impl<'a, T: ModuleAccess> ModuleView<'a, T> {
     pub fn id(&self) -> ModuleId {
         self.module.self_id()
     }
+
+    /// Returns every instruction in the code of the functions of the module for which `predicate`
+    /// holds, in order of function definition and then of offset.
+    ///
+    /// For example, `view.find_instructions(|bytecode| bytecode == &Bytecode::Ret)` finds every
+    /// return.
+    pub fn find_instructions<P>(&self, mut predicate: P) -> Vec<InstructionMatch<'a>>
+    where
+        P: FnMut(&Bytecode) -> bool,
+    {
+        let mut matches = vec![];
+        for (function_idx, function_def) in self.module.function_defs().iter().enumerate() {
+            for (offset, instruction) in function_def.code.code.iter().enumerate() {
+                if predicate(instruction) {
+                    matches.push(InstructionMatch {
+                        function: FunctionDefinitionIndex::new(function_idx as TableIndex),
+                        offset: offset as CodeOffset,
+                        instruction,
+                    });
+                }
+            }
+        }
+        matches
+    }
+}
+
+/// An instruction found by `ModuleView::find_instructions`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InstructionMatch<'a> {
+    /// The function definition whose code the instruction is in.
+    pub function: FunctionDefinitionIndex,
+    pub offset: CodeOffset,
+    pub instruction: &'a Bytecode,
+}
+
+/// Represents a lazily evaluated abstraction over a script.
+///
+/// `T` here is any sort of `ScriptAccess`. See the documentation in access.rs for more.
+pub struct ScriptView<'a, T> {
+    script: &'a T,
+}
+
+impl<'a, T: ScriptAccess> ScriptView<'a, T> {
+    pub fn new(script: &'a T) -> Self {
+        Self { script }
+    }
+
+    pub fn main(&self) -> &'a FunctionDefinition {
+        self.script.main()
+    }
+
+    /// Returns the signature of the main function, which the transaction arguments are passed to.
+    pub fn signature(&self) -> &'a FunctionSignature {
+        let handle = self.script.function_handle_at(self.main().function);
+        self.script.function_signature_at(handle.signature)
+    }
+
+    pub fn arg_tokens(&self) -> impl DoubleEndedIterator<Item = &'a SignatureToken> + 'a {
+        self.signature().arg_types.iter()
+    }
+
+    pub fn arg_count(&self) -> usize {
+        self.signature().arg_types.len()
+    }
 }
 
 pub struct ModuleHandleView<'a, T> {
@@ -230,6 +305,10 @@ impl<'a, T: ModuleAccess> StructHandleView<'a, T> {
         self.module.string_at(self.struct_handle.name)
     }
 
+    pub fn identifier(&self) -> &'a Identifier {
+        self.module.identifier_at(self.struct_handle.name)
+    }
+
     pub fn module_id(&self) -> ModuleId {
         self.module.module_id_for_handle(self.module_handle())
     }
@@ -256,6 +335,10 @@ impl<'a, T: ModuleAccess> FunctionHandleView<'a, T> {
         self.module.string_at(self.function_handle.name)
     }
 
+    pub fn identifier(&self) -> &'a Identifier {
+        self.module.identifier_at(self.function_handle.name)
+    }
+
     pub fn signature(&self) -> FunctionSignatureView<'a, T> {
         let function_signature = self
             .module
@@ -268,6 +351,19 @@ impl<'a, T: ModuleAccess> FunctionHandleView<'a, T> {
     }
 }
 
+/// Returns true if the struct handles `a` and `b`, which may belong to different modules, refer to
+/// the same struct and agree on it: they name the same struct of the same module, and have the
+/// same nominal resource flag and type formals (the same number, with the same kinds).
+pub fn struct_handles_compatible<A: ModuleAccess, B: ModuleAccess>(
+    a: &StructHandleView<A>,
+    b: &StructHandleView<B>,
+) -> bool {
+    a.name() == b.name()
+        && a.module_id() == b.module_id()
+        && a.is_nominal_resource() == b.is_nominal_resource()
+        && a.type_formals() == b.type_formals()
+}
+
 pub struct StructDefinitionView<'a, T> {
     module: &'a T,
     struct_def: &'a StructDefinition,
@@ -300,6 +396,13 @@ impl<'a, T: ModuleAccess> StructDefinitionView<'a, T> {
         self.struct_handle_view.type_formals()
     }
 
+    pub fn handle(&self) -> StructHandleView<'a, T> {
+        StructHandleView::new(
+            self.module,
+            self.module.struct_handle_at(self.struct_def.struct_handle),
+        )
+    }
+
     pub fn fields(
         &self,
     ) -> Option<impl DoubleEndedIterator<Item = FieldDefinitionView<'a, T>> + Send> {
@@ -321,6 +424,10 @@ impl<'a, T: ModuleAccess> StructDefinitionView<'a, T> {
     pub fn name(&self) -> &'a str {
         self.struct_handle_view.name()
     }
+
+    pub fn identifier(&self) -> &'a Identifier {
+        self.struct_handle_view.identifier()
+    }
 }
 
 pub struct FieldDefinitionView<'a, T> {
@@ -337,6 +444,10 @@ impl<'a, T: ModuleAccess> FieldDefinitionView<'a, T> {
         self.module.string_at(self.field_def.name)
     }
 
+    pub fn identifier(&self) -> &'a Identifier {
+        self.module.identifier_at(self.field_def.name)
+    }
+
     pub fn type_signature(&self) -> TypeSignatureView<'a, T> {
         let type_signature = self.module.type_signature_at(self.field_def.signature);
         TypeSignatureView::new(self.module, type_signature)
@@ -391,6 +502,10 @@ impl<'a, T: ModuleAccess> FunctionDefinitionView<'a, T> {
         self.function_handle_view.name()
     }
 
+    pub fn identifier(&self) -> &'a Identifier {
+        self.function_handle_view.identifier()
+    }
+
     pub fn signature(&self) -> FunctionSignatureView<'a, T> {
         self.function_handle_view.signature()
     }
@@ -398,6 +513,21 @@ impl<'a, T: ModuleAccess> FunctionDefinitionView<'a, T> {
     pub fn code(&self) -> &'a CodeUnit {
         &self.function_def.code
     }
+
+    /// Returns the number of items of `kind` that an index in this function can refer to.
+    ///
+    /// Unlike `CompiledModule::kind_count`, this also answers for the kinds that are local to a
+    /// function: `LocalPool` (the locals of the function, including its arguments),
+    /// `CodeDefinition` (the instructions in its code) and `TypeParameter` (its type formals).
+    /// Other kinds are counted across the module.
+    pub fn kind_count(&self, kind: IndexKind) -> usize {
+        match kind {
+            IndexKind::LocalPool => self.locals_signature().len(),
+            IndexKind::CodeDefinition => self.function_def.code.code.len(),
+            IndexKind::TypeParameter => self.signature().type_formal_count(),
+            other => self.module.as_module().kind_count(other),
+        }
+    }
 }
 
 pub struct TypeSignatureView<'a, T> {
@@ -469,6 +599,10 @@ impl<'a, T: ModuleAccess> FunctionSignatureView<'a, T> {
     pub fn arg_count(&self) -> usize {
         self.function_signature.arg_types.len()
     }
+
+    pub fn type_formal_count(&self) -> usize {
+        self.function_signature.type_formals.len()
+    }
 }
 
 pub struct LocalsSignatureView<'a, T> {
@@ -584,9 +718,8 @@ impl<'a, T: ModuleAccess> SignatureTokenView<'a, T> {
     /// Determines if the given signature token contains a nominal resource.
     /// More specifically, a signature token contains a nominal resource if
     ///   1) it is a type variable explicitly marked as resource kind.
-    ///   2) it is a struct that
-    ///       a) is marked as resource.
-    ///       b) has a type actual which is a nominal resource.
+    ///   2) it is a struct that a) is marked as resource. b) has a type actual which is a nominal
+    ///      resource.
     ///
     /// Similar to `SignatureTokenView::kind`, the context is used for looking up struct
     /// definitions & type formals.
@@ -683,3 +816,157 @@ impl_view_internals!(TypeSignatureView, TypeSignature, type_signature);
 impl_view_internals!(FunctionSignatureView, FunctionSignature, function_signature);
 impl_view_internals!(LocalsSignatureView, LocalsSignature, locals_signature);
 impl_view_internals!(SignatureTokenView, SignatureToken, token);
+
+impl Bytecode {
+    /// Returns a value that displays this instruction with its operands resolved to names in
+    /// `view` (e.g. `Call 0x1::Coin::mint`, `Pack Coin::T`).
+    ///
+    /// Instructions whose operands aren't indexes, or don't resolve in `view`, are displayed with
+    /// their indexes, the same way as with `Debug`.
+    pub fn display<'a, T: ModuleAccess>(
+        &'a self,
+        view: &'a ModuleView<'a, T>,
+    ) -> BytecodeDisplay<'a, T> {
+        BytecodeDisplay {
+            bytecode: self,
+            view,
+        }
+    }
+}
+
+/// Displays a `Bytecode` with its operands resolved to names. Created by `Bytecode::display`.
+pub struct BytecodeDisplay<'a, T> {
+    bytecode: &'a Bytecode,
+    view: &'a ModuleView<'a, T>,
+}
+
+impl<'a, T: ModuleAccess> BytecodeDisplay<'a, T> {
+    /// Returns the mnemonic and the resolved operand of the instruction, or `None` if it has no
+    /// operand to resolve or the operand doesn't resolve.
+    fn resolve(&self) -> Option<(&'static str, String)> {
+        let module = self.view.module;
+        let resolved = match self.bytecode {
+            Bytecode::LdStr(idx) => ("LdStr", format!("{:?}", module.get_string_at(*idx).ok()?)),
+            Bytecode::LdByteArray(idx) => (
+                "LdByteArray",
+                format!(
+                    "0x{}",
+                    hex::encode(module.get_byte_array_at(*idx).ok()?.as_bytes())
+                ),
+            ),
+            Bytecode::LdAddr(idx) => ("LdAddr", short_address(module.get_address_at(*idx).ok()?)),
+            Bytecode::Call(idx, type_actuals) => {
+                let handle = module.get_function_handle_at(*idx).ok()?;
+                let module_handle = module.get_module_handle_at(handle.module).ok()?;
+                let name = format!(
+                    "{}::{}::{}{}",
+                    short_address(module.get_address_at(module_handle.address).ok()?),
+                    module.get_string_at(module_handle.name).ok()?,
+                    module.get_string_at(handle.name).ok()?,
+                    type_actuals_name(module, *type_actuals)?
+                );
+                ("Call", name)
+            }
+            Bytecode::Pack(idx, type_actuals)
+            | Bytecode::Unpack(idx, type_actuals)
+            | Bytecode::Exists(idx, type_actuals)
+            | Bytecode::BorrowGlobal(idx, type_actuals)
+            | Bytecode::MoveFrom(idx, type_actuals)
+            | Bytecode::MoveToSender(idx, type_actuals) => {
+                let struct_def = module.get_struct_def_at(*idx).ok()?;
+                let name = format!(
+                    "{}{}",
+                    struct_name(module, struct_def.struct_handle)?,
+                    type_actuals_name(module, *type_actuals)?
+                );
+                (mnemonic(self.bytecode), name)
+            }
+            Bytecode::MutBorrowField(idx) | Bytecode::ImmBorrowField(idx) => {
+                let field_def = module.get_field_def_at(*idx).ok()?;
+                let name = format!(
+                    "{}.{}",
+                    struct_name(module, field_def.struct_)?,
+                    module.get_string_at(field_def.name).ok()?
+                );
+                (mnemonic(self.bytecode), name)
+            }
+            _ => return None,
+        };
+        Some(resolved)
+    }
+}
+
+impl<'a, T: ModuleAccess> fmt::Display for BytecodeDisplay<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.resolve() {
+            Some((mnemonic, operand)) => write!(f, "{} {}", mnemonic, operand),
+            None => write!(f, "{:?}", self.bytecode),
+        }
+    }
+}
+
+/// Returns the mnemonic of an instruction that refers to a struct or a field definition.
+fn mnemonic(bytecode: &Bytecode) -> &'static str {
+    match bytecode {
+        Bytecode::Pack(..) => "Pack",
+        Bytecode::Unpack(..) => "Unpack",
+        Bytecode::Exists(..) => "Exists",
+        Bytecode::BorrowGlobal(..) => "BorrowGlobal",
+        Bytecode::MoveFrom(..) => "MoveFrom",
+        Bytecode::MoveToSender(..) => "MoveToSender",
+        Bytecode::MutBorrowField(_) => "MutBorrowField",
+        Bytecode::ImmBorrowField(_) => "ImmBorrowField",
+        _ => unreachable!("{:?} doesn't refer to a struct or a field", bytecode),
+    }
+}
+
+/// Formats the name of a struct qualified by the name of its module, e.g. `Coin::T`.
+fn struct_name<T: ModuleAccess>(module: &T, idx: StructHandleIndex) -> Option<String> {
+    let handle = module.get_struct_handle_at(idx).ok()?;
+    let module_handle = module.get_module_handle_at(handle.module).ok()?;
+    Some(format!(
+        "{}::{}",
+        module.get_string_at(module_handle.name).ok()?,
+        module.get_string_at(handle.name).ok()?
+    ))
+}
+
+/// Formats the type actuals of an instruction, e.g. `<U64, &Coin::T>`, or nothing if there are
+/// none.
+fn type_actuals_name<T: ModuleAccess>(module: &T, idx: LocalsSignatureIndex) -> Option<String> {
+    let tokens = &module.get_locals_signature_at(idx).ok()?.0;
+    if tokens.is_empty() {
+        return Some(String::new());
+    }
+    let names = tokens
+        .iter()
+        .map(|token| token_name(module, token))
+        .collect::<Option<Vec<_>>>()?;
+    Some(format!("<{}>", names.join(", ")))
+}
+
+/// Formats a type, e.g. `&mut Coin::T<U64>`.
+fn token_name<T: ModuleAccess>(module: &T, token: &SignatureToken) -> Option<String> {
+    let name = match token {
+        SignatureToken::Bool => "Bool".to_string(),
+        SignatureToken::U64 => "U64".to_string(),
+        SignatureToken::String => "String".to_string(),
+        SignatureToken::ByteArray => "ByteArray".to_string(),
+        SignatureToken::Address => "Address".to_string(),
+        SignatureToken::Struct(idx, actuals) => {
+            let names = actuals
+                .iter()
+                .map(|actual| token_name(module, actual))
+                .collect::<Option<Vec<_>>>()?;
+            if names.is_empty() {
+                struct_name(module, *idx)?
+            } else {
+                format!("{}<{}>", struct_name(module, *idx)?, names.join(", "))
+            }
+        }
+        SignatureToken::Reference(inner) => format!("&{}", token_name(module, inner)?),
+        SignatureToken::MutableReference(inner) => format!("&mut {}", token_name(module, inner)?),
+        SignatureToken::TypeParameter(idx) => format!("T{}", idx),
+    };
+    Some(name)
+}
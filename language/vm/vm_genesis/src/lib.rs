@@ -27,7 +27,7 @@ use types::{
     },
     validator_public_keys::ValidatorPublicKeys,
 };
-use vm::{access::ModuleAccess, transaction_metadata::TransactionMetadata};
+use vm::{access::ModuleAccess, transaction_metadata::TransactionMetadataBuilder};
 use vm_cache_map::Arena;
 use vm_runtime::{
     code_cache::{
@@ -314,8 +314,10 @@ pub fn encode_genesis_transaction_with_validator(
         let data_cache = BlockDataCache::new(&state_view);
         let block_cache = BlockModuleCache::new(&vm_cache, fake_fetcher);
         {
-            let mut txn_data = TransactionMetadata::default();
-            txn_data.sender = genesis_addr;
+            let txn_data = TransactionMetadataBuilder::new()
+                .with_sender(genesis_addr)
+                .build()
+                .expect("genesis transaction metadata should be valid");
 
             let mut txn_executor = TransactionExecutor::new(&block_cache, &data_cache, txn_data);
             txn_executor.create_account(genesis_addr).unwrap().unwrap();
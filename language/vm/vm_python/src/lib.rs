@@ -0,0 +1,206 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Exposes compiled modules to Python through pyo3, for analyses of on-chain modules written in
+//! Python. The crate builds as an extension module named `vm_python`:
+//!
+//! ```python
+//! import vm_python
+//!
+//! module = vm_python.load_module("LibraCoin.mv")
+//! print(module.address, module.name, module.stats()["instructions"])
+//! for function in module.functions():
+//!     print(function.name, function.public, function.instructions)
+//! print(module.disassemble())
+//! ```
+//!
+//! Everything is read-only: the Python classes are snapshots of the module they were taken from.
+
+use pyo3::{exceptions::ValueError, prelude::*, types::PyBytes, wrap_pyfunction};
+use std::{collections::HashMap, fs};
+use vm::{
+    access::ModuleAccess,
+    errors::short_address,
+    file_format::CompiledModule,
+    views::{ModuleView, ViewInternals},
+};
+
+/// A deserialized module.
+#[pyclass(name = Module)]
+pub struct PyCompiledModule {
+    module: CompiledModule,
+}
+
+/// A struct definition of a module.
+#[pyclass(name = Struct)]
+pub struct PyStructDefinition {
+    #[pyo3(get)]
+    name: String,
+    #[pyo3(get)]
+    resource: bool,
+    #[pyo3(get)]
+    native: bool,
+    /// The names of the fields, in order. Native structs have none.
+    #[pyo3(get)]
+    fields: Vec<String>,
+}
+
+/// A function definition of a module.
+#[pyclass(name = Function)]
+pub struct PyFunctionDefinition {
+    #[pyo3(get)]
+    name: String,
+    #[pyo3(get)]
+    public: bool,
+    #[pyo3(get)]
+    native: bool,
+    #[pyo3(get)]
+    arguments: usize,
+    #[pyo3(get)]
+    returns: usize,
+    /// The number of locals, arguments included.
+    #[pyo3(get)]
+    locals: usize,
+    /// The number of instructions of the code. Native functions have none.
+    #[pyo3(get)]
+    instructions: usize,
+}
+
+#[pymethods]
+impl PyCompiledModule {
+    /// Deserializes a module from its binary form, raising `ValueError` if it is malformed.
+    #[staticmethod]
+    fn deserialize(binary: &PyBytes) -> PyResult<Self> {
+        CompiledModule::deserialize(binary.as_bytes())
+            .map(|module| Self { module })
+            .map_err(|err| PyErr::new::<ValueError, _>(format!("malformed module: {}", err)))
+    }
+
+    #[getter]
+    fn address(&self) -> String {
+        short_address(self.module.self_id().address())
+    }
+
+    #[getter]
+    fn name(&self) -> String {
+        self.module.self_id().name().to_string()
+    }
+
+    /// Returns the modules this module depends on, as `address::name`.
+    fn dependencies(&self) -> Vec<String> {
+        let id = self.module.self_id();
+        ModuleView::new(&self.module)
+            .module_handles()
+            .map(|handle| handle.module_id())
+            .filter(|dependency| *dependency != id)
+            .map(|dependency| {
+                format!(
+                    "{}::{}",
+                    short_address(dependency.address()),
+                    dependency.name()
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the struct definitions, in order.
+    fn structs(&self, py: Python) -> PyResult<Vec<Py<PyStructDefinition>>> {
+        ModuleView::new(&self.module)
+            .structs()
+            .map(|struct_def| {
+                let fields = match struct_def.fields() {
+                    Some(fields) => fields.map(|field| field.name().to_string()).collect(),
+                    None => vec![],
+                };
+                Py::new(
+                    py,
+                    PyStructDefinition {
+                        name: struct_def.name().to_string(),
+                        resource: struct_def.is_nominal_resource(),
+                        native: struct_def.is_native(),
+                        fields,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the function definitions, in order.
+    fn functions(&self, py: Python) -> PyResult<Vec<Py<PyFunctionDefinition>>> {
+        ModuleView::new(&self.module)
+            .functions()
+            .map(|function| {
+                let signature = function.signature();
+                Py::new(
+                    py,
+                    PyFunctionDefinition {
+                        name: function.name().to_string(),
+                        public: function.is_public(),
+                        native: function.is_native(),
+                        arguments: signature.arg_count(),
+                        returns: signature.return_count(),
+                        locals: function.locals_signature().len(),
+                        instructions: function.as_inner().code.code.len(),
+                    },
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the number of entries of every table of the module, the total number of
+    /// instructions, and the size of the module in binary form, in bytes.
+    fn stats(&self) -> PyResult<HashMap<&'static str, usize>> {
+        let module = &self.module;
+        let mut binary = vec![];
+        module
+            .serialize(&mut binary)
+            .map_err(|err| PyErr::new::<ValueError, _>(err.to_string()))?;
+        let instructions = module
+            .function_defs()
+            .iter()
+            .map(|function_def| function_def.code.code.len())
+            .sum();
+        Ok(vec![
+            ("module_handles", module.module_handles().len()),
+            ("struct_handles", module.struct_handles().len()),
+            ("function_handles", module.function_handles().len()),
+            ("type_signatures", module.type_signatures().len()),
+            ("function_signatures", module.function_signatures().len()),
+            ("locals_signatures", module.locals_signatures().len()),
+            ("string_pool", module.string_pool().len()),
+            ("byte_array_pool", module.byte_array_pool().len()),
+            ("address_pool", module.address_pool().len()),
+            ("struct_defs", module.struct_defs().len()),
+            ("field_defs", module.field_defs().len()),
+            ("function_defs", module.function_defs().len()),
+            ("instructions", instructions),
+            ("binary_size", binary.len()),
+        ]
+        .into_iter()
+        .collect())
+    }
+
+    /// Returns the disassembly of the module.
+    fn disassemble(&self) -> String {
+        self.module.to_string()
+    }
+}
+
+/// Loads the module in binary form at `path`, raising `IOError` if it can't be read and
+/// `ValueError` if it is malformed.
+#[pyfunction]
+fn load_module(path: &str) -> PyResult<PyCompiledModule> {
+    let binary = fs::read(path)?;
+    CompiledModule::deserialize(&binary)
+        .map(|module| PyCompiledModule { module })
+        .map_err(|err| PyErr::new::<ValueError, _>(format!("malformed module: {}", err)))
+}
+
+#[pymodule]
+fn vm_python(_py: Python, module: &PyModule) -> PyResult<()> {
+    module.add_class::<PyCompiledModule>()?;
+    module.add_class::<PyStructDefinition>()?;
+    module.add_class::<PyFunctionDefinition>()?;
+    module.add_wrapped(wrap_pyfunction!(load_module))?;
+    Ok(())
+}
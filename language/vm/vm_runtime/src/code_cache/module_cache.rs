@@ -4,13 +4,12 @@
 
 use crate::{
     code_cache::module_adapter::{ModuleFetcher, NullFetcher},
-    gas_meter::GasMeter,
     loaded_data::{
         function::{FunctionRef, FunctionReference},
         loaded_module::LoadedModule,
     },
 };
-use bytecode_verifier::VerifiedModule;
+use bytecode_verifier::{LruVerificationCache, VerifiedModule};
 use std::marker::PhantomData;
 use types::language_storage::ModuleId;
 use vm::{
@@ -20,6 +19,7 @@ use vm::{
         FunctionHandleIndex, SignatureToken, StructDefinitionIndex, StructFieldInformation,
         StructHandleIndex,
     },
+    gas_schedule::GasMetering,
     views::{FunctionHandleView, StructHandleView},
 };
 use vm_cache_map::{Arena, CacheRefMap};
@@ -28,6 +28,17 @@ use vm_runtime_types::loaded_data::{struct_def::StructDef, types::Type};
 #[cfg(test)]
 use crate::code_cache::module_adapter::FakeFetcher;
 
+/// The number of verification outcomes kept across module caches.
+const VERIFICATION_CACHE_CAPACITY: usize = 1024;
+
+lazy_static! {
+    /// Outcomes of verifying modules fetched from storage. Module caches only live as long as a
+    /// block, so this is shared by all of them to avoid verifying the same published modules (like
+    /// the standard library) over and over again.
+    static ref VERIFICATION_CACHE: LruVerificationCache =
+        LruVerificationCache::new(VERIFICATION_CACHE_CAPACITY);
+}
+
 /// Trait that describe a cache for modules. The idea is that this trait will in charge of
 /// loading resolving all dependencies of needed module from the storage.
 pub trait ModuleCache<'alloc> {
@@ -59,7 +70,7 @@ pub trait ModuleCache<'alloc> {
         &self,
         module: &LoadedModule,
         idx: StructDefinitionIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
     ) -> VMResult<Option<StructDef>>;
 
     /// Resolve a ModuleId into a LoadedModule if the module has been cached already.
@@ -96,7 +107,7 @@ where
         &self,
         module: &LoadedModule,
         idx: StructDefinitionIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
     ) -> VMResult<Option<StructDef>> {
         (*self).resolve_struct_def(module, idx, gas_meter)
     }
@@ -152,7 +163,7 @@ impl<'alloc> VMModuleCache<'alloc> {
         };
 
         // Verify the module before using it.
-        let module = match VerifiedModule::new(module) {
+        let module = match VerifiedModule::new_with_cache(module, &*VERIFICATION_CACHE) {
             Ok(module) => module,
             Err((_, errors)) => {
                 return Err(VMRuntimeError {
@@ -195,7 +206,7 @@ impl<'alloc> VMModuleCache<'alloc> {
         F: ModuleFetcher,
     {
         let function_handle = caller_module.function_handle_at(idx);
-        let callee_name = caller_module.string_at(function_handle.name);
+        let callee_name = caller_module.identifier_at(function_handle.name);
         let callee_module_id = FunctionHandleView::new(caller_module, function_handle).module_id();
 
         match self.get_loaded_module_with_fetcher(&callee_module_id, fetcher) {
@@ -216,11 +227,11 @@ impl<'alloc> VMModuleCache<'alloc> {
         &self,
         module: &LoadedModule,
         idx: StructHandleIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
         fetcher: &F,
     ) -> VMResult<Option<StructDef>> {
         let struct_handle = module.struct_handle_at(idx);
-        let struct_name = module.string_at(struct_handle.name);
+        let struct_name = module.identifier_at(struct_handle.name);
         let struct_def_module_id = StructHandleView::new(module, struct_handle).module_id();
         match self.get_loaded_module_with_fetcher(&struct_def_module_id, fetcher) {
             Ok(Some(module)) => {
@@ -240,7 +251,7 @@ impl<'alloc> VMModuleCache<'alloc> {
         &'txn self,
         module: &LoadedModule,
         tok: &SignatureToken,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
         fetcher: &F,
     ) -> VMResult<Option<Type>> {
         match tok {
@@ -277,7 +288,7 @@ impl<'alloc> VMModuleCache<'alloc> {
         &'txn self,
         module: &LoadedModule,
         idx: StructDefinitionIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
         fetcher: &F,
     ) -> VMResult<Option<StructDef>> {
         if let Some(def) = module.cached_struct_def_at(idx) {
@@ -337,7 +348,7 @@ impl<'alloc> ModuleCache<'alloc> for VMModuleCache<'alloc> {
         &self,
         module: &LoadedModule,
         idx: StructDefinitionIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
     ) -> VMResult<Option<StructDef>> {
         self.resolve_struct_def_with_fetcher(module, idx, gas_meter, &NullFetcher())
     }
@@ -412,7 +423,7 @@ impl<'alloc, 'blk, F: ModuleFetcher> ModuleCache<'alloc> for BlockModuleCache<'a
         &self,
         module: &LoadedModule,
         idx: StructDefinitionIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
     ) -> VMResult<Option<StructDef>> {
         self.vm_cache
             .resolve_struct_def_with_fetcher(module, idx, gas_meter, &self.storage)
@@ -483,7 +494,7 @@ where
         &self,
         module: &LoadedModule,
         idx: StructDefinitionIndex,
-        gas_meter: &GasMeter,
+        gas_meter: &dyn GasMetering,
     ) -> VMResult<Option<StructDef>> {
         if let Some(f) = try_runtime!(self.local_cache.resolve_struct_def(module, idx, gas_meter)) {
             Ok(Ok(Some(f)))
@@ -11,9 +11,9 @@ use vm::{access::ModuleAccess, errors::*, file_format::Bytecode, gas_schedule::*
 use vm_runtime_types::value::Local;
 
 /// Holds the state of the gas meter.
-pub struct GasMeter {
-    // The current amount of gas that is left ("unburnt gas") in the gas meter.
-    current_gas_left: GasUnits<GasCarrier>,
+pub struct GasMeter<'a> {
+    // The meter gas is charged to. It keeps track of the gas that is left ("unburnt gas").
+    meter: Box<dyn GasMetering + 'a>,
 
     // We need to disable and enable gas metering for both the prologue and epilogue of the Account
     // contract. The VM will then internally unset/set this flag before executing either of them.
@@ -23,11 +23,23 @@ pub struct GasMeter {
 // NB: A number of the functions/methods in this struct will return a VMResult<T>
 // since we will need to access stack and memory states, and we need to be able
 // to report errors properly from these accesses.
-impl GasMeter {
+impl<'a> GasMeter<'a> {
     /// Create a new gas meter with starting gas amount `gas_amount`
     pub fn new(gas_amount: GasUnits<GasCarrier>) -> Self {
+        Self::with_meter(BoundedGasMeter::new(gas_amount))
+    }
+
+    /// Create a new gas meter holding the maximum amount of gas of the transaction described by
+    /// `context`.
+    pub fn from_context(context: &GasContext) -> Self {
+        Self::new(context.max_gas_amount())
+    }
+
+    /// Create a new gas meter that charges gas to `meter`, such as an `UnmeteredGasMeter` for
+    /// simulations or a `RecordingGasMeter` for gas profiling.
+    pub fn with_meter(meter: impl GasMetering + 'a) -> Self {
         GasMeter {
-            current_gas_left: gas_amount,
+            meter: Box::new(meter),
             meter_on: true,
         }
     }
@@ -48,13 +60,13 @@ impl GasMeter {
     {
         precondition!(transaction_size.get() <= (MAX_TRANSACTION_SIZE_IN_BYTES as u64));
         let cost = calculate_intrinsic_gas(transaction_size);
-        self.consume_gas(cost, stk)
+        self.consume(GasCharge::other(transaction_size, cost), stk)
     }
 
     /// Queries the internal state of the gas meter to determine if it has at
     /// least `needed_gas` amount of gas.
     pub fn has_gas(&self, needed_gas: GasUnits<GasCarrier>) -> bool {
-        self.current_gas_left
+        self.remaining_gas()
             .app(&needed_gas, |curr_gas, needed_gas| curr_gas >= needed_gas)
     }
 
@@ -89,7 +101,10 @@ impl GasMeter {
     {
         if self.meter_on {
             let instruction_gas = try_runtime!(self.gas_for_instruction(instr, stk, memory_size));
-            self.consume_gas(instruction_gas, stk)
+            self.consume(
+                GasCharge::for_instruction(instr, memory_size, instruction_gas),
+                stk,
+            )
         } else {
             Ok(Ok(()))
         }
@@ -291,14 +306,6 @@ impl GasMeter {
         Ok(Ok(instruction_reqs))
     }
 
-    /// Get the amount of gas that remains (that has _not_ been consumed) in the gas meter.
-    ///
-    /// This method is used by the `GetGasRemaining` bytecode instruction to get the current
-    /// amount of gas remaining at the point of call.
-    pub fn remaining_gas(&self) -> GasUnits<GasCarrier> {
-        self.current_gas_left
-    }
-
     /// Consume the amount of gas given by `gas_amount`. If there is not enough gas
     /// left in the internal state, an `OutOfGasError` is returned.
     pub fn consume_gas<'alloc, 'txn, P>(
@@ -310,18 +317,26 @@ impl GasMeter {
         'alloc: 'txn,
         P: ModuleCache<'alloc>,
     {
-        if !self.meter_on {
-            return Ok(Ok(()));
-        }
-        if self
-            .current_gas_left
-            .app(&gas_amount, |curr_gas, gas_amt| curr_gas >= gas_amt)
-        {
-            self.current_gas_left = self.current_gas_left.sub(gas_amount);
+        self.consume(
+            GasCharge::other(AbstractMemorySize::new(0), gas_amount),
+            stk,
+        )
+    }
+
+    /// Makes `charge`, returning an `OutOfGasError` at the current location of `stk` if there
+    /// isn't enough gas left for it.
+    fn consume<'alloc, 'txn, P>(
+        &mut self,
+        charge: GasCharge,
+        stk: &ExecutionStack<'alloc, 'txn, P>,
+    ) -> VMResult<()>
+    where
+        'alloc: 'txn,
+        P: ModuleCache<'alloc>,
+    {
+        if self.charge(charge) {
             Ok(Ok(()))
         } else {
-            // Zero out the internal gas state
-            self.current_gas_left = GasUnits::new(0);
             let location = stk.location().unwrap_or_default();
             Ok(Err(VMRuntimeError {
                 loc: location,
@@ -338,3 +353,17 @@ impl GasMeter {
         gas_cost.instruction_gas.add(gas_cost.memory_gas)
     }
 }
+
+impl<'a> GasMetering for GasMeter<'a> {
+    fn charge(&mut self, charge: GasCharge) -> bool {
+        !self.meter_on || self.meter.charge(charge)
+    }
+
+    /// Get the amount of gas that remains (that has _not_ been consumed) in the gas meter.
+    ///
+    /// This method is used by the `GetGasRemaining` bytecode instruction to get the current
+    /// amount of gas remaining at the point of call.
+    fn remaining_gas(&self) -> GasUnits<GasCarrier> {
+        self.meter.remaining_gas()
+    }
+}
@@ -12,6 +12,7 @@ use vm::{
         CompiledModule, FieldDefinitionIndex, FunctionDefinitionIndex, StructDefinitionIndex,
         StructFieldInformation, TableIndex,
     },
+    identifier::Identifier,
     internals::ModuleIndex,
 };
 use vm_runtime_types::loaded_data::struct_def::StructDef;
@@ -22,11 +23,11 @@ use vm_runtime_types::loaded_data::struct_def::StructDef;
 pub struct LoadedModule {
     module: VerifiedModule,
     #[allow(dead_code)]
-    pub struct_defs_table: HashMap<String, StructDefinitionIndex>,
+    pub struct_defs_table: HashMap<Identifier, StructDefinitionIndex>,
     #[allow(dead_code)]
-    pub field_defs_table: HashMap<String, FieldDefinitionIndex>,
+    pub field_defs_table: HashMap<Identifier, FieldDefinitionIndex>,
 
-    pub function_defs_table: HashMap<String, FunctionDefinitionIndex>,
+    pub function_defs_table: HashMap<Identifier, FunctionDefinitionIndex>,
 
     pub function_defs: Vec<FunctionDef>,
 
@@ -75,8 +76,8 @@ impl LoadedModule {
 
         for (idx, struct_def) in module.struct_defs().iter().enumerate() {
             let name = module
-                .string_at(module.struct_handle_at(struct_def.struct_handle).name)
-                .to_string();
+                .identifier_at(module.struct_handle_at(struct_def.struct_handle).name)
+                .clone();
             let sd_idx = StructDefinitionIndex::new(idx as TableIndex);
             struct_defs_table.insert(name, sd_idx);
 
@@ -94,15 +95,15 @@ impl LoadedModule {
             }
         }
         for (idx, field_def) in module.field_defs().iter().enumerate() {
-            let name = module.string_at(field_def.name).to_string();
+            let name = module.identifier_at(field_def.name).clone();
             let fd_idx = FieldDefinitionIndex::new(idx as TableIndex);
             field_defs_table.insert(name, fd_idx);
         }
 
         for (idx, function_def) in module.function_defs().iter().enumerate() {
             let name = module
-                .string_at(module.function_handle_at(function_def.function).name)
-                .to_string();
+                .identifier_at(module.function_handle_at(function_def.function).name)
+                .clone();
             let fd_idx = FunctionDefinitionIndex::new(idx as TableIndex);
             function_defs_table.insert(name, fd_idx);
             // `function_defs` is initally empty, a single element is pushed per loop iteration and
@@ -7,7 +7,7 @@ use crate::{
     process_txn::{execute::ExecutedTransaction, validate::ValidatedTransaction},
     txn_executor::TransactionExecutor,
 };
-use bytecode_verifier::{VerifiedModule, VerifiedScript};
+use bytecode_verifier::{verify_module_address, VerifiedModule, VerifiedScript};
 use logger::prelude::*;
 use types::{
     account_address::AccountAddress,
@@ -18,10 +18,8 @@ use types::{
     vm_error::{VMStatus, VMVerificationError, VMVerificationStatus},
 };
 use vm::{
-    access::ModuleAccess,
-    errors::{VMStaticViolation, VerificationError, VerificationStatus},
+    errors::VerificationStatus,
     file_format::{CompiledModule, CompiledScript, FunctionSignature, SignatureToken},
-    IndexKind,
 };
 
 /// Represents a transaction which has been validated and for which the program has been run
@@ -152,15 +150,13 @@ where
         };
 
         // Make sure the module's self address matches the transaction sender. The self address is
-        // where the module will actually be published. If we did not check this, the sender could
-        // publish a module under anyone's account.
-        if compiled_module.address() != sender_address {
-            let error = VerificationError {
-                kind: IndexKind::AddressPool,
-                idx: CompiledModule::IMPLEMENTED_MODULE_INDEX as usize,
-                err: VMStaticViolation::ModuleAddressDoesNotMatchSender,
-            };
-            let statuses = vec![VerificationStatus::Module(0, error)];
+        // where the module will actually be published.
+        let errors = verify_module_address(&compiled_module, sender_address);
+        if !errors.is_empty() {
+            let statuses: Vec<_> = errors
+                .into_iter()
+                .map(|error| VerificationStatus::Module(0, error))
+                .collect();
             return Err(statuses.iter().collect());
         }
 
@@ -260,30 +256,19 @@ fn static_verify_modules(
 
     for (module_idx, module) in modules.into_iter().enumerate() {
         // Make sure the module's self address matches the transaction sender. The self address is
-        // where the module will actually be published. If we did not check this, the sender could
-        // publish a module under anyone's account.
+        // where the module will actually be published.
         //
         // For scripts this isn't a problem because they don't get published to accounts.
-        let self_error = if module.address() != sender_address {
-            Some(VerificationError {
-                kind: IndexKind::AddressPool,
-                idx: CompiledModule::IMPLEMENTED_MODULE_INDEX as usize,
-                err: VMStaticViolation::ModuleAddressDoesNotMatchSender,
-            })
-        } else {
-            None
-        };
+        let mut self_errors = verify_module_address(&module, sender_address);
 
         let (module, mut errors) = match VerifiedModule::new(module) {
             Ok(module) => (Some(module), vec![]),
             Err((_, errors)) => (None, errors),
         };
 
-        if let Some(error) = self_error {
-            // Verification should stop before we generate enough errors to overflow
-            assume!(errors.len() < usize::max_value());
-            errors.push(error);
-        }
+        // Verification should stop before we generate enough errors to overflow
+        assume!(errors.len() <= usize::max_value() - self_errors.len());
+        errors.append(&mut self_errors);
 
         if errors.is_empty() {
             // `modules_out` is initally empty, a single element is pushed per loop iteration and
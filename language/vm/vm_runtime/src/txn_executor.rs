@@ -33,7 +33,10 @@ use vm::{
     access::ModuleAccess,
     errors::*,
     file_format::{Bytecode, CodeOffset, CompiledScript, StructDefinitionIndex},
-    gas_schedule::{AbstractMemorySize, GasAlgebra, GasUnits},
+    gas_schedule::{
+        AbstractMemorySize, BoundedGasMeter, GasAlgebra, GasMetering, GasUnits, GAS_SCHEDULE,
+    },
+    identifier::Identifier,
     transaction_metadata::TransactionMetadata,
 };
 use vm_cache_map::Arena;
@@ -92,7 +95,7 @@ where
 
     #[cfg(not(feature = "instruction_synthesis"))]
     execution_stack: ExecutionStack<'alloc, 'txn, P>,
-    gas_meter: GasMeter,
+    gas_meter: GasMeter<'txn>,
     txn_data: TransactionMetadata,
     event_data: Vec<ContractEvent>,
     data_view: TransactionDataCache<'txn>,
@@ -111,10 +114,22 @@ where
         module_cache: P,
         data_cache: &'txn dyn RemoteCache,
         txn_data: TransactionMetadata,
+    ) -> Self {
+        let gas_meter = BoundedGasMeter::new(txn_data.gas_context(&GAS_SCHEDULE).max_gas_amount());
+        Self::with_gas_meter(module_cache, data_cache, txn_data, gas_meter)
+    }
+
+    /// Create a new `TransactionExecutor` that charges gas to `gas_meter` instead of metering the
+    /// maximum amount of gas of the transaction, e.g. to simulate the transaction.
+    pub fn with_gas_meter(
+        module_cache: P,
+        data_cache: &'txn dyn RemoteCache,
+        txn_data: TransactionMetadata,
+        gas_meter: impl GasMetering + 'txn,
     ) -> Self {
         TransactionExecutor {
             execution_stack: ExecutionStack::new(module_cache),
-            gas_meter: GasMeter::new(txn_data.max_gas_amount()),
+            gas_meter: GasMeter::with_meter(gas_meter),
             txn_data,
             event_data: Vec::new(),
             data_view: TransactionDataCache::new(data_cache),
@@ -673,9 +688,8 @@ where
             .pop()?
             .value()
             .ok_or(VMInvariantViolation::LinkerError)?;
-        let account_struct_id = account_module
-            .struct_defs_table
-            .get(ACCOUNT_STRUCT_NAME)
+        let account_struct_id = Identifier::find(ACCOUNT_STRUCT_NAME)
+            .and_then(|name| account_module.struct_defs_table.get(&name))
             .ok_or(VMInvariantViolation::LinkerError)?;
         let account_struct_def = try_runtime!(self
             .execution_stack
@@ -758,7 +772,7 @@ where
     pub(crate) fn execute_function_impl(&mut self, func: FunctionRef<'txn>) -> VMResult<()> {
         // We charge an intrinsic amount of gas based upon the size of the transaction submitted
         // (in raw bytes).
-        let txn_size = self.txn_data.transaction_size;
+        let txn_size = self.txn_data.transaction_size();
         // The callers of this function verify the transaction before executing it. Transaction
         // verification ensures the following condition.
         assume!(txn_size.get() <= (MAX_TRANSACTION_SIZE_IN_BYTES as u64));
@@ -802,9 +816,8 @@ where
                 Some(module) => module,
                 None => return Err(VMInvariantViolation::LinkerError),
             };
-        let func_idx = loaded_module
-            .function_defs_table
-            .get(function_name)
+        let func_idx = Identifier::find(function_name)
+            .and_then(|name| loaded_module.function_defs_table.get(&name))
             .ok_or(VMInvariantViolation::LinkerError)?;
         let func = FunctionRef::new(loaded_module, *func_idx);
 
@@ -831,9 +844,8 @@ where
         // account in the account module's epilogue.
         let gas: u64 = self
             .txn_data
-            .max_gas_amount
-            .sub(self.gas_meter.remaining_gas())
-            .mul(self.txn_data.gas_unit_price)
+            .gas_context(&GAS_SCHEDULE)
+            .gas_fee(self.gas_meter.remaining_gas())
             .get();
         let write_set = self.data_view.make_write_set(to_be_published_modules)?;
 
@@ -882,7 +894,7 @@ pub fn execute_function(
     }
     let mut vm = TransactionExecutor {
         execution_stack: ExecutionStack::new(&module_cache),
-        gas_meter: GasMeter::new(txn_metadata.max_gas_amount()),
+        gas_meter: GasMeter::from_context(&txn_metadata.gas_context(&GAS_SCHEDULE)),
         txn_data: txn_metadata,
         event_data: Vec::new(),
         data_view: TransactionDataCache::new(data_cache),
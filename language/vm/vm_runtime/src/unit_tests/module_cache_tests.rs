@@ -22,6 +22,7 @@ use vm::{
     errors::{VMErrorKind, VMRuntimeError, VerificationStatus},
     file_format::*,
     gas_schedule::{GasAlgebra, GasUnits},
+    identifier::Identifier,
 };
 use vm_cache_map::Arena;
 use vm_runtime_types::loaded_data::{struct_def::StructDef, types::Type};
@@ -73,18 +74,18 @@ fn test_module(name: String) -> VerifiedModule {
         type_signatures: vec![],
         function_signatures: vec![
             FunctionSignature {
-                return_types: vec![],
-                arg_types: vec![],
+                return_types: vec![].into(),
+                arg_types: vec![].into(),
                 type_formals: vec![],
             },
             FunctionSignature {
-                return_types: vec![],
-                arg_types: vec![SignatureToken::U64],
+                return_types: vec![].into(),
+                arg_types: vec![SignatureToken::U64].into(),
                 type_formals: vec![],
             },
         ],
-        locals_signatures: vec![LocalsSignature(vec![])],
-        string_pool: vec![name, "func1".to_string(), "func2".to_string()],
+        locals_signatures: vec![LocalsSignature::default()],
+        string_pool: vec![name.into(), "func1".into(), "func2".into()],
         byte_array_pool: vec![],
         address_pool: vec![AccountAddress::default()],
     }
@@ -136,23 +137,23 @@ fn test_script() -> VerifiedScript {
         type_signatures: vec![],
         function_signatures: vec![
             FunctionSignature {
-                return_types: vec![],
-                arg_types: vec![],
+                return_types: vec![].into(),
+                arg_types: vec![].into(),
                 type_formals: vec![],
             },
             FunctionSignature {
-                return_types: vec![],
-                arg_types: vec![SignatureToken::U64],
+                return_types: vec![].into(),
+                arg_types: vec![SignatureToken::U64].into(),
                 type_formals: vec![],
             },
         ],
-        locals_signatures: vec![LocalsSignature(vec![])],
+        locals_signatures: vec![LocalsSignature::default()],
         string_pool: vec![
-            "hello".to_string(),
-            "module".to_string(),
-            "func1".to_string(),
-            "func2".to_string(),
-            "main".to_string(),
+            "hello".into(),
+            "module".into(),
+            "func1".into(),
+            "func2".into(),
+            "main".into(),
         ],
         byte_array_pool: vec![],
         address_pool: vec![AccountAddress::default()],
@@ -424,24 +425,24 @@ fn test_multi_level_cache_write_back() {
         type_signatures: vec![],
         function_signatures: vec![
             FunctionSignature {
-                return_types: vec![],
-                arg_types: vec![],
+                return_types: vec![].into(),
+                arg_types: vec![].into(),
                 type_formals: vec![],
             },
             FunctionSignature {
-                return_types: vec![],
-                arg_types: vec![SignatureToken::U64],
+                return_types: vec![].into(),
+                arg_types: vec![SignatureToken::U64].into(),
                 type_formals: vec![],
             },
         ],
-        locals_signatures: vec![LocalsSignature(vec![])],
+        locals_signatures: vec![LocalsSignature::default()],
         string_pool: vec![
-            "hello".to_string(),
-            "module".to_string(),
-            "existing_module".to_string(),
-            "func1".to_string(),
-            "func2".to_string(),
-            "main".to_string(),
+            "hello".into(),
+            "module".into(),
+            "existing_module".into(),
+            "func1".into(),
+            "func2".into(),
+            "main".into(),
         ],
         byte_array_pool: vec![],
         address_pool: vec![AccountAddress::default()],
@@ -641,19 +642,34 @@ fn test_field_offset_resolution() {
             .unwrap()
             .unwrap();
 
-        let f_idx = module_ref.field_defs_table.get("f").unwrap();
+        let f_idx = module_ref
+            .field_defs_table
+            .get(&Identifier::new("f"))
+            .unwrap();
         assert_eq!(module_ref.get_field_offset(*f_idx).unwrap(), 0);
 
-        let g_idx = module_ref.field_defs_table.get("g").unwrap();
+        let g_idx = module_ref
+            .field_defs_table
+            .get(&Identifier::new("g"))
+            .unwrap();
         assert_eq!(module_ref.get_field_offset(*g_idx).unwrap(), 1);
 
-        let i_idx = module_ref.field_defs_table.get("i").unwrap();
+        let i_idx = module_ref
+            .field_defs_table
+            .get(&Identifier::new("i"))
+            .unwrap();
         assert_eq!(module_ref.get_field_offset(*i_idx).unwrap(), 0);
 
-        let x_idx = module_ref.field_defs_table.get("x").unwrap();
+        let x_idx = module_ref
+            .field_defs_table
+            .get(&Identifier::new("x"))
+            .unwrap();
         assert_eq!(module_ref.get_field_offset(*x_idx).unwrap(), 1);
 
-        let y_idx = module_ref.field_defs_table.get("y").unwrap();
+        let y_idx = module_ref
+            .field_defs_table
+            .get(&Identifier::new("y"))
+            .unwrap();
         assert_eq!(module_ref.get_field_offset(*y_idx).unwrap(), 2);
     }
 }
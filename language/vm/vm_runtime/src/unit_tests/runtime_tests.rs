@@ -11,7 +11,6 @@ use crate::{
     txn_executor::TransactionExecutor,
 };
 use bytecode_verifier::{VerifiedModule, VerifiedScript};
-use crypto::ed25519::compat;
 use std::collections::HashMap;
 use types::{access_path::AccessPath, account_address::AccountAddress, byte_array::ByteArray};
 use vm::{
@@ -23,8 +22,12 @@ use vm::{
         FunctionSignatureIndex, LocalsSignature, LocalsSignatureIndex, ModuleHandle,
         ModuleHandleIndex, SignatureToken, StringPoolIndex, NO_TYPE_ACTUALS,
     },
-    gas_schedule::{AbstractMemorySize, GasAlgebra, GasPrice, GasUnits},
-    transaction_metadata::TransactionMetadata,
+    gas_schedule::{
+        AbstractMemorySize, GasAlgebra, GasPrice, GasUnits, InstructionKey, RecordingGasMeter,
+        UnmeteredGasMeter, MAXIMUM_NUMBER_OF_GAS_UNITS,
+    },
+    identifier::Identifier,
+    transaction_metadata::{TransactionMetadata, TransactionMetadataBuilder},
 };
 use vm_cache_map::Arena;
 use vm_runtime_types::value::Local;
@@ -73,12 +76,12 @@ fn fake_script() -> VerifiedScript {
         }],
         type_signatures: vec![],
         function_signatures: vec![FunctionSignature {
-            arg_types: vec![],
-            return_types: vec![],
+            arg_types: vec![].into(),
+            return_types: vec![].into(),
             type_formals: vec![],
         }],
-        locals_signatures: vec![LocalsSignature(vec![])],
-        string_pool: vec!["hello".to_string()],
+        locals_signatures: vec![LocalsSignature::default()],
+        string_pool: vec!["hello".into()],
         byte_array_pool: vec![ByteArray::new(vec![0u8; 32])],
         address_pool: vec![AccountAddress::default()],
     }
@@ -558,12 +561,12 @@ fn test_arith_instructions() {
 }
 
 fn fake_module_with_calls(sigs: Vec<(Vec<SignatureToken>, FunctionSignature)>) -> VerifiedModule {
-    let mut names: Vec<String> = sigs
+    let mut names: Vec<Identifier> = sigs
         .iter()
         .enumerate()
-        .map(|(i, _)| format!("func{}", i))
+        .map(|(i, _)| format!("func{}", i).into())
         .collect();
-    names.insert(0, "module".to_string());
+    names.insert(0, "module".into());
     let function_defs = sigs
         .iter()
         .enumerate()
@@ -601,7 +604,10 @@ fn fake_module_with_calls(sigs: Vec<(Vec<SignatureToken>, FunctionSignature)>) -
         function_handles,
         type_signatures: vec![],
         function_signatures: function_sigs,
-        locals_signatures: local_sigs.into_iter().map(LocalsSignature).collect(),
+        locals_signatures: local_sigs
+            .into_iter()
+            .map(|locals| LocalsSignature(locals.into()))
+            .collect(),
         string_pool: names,
         byte_array_pool: vec![],
         address_pool: vec![AccountAddress::default()],
@@ -623,8 +629,8 @@ fn test_call() {
         (
             vec![],
             FunctionSignature {
-                arg_types: vec![],
-                return_types: vec![],
+                arg_types: vec![].into(),
+                return_types: vec![].into(),
                 type_formals: vec![],
             },
         ),
@@ -632,8 +638,8 @@ fn test_call() {
         (
             vec![SignatureToken::U64, SignatureToken::U64],
             FunctionSignature {
-                arg_types: vec![],
-                return_types: vec![],
+                arg_types: vec![].into(),
+                return_types: vec![].into(),
                 type_formals: vec![],
             },
         ),
@@ -641,8 +647,8 @@ fn test_call() {
         (
             vec![SignatureToken::U64, SignatureToken::U64],
             FunctionSignature {
-                arg_types: vec![SignatureToken::U64, SignatureToken::U64],
-                return_types: vec![],
+                arg_types: vec![SignatureToken::U64, SignatureToken::U64].into(),
+                return_types: vec![].into(),
                 type_formals: vec![],
             },
         ),
@@ -654,8 +660,8 @@ fn test_call() {
                 SignatureToken::Bool,
             ],
             FunctionSignature {
-                arg_types: vec![SignatureToken::U64, SignatureToken::U64],
-                return_types: vec![],
+                arg_types: vec![SignatureToken::U64, SignatureToken::U64].into(),
+                return_types: vec![].into(),
                 type_formals: vec![],
             },
         ),
@@ -730,17 +736,13 @@ fn test_transaction_info() {
     let loaded_main = LoadedModule::new(main_module);
     let entry_func = FunctionRef::new(&loaded_main, CompiledScript::MAIN_INDEX);
 
-    let txn_info = {
-        let (_, public_key) = compat::generate_genesis_keypair();
-        TransactionMetadata {
-            sender: AccountAddress::default(),
-            public_key,
-            sequence_number: 10,
-            max_gas_amount: GasUnits::new(100_000_009),
-            gas_unit_price: GasPrice::new(5),
-            transaction_size: AbstractMemorySize::new(100),
-        }
-    };
+    let txn_info = TransactionMetadataBuilder::new()
+        .with_sequence_number(10)
+        .with_max_gas_amount(GasUnits::new(100_000_009))
+        .with_gas_unit_price(GasPrice::new(5))
+        .with_transaction_size(AbstractMemorySize::new(100))
+        .build()
+        .expect("transaction metadata should be valid");
     let data_cache = FakeDataCache::new();
     let mut vm = TransactionExecutor::new(module_cache, &data_cache, txn_info);
 
@@ -779,3 +781,46 @@ fn test_transaction_info() {
         1,
     );
 }
+
+#[test]
+fn test_custom_gas_meter() {
+    let allocator = Arena::new();
+    let module_cache = VMModuleCache::new(&allocator);
+    let main_module = fake_script().into_module();
+    let loaded_main = LoadedModule::new(main_module);
+    let entry_func = FunctionRef::new(&loaded_main, CompiledScript::MAIN_INDEX);
+    let data_cache = FakeDataCache::new();
+    let mut meter = RecordingGasMeter::<UnmeteredGasMeter>::default();
+    {
+        let mut vm = TransactionExecutor::with_gas_meter(
+            module_cache,
+            &data_cache,
+            TransactionMetadata::default(),
+            &mut meter,
+        );
+        vm.execution_stack
+            .push_frame(entry_func)
+            .unwrap()
+            .expect("push to empty execution stack should succeed");
+
+        test_simple_instruction(
+            &mut vm,
+            Bytecode::GetGasRemaining,
+            vec![],
+            vec![Local::u64(MAXIMUM_NUMBER_OF_GAS_UNITS.get())],
+            vec![],
+            vec![],
+            1,
+        );
+    }
+
+    let instructions: Vec<_> = meter
+        .charges()
+        .iter()
+        .map(|charge| charge.instruction)
+        .collect();
+    assert_eq!(
+        instructions,
+        vec![Some(InstructionKey::new(&Bytecode::GetGasRemaining))]
+    );
+}
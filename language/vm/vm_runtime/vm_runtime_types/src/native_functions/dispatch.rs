@@ -59,8 +59,8 @@ macro_rules! add {
     }};
     ($m:ident, $addr:expr, $module:expr, $name:expr, $dis:expr, $kinds:expr, $args:expr, $ret:expr) => {{
         let expected_signature = FunctionSignature {
-            return_types: $ret,
-            arg_types: $args,
+            return_types: $ret.into(),
+            arg_types: $args.into(),
             type_formals: $kinds,
         };
         let f = NativeFunction {
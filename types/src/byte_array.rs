@@ -1,16 +1,19 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use bytes::Bytes;
 use canonical_serialization::{
     CanonicalDeserialize, CanonicalDeserializer, CanonicalSerialize, CanonicalSerializer,
 };
 use failure::Result;
 use hex;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Default, Clone, Serialize, Deserialize)]
+#[derive(Ord, PartialOrd, Eq, PartialEq, Hash, Default, Clone)]
 /// A struct that represents a ByteArray in Move.
-pub struct ByteArray(Vec<u8>);
+///
+/// The bytes are reference counted, so cloning a byte array doesn't copy them.
+pub struct ByteArray(Bytes);
 
 impl ByteArray {
     pub fn as_bytes(&self) -> &[u8] {
@@ -18,7 +21,12 @@ impl ByteArray {
     }
 
     pub fn new(buf: Vec<u8>) -> Self {
-        ByteArray(buf)
+        ByteArray(Bytes::from(buf))
+    }
+
+    /// Returns a byte array sharing `bytes`, which may be a slice of a larger buffer.
+    pub fn from_bytes(bytes: Bytes) -> Self {
+        ByteArray(bytes)
     }
 
     pub fn len(&self) -> usize {
@@ -30,7 +38,20 @@ impl ByteArray {
     }
 
     pub fn into_inner(self) -> Vec<u8> {
-        self.0
+        self.0.to_vec()
+    }
+}
+
+// Byte arrays are serialized as a sequence of bytes, like a `Vec<u8>`.
+impl Serialize for ByteArray {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteArray {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        Vec::<u8>::deserialize(deserializer).map(ByteArray::new)
     }
 }
 
@@ -65,7 +86,7 @@ impl CanonicalSerialize for ByteArray {
 impl CanonicalDeserialize for ByteArray {
     fn deserialize(deserializer: &mut impl CanonicalDeserializer) -> Result<Self> {
         let bytes = deserializer.decode_bytes()?;
-        Ok(ByteArray(bytes))
+        Ok(ByteArray::new(bytes))
     }
 }
 
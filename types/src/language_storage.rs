@@ -15,7 +15,7 @@ use failure::Result;
 use proptest_derive::Arbitrary;
 use proto_conv::{FromProto, IntoProto};
 use serde::{Deserialize, Serialize};
-use std::string::String;
+use std::{fmt, string::String};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Hash, Eq, Clone, PartialOrd, Ord)]
 pub struct StructTag {
@@ -76,6 +76,13 @@ impl ModuleId {
     }
 }
 
+/// Displays the module as `<address>::<name>`.
+impl fmt::Display for ModuleId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}::{}", self.address, self.name)
+    }
+}
+
 impl<'a> From<&'a ModuleId> for AccessPath {
     fn from(module_id: &'a ModuleId) -> Self {
         AccessPath::code_access_path(module_id)
@@ -1,4 +1,4 @@
-use crate::language_storage::ModuleId;
+use crate::{account_address::AccountAddress, language_storage::ModuleId};
 use canonical_serialization::test_helper::assert_canonical_encode_decode;
 use proptest::prelude::*;
 use proto_conv::test_helper::assert_protobuf_encode_decode;
@@ -14,3 +14,9 @@ proptest! {
         assert_canonical_encode_decode(&module_id);
     }
 }
+
+#[test]
+fn test_module_id_display() {
+    let module_id = ModuleId::new(AccountAddress::new([1u8; 32]), "M".to_string());
+    assert_eq!(module_id.to_string(), format!("0x{}::M", "01".repeat(32)));
+}
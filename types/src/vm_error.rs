@@ -114,6 +114,28 @@ pub enum VMVerificationError {
     InvalidAcquiresResourceAnnotationError(String),
     ConstraintKindMismatch(String),
     NumberOfTypeActualsMismatch(String),
+    VerificationBudgetExceeded(String),
+    StackHeightLimitExceeded(String),
+    InvalidMainFunctionReturn(String),
+    InvalidMainFunctionArgument(String),
+    StructKindMismatch(String),
+    FunctionSignatureMismatch(String),
+    FunctionVisibilityMismatch(String),
+    NativeFlagMismatch(String),
+    CyclicModuleDependency(String),
+    TooManyTypeParameters(String),
+    TypeInstantiationTooDeep(String),
+    TooManyStructFields(String),
+    TooManyBasicBlocks(String),
+    UnreachableBlock(String),
+    DeadCodeAfterBranch(String),
+    UnknownNativeFunction(String),
+    NativeFunctionSignatureMismatch(String),
+    IrreducibleControlFlow(String),
+    UncheckedArithmetic(String),
+    UnboundedLoop(String),
+    ResourceReturnedByReference(String),
+    CodeUnitTooLong(String),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Hash)]
@@ -597,6 +619,68 @@ impl IntoProto for VMVerificationError {
             VMVerificationError::NumberOfTypeActualsMismatch(message) => {
                 (ProtoKind::NumberOfTypeActualsMismatch, message)
             }
+            VMVerificationError::VerificationBudgetExceeded(message) => {
+                (ProtoKind::VerificationBudgetExceeded, message)
+            }
+            VMVerificationError::StackHeightLimitExceeded(message) => {
+                (ProtoKind::StackHeightLimitExceeded, message)
+            }
+            VMVerificationError::InvalidMainFunctionReturn(message) => {
+                (ProtoKind::InvalidMainFunctionReturn, message)
+            }
+            VMVerificationError::InvalidMainFunctionArgument(message) => {
+                (ProtoKind::InvalidMainFunctionArgument, message)
+            }
+            VMVerificationError::StructKindMismatch(message) => {
+                (ProtoKind::StructKindMismatch, message)
+            }
+            VMVerificationError::FunctionSignatureMismatch(message) => {
+                (ProtoKind::FunctionSignatureMismatch, message)
+            }
+            VMVerificationError::FunctionVisibilityMismatch(message) => {
+                (ProtoKind::FunctionVisibilityMismatch, message)
+            }
+            VMVerificationError::NativeFlagMismatch(message) => {
+                (ProtoKind::NativeFlagMismatch, message)
+            }
+            VMVerificationError::CyclicModuleDependency(message) => {
+                (ProtoKind::CyclicModuleDependency, message)
+            }
+            VMVerificationError::TooManyTypeParameters(message) => {
+                (ProtoKind::TooManyTypeParameters, message)
+            }
+            VMVerificationError::TypeInstantiationTooDeep(message) => {
+                (ProtoKind::TypeInstantiationTooDeep, message)
+            }
+            VMVerificationError::TooManyStructFields(message) => {
+                (ProtoKind::TooManyStructFields, message)
+            }
+            VMVerificationError::TooManyBasicBlocks(message) => {
+                (ProtoKind::TooManyBasicBlocks, message)
+            }
+            VMVerificationError::UnreachableBlock(message) => {
+                (ProtoKind::UnreachableBlock, message)
+            }
+            VMVerificationError::DeadCodeAfterBranch(message) => {
+                (ProtoKind::DeadCodeAfterBranch, message)
+            }
+            VMVerificationError::UnknownNativeFunction(message) => {
+                (ProtoKind::UnknownNativeFunction, message)
+            }
+            VMVerificationError::NativeFunctionSignatureMismatch(message) => {
+                (ProtoKind::NativeFunctionSignatureMismatch, message)
+            }
+            VMVerificationError::IrreducibleControlFlow(message) => {
+                (ProtoKind::IrreducibleControlFlow, message)
+            }
+            VMVerificationError::UncheckedArithmetic(message) => {
+                (ProtoKind::UncheckedArithmetic, message)
+            }
+            VMVerificationError::UnboundedLoop(message) => (ProtoKind::UnboundedLoop, message),
+            VMVerificationError::ResourceReturnedByReference(message) => {
+                (ProtoKind::ResourceReturnedByReference, message)
+            }
+            VMVerificationError::CodeUnitTooLong(message) => (ProtoKind::CodeUnitTooLong, message),
         }
     }
 }
@@ -801,6 +885,54 @@ impl FromProto for VMVerificationError {
             ProtoKind::NumberOfTypeActualsMismatch => {
                 Ok(VMVerificationError::NumberOfTypeActualsMismatch(message))
             }
+            ProtoKind::VerificationBudgetExceeded => {
+                Ok(VMVerificationError::VerificationBudgetExceeded(message))
+            }
+            ProtoKind::StackHeightLimitExceeded => {
+                Ok(VMVerificationError::StackHeightLimitExceeded(message))
+            }
+            ProtoKind::InvalidMainFunctionReturn => {
+                Ok(VMVerificationError::InvalidMainFunctionReturn(message))
+            }
+            ProtoKind::InvalidMainFunctionArgument => {
+                Ok(VMVerificationError::InvalidMainFunctionArgument(message))
+            }
+            ProtoKind::StructKindMismatch => Ok(VMVerificationError::StructKindMismatch(message)),
+            ProtoKind::FunctionSignatureMismatch => {
+                Ok(VMVerificationError::FunctionSignatureMismatch(message))
+            }
+            ProtoKind::FunctionVisibilityMismatch => {
+                Ok(VMVerificationError::FunctionVisibilityMismatch(message))
+            }
+            ProtoKind::NativeFlagMismatch => Ok(VMVerificationError::NativeFlagMismatch(message)),
+            ProtoKind::CyclicModuleDependency => {
+                Ok(VMVerificationError::CyclicModuleDependency(message))
+            }
+            ProtoKind::TooManyTypeParameters => {
+                Ok(VMVerificationError::TooManyTypeParameters(message))
+            }
+            ProtoKind::TypeInstantiationTooDeep => {
+                Ok(VMVerificationError::TypeInstantiationTooDeep(message))
+            }
+            ProtoKind::TooManyStructFields => Ok(VMVerificationError::TooManyStructFields(message)),
+            ProtoKind::TooManyBasicBlocks => Ok(VMVerificationError::TooManyBasicBlocks(message)),
+            ProtoKind::UnreachableBlock => Ok(VMVerificationError::UnreachableBlock(message)),
+            ProtoKind::DeadCodeAfterBranch => Ok(VMVerificationError::DeadCodeAfterBranch(message)),
+            ProtoKind::UnknownNativeFunction => {
+                Ok(VMVerificationError::UnknownNativeFunction(message))
+            }
+            ProtoKind::NativeFunctionSignatureMismatch => Ok(
+                VMVerificationError::NativeFunctionSignatureMismatch(message),
+            ),
+            ProtoKind::IrreducibleControlFlow => {
+                Ok(VMVerificationError::IrreducibleControlFlow(message))
+            }
+            ProtoKind::UncheckedArithmetic => Ok(VMVerificationError::UncheckedArithmetic(message)),
+            ProtoKind::UnboundedLoop => Ok(VMVerificationError::UnboundedLoop(message)),
+            ProtoKind::ResourceReturnedByReference => {
+                Ok(VMVerificationError::ResourceReturnedByReference(message))
+            }
+            ProtoKind::CodeUnitTooLong => Ok(VMVerificationError::CodeUnitTooLong(message)),
             ProtoKind::UnknownVerificationError => {
                 bail_err!(DecodingError::UnknownVerificationErrorEncountered)
             }